@@ -0,0 +1,250 @@
+use crate::Analyzer;
+use crate::region_overlay::FlaggedRegion;
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Content-adaptive LSB analysis. Adaptive embedders only touch textured regions, which
+// dilutes global statistics; comparing textured blocks against smooth ones catches them.
+pub struct AdaptiveLsbAnalyzer;
+
+pub const BLOCK_SIZE: u32 = 8;
+
+// Luma variance (with LSBs cleared) above which a block counts as textured
+pub const TEXTURE_VARIANCE_THRESHOLD: f64 = 25.0;
+
+// Pairs-of-values chi-square per degree of freedom is ~1 once LSBs are random
+pub const RANDOM_POV_LIMIT: f64 = 2.0;
+
+// How many times more structured smooth regions must be than textured ones
+pub const POV_CONTRAST_THRESHOLD: f64 = 2.0;
+
+// Blocks each region needs before the comparison is trusted
+pub const MIN_REGION_BLOCKS: usize = 4;
+
+#[derive(Debug)]
+pub enum AdaptiveLsbError {
+    ImageTooSmall(u32, u32),
+}
+
+impl Display for AdaptiveLsbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdaptiveLsbError::ImageTooSmall(w, h) => {
+                write!(f, "Image too small for adaptive LSB analysis: {}x{}", w, h)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AdaptiveLsbError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct RegionStats {
+    pub blocks: usize,
+    // Chi-square over value pairs (2k, 2k+1) divided by the pairs used
+    pub pov_score: f64,
+    // Fraction of channel samples with LSB set
+    pub lsb_ones_ratio: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveLsbAnalysis {
+    pub block_size: u32,
+    pub smooth: RegionStats,
+    pub textured: RegionStats,
+    // smooth.pov_score / textured.pov_score; large when only textured LSBs look random
+    pub pov_contrast: f64,
+    // The textured blocks, where adaptive embedding would have gone, for overlays
+    pub textured_regions: Vec<FlaggedRegion>,
+    pub suspicious: bool,
+}
+
+impl AdaptiveLsbAnalysis {
+    pub fn is_suspicious_at(&self, contrast_threshold: f64) -> bool {
+        self.smooth.blocks >= MIN_REGION_BLOCKS
+            && self.textured.blocks >= MIN_REGION_BLOCKS
+            && self.textured.pov_score < RANDOM_POV_LIMIT
+            && self.pov_contrast > contrast_threshold
+    }
+}
+
+// Histogram and LSB counts accumulated over all blocks of one region
+struct RegionAccumulator {
+    histograms: [[u64; 256]; 3],
+    ones: u64,
+    samples: u64,
+    blocks: usize,
+}
+
+impl RegionAccumulator {
+    fn new() -> Self {
+        Self {
+            histograms: [[0; 256]; 3],
+            ones: 0,
+            samples: 0,
+            blocks: 0,
+        }
+    }
+
+    fn add_block(&mut self, image: &RgbaImage, x0: u32, y0: u32) {
+        for y in y0..y0 + BLOCK_SIZE {
+            for x in x0..x0 + BLOCK_SIZE {
+                let pixel = image.get_pixel(x, y);
+                for channel in 0..3 {
+                    let value = pixel[channel];
+                    self.histograms[channel][value as usize] += 1;
+                    self.ones += (value & 1) as u64;
+                    self.samples += 1;
+                }
+            }
+        }
+        self.blocks += 1;
+    }
+
+    fn finish(&self) -> RegionStats {
+        let mut chi_square = 0.0;
+        let mut pairs = 0;
+        for histogram in &self.histograms {
+            for k in 0..128 {
+                let even = histogram[2 * k] as f64;
+                let odd = histogram[2 * k + 1] as f64;
+                // Skip sparse pairs, where the statistic is dominated by noise
+                if even + odd < 5.0 {
+                    continue;
+                }
+                chi_square += (even - odd).powi(2) / (even + odd);
+                pairs += 1;
+            }
+        }
+
+        RegionStats {
+            blocks: self.blocks,
+            pov_score: if pairs == 0 {
+                0.0
+            } else {
+                chi_square / pairs as f64
+            },
+            lsb_ones_ratio: if self.samples == 0 {
+                0.0
+            } else {
+                self.ones as f64 / self.samples as f64
+            },
+        }
+    }
+}
+
+impl Analyzer for AdaptiveLsbAnalyzer {
+    type Input = DynamicImage;
+    type Output = AdaptiveLsbAnalysis;
+    type Error = AdaptiveLsbError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let rgba = input.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width < BLOCK_SIZE * 2 || height < BLOCK_SIZE * 2 {
+            return Err(AdaptiveLsbError::ImageTooSmall(width, height));
+        }
+
+        let mut smooth = RegionAccumulator::new();
+        let mut textured = RegionAccumulator::new();
+        let mut textured_regions = Vec::new();
+
+        for y in (0..=height - BLOCK_SIZE).step_by(BLOCK_SIZE as usize) {
+            for x in (0..=width - BLOCK_SIZE).step_by(BLOCK_SIZE as usize) {
+                if block_variance(&rgba, x, y) > TEXTURE_VARIANCE_THRESHOLD {
+                    textured.add_block(&rgba, x, y);
+                    textured_regions.push(FlaggedRegion {
+                        x,
+                        y,
+                        width: BLOCK_SIZE,
+                        height: BLOCK_SIZE,
+                        intensity: 1.0,
+                    });
+                } else {
+                    smooth.add_block(&rgba, x, y);
+                }
+            }
+        }
+
+        let smooth = smooth.finish();
+        let textured = textured.finish();
+        let pov_contrast = if textured.pov_score > 0.0 {
+            smooth.pov_score / textured.pov_score
+        } else {
+            0.0
+        };
+
+        let mut analysis = AdaptiveLsbAnalysis {
+            block_size: BLOCK_SIZE,
+            smooth,
+            textured,
+            pov_contrast,
+            textured_regions,
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.is_suspicious_at(POV_CONTRAST_THRESHOLD);
+
+        Ok(analysis)
+    }
+}
+
+// Luma variance of a block with the LSBs masked off, so embedding can't move a
+// block from one region to the other
+fn block_variance(image: &RgbaImage, x0: u32, y0: u32) -> f64 {
+    let mut values = Vec::with_capacity((BLOCK_SIZE * BLOCK_SIZE) as usize);
+    for y in y0..y0 + BLOCK_SIZE {
+        for x in x0..x0 + BLOCK_SIZE {
+            let pixel = image.get_pixel(x, y);
+            let luma = 0.299 * (pixel[0] & !1) as f64
+                + 0.587 * (pixel[1] & !1) as f64
+                + 0.114 * (pixel[2] & !1) as f64;
+            values.push(luma);
+        }
+    }
+
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use image::Rgba;
+
+    #[test]
+    fn test_textured_only_embedding() {
+        // Left half smooth, right half textured; cover values are all even, so both
+        // regions start with maximally uneven value pairs
+        let cover = RgbaImage::from_fn(128, 128, |x, y| {
+            let v = if x < 64 {
+                100 + 2 * ((x + y) / 16)
+            } else {
+                40 + 2 * ((x * 7 + y * 13) % 80)
+            } as u8;
+            Rgba([v, v, v, 255])
+        });
+        let clean = AdaptiveLsbAnalyzer::analyze(DynamicImage::ImageRgba8(cover.clone())).unwrap();
+        assert!(clean.smooth.blocks >= MIN_REGION_BLOCKS);
+        assert!(clean.textured.blocks >= MIN_REGION_BLOCKS);
+        assert!(!clean.suspicious);
+
+        // Embed random bits only in the textured half
+        let mut stego = cover;
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for (x, _, pixel) in stego.enumerate_pixels_mut() {
+            if x < 64 {
+                continue;
+            }
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] & !1) | rng.bit();
+            }
+        }
+        let dirty = AdaptiveLsbAnalyzer::analyze(DynamicImage::ImageRgba8(stego)).unwrap();
+        assert_eq!(dirty.smooth.blocks, clean.smooth.blocks);
+        assert!(dirty.suspicious, "contrast {}", dirty.pov_contrast);
+    }
+}
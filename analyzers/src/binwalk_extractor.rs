@@ -0,0 +1,242 @@
+use crate::payload_carver::embedded_file_end;
+use binwalk::Binwalk;
+use binwalk::signatures::common::SignatureResult;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Recovers the embedded files the magic bytes scan only reports. Each signature past the
+// primary file goes to binwalk's extractor for its type; when there is none, or it fails,
+// the signature's bytes are carved out dd-style instead.
+pub struct BinwalkExtractorWithPath<'a> {
+    path: &'a Path,
+    output_dir: &'a Path,
+}
+
+#[derive(Debug)]
+pub enum BinwalkExtractorError {
+    IO(std::io::Error),
+    // Refuse to mix results into a directory that already has files in it
+    OutputNotEmpty(PathBuf),
+    Binwalk,
+}
+
+impl Display for BinwalkExtractorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinwalkExtractorError::IO(e) => write!(f, "IO error: {}", e),
+            BinwalkExtractorError::OutputNotEmpty(dir) => write!(
+                f,
+                "Extraction directory {} is not empty, remove it or pick another output directory",
+                dir.display()
+            ),
+            BinwalkExtractorError::Binwalk => {
+                write!(f, "Failed to initialize binwalk extraction directory")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BinwalkExtractorError {}
+
+impl From<std::io::Error> for BinwalkExtractorError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExtractionMethod {
+    // Name of the binwalk extractor that unpacked the data
+    Extractor(String),
+    Carved,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractedPayload {
+    pub offset: usize,
+    pub size: usize,
+    pub description: String,
+    pub method: ExtractionMethod,
+    // Directory holding the unpacked contents, or the carved file itself
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractionResults {
+    pub output_directory: PathBuf,
+    pub payloads: Vec<ExtractedPayload>,
+    // Symlinks from unpacked archives that pointed outside the output directory
+    pub removed_links: usize,
+}
+
+impl<'a> BinwalkExtractorWithPath<'a> {
+    pub fn new(path: &'a Path, output_dir: &'a Path) -> Self {
+        Self { path, output_dir }
+    }
+
+    pub fn extract(&self) -> Result<ExtractionResults, BinwalkExtractorError> {
+        let file_data = fs::read(self.path)?;
+
+        if fs::read_dir(self.output_dir).is_ok_and(|mut entries| entries.next().is_some()) {
+            return Err(BinwalkExtractorError::OutputNotEmpty(
+                self.output_dir.to_path_buf(),
+            ));
+        }
+        fs::create_dir_all(self.output_dir)?;
+        let output_dir = fs::canonicalize(self.output_dir)?;
+
+        // binwalk places a symlink to the target inside the output directory and writes
+        // each extraction to <target>.extracted/<hex offset>
+        let binwalk = Binwalk::configure(
+            Some(self.path.to_string_lossy().to_string()),
+            Some(output_dir.to_string_lossy().to_string()),
+            None,
+            None,
+            None,
+            false,
+        )
+        .map_err(|_| BinwalkExtractorError::Binwalk)?;
+
+        // The primary file at offset 0 is the media being scanned, not a payload
+        let signatures: Vec<SignatureResult> = binwalk
+            .scan(&file_data)
+            .into_iter()
+            .filter(|sig| sig.offset > 0)
+            .collect();
+        let extractions = binwalk.extract(&file_data, &binwalk.base_target_file, &signatures);
+        let extracted_root = PathBuf::from(format!("{}.extracted", binwalk.base_target_file));
+
+        let mut payloads = Vec::new();
+        for (idx, sig) in signatures.iter().enumerate() {
+            let end = carve_end(&signatures, idx, &file_data);
+
+            match extractions.get(&sig.id) {
+                Some(result) if result.success => payloads.push(ExtractedPayload {
+                    offset: sig.offset,
+                    size: result.size.unwrap_or(end - sig.offset),
+                    description: sig.description.clone(),
+                    method: ExtractionMethod::Extractor(result.extractor.clone()),
+                    path: PathBuf::from(&result.output_directory),
+                }),
+                _ => {
+                    let dir = extracted_root.join(format!("{:X}", sig.offset));
+                    fs::create_dir_all(&dir)?;
+                    let carved = dir.join(format!("carved_0x{:X}.bin", sig.offset));
+                    fs::write(&carved, &file_data[sig.offset..end])?;
+                    payloads.push(ExtractedPayload {
+                        offset: sig.offset,
+                        size: end - sig.offset,
+                        description: sig.description.clone(),
+                        method: ExtractionMethod::Carved,
+                        path: carved,
+                    });
+                }
+            }
+        }
+
+        // The target symlink is only needed while binwalk runs
+        fs::remove_file(&binwalk.base_target_file)?;
+        let removed_links = remove_escaping_links(&output_dir, &output_dir)?;
+
+        Ok(ExtractionResults {
+            output_directory: output_dir,
+            payloads,
+            removed_links,
+        })
+    }
+}
+
+// A signature runs for its reported size when binwalk knows it, then to the end marker of a
+// PNG, JPEG or ZIP, otherwise until the next signature or the end of the file
+fn carve_end(signatures: &[SignatureResult], idx: usize, file_data: &[u8]) -> usize {
+    let file_len = file_data.len();
+    let sig = &signatures[idx];
+    if sig.size > 0 && sig.offset + sig.size <= file_len {
+        return sig.offset + sig.size;
+    }
+    if let Some(end) = embedded_file_end(file_data, sig.offset) {
+        return end;
+    }
+    signatures[idx + 1..]
+        .iter()
+        .map(|next| next.offset)
+        .find(|&offset| offset > sig.offset)
+        .unwrap_or(file_len)
+        .min(file_len)
+}
+
+// Unpacked archives can contain symlinks to host paths (e.g. /etc/passwd); delete any
+// that resolve outside the output directory so later tools can't be led out of it
+fn remove_escaping_links(dir: &Path, root: &Path) -> std::io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.file_type().is_symlink() {
+            let inside = fs::canonicalize(&path).is_ok_and(|target| target.starts_with(root));
+            if !inside {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        } else if metadata.is_dir() {
+            removed += remove_escaping_links(&path, root)?;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(offset: usize, size: usize) -> SignatureResult {
+        SignatureResult {
+            offset,
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_carve_end() {
+        let data = vec![0; 100];
+        let signatures = vec![signature(10, 5), signature(40, 0), signature(70, 500)];
+        assert_eq!(carve_end(&signatures, 0, &data), 15);
+        assert_eq!(carve_end(&signatures, 1, &data), 70);
+        // Reported size runs past the end of the file
+        assert_eq!(carve_end(&signatures, 2, &data), 100);
+    }
+
+    #[test]
+    fn test_refuses_non_empty_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.bin");
+        fs::write(&input, b"data").unwrap();
+        let output = dir.path().join("out");
+        fs::create_dir(&output).unwrap();
+        fs::write(output.join("existing.txt"), b"keep me").unwrap();
+
+        let result = BinwalkExtractorWithPath::new(&input, &output).extract();
+        assert!(matches!(
+            result,
+            Err(BinwalkExtractorError::OutputNotEmpty(_))
+        ));
+        assert!(output.join("existing.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_removes_escaping_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("nested")).unwrap();
+        fs::write(root.join("inside.txt"), b"ok").unwrap();
+        std::os::unix::fs::symlink(root.join("inside.txt"), root.join("nested/good")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", root.join("nested/bad")).unwrap();
+
+        assert_eq!(remove_escaping_links(&root, &root).unwrap(), 1);
+        assert!(fs::symlink_metadata(root.join("nested/good")).is_ok());
+        assert!(fs::symlink_metadata(root.join("nested/bad")).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::Analyzer;
+use crate::region_overlay::FlaggedRegion;
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Bit-plane complexity segmentation steganalysis. BPCS embedding replaces every "complex"
+// 8x8 block of a bit plane with payload, in the higher planes as well as the LSBs, which
+// LSB-only statistics never look at. A natural plane that is partly structured has complex
+// blocks of every complexity down to the threshold; payload blocks are random data, whose
+// complexity clusters tightly around 0.5. So a structured plane whose complex blocks have
+// all left the band just above the threshold has been written over.
+//
+// Planes are taken from the canonical Gray code of each channel, as BPCS embedders do.
+pub struct BpcsAnalyzer;
+
+pub const BLOCK_SIZE: u32 = 8;
+
+// Borders between adjacent pixels in a block: 8 rows and 8 columns of 7
+const MAX_BORDERS: f64 = 112.0;
+
+// Complexity at and above which embedders treat a block as noise
+pub const COMPLEXITY_THRESHOLD: f64 = 0.3;
+
+// Band just above the threshold that natural complex blocks populate and random data,
+// at about 2 standard deviations below 0.5, almost never reaches
+pub const TRANSITION_BAND: f64 = 0.1;
+
+// Share of a plane's complex blocks in the transition band below which it looks embedded
+pub const TRANSITION_RATIO_LIMIT: f64 = 0.04;
+
+// A plane needs both simple and complex blocks before it says anything: all-noise planes
+// look the same embedded or not, and all-simple ones have nothing embedded
+pub const MIN_SIMPLE_RATIO: f64 = 0.15;
+pub const MIN_COMPLEX_BLOCKS: usize = 32;
+
+// Random-looking planes needed before the image is flagged
+pub const NOISE_PLANE_THRESHOLD: usize = 2;
+
+#[derive(Debug)]
+pub enum BpcsError {
+    ImageTooSmall(u32, u32),
+}
+
+impl Display for BpcsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BpcsError::ImageTooSmall(w, h) => {
+                write!(f, "Image too small for BPCS analysis: {}x{}", w, h)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BpcsError {}
+
+#[derive(Debug, Clone)]
+pub struct BitPlaneComplexity {
+    // R, G or B
+    pub channel: String,
+    // 0 is the least significant
+    pub plane: u8,
+    pub complex_blocks: usize,
+    pub simple_ratio: f64,
+    // Mean complexity of the complex blocks
+    pub mean_complexity: f64,
+    // Share of complex blocks within TRANSITION_BAND of the threshold
+    pub transition_ratio: f64,
+    // Structured plane whose complex blocks look like random data
+    pub noise_like: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BpcsAnalysis {
+    pub block_size: u32,
+    pub blocks: usize,
+    pub planes: Vec<BitPlaneComplexity>,
+    pub noise_planes: usize,
+    // Blocks that are random-looking in the noise-like planes, shaded by how many of them
+    pub flagged_regions: Vec<FlaggedRegion>,
+    pub suspicious: bool,
+}
+
+impl BpcsAnalysis {
+    pub fn is_suspicious_at(&self, noise_plane_threshold: usize) -> bool {
+        self.noise_planes >= noise_plane_threshold.max(1)
+    }
+}
+
+impl Analyzer for BpcsAnalyzer {
+    type Input = DynamicImage;
+    type Output = BpcsAnalysis;
+    type Error = BpcsError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let rgba = input.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width < BLOCK_SIZE * 2 || height < BLOCK_SIZE * 2 {
+            return Err(BpcsError::ImageTooSmall(width, height));
+        }
+
+        let columns = width / BLOCK_SIZE;
+        let rows = height / BLOCK_SIZE;
+        let blocks = (columns * rows) as usize;
+
+        // complexity[channel * 8 + plane][block], blocks in raster order
+        let mut complexity: Vec<Vec<f64>> = (0..24).map(|_| Vec::with_capacity(blocks)).collect();
+        for row in 0..rows {
+            for column in 0..columns {
+                let profile = block_profile(&rgba, column * BLOCK_SIZE, row * BLOCK_SIZE);
+                for (plane, value) in profile.into_iter().enumerate() {
+                    complexity[plane].push(value);
+                }
+            }
+        }
+
+        let mut planes = Vec::new();
+        for (index, values) in complexity.iter().enumerate() {
+            planes.push(plane_stats(index, values));
+        }
+        let noise_planes = planes.iter().filter(|p| p.noise_like).count();
+
+        // A block is flagged where it is complex in the planes that look written over
+        let mut hits = vec![0usize; blocks];
+        for (_, values) in planes.iter().zip(&complexity).filter(|(p, _)| p.noise_like) {
+            for (count, &value) in hits.iter_mut().zip(values) {
+                if value >= COMPLEXITY_THRESHOLD {
+                    *count += 1;
+                }
+            }
+        }
+        let flagged_regions = hits
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(block, &count)| FlaggedRegion {
+                x: (block as u32 % columns) * BLOCK_SIZE,
+                y: (block as u32 / columns) * BLOCK_SIZE,
+                width: BLOCK_SIZE,
+                height: BLOCK_SIZE,
+                intensity: count as f64 / noise_planes as f64,
+            })
+            .collect();
+
+        let mut analysis = BpcsAnalysis {
+            block_size: BLOCK_SIZE,
+            blocks,
+            planes,
+            noise_planes,
+            flagged_regions,
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.is_suspicious_at(NOISE_PLANE_THRESHOLD);
+
+        Ok(analysis)
+    }
+}
+
+// Complexity of one block in all 24 Gray-coded planes, indexed channel * 8 + plane
+fn block_profile(image: &RgbaImage, x0: u32, y0: u32) -> [f64; 24] {
+    let mut gray = [[0u8; 3]; (BLOCK_SIZE * BLOCK_SIZE) as usize];
+    for y in 0..BLOCK_SIZE {
+        for x in 0..BLOCK_SIZE {
+            let pixel = image.get_pixel(x0 + x, y0 + y);
+            for channel in 0..3 {
+                gray[(y * BLOCK_SIZE + x) as usize][channel] =
+                    pixel[channel] ^ (pixel[channel] >> 1);
+            }
+        }
+    }
+
+    let size = BLOCK_SIZE as usize;
+    let mut borders = [0u32; 24];
+    for y in 0..size {
+        for x in 0..size {
+            let here = gray[y * size + x];
+            let mut neighbours = Vec::with_capacity(2);
+            if x + 1 < size {
+                neighbours.push(gray[y * size + x + 1]);
+            }
+            if y + 1 < size {
+                neighbours.push(gray[(y + 1) * size + x]);
+            }
+            for there in neighbours {
+                for channel in 0..3 {
+                    let changed = here[channel] ^ there[channel];
+                    for plane in 0..8 {
+                        borders[channel * 8 + plane] += ((changed >> plane) & 1) as u32;
+                    }
+                }
+            }
+        }
+    }
+
+    borders.map(|count| count as f64 / MAX_BORDERS)
+}
+
+fn plane_stats(index: usize, values: &[f64]) -> BitPlaneComplexity {
+    let complex: Vec<f64> = values
+        .iter()
+        .copied()
+        .filter(|&c| c >= COMPLEXITY_THRESHOLD)
+        .collect();
+    let simple_ratio = 1.0 - complex.len() as f64 / values.len().max(1) as f64;
+    let (mean_complexity, transition_ratio) = if complex.is_empty() {
+        (0.0, 0.0)
+    } else {
+        let transition = complex
+            .iter()
+            .filter(|&&c| c < COMPLEXITY_THRESHOLD + TRANSITION_BAND)
+            .count();
+        (
+            complex.iter().sum::<f64>() / complex.len() as f64,
+            transition as f64 / complex.len() as f64,
+        )
+    };
+
+    BitPlaneComplexity {
+        channel: ["R", "G", "B"][index / 8].to_string(),
+        plane: (index % 8) as u8,
+        complex_blocks: complex.len(),
+        simple_ratio,
+        mean_complexity,
+        transition_ratio,
+        noise_like: complex.len() >= MIN_COMPLEX_BLOCKS
+            && simple_ratio >= MIN_SIMPLE_RATIO
+            && transition_ratio < TRANSITION_RATIO_LIMIT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use image::Rgba;
+
+    // Smooth gradients with texture that varies across the image, so the middle planes are
+    // partly structured
+    fn cover() -> RgbaImage {
+        let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+        RgbaImage::from_fn(256, 256, |x, y| {
+            let amplitude = 1 + (x / 32 + y / 64) % 12;
+            let mut channel = |base: u32| {
+                let noise = (rng.next() % (2 * amplitude as u64 + 1)) as i32 - amplitude as i32;
+                (base as i32 + noise).clamp(0, 255) as u8
+            };
+            Rgba([
+                channel(40 + x / 2),
+                channel(60 + y / 2),
+                channel(90 + (x + y) / 4),
+                255,
+            ])
+        })
+    }
+
+    // Replace every complex block of the given planes with random bits, as BPCS does
+    fn embed(image: &mut RgbaImage, planes: std::ops::Range<u8>) {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for by in (0..image.height()).step_by(BLOCK_SIZE as usize) {
+            for bx in (0..image.width()).step_by(BLOCK_SIZE as usize) {
+                let profile = block_profile(image, bx, by);
+                for channel in 0..3 {
+                    for plane in planes.clone() {
+                        if profile[channel * 8 + plane as usize] < COMPLEXITY_THRESHOLD {
+                            continue;
+                        }
+                        for y in by..by + BLOCK_SIZE {
+                            for x in bx..bx + BLOCK_SIZE {
+                                let pixel = image.get_pixel_mut(x, y);
+                                let mut gray = pixel[channel] ^ (pixel[channel] >> 1);
+                                gray = (gray & !(1 << plane)) | (rng.bit() << plane);
+                                // Back from Gray code to binary
+                                let mut value = gray;
+                                let mut shift = gray >> 1;
+                                while shift != 0 {
+                                    value ^= shift;
+                                    shift >>= 1;
+                                }
+                                pixel[channel] = value;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_flags_embedded_planes() {
+        let cover = cover();
+        let clean = BpcsAnalyzer::analyze(DynamicImage::ImageRgba8(cover.clone())).unwrap();
+        assert_eq!(clean.blocks, 32 * 32);
+        assert_eq!(clean.planes.len(), 24);
+        assert!(!clean.suspicious, "{:?}", clean.planes);
+        assert!(clean.flagged_regions.is_empty());
+
+        let mut stego = cover;
+        embed(&mut stego, 1..4);
+        let dirty = BpcsAnalyzer::analyze(DynamicImage::ImageRgba8(stego)).unwrap();
+        assert!(dirty.suspicious, "{:?}", dirty.planes);
+        assert!(!dirty.flagged_regions.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_tiny_images() {
+        let tiny = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+        assert!(BpcsAnalyzer::analyze(tiny).is_err());
+    }
+}
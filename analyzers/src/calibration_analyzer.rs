@@ -0,0 +1,470 @@
+use crate::Analyzer;
+use image::DynamicImage;
+use std::f32::consts::PI;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Calibration steganalysis for JPEGs. The image is decompressed, shifted by half a block and
+// quantized again with the file's own luminance table, which is all recompression does
+// besides lossless entropy coding. The shifted grid no longer lines up with any embedding
+// changes, so its DCT histograms estimate those of the cover. How far the file's own
+// histograms sit from that reference estimates how many coefficients embedding touched,
+// which holds up across quality settings and content where absolute thresholds do not.
+//
+// The change rate comes from the shrinkage estimator of Fridrich, Goljan and Hogea: F5-style
+// embedding moves coefficients toward zero, so h(0) grows and h(1) drops by amounts
+// proportional to the share of coefficients changed.
+pub struct CalibrationAnalyzer;
+
+// Pixels cropped from the top and left edges for the reference
+pub const CROP: u32 = 4;
+
+// Low-frequency modes used for the estimate, as (row, column) of the 8x8 block. They hold
+// enough non-zero coefficients to give stable histograms even at low quality.
+pub const MODES: [(u8, u8); 3] = [(0, 1), (1, 0), (1, 1)];
+
+// Histogram values compared for the divergence, from -HISTOGRAM_RANGE to HISTOGRAM_RANGE
+const HISTOGRAM_RANGE: i32 = 8;
+
+// Estimated share of changed coefficients above which the image is flagged. Covers land
+// within a few percent of zero.
+pub const CHANGE_RATE_THRESHOLD: f64 = 0.1;
+
+// Natural (row-major) index of each coefficient in zig-zag order, the order of DQT tables
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+#[derive(Debug)]
+pub enum CalibrationError {
+    NotJpeg,
+    MissingQuantizationTable,
+    Decode(String),
+    ImageTooSmall(u32, u32),
+}
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationError::NotJpeg => write!(f, "Not a JPEG file"),
+            CalibrationError::MissingQuantizationTable => {
+                write!(f, "No luminance quantization table found")
+            }
+            CalibrationError::Decode(e) => write!(f, "Failed to decode JPEG: {}", e),
+            CalibrationError::ImageTooSmall(w, h) => {
+                write!(f, "Image too small for calibration: {}x{}", w, h)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {}
+
+#[derive(Debug, Clone)]
+pub struct CalibrationMode {
+    // Row and column of the coefficient within the block
+    pub mode: (u8, u8),
+    // Coefficients with magnitude 0, 1 and 2 in the file and in the calibrated reference
+    pub observed: [usize; 3],
+    pub reference: [usize; 3],
+    // Total variation distance between the two histograms, 0 to 1
+    pub divergence: f64,
+    // None when the reference has too few coefficients of magnitude 1 and 2 to estimate from
+    pub change_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalibrationAnalysis {
+    // Full 8x8 blocks of the file's own grid
+    pub blocks: usize,
+    pub modes: Vec<CalibrationMode>,
+    // Mean divergence over the modes
+    pub divergence: f64,
+    // Mean of the per-mode estimates, clamped to 0..1
+    pub estimated_change_rate: f64,
+    pub suspicious: bool,
+}
+
+impl CalibrationAnalysis {
+    // Verdict for a caller-supplied change rate threshold
+    pub fn is_suspicious_at(&self, change_rate_threshold: f64) -> bool {
+        self.estimated_change_rate > change_rate_threshold
+    }
+}
+
+impl Analyzer for CalibrationAnalyzer {
+    type Input = Vec<u8>;
+    type Output = CalibrationAnalysis;
+    type Error = CalibrationError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if !input.starts_with(&[0xFF, 0xD8]) {
+            return Err(CalibrationError::NotJpeg);
+        }
+        let table = luminance_table(&input).ok_or(CalibrationError::MissingQuantizationTable)?;
+        let image = image::load_from_memory_with_format(&input, image::ImageFormat::Jpeg)
+            .map_err(|e| CalibrationError::Decode(e.to_string()))?;
+        let (width, height, luma) = luminance(&image);
+        calibrate(&luma, width, height, &table)
+    }
+}
+
+// Compare the file's grid with the grid shifted by CROP pixels
+fn calibrate(
+    luma: &[f32],
+    width: u32,
+    height: u32,
+    table: &[u16; 64],
+) -> Result<CalibrationAnalysis, CalibrationError> {
+    if width < 16 + CROP || height < 16 + CROP {
+        return Err(CalibrationError::ImageTooSmall(width, height));
+    }
+
+    let observed = quantized_blocks(luma, width, height, 0, table);
+    let reference = quantized_blocks(luma, width, height, CROP, table);
+
+    let modes: Vec<CalibrationMode> = MODES
+        .iter()
+        .map(|&(row, column)| {
+            let index = row as usize * 8 + column as usize;
+            let observed_values: Vec<i32> = observed.iter().map(|block| block[index]).collect();
+            let reference_values: Vec<i32> = reference.iter().map(|block| block[index]).collect();
+            let observed_counts = magnitude_counts(&observed_values);
+            let reference_counts = magnitude_counts(&reference_values);
+            CalibrationMode {
+                mode: (row, column),
+                observed: observed_counts,
+                reference: reference_counts,
+                divergence: histogram_divergence(&observed_values, &reference_values),
+                change_rate: shrinkage_estimate(
+                    &observed_counts,
+                    &reference_counts,
+                    observed_values.len() as f64 / reference_values.len().max(1) as f64,
+                ),
+            }
+        })
+        .collect();
+
+    let divergence = modes.iter().map(|m| m.divergence).sum::<f64>() / modes.len() as f64;
+    let estimates: Vec<f64> = modes.iter().filter_map(|m| m.change_rate).collect();
+    let estimated_change_rate = if estimates.is_empty() {
+        0.0
+    } else {
+        (estimates.iter().sum::<f64>() / estimates.len() as f64).clamp(0.0, 1.0)
+    };
+
+    Ok(CalibrationAnalysis {
+        blocks: observed.len(),
+        modes,
+        divergence,
+        estimated_change_rate,
+        suspicious: estimated_change_rate > CHANGE_RATE_THRESHOLD,
+    })
+}
+
+// Luminance quantization table in natural order: the table of the first frame component
+fn luminance_table(data: &[u8]) -> Option<[u16; 64]> {
+    let mut tables: [Option<[u16; 64]>; 4] = [None; 4];
+    let mut luma_table = None;
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // Fill bytes and markers without a length
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(data.len());
+        let body = &data[(pos + 4).min(end)..end];
+        match marker {
+            0xDB => {
+                let mut at = 0;
+                while at < body.len() {
+                    let precision = body[at] >> 4;
+                    let id = (body[at] & 0x0F) as usize;
+                    let size = if precision == 0 { 64 } else { 128 };
+                    let Some(values) = body.get(at + 1..at + 1 + size) else {
+                        break;
+                    };
+                    let mut table = [0u16; 64];
+                    for (k, &natural) in ZIGZAG.iter().enumerate() {
+                        table[natural] = if precision == 0 {
+                            values[k] as u16
+                        } else {
+                            u16::from_be_bytes([values[2 * k], values[2 * k + 1]])
+                        };
+                    }
+                    if let Some(slot) = tables.get_mut(id) {
+                        *slot = Some(table);
+                    }
+                    at += 1 + size;
+                }
+            }
+            // Frame headers, minus DHT, JPG and DAC which share the range
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                luma_table = body.get(8).map(|&id| id as usize);
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    let table = tables.get(luma_table.unwrap_or(0)).copied().flatten()?;
+    // A zero step can't be divided by, and no encoder writes one
+    table.iter().all(|&q| q > 0).then_some(table)
+}
+
+// Luminance of each pixel, level-shifted to be centered on zero as JPEG encodes it
+fn luminance(image: &DynamicImage) -> (u32, u32, Vec<f32>) {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let luma = rgb
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32 - 128.0)
+        .collect();
+    (width, height, luma)
+}
+
+// Quantized DCT coefficients of every full block of the grid starting at (offset, offset)
+fn quantized_blocks(
+    luma: &[f32],
+    width: u32,
+    height: u32,
+    offset: u32,
+    table: &[u16; 64],
+) -> Vec<[i32; 64]> {
+    let cosines = dct_cosines();
+    let width = width as usize;
+    let offset = offset as usize;
+    let blocks_x = (width - offset) / 8;
+    let blocks_y = (height as usize - offset) / 8;
+    let mut blocks = Vec::with_capacity(blocks_x * blocks_y);
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let mut samples = [0f32; 64];
+            for y in 0..8 {
+                let row = (offset + by * 8 + y) * width + offset + bx * 8;
+                samples[y * 8..y * 8 + 8].copy_from_slice(&luma[row..row + 8]);
+            }
+            let coefficients = dct(&samples, &cosines);
+            let mut block = [0i32; 64];
+            for (i, value) in block.iter_mut().enumerate() {
+                *value = (coefficients[i] / table[i] as f32).round() as i32;
+            }
+            blocks.push(block);
+        }
+    }
+    blocks
+}
+
+// cosines[u][x] = C(u) / 2 * cos((2x + 1) u pi / 16), the orthonormal 8-point DCT-II basis
+fn dct_cosines() -> [[f32; 8]; 8] {
+    let mut cosines = [[0f32; 8]; 8];
+    for (u, row) in cosines.iter_mut().enumerate() {
+        let scale = if u == 0 { 0.5 / 2f32.sqrt() } else { 0.5 };
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = scale * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+    cosines
+}
+
+// 2D DCT of a block in row-major order, rows first
+fn dct(samples: &[f32; 64], cosines: &[[f32; 8]; 8]) -> [f32; 64] {
+    let mut rows = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| cosines[u][x] * samples[y * 8 + x]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for u in 0..8 {
+        for v in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| cosines[v][y] * rows[y * 8 + u]).sum();
+        }
+    }
+    out
+}
+
+fn magnitude_counts(values: &[i32]) -> [usize; 3] {
+    let mut counts = [0usize; 3];
+    for value in values {
+        if let Some(count) = counts.get_mut(value.unsigned_abs() as usize) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+// Total variation distance between the normalized histograms of the two value sets
+fn histogram_divergence(observed: &[i32], reference: &[i32]) -> f64 {
+    let bins = (2 * HISTOGRAM_RANGE + 1) as usize;
+    let histogram = |values: &[i32]| {
+        let mut counts = vec![0f64; bins];
+        for &value in values {
+            let bin = (value.clamp(-HISTOGRAM_RANGE, HISTOGRAM_RANGE) + HISTOGRAM_RANGE) as usize;
+            counts[bin] += 1.0;
+        }
+        let total = values.len().max(1) as f64;
+        counts.iter_mut().for_each(|count| *count /= total);
+        counts
+    };
+    let observed = histogram(observed);
+    let reference = histogram(reference);
+    observed
+        .iter()
+        .zip(&reference)
+        .map(|(a, b)| (a - b).abs())
+        .sum::<f64>()
+        / 2.0
+}
+
+// Least-squares change rate fitting h_obs(0) = h(0) + b h(1) and h_obs(1) = (1 - b) h(1) +
+// b h(2). The reference counts are scaled to the file's block count first, since the cropped
+// grid has a row and column of blocks fewer.
+fn shrinkage_estimate(observed: &[usize; 3], reference: &[usize; 3], scale: f64) -> Option<f64> {
+    let h = reference.map(|count| count as f64 * scale);
+    let denominator = h[1] * h[1] + (h[2] - h[1]) * (h[2] - h[1]);
+    if h[1] < 1.0 || denominator <= 0.0 {
+        return None;
+    }
+    let numerator =
+        h[1] * (observed[0] as f64 - h[0]) + (observed[1] as f64 - h[1]) * (h[2] - h[1]);
+    Some(numerator / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // Standard JPEG luminance table at quality 75
+    fn table() -> [u16; 64] {
+        let base: [u16; 64] = [
+            16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57,
+            69, 56, 14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55,
+            64, 81, 104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100,
+            103, 99,
+        ];
+        base.map(|q| ((q as u32 * 50 + 50) / 100).clamp(1, 255) as u16)
+    }
+
+    // Textured scene: overlapping waves plus pseudo-random grain
+    fn scene() -> RgbImage {
+        let mut state = 99u32;
+        RgbImage::from_fn(256, 256, |x, y| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let grain = ((state >> 16) % 13) as f32 - 6.0;
+            let value = 128.0
+                + 20.0 * (x as f32 / 9.0).sin()
+                + 15.0 * (y as f32 / 13.0).cos()
+                + 8.0 * ((x + y) as f32 / 5.0).sin()
+                + grain;
+            let v = value.clamp(0.0, 255.0) as u8;
+            Rgb([v, v, v])
+        })
+    }
+
+    fn idct(coefficients: &[f32; 64], cosines: &[[f32; 8]; 8]) -> [f32; 64] {
+        let mut out = [0f32; 64];
+        for y in 0..8 {
+            for x in 0..8 {
+                out[y * 8 + x] = (0..8)
+                    .flat_map(|v| (0..8).map(move |u| (u, v)))
+                    .map(|(u, v)| cosines[v][y] * cosines[u][x] * coefficients[v * 8 + u])
+                    .sum();
+            }
+        }
+        out
+    }
+
+    // Decompressed luminance of a JPEG with the given quantized blocks on the aligned grid
+    fn render(blocks: &[[i32; 64]], blocks_x: usize, table: &[u16; 64]) -> Vec<f32> {
+        let cosines = dct_cosines();
+        let width = blocks_x * 8;
+        let mut luma = vec![0f32; blocks.len() * 64];
+        for (i, block) in blocks.iter().enumerate() {
+            let mut coefficients = [0f32; 64];
+            for k in 0..64 {
+                coefficients[k] = (block[k] * table[k] as i32) as f32;
+            }
+            let samples = idct(&coefficients, &cosines);
+            let (bx, by) = (i % blocks_x, i / blocks_x);
+            for y in 0..8 {
+                for x in 0..8 {
+                    let pixel = (samples[y * 8 + x] + 128.0).round().clamp(0.0, 255.0);
+                    luma[(by * 8 + y) * width + bx * 8 + x] = pixel - 128.0;
+                }
+            }
+        }
+        luma
+    }
+
+    #[test]
+    fn test_clean_jpeg_calibrates_near_zero() {
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 75)
+            .encode_image(&scene())
+            .unwrap();
+
+        let calibration = CalibrationAnalyzer::analyze(jpeg).unwrap();
+        assert_eq!(calibration.blocks, 32 * 32);
+        assert!(!calibration.suspicious, "{:?}", calibration);
+        assert!(
+            calibration.estimated_change_rate < 0.05,
+            "{:?}",
+            calibration
+        );
+    }
+
+    #[test]
+    fn test_f5_shrinkage_is_estimated() {
+        let table = table();
+        let luma: Vec<f32> = scene().pixels().map(|p| p[0] as f32 - 128.0).collect();
+        let mut blocks = quantized_blocks(&luma, 256, 256, 0, &table);
+        let clean = calibrate(&render(&blocks, 32, &table), 256, 256, &table).unwrap();
+
+        // F5 decrements the magnitude of about every third non-zero AC coefficient
+        let mut state = 3u32;
+        for block in blocks.iter_mut() {
+            for value in block.iter_mut().skip(1).filter(|v| **v != 0) {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                if (state >> 16).is_multiple_of(3) {
+                    *value -= value.signum();
+                }
+            }
+        }
+        let stego = calibrate(&render(&blocks, 32, &table), 256, 256, &table).unwrap();
+
+        assert!(!clean.suspicious, "{:?}", clean);
+        assert!(stego.suspicious, "{:?}", stego);
+        assert!(stego.divergence > clean.divergence);
+        assert!(
+            (stego.estimated_change_rate - 0.33).abs() < 0.15,
+            "{:?}",
+            stego
+        );
+    }
+
+    #[test]
+    fn test_not_jpeg() {
+        assert!(matches!(
+            CalibrationAnalyzer::analyze(b"\x89PNG\r\n\x1a\n".to_vec()),
+            Err(CalibrationError::NotJpeg)
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::Analyzer;
+use crate::region_overlay::FlaggedRegion;
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Inter-channel LSB correlation. In smooth regions the R, G and B LSB planes move
+// together with the planes above them; LSB embedding randomises bit 0 independently
+// per channel, so its correlation collapses while bit 1 keeps the original structure.
+pub struct ChannelCorrelationAnalyzer;
+
+pub const BLOCK_SIZE: u32 = 16;
+
+// Bit-1 correlation a block needs before its LSB correlation is meaningful
+pub const REFERENCE_CORRELATION_MIN: f64 = 0.5;
+
+// A block collapses when its LSB correlation falls below this fraction of bit 1's
+pub const COLLAPSE_RATIO: f64 = 0.5;
+
+// Fraction of evaluated blocks that must collapse before the image is flagged
+pub const COLLAPSED_BLOCK_THRESHOLD: f64 = 0.2;
+
+// Cap on how many collapsed block locations are kept for reporting
+pub const MAX_REPORTED_BLOCKS: usize = 64;
+
+#[derive(Debug)]
+pub enum ChannelCorrelationError {
+    ImageTooSmall(u32, u32),
+}
+
+impl Display for ChannelCorrelationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelCorrelationError::ImageTooSmall(w, h) => write!(
+                f,
+                "Image too small for channel correlation: {}x{} (need {}x{})",
+                w, h, BLOCK_SIZE, BLOCK_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChannelCorrelationError {}
+
+#[derive(Debug, Clone)]
+pub struct CollapsedBlock {
+    // Top-left pixel of the block
+    pub x: u32,
+    pub y: u32,
+    pub lsb_correlation: f64,
+    pub reference_correlation: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelCorrelationAnalysis {
+    pub block_size: u32,
+    // Smooth blocks whose bit-1 planes were correlated enough to judge
+    pub blocks_evaluated: usize,
+    pub collapsed_block_count: usize,
+    pub collapsed_ratio: f64,
+    pub mean_lsb_correlation: f64,
+    pub mean_reference_correlation: f64,
+    // First MAX_REPORTED_BLOCKS collapsed blocks in raster order
+    pub collapsed_blocks: Vec<CollapsedBlock>,
+    // Every collapsed block, shaded by how far its LSB correlation fell, for overlays
+    pub collapsed_regions: Vec<FlaggedRegion>,
+    pub suspicious: bool,
+}
+
+impl ChannelCorrelationAnalysis {
+    pub fn is_suspicious_at(&self, collapsed_ratio_threshold: f64) -> bool {
+        self.blocks_evaluated > 0 && self.collapsed_ratio > collapsed_ratio_threshold
+    }
+}
+
+impl Analyzer for ChannelCorrelationAnalyzer {
+    type Input = DynamicImage;
+    type Output = ChannelCorrelationAnalysis;
+    type Error = ChannelCorrelationError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let rgba = input.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width < BLOCK_SIZE || height < BLOCK_SIZE {
+            return Err(ChannelCorrelationError::ImageTooSmall(width, height));
+        }
+
+        let mut blocks_evaluated = 0;
+        let mut collapsed_block_count = 0;
+        let mut lsb_total = 0.0;
+        let mut reference_total = 0.0;
+        let mut collapsed_blocks = Vec::new();
+        let mut collapsed_regions = Vec::new();
+
+        for y in (0..=height - BLOCK_SIZE).step_by(BLOCK_SIZE as usize) {
+            for x in (0..=width - BLOCK_SIZE).step_by(BLOCK_SIZE as usize) {
+                let reference_correlation = block_correlation(&rgba, x, y, 1);
+                if reference_correlation < REFERENCE_CORRELATION_MIN {
+                    continue;
+                }
+                let lsb_correlation = block_correlation(&rgba, x, y, 0);
+
+                blocks_evaluated += 1;
+                lsb_total += lsb_correlation;
+                reference_total += reference_correlation;
+
+                if lsb_correlation < reference_correlation * COLLAPSE_RATIO {
+                    collapsed_block_count += 1;
+                    collapsed_regions.push(FlaggedRegion {
+                        x,
+                        y,
+                        width: BLOCK_SIZE,
+                        height: BLOCK_SIZE,
+                        intensity: 1.0 - lsb_correlation / reference_correlation,
+                    });
+                    if collapsed_blocks.len() < MAX_REPORTED_BLOCKS {
+                        collapsed_blocks.push(CollapsedBlock {
+                            x,
+                            y,
+                            lsb_correlation,
+                            reference_correlation,
+                        });
+                    }
+                }
+            }
+        }
+
+        let (collapsed_ratio, mean_lsb_correlation, mean_reference_correlation) =
+            if blocks_evaluated == 0 {
+                (0.0, 0.0, 0.0)
+            } else {
+                let n = blocks_evaluated as f64;
+                (
+                    collapsed_block_count as f64 / n,
+                    lsb_total / n,
+                    reference_total / n,
+                )
+            };
+
+        let mut analysis = ChannelCorrelationAnalysis {
+            block_size: BLOCK_SIZE,
+            blocks_evaluated,
+            collapsed_block_count,
+            collapsed_ratio,
+            mean_lsb_correlation,
+            mean_reference_correlation,
+            collapsed_blocks,
+            collapsed_regions,
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.is_suspicious_at(COLLAPSED_BLOCK_THRESHOLD);
+
+        Ok(analysis)
+    }
+}
+
+// Mean absolute correlation of one bit plane across the R-G, G-B and R-B pairs.
+// Uses agreement (2 * matches / n - 1) rather than Pearson so flat blocks, where a
+// plane is constant, still count as perfectly correlated.
+fn block_correlation(image: &RgbaImage, x0: u32, y0: u32, bit: u8) -> f64 {
+    let mut matches = [0u32; 3];
+    let mut total = 0u32;
+
+    for y in y0..y0 + BLOCK_SIZE {
+        for x in x0..x0 + BLOCK_SIZE {
+            let pixel = image.get_pixel(x, y);
+            let [r, g, b] = [0, 1, 2].map(|c| (pixel[c] >> bit) & 1);
+            matches[0] += (r == g) as u32;
+            matches[1] += (g == b) as u32;
+            matches[2] += (r == b) as u32;
+            total += 1;
+        }
+    }
+
+    matches
+        .iter()
+        .map(|&m| (2.0 * m as f64 / total as f64 - 1.0).abs())
+        .sum::<f64>()
+        / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use image::Rgba;
+
+    #[test]
+    fn test_embedding_collapses_smooth_blocks() {
+        // Smooth grey gradient: every channel carries the same value
+        let cover = RgbaImage::from_fn(128, 128, |x, y| {
+            let v = ((x + y) / 4) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let clean =
+            ChannelCorrelationAnalyzer::analyze(DynamicImage::ImageRgba8(cover.clone())).unwrap();
+        assert!(clean.blocks_evaluated > 0);
+        assert_eq!(clean.collapsed_block_count, 0);
+        assert!(!clean.suspicious);
+
+        // Overwrite each channel's LSB with an independent pseudo-random bit
+        let mut stego = cover;
+        let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+        for pixel in stego.pixels_mut() {
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] & !1) | rng.bit();
+            }
+        }
+        let dirty = ChannelCorrelationAnalyzer::analyze(DynamicImage::ImageRgba8(stego)).unwrap();
+        assert!(
+            dirty.suspicious,
+            "collapsed ratio {}",
+            dirty.collapsed_ratio
+        );
+        assert!(dirty.mean_lsb_correlation < clean.mean_lsb_correlation);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::Analyzer;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Display;
+
+// Tells photographs from synthetic images such as screenshots, UI captures and diagrams.
+// Sensor noise makes neighbouring photo pixels differ, while rendered content is mostly
+// runs of identical colors. Pixels are compared with their LSBs masked off, so embedding
+// does not change the class.
+//
+// The two classes need different LSB baselines. In a photo the LSB plane is noise to begin
+// with and the plane-wide statistics apply. In a screenshot the flat runs have identical
+// LSBs too, so any LSB disagreement between pixels that are otherwise equal is the signal.
+pub struct ContentClassifier;
+
+// Share of neighbouring pixel pairs equal above the LSB from which an image is synthetic
+pub const FLAT_RATIO_THRESHOLD: f64 = 0.5;
+
+// Share of those equal pairs whose LSBs disagree before a synthetic image is flagged
+pub const FLAT_LSB_NOISE_THRESHOLD: f64 = 0.05;
+
+// Pixels sampled for the distinct color count
+const MAX_COLOR_SAMPLES: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum ContentClassifierError {
+    EmptyImage,
+}
+
+impl Display for ContentClassifierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentClassifierError::EmptyImage => write!(f, "Image has no neighbouring pixels"),
+        }
+    }
+}
+
+impl std::error::Error for ContentClassifierError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentClass {
+    Photo,
+    // Screenshots, UI captures, diagrams and other rendered content
+    Synthetic,
+}
+
+impl Display for ContentClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentClass::Photo => write!(f, "photo"),
+            ContentClass::Synthetic => write!(f, "synthetic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentClassification {
+    pub class: ContentClass,
+    // Share of horizontal and vertical neighbour pairs equal once LSBs are masked off
+    pub flat_ratio: f64,
+    // Distinct RGB colors, over at most MAX_COLOR_SAMPLES pixels
+    pub unique_colors: usize,
+    // Share of those flat pairs whose LSBs differ in any channel
+    pub flat_lsb_noise: f64,
+}
+
+impl ContentClassification {
+    // Reclassify with a caller-supplied flat ratio
+    pub fn class_at(&self, flat_ratio_threshold: f64) -> ContentClass {
+        if self.flat_ratio >= flat_ratio_threshold {
+            ContentClass::Synthetic
+        } else {
+            ContentClass::Photo
+        }
+    }
+
+    // LSB disagreement in flat runs above the threshold; only meaningful for synthetic images
+    pub fn has_flat_lsb_noise(&self, noise_threshold: f64) -> bool {
+        self.flat_lsb_noise > noise_threshold
+    }
+}
+
+impl Analyzer for ContentClassifier {
+    type Input = DynamicImage;
+    type Output = ContentClassification;
+    type Error = ContentClassifierError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let rgb = input.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        if width < 2 && height < 2 {
+            return Err(ContentClassifierError::EmptyImage);
+        }
+
+        let mut pairs = 0u64;
+        let mut flat = 0u64;
+        let mut noisy = 0u64;
+        let mut compare = |a: &image::Rgb<u8>, b: &image::Rgb<u8>| {
+            pairs += 1;
+            if a.0
+                .iter()
+                .zip(b.0.iter())
+                .all(|(x, y)| x & 0xFE == y & 0xFE)
+            {
+                flat += 1;
+                if a.0 != b.0 {
+                    noisy += 1;
+                }
+            }
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = rgb.get_pixel(x, y);
+                if x + 1 < width {
+                    compare(pixel, rgb.get_pixel(x + 1, y));
+                }
+                if y + 1 < height {
+                    compare(pixel, rgb.get_pixel(x, y + 1));
+                }
+            }
+        }
+
+        let pixel_count = (width as usize) * (height as usize);
+        let step = pixel_count.div_ceil(MAX_COLOR_SAMPLES).max(1);
+        let unique_colors = rgb
+            .pixels()
+            .step_by(step)
+            .map(|p| u32::from_be_bytes([0, p[0], p[1], p[2]]))
+            .collect::<HashSet<_>>()
+            .len();
+
+        let flat_ratio = flat as f64 / pairs as f64;
+        let flat_lsb_noise = if flat > 0 {
+            noisy as f64 / flat as f64
+        } else {
+            0.0
+        };
+        let mut classification = ContentClassification {
+            class: ContentClass::Photo,
+            flat_ratio,
+            unique_colors,
+            flat_lsb_noise,
+        };
+        classification.class = classification.class_at(FLAT_RATIO_THRESHOLD);
+        Ok(classification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // Window-like layout: a title bar, a panel and a few text lines
+    fn screenshot() -> RgbImage {
+        RgbImage::from_fn(128, 96, |x, y| {
+            if y < 12 {
+                Rgb([40, 44, 52])
+            } else if x < 32 {
+                Rgb([230, 230, 230])
+            } else if y % 16 == 4 && x % 7 < 4 {
+                Rgb([20, 20, 20])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        })
+    }
+
+    // Smooth gradient with pseudo-random sensor noise of a few levels
+    fn photo() -> RgbImage {
+        let mut state = 12345u32;
+        RgbImage::from_fn(128, 96, |x, y| {
+            let mut noise = || {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((state >> 16) % 9) as i32 - 4
+            };
+            let base = (x + y) as i32;
+            Rgb([
+                (base + 40 + noise()).clamp(0, 255) as u8,
+                (base + 60 + noise()).clamp(0, 255) as u8,
+                (base + 20 + noise()).clamp(0, 255) as u8,
+            ])
+        })
+    }
+
+    #[test]
+    fn test_screenshot_and_photo_classes() {
+        let shot = ContentClassifier::analyze(DynamicImage::ImageRgb8(screenshot())).unwrap();
+        assert_eq!(shot.class, ContentClass::Synthetic);
+        assert!(shot.unique_colors <= 4);
+        assert_eq!(shot.flat_lsb_noise, 0.0);
+
+        let photo = ContentClassifier::analyze(DynamicImage::ImageRgb8(photo())).unwrap();
+        assert_eq!(photo.class, ContentClass::Photo, "{:?}", photo);
+    }
+
+    #[test]
+    fn test_lsb_embedding_in_screenshot() {
+        let mut image = screenshot();
+        let mut state = 7u32;
+        // Replace every LSB of the first half of the image with message bits
+        for (i, pixel) in image.pixels_mut().enumerate() {
+            if i >= 128 * 48 {
+                break;
+            }
+            for channel in pixel.0.iter_mut() {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                *channel = (*channel & 0xFE) | ((state >> 16) & 1) as u8;
+            }
+        }
+
+        let stego = ContentClassifier::analyze(DynamicImage::ImageRgb8(image)).unwrap();
+        // Still synthetic: the class ignores the LSBs
+        assert_eq!(stego.class, ContentClass::Synthetic);
+        assert!(
+            stego.has_flat_lsb_noise(FLAT_LSB_NOISE_THRESHOLD),
+            "{:?}",
+            stego
+        );
+    }
+}
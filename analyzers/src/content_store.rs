@@ -0,0 +1,284 @@
+use crate::shared;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Content-addressable storage for carved payloads. Objects are named after the SHA-256 of their
+// content, so a payload carved from many files is written once, and the index entry kept next
+// to each object lists every place it was carved from:
+//
+//   <root>/<sha256>.<ext>   the data
+//   <root>/<sha256>.json    its index entry
+
+// Index entries are read, updated and written back; serialize that across every store in the
+// process so concurrent scans don't drop each other's references
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectReference {
+    // The file or scan the object was carved from
+    pub source: String,
+    pub offset: usize,
+    pub description: String,
+    // Suggested file name when the object is handed out
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredObject {
+    pub sha256: String,
+    // Name of the data file under the store root
+    pub file_name: String,
+    pub size_bytes: usize,
+    // Seconds since the Unix epoch when the object was first stored
+    pub created_at: u64,
+    pub references: Vec<ObjectReference>,
+}
+
+impl StoredObject {
+    // Sources other than the given one the same content was carved from, first seen first
+    pub fn other_sources(&self, source: &str) -> Vec<String> {
+        let mut sources: Vec<String> = Vec::new();
+        for reference in &self.references {
+            if reference.source != source && !sources.contains(&reference.source) {
+                sources.push(reference.source.clone());
+            }
+        }
+        sources
+    }
+}
+
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn hash(data: &[u8]) -> String {
+        shared::hex(&Sha256::digest(data))
+    }
+
+    // Lowercase hex SHA-256; anything else could name a path outside the store
+    pub fn is_valid_hash(hash: &str) -> bool {
+        hash.len() == 64
+            && hash
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    }
+
+    // Store the data unless identical content is already there, and add the reference to its
+    // index entry. The extension only applies to the first copy stored.
+    pub fn put(
+        &self,
+        data: &[u8],
+        extension: &str,
+        reference: ObjectReference,
+    ) -> std::io::Result<StoredObject> {
+        let sha256 = Self::hash(data);
+        let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::fs::create_dir_all(&self.root)?;
+
+        let mut object = match self.read_entry(&sha256) {
+            Some(object) if self.path(&object).exists() => object,
+            _ => {
+                let object = StoredObject {
+                    file_name: format!("{}.{}", sha256, sanitize_extension(extension)),
+                    sha256: sha256.clone(),
+                    size_bytes: data.len(),
+                    created_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs()),
+                    references: Vec::new(),
+                };
+                std::fs::write(self.path(&object), data)?;
+                object
+            }
+        };
+
+        if !object.references.contains(&reference) {
+            object.references.push(reference);
+        }
+        self.write_entry(&object)?;
+
+        Ok(object)
+    }
+
+    // Index entry of a stored object
+    pub fn get(&self, sha256: &str) -> Option<StoredObject> {
+        if !Self::is_valid_hash(sha256) {
+            return None;
+        }
+        self.read_entry(sha256)
+    }
+
+    // Index entry and data of a stored object
+    pub fn read(&self, sha256: &str) -> Option<(StoredObject, Vec<u8>)> {
+        let object = self.get(sha256)?;
+        let data = std::fs::read(self.path(&object)).ok()?;
+        Some((object, data))
+    }
+
+    // Where the object's data lives
+    pub fn path(&self, object: &StoredObject) -> PathBuf {
+        self.root.join(&object.file_name)
+    }
+
+    // Bytes the object's data and index entry take on disk
+    pub fn stored_bytes(&self, sha256: &str) -> u64 {
+        let Some(object) = self.get(sha256) else {
+            return 0;
+        };
+        [self.path(&object), self.entry_path(sha256)]
+            .iter()
+            .map(|path| std::fs::metadata(path).map_or(0, |m| m.len()))
+            .sum()
+    }
+
+    // Index entries of every stored object, in no particular order
+    pub fn objects(&self) -> std::io::Result<Vec<StoredObject>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut objects = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Some(object) = self.get(stem) {
+                objects.push(object);
+            }
+        }
+
+        Ok(objects)
+    }
+
+    // Drop the source's references to an object, removing the object once nothing references
+    // it. Returns the bytes freed.
+    pub fn release(&self, sha256: &str, source: &str) -> std::io::Result<u64> {
+        let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(mut object) = self.get(sha256) else {
+            return Ok(0);
+        };
+
+        object
+            .references
+            .retain(|reference| reference.source != source);
+        if object.references.is_empty() {
+            return self.remove_object(&object);
+        }
+        self.write_entry(&object)?;
+        Ok(0)
+    }
+
+    // Remove an object regardless of its references, returning the bytes freed
+    pub fn remove(&self, sha256: &str) -> std::io::Result<u64> {
+        let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        match self.get(sha256) {
+            Some(object) => self.remove_object(&object),
+            None => Ok(0),
+        }
+    }
+
+    fn remove_object(&self, object: &StoredObject) -> std::io::Result<u64> {
+        let mut freed = 0;
+        for path in [self.path(object), self.entry_path(&object.sha256)] {
+            let size = std::fs::metadata(&path).map_or(0, |m| m.len());
+            match std::fs::remove_file(&path) {
+                Ok(()) => freed += size,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(freed)
+    }
+
+    fn entry_path(&self, sha256: &str) -> PathBuf {
+        self.root.join(format!("{}.json", sha256))
+    }
+
+    fn read_entry(&self, sha256: &str) -> Option<StoredObject> {
+        let json = std::fs::read_to_string(self.entry_path(sha256)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn write_entry(&self, object: &StoredObject) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(object).map_err(std::io::Error::other)?;
+        std::fs::write(self.entry_path(&object.sha256), json)
+    }
+}
+
+// Extensions end up in file names; keep them short and alphanumeric
+fn sanitize_extension(extension: &str) -> String {
+    let extension: String = extension
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if extension.is_empty() {
+        "bin".to_string()
+    } else {
+        extension
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(source: &str, offset: usize) -> ObjectReference {
+        ObjectReference {
+            source: source.to_string(),
+            offset,
+            description: "ZIP archive".to_string(),
+            name: format!("carved_0x{:X}.bin", offset),
+        }
+    }
+
+    #[test]
+    fn test_identical_payloads_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(dir.path());
+        let payload = b"PK\x03\x04 hidden archive".to_vec();
+
+        let first = store.put(&payload, "zip", reference("a.png", 64)).unwrap();
+        let second = store.put(&payload, "bin", reference("b.jpg", 128)).unwrap();
+        store.put(&payload, "zip", reference("a.png", 64)).unwrap();
+
+        assert_eq!(first.sha256, second.sha256);
+        assert_eq!(second.file_name, format!("{}.zip", first.sha256));
+        assert_eq!(second.references.len(), 2);
+        assert_eq!(second.other_sources("b.jpg"), vec!["a.png".to_string()]);
+        assert_eq!(store.objects().unwrap().len(), 1);
+        assert_eq!(store.read(&first.sha256).unwrap().1, payload);
+    }
+
+    #[test]
+    fn test_release_removes_unreferenced_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(dir.path());
+        let object = store.put(b"payload", "bin", reference("a.png", 0)).unwrap();
+        store.put(b"payload", "bin", reference("b.png", 0)).unwrap();
+
+        assert_eq!(store.release(&object.sha256, "a.png").unwrap(), 0);
+        assert!(store.get(&object.sha256).is_some());
+        assert!(store.release(&object.sha256, "b.png").unwrap() > 0);
+        assert!(store.get(&object.sha256).is_none());
+        assert!(!store.path(&object).exists());
+        assert!(store.get("../../etc/passwd").is_none());
+    }
+}
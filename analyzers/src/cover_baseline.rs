@@ -0,0 +1,578 @@
+use crate::content_classifier::{ContentClass, ContentClassification};
+use crate::lsb_analyzer::LsbAnalysis;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::Path;
+
+// What the LSB planes of clean covers look like, per format and content class, so an image is
+// judged by how far it strays from covers like it instead of by one fixed cut-off. A decoded
+// JPEG photo has LSBs close to noise already, a PNG screenshot has long runs of identical
+// LSBs, and camera RAW data carries sensor noise all the way down; no single chi-square or
+// entropy limit fits all three.
+//
+// The shipped numbers are estimates. A calibration measured on a site's own known-clean files
+// replaces the shipped entries for the formats and classes it covers, and can carry the
+// high-frequency spectral energy of clean recordings too:
+//
+//   baselines:
+//     - format: jpeg
+//       content: photo
+//       chi_square: { mean: 0.004, std_dev: 0.006 }
+//       entropy: { mean: 0.9985, std_dev: 0.002 }
+//       samples: 1200
+//   audio:
+//     high_frequency_energy: { mean: 0.02, std_dev: 0.01 }
+//     samples: 85
+
+// Standard deviations from the baseline mean before a statistic is flagged
+pub const BASELINE_SIGMA: f64 = 3.0;
+
+// Keeps a baseline measured on identical covers from dividing by zero
+const MIN_STD_DEV: f64 = 1e-6;
+
+#[derive(Debug)]
+pub enum CoverBaselineError {
+    Io(std::io::Error),
+    InvalidCalibration(String),
+}
+
+impl Display for CoverBaselineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverBaselineError::Io(e) => write!(f, "Failed to read calibration: {}", e),
+            CoverBaselineError::InvalidCalibration(e) => write!(f, "Invalid calibration: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CoverBaselineError {}
+
+impl From<std::io::Error> for CoverBaselineError {
+    fn from(e: std::io::Error) -> Self {
+        CoverBaselineError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverFormat {
+    Jpeg,
+    Png,
+    // Camera RAW: DNG, CR2, NEF, ARW and the like
+    Raw,
+    Other,
+}
+
+const RAW_EXTENSIONS: [&str; 10] = [
+    "dng", "cr2", "cr3", "nef", "arw", "orf", "rw2", "raf", "pef", "srw",
+];
+
+impl CoverFormat {
+    // By extension: RAW files are TIFF containers, so the structure alone doesn't say the
+    // pixels are undeveloped sensor data
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "jpg" | "jpeg" | "jpe" | "jfif" => CoverFormat::Jpeg,
+            "png" => CoverFormat::Png,
+            ext if RAW_EXTENSIONS.contains(&ext) => CoverFormat::Raw,
+            _ => CoverFormat::Other,
+        }
+    }
+}
+
+impl Display for CoverFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverFormat::Jpeg => write!(f, "jpeg"),
+            CoverFormat::Png => write!(f, "png"),
+            CoverFormat::Raw => write!(f, "raw"),
+            CoverFormat::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Statistic {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Statistic {
+    fn is_valid(&self) -> bool {
+        self.mean.is_finite() && self.std_dev.is_finite() && self.std_dev >= 0.0
+    }
+
+    // Mean and sample standard deviation, None without samples
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = if samples.len() > 1 {
+            samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+
+    // Signed distance from the mean in standard deviations
+    pub fn z_score(&self, value: f64) -> f64 {
+        (value - self.mean) / self.std_dev.max(MIN_STD_DEV)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoverBaseline {
+    pub format: CoverFormat,
+    pub content: ContentClass,
+    // Chi-square per LSB pair, which unlike the raw score doesn't grow with the image
+    pub chi_square: Statistic,
+    pub entropy: Statistic,
+    // Channels the statistics were measured on, 0 for the shipped estimates
+    #[serde(default)]
+    pub samples: usize,
+}
+
+// Spectral energy above the high-frequency cutoff in clean recordings, one sample per
+// spectrogram window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioBaseline {
+    pub high_frequency_energy: Statistic,
+    #[serde(default)]
+    pub samples: usize,
+}
+
+impl AudioBaseline {
+    pub fn measure(high_frequency_energies: &[f64]) -> Option<Self> {
+        Some(Self {
+            high_frequency_energy: Statistic::from_samples(high_frequency_energies)?,
+            samples: high_frequency_energies.len(),
+        })
+    }
+
+    // Energy this many standard deviations above clean recordings; only more is unusual,
+    // a recording quieter up high than the corpus hides nothing there
+    pub fn high_frequency_energy_threshold(&self, sigma: f64) -> f64 {
+        self.high_frequency_energy.mean + sigma * self.high_frequency_energy.std_dev
+    }
+}
+
+// Statistics measured on known-clean files, as written by a calibration run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Calibration {
+    #[serde(default)]
+    pub baselines: Vec<CoverBaseline>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioBaseline>,
+}
+
+impl Calibration {
+    // A YAML file, or JSON when the extension is .json
+    pub fn read(path: &Path) -> Result<Self, CoverBaselineError> {
+        let text = std::fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let calibration: Self = if is_json {
+            serde_json::from_str(&text)
+                .map_err(|e| CoverBaselineError::InvalidCalibration(e.to_string()))?
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| CoverBaselineError::InvalidCalibration(e.to_string()))?
+        };
+
+        let invalid = |kind: String| {
+            CoverBaselineError::InvalidCalibration(format!(
+                "{}: means must be numbers and standard deviations non-negative",
+                kind
+            ))
+        };
+        for baseline in &calibration.baselines {
+            if !baseline.chi_square.is_valid() || !baseline.entropy.is_valid() {
+                return Err(invalid(format!("{}/{}", baseline.format, baseline.content)));
+            }
+        }
+        if let Some(audio) = &calibration.audio
+            && !audio.high_frequency_energy.is_valid()
+        {
+            return Err(invalid("audio".to_string()));
+        }
+        Ok(calibration)
+    }
+
+    // Take newly measured statistics, keeping those the new run had no files for
+    pub fn update(&mut self, measured: Calibration) {
+        for baseline in measured.baselines {
+            match self
+                .baselines
+                .iter_mut()
+                .find(|b| b.format == baseline.format && b.content == baseline.content)
+            {
+                Some(existing) => *existing = baseline,
+                None => self.baselines.push(baseline),
+            }
+        }
+        if measured.audio.is_some() {
+            self.audio = measured.audio;
+        }
+    }
+
+    pub fn to_yaml(&self) -> Result<String, CoverBaselineError> {
+        serde_yaml::to_string(self)
+            .map_err(|e| CoverBaselineError::InvalidCalibration(e.to_string()))
+    }
+}
+
+// How one channel compares with its baseline
+#[derive(Debug, Clone)]
+pub struct ChannelDeviation {
+    pub channel: String,
+    pub chi_square_per_pair: f64,
+    pub chi_square_z: f64,
+    pub entropy_z: f64,
+}
+
+impl ChannelDeviation {
+    // The larger distance of the two statistics, either way from the mean
+    pub fn max_z(&self) -> f64 {
+        self.chi_square_z.abs().max(self.entropy_z.abs())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LsbDeviation {
+    pub baseline: CoverBaseline,
+    pub channels: Vec<ChannelDeviation>,
+}
+
+impl LsbDeviation {
+    pub fn max_z(&self) -> f64 {
+        self.channels
+            .iter()
+            .map(ChannelDeviation::max_z)
+            .fold(0.0, f64::max)
+    }
+
+    // Any channel further than `sigma` standard deviations from clean covers of its kind
+    pub fn is_suspicious_at(&self, sigma: f64) -> bool {
+        self.max_z() > sigma
+    }
+
+    // The verdict for an image of this content class: a synthetic image is also flagged for
+    // LSB noise in its flat runs, which the plane-wide statistics barely register
+    pub fn is_suspicious_for(
+        &self,
+        content: &ContentClassification,
+        sigma: f64,
+        flat_lsb_noise_threshold: f64,
+    ) -> bool {
+        self.is_suspicious_at(sigma)
+            || (content.class == ContentClass::Synthetic
+                && content.has_flat_lsb_noise(flat_lsb_noise_threshold))
+    }
+}
+
+// One channel of a known-clean cover, for `measure`
+#[derive(Debug, Clone, Copy)]
+pub struct CoverSample {
+    pub format: CoverFormat,
+    pub content: ContentClass,
+    pub chi_square_per_pair: f64,
+    pub entropy: f64,
+}
+
+impl CoverSample {
+    pub fn from_analysis(
+        format: CoverFormat,
+        content: ContentClass,
+        lsb: &LsbAnalysis,
+    ) -> Vec<Self> {
+        (0..lsb.chi_square_scores.len())
+            .map(|channel| Self {
+                format,
+                content,
+                chi_square_per_pair: chi_square_per_pair(lsb, channel),
+                entropy: lsb.entropy_scores[channel],
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverBaselines {
+    baselines: Vec<CoverBaseline>,
+}
+
+impl Default for CoverBaselines {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl CoverBaselines {
+    pub fn builtin() -> Self {
+        let baseline =
+            |format, content, chi_square: (f64, f64), entropy: (f64, f64)| CoverBaseline {
+                format,
+                content,
+                chi_square: Statistic {
+                    mean: chi_square.0,
+                    std_dev: chi_square.1,
+                },
+                entropy: Statistic {
+                    mean: entropy.0,
+                    std_dev: entropy.1,
+                },
+                samples: 0,
+            };
+        Self {
+            baselines: vec![
+                // Rounding in the inverse DCT leaves decoded JPEG LSBs close to a coin toss
+                baseline(
+                    CoverFormat::Jpeg,
+                    ContentClass::Photo,
+                    (0.004, 0.006),
+                    (0.9985, 0.002),
+                ),
+                // Lossless photos keep their sensor noise, smoothed a little by demosaicing
+                baseline(
+                    CoverFormat::Png,
+                    ContentClass::Photo,
+                    (0.01, 0.015),
+                    (0.995, 0.006),
+                ),
+                // Flat UI regions: anywhere from no structure to identical LSBs throughout
+                baseline(
+                    CoverFormat::Png,
+                    ContentClass::Synthetic,
+                    (1.5, 1.0),
+                    (0.5, 0.35),
+                ),
+                // Undeveloped sensor data, noise down to the last bit
+                baseline(
+                    CoverFormat::Raw,
+                    ContentClass::Photo,
+                    (0.001, 0.002),
+                    (0.9998, 0.0005),
+                ),
+            ],
+        }
+    }
+
+    // The shipped baselines with those of a calibration file, taking the place of any for
+    // the same format and content class
+    pub fn load(path: &Path) -> Result<Self, CoverBaselineError> {
+        Ok(Self::calibrated(Calibration::read(path)?))
+    }
+
+    pub fn calibrated(calibration: Calibration) -> Self {
+        let mut baselines = Self::builtin();
+        for baseline in calibration.baselines {
+            baselines.insert(baseline);
+        }
+        baselines
+    }
+
+    // Replace the baseline for the same format and content class, or add it
+    pub fn insert(&mut self, baseline: CoverBaseline) {
+        match self
+            .baselines
+            .iter_mut()
+            .find(|b| b.format == baseline.format && b.content == baseline.content)
+        {
+            Some(existing) => *existing = baseline,
+            None => self.baselines.push(baseline),
+        }
+    }
+
+    pub fn baselines(&self) -> &[CoverBaseline] {
+        &self.baselines
+    }
+
+    pub fn get(&self, format: CoverFormat, content: ContentClass) -> Option<&CoverBaseline> {
+        self.baselines
+            .iter()
+            .find(|b| b.format == format && b.content == content)
+    }
+
+    // How each channel of an image compares with clean covers of its kind, None when there
+    // is no baseline for it
+    pub fn deviation(
+        &self,
+        format: CoverFormat,
+        content: ContentClass,
+        lsb: &LsbAnalysis,
+    ) -> Option<LsbDeviation> {
+        let baseline = self.get(format, content)?;
+        let channels = (0..lsb.chi_square_scores.len())
+            .map(|channel| {
+                let per_pair = chi_square_per_pair(lsb, channel);
+                ChannelDeviation {
+                    channel: lsb.channel_names[channel].clone(),
+                    chi_square_per_pair: per_pair,
+                    chi_square_z: baseline.chi_square.z_score(per_pair),
+                    entropy_z: baseline.entropy.z_score(lsb.entropy_scores[channel]),
+                }
+            })
+            .collect();
+        Some(LsbDeviation {
+            baseline: baseline.clone(),
+            channels,
+        })
+    }
+
+    // Baselines measured on the channels of known-clean covers, one per format and class seen
+    pub fn measure(samples: &[CoverSample]) -> Vec<CoverBaseline> {
+        let mut kinds: Vec<(CoverFormat, ContentClass)> = Vec::new();
+        for sample in samples {
+            if !kinds.contains(&(sample.format, sample.content)) {
+                kinds.push((sample.format, sample.content));
+            }
+        }
+
+        kinds
+            .into_iter()
+            .filter_map(|(format, content)| {
+                let kind: Vec<&CoverSample> = samples
+                    .iter()
+                    .filter(|s| s.format == format && s.content == content)
+                    .collect();
+                let chi_square: Vec<f64> = kind.iter().map(|s| s.chi_square_per_pair).collect();
+                let entropy: Vec<f64> = kind.iter().map(|s| s.entropy).collect();
+                Some(CoverBaseline {
+                    format,
+                    content,
+                    chi_square: Statistic::from_samples(&chi_square)?,
+                    entropy: Statistic::from_samples(&entropy)?,
+                    samples: kind.len(),
+                })
+            })
+            .collect()
+    }
+}
+
+// The chi-square score of a channel over the number of LSB pairs it was computed on
+pub fn chi_square_per_pair(lsb: &LsbAnalysis, channel: usize) -> f64 {
+    let pixels = lsb
+        .lsb_planes
+        .get(channel)
+        .map(|plane| plane.width() as f64 * plane.height() as f64)
+        .unwrap_or_default();
+    let pairs = (pixels / 2.0).floor();
+    if pairs < 1.0 {
+        return 0.0;
+    }
+    lsb.chi_square_scores[channel] / pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Analyzer;
+    use crate::lsb_analyzer::LsbAnalyzer;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    // Pseudo-random noise in every bit, like a photo's sensor noise
+    fn noisy_image(seed: u32) -> DynamicImage {
+        let mut state = seed.wrapping_mul(2654435761).max(1);
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |_, _| {
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state & 0xFF) as u8
+            };
+            Rgb([next(), next(), next()])
+        }))
+    }
+
+    #[test]
+    fn test_deviation_from_measured_baseline() {
+        let mut samples = Vec::new();
+        for seed in 1..=8 {
+            let lsb = LsbAnalyzer::analyze(noisy_image(seed)).unwrap();
+            samples.extend(CoverSample::from_analysis(
+                CoverFormat::Png,
+                ContentClass::Photo,
+                &lsb,
+            ));
+        }
+        let measured = CoverBaselines::measure(&samples);
+        assert_eq!(measured.len(), 1);
+        assert_eq!(measured[0].samples, 24);
+        assert!(measured[0].entropy.mean > 0.99);
+
+        // Round trip through a calibration file, replacing the shipped PNG photo entry
+        let mut calibration = Calibration {
+            baselines: measured.clone(),
+            audio: None,
+        };
+        calibration.update(Calibration {
+            baselines: Vec::new(),
+            audio: AudioBaseline::measure(&[0.01, 0.02, 0.03]),
+        });
+        let path =
+            std::env::temp_dir().join(format!("stegascan-calibration-{}.yaml", std::process::id()));
+        std::fs::write(&path, calibration.to_yaml().unwrap()).unwrap();
+        let baselines = CoverBaselines::load(&path).unwrap();
+        let read = Calibration::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, calibration);
+        let audio = read.audio.unwrap();
+        assert_eq!(audio.samples, 3);
+        assert!((audio.high_frequency_energy_threshold(BASELINE_SIGMA) - 0.05).abs() < 1e-9);
+        assert_eq!(
+            baselines.baselines().len(),
+            CoverBaselines::builtin().baselines().len()
+        );
+        assert_eq!(
+            baselines.get(CoverFormat::Png, ContentClass::Photo),
+            Some(&measured[0])
+        );
+
+        // Another noisy cover sits inside the baseline; a plane of constant LSBs is far out
+        let clean = LsbAnalyzer::analyze(noisy_image(99)).unwrap();
+        let deviation = baselines
+            .deviation(CoverFormat::Png, ContentClass::Photo, &clean)
+            .unwrap();
+        assert!(!deviation.is_suspicious_at(BASELINE_SIGMA));
+        let flat = LsbAnalyzer::analyze(DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            64,
+            64,
+            Rgb([10, 20, 30]),
+        )))
+        .unwrap();
+        let deviation = baselines
+            .deviation(CoverFormat::Png, ContentClass::Photo, &flat)
+            .unwrap();
+        assert!(deviation.is_suspicious_at(BASELINE_SIGMA));
+        assert!(deviation.channels[0].entropy_z < 0.0);
+
+        assert!(
+            baselines
+                .deviation(CoverFormat::Other, ContentClass::Photo, &flat)
+                .is_none()
+        );
+        assert_eq!(
+            CoverFormat::from_path(Path::new("IMG_0001.CR2")),
+            CoverFormat::Raw
+        );
+        assert_eq!(
+            CoverFormat::from_path(Path::new("a.jpeg")),
+            CoverFormat::Jpeg
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::payload_sniffer::gzip_body;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::inflate_flags::{
+    TINFL_FLAG_COMPUTE_ADLER32, TINFL_FLAG_HAS_MORE_INPUT, TINFL_FLAG_PARSE_ZLIB_HEADER,
+    TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+};
+use miniz_oxide::inflate::core::{DecompressorOxide, decompress};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Brute-force search for compressed streams anywhere in a file, as binwalk -z does. Every
+// offset that could start a zlib, gzip or raw deflate stream is inflated a bounded amount, and
+// the streams that decode are reported with what they decode to. A payload deflated without
+// a header carries no signature for the magic bytes scan to find.
+//
+// The compressed data a container declares for itself, such as PNG image data, ZIP entries and
+// PDF streams, is skipped: it decodes by design and would bury the streams that don't belong.
+pub struct DeflateHunter;
+
+// Bytes searched for stream starts; the rest of a larger file is left out
+pub const MAX_HUNT_BYTES: usize = 16 << 20;
+
+// Streams reported at most
+pub const MAX_STREAMS: usize = 64;
+
+// Bytes inflated from one stream at most
+pub const MAX_STREAM_BYTES: usize = 1 << 20;
+
+// Output a zlib or gzip stream has to produce. Their headers and checksums already rule out
+// chance, so this only drops empty streams.
+const MIN_FRAMED_BYTES: usize = 8;
+
+// Raw deflate has neither header nor checksum, and random bytes decode as a short fixed
+// Huffman block often enough over millions of offsets, a few literals repeated over and over
+// among them. A raw stream has to run to its final block, produce this much and decode to a
+// known file type or to text with at least MIN_TEXT_SYMBOLS different characters.
+const MIN_RAW_BYTES: usize = 64;
+const MIN_TEXT_SYMBOLS: usize = 16;
+
+// Literals a fixed-code raw block has to open with; nothing can be copied before there is
+// output, and most offsets in random data fail this long before the inflater would
+const FIXED_LEAD_LITERALS: usize = 12;
+
+// Characters of an inflated text stream shown in the report
+const PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug)]
+pub enum DeflateHuntError {
+    Empty,
+}
+
+impl Display for DeflateHuntError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeflateHuntError::Empty => write!(f, "Nothing to search in an empty file"),
+        }
+    }
+}
+
+impl std::error::Error for DeflateHuntError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    Zlib,
+    Gzip,
+    // Deflate without a header
+    Raw,
+}
+
+impl Display for StreamFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamFormat::Zlib => write!(f, "zlib"),
+            StreamFormat::Gzip => write!(f, "gzip"),
+            StreamFormat::Raw => write!(f, "deflate"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeflateStream {
+    pub offset: usize,
+    pub format: StreamFormat,
+    // Bytes of the file the stream takes up, header included
+    pub compressed_length: usize,
+    // Inflated bytes, at most MAX_STREAM_BYTES
+    pub data: Vec<u8>,
+    // Reached the end of the stream rather than the end of the file or MAX_STREAM_BYTES
+    pub complete: bool,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    // Start of the inflated text, escaped, for text streams
+    pub preview: Option<String>,
+}
+
+impl DeflateStream {
+    // File extension for writing the inflated data out
+    pub fn extension(&self) -> &'static str {
+        match infer::get(&self.data) {
+            Some(kind) => kind.extension(),
+            None if self.classification == "text" => "txt",
+            None => "bin",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeflateHuntAnalysis {
+    pub streams: Vec<DeflateStream>,
+    pub searched_bytes: usize,
+    // Compressed data of the container itself, which was not searched
+    pub container_bytes: usize,
+    pub suspicious: bool,
+}
+
+impl Analyzer for DeflateHunter {
+    type Input = Vec<u8>;
+    type Output = DeflateHuntAnalysis;
+    type Error = DeflateHuntError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if input.is_empty() {
+            return Err(DeflateHuntError::Empty);
+        }
+        let container = container_ranges(&input);
+        let searched_bytes = input.len().min(MAX_HUNT_BYTES);
+
+        let mut inflater = Inflater::new();
+        let mut streams = Vec::new();
+        let mut offset = 0;
+        while offset < searched_bytes && streams.len() < MAX_STREAMS {
+            if let Some(&(_, end)) = container
+                .iter()
+                .find(|&&(start, end)| (start..end).contains(&offset))
+            {
+                offset = end;
+                continue;
+            }
+            match stream_at(&input, offset, &mut inflater) {
+                Some(stream) => {
+                    offset += stream.compressed_length.max(1);
+                    streams.push(stream);
+                }
+                None => offset += 1,
+            }
+        }
+
+        let suspicious = !streams.is_empty();
+        Ok(DeflateHuntAnalysis {
+            streams,
+            searched_bytes,
+            container_bytes: container.iter().map(|(start, end)| end - start).sum(),
+            suspicious,
+        })
+    }
+}
+
+// The stream starting at `offset`, trying the framed formats before raw deflate
+fn stream_at(data: &[u8], offset: usize, inflater: &mut Inflater) -> Option<DeflateStream> {
+    let rest = &data[offset..];
+    let (format, body) = if is_zlib_header(rest) {
+        (StreamFormat::Zlib, rest)
+    } else if rest.starts_with(&[0x1f, 0x8b, 0x08]) && rest.get(3).is_some_and(|f| f & 0xe0 == 0) {
+        (StreamFormat::Gzip, gzip_body(rest)?)
+    } else if is_raw_deflate_start(rest) {
+        (StreamFormat::Raw, rest)
+    } else {
+        return None;
+    };
+
+    let (read, out, complete) = inflater.inflate(body, format == StreamFormat::Zlib)?;
+    let classification = classify_payload(out);
+    let plausible = match format {
+        StreamFormat::Zlib | StreamFormat::Gzip => out.len() >= MIN_FRAMED_BYTES,
+        StreamFormat::Raw => {
+            complete
+                && out.len() >= MIN_RAW_BYTES
+                && (classification.contains('/')
+                    || (classification == "text" && distinct_bytes(out) >= MIN_TEXT_SYMBOLS))
+        }
+    };
+    plausible.then(|| DeflateStream {
+        offset,
+        format,
+        compressed_length: rest.len() - body.len() + read,
+        data: out.to_vec(),
+        complete,
+        preview: (classification == "text").then(|| {
+            let text: String = String::from_utf8_lossy(out)
+                .chars()
+                .take(PREVIEW_CHARS)
+                .collect();
+            text.escape_debug().to_string()
+        }),
+        classification,
+    })
+}
+
+// One decompressor and output buffer for every offset tried; setting them up anew each time
+// would cost more than the attempts themselves, which mostly fail within a few bytes
+struct Inflater {
+    state: Box<DecompressorOxide>,
+    out: Vec<u8>,
+}
+
+impl Inflater {
+    fn new() -> Self {
+        Self {
+            state: Box::default(),
+            out: vec![0; MAX_STREAM_BYTES],
+        }
+    }
+
+    // Bytes read, the inflated bytes and whether the stream ended; None for data that isn't
+    // deflate. Running out of input or of room keeps what was decoded. The output buffer
+    // doesn't wrap, so a back-reference to before the start of the output fails the stream
+    // instead of reading the zeros a window would start with.
+    fn inflate(&mut self, body: &[u8], zlib: bool) -> Option<(usize, &[u8], bool)> {
+        self.state.init();
+        let mut flags = TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF | TINFL_FLAG_HAS_MORE_INPUT;
+        if zlib {
+            flags |= TINFL_FLAG_PARSE_ZLIB_HEADER | TINFL_FLAG_COMPUTE_ADLER32;
+        }
+        let (status, read, written) = decompress(&mut self.state, body, &mut self.out, 0, flags);
+        let complete = match status {
+            TINFLStatus::Done => true,
+            TINFLStatus::NeedsMoreInput | TINFLStatus::HasMoreOutput => false,
+            _ => return None,
+        };
+        Some((read, &self.out[..written], complete))
+    }
+}
+
+fn distinct_bytes(bytes: &[u8]) -> usize {
+    let mut seen = [false; 256];
+    for &b in bytes {
+        seen[b as usize] = true;
+    }
+    seen.iter().filter(|&&seen| seen).count()
+}
+
+// Deflate with a 32K window or less, no preset dictionary and a valid header checksum
+fn is_zlib_header(bytes: &[u8]) -> bool {
+    let [cmf, flg, ..] = *bytes else {
+        return false;
+    };
+    cmf & 0x0f == 8
+        && cmf >> 4 <= 7
+        && flg & 0x20 == 0
+        && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+// Whether the first block header is one an encoder would write: stored blocks need their
+// length and its complement, dynamic blocks in-range code counts
+fn is_raw_deflate_start(bytes: &[u8]) -> bool {
+    let Some(&first) = bytes.first() else {
+        return false;
+    };
+    match (first >> 1) & 0b11 {
+        0 => {
+            let Some(lengths) = bytes.get(1..5) else {
+                return false;
+            };
+            let len = u16::from_le_bytes([lengths[0], lengths[1]]);
+            let nlen = u16::from_le_bytes([lengths[2], lengths[3]]);
+            first >> 3 == 0 && len != 0 && len == !nlen
+        }
+        1 => opens_with_literals(bytes, FIXED_LEAD_LITERALS),
+        2 => first >> 3 <= 29 && bytes.get(1).is_some_and(|second| second & 0x1f <= 29),
+        _ => false,
+    }
+}
+
+// Whether a fixed-code block starts with `count` literals, decoding the codes by hand
+fn opens_with_literals(bytes: &[u8], count: usize) -> bool {
+    let mut bit = 3;
+    let mut next_bit = || {
+        let value = bytes.get(bit / 8).map(|b| u16::from((b >> (bit % 8)) & 1));
+        bit += 1;
+        value
+    };
+    for _ in 0..count {
+        let mut code = 0;
+        for _ in 0..7 {
+            let Some(b) = next_bit() else {
+                return false;
+            };
+            code = code << 1 | b;
+        }
+        // 7-bit codes are end of block and lengths 257-279
+        if code < 0x18 {
+            return false;
+        }
+        let Some(b) = next_bit() else {
+            return false;
+        };
+        code = code << 1 | b;
+        // 8-bit codes are literals 0-143 then lengths 280-287; the rest are 9-bit literals
+        match code {
+            0x30..=0xbf => {}
+            0xc0..=0xc7 => return false,
+            _ => {
+                if next_bit().is_none() {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+// Byte ranges holding the container's own compressed data
+fn container_ranges(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_ranges(data, &mut ranges);
+    } else if data.starts_with(b"%PDF") {
+        pdf_ranges(data, &mut ranges);
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        ranges.push((0, data.len()));
+    }
+    // ZIP entries wherever they are, so an archive appended to an image counts too; the
+    // magic bytes scan already reports those
+    zip_ranges(data, &mut ranges);
+    ranges
+}
+
+fn png_ranges(data: &[u8], ranges: &mut Vec<(usize, usize)>) {
+    let mut offset = 8;
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let chunk_type = &data[offset + 4..offset + 8];
+        let start = offset + 8;
+        let end = start.saturating_add(length).min(data.len());
+        if matches!(chunk_type, b"IDAT" | b"fdAT" | b"zTXt" | b"iTXt" | b"iCCP") {
+            ranges.push((start, end));
+        }
+        if chunk_type == b"IEND" {
+            break;
+        }
+        offset = end + 4;
+    }
+}
+
+fn pdf_ranges(data: &[u8], ranges: &mut Vec<(usize, usize)>) {
+    let mut offset = 0;
+    while let Some(found) = find(&data[offset..], b"stream") {
+        let mut start = offset + found + b"stream".len();
+        // "endstream" contains "stream" too
+        if data[..offset + found].ends_with(b"end") {
+            offset = start;
+            continue;
+        }
+        if data[start..].starts_with(b"\r\n") {
+            start += 2;
+        } else if data[start..].starts_with(b"\n") {
+            start += 1;
+        }
+        let end = find(&data[start..], b"endstream").map_or(data.len(), |found| start + found);
+        ranges.push((start, end));
+        offset = end;
+    }
+}
+
+fn zip_ranges(data: &[u8], ranges: &mut Vec<(usize, usize)>) {
+    let mut offset = 0;
+    while let Some(found) = find(&data[offset..], b"PK\x03\x04") {
+        let header = offset + found;
+        let Some(fields) = data.get(header..header + 30) else {
+            break;
+        };
+        let flags = u16::from_le_bytes([fields[6], fields[7]]);
+        let compressed = u32::from_le_bytes(fields[18..22].try_into().unwrap()) as usize;
+        let name_length = u16::from_le_bytes([fields[26], fields[27]]) as usize;
+        let extra_length = u16::from_le_bytes([fields[28], fields[29]]) as usize;
+        let start = (header + 30 + name_length + extra_length).min(data.len());
+        // With a data descriptor the size follows the data, so the entry runs to the next header
+        let end = if flags & 0x08 != 0 || compressed == 0 {
+            find(&data[start..], b"PK").map_or(data.len(), |found| start + found)
+        } else {
+            start.saturating_add(compressed).min(data.len())
+        };
+        ranges.push((start, end));
+        offset = end.max(header + 4);
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::{DeflateEncoder, ZlibEncoder};
+    use std::io::Write;
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_streams_between_noise() {
+        let secret = b"meet at the old pier at midnight, bring the second key. ".repeat(8);
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::best());
+        raw.write_all(&secret).unwrap();
+        let raw = raw.finish().unwrap();
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(b"GIF89a and some more bytes of an image")
+            .unwrap();
+        let zlib = zlib.finish().unwrap();
+
+        let mut data = noise(5000);
+        let raw_offset = data.len();
+        data.extend_from_slice(&raw);
+        data.extend(noise(3000));
+        let zlib_offset = data.len();
+        data.extend_from_slice(&zlib);
+        data.extend(noise(2000));
+
+        let analysis = DeflateHunter::analyze(data).unwrap();
+        assert!(analysis.suspicious);
+        assert_eq!(analysis.streams.len(), 2, "{:?}", analysis.streams);
+
+        let found_raw = &analysis.streams[0];
+        assert_eq!(
+            (found_raw.offset, found_raw.format),
+            (raw_offset, StreamFormat::Raw)
+        );
+        assert_eq!(found_raw.data, secret);
+        assert_eq!(found_raw.classification, "text");
+        assert_eq!(found_raw.compressed_length, raw.len());
+
+        let found_zlib = &analysis.streams[1];
+        assert_eq!(
+            (found_zlib.offset, found_zlib.format),
+            (zlib_offset, StreamFormat::Zlib)
+        );
+        assert!(found_zlib.complete);
+        assert_eq!(found_zlib.classification, "image/gif");
+    }
+
+    #[test]
+    fn test_skips_the_containers_own_streams() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(&[0u8; 4096]).unwrap();
+        let idat = zlib.finish().unwrap();
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (chunk_type, body) in [(&b"IDAT"[..], &idat[..]), (&b"IEND"[..], &[][..])] {
+            png.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            png.extend_from_slice(chunk_type);
+            png.extend_from_slice(body);
+            png.extend_from_slice(&[0; 4]);
+        }
+
+        let analysis = DeflateHunter::analyze(png).unwrap();
+        assert!(analysis.streams.is_empty());
+        assert!(!analysis.suspicious);
+        assert_eq!(analysis.container_bytes, idat.len());
+    }
+}
@@ -0,0 +1,572 @@
+use crate::Analyzer;
+use crate::shared::fft_forward;
+use rustfft::num_complex::Complex;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Tries to recover the data carried by tones the spectral analyzers flag. Each carrier is
+// tried as two-tone FSK, by tracking the spectral peak in the band around it, and as binary
+// PSK, by following the phase of the carrier once it is mixed down to 0 Hz. A "persistent
+// tone" finding then comes with the bits it carries, if it carries any.
+pub struct Demodulator;
+
+// Demodulated bits packed into bytes are capped at this many
+pub const MAX_DECODED_BYTES: usize = 64;
+
+// Carriers closer than this are the same tone seen in neighbouring bins
+const CARRIER_MERGE_HZ: f32 = 100.0;
+
+// Frame length of the averaged spectrum that places each carrier, about 10 Hz per bin at
+// 44.1 kHz
+const PEAK_FRAME_SIZE: usize = 4096;
+
+// FSK partner tones are searched for this far either side of a carrier
+const FSK_SEARCH_HZ: f32 = 2500.0;
+
+const FRAME_SIZE: usize = 1024;
+const HOP_SIZE: usize = FRAME_SIZE / 2;
+
+// A frame is tonal when its strongest band bin carries this many times the mean bin power
+// of the band
+const TONAL_PEAK_RATIO: f32 = 8.0;
+
+// Share of the tonal frames a frequency needs to count as a tone
+const MIN_TONE_SHARE: f64 = 0.05;
+
+// Share of the tonal frames the two strongest tones need between them to be keyed data
+const FSK_TONE_COVERAGE: f64 = 0.8;
+const MIN_FSK_FRAMES: usize = 16;
+
+// Symbol changes needed before a keyed signal is believed
+const MIN_TRANSITIONS: usize = 4;
+
+// PSK phasors are taken over triangular windows of twice this length, at this hop, and
+// span at least PSK_MIN_CYCLES carrier periods so the image at twice the carrier averages out
+const PSK_HOP_SECONDS: f32 = 0.0005;
+const PSK_MIN_CYCLES: f32 = 8.0;
+
+// Mean |cos| of the phasor angles around the decision axis. BPSK sits near 1; a tone whose
+// phase wanders freely averages 2/pi.
+const PSK_MIN_CONSTELLATION: f32 = 0.85;
+
+#[derive(Debug)]
+pub enum DemodulatorError {
+    NoCarriers,
+    TooShort(usize),
+}
+
+impl Display for DemodulatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DemodulatorError::NoCarriers => write!(f, "No carrier frequencies to demodulate"),
+            DemodulatorError::TooShort(len) => {
+                write!(f, "{} samples is shorter than one analysis frame", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DemodulatorError {}
+
+#[derive(Debug, Clone)]
+pub struct FskSignal {
+    // The higher tone is read as 1
+    pub mark_hz: f32,
+    pub space_hz: f32,
+    pub baud: f32,
+    pub bits: Vec<bool>,
+    // Bits packed most significant first, at most MAX_DECODED_BYTES
+    pub decoded: Vec<u8>,
+}
+
+impl FskSignal {
+    // The decoded bytes as text, when they read as ASCII
+    pub fn decoded_text(&self) -> Option<String> {
+        decoded_text(&self.decoded)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PskSignal {
+    pub carrier_hz: f32,
+    pub baud: f32,
+    // Relative to the phase the carrier spends most of its time in; BPSK can't tell which
+    // phase is 0, so the bits may come out inverted
+    pub bits: Vec<bool>,
+    pub decoded: Vec<u8>,
+    // Mean |cos| of the phasors around the decision axis, 1 for a clean constellation
+    pub constellation: f32,
+}
+
+impl PskSignal {
+    pub fn decoded_text(&self) -> Option<String> {
+        decoded_text(&self.decoded)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CarrierDemodulation {
+    pub carrier_hz: f32,
+    pub fsk: Option<FskSignal>,
+    pub psk: Option<PskSignal>,
+}
+
+impl CarrierDemodulation {
+    pub fn recovered(&self) -> bool {
+        self.fsk.is_some() || self.psk.is_some()
+    }
+}
+
+impl Analyzer for Demodulator {
+    // Mono samples, their sample rate and the carrier frequencies to try, in Hz
+    type Input = (Vec<f32>, u32, Vec<f32>);
+    // One entry per distinct carrier
+    type Output = Vec<CarrierDemodulation>;
+    type Error = DemodulatorError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (samples, sample_rate, mut carriers) = input;
+        let nyquist = sample_rate as f32 / 2.0;
+        carriers.retain(|&c| c > 0.0 && c < nyquist);
+        if carriers.is_empty() {
+            return Err(DemodulatorError::NoCarriers);
+        }
+        if samples.len() < FRAME_SIZE {
+            return Err(DemodulatorError::TooShort(samples.len()));
+        }
+
+        // A keyed tone spreads over neighbouring bins, so runs of nearby carriers are
+        // replaced by the strongest frequency in their span
+        carriers.sort_by(|a, b| a.total_cmp(b));
+        let mut spans: Vec<(f32, f32)> = Vec::new();
+        for carrier in carriers {
+            match spans.last_mut() {
+                Some(span) if carrier - span.1 < CARRIER_MERGE_HZ => span.1 = carrier,
+                _ => spans.push((carrier, carrier)),
+            }
+        }
+        let power = average_power(&samples);
+        let bin_hz = sample_rate as f32 / PEAK_FRAME_SIZE as f32;
+        let mut peaks: Vec<f32> = Vec::new();
+        for (low, high) in spans {
+            let first = ((low - CARRIER_MERGE_HZ / 2.0) / bin_hz).floor().max(1.0) as usize;
+            let last =
+                (((high + CARRIER_MERGE_HZ / 2.0) / bin_hz).ceil() as usize).min(power.len() - 1);
+            let peak = (first..=last)
+                .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+                .map_or((low + high) / 2.0, |bin| bin as f32 * bin_hz);
+            if peaks
+                .last()
+                .is_none_or(|&last| peak - last >= CARRIER_MERGE_HZ)
+            {
+                peaks.push(peak);
+            }
+        }
+
+        let mut results: Vec<CarrierDemodulation> = Vec::new();
+        for carrier_hz in peaks {
+            // Both tones of an FSK pair are usually flagged; the pair is reported once
+            let fsk = fsk_around(&samples, sample_rate, carrier_hz).filter(|fsk| {
+                !results
+                    .iter()
+                    .flat_map(|r| &r.fsk)
+                    .any(|seen| seen.mark_hz == fsk.mark_hz && seen.space_hz == fsk.space_hz)
+            });
+            results.push(CarrierDemodulation {
+                carrier_hz,
+                fsk,
+                psk: demodulate_psk(&samples, sample_rate, carrier_hz),
+            });
+        }
+        Ok(results)
+    }
+}
+
+// Power spectrum averaged over every PEAK_FRAME_SIZE block of the samples
+fn average_power(samples: &[f32]) -> Vec<f32> {
+    let fft = fft_forward(PEAK_FRAME_SIZE);
+    let mut power = vec![0.0f32; PEAK_FRAME_SIZE / 2];
+    for block in samples.chunks(PEAK_FRAME_SIZE) {
+        let mut buffer: Vec<Complex<f32>> = (0..PEAK_FRAME_SIZE)
+            .map(|i| {
+                let window = 0.5
+                    * (1.0
+                        - (2.0 * std::f32::consts::PI * i as f32 / PEAK_FRAME_SIZE as f32).cos());
+                Complex::new(block.get(i).copied().unwrap_or(0.0) * window, 0.0)
+            })
+            .collect();
+        fft.process(&mut buffer);
+        for (total, value) in power.iter_mut().zip(&buffer) {
+            *total += value.norm_sqr();
+        }
+    }
+    power
+}
+
+// Bin of the strongest peak in power[low_bin..high_bin], when it stands out from the band
+// and isn't leakage or rounding noise more than 80 dB under the frame
+pub(crate) fn frame_peak(
+    power: &[f32],
+    low_bin: usize,
+    high_bin: usize,
+    frame_total: f32,
+) -> Option<usize> {
+    let band = &power[low_bin..high_bin];
+    let band_power: f32 = band.iter().sum();
+    let (peak_bin, &peak_power) = band.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+    (peak_power > 1e-8 * frame_total
+        && peak_power >= TONAL_PEAK_RATIO * band_power / band.len() as f32)
+        .then_some(low_bin + peak_bin)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ToneCluster {
+    first_bin: usize,
+    last_bin: usize,
+    // Frame-weighted mean bin
+    pub(crate) center: f32,
+    pub(crate) frames: usize,
+}
+
+impl ToneCluster {
+    pub(crate) fn contains(&self, bin: usize) -> bool {
+        (self.first_bin..=self.last_bin).contains(&bin)
+    }
+}
+
+// Group the peak bins of tonal frames into runs of adjacent bins, strongest first, keeping
+// those that hold at least MIN_TONE_SHARE of the tonal frames
+pub(crate) fn tone_clusters(peaks: &[Option<usize>]) -> Vec<ToneCluster> {
+    let mut bins: Vec<usize> = peaks.iter().flatten().copied().collect();
+    let tonal_frames = bins.len();
+    bins.sort_unstable();
+
+    let mut clusters: Vec<ToneCluster> = Vec::new();
+    let mut weighted = 0usize;
+    for bin in bins {
+        match clusters.last_mut() {
+            Some(cluster) if bin <= cluster.last_bin + 1 => {
+                cluster.last_bin = bin;
+                cluster.frames += 1;
+            }
+            _ => {
+                if let Some(cluster) = clusters.last_mut() {
+                    cluster.center = weighted as f32 / cluster.frames as f32;
+                }
+                weighted = 0;
+                clusters.push(ToneCluster {
+                    first_bin: bin,
+                    last_bin: bin,
+                    center: bin as f32,
+                    frames: 1,
+                });
+            }
+        }
+        weighted += bin;
+    }
+    if let Some(cluster) = clusters.last_mut() {
+        cluster.center = weighted as f32 / cluster.frames as f32;
+    }
+
+    clusters.retain(|c| c.frames as f64 >= MIN_TONE_SHARE * tonal_frames as f64);
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.frames));
+    clusters
+}
+
+// Read the frames whose peak is one of the two strongest tones as a keyed signal
+pub(crate) fn demodulate_fsk(
+    peaks: &[Option<usize>],
+    clusters: &[ToneCluster],
+    bin_hz: f32,
+    frame_rate: f32,
+) -> Option<FskSignal> {
+    let [first, second, ..] = clusters else {
+        return None;
+    };
+    let tonal_frames = peaks.iter().flatten().count();
+    if tonal_frames < MIN_FSK_FRAMES
+        || ((first.frames + second.frames) as f64) < FSK_TONE_COVERAGE * tonal_frames as f64
+    {
+        return None;
+    }
+    let (high, low) = if second.center > first.center {
+        (second, first)
+    } else {
+        (first, second)
+    };
+
+    // Frames on neither tone are dropped
+    let mut runs: Vec<(bool, usize)> = Vec::new();
+    for &bin in peaks.iter().flatten() {
+        let mark = if high.contains(bin) {
+            true
+        } else if low.contains(bin) {
+            false
+        } else {
+            continue;
+        };
+        push_run(&mut runs, mark);
+    }
+
+    let (symbol_frames, bits) = bits_from_runs(&runs)?;
+    Some(FskSignal {
+        mark_hz: high.center * bin_hz,
+        space_hz: low.center * bin_hz,
+        baud: frame_rate / symbol_frames,
+        decoded: pack_bits(&bits),
+        bits,
+    })
+}
+
+// FSK whose mark or space is the carrier, with the other tone within FSK_SEARCH_HZ
+fn fsk_around(samples: &[f32], sample_rate: u32, carrier_hz: f32) -> Option<FskSignal> {
+    let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+    let low_bin = ((carrier_hz - FSK_SEARCH_HZ) / bin_hz).floor().max(1.0) as usize;
+    let high_bin = (((carrier_hz + FSK_SEARCH_HZ) / bin_hz).ceil() as usize).min(FRAME_SIZE / 2);
+    if high_bin <= low_bin + 2 {
+        return None;
+    }
+
+    let fft = fft_forward(FRAME_SIZE);
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos()))
+        .collect();
+    let peaks: Vec<Option<usize>> = samples
+        .windows(FRAME_SIZE)
+        .step_by(HOP_SIZE)
+        .map(|frame| {
+            let mut buffer: Vec<Complex<f32>> = frame
+                .iter()
+                .zip(&window)
+                .map(|(&s, &w)| Complex::new(s * w, 0.0))
+                .collect();
+            fft.process(&mut buffer);
+            let power: Vec<f32> = buffer[..FRAME_SIZE / 2]
+                .iter()
+                .map(|c| c.norm_sqr())
+                .collect();
+            frame_peak(&power, low_bin, high_bin, power.iter().sum())
+        })
+        .collect();
+
+    let clusters = tone_clusters(&peaks);
+    let carrier_bin = (carrier_hz / bin_hz).round() as usize;
+    let signal = demodulate_fsk(
+        &peaks,
+        &clusters,
+        bin_hz,
+        sample_rate as f32 / HOP_SIZE as f32,
+    )?;
+    // The keyed pair has to include the carrier that was asked about
+    [signal.mark_hz, signal.space_hz]
+        .iter()
+        .any(|&tone| (tone / bin_hz - carrier_bin as f32).abs() <= 2.0)
+        .then_some(signal)
+}
+
+// Mix the carrier down to 0 Hz and read the sign of its phasor along the axis the phase
+// keeps returning to
+fn demodulate_psk(samples: &[f32], sample_rate: u32, carrier_hz: f32) -> Option<PskSignal> {
+    let rate = sample_rate as f32;
+    let hop =
+        ((rate * PSK_HOP_SECONDS).max(PSK_MIN_CYCLES * rate / carrier_hz / 2.0)).round() as usize;
+    let hop = hop.max(1);
+    let step = -2.0 * std::f64::consts::PI * carrier_hz as f64 / sample_rate as f64;
+
+    // Triangular windows of 2 * hop are two cascaded moving averages, whose sidelobes fall
+    // off fast enough to keep loud audio at other frequencies out of the phasor
+    let mut phasors = Vec::new();
+    let mut start = 0;
+    while start + 2 * hop <= samples.len() {
+        let mut sum = Complex::new(0.0f64, 0.0);
+        for (i, &s) in samples[start..start + 2 * hop].iter().enumerate() {
+            let weight = if i < hop { i + 1 } else { 2 * hop - i } as f64;
+            let angle = (step * (start + i) as f64).rem_euclid(2.0 * std::f64::consts::PI);
+            sum += Complex::from_polar(s as f64 * weight, angle);
+        }
+        phasors.push(sum / (hop * hop) as f64);
+        start += hop;
+    }
+
+    // Only phasors where the carrier is present take part
+    let loudest = phasors.iter().map(|p| p.norm()).fold(0.0, f64::max);
+    if loudest <= 0.0 {
+        return None;
+    }
+    let present: Vec<(usize, Complex<f64>)> = phasors
+        .into_iter()
+        .enumerate()
+        .filter(|(_, p)| p.norm() >= 0.3 * loudest)
+        .collect();
+
+    // Squaring strips the BPSK modulation, leaving twice the residual rotation from an
+    // imprecise carrier frequency
+    let squared_turn: Complex<f64> = present
+        .windows(2)
+        .map(|w| (w[1].1 * w[1].1) * (w[0].1 * w[0].1).conj() / (w[1].0 - w[0].0) as f64)
+        .sum();
+    let rotation = squared_turn.arg() / 2.0;
+    let derotated: Vec<Complex<f64>> = present
+        .iter()
+        .map(|&(k, p)| p * Complex::from_polar(1.0, -rotation * k as f64))
+        .collect();
+    let axis = derotated.iter().map(|p| p * p).sum::<Complex<f64>>().arg() / 2.0;
+    let along: Vec<f64> = derotated
+        .iter()
+        .map(|p| (p * Complex::from_polar(1.0, -axis)).re)
+        .collect();
+
+    let constellation = (along
+        .iter()
+        .zip(&derotated)
+        .map(|(a, p)| a.abs() / p.norm())
+        .sum::<f64>()
+        / along.len() as f64) as f32;
+    if constellation < PSK_MIN_CONSTELLATION {
+        return None;
+    }
+
+    // The phase held longest reads as 0
+    let negative = along.iter().filter(|&&a| a < 0.0).count();
+    let inverted = negative * 2 > along.len();
+    let mut runs = Vec::new();
+    for a in along {
+        push_run(&mut runs, (a < 0.0) != inverted);
+    }
+    let (symbol_hops, bits) = bits_from_runs(&runs)?;
+    Some(PskSignal {
+        carrier_hz,
+        baud: rate / hop as f32 / symbol_hops,
+        decoded: pack_bits(&bits),
+        bits,
+        constellation,
+    })
+}
+
+fn push_run(runs: &mut Vec<(bool, usize)>, bit: bool) {
+    match runs.last_mut() {
+        Some((last, len)) if *last == bit => *len += 1,
+        _ => runs.push((bit, 1)),
+    }
+}
+
+// Turn runs of identical decisions into bits. A symbol is the shortest run that recurs,
+// since runs at either end may be cut short; averaging the runs up to half a symbol longer
+// smooths out decisions that straddle a symbol boundary. Returns the decisions per symbol.
+fn bits_from_runs(runs: &[(bool, usize)]) -> Option<(f32, Vec<bool>)> {
+    if runs.len() <= MIN_TRANSITIONS {
+        return None;
+    }
+    let mut interior: Vec<usize> = runs[1..runs.len() - 1].iter().map(|r| r.1).collect();
+    interior.sort_unstable();
+    let shortest = interior
+        .windows(2)
+        .find(|w| w[0] == w[1])
+        .map_or(interior[0], |w| w[0]);
+    let near: Vec<usize> = interior
+        .iter()
+        .copied()
+        .filter(|&len| len as f32 <= shortest as f32 * 1.5)
+        .collect();
+    let symbol = near.iter().sum::<usize>() as f32 / near.len() as f32;
+
+    let bits = runs
+        .iter()
+        .flat_map(|&(bit, len)| {
+            let count = ((len as f32 / symbol).round() as usize).max(1);
+            std::iter::repeat_n(bit, count)
+        })
+        .collect();
+    Some((symbol, bits))
+}
+
+pub(crate) fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks_exact(8)
+        .take(MAX_DECODED_BYTES)
+        .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8))
+        .collect()
+}
+
+pub(crate) fn decoded_text(decoded: &[u8]) -> Option<String> {
+    let printable = decoded
+        .iter()
+        .filter(|&&b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        .count();
+    (!decoded.is_empty() && printable * 10 >= decoded.len() * 9)
+        .then(|| String::from_utf8_lossy(decoded).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 44100;
+
+    fn message_bits(message: &[u8]) -> Vec<bool> {
+        message
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .collect()
+    }
+
+    fn keyed(bits: &[bool], baud: usize, sample: impl Fn(bool, f32) -> f32) -> Vec<f32> {
+        let samples_per_bit = RATE as usize / baud;
+        (0..bits.len() * samples_per_bit)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                // Music-like audible content under the keyed carrier
+                0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                    + sample(bits[i / samples_per_bit], t)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recovers_psk_on_persistent_tone() {
+        // Leading zero bit so the phase held longest is the one read as 0
+        let bits = message_bits(b"\x00PSK!");
+        let samples = keyed(&bits, 100, |bit, t| {
+            let phase = if bit { std::f32::consts::PI } else { 0.0 };
+            0.05 * (2.0 * std::f32::consts::PI * 17000.0 * t + phase).sin()
+        });
+
+        // Two neighbouring bins of the same tone, 10 Hz off the true carrier
+        let result = Demodulator::analyze((samples, RATE, vec![17001.0, 17019.0])).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].fsk.is_none());
+        let psk = result[0].psk.as_ref().unwrap();
+        assert!((psk.baud - 100.0).abs() < 5.0);
+        assert_eq!(psk.decoded, b"\x00PSK!");
+    }
+
+    #[test]
+    fn test_recovers_fsk_around_carrier() {
+        let bits = message_bits(b"FSK");
+        // 20 baud leaves four analysis frames per symbol at 44.1 kHz
+        let samples = keyed(&bits, 20, |bit, t| {
+            let tone = if bit { 18000.0 } else { 17000.0 };
+            0.05 * (2.0 * std::f32::consts::PI * tone * t).sin()
+        });
+
+        let result = Demodulator::analyze((samples, RATE, vec![17000.0])).unwrap();
+        let fsk = result[0].fsk.as_ref().unwrap();
+        assert!((fsk.mark_hz - 18000.0).abs() < 50.0);
+        assert_eq!(fsk.decoded_text().as_deref(), Some("FSK"));
+        assert!(result[0].psk.is_none());
+    }
+
+    #[test]
+    fn test_plain_tone_carries_nothing() {
+        let samples = keyed(&[false; 20], 50, |_, t| {
+            0.05 * (2.0 * std::f32::consts::PI * 16000.0 * t).sin()
+        });
+        let result = Demodulator::analyze((samples, RATE, vec![16000.0])).unwrap();
+        assert!(!result[0].recovered());
+        assert!(matches!(
+            Demodulator::analyze((vec![0.0; 4096], RATE, vec![30000.0])),
+            Err(DemodulatorError::NoCarriers)
+        ));
+    }
+}
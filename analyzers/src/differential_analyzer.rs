@@ -0,0 +1,484 @@
+use crate::Analyzer;
+use crate::demodulator::{decoded_text, pack_bits};
+use crate::payload_sniffer::{self, PayloadInterpretation};
+use crate::shared::{fft_forward, fft_inverse};
+use rustfft::num_complex::Complex;
+use std::fmt::Display;
+
+// Compares a suspect recording with the clean reference it was made from. After lining the
+// two up, whatever is left in the difference was added to the suspect: the residual's
+// spectrum tells white LSB noise from injected tones or echoes, and the samples it touched
+// give the regions and an estimate of how many bits were hidden there.
+pub struct DifferentialAnalyzer;
+
+// Largest offset between the recordings that alignment searches, in seconds
+pub const MAX_OFFSET_SECONDS: f64 = 1.0;
+
+// Normalized cross-correlation below which the reference isn't a version of the suspect
+pub const MIN_ALIGNMENT_CORRELATION: f64 = 0.9;
+
+// Samples from the start of each recording used to find the offset
+const ALIGNMENT_WINDOW: usize = 1 << 17;
+
+// A least-squares gain this close to 1 is taken as exactly 1, so the residual of a lossless
+// copy stays whole LSB steps
+const UNITY_GAIN_TOLERANCE: f64 = 0.005;
+
+// Residual magnitude that counts as a change when the audio has no integer sample grid,
+// about -80 dBFS
+const FLOAT_CHANGE_THRESHOLD: f32 = 1e-4;
+
+// LSBs read from the first region for the payload candidate, 16 KiB when read plain and less
+// through the codings that spend several LSBs per payload bit
+const MAX_LSB_CANDIDATE_BITS: usize = 1 << 17;
+
+// Regions are built from windows of this length
+const REGION_WINDOW_SECONDS: f64 = 0.1;
+
+// Share of a window's samples that must have changed for the window to join a region
+const MIN_WINDOW_CHANGED_SHARE: f64 = 0.005;
+
+const SPECTRUM_FFT_SIZE: usize = 2048;
+// Residual frames averaged into the spectrum at most
+const MAX_SPECTRUM_FRAMES: usize = 512;
+
+#[derive(Debug)]
+pub enum DifferentialAnalyzerError {
+    SampleRateMismatch(u32, u32),
+    TooShort(usize),
+}
+
+impl Display for DifferentialAnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DifferentialAnalyzerError::SampleRateMismatch(suspect, reference) => write!(
+                f,
+                "Sample rates differ: {} Hz against a {} Hz reference",
+                suspect, reference
+            ),
+            DifferentialAnalyzerError::TooShort(len) => write!(
+                f,
+                "{} overlapping samples is too short to compare (at least {} needed)",
+                len, SPECTRUM_FFT_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DifferentialAnalyzerError {}
+
+#[derive(Debug, Clone)]
+pub struct DifferentialAnalysis {
+    // Samples the suspect lags the reference by; negative when it starts earlier
+    pub offset_samples: i64,
+    pub alignment_correlation: f64,
+    // Reference gain that best matches the suspect
+    pub gain: f64,
+    pub compared_samples: usize,
+    // Bit depth of the reference's sample grid, None for float or lossy audio
+    pub bit_depth: Option<u32>,
+    // Residual energy relative to the reference, in dB
+    pub residual_db: f64,
+    pub changed_samples: usize,
+    // Share of changed samples that moved by exactly one LSB step
+    pub lsb_only_share: f64,
+    // Strongest frequency in the residual's averaged spectrum
+    pub residual_peak_hz: f64,
+    // Geometric over arithmetic mean of the residual's power spectrum: near 1 for white
+    // noise such as LSB embedding, near 0 for tones
+    pub residual_flatness: f64,
+    pub regions: Vec<ResidualRegion>,
+    // Payload size estimate, assuming payload bits replace sample bits directly
+    pub estimated_bits: Option<u64>,
+    pub estimated_bitrate_bps: Option<f64>,
+    // The suspect's LSBs from the start of the first region, packed most significant first
+    pub lsb_decoded: Vec<u8>,
+    // Best reading of a longer run of those LSBs: a file, compressed data, text or binary
+    pub lsb_payload: Option<PayloadInterpretation>,
+    pub suspicious: bool,
+}
+
+impl DifferentialAnalysis {
+    pub fn aligned(&self) -> bool {
+        self.alignment_correlation >= MIN_ALIGNMENT_CORRELATION
+    }
+
+    pub fn lsb_decoded_text(&self) -> Option<String> {
+        decoded_text(&self.lsb_decoded)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResidualRegion {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub changed_samples: usize,
+    pub estimated_bits: Option<u64>,
+}
+
+impl Analyzer for DifferentialAnalyzer {
+    // Suspect samples, reference samples and the sample rate of each
+    type Input = (Vec<f32>, u32, Vec<f32>, u32);
+    type Output = DifferentialAnalysis;
+    type Error = DifferentialAnalyzerError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (suspect, sample_rate, reference, reference_rate) = input;
+        if sample_rate != reference_rate {
+            return Err(DifferentialAnalyzerError::SampleRateMismatch(
+                sample_rate,
+                reference_rate,
+            ));
+        }
+
+        let max_offset = (MAX_OFFSET_SECONDS * sample_rate as f64) as usize;
+        let (offset, alignment_correlation) = find_offset(&suspect, &reference, max_offset);
+
+        // Overlapping stretch, as slices that line up sample for sample
+        let (suspect, reference) = if offset >= 0 {
+            (
+                &suspect[(offset as usize).min(suspect.len())..],
+                &reference[..],
+            )
+        } else {
+            (
+                &suspect[..],
+                &reference[(-offset as usize).min(reference.len())..],
+            )
+        };
+        let len = suspect.len().min(reference.len());
+        let (suspect, reference) = (&suspect[..len], &reference[..len]);
+        if len < SPECTRUM_FFT_SIZE {
+            return Err(DifferentialAnalyzerError::TooShort(len));
+        }
+
+        let cross: f64 = suspect
+            .iter()
+            .zip(reference)
+            .map(|(&s, &r)| s as f64 * r as f64)
+            .sum();
+        let reference_energy: f64 = reference.iter().map(|&r| r as f64 * r as f64).sum();
+        let mut gain = if reference_energy > 0.0 {
+            cross / reference_energy
+        } else {
+            1.0
+        };
+        if (gain - 1.0).abs() < UNITY_GAIN_TOLERANCE {
+            gain = 1.0;
+        }
+
+        let residual: Vec<f32> = suspect
+            .iter()
+            .zip(reference)
+            .map(|(&s, &r)| (s as f64 - gain * r as f64) as f32)
+            .collect();
+        let residual_energy: f64 = residual.iter().map(|&x| x as f64 * x as f64).sum();
+        let residual_db = 10.0 * (residual_energy.max(1e-20) / reference_energy.max(1e-20)).log10();
+
+        // Residual in whole LSB steps where the reference has an integer sample grid and the
+        // copy wasn't rescaled
+        let bit_depth = bit_depth(reference).filter(|_| gain == 1.0);
+        let steps: Option<Vec<i64>> = bit_depth.map(|bits| {
+            let scale = (1u64 << (bits - 1)) as f64;
+            residual
+                .iter()
+                .map(|&x| (x as f64 * scale).round() as i64)
+                .collect()
+        });
+        let changed = |i: usize| match &steps {
+            Some(steps) => steps[i] != 0,
+            None => residual[i].abs() > FLOAT_CHANGE_THRESHOLD,
+        };
+        // Bits a changed sample carries: a random payload bit leaves the sample alone half
+        // of the time, so every bit of the change stands for two payload bits
+        let sample_bits = |i: usize| -> u64 {
+            steps.as_ref().map_or(0, |steps| {
+                2 * (64 - steps[i].unsigned_abs().leading_zeros()) as u64
+            })
+        };
+
+        let changed_samples = (0..len).filter(|&i| changed(i)).count();
+        let lsb_only_share = match &steps {
+            Some(steps) if changed_samples > 0 => {
+                steps.iter().filter(|s| s.abs() == 1).count() as f64 / changed_samples as f64
+            }
+            _ => 0.0,
+        };
+
+        let window = ((REGION_WINDOW_SECONDS * sample_rate as f64) as usize).max(1);
+        let mut regions: Vec<ResidualRegion> = Vec::new();
+        let mut previous_active = false;
+        for start in (0..len).step_by(window) {
+            let end = (start + window).min(len);
+            let window_changed = (start..end).filter(|&i| changed(i)).count();
+            let active = window_changed as f64 > MIN_WINDOW_CHANGED_SHARE * (end - start) as f64;
+            if active {
+                let bits = steps
+                    .is_some()
+                    .then(|| (start..end).map(sample_bits).sum::<u64>());
+                match regions.last_mut() {
+                    Some(region) if previous_active => {
+                        region.end_seconds = end as f64 / sample_rate as f64;
+                        region.changed_samples += window_changed;
+                        region.estimated_bits = region.estimated_bits.zip(bits).map(|(a, b)| a + b);
+                    }
+                    _ => regions.push(ResidualRegion {
+                        start_seconds: start as f64 / sample_rate as f64,
+                        end_seconds: end as f64 / sample_rate as f64,
+                        changed_samples: window_changed,
+                        estimated_bits: bits,
+                    }),
+                }
+            }
+            previous_active = active;
+        }
+
+        let estimated_bits = steps
+            .is_some()
+            .then(|| regions.iter().filter_map(|r| r.estimated_bits).sum::<u64>());
+        let region_seconds: f64 = regions
+            .iter()
+            .map(|r| r.end_seconds - r.start_seconds)
+            .sum();
+        let estimated_bitrate_bps = estimated_bits
+            .filter(|_| region_seconds > 0.0)
+            .map(|bits| bits as f64 / region_seconds);
+
+        let (residual_peak_hz, residual_flatness) = residual_spectrum(&residual, sample_rate);
+
+        let lsbs: Vec<bool> = match (&regions.first(), bit_depth) {
+            (Some(region), Some(bits)) => {
+                let scale = (1u64 << (bits - 1)) as f64;
+                let start = (region.start_seconds * sample_rate as f64) as usize;
+                suspect[start..]
+                    .iter()
+                    .map(|&s| (s as f64 * scale).round() as i64 & 1 == 1)
+                    .take(MAX_LSB_CANDIDATE_BITS)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        let lsb_payload = (lsbs.len() >= 8).then(|| payload_sniffer::sniff_bits(&lsbs));
+        let lsb_decoded = pack_bits(&lsbs);
+
+        let mut analysis = DifferentialAnalysis {
+            offset_samples: offset,
+            alignment_correlation,
+            gain,
+            compared_samples: len,
+            bit_depth,
+            residual_db,
+            changed_samples,
+            lsb_only_share,
+            residual_peak_hz,
+            residual_flatness,
+            regions,
+            estimated_bits,
+            estimated_bitrate_bps,
+            lsb_decoded,
+            lsb_payload,
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.aligned() && !analysis.regions.is_empty();
+        Ok(analysis)
+    }
+}
+
+// Offset in samples that best lines the suspect up with the reference, from an FFT
+// cross-correlation of the start of both, and the normalized correlation there
+fn find_offset(suspect: &[f32], reference: &[f32], max_offset: usize) -> (i64, f64) {
+    let suspect = &suspect[..suspect.len().min(ALIGNMENT_WINDOW + max_offset)];
+    let reference = &reference[..reference.len().min(ALIGNMENT_WINDOW)];
+    let n = (suspect.len() + reference.len()).next_power_of_two();
+
+    let spectrum = |samples: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = samples
+            .iter()
+            .map(|&x| Complex::new(x, 0.0))
+            .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+            .take(n)
+            .collect();
+        fft_forward(n).process(&mut buffer);
+        buffer
+    };
+    let mut correlation: Vec<Complex<f32>> = spectrum(suspect)
+        .iter()
+        .zip(spectrum(reference))
+        .map(|(s, r)| s * r.conj())
+        .collect();
+    fft_inverse(n).process(&mut correlation);
+
+    // correlation[k] sums suspect[i + k] * reference[i]; negative lags wrap to the end
+    let best = (-(max_offset as i64)..=max_offset as i64)
+        .filter(|&lag| lag.unsigned_abs() < n as u64)
+        .max_by(|&a, &b| {
+            let at = |lag: i64| correlation[lag.rem_euclid(n as i64) as usize].re;
+            at(a).total_cmp(&at(b))
+        })
+        .unwrap_or(0);
+
+    // Normalize over the overlap at that lag
+    let (s, r) = if best >= 0 {
+        (&suspect[(best as usize).min(suspect.len())..], reference)
+    } else {
+        (suspect, &reference[(-best as usize).min(reference.len())..])
+    };
+    let overlap = s.len().min(r.len());
+    let (mut sr, mut ss, mut rr) = (0.0f64, 0.0f64, 0.0f64);
+    for (&a, &b) in s[..overlap].iter().zip(&r[..overlap]) {
+        sr += a as f64 * b as f64;
+        ss += a as f64 * a as f64;
+        rr += b as f64 * b as f64;
+    }
+    let normalized = if ss > 0.0 && rr > 0.0 {
+        sr / (ss * rr).sqrt()
+    } else {
+        0.0
+    };
+    (best, normalized)
+}
+
+// Bit depth of the integer grid every sample sits on, checking 8, 16 and 24 bits
+fn bit_depth(samples: &[f32]) -> Option<u32> {
+    [8, 16, 24].into_iter().find(|&bits| {
+        let scale = (1u64 << (bits - 1)) as f32;
+        samples.iter().all(|&x| (x * scale).fract() == 0.0)
+    })
+}
+
+// Peak frequency and spectral flatness of the Hann-windowed, averaged power spectrum
+fn residual_spectrum(residual: &[f32], sample_rate: u32) -> (f64, f64) {
+    let fft = fft_forward(SPECTRUM_FFT_SIZE);
+    let hann: Vec<f32> = (0..SPECTRUM_FFT_SIZE)
+        .map(|i| {
+            let phase = 2.0 * std::f32::consts::PI * i as f32 / SPECTRUM_FFT_SIZE as f32;
+            0.5 - 0.5 * phase.cos()
+        })
+        .collect();
+
+    let bins = SPECTRUM_FFT_SIZE / 2;
+    let mut power = vec![0.0f64; bins];
+    for frame in residual
+        .chunks_exact(SPECTRUM_FFT_SIZE)
+        .take(MAX_SPECTRUM_FRAMES)
+    {
+        let mut buffer: Vec<Complex<f32>> = frame
+            .iter()
+            .zip(&hann)
+            .map(|(&x, &w)| Complex::new(x * w, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        for (total, value) in power.iter_mut().zip(&buffer[..bins]) {
+            *total += value.norm_sqr() as f64;
+        }
+    }
+
+    // DC carries offsets rather than content
+    let usable = &power[1..];
+    let (peak_bin, _) = usable
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap_or((0, &0.0));
+    let peak_hz = (peak_bin + 1) as f64 * sample_rate as f64 / SPECTRUM_FFT_SIZE as f64;
+
+    let mean = usable.iter().sum::<f64>() / usable.len() as f64;
+    let flatness = if mean > 0.0 {
+        let log_mean = usable.iter().map(|&p| (p + 1e-30).ln()).sum::<f64>() / usable.len() as f64;
+        (log_mean.exp() / mean).min(1.0)
+    } else {
+        0.0
+    };
+    (peak_hz, flatness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 8000;
+
+    // Two seconds of a 16-bit tone mix
+    fn cover() -> Vec<f32> {
+        (0..2 * RATE as usize)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                let value = 0.3 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                    + 0.2 * (2.0 * std::f32::consts::PI * 1234.0 * t).sin();
+                (value * 32768.0).round() / 32768.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_offset_and_lsb_payload() {
+        let reference = cover();
+        let mut state = 0x9E37_79B9u32;
+        let bits: Vec<bool> = b"HIDDEN"
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .chain((0..8000).map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state & 1 == 1
+            }))
+            .collect();
+
+        // LSB replacement over the first second, then shift by 100 samples
+        let mut stego = reference.clone();
+        for (sample, &bit) in stego.iter_mut().zip(&bits) {
+            let value = (*sample * 32768.0).round() as i32;
+            *sample = ((value & !1) | bit as i32) as f32 / 32768.0;
+        }
+        let mut suspect = vec![0.0f32; 100];
+        suspect.extend(&stego);
+
+        let analysis = DifferentialAnalyzer::analyze((suspect, RATE, reference, RATE)).unwrap();
+
+        assert_eq!(analysis.offset_samples, 100);
+        assert!(analysis.aligned());
+        assert_eq!(analysis.bit_depth, Some(16));
+        assert!(analysis.suspicious);
+        assert_eq!(analysis.regions.len(), 1);
+        assert_eq!(analysis.regions[0].start_seconds, 0.0);
+        assert!(analysis.regions[0].end_seconds <= 1.1);
+        assert_eq!(analysis.lsb_only_share, 1.0);
+        assert!(
+            analysis.residual_flatness > 0.5,
+            "{}",
+            analysis.residual_flatness
+        );
+        // About 2 bits per changed sample over the ~8000 embedded bits
+        let bits = analysis.estimated_bits.unwrap();
+        assert!((7000..=9500).contains(&bits), "{}", bits);
+        assert!(analysis.lsb_decoded.starts_with(b"HIDDEN"));
+        // The noise after the text doesn't read as anything
+        let payload = analysis.lsb_payload.unwrap();
+        assert!(payload.data.starts_with(b"HIDDEN"));
+        assert!(!payload.is_recognized());
+    }
+
+    #[test]
+    fn test_identical_and_unrelated_audio() {
+        let reference = cover();
+        let same =
+            DifferentialAnalyzer::analyze((reference.clone(), RATE, reference.clone(), RATE))
+                .unwrap();
+        assert_eq!(same.offset_samples, 0);
+        assert_eq!(same.changed_samples, 0);
+        assert!(!same.suspicious);
+
+        let noise: Vec<f32> = (0..reference.len())
+            .map(|i| ((i * 2654435761) % 1000) as f32 / 1000.0 - 0.5)
+            .collect();
+        let unrelated = DifferentialAnalyzer::analyze((noise, RATE, reference, RATE)).unwrap();
+        assert!(!unrelated.aligned());
+        assert!(!unrelated.suspicious);
+
+        assert!(matches!(
+            DifferentialAnalyzer::analyze((vec![0.0; 4096], 8000, vec![0.0; 4096], 16000)),
+            Err(DifferentialAnalyzerError::SampleRateMismatch(8000, 16000))
+        ));
+    }
+}
@@ -0,0 +1,769 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::text_stego_analyzer::{TextStegoAnalysis, analyze_text};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::fmt::Display;
+use std::io::{Cursor, Read};
+
+// Packaging of EPUB and CHM ebooks. An EPUB is a zip whose package document (the OPF) lists
+// every resource the book uses; a CHM is a small file system stored in sections, the content
+// usually compressed in one of them. Members nothing refers to, content that isn't what it is
+// declared as and packaging the specifications forbid are reported, and each chapter is put
+// through the text steganography checks.
+//
+// CHM content compressed with LZX isn't decompressed: those pages are counted, not analyzed.
+pub struct EbookAnalyzer;
+
+const ZIP_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const CHM_SIGNATURE: &[u8; 4] = b"ITSF";
+const EPUB_MIMETYPE: &str = "application/epub+zip";
+
+// Members reading systems and stores add next to what the package document lists
+const KNOWN_EXTRAS: [&str; 4] = [
+    "iTunesMetadata.plist",
+    "iTunesArtwork",
+    "calibre_bookmarks.txt",
+    "com.apple.ibooks.display-options.xml",
+];
+
+// Members larger than this aren't read
+const MAX_MEMBER_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum EbookError {
+    NotEbook,
+    Corrupt(String),
+}
+
+impl Display for EbookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EbookError::NotEbook => write!(f, "Not an EPUB or CHM file"),
+            EbookError::Corrupt(e) => write!(f, "Unreadable ebook: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EbookError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EbookFormat {
+    Epub,
+    Chm,
+}
+
+impl Display for EbookFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EbookFormat::Epub => write!(f, "EPUB"),
+            EbookFormat::Chm => write!(f, "CHM"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EbookMember {
+    pub path: String,
+    pub size: u64,
+    // Deflated in the zip, or stored in a CHM's compressed section
+    pub compressed: bool,
+    // Listed in the package document, or a file the format defines
+    pub declared: bool,
+    // Media type the package document gives
+    pub media_type: Option<String>,
+    // MIME type, "text" or "binary"; None when the member couldn't be read
+    pub classification: Option<String>,
+    // A program: PE, ELF or Mach-O binary, DEX, Java class and the like
+    pub executable: bool,
+    // Contents of members that aren't declared, kept so they can be saved
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EbookChapter {
+    pub path: String,
+    pub text: TextStegoAnalysis,
+    // Elements styled or marked so they are never displayed
+    pub hidden_elements: usize,
+    pub suspicious: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct EbookAnalysis {
+    pub format: EbookFormat,
+    pub members: Vec<EbookMember>,
+    // Packaging that breaks the format's rules
+    pub anomalies: Vec<String>,
+    pub chapters: Vec<EbookChapter>,
+    // Pages in compressed CHM sections, which aren't analyzed
+    pub unanalyzed_chapters: usize,
+    pub suspicious: bool,
+}
+
+impl EbookAnalysis {
+    pub fn extraneous(&self) -> impl Iterator<Item = &EbookMember> {
+        self.members.iter().filter(|m| !m.declared)
+    }
+}
+
+// Whether the data starts like an EPUB or a CHM
+pub fn is_ebook(data: &[u8]) -> bool {
+    data.starts_with(CHM_SIGNATURE)
+        || (data.starts_with(&ZIP_SIGNATURE)
+            && zip::ZipArchive::new(Cursor::new(data)).is_ok_and(|mut archive| {
+                archive
+                    .by_name("mimetype")
+                    .ok()
+                    .and_then(|mut file| {
+                        let mut mimetype = String::new();
+                        file.read_to_string(&mut mimetype).ok()?;
+                        Some(mimetype.trim() == EPUB_MIMETYPE)
+                    })
+                    .unwrap_or(false)
+                    || archive.index_for_name("META-INF/container.xml").is_some()
+            }))
+}
+
+impl Analyzer for EbookAnalyzer {
+    type Input = Vec<u8>;
+    type Output = EbookAnalysis;
+    type Error = EbookError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let mut analysis = if input.starts_with(CHM_SIGNATURE) {
+            analyze_chm(&input)?
+        } else if input.starts_with(&ZIP_SIGNATURE) {
+            analyze_epub(input)?
+        } else {
+            return Err(EbookError::NotEbook);
+        };
+
+        for member in analysis.members.iter().filter(|m| m.executable) {
+            analysis.anomalies.push(format!(
+                "{} is an executable ({})",
+                member.path,
+                member.classification.as_deref().unwrap_or("binary")
+            ));
+        }
+        analysis.suspicious = analysis.extraneous().next().is_some()
+            || !analysis.anomalies.is_empty()
+            || analysis.chapters.iter().any(|c| c.suspicious);
+        Ok(analysis)
+    }
+}
+
+fn analyze_epub(data: Vec<u8>) -> Result<EbookAnalysis, EbookError> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).map_err(|e| EbookError::Corrupt(e.to_string()))?;
+    let mut anomalies = Vec::new();
+
+    // The mimetype member has to come first and be stored, so the format can be told from the
+    // first bytes of the file
+    match archive.by_name("mimetype") {
+        Ok(mut file) => {
+            let mut mimetype = String::new();
+            let _ = file.read_to_string(&mut mimetype);
+            if mimetype != EPUB_MIMETYPE {
+                anomalies.push(format!(
+                    "mimetype reads {:?} rather than {:?}",
+                    mimetype, EPUB_MIMETYPE
+                ));
+            }
+            if file.compression() != zip::CompressionMethod::Stored {
+                anomalies.push("mimetype is compressed".to_string());
+            }
+        }
+        Err(_) => anomalies.push("no mimetype member".to_string()),
+    }
+    if archive.name_for_index(0) != Some("mimetype") && archive.index_for_name("mimetype").is_some()
+    {
+        anomalies.push("mimetype isn't the first member".to_string());
+    }
+
+    let opf_path = read_member(&mut archive, "META-INF/container.xml")
+        .and_then(|container| rootfile(&String::from_utf8_lossy(&container)));
+    let manifest = match &opf_path {
+        Some(path) => match read_member(&mut archive, path) {
+            Some(opf) => manifest(&String::from_utf8_lossy(&opf), path),
+            None => {
+                anomalies.push(format!("package document {} is missing", path));
+                Vec::new()
+            }
+        },
+        None => {
+            anomalies.push("META-INF/container.xml names no package document".to_string());
+            Vec::new()
+        }
+    };
+
+    let names: Vec<String> = archive.file_names().map(String::from).collect();
+    for item in &manifest {
+        if !names.contains(&item.href) && !item.href.contains("://") {
+            anomalies.push(format!(
+                "manifest lists {}, which isn't in the package",
+                item.href
+            ));
+        }
+    }
+
+    let mut members = Vec::new();
+    let mut chapters = Vec::new();
+    for index in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(index) else {
+            continue;
+        };
+        if file.is_dir() {
+            continue;
+        }
+        let path = file.name().to_string();
+        if path.starts_with('/') || path.split('/').any(|part| part == "..") {
+            anomalies.push(format!("{} points outside the package", path));
+        }
+        let item = manifest.iter().find(|item| item.href == path);
+        let declared = item.is_some()
+            || path == "mimetype"
+            || path.starts_with("META-INF/")
+            || opf_path.as_ref() == Some(&path)
+            || KNOWN_EXTRAS.contains(&path.as_str());
+        let media_type = item.map(|item| item.media_type.clone());
+
+        let mut data = Vec::new();
+        let readable = file.size() <= MAX_MEMBER_BYTES
+            && (&mut file)
+                .take(MAX_MEMBER_BYTES)
+                .read_to_end(&mut data)
+                .is_ok();
+        let classification = readable.then(|| classify_payload(&data));
+        if let (Some(media_type), true) = (&media_type, readable)
+            && let Some(kind) = infer::get(&data)
+            && contradicts(media_type, &kind)
+        {
+            anomalies.push(format!(
+                "{} is declared {} but holds {}",
+                path,
+                media_type,
+                kind.mime_type()
+            ));
+        }
+        if readable && media_type.as_deref().is_some_and(is_chapter_type) {
+            chapters.push(chapter(&path, &String::from_utf8_lossy(&data)));
+        }
+
+        members.push(EbookMember {
+            path,
+            size: file.size(),
+            compressed: file.compression() != zip::CompressionMethod::Stored,
+            declared,
+            media_type,
+            classification,
+            executable: readable && is_executable(&data),
+            data: (!declared && readable).then_some(data),
+        });
+    }
+
+    Ok(EbookAnalysis {
+        format: EbookFormat::Epub,
+        members,
+        anomalies,
+        chapters,
+        unanalyzed_chapters: 0,
+        suspicious: false,
+    })
+}
+
+fn read_member(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Option<Vec<u8>> {
+    let file = archive.by_name(name).ok()?;
+    if file.size() > MAX_MEMBER_BYTES {
+        return None;
+    }
+    let mut data = Vec::new();
+    file.take(MAX_MEMBER_BYTES).read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+// full-path of the first rootfile in META-INF/container.xml
+fn rootfile(container: &str) -> Option<String> {
+    let mut reader = Reader::from_str(container);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"rootfile" => {
+                return attribute(&e, b"full-path");
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+struct ManifestItem {
+    // Resolved against the package document's folder
+    href: String,
+    media_type: String,
+}
+
+fn manifest(opf: &str, opf_path: &str) -> Vec<ManifestItem> {
+    let base = opf_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    let mut items = Vec::new();
+    let mut reader = Reader::from_str(opf);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"item" => {
+                if let Some(href) = attribute(&e, b"href") {
+                    items.push(ManifestItem {
+                        // Remote resources are left as they are
+                        href: if href.contains("://") {
+                            href
+                        } else {
+                            resolve(base, &percent_decode(&href))
+                        },
+                        media_type: attribute(&e, b"media-type").unwrap_or_default(),
+                    });
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    items
+}
+
+fn attribute(element: &quick_xml::events::BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.to_string()))
+}
+
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn is_executable(data: &[u8]) -> bool {
+    infer::get(data).is_some_and(|kind| kind.matcher_type() == infer::MatcherType::App)
+}
+
+fn is_chapter_type(media_type: &str) -> bool {
+    matches!(media_type, "application/xhtml+xml" | "text/html")
+}
+
+// Content a signature identifies as something its declared media type can't be: a program or
+// an archive anywhere, anything but an image where an image is declared, and any binary
+// format where markup or text is. Markup infer recognizes (HTML, XML) fits any text type.
+fn contradicts(media_type: &str, kind: &infer::Type) -> bool {
+    match kind.matcher_type() {
+        infer::MatcherType::App | infer::MatcherType::Archive => true,
+        infer::MatcherType::Font | infer::MatcherType::Text => false,
+        _ if media_type.starts_with("image/") => kind.matcher_type() != infer::MatcherType::Image,
+        _ => {
+            media_type.starts_with("text/")
+                || media_type.ends_with("+xml")
+                || media_type.ends_with("/xml")
+        }
+    }
+}
+
+fn chapter(path: &str, markup: &str) -> EbookChapter {
+    let text = analyze_text(markup);
+    let hidden_elements = hidden_elements(markup);
+    EbookChapter {
+        path: path.to_string(),
+        suspicious: text.suspicious || hidden_elements > 0,
+        text,
+        hidden_elements,
+    }
+}
+
+// Start tags whose style hides them or that carry the hidden attribute
+fn hidden_elements(markup: &str) -> usize {
+    markup
+        .split('<')
+        .skip(1)
+        .filter_map(|tag| tag.split('>').next())
+        .filter(|tag| !tag.starts_with(['/', '!', '?']))
+        .filter(|tag| {
+            let tag = tag.to_ascii_lowercase();
+            let compact: String = tag.chars().filter(|c| !c.is_whitespace()).collect();
+            [
+                "display:none",
+                "visibility:hidden",
+                "font-size:0;",
+                "font-size:0\"",
+                "font-size:0'",
+            ]
+            .iter()
+            .any(|style| compact.contains(style))
+                || tag
+                    .split_whitespace()
+                    .skip(1)
+                    .any(|attribute| attribute == "hidden" || attribute.starts_with("hidden="))
+        })
+        .count()
+}
+
+fn analyze_chm(data: &[u8]) -> Result<EbookAnalysis, EbookError> {
+    let corrupt = |what: &str| EbookError::Corrupt(format!("CHM {}", what));
+    let u32_at = |pos: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+    };
+    let u64_at = |pos: usize| -> Option<u64> {
+        Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+    };
+
+    let version = u32_at(0x04).ok_or_else(|| corrupt("header is cut short"))?;
+    let directory_offset = u64_at(0x48).ok_or_else(|| corrupt("header is cut short"))? as usize;
+    let directory_length = u64_at(0x50).ok_or_else(|| corrupt("header is cut short"))? as usize;
+    let content_offset = match version {
+        3 => u64_at(0x58).ok_or_else(|| corrupt("header is cut short"))? as usize,
+        _ => directory_offset + directory_length,
+    };
+    let mut anomalies = Vec::new();
+    if !(2..=3).contains(&version) {
+        anomalies.push(format!("unknown ITSF version {}", version));
+    }
+
+    let directory = data
+        .get(directory_offset..directory_offset.saturating_add(directory_length))
+        .filter(|directory| directory.starts_with(b"ITSP"))
+        .ok_or_else(|| corrupt("directory is missing"))?;
+    let dir_u32 = |pos: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            directory.get(pos..pos + 4)?.try_into().ok()?,
+        ))
+    };
+    let header_length = dir_u32(0x08).ok_or_else(|| corrupt("directory is cut short"))? as usize;
+    let chunk_size = dir_u32(0x10).ok_or_else(|| corrupt("directory is cut short"))? as usize;
+    if chunk_size < 0x14 {
+        return Err(corrupt("directory chunk size is invalid"));
+    }
+
+    let mut entries = Vec::new();
+    for chunk in directory
+        .get(header_length..)
+        .unwrap_or_default()
+        .chunks_exact(chunk_size)
+        .filter(|chunk| chunk.starts_with(b"PMGL"))
+    {
+        let free_space = u32::from_le_bytes(chunk[4..8].try_into().unwrap_or_default()) as usize;
+        let end = chunk_size.saturating_sub(free_space);
+        let mut pos = 0x14;
+        while pos < end {
+            let Some(entry) = chm_entry(chunk, &mut pos) else {
+                break;
+            };
+            entries.push(entry);
+        }
+    }
+
+    let mut members = Vec::new();
+    let mut chapters = Vec::new();
+    let mut unanalyzed_chapters = 0;
+    let mut stored_end = content_offset;
+    for (name, section, offset, length) in entries {
+        if name.ends_with('/') {
+            continue;
+        }
+        let is_page = name.ends_with(".htm") || name.ends_with(".html");
+        let system = name.starts_with("::") || name.starts_with("/#") || name.starts_with("/$");
+        let mut executable = false;
+        let (declared, classification, data_kept) = if section == 0 {
+            let start = content_offset.saturating_add(offset as usize);
+            let stored = data.get(start..start.saturating_add(length as usize));
+            match stored {
+                Some(stored) => {
+                    stored_end = stored_end.max(start + stored.len());
+                    executable = is_executable(stored);
+                    if is_page {
+                        chapters.push(chapter(&name, &String::from_utf8_lossy(stored)));
+                    }
+                    // Help compilers compress the content; only system files are stored as is
+                    (
+                        system,
+                        Some(classify_payload(stored)),
+                        (!system).then(|| stored.to_vec()),
+                    )
+                }
+                None => {
+                    anomalies.push(format!("{} points past the end of the file", name));
+                    (system, None, None)
+                }
+            }
+        } else {
+            if is_page {
+                unanalyzed_chapters += 1;
+            }
+            (true, None, None)
+        };
+        members.push(EbookMember {
+            path: name,
+            size: length,
+            compressed: section != 0,
+            declared,
+            media_type: None,
+            classification,
+            executable,
+            data: data_kept,
+        });
+    }
+    if stored_end < data.len() && members.iter().any(|m| !m.compressed) {
+        anomalies.push(format!(
+            "{} bytes after the last stored member",
+            data.len() - stored_end
+        ));
+    }
+
+    Ok(EbookAnalysis {
+        format: EbookFormat::Chm,
+        members,
+        anomalies,
+        chapters,
+        unanalyzed_chapters,
+        suspicious: false,
+    })
+}
+
+// (name, section, offset, length) of a directory listing entry
+fn chm_entry(chunk: &[u8], pos: &mut usize) -> Option<(String, u64, u64, u64)> {
+    let name_length = encint(chunk, pos)? as usize;
+    let name = chunk.get(*pos..*pos + name_length)?;
+    *pos += name_length;
+    let name = String::from_utf8_lossy(name).to_string();
+    let section = encint(chunk, pos)?;
+    let offset = encint(chunk, pos)?;
+    let length = encint(chunk, pos)?;
+    Some((name, section, offset, length))
+}
+
+// Variable-length integer: 7 bits per byte, most significant first, high bit set on all but
+// the last byte
+fn encint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for _ in 0..10 {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value = (value << 7) | u64::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <manifest>
+    <item id="c1" href="Text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="cover" href="Images/cover.jpg" media-type="image/jpeg"/>
+    <item id="css" href="../Styles/book.css" media-type="text/css"/>
+  </manifest>
+  <spine><itemref idref="c1"/></spine>
+</package>"#;
+
+    fn epub(parts: &[(&str, &[u8])], mimetype_first: bool) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let stored =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        if mimetype_first {
+            zip.start_file("mimetype", stored).unwrap();
+            zip.write_all(EPUB_MIMETYPE.as_bytes()).unwrap();
+        }
+        for (name, data) in parts {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        if !mimetype_first {
+            zip.start_file("mimetype", SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(EPUB_MIMETYPE.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_clean_epub() {
+        let chapter = b"<html><body><p>It was a dark and stormy night.</p></body></html>";
+        let book = epub(
+            &[
+                ("META-INF/container.xml", CONTAINER.as_bytes()),
+                ("OEBPS/content.opf", OPF.as_bytes()),
+                ("OEBPS/Text/chapter 1.xhtml", chapter),
+                (
+                    "OEBPS/Images/cover.jpg",
+                    b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00",
+                ),
+                ("Styles/book.css", b"p { margin: 0 }"),
+            ],
+            true,
+        );
+        assert!(is_ebook(&book));
+
+        let analysis = EbookAnalyzer::analyze(book).unwrap();
+        assert_eq!(analysis.format, EbookFormat::Epub);
+        assert_eq!(analysis.anomalies, Vec::<String>::new());
+        assert_eq!(analysis.extraneous().count(), 0);
+        assert_eq!(analysis.chapters.len(), 1);
+        assert_eq!(analysis.chapters[0].path, "OEBPS/Text/chapter 1.xhtml");
+        assert!(!analysis.suspicious);
+    }
+
+    #[test]
+    fn test_epub_packaging_anomalies_and_hidden_text() {
+        let chapter = b"<html><body><p>Chapter one.</p>\
+            <p style=\"display: none\">meet at dawn</p>\
+            <div hidden=\"\">second note</div></body></html>";
+        let book = epub(
+            &[
+                ("META-INF/container.xml", CONTAINER.as_bytes()),
+                ("OEBPS/content.opf", OPF.as_bytes()),
+                ("OEBPS/Text/chapter 1.xhtml", chapter),
+                (
+                    "OEBPS/Images/cover.jpg",
+                    b"MZ\x90\x00\x03\x00\x00\x00\x04\x00\x00\x00\xFF\xFF",
+                ),
+                ("OEBPS/Misc/notes.bin", b"secret"),
+            ],
+            false,
+        );
+
+        let analysis = EbookAnalyzer::analyze(book).unwrap();
+        assert!(analysis.suspicious);
+        let extraneous: Vec<&str> = analysis.extraneous().map(|m| m.path.as_str()).collect();
+        assert_eq!(extraneous, ["OEBPS/Misc/notes.bin"]);
+        assert_eq!(
+            analysis.extraneous().next().unwrap().data.as_deref(),
+            Some(&b"secret"[..])
+        );
+        for expected in [
+            "mimetype is compressed",
+            "mimetype isn't the first member",
+            "manifest lists Styles/book.css, which isn't in the package",
+            "OEBPS/Images/cover.jpg is declared image/jpeg but holds application/vnd.microsoft.portable-executable",
+        ] {
+            assert!(
+                analysis.anomalies.iter().any(|a| a == expected),
+                "{:?} missing from {:?}",
+                expected,
+                analysis.anomalies
+            );
+        }
+        assert_eq!(analysis.chapters[0].hidden_elements, 2);
+    }
+
+    fn encint_bytes(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_chm_directory() {
+        let page = b"<html><body>Help</body></html>";
+        let extra = b"stored outside the compressed section";
+        let mut listing = Vec::new();
+        for (name, section, offset, length) in [
+            ("/", 0u64, 0u64, 0u64),
+            ("/#SYSTEM", 0, 0, 4),
+            ("/index.html", 1, 0, 500),
+            ("/hidden.html", 0, 4, page.len() as u64),
+            ("/payload.txt", 0, 4 + page.len() as u64, extra.len() as u64),
+        ] {
+            listing.extend(encint_bytes(name.len() as u64));
+            listing.extend_from_slice(name.as_bytes());
+            for value in [section, offset, length] {
+                listing.extend(encint_bytes(value));
+            }
+        }
+        let chunk_size = 0x200;
+        let mut chunk = b"PMGL".to_vec();
+        chunk.extend_from_slice(&((chunk_size - 0x14 - listing.len()) as u32).to_le_bytes());
+        chunk.extend_from_slice(&[0; 12]);
+        chunk.extend(listing);
+        chunk.resize(chunk_size, 0);
+
+        let mut directory = b"ITSP".to_vec();
+        directory.extend_from_slice(&1u32.to_le_bytes());
+        directory.extend_from_slice(&0x54u32.to_le_bytes());
+        directory.extend_from_slice(&10u32.to_le_bytes());
+        directory.extend_from_slice(&(chunk_size as u32).to_le_bytes());
+        directory.resize(0x54, 0);
+        directory.extend(chunk);
+
+        let directory_offset = 0x60u64;
+        let content_offset = directory_offset + directory.len() as u64;
+        let mut chm = b"ITSF".to_vec();
+        chm.extend_from_slice(&3u32.to_le_bytes());
+        chm.extend_from_slice(&0x60u32.to_le_bytes());
+        chm.resize(0x48, 0);
+        chm.extend_from_slice(&directory_offset.to_le_bytes());
+        chm.extend_from_slice(&(directory.len() as u64).to_le_bytes());
+        chm.extend_from_slice(&content_offset.to_le_bytes());
+        chm.extend(directory);
+        chm.extend_from_slice(b"\x01\x02\x03\x04");
+        chm.extend_from_slice(page);
+        chm.extend_from_slice(extra);
+        chm.extend_from_slice(b"trailing");
+        assert!(is_ebook(&chm));
+
+        let analysis = EbookAnalyzer::analyze(chm).unwrap();
+        assert_eq!(analysis.format, EbookFormat::Chm);
+        assert_eq!(analysis.members.len(), 4);
+        let extraneous: Vec<&str> = analysis.extraneous().map(|m| m.path.as_str()).collect();
+        assert_eq!(extraneous, ["/hidden.html", "/payload.txt"]);
+        assert_eq!(analysis.chapters.len(), 1);
+        assert_eq!(analysis.unanalyzed_chapters, 1);
+        assert_eq!(analysis.anomalies, ["8 bytes after the last stored member"]);
+        assert!(analysis.suspicious);
+    }
+}
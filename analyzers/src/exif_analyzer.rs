@@ -1,8 +1,14 @@
 use crate::Analyzer;
+use crate::content_classifier::ContentClass;
+use crate::iptc_analyzer::{IptcAnalyzer, IptcData};
+use crate::makernote_analyzer::{MakerNoteAnalysis, analyze_maker_note};
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::Path;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
 pub struct ExifAnalyzer;
 
 #[derive(Debug)]
@@ -35,6 +41,7 @@ pub struct ExifData {
     pub thumbnail_size: Option<usize>,
     pub suspicious_fields: Vec<String>,
     pub comment_fields: Vec<String>,
+    pub maker_note: Option<MakerNoteAnalysis>,
 }
 
 impl ExifData {
@@ -45,6 +52,7 @@ impl ExifData {
             thumbnail_size: None,
             suspicious_fields: Vec::new(),
             comment_fields: Vec::new(),
+            maker_note: None,
         }
     }
 }
@@ -65,66 +73,212 @@ impl<'a> ExifAnalyzerWithPath<'a> {
     }
 
     pub fn analyze(&self) -> Result<ExifData, ExifAnalyzerError> {
-        use exif::{In, Reader, Tag};
+        let bytes = std::fs::read(self.path)?;
+        let exif = exif::Reader::new().read_from_container(&mut std::io::Cursor::new(&bytes));
+        let iptc = IptcAnalyzer::analyze(bytes).ok();
 
-        let file = std::fs::File::open(self.path)?;
-        let mut bufreader = std::io::BufReader::new(&file);
+        let mut exif_data = ExifData::new();
+        match exif {
+            Ok(exif) => collect_exif(&exif, &mut exif_data),
+            // IPTC on its own still makes a metadata report
+            Err(e) if iptc.is_none() => {
+                return Err(ExifAnalyzerError::ExifError(format!("{:?}", e)));
+            }
+            Err(_) => {}
+        }
+        if let Some(iptc) = iptc {
+            collect_iptc(&iptc, &mut exif_data);
+        }
 
-        let exifreader = Reader::new();
-        let exif = match exifreader.read_from_container(&mut bufreader) {
-            Ok(exif) => exif,
-            Err(e) => return Err(ExifAnalyzerError::ExifError(format!("{:?}", e))),
-        };
+        Ok(exif_data)
+    }
+}
 
-        let mut exif_data = ExifData::new();
+fn collect_exif(exif: &exif::Exif, exif_data: &mut ExifData) {
+    use exif::{In, Tag, Value};
 
-        // Extract all EXIF fields
-        for field in exif.fields() {
-            let tag_name = format!("{}", field.tag);
-            let value = field.display_value().to_string();
-
-            exif_data.metadata.insert(tag_name.clone(), value.clone());
-
-            // Check for comment/description fields that could hide data
-            match field.tag {
-                Tag::UserComment | Tag::ImageDescription => {
-                    exif_data
-                        .comment_fields
-                        .push(format!("{}: {}", tag_name, value));
-                }
-                _ => {}
-            }
+    // Extract all EXIF fields
+    for field in exif.fields() {
+        let tag_name = format!("{}", field.tag);
+        let value = field.display_value().to_string();
 
-            // Check for suspicious patterns
-            if value.len() > 1000 {
-                exif_data.suspicious_fields.push(format!(
-                    "{}: unusually large ({}+ bytes)",
-                    tag_name,
-                    value.len()
-                ));
-            }
+        exif_data.metadata.insert(tag_name.clone(), value.clone());
 
-            // Check for base64-like patterns
-            if is_potential_base64(&value) && value.len() > 50 {
+        // Check for comment/description fields that could hide data
+        match field.tag {
+            Tag::UserComment | Tag::ImageDescription => {
                 exif_data
-                    .suspicious_fields
-                    .push(format!("{}: potential encoded data", tag_name));
+                    .comment_fields
+                    .push(format!("{}: {}", tag_name, value));
             }
+            _ => {}
+        }
+
+        // The MakerNote is walked on its own below; its hex dump is always long
+        if field.tag == Tag::MakerNote {
+            continue;
+        }
+
+        check_field(&tag_name, &value, &mut exif_data.suspicious_fields);
+    }
+
+    // Check for thumbnail
+    if let Some(_thumbnail) = exif.get_field(Tag::JPEGInterchangeFormat, In::PRIMARY) {
+        exif_data.has_thumbnail = true;
+        if let Some(size_field) = exif.get_field(Tag::JPEGInterchangeFormatLength, In::PRIMARY)
+            && let Some(size) = size_field.value.get_uint(0)
+        {
+            exif_data.thumbnail_size = Some(size as usize);
+        }
+    }
+
+    // Walk the vendor MakerNote instead of treating it as one opaque value
+    if let Some(field) = exif.get_field(Tag::MakerNote, In::PRIMARY)
+        && let Value::Undefined(ref bytes, offset) = field.value
+    {
+        let make = match exif.get_field(Tag::Make, In::PRIMARY).map(|f| &f.value) {
+            Some(Value::Ascii(values)) => values
+                .first()
+                .map(|v| String::from_utf8_lossy(v).into_owned())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let maker_note = analyze_maker_note(
+            exif.buf(),
+            exif.little_endian(),
+            offset as usize,
+            bytes.len(),
+            &make,
+        );
+        for finding in &maker_note.findings {
+            exif_data.suspicious_fields.push(format!(
+                "MakerNote: {} at offset 0x{:X} ({} bytes)",
+                finding.description, finding.offset, finding.size
+            ));
+        }
+        exif_data.maker_note = Some(maker_note);
+    }
+}
+
+fn collect_iptc(iptc: &IptcData, exif_data: &mut ExifData) {
+    for dataset in &iptc.datasets {
+        let key = format!("IPTC:{}", dataset.name);
+        // Records 7 and up hold the object itself rather than text about it
+        if dataset.record >= 7 {
+            exif_data
+                .metadata
+                .insert(key.clone(), format!("<{} bytes>", dataset.value.len()));
+            exif_data.suspicious_fields.push(format!(
+                "{}: {} bytes of object data embedded in IPTC",
+                key,
+                dataset.value.len()
+            ));
+            continue;
         }
 
-        // Check for thumbnail
-        if let Some(_thumbnail) = exif.get_field(Tag::JPEGInterchangeFormat, In::PRIMARY) {
-            exif_data.has_thumbnail = true;
-            if let Some(size_field) = exif.get_field(Tag::JPEGInterchangeFormatLength, In::PRIMARY)
-            {
-                if let Some(size) = size_field.value.get_uint(0) {
-                    exif_data.thumbnail_size = Some(size as usize);
-                }
+        let value = dataset.text();
+        // Keywords and other repeatable datasets appear once per value
+        exif_data
+            .metadata
+            .entry(key.clone())
+            .and_modify(|existing| {
+                existing.push_str("; ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.clone());
+
+        if dataset.is_caption() {
+            exif_data.comment_fields.push(format!("{}: {}", key, value));
+            if is_binary(&dataset.value) {
+                exif_data
+                    .suspicious_fields
+                    .push(format!("{}: binary data in a text field", key));
             }
         }
+        if let Some(max_length) = dataset.max_length
+            && dataset.value.len() > max_length
+        {
+            exif_data.suspicious_fields.push(format!(
+                "{}: {} bytes exceeds the IIM maximum of {}",
+                key,
+                dataset.value.len(),
+                max_length
+            ));
+        }
+        check_field(&key, &value, &mut exif_data.suspicious_fields);
+    }
 
-        Ok(exif_data)
+    if let Some((offset, size)) = iptc.trailing_data {
+        exif_data.suspicious_fields.push(format!(
+            "IPTC: {} bytes of trailing data at offset 0x{:X}",
+            size, offset
+        ));
+    }
+}
+
+// Heuristics applied to every EXIF and IPTC value
+fn check_field(name: &str, value: &str, suspicious_fields: &mut Vec<String>) {
+    // Check for suspicious patterns
+    if value.len() > 1000 {
+        suspicious_fields.push(format!(
+            "{}: unusually large ({}+ bytes)",
+            name,
+            value.len()
+        ));
+    }
+
+    // Check for base64-like patterns
+    if is_potential_base64(value) && value.len() > 50 {
+        suspicious_fields.push(format!("{}: potential encoded data", name));
+    }
+}
+
+// NULs or a noticeable share of control characters; ESC is allowed for ISO 2022 escapes
+fn is_binary(value: &[u8]) -> bool {
+    let control = value
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x1B)) || b == 0x7F)
+        .count();
+    value.contains(&0) || control * 10 > value.len()
+}
+
+// Tags every camera writes into the photos it takes
+pub const CAMERA_TAGS: [&str; 3] = ["Make", "Model", "DateTimeOriginal"];
+
+// Metadata a file should carry given what it looks like, but doesn't. Embedding tools and
+// the re-encoding around them commonly drop it, so its absence is a weak signal on its own.
+#[derive(Debug, Clone)]
+pub struct MissingMetadata {
+    pub expected_source: &'static str,
+    pub missing_fields: Vec<String>,
+}
+
+// A photograph in a format cameras write is expected to carry the camera tags. Synthetic
+// images and formats cameras don't produce are never expected to have any.
+pub fn missing_metadata(
+    mime: &str,
+    content: ContentClass,
+    exif: Option<&ExifData>,
+) -> Option<MissingMetadata> {
+    let camera_format = matches!(
+        mime,
+        "image/jpeg" | "image/tiff" | "image/heif" | "image/heic"
+    );
+    if !camera_format || content != ContentClass::Photo {
+        return None;
     }
+    let missing_fields: Vec<String> = CAMERA_TAGS
+        .iter()
+        .filter(|tag| exif.is_none_or(|exif| !exif.metadata.contains_key(**tag)))
+        .map(|tag| tag.to_string())
+        .collect();
+    if missing_fields.is_empty() {
+        return None;
+    }
+    Some(MissingMetadata {
+        expected_source: "camera photo",
+        missing_fields,
+    })
 }
 
 // Placeholder analyzer trait implementation (requires path, not just image data)
@@ -156,6 +310,7 @@ fn is_potential_base64(s: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::iptc_analyzer::IptcDataset;
 
     #[test]
     fn test_base64_detection() {
@@ -164,4 +319,67 @@ mod tests {
         assert!(!is_potential_base64("Hello World"));
         assert!(!is_potential_base64("abc"));
     }
+
+    #[test]
+    fn test_iptc_merged_into_metadata() {
+        let dataset = |dataset: u8, value: &[u8]| IptcDataset {
+            record: 2,
+            dataset,
+            name: if dataset == 120 {
+                "Caption-Abstract"
+            } else {
+                "Keywords"
+            }
+            .to_string(),
+            offset: 0,
+            value: value.to_vec(),
+            max_length: Some(if dataset == 120 { 2000 } else { 64 }),
+        };
+        let iptc = IptcData {
+            datasets: vec![
+                dataset(120, b"caption\0\x01\x02payload"),
+                dataset(25, b"beach"),
+                dataset(25, "beach ".repeat(14).as_bytes()),
+            ],
+            trailing_data: None,
+        };
+        let mut exif_data = ExifData::new();
+        collect_iptc(&iptc, &mut exif_data);
+
+        assert!(exif_data.metadata["IPTC:Keywords"].starts_with("beach; beach beach"));
+        assert_eq!(exif_data.comment_fields.len(), 1);
+        assert_eq!(
+            exif_data.suspicious_fields,
+            vec![
+                "IPTC:Caption-Abstract: binary data in a text field".to_string(),
+                "IPTC:Keywords: 84 bytes exceeds the IIM maximum of 64".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_camera_photo_without_camera_tags() {
+        let mut exif = ExifData::new();
+        exif.metadata
+            .insert("Make".to_string(), "Canon".to_string());
+
+        let missing = missing_metadata("image/jpeg", ContentClass::Photo, Some(&exif)).unwrap();
+        assert_eq!(missing.missing_fields, ["Model", "DateTimeOriginal"]);
+        assert_eq!(
+            missing_metadata("image/jpeg", ContentClass::Photo, None)
+                .unwrap()
+                .missing_fields
+                .len(),
+            3
+        );
+
+        // Screenshots and PNGs never came from a camera
+        assert!(missing_metadata("image/jpeg", ContentClass::Synthetic, None).is_none());
+        assert!(missing_metadata("image/png", ContentClass::Photo, None).is_none());
+
+        for tag in CAMERA_TAGS {
+            exif.metadata.insert(tag.to_string(), "x".to_string());
+        }
+        assert!(missing_metadata("image/jpeg", ContentClass::Photo, Some(&exif)).is_none());
+    }
 }
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// Runs an external analyzer and reads its findings back as JSON, so detectors can be
+// added without touching this crate. Process plugins are executables; WebAssembly plugins
+// (built with the `wasm` feature) run sandboxed in-process, see wasm_plugin.rs.
+//
+// An executable gets the file path as its last argument, or the file bytes on stdin, and
+// prints a single JSON object to stdout:
+//
+//   {"suspicious": true,
+//    "findings": [{"description": "Acme watermark", "offset": 4096, "confidence": 0.9}],
+//    "details": {"anything": "else"}}
+//
+// A non-zero exit status is a failure; stderr is kept for the error message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginSpec {
+    pub name: String,
+    #[serde(default)]
+    pub kind: PluginKind,
+    // Executable, or the .wasm/.wat module for WebAssembly plugins
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub input: PluginInput,
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind {
+    #[default]
+    Process,
+    // Always receives the file bytes; `args` and `input` are ignored
+    Wasm,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginInput {
+    // File path appended to the arguments
+    #[default]
+    Path,
+    // File bytes written to stdin; STEGASCAN_FILE_NAME carries the original name
+    Stdin,
+}
+
+fn default_timeout() -> u64 {
+    60
+}
+
+#[derive(Debug)]
+pub enum PluginError {
+    IO(std::io::Error),
+    InvalidSpec(String),
+    Spawn(String, std::io::Error),
+    Timeout(String, u64),
+    Failed(String, Option<i32>, String),
+    InvalidOutput(String, String),
+    Wasm(String, String),
+    // A WebAssembly plugin was configured but the `wasm` feature is off
+    WasmUnsupported(String),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::IO(e) => write!(f, "IO error: {}", e),
+            PluginError::InvalidSpec(e) => write!(f, "Invalid plugin definitions: {}", e),
+            PluginError::Spawn(name, e) => write!(f, "Failed to start plugin {}: {}", name, e),
+            PluginError::Timeout(name, secs) => {
+                write!(f, "Plugin {} did not finish within {}s", name, secs)
+            }
+            PluginError::Failed(name, status, stderr) => match status {
+                Some(code) => write!(f, "Plugin {} exited with {}: {}", name, code, stderr),
+                None => write!(f, "Plugin {} was killed: {}", name, stderr),
+            },
+            PluginError::InvalidOutput(name, e) => {
+                write!(f, "Plugin {} printed invalid JSON: {}", name, e)
+            }
+            PluginError::Wasm(name, e) => write!(f, "WebAssembly plugin {} failed: {}", name, e),
+            PluginError::WasmUnsupported(name) => write!(
+                f,
+                "Plugin {} is a WebAssembly module, rebuild with the `wasm` feature to run it",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+impl From<std::io::Error> for PluginError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginOutput {
+    pub suspicious: bool,
+    pub findings: Vec<PluginFinding>,
+    // Free-form plugin data, passed through to the report untouched
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginFinding {
+    pub description: String,
+    pub offset: Option<usize>,
+    pub confidence: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct PluginFile {
+    plugins: Vec<PluginSpec>,
+}
+
+impl PluginSpec {
+    // Load definitions from a YAML file, or JSON when the extension is .json:
+    //
+    //   plugins:
+    //     - name: acme-watermark
+    //       command: /opt/acme/detect
+    //       args: ["--json"]
+    //       input: stdin
+    //       timeout_secs: 30
+    pub fn load(path: &Path) -> Result<Vec<Self>, PluginError> {
+        let text = std::fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let file: PluginFile = if is_json {
+            serde_json::from_str(&text).map_err(|e| PluginError::InvalidSpec(e.to_string()))?
+        } else {
+            serde_yaml::from_str(&text).map_err(|e| PluginError::InvalidSpec(e.to_string()))?
+        };
+        Ok(file.plugins)
+    }
+
+    pub fn run(&self, file_path: &Path) -> Result<PluginOutput, PluginError> {
+        match self.kind {
+            PluginKind::Process => self.run_process(file_path),
+            #[cfg(feature = "wasm")]
+            PluginKind::Wasm => crate::wasm_plugin::run(self, file_path),
+            #[cfg(not(feature = "wasm"))]
+            PluginKind::Wasm => Err(PluginError::WasmUnsupported(self.name.clone())),
+        }
+    }
+
+    fn run_process(&self, file_path: &Path) -> Result<PluginOutput, PluginError> {
+        let mut command = Command::new(&self.command);
+        command
+            .args(&self.args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let input = match self.input {
+            PluginInput::Path => {
+                command.arg(file_path).stdin(Stdio::null());
+                None
+            }
+            PluginInput::Stdin => {
+                if let Some(name) = file_path.file_name() {
+                    command.env("STEGASCAN_FILE_NAME", name);
+                }
+                command.stdin(Stdio::piped());
+                Some(std::fs::read(file_path)?)
+            }
+        };
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| PluginError::Spawn(self.name.clone(), e))?;
+
+        // The pipes are drained on their own threads so a chatty plugin can't block on a
+        // full pipe while we wait for it to exit
+        let stdin = child.stdin.take();
+        let writer = std::thread::spawn(move || {
+            if let (Some(mut stdin), Some(data)) = (stdin, input) {
+                // A plugin that exits without reading everything closes the pipe early
+                let _ = stdin.write_all(&data);
+            }
+        });
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let reader = std::thread::spawn(move || {
+            let mut out = Vec::new();
+            stdout.read_to_end(&mut out).map(|_| out)
+        });
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let err_reader = std::thread::spawn(move || {
+            let mut err = String::new();
+            let _ = stderr.read_to_string(&mut err);
+            err
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(PluginError::Timeout(self.name.clone(), self.timeout_secs));
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        let _ = writer.join();
+        let stdout = reader.join().unwrap_or_else(|_| Ok(Vec::new()))?;
+        let stderr = err_reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(PluginError::Failed(
+                self.name.clone(),
+                status.code(),
+                stderr.trim().to_string(),
+            ));
+        }
+
+        serde_json::from_slice(&stdout)
+            .map_err(|e| PluginError::InvalidOutput(self.name.clone(), e.to_string()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell_plugin(script: &str, input: PluginInput, timeout_secs: u64) -> PluginSpec {
+        PluginSpec {
+            name: "test".to_string(),
+            kind: PluginKind::Process,
+            command: PathBuf::from("sh"),
+            args: vec!["-c".to_string(), script.to_string()],
+            input,
+            timeout_secs,
+        }
+    }
+
+    #[test]
+    fn test_plugin_protocol() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"0123456789").unwrap();
+
+        // Reports the byte count it received on stdin
+        let plugin = shell_plugin(
+            r#"n=$(wc -c | tr -d ' '); echo "{\"suspicious\": true, \"findings\": [{\"description\": \"$n bytes\", \"offset\": 4}]}""#,
+            PluginInput::Stdin,
+            10,
+        );
+        let output = plugin.run(file.path()).unwrap();
+        assert!(output.suspicious);
+        assert_eq!(output.findings[0].description, "10 bytes");
+        assert_eq!(output.findings[0].offset, Some(4));
+
+        // With path input `sh -c` sees the file path as $0
+        let plugin = shell_plugin(r#"test -f "$0" && echo '{}'"#, PluginInput::Path, 10);
+        let output = plugin.run(file.path()).unwrap();
+        assert!(!output.suspicious);
+        assert!(output.findings.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_failures() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let failing = shell_plugin("echo broken >&2; exit 3", PluginInput::Path, 10);
+        assert!(matches!(
+            failing.run(file.path()),
+            Err(PluginError::Failed(_, Some(3), ref stderr)) if stderr == "broken"
+        ));
+
+        let garbage = shell_plugin("echo not json", PluginInput::Path, 10);
+        assert!(matches!(
+            garbage.run(file.path()),
+            Err(PluginError::InvalidOutput(..))
+        ));
+
+        let slow = shell_plugin("sleep 5", PluginInput::Path, 1);
+        assert!(matches!(
+            slow.run(file.path()),
+            Err(PluginError::Timeout(_, 1))
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::video_frame_analyzer::{VideoFrameAnalysis, VideoFrameAnalyzerError, analyze_frame};
+use image::RgbaImage;
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+pub type FrameResult = Result<VideoFrameAnalysis, VideoFrameAnalyzerError>;
+
+// Takes frames back once they are analyzed, typically to hand their buffers to the decoder
+pub type FrameRecycler = Arc<dyn Fn(RgbaImage) + Send + Sync>;
+
+// CPU time of the calling thread, read before and after each analysis so a caller metering
+// its own thread can also count the work done on the pool
+pub type CpuClock = fn() -> Duration;
+
+// Analyzes sampled video frames on the rayon pool while the caller keeps decoding, and hands
+// the analyses back in the order the frames were submitted. Decoding is sequential by nature
+// but each frame's analysis stands alone, so this keeps every core busy instead of one.
+pub struct FramePipeline {
+    sender: Sender<(usize, FrameResult)>,
+    receiver: Receiver<(usize, FrameResult)>,
+    // Frames allowed to be queued or under analysis at once; submitting waits beyond that so
+    // a decoder faster than the pool doesn't pile decoded frames up in memory
+    max_in_flight: usize,
+    in_flight: usize,
+    // Submitted frame indices not handed back yet, oldest first
+    order: VecDeque<usize>,
+    // Analyses that finished ahead of an earlier frame
+    finished: BTreeMap<usize, FrameResult>,
+    recycler: Option<FrameRecycler>,
+    cpu_clock: Option<CpuClock>,
+    // Nanoseconds of CPU time the analyses took, by `cpu_clock`
+    cpu_nanos: Arc<AtomicU64>,
+}
+
+impl Default for FramePipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePipeline {
+    pub fn new() -> Self {
+        Self::with_max_in_flight(2 * rayon::current_num_threads())
+    }
+
+    pub fn with_max_in_flight(max_in_flight: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            max_in_flight: max_in_flight.max(1),
+            in_flight: 0,
+            order: VecDeque::new(),
+            finished: BTreeMap::new(),
+            recycler: None,
+            cpu_clock: None,
+            cpu_nanos: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn with_recycler(mut self, recycler: FrameRecycler) -> Self {
+        self.recycler = Some(recycler);
+        self
+    }
+
+    pub fn with_cpu_clock(mut self, clock: CpuClock) -> Self {
+        self.cpu_clock = Some(clock);
+        self
+    }
+
+    // CPU time the analyses finished so far took; zero without a clock
+    pub fn cpu_time(&self) -> Duration {
+        Duration::from_nanos(self.cpu_nanos.load(Ordering::Relaxed))
+    }
+
+    pub fn submit(&mut self, index: usize, frame: RgbaImage) {
+        while self.in_flight >= self.max_in_flight {
+            self.receive();
+        }
+
+        let sender = self.sender.clone();
+        let recycler = self.recycler.clone();
+        let (clock, cpu_nanos) = (self.cpu_clock, self.cpu_nanos.clone());
+        rayon::spawn(move || {
+            let started = clock.map(|clock| clock());
+            // A panicking analysis would otherwise take the pool down and never report back
+            let result = catch_unwind(AssertUnwindSafe(|| analyze_frame(&frame)))
+                .map(|mut analysis| {
+                    analysis.frame_index = index;
+                    analysis
+                })
+                .map_err(|_| {
+                    VideoFrameAnalyzerError::FrameProcessing("analysis panicked".to_string())
+                });
+            if let Some(recycle) = recycler {
+                recycle(frame);
+            }
+            if let (Some(clock), Some(started)) = (clock, started) {
+                let spent = clock().saturating_sub(started);
+                cpu_nanos.fetch_add(spent.as_nanos() as u64, Ordering::Relaxed);
+            }
+            let _ = sender.send((index, result));
+        });
+        self.in_flight += 1;
+        self.order.push_back(index);
+    }
+
+    // The analyses that are next in order and done. With `wait` set this first waits for
+    // every submitted frame, so nothing is left behind after the last one.
+    pub fn ready(&mut self, wait: bool) -> Vec<(usize, FrameResult)> {
+        if wait {
+            while self.in_flight > 0 {
+                self.receive();
+            }
+        } else {
+            while let Ok((index, result)) = self.receiver.try_recv() {
+                self.in_flight -= 1;
+                self.finished.insert(index, result);
+            }
+        }
+
+        let mut ready = Vec::new();
+        while let Some(&index) = self.order.front() {
+            let Some(result) = self.finished.remove(&index) else {
+                break;
+            };
+            self.order.pop_front();
+            ready.push((index, result));
+        }
+        ready
+    }
+
+    fn receive(&mut self) {
+        // The pipeline holds a sender itself, so this only fails if the channel is torn down
+        if let Ok((index, result)) = self.receiver.recv() {
+            self.in_flight -= 1;
+            self.finished.insert(index, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_results_come_back_in_order() {
+        let recycled = Arc::new(AtomicUsize::new(0));
+        let counter = recycled.clone();
+        let mut pipeline =
+            FramePipeline::with_max_in_flight(3).with_recycler(Arc::new(move |_frame| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }));
+        let mut indices = Vec::new();
+        for index in (0..40).step_by(3) {
+            // Frames of varying size, so analyses finish out of order
+            let side = 8 + (index as u32 * 7) % 50;
+            let frame = RgbaImage::from_fn(side, side, |x, y| {
+                Rgba([(x * y) as u8, index as u8, (x + y) as u8, 255])
+            });
+            pipeline.submit(index, frame);
+            indices.extend(pipeline.ready(false).into_iter().map(|(i, _)| i));
+        }
+        let rest = pipeline.ready(true);
+        assert!(rest.iter().all(|(i, result)| {
+            result
+                .as_ref()
+                .is_ok_and(|analysis| analysis.frame_index == *i)
+        }));
+        indices.extend(rest.into_iter().map(|(i, _)| i));
+
+        assert_eq!(indices, (0..40).step_by(3).collect::<Vec<_>>());
+        assert!(pipeline.ready(true).is_empty());
+        assert_eq!(recycled.load(Ordering::Relaxed), indices.len());
+    }
+
+    #[test]
+    fn test_analysis_cpu_time_is_counted() {
+        // A clock that moves a millisecond every time it is read
+        static TICKS: AtomicU64 = AtomicU64::new(0);
+        fn clock() -> Duration {
+            Duration::from_millis(TICKS.fetch_add(1, Ordering::Relaxed))
+        }
+
+        let mut pipeline = FramePipeline::with_max_in_flight(2).with_cpu_clock(clock);
+        for index in 0..5 {
+            pipeline.submit(index, RgbaImage::new(8, 8));
+        }
+        assert_eq!(pipeline.ready(true).len(), 5);
+        assert!(pipeline.cpu_time() >= Duration::from_millis(5));
+        assert_eq!(FramePipeline::new().cpu_time(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,245 @@
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Picks which decoded video frames get the full per-frame analysis. A fixed stride can line up
+// with a payload that was embedded on the same stride and miss every carrier frame, so the
+// alternatives sample pseudo-randomly from a seed, after scene cuts, or at fixed time
+// intervals. Every strategy is deterministic: the same file and parameters always give the
+// same frames.
+
+// Half the L1 distance between normalized luma histograms of consecutive frames above which
+// a cut is assumed
+pub const SCENE_CHANGE_THRESHOLD: f64 = 0.3;
+
+const HISTOGRAM_BINS: usize = 32;
+// Only every Nth pixel goes into the histogram, which is plenty for cut detection
+const HISTOGRAM_PIXEL_STRIDE: usize = 7;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingMode {
+    // Every Nth frame
+    #[default]
+    Stride,
+    // Each frame with probability 1/N from a seeded generator
+    Random,
+    // The first frame of every scene
+    Scene,
+    // One frame per fixed interval of presentation time
+    Time,
+}
+
+impl FromStr for SamplingMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "stride" => Ok(Self::Stride),
+            "random" => Ok(Self::Random),
+            "scene" => Ok(Self::Scene),
+            "time" => Ok(Self::Time),
+            other => Err(format!(
+                "unknown sampling mode {:?} (expected stride, random, scene or time)",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for SamplingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SamplingMode::Stride => write!(f, "stride"),
+            SamplingMode::Random => write!(f, "random"),
+            SamplingMode::Scene => write!(f, "scene"),
+            SamplingMode::Time => write!(f, "time"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplingStrategy {
+    EveryNth(usize),
+    Random { one_in: usize, seed: u64 },
+    SceneChange { threshold: f64 },
+    UniformTime { interval_seconds: f64 },
+}
+
+impl Display for SamplingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SamplingStrategy::EveryNth(n) => write!(f, "one frame in every {}", n),
+            SamplingStrategy::Random { one_in, seed } => {
+                write!(f, "1 in {} frames at random, seed {}", one_in, seed)
+            }
+            SamplingStrategy::SceneChange { threshold } => {
+                write!(f, "first frame of each scene, cut threshold {}", threshold)
+            }
+            SamplingStrategy::UniformTime { interval_seconds } => {
+                write!(f, "one frame every {} s", interval_seconds)
+            }
+        }
+    }
+}
+
+pub struct FrameSampler {
+    strategy: SamplingStrategy,
+    previous_histogram: Option<[f64; HISTOGRAM_BINS]>,
+    next_time: f64,
+}
+
+impl FrameSampler {
+    pub fn new(strategy: SamplingStrategy) -> Self {
+        Self {
+            strategy,
+            previous_histogram: None,
+            next_time: 0.0,
+        }
+    }
+
+    // Whether the frame at `index` should be analyzed. Frames have to be offered in decode
+    // order, and every frame has to be offered for scene changes to be seen. Without a
+    // timestamp, time-based sampling takes the frame rather than risk skipping it.
+    pub fn sample(&mut self, index: usize, timestamp: Option<f64>, frame: &RgbaImage) -> bool {
+        match self.strategy {
+            SamplingStrategy::EveryNth(n) => index.is_multiple_of(n.max(1)),
+            SamplingStrategy::Random { one_in, seed } => {
+                splitmix64(seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+                    .is_multiple_of(one_in.max(1) as u64)
+            }
+            SamplingStrategy::SceneChange { threshold } => {
+                let histogram = luma_histogram(frame);
+                let cut = self
+                    .previous_histogram
+                    .is_none_or(|previous| histogram_distance(&previous, &histogram) > threshold);
+                self.previous_histogram = Some(histogram);
+                cut
+            }
+            SamplingStrategy::UniformTime { interval_seconds } => {
+                let Some(time) = timestamp else {
+                    return true;
+                };
+                // A small tolerance absorbs timestamp rounding right on an interval boundary
+                if time + 1e-6 < self.next_time {
+                    return false;
+                }
+                let interval = interval_seconds.max(1e-3);
+                self.next_time = ((time + 1e-6) / interval).floor() * interval + interval;
+                true
+            }
+        }
+    }
+}
+
+// Normalized luma histogram over a subsample of the pixels
+pub fn luma_histogram(frame: &RgbaImage) -> [f64; HISTOGRAM_BINS] {
+    let mut histogram = [0.0f64; HISTOGRAM_BINS];
+    let mut total = 0.0;
+    for pixel in frame.pixels().step_by(HISTOGRAM_PIXEL_STRIDE) {
+        let luma = 0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64;
+        let bin = (luma as usize * HISTOGRAM_BINS / 256).min(HISTOGRAM_BINS - 1);
+        histogram[bin] += 1.0;
+        total += 1.0;
+    }
+    if total > 0.0 {
+        histogram.iter_mut().for_each(|count| *count /= total);
+    }
+    histogram
+}
+
+// 0 for identical histograms, 1 for histograms with no overlap
+pub fn histogram_distance(a: &[f64; HISTOGRAM_BINS], b: &[f64; HISTOGRAM_BINS]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f64>() / 2.0
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(16, 16, Rgba([value, value, value, 255]))
+    }
+
+    fn sampled(strategy: SamplingStrategy, frames: &[(RgbaImage, Option<f64>)]) -> Vec<usize> {
+        let mut sampler = FrameSampler::new(strategy);
+        frames
+            .iter()
+            .enumerate()
+            .filter(|(index, (frame, time))| sampler.sample(*index, *time, frame))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    #[test]
+    fn test_random_sampling_is_seeded() {
+        let frames: Vec<_> = (0..600).map(|_| (solid(0), None)).collect();
+        let first = sampled(
+            SamplingStrategy::Random {
+                one_in: 30,
+                seed: 7,
+            },
+            &frames,
+        );
+        let again = sampled(
+            SamplingStrategy::Random {
+                one_in: 30,
+                seed: 7,
+            },
+            &frames,
+        );
+        let other = sampled(
+            SamplingStrategy::Random {
+                one_in: 30,
+                seed: 8,
+            },
+            &frames,
+        );
+
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        // Roughly 1 in 30, and not on a fixed stride
+        assert!((8..=35).contains(&first.len()), "{}", first.len());
+        assert!(first.windows(2).any(|w| w[1] - w[0] != 30));
+    }
+
+    #[test]
+    fn test_scene_and_time_sampling() {
+        // Three scenes of 10 frames at 25 fps
+        let frames: Vec<_> = (0..30)
+            .map(|i| (solid([20, 200, 90][i / 10]), Some(i as f64 / 25.0)))
+            .collect();
+
+        assert_eq!(
+            sampled(
+                SamplingStrategy::SceneChange {
+                    threshold: SCENE_CHANGE_THRESHOLD
+                },
+                &frames
+            ),
+            vec![0, 10, 20]
+        );
+        assert_eq!(
+            sampled(
+                SamplingStrategy::UniformTime {
+                    interval_seconds: 0.4
+                },
+                &frames
+            ),
+            vec![0, 10, 20]
+        );
+        assert_eq!(
+            sampled(SamplingStrategy::EveryNth(12), &frames),
+            vec![0, 12, 24]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Walks the block structure of a GIF and pulls out what decoders skip: comment extensions,
+// application extensions other than the animation loop count, plain text and unknown
+// extensions, and anything after the trailer. Comment blocks are a common place to stuff a
+// payload since nothing ever displays them.
+pub struct GifExtensionAnalyzer;
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+const PLAIN_TEXT_LABEL: u8 = 0x01;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+const COMMENT_LABEL: u8 = 0xFE;
+const APPLICATION_LABEL: u8 = 0xFF;
+
+// Application identifier plus authentication code, as written by common encoders
+const KNOWN_APPLICATIONS: [&str; 6] = [
+    "NETSCAPE2.0",
+    "ANIMEXTS1.0",
+    "XMP DataXMP",
+    "ICCRGBG1012",
+    "MGK8BIM0000",
+    "MGKIPTC0000",
+];
+
+// Loop count extensions carry a 3-byte sub-block, the buffering variant 5
+const MAX_LOOP_EXTENSION_SIZE: usize = 5;
+
+// Shortest comment decoded as base64; shorter runs are too often ordinary words
+const MIN_BASE64_COMMENT: usize = 16;
+
+// Characters of a comment kept for display
+const PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug)]
+pub enum GifExtensionError {
+    NotGif,
+}
+
+impl Display for GifExtensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GifExtensionError::NotGif => write!(f, "Not a GIF87a/GIF89a file"),
+        }
+    }
+}
+
+impl std::error::Error for GifExtensionError {}
+
+#[derive(Debug, Clone)]
+pub struct GifComment {
+    pub offset: usize,
+    pub size: usize,
+    // Start of the text, control characters escaped
+    pub preview: String,
+    // The comment was base64 and `decoded` holds the decoded bytes
+    pub base64: bool,
+    pub decoded: Vec<u8>,
+    // MIME type of the decoded bytes, or "text"/"binary" when unrecognized
+    pub classification: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GifApplication {
+    pub offset: usize,
+    // Identifier and authentication code, e.g. NETSCAPE2.0
+    pub identifier: String,
+    pub size: usize,
+    pub known: bool,
+    pub data: Vec<u8>,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GifExtensionAnalysis {
+    pub version: String,
+    pub frame_count: usize,
+    pub comments: Vec<GifComment>,
+    pub applications: Vec<GifApplication>,
+    pub plain_text_blocks: usize,
+    // Bytes after the trailer
+    pub trailing_bytes: usize,
+    pub anomalies: Vec<String>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for GifExtensionAnalyzer {
+    type Input = Vec<u8>;
+    type Output = GifExtensionAnalysis;
+    type Error = GifExtensionError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if input.len() < 13 || (&input[0..6] != b"GIF87a" && &input[0..6] != b"GIF89a") {
+            return Err(GifExtensionError::NotGif);
+        }
+
+        let mut analysis = GifExtensionAnalysis {
+            version: String::from_utf8_lossy(&input[3..6]).to_string(),
+            frame_count: 0,
+            comments: Vec::new(),
+            applications: Vec::new(),
+            plain_text_blocks: 0,
+            trailing_bytes: 0,
+            anomalies: Vec::new(),
+            suspicious: false,
+        };
+
+        // Logical screen descriptor, then the global color table if the flags announce one
+        let mut offset = 13 + color_table_size(input[10]);
+        let mut found_trailer = false;
+
+        while offset < input.len() {
+            let block = offset;
+            match input[offset] {
+                EXTENSION_INTRODUCER => {
+                    let Some(&label) = input.get(offset + 1) else {
+                        analysis
+                            .anomalies
+                            .push(format!("Truncated extension at offset {}", block));
+                        break;
+                    };
+                    let Some((data, end)) = read_sub_blocks(&input, offset + 2) else {
+                        analysis.anomalies.push(format!(
+                            "Extension 0x{:02X} at offset {} runs past the end of the file",
+                            label, block
+                        ));
+                        break;
+                    };
+                    offset = end;
+                    match label {
+                        COMMENT_LABEL => analysis.comments.push(comment(block, data)),
+                        APPLICATION_LABEL => {
+                            analysis.applications.push(application(block, data));
+                        }
+                        PLAIN_TEXT_LABEL => analysis.plain_text_blocks += 1,
+                        GRAPHIC_CONTROL_LABEL => {}
+                        _ => analysis.anomalies.push(format!(
+                            "Unknown extension 0x{:02X} at offset {} ({} bytes)",
+                            label,
+                            block,
+                            data.len()
+                        )),
+                    }
+                }
+                IMAGE_SEPARATOR => {
+                    // Descriptor, optional local color table, LZW code size, image data
+                    let Some(&flags) = input.get(offset + 9) else {
+                        analysis
+                            .anomalies
+                            .push(format!("Truncated image descriptor at offset {}", block));
+                        break;
+                    };
+                    let data_start = offset + 10 + color_table_size(flags) + 1;
+                    let Some((_, end)) = read_sub_blocks(&input, data_start) else {
+                        analysis.anomalies.push(format!(
+                            "Image data at offset {} runs past the end of the file",
+                            block
+                        ));
+                        break;
+                    };
+                    analysis.frame_count += 1;
+                    offset = end;
+                }
+                TRAILER => {
+                    found_trailer = true;
+                    offset += 1;
+                    break;
+                }
+                other => {
+                    analysis.anomalies.push(format!(
+                        "Unexpected byte 0x{:02X} at offset {} where a block should start",
+                        other, block
+                    ));
+                    break;
+                }
+            }
+        }
+
+        if found_trailer {
+            analysis.trailing_bytes = input.len() - offset;
+            if analysis.trailing_bytes > 0 {
+                analysis.anomalies.push(format!(
+                    "{} bytes after the GIF trailer at offset {}",
+                    analysis.trailing_bytes, offset
+                ));
+            }
+        } else if analysis.anomalies.is_empty() {
+            analysis
+                .anomalies
+                .push("File ends without a GIF trailer".to_string());
+        }
+
+        for comment in &analysis.comments {
+            if comment.base64 {
+                analysis.anomalies.push(format!(
+                    "Comment at offset {} holds base64 decoding to {} bytes ({})",
+                    comment.offset,
+                    comment.decoded.len(),
+                    comment.classification
+                ));
+            } else if comment.classification != "text" {
+                analysis.anomalies.push(format!(
+                    "Comment at offset {} holds {} bytes of {} data",
+                    comment.offset, comment.size, comment.classification
+                ));
+            }
+        }
+        for app in &analysis.applications {
+            if !app.known {
+                analysis.anomalies.push(format!(
+                    "Unknown application extension '{}' at offset {} ({} bytes, {})",
+                    app.identifier.escape_debug(),
+                    app.offset,
+                    app.size,
+                    app.classification
+                ));
+            } else if app.identifier == "NETSCAPE2.0" && app.size > MAX_LOOP_EXTENSION_SIZE {
+                analysis.anomalies.push(format!(
+                    "NETSCAPE2.0 extension at offset {} carries {} bytes, a loop count needs {}",
+                    app.offset, app.size, MAX_LOOP_EXTENSION_SIZE
+                ));
+            }
+        }
+
+        analysis.suspicious = !analysis.anomalies.is_empty();
+        Ok(analysis)
+    }
+}
+
+// Packed flags: bit 7 announces a color table of 2^(n+1) RGB entries, n in the low 3 bits
+fn color_table_size(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 * (1 << ((flags & 0x07) + 1))
+    }
+}
+
+// Data sub-blocks are a length byte and that many bytes, ended by a zero length. Returns the
+// concatenated data and the offset after the terminator.
+fn read_sub_blocks(data: &[u8], mut offset: usize) -> Option<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    loop {
+        let len = *data.get(offset)? as usize;
+        offset += 1;
+        if len == 0 {
+            return Some((out, offset));
+        }
+        out.extend_from_slice(data.get(offset..offset + len)?);
+        offset += len;
+    }
+}
+
+fn comment(offset: usize, data: Vec<u8>) -> GifComment {
+    use base64::Engine;
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+
+    let text = String::from_utf8_lossy(&data);
+    let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    let preview = preview.escape_debug().to_string();
+
+    let trimmed = text.trim();
+    let decoded = (trimmed.len() >= MIN_BASE64_COMMENT)
+        .then(|| {
+            STANDARD
+                .decode(trimmed)
+                .or_else(|_| STANDARD_NO_PAD.decode(trimmed.trim_end_matches('=')))
+                .ok()
+        })
+        .flatten();
+
+    match decoded {
+        Some(decoded) => GifComment {
+            offset,
+            size: data.len(),
+            preview,
+            base64: true,
+            classification: classify_payload(&decoded),
+            decoded,
+        },
+        None => GifComment {
+            offset,
+            size: data.len(),
+            preview,
+            base64: false,
+            classification: classify_payload(&data),
+            decoded: data,
+        },
+    }
+}
+
+// The first sub-block is the 8-byte identifier and 3-byte authentication code
+fn application(offset: usize, data: Vec<u8>) -> GifApplication {
+    let header = data.len().min(11);
+    let identifier = String::from_utf8_lossy(&data[..header]).to_string();
+    let data = data[header..].to_vec();
+    GifApplication {
+        offset,
+        known: KNOWN_APPLICATIONS.contains(&identifier.as_str()),
+        identifier,
+        size: data.len(),
+        classification: classify_payload(&data),
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub_blocks(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in data.chunks(255) {
+            out.push(chunk.len() as u8);
+            out.extend_from_slice(chunk);
+        }
+        out.push(0);
+        out
+    }
+
+    fn extension(label: u8, data: &[u8]) -> Vec<u8> {
+        [vec![EXTENSION_INTRODUCER, label], sub_blocks(data)].concat()
+    }
+
+    // 1x1 GIF89a with a 2-entry global color table and the given blocks before the image
+    fn gif(blocks: &[Vec<u8>], trailing: &[u8]) -> Vec<u8> {
+        let mut out = b"GIF89a".to_vec();
+        out.extend_from_slice(&[1, 0, 1, 0, 0x80, 0, 0]);
+        out.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        for block in blocks {
+            out.extend_from_slice(block);
+        }
+        out.extend_from_slice(&[IMAGE_SEPARATOR, 0, 0, 0, 0, 1, 0, 1, 0, 0]);
+        out.push(2);
+        out.extend_from_slice(&sub_blocks(&[0x44, 0x01]));
+        out.push(TRAILER);
+        out.extend_from_slice(trailing);
+        out
+    }
+
+    #[test]
+    fn test_clean_animation() {
+        let netscape = [b"NETSCAPE2.0".as_slice(), &[1, 0, 0]].concat();
+        let analysis = GifExtensionAnalyzer::analyze(gif(
+            &[
+                extension(APPLICATION_LABEL, &netscape),
+                extension(COMMENT_LABEL, b"Made with an editor"),
+                extension(GRAPHIC_CONTROL_LABEL, &[0, 10, 0, 0]),
+            ],
+            &[],
+        ))
+        .unwrap();
+
+        assert_eq!(analysis.version, "89a");
+        assert_eq!(analysis.frame_count, 1);
+        assert_eq!(analysis.comments.len(), 1);
+        assert_eq!(analysis.comments[0].classification, "text");
+        assert!(analysis.applications[0].known);
+        assert!(!analysis.suspicious, "{:?}", analysis.anomalies);
+    }
+
+    #[test]
+    fn test_payloads_in_extensions() {
+        let zip = b"PK\x03\x04\x14\x00\x00\x00\x08\x00secret payload";
+        let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, zip);
+        let long_comment = b"frame ".repeat(100);
+        let analysis = GifExtensionAnalyzer::analyze(gif(
+            &[
+                extension(COMMENT_LABEL, encoded.as_bytes()),
+                extension(COMMENT_LABEL, &long_comment),
+                extension(APPLICATION_LABEL, b"STEGTOOL1.0hidden bytes"),
+            ],
+            b"appended",
+        ))
+        .unwrap();
+
+        assert!(analysis.comments[0].base64);
+        assert_eq!(analysis.comments[0].decoded, zip);
+        assert_eq!(analysis.comments[0].classification, "application/zip");
+        // Comments span several sub-blocks
+        assert_eq!(analysis.comments[1].size, 600);
+        assert_eq!(analysis.applications[0].identifier, "STEGTOOL1.0");
+        assert_eq!(analysis.applications[0].data, b"hidden bytes");
+        assert!(!analysis.applications[0].known);
+        assert_eq!(analysis.trailing_bytes, 8);
+        assert!(analysis.suspicious);
+        assert_eq!(analysis.frame_count, 1);
+    }
+
+    #[test]
+    fn test_rejects_non_gif() {
+        assert!(GifExtensionAnalyzer::analyze(b"\x89PNG\r\n\x1a\n".to_vec()).is_err());
+    }
+}
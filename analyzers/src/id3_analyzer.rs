@@ -1,8 +1,12 @@
 use crate::Analyzer;
+use crate::slack_space_analyzer::mpeg_frame_len;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::Path;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
 pub struct Id3Analyzer;
 
 #[derive(Debug)]
@@ -40,6 +44,62 @@ pub struct Id3Data {
     pub suspicious_frames: Vec<String>,
     pub pictures: Vec<PictureInfo>,
     pub private_frames: Vec<PrivateFrame>,
+    pub chapters: Vec<ChapterInfo>,
+    pub tables_of_contents: Vec<TableOfContentsInfo>,
+    pub synced_lyrics_entries: usize,
+    // Encoded or binary content found in USLT/SYLT text
+    pub lyrics_payloads: Vec<LyricsPayload>,
+    // Podcast frame ID and its value
+    pub podcast_frames: Vec<(String, String)>,
+    // ID3v2 tags at the start of the file; readers only look at the first
+    pub v2_tags: Vec<Id3v2Header>,
+    pub v1_tag: Option<Id3v1Tag>,
+    // Data between the ID3v2 tags and the first audio frame
+    pub tag_gaps: Vec<TagGap>,
+    pub tag_mismatches: Vec<TagMismatch>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Id3v2Header {
+    pub offset: usize,
+    // Major version and revision: (4, 0) for ID3v2.4.0
+    pub version: (u8, u8),
+    // Header, frames, padding and footer
+    pub size: usize,
+}
+
+// The 128-byte tag at the end of the file, read as ID3v1.1 when the comment holds a track number
+#[derive(Debug, Clone)]
+pub struct Id3v1Tag {
+    pub offset: usize,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    pub track: Option<u8>,
+    pub genre: u8,
+    // The 227-byte "TAG+" extension in front of the tag
+    pub extended_offset: Option<usize>,
+    // Fields with bytes after their terminating NUL, and those bytes
+    pub hidden_bytes: Vec<(String, Vec<u8>)>,
+}
+
+// Bytes that are neither zero padding, an ID3v2 tag nor audio; no reader looks at them
+#[derive(Debug, Clone)]
+pub struct TagGap {
+    pub offset: usize,
+    pub data: Vec<u8>,
+    // MIME type of the bytes, or "text"/"binary" when unrecognized
+    pub classification: String,
+}
+
+// A field the ID3v1 tag and the ID3v2 tag disagree on
+#[derive(Debug, Clone)]
+pub struct TagMismatch {
+    pub field: String,
+    pub v1: String,
+    pub v2: String,
 }
 
 #[derive(Debug, Clone)]
@@ -48,6 +108,71 @@ pub struct PictureInfo {
     pub mime_type: String,
     pub description: String,
     pub data_size: usize,
+    // Where the image bytes sit in the file; None when the tag is unsynchronised or compressed
+    pub offset: Option<usize>,
+    pub data: Vec<u8>,
+}
+
+impl PictureInfo {
+    // File extension for saving the picture, from its MIME type
+    pub fn extension(&self) -> &str {
+        match self.mime_type.to_lowercase().as_str() {
+            "image/jpeg" | "image/jpg" => "jpg",
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/bmp" => "bmp",
+            "image/webp" => "webp",
+            "image/tiff" => "tiff",
+            _ => "bin",
+        }
+    }
+}
+
+// CHAP frame; its sub-frames (title, images, links) are parsed by the id3 crate but were
+// never looked at
+#[derive(Debug, Clone)]
+pub struct ChapterInfo {
+    pub element_id: String,
+    pub start_time_ms: u32,
+    pub end_time_ms: u32,
+    pub title: Option<String>,
+    pub sub_frames: Vec<String>,
+    pub urls: Vec<String>,
+    pub picture_count: usize,
+    // Size of the largest binary sub-frame (APIC, GEOB, PRIV or unknown)
+    pub largest_binary_bytes: usize,
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LyricsPayloadKind {
+    Base64,
+    // Control characters that have no place in lyrics text
+    Binary,
+}
+
+#[derive(Debug, Clone)]
+pub struct LyricsPayload {
+    // USLT or SYLT
+    pub frame: String,
+    // SYLT timestamp of the entry the payload starts in, in the frame's own unit
+    pub timestamp: Option<u32>,
+    pub kind: LyricsPayloadKind,
+    // Characters of lyrics text the payload spans
+    pub length: usize,
+    pub decoded: Vec<u8>,
+    // MIME type of the decoded bytes, or "text"/"binary" when unrecognized
+    pub classification: String,
+}
+
+// CTOC frame listing chapters or nested tables of contents
+#[derive(Debug, Clone)]
+pub struct TableOfContentsInfo {
+    pub element_id: String,
+    pub top_level: bool,
+    pub ordered: bool,
+    pub children: Vec<String>,
+    pub findings: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +195,15 @@ impl Id3Data {
             suspicious_frames: Vec::new(),
             pictures: Vec::new(),
             private_frames: Vec::new(),
+            chapters: Vec::new(),
+            tables_of_contents: Vec::new(),
+            synced_lyrics_entries: 0,
+            lyrics_payloads: Vec::new(),
+            podcast_frames: Vec::new(),
+            v2_tags: Vec::new(),
+            v1_tag: None,
+            tag_gaps: Vec::new(),
+            tag_mismatches: Vec::new(),
         }
     }
 }
@@ -80,6 +214,15 @@ impl Default for Id3Data {
     }
 }
 
+// Chapter images are normally small thumbnails; binary sub-frames above this are flagged
+pub const MAX_CHAPTER_BINARY_BYTES: usize = 256 * 1024;
+
+// Shortest unbroken base64 run in lyrics worth decoding; shorter runs are ordinary words
+pub const MIN_BASE64_RUN: usize = 24;
+
+// Podcast frames from the iTunes extension to ID3v2 (feed URL, episode ID, etc.)
+const PODCAST_FRAMES: [&str; 6] = ["PCST", "WFED", "TGID", "TDES", "TKWD", "TCAT"];
+
 pub struct Id3AnalyzerWithPath<'a> {
     path: &'a Path,
 }
@@ -92,8 +235,14 @@ impl<'a> Id3AnalyzerWithPath<'a> {
     pub fn analyze(&self) -> Result<Id3Data, Id3AnalyzerError> {
         use id3::{Tag, TagLike};
 
-        let tag = Tag::read_from_path(self.path)
-            .map_err(|e| Id3AnalyzerError::Id3Error(format!("{:?}", e)))?;
+        let file_data = std::fs::read(self.path)?;
+        let v1_tag = read_v1_tag(&file_data);
+        let tag = match Tag::read_from_path(self.path) {
+            Ok(tag) => tag,
+            // A file with only an ID3v1 tag is still read for it
+            Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) && v1_tag.is_some() => Tag::new(),
+            Err(e) => return Err(Id3AnalyzerError::Id3Error(format!("{:?}", e))),
+        };
 
         let mut id3_data = Id3Data::new();
 
@@ -126,8 +275,10 @@ impl<'a> Id3AnalyzerWithPath<'a> {
         }
 
         // Extract lyrics
-        if let Some(lyrics) = tag.lyrics().next() {
-            id3_data.lyrics = Some(lyrics.text.clone());
+        for lyrics in tag.lyrics() {
+            if id3_data.lyrics.is_none() {
+                id3_data.lyrics = Some(lyrics.text.clone());
+            }
 
             if lyrics.text.len() > 10000 {
                 id3_data.suspicious_frames.push(format!(
@@ -135,6 +286,51 @@ impl<'a> Id3AnalyzerWithPath<'a> {
                     lyrics.text.len()
                 ));
             }
+
+            id3_data
+                .lyrics_payloads
+                .extend(scan_lyrics_text("USLT", &lyrics.text, &[]));
+        }
+
+        // Synchronised lyrics (SYLT). Entries are scanned joined together, since a payload
+        // can be split across many short timed segments
+        for synced in tag.synchronised_lyrics() {
+            id3_data.synced_lyrics_entries += synced.content.len();
+
+            let mut text = String::new();
+            let mut starts = Vec::new();
+            for (timestamp, segment) in &synced.content {
+                starts.push((text.len(), *timestamp));
+                text.push_str(segment);
+            }
+            id3_data
+                .lyrics_payloads
+                .extend(scan_lyrics_text("SYLT", &text, &starts));
+
+            if synced.content.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+                id3_data
+                    .suspicious_frames
+                    .push("SYLT timestamps are out of order".to_string());
+            }
+        }
+
+        for payload in &id3_data.lyrics_payloads {
+            let location = match payload.timestamp {
+                Some(timestamp) => format!("{} entry at {}", payload.frame, timestamp),
+                None => payload.frame.clone(),
+            };
+            id3_data.suspicious_frames.push(match payload.kind {
+                LyricsPayloadKind::Base64 => format!(
+                    "{} contains base64 decoding to {} bytes ({})",
+                    location,
+                    payload.decoded.len(),
+                    payload.classification
+                ),
+                LyricsPayloadKind::Binary => format!(
+                    "{} contains {} characters of binary content ({})",
+                    location, payload.length, payload.classification
+                ),
+            });
         }
 
         // Extract pictures (APIC frames)
@@ -144,6 +340,8 @@ impl<'a> Id3AnalyzerWithPath<'a> {
                 mime_type: picture.mime_type.clone(),
                 description: picture.description.clone(),
                 data_size: picture.data.len(),
+                offset: find_subslice(&file_data, &picture.data),
+                data: picture.data.clone(),
             };
 
             // Check for suspicious picture sizes
@@ -179,12 +377,104 @@ impl<'a> Id3AnalyzerWithPath<'a> {
                 id3_data.private_frames.push(priv_info);
             }
 
+            if PODCAST_FRAMES.contains(&frame.id()) {
+                let (value, finding) = podcast_frame(frame);
+                if let Some(finding) = finding {
+                    id3_data.suspicious_frames.push(finding);
+                }
+                id3_data
+                    .podcast_frames
+                    .push((frame.id().to_string(), value));
+            }
+
             // Store all frames
             let frame_id = frame.id().to_string();
             let frame_value = format!("{:?}", frame.content());
             id3_data.all_frames.insert(frame_id, frame_value);
         }
 
+        // Chapters (CHAP) and tables of contents (CTOC)
+        for chapter in tag.chapters() {
+            let info = chapter_info(chapter);
+            for finding in &info.findings {
+                id3_data
+                    .suspicious_frames
+                    .push(format!("Chapter '{}': {}", info.element_id, finding));
+            }
+            id3_data.chapters.push(info);
+        }
+        let element_ids: Vec<&str> = tag
+            .chapters()
+            .map(|c| c.element_id.as_str())
+            .chain(tag.tables_of_contents().map(|t| t.element_id.as_str()))
+            .collect();
+        for toc in tag.tables_of_contents() {
+            let info = table_of_contents_info(toc, &element_ids);
+            for finding in &info.findings {
+                id3_data.suspicious_frames.push(format!(
+                    "Table of contents '{}': {}",
+                    info.element_id, finding
+                ));
+            }
+            id3_data.tables_of_contents.push(info);
+        }
+
+        // The id3 crate reads the first ID3v2 tag and ignores everything around it
+        let (v2_tags, tag_gaps) = read_v2_layout(&file_data);
+        if v2_tags.len() > 1 {
+            let offsets: Vec<String> = v2_tags.iter().map(|t| t.offset.to_string()).collect();
+            id3_data.suspicious_frames.push(format!(
+                "{} ID3v2 tags stacked at offsets {}; readers only use the first",
+                v2_tags.len(),
+                offsets.join(", ")
+            ));
+        }
+        for gap in &tag_gaps {
+            id3_data.suspicious_frames.push(format!(
+                "{} bytes of {} at offset {} between the ID3v2 tags and the audio",
+                gap.data.len(),
+                gap.classification,
+                gap.offset
+            ));
+        }
+        if let Some(v1) = &v1_tag {
+            for (field, bytes) in &v1.hidden_bytes {
+                id3_data.suspicious_frames.push(format!(
+                    "ID3v1 {} hides {} bytes after its terminator",
+                    field,
+                    bytes.len()
+                ));
+            }
+            let comment = tag.comments().next().map(|c| c.text.clone());
+            let year = tag.year().map(|year| year.to_string());
+            id3_data.tag_mismatches = [
+                ("title", &v1.title, id3_data.title.as_ref(), 30),
+                ("artist", &v1.artist, id3_data.artist.as_ref(), 30),
+                ("album", &v1.album, id3_data.album.as_ref(), 30),
+                ("year", &v1.year, year.as_ref(), 4),
+                ("comment", &v1.comment, comment.as_ref(), 28),
+            ]
+            .into_iter()
+            .filter_map(|(field, v1, v2, width)| {
+                let v2 = v2?;
+                disagree(v1, v2, width).then(|| TagMismatch {
+                    field: field.to_string(),
+                    v1: v1.clone(),
+                    v2: v2.clone(),
+                })
+            })
+            .collect();
+            for mismatch in &id3_data.tag_mismatches {
+                id3_data.suspicious_frames.push(format!(
+                    "ID3v1 and ID3v2 {} disagree: {:?} vs {:?}",
+                    mismatch.field, mismatch.v1, mismatch.v2
+                ));
+            }
+        }
+        id3_data.v2_tags = v2_tags;
+        id3_data.tag_gaps = tag_gaps;
+        id3_data.v1_tag = v1_tag;
+
         Ok(id3_data)
     }
 }
@@ -201,6 +491,373 @@ impl Analyzer for Id3Analyzer {
     }
 }
 
+fn chapter_info(chapter: &id3::frame::Chapter) -> ChapterInfo {
+    use id3::Content;
+
+    let mut info = ChapterInfo {
+        element_id: chapter.element_id.clone(),
+        start_time_ms: chapter.start_time,
+        end_time_ms: chapter.end_time,
+        title: None,
+        sub_frames: Vec::new(),
+        urls: Vec::new(),
+        picture_count: 0,
+        largest_binary_bytes: 0,
+        findings: Vec::new(),
+    };
+
+    for frame in &chapter.frames {
+        info.sub_frames.push(frame.id().to_string());
+        match frame.content() {
+            Content::Text(text) if frame.id() == "TIT2" => info.title = Some(text.clone()),
+            Content::Link(url) => info.urls.push(url.clone()),
+            Content::ExtendedLink(link) => info.urls.push(link.link.clone()),
+            Content::Picture(_) => info.picture_count += 1,
+            _ => {}
+        }
+        if let Some(size) = binary_size(frame.content()) {
+            info.largest_binary_bytes = info.largest_binary_bytes.max(size);
+            if size > MAX_CHAPTER_BINARY_BYTES {
+                info.findings.push(format!(
+                    "Oversized {} sub-frame: {} bytes",
+                    frame.id(),
+                    size
+                ));
+            }
+        }
+    }
+
+    if info.picture_count > 0 {
+        info.findings
+            .push(format!("Embeds {} image(s)", info.picture_count));
+    }
+    if !info.urls.is_empty() {
+        info.findings
+            .push(format!("Links to {}", info.urls.join(", ")));
+    }
+    if chapter.end_time < chapter.start_time {
+        info.findings.push(format!(
+            "Ends before it starts ({} ms - {} ms)",
+            chapter.start_time, chapter.end_time
+        ));
+    }
+
+    info
+}
+
+fn table_of_contents_info(
+    toc: &id3::frame::TableOfContents,
+    element_ids: &[&str],
+) -> TableOfContentsInfo {
+    let mut findings = Vec::new();
+
+    for child in &toc.elements {
+        if !element_ids.contains(&child.as_str()) {
+            findings.push(format!("References missing element '{}'", child));
+        }
+    }
+    for frame in &toc.frames {
+        if let Some(size) = binary_size(frame.content())
+            && size > MAX_CHAPTER_BINARY_BYTES
+        {
+            findings.push(format!(
+                "Oversized {} sub-frame: {} bytes",
+                frame.id(),
+                size
+            ));
+        }
+    }
+
+    TableOfContentsInfo {
+        element_id: toc.element_id.clone(),
+        top_level: toc.top_level,
+        ordered: toc.ordered,
+        children: toc.elements.clone(),
+        findings,
+    }
+}
+
+// Payload size of frames that carry arbitrary bytes
+fn binary_size(content: &id3::Content) -> Option<usize> {
+    use id3::Content;
+
+    match content {
+        Content::Picture(picture) => Some(picture.data.len()),
+        Content::EncapsulatedObject(object) => Some(object.data.len()),
+        Content::Private(private) => Some(private.private_data.len()),
+        Content::Unknown(unknown) => Some(unknown.data.len()),
+        _ => None,
+    }
+}
+
+// Returns a display value for a podcast frame and a finding if it looks abused
+fn podcast_frame(frame: &id3::Frame) -> (String, Option<String>) {
+    use id3::Content;
+
+    match frame.content() {
+        Content::Text(text) | Content::Link(text) => {
+            let finding = (is_potential_base64(text) && text.len() > 50).then(|| {
+                format!(
+                    "Podcast frame {} contains potential encoded data",
+                    frame.id()
+                )
+            });
+            (text.clone(), finding)
+        }
+        // PCST is a 4-byte flag, normally 00 00 00 01
+        Content::Unknown(unknown) => {
+            let finding = (frame.id() == "PCST" && unknown.data.len() != 4).then(|| {
+                format!(
+                    "PCST frame carries {} bytes instead of a 4-byte flag",
+                    unknown.data.len()
+                )
+            });
+            (format!("{} bytes", unknown.data.len()), finding)
+        }
+        other => (other.to_string(), None),
+    }
+}
+
+// Finds decodable base64 runs and binary content in lyrics text. `starts` maps byte
+// offsets in `text` to SYLT timestamps and is empty for USLT.
+fn scan_lyrics_text(frame: &str, text: &str, starts: &[(usize, u32)]) -> Vec<LyricsPayload> {
+    use base64::Engine;
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+
+    let timestamp_at = |offset: usize| {
+        starts
+            .iter()
+            .take_while(|(start, _)| *start <= offset)
+            .last()
+            .map(|(_, timestamp)| *timestamp)
+    };
+    let mut payloads = Vec::new();
+
+    let is_base64_char = |b: u8| b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'=';
+    let bytes = text.as_bytes();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let run = bytes[offset..]
+            .iter()
+            .take_while(|&&b| is_base64_char(b))
+            .count();
+        if run >= MIN_BASE64_RUN {
+            let encoded = &text[offset..offset + run];
+            let decoded = STANDARD
+                .decode(encoded)
+                .or_else(|_| STANDARD_NO_PAD.decode(encoded.trim_end_matches('=')));
+            if let Ok(decoded) = decoded {
+                payloads.push(LyricsPayload {
+                    frame: frame.to_string(),
+                    timestamp: timestamp_at(offset),
+                    kind: LyricsPayloadKind::Base64,
+                    length: run,
+                    classification: classify_payload(&decoded),
+                    decoded,
+                });
+            }
+        }
+        offset += run.max(1);
+    }
+
+    // Binary data stored as text shows up as control characters or replacement characters
+    // from failed decoding
+    let binary_chars = text
+        .chars()
+        .filter(|&c| (c.is_control() && !c.is_whitespace()) || c == char::REPLACEMENT_CHARACTER)
+        .count();
+    if binary_chars >= 8 && binary_chars * 10 > text.chars().count() {
+        let first = text
+            .find(|c: char| {
+                (c.is_control() && !c.is_whitespace()) || c == char::REPLACEMENT_CHARACTER
+            })
+            .unwrap_or(0);
+        let decoded = text.as_bytes().to_vec();
+        payloads.push(LyricsPayload {
+            frame: frame.to_string(),
+            timestamp: timestamp_at(first),
+            kind: LyricsPayloadKind::Binary,
+            length: text.chars().count(),
+            classification: classify_payload(&decoded),
+            decoded,
+        });
+    }
+
+    payloads
+}
+
+// ID3v2 header at `offset`: "ID3", a version from 2.2 to 2.4, flags and a synchsafe size
+fn v2_header_at(data: &[u8], offset: usize) -> Option<Id3v2Header> {
+    let header = data.get(offset..offset + 10)?;
+    if &header[..3] != b"ID3"
+        || !(2..=4).contains(&header[3])
+        || header[4] == 0xFF
+        || header[6..].iter().any(|&b| b >= 0x80)
+    {
+        return None;
+    }
+    let body = header[6..]
+        .iter()
+        .fold(0usize, |size, &b| (size << 7) | b as usize);
+    let footer = if header[3] == 4 && header[5] & 0x10 != 0 {
+        10
+    } else {
+        0
+    };
+    Some(Id3v2Header {
+        offset,
+        version: (header[3], header[4]),
+        size: 10 + body + footer,
+    })
+}
+
+// First MPEG audio frame at or after `from` that another frame follows, so a stray 0xFF in
+// data isn't taken for one
+fn first_audio_frame(data: &[u8], from: usize) -> Option<usize> {
+    (from..data.len()).find(|&offset| {
+        data[offset] == 0xFF
+            && mpeg_frame_len(&data[offset..]).is_some_and(|length| {
+                mpeg_frame_len(data.get(offset + length..).unwrap_or_default()).is_some()
+            })
+    })
+}
+
+// ID3v2 tags stacked at the start of the file and the non-zero data around them. Without MPEG
+// audio to show where the tags end, only tags directly after one another (past zero padding)
+// are found.
+fn read_v2_layout(data: &[u8]) -> (Vec<Id3v2Header>, Vec<TagGap>) {
+    let mut tags = Vec::new();
+    let mut gaps = Vec::new();
+    let Some(first) = v2_header_at(data, 0) else {
+        return (tags, gaps);
+    };
+    let mut end = first.size.min(data.len());
+    tags.push(first);
+
+    let audio = first_audio_frame(data, end);
+    loop {
+        let next = match audio {
+            Some(audio) => (end..audio).find_map(|offset| v2_header_at(data, offset)),
+            None => data[end..]
+                .iter()
+                .position(|&b| b != 0)
+                .and_then(|skip| v2_header_at(data, end + skip)),
+        };
+        let gap_end = next.as_ref().map(|tag| tag.offset).or(audio).unwrap_or(end);
+        let gap = &data[end..gap_end];
+        if let (Some(start), Some(last)) = (
+            gap.iter().position(|&b| b != 0),
+            gap.iter().rposition(|&b| b != 0),
+        ) {
+            let bytes = gap[start..=last].to_vec();
+            gaps.push(TagGap {
+                offset: end + start,
+                classification: classify_payload(&bytes),
+                data: bytes,
+            });
+        }
+        match next {
+            Some(tag) => {
+                end = (tag.offset + tag.size).min(data.len());
+                tags.push(tag);
+            }
+            None => break,
+        }
+    }
+    (tags, gaps)
+}
+
+// The ID3v1 tag in the last 128 bytes of the file
+fn read_v1_tag(data: &[u8]) -> Option<Id3v1Tag> {
+    let offset = data.len().checked_sub(128)?;
+    let tag = &data[offset..];
+    if &tag[..3] != b"TAG" {
+        return None;
+    }
+
+    let mut hidden_bytes = Vec::new();
+    let mut field = |name: &str, bytes: &[u8]| {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let rest = &bytes[end..];
+        if let (Some(start), Some(last)) = (
+            rest.iter().position(|&b| b != 0 && b != b' '),
+            rest.iter().rposition(|&b| b != 0 && b != b' '),
+        ) {
+            hidden_bytes.push((name.to_string(), rest[start..=last].to_vec()));
+        }
+        // ID3v1 text is ISO-8859-1, whose code points are the byte values
+        bytes[..end]
+            .iter()
+            .map(|&b| b as char)
+            .collect::<String>()
+            .trim()
+            .to_string()
+    };
+    let title = field("title", &tag[3..33]);
+    let artist = field("artist", &tag[33..63]);
+    let album = field("album", &tag[63..93]);
+    let year = field("year", &tag[93..97]);
+    // ID3v1.1 keeps the track number in the last byte of the comment, after a NUL
+    let (comment, track) = if tag[125] == 0 && tag[126] != 0 {
+        (field("comment", &tag[97..125]), Some(tag[126]))
+    } else {
+        (field("comment", &tag[97..127]), None)
+    };
+
+    let extended_offset = offset
+        .checked_sub(227)
+        .filter(|&extended| data[extended..].starts_with(b"TAG+"));
+    Some(Id3v1Tag {
+        offset,
+        title,
+        artist,
+        album,
+        year,
+        comment,
+        track,
+        genre: tag[127],
+        extended_offset,
+        hidden_bytes,
+    })
+}
+
+// ID3v1 fields are cut to `width` characters and can't hold what ISO-8859-1 lacks, so a
+// truncated or transliterated copy of the ID3v2 value isn't a disagreement
+fn disagree(v1: &str, v2: &str, width: usize) -> bool {
+    let v2 = v2.trim();
+    if v1.is_empty() || v2.is_empty() || v2.chars().any(|c| c as u32 > 0xFF) {
+        return false;
+    }
+    let v1 = v1.to_lowercase();
+    let v2 = v2.to_lowercase();
+    if v1.chars().count() >= width {
+        !v2.starts_with(&v1)
+    } else {
+        v1 != v2
+    }
+}
+
+pub(crate) fn classify_payload(data: &[u8]) -> String {
+    if let Some(kind) = infer::get(data) {
+        return kind.mime_type().to_string();
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if text.chars().all(|c| !c.is_control() || c.is_whitespace()) => {
+            "text".to_string()
+        }
+        _ => "binary".to_string(),
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
 fn is_potential_base64(s: &str) -> bool {
     if s.len() < 4 {
         return false;
@@ -219,6 +876,211 @@ fn is_potential_base64(s: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_picture_extraction() {
+        use id3::frame::{Picture, PictureType};
+        use id3::{Tag, TagLike, Version};
+
+        let image_data: Vec<u8> = (0..200u8).collect();
+        let mut tag = Tag::new();
+        tag.set_title("Cover test");
+        tag.add_frame(Picture {
+            mime_type: "image/png".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: "cover".to_string(),
+            data: image_data.clone(),
+        });
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        tag.write_to_path(file.path(), Version::Id3v23).unwrap();
+
+        let id3_data = Id3AnalyzerWithPath::new(file.path()).analyze().unwrap();
+        let picture = &id3_data.pictures[0];
+        assert_eq!(picture.data, image_data);
+        assert_eq!(picture.extension(), "png");
+
+        let raw = std::fs::read(file.path()).unwrap();
+        let offset = picture.offset.unwrap();
+        assert_eq!(
+            &raw[offset..offset + image_data.len()],
+            image_data.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_chapter_analysis() {
+        use id3::frame::{Chapter, ExtendedLink, Frame, Picture, PictureType, TableOfContents};
+        use id3::{Tag, TagLike, Version};
+
+        let mut tag = Tag::new();
+        tag.add_frame(Chapter {
+            element_id: "chp0".to_string(),
+            start_time: 0,
+            end_time: 1000,
+            start_offset: 0xFFFFFFFF,
+            end_offset: 0xFFFFFFFF,
+            frames: vec![Frame::text("TIT2", "Intro")],
+        });
+        tag.add_frame(Chapter {
+            element_id: "chp1".to_string(),
+            start_time: 1000,
+            end_time: 2000,
+            start_offset: 0xFFFFFFFF,
+            end_offset: 0xFFFFFFFF,
+            frames: vec![
+                Frame::text("TIT2", "Payload"),
+                Frame::with_content(
+                    "WXXX",
+                    id3::Content::ExtendedLink(ExtendedLink {
+                        description: String::new(),
+                        link: "https://example.com/drop".to_string(),
+                    }),
+                ),
+                Frame::with_content(
+                    "APIC",
+                    id3::Content::Picture(Picture {
+                        mime_type: "image/png".to_string(),
+                        picture_type: PictureType::Other,
+                        description: String::new(),
+                        data: vec![0xAB; MAX_CHAPTER_BINARY_BYTES + 1],
+                    }),
+                ),
+            ],
+        });
+        tag.add_frame(TableOfContents {
+            element_id: "toc".to_string(),
+            top_level: true,
+            ordered: true,
+            elements: vec!["chp0".to_string(), "chp1".to_string(), "chp9".to_string()],
+            frames: Vec::new(),
+        });
+        tag.add_frame(Frame::text("TGID", "episode-42"));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        tag.write_to_path(file.path(), Version::Id3v24).unwrap();
+        let id3_data = Id3AnalyzerWithPath::new(file.path()).analyze().unwrap();
+
+        assert_eq!(id3_data.chapters.len(), 2);
+        assert!(id3_data.chapters[0].findings.is_empty());
+        let flagged = &id3_data.chapters[1];
+        assert_eq!(flagged.title.as_deref(), Some("Payload"));
+        assert_eq!(flagged.urls, vec!["https://example.com/drop".to_string()]);
+        assert_eq!(flagged.picture_count, 1);
+        assert_eq!(flagged.findings.len(), 3);
+
+        assert_eq!(
+            id3_data.tables_of_contents[0].findings,
+            vec!["References missing element 'chp9'".to_string()]
+        );
+        assert_eq!(
+            id3_data.podcast_frames,
+            vec![("TGID".to_string(), "episode-42".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_synced_lyrics_payload() {
+        use id3::frame::{SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat};
+        use id3::{Tag, TagLike, Version};
+
+        // A PNG header split across two timed segments
+        let encoded = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR4nGNgYGD4DwABBAEAcCBlCwAAAABJRU5ErkJggg==";
+        let (first, second) = encoded.split_at(40);
+        let mut tag = Tag::new();
+        tag.add_frame(SynchronisedLyrics {
+            lang: "eng".to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: vec![
+                (0, "Hello darkness my old friend ".to_string()),
+                (1500, first.to_string()),
+                (1600, second.to_string()),
+            ],
+        });
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        tag.write_to_path(file.path(), Version::Id3v24).unwrap();
+        let id3_data = Id3AnalyzerWithPath::new(file.path()).analyze().unwrap();
+
+        assert_eq!(id3_data.synced_lyrics_entries, 3);
+        assert_eq!(id3_data.lyrics_payloads.len(), 1);
+        let payload = &id3_data.lyrics_payloads[0];
+        assert_eq!(payload.kind, LyricsPayloadKind::Base64);
+        assert_eq!(payload.timestamp, Some(1500));
+        assert_eq!(payload.classification, "image/png");
+        assert!(id3_data.suspicious_frames[0].contains("SYLT entry at 1500"));
+
+        let plain = scan_lyrics_text("USLT", "Just some ordinary lyrics\nwith two lines", &[]);
+        assert!(plain.is_empty());
+        let binary = scan_lyrics_text("USLT", "\u{1}\u{2}\u{3}\u{4}\u{5}\u{6}\u{7}\u{8}ab", &[]);
+        assert_eq!(binary[0].kind, LyricsPayloadKind::Binary);
+    }
+
+    #[test]
+    fn test_tag_layout() {
+        use id3::{Tag, TagLike, Version};
+
+        let mut first = Tag::new();
+        first.set_title("Morning Song");
+        first.set_artist("The Band");
+        let mut second = Tag::new();
+        second.set_title("Decoy");
+
+        let mut file = Vec::new();
+        first.write_to(&mut file, Version::Id3v24).unwrap();
+        let hidden_at = file.len();
+        file.extend_from_slice(b"exfiltrated between the tags");
+        let second_at = file.len();
+        second.write_to(&mut file, Version::Id3v23).unwrap();
+        file.extend_from_slice(&[0; 16]);
+        // MPEG-1 Layer III frames at 128 kbps and 44.1 kHz, 417 bytes each
+        for _ in 0..3 {
+            let mut frame = vec![0; 417];
+            frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+            file.extend_from_slice(&frame);
+        }
+        let mut v1 = [0u8; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        v1[3..15].copy_from_slice(b"Morning Song");
+        v1[16..22].copy_from_slice(b"secret");
+        v1[33..44].copy_from_slice(b"Other Band ");
+        v1[93..97].copy_from_slice(b"1999");
+        v1[126] = 7;
+        v1[127] = 12;
+        file.extend_from_slice(&v1);
+
+        let path = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(path.path(), &file).unwrap();
+        let id3_data = Id3AnalyzerWithPath::new(path.path()).analyze().unwrap();
+
+        assert_eq!(id3_data.v2_tags.len(), 2);
+        assert_eq!(id3_data.v2_tags[0].version, (4, 0));
+        assert_eq!(id3_data.v2_tags[1].offset, second_at);
+        assert_eq!(id3_data.tag_gaps.len(), 1);
+        assert_eq!(id3_data.tag_gaps[0].offset, hidden_at);
+        assert_eq!(id3_data.tag_gaps[0].data, b"exfiltrated between the tags");
+
+        let v1 = id3_data.v1_tag.as_ref().unwrap();
+        assert_eq!(v1.title, "Morning Song");
+        assert_eq!(v1.track, Some(7));
+        assert_eq!(
+            v1.hidden_bytes,
+            vec![("title".to_string(), b"secret".to_vec())]
+        );
+        assert_eq!(id3_data.tag_mismatches.len(), 1);
+        assert_eq!(id3_data.tag_mismatches[0].field, "artist");
+        assert_eq!(id3_data.suspicious_frames.len(), 4);
+
+        // Only an ID3v1 tag: still read, nothing to compare it with
+        let v1_only = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(v1_only.path(), &file[file.len() - 128 - 417..]).unwrap();
+        let id3_data = Id3AnalyzerWithPath::new(v1_only.path()).analyze().unwrap();
+        assert!(id3_data.v2_tags.is_empty());
+        assert!(id3_data.tag_mismatches.is_empty());
+        assert_eq!(id3_data.v1_tag.unwrap().year, "1999");
+    }
+
     #[test]
     fn test_base64_detection() {
         assert!(is_potential_base64("SGVsbG8gV29ybGQ="));
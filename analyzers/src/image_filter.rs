@@ -1,9 +1,12 @@
 use std::fmt::Display;
 
-use image::{DynamicImage, ImageBuffer, RgbaImage};
+use image::{DynamicImage, Rgba, RgbaImage};
 
 use crate::Analyzer;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
 pub struct ImageFilterAnalyzer;
 
 #[derive(Debug)]
@@ -23,114 +26,29 @@ impl Analyzer for ImageFilterAnalyzer {
     type Error = ImageFilterErrors;
 
     fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
-        let mut output = Vec::new();
-        output.push(input.clone().into_rgba8());
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [p[0], 0, 0, 0])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [0, p[1], 0, 0])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [0, 0, p[2], 0])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [0, 0, 0, p[3]])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [p[0], 255, 255, 255])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [255, p[1], 255, 255])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [255, 255, p[2], 255])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
-        output.push(
-            ImageBuffer::from_vec(
-                input.width(),
-                input.height(),
-                input
-                    .clone()
-                    .into_rgba8()
-                    .pixels()
-                    .flat_map(|p| return [255, 255, 255, p[3]])
-                    .collect::<Vec<u8>>(),
-            )
-            .expect("to be able to make an image that is single channel from the original image"),
-        );
+        // Converted once; every channel view below is built from this one buffer
+        let rgba = input.to_rgba8();
+        let channel_views = [
+            map_pixels(&rgba, |p| [p[0], 0, 0, 0]),
+            map_pixels(&rgba, |p| [0, p[1], 0, 0]),
+            map_pixels(&rgba, |p| [0, 0, p[2], 0]),
+            map_pixels(&rgba, |p| [0, 0, 0, p[3]]),
+            map_pixels(&rgba, |p| [p[0], 255, 255, 255]),
+            map_pixels(&rgba, |p| [255, p[1], 255, 255]),
+            map_pixels(&rgba, |p| [255, 255, p[2], 255]),
+            map_pixels(&rgba, |p| [255, 255, 255, p[3]]),
+        ];
+
+        let mut output = Vec::with_capacity(channel_views.len() + 3);
+        output.push(rgba);
+        output.extend(channel_views);
         output.push(input.adjust_contrast(-10.0).into_rgba8());
         output.push(input.adjust_contrast(10.0).into_rgba8());
         Ok(output)
     }
 }
+
+fn map_pixels(image: &RgbaImage, f: impl Fn(&Rgba<u8>) -> [u8; 4]) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| Rgba(f(image.get_pixel(x, y))))
+}
@@ -0,0 +1,302 @@
+use crate::Analyzer;
+use std::fmt::Display;
+
+// IPTC-IIM reader. JPEGs carry IIM inside Photoshop image resource blocks (APP13,
+// resource 0x0404) and TIFFs under tag 33723. Each dataset is a 0x1C marker, record and
+// dataset numbers, and a length; anything after the last dataset that is not padding is
+// kept as trailing data.
+pub struct IptcAnalyzer;
+
+const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+const IPTC_RESOURCE_ID: u16 = 0x0404;
+const TIFF_IPTC_TAG: u16 = 33723;
+
+#[derive(Debug)]
+pub enum IptcError {
+    NotFound,
+}
+
+impl Display for IptcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IptcError::NotFound => write!(f, "No IPTC-IIM block found"),
+        }
+    }
+}
+
+impl std::error::Error for IptcError {}
+
+#[derive(Debug, Clone)]
+pub struct IptcDataset {
+    pub record: u8,
+    pub dataset: u8,
+    pub name: String,
+    // Offset of the dataset marker within the IIM block
+    pub offset: usize,
+    pub value: Vec<u8>,
+    // Maximum length the IIM specification allows, when it defines one
+    pub max_length: Option<usize>,
+}
+
+impl IptcDataset {
+    // Value as text: UTF-8 when the bytes are valid, else Latin-1
+    pub fn text(&self) -> String {
+        match std::str::from_utf8(&self.value) {
+            Ok(text) => text.to_string(),
+            Err(_) => self.value.iter().map(|&b| b as char).collect(),
+        }
+    }
+
+    // Free-text fields a person writes into, where binary data has no business
+    pub fn is_caption(&self) -> bool {
+        matches!(
+            (self.record, self.dataset),
+            (2, 5) | (2, 40) | (2, 105) | (2, 120)
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IptcData {
+    pub datasets: Vec<IptcDataset>,
+    // Non-padding bytes after the last dataset, with their offset in the IIM block
+    pub trailing_data: Option<(usize, usize)>,
+}
+
+impl Analyzer for IptcAnalyzer {
+    type Input = Vec<u8>;
+    type Output = IptcData;
+    type Error = IptcError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let block = if input.starts_with(&[0xFF, 0xD8]) {
+            jpeg_iptc(&input)
+        } else if input.starts_with(b"II*\0") || input.starts_with(b"MM\0*") {
+            tiff_iptc(&input)
+        } else {
+            None
+        };
+        block
+            .map(|block| parse_iim(&block))
+            .ok_or(IptcError::NotFound)
+    }
+}
+
+// IIM from the Photoshop resources of all APP13 segments, which large blocks span
+fn jpeg_iptc(data: &[u8]) -> Option<Vec<u8>> {
+    let mut resources = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        // Entropy-coded data follows SOS; metadata segments all come before it
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(data.len());
+        let body = &data[(pos + 4).min(end)..end];
+        if marker == 0xED
+            && let Some(rest) = body.strip_prefix(PHOTOSHOP_SIGNATURE)
+        {
+            resources.extend_from_slice(rest);
+        }
+        pos = end;
+    }
+    photoshop_resource(&resources, IPTC_RESOURCE_ID)
+}
+
+// Data of one 8BIM image resource block
+fn photoshop_resource(data: &[u8], id: u16) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    while pos + 8 <= data.len() && &data[pos..pos + 4] == b"8BIM" {
+        let resource_id = u16::from_be_bytes([data[pos + 4], data[pos + 5]]);
+        // Pascal name, padded so length byte and name are even
+        let name_length = data[pos + 6] as usize;
+        let mut cursor = pos + 6 + (name_length + 2) / 2 * 2;
+        let size_bytes: [u8; 4] = data.get(cursor..cursor + 4)?.try_into().ok()?;
+        let size = u32::from_be_bytes(size_bytes) as usize;
+        cursor += 4;
+        let end = cursor.checked_add(size)?.min(data.len());
+        if resource_id == id {
+            return Some(data[cursor..end].to_vec());
+        }
+        pos = end + size % 2;
+    }
+    None
+}
+
+fn tiff_iptc(data: &[u8]) -> Option<Vec<u8>> {
+    let little_endian = data[0] == b'I';
+    let u16_at = |at: usize| -> Option<u16> {
+        let bytes: [u8; 2] = data.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    for i in 0..count {
+        let entry = ifd + 2 + 12 * i;
+        if u16_at(entry)? != TIFF_IPTC_TAG {
+            continue;
+        }
+        // Written as UNDEFINED, BYTE or LONG depending on the software
+        let unit = match u16_at(entry + 2)? {
+            4 => 4,
+            _ => 1,
+        };
+        let size = (u32_at(entry + 4)? as usize).checked_mul(unit)?;
+        let start = if size <= 4 {
+            entry + 8
+        } else {
+            u32_at(entry + 8)? as usize
+        };
+        return data
+            .get(start..start.checked_add(size)?)
+            .map(<[u8]>::to_vec);
+    }
+    None
+}
+
+fn parse_iim(block: &[u8]) -> IptcData {
+    let mut datasets = Vec::new();
+    let mut pos = 0;
+    while pos + 5 <= block.len() && block[pos] == 0x1C {
+        let record = block[pos + 1];
+        let dataset = block[pos + 2];
+        let mut length = u16::from_be_bytes([block[pos + 3], block[pos + 4]]) as usize;
+        let mut value_start = pos + 5;
+        // Extended dataset: the low 15 bits give how many bytes hold the real length
+        if length & 0x8000 != 0 {
+            let length_bytes = length & 0x7FFF;
+            if length_bytes > 8 {
+                break;
+            }
+            let Some(bytes) = block.get(value_start..value_start + length_bytes) else {
+                break;
+            };
+            length = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            value_start += length_bytes;
+        }
+        let value_end = value_start.saturating_add(length).min(block.len());
+        let (name, max_length) = dataset_info(record, dataset);
+        datasets.push(IptcDataset {
+            record,
+            dataset,
+            name,
+            offset: pos,
+            value: block[value_start.min(value_end)..value_end].to_vec(),
+            max_length,
+        });
+        pos = value_end;
+    }
+
+    let trailing_data = block
+        .get(pos..)
+        .filter(|rest| rest.iter().any(|&b| b != 0))
+        .map(|rest| (pos, rest.len()));
+    IptcData {
+        datasets,
+        trailing_data,
+    }
+}
+
+// Name and maximum length of the common datasets, from the IIM 4.2 specification
+fn dataset_info(record: u8, dataset: u8) -> (String, Option<usize>) {
+    let known = match (record, dataset) {
+        (1, 90) => Some(("CodedCharacterSet", 32)),
+        (2, 0) => Some(("RecordVersion", 2)),
+        (2, 5) => Some(("ObjectName", 64)),
+        (2, 7) => Some(("EditStatus", 64)),
+        (2, 10) => Some(("Urgency", 1)),
+        (2, 15) => Some(("Category", 3)),
+        (2, 20) => Some(("SupplementalCategory", 32)),
+        (2, 25) => Some(("Keywords", 64)),
+        (2, 40) => Some(("SpecialInstructions", 256)),
+        (2, 55) => Some(("DateCreated", 8)),
+        (2, 60) => Some(("TimeCreated", 11)),
+        (2, 80) => Some(("By-line", 32)),
+        (2, 85) => Some(("By-lineTitle", 32)),
+        (2, 90) => Some(("City", 32)),
+        (2, 95) => Some(("Province-State", 32)),
+        (2, 100) => Some(("CountryCode", 3)),
+        (2, 101) => Some(("CountryName", 64)),
+        (2, 103) => Some(("OriginalTransmissionReference", 32)),
+        (2, 105) => Some(("Headline", 256)),
+        (2, 110) => Some(("Credit", 32)),
+        (2, 115) => Some(("Source", 32)),
+        (2, 116) => Some(("CopyrightNotice", 128)),
+        (2, 118) => Some(("Contact", 128)),
+        (2, 120) => Some(("Caption-Abstract", 2000)),
+        (2, 122) => Some(("Writer-Editor", 32)),
+        _ => None,
+    };
+    match known {
+        Some((name, max_length)) => (name.to_string(), Some(max_length)),
+        None => (format!("IPTC {}:{}", record, dataset), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset(record: u8, dataset: u8, value: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1C, record, dataset];
+        out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        out.extend_from_slice(value);
+        out
+    }
+
+    #[test]
+    fn test_jpeg_app13_iptc() {
+        let mut iim = dataset(2, 120, b"Sunset over the bay");
+        iim.extend(dataset(2, 25, b"beach"));
+        iim.extend_from_slice(&[0, 0]);
+
+        let mut resources = PHOTOSHOP_SIGNATURE.to_vec();
+        resources.extend_from_slice(b"8BIM\x04\x04\0\0");
+        resources.extend_from_slice(&(iim.len() as u32).to_be_bytes());
+        resources.extend_from_slice(&iim);
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xED];
+        jpeg.extend_from_slice(&(resources.len() as u16 + 2).to_be_bytes());
+        jpeg.extend_from_slice(&resources);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+
+        let iptc = IptcAnalyzer::analyze(jpeg).unwrap();
+        assert_eq!(iptc.datasets.len(), 2);
+        assert_eq!(iptc.datasets[0].name, "Caption-Abstract");
+        assert_eq!(iptc.datasets[0].text(), "Sunset over the bay");
+        assert!(iptc.datasets[0].is_caption());
+        assert_eq!(iptc.datasets[1].offset, 5 + 19);
+        assert!(iptc.trailing_data.is_none());
+    }
+
+    #[test]
+    fn test_extended_dataset_and_trailing_data() {
+        let mut iim = vec![0x1C, 8, 10, 0x80, 0x04, 0, 0, 0x01, 0x00];
+        iim.extend_from_slice(&[0xAB; 256]);
+        iim.extend_from_slice(b"hidden");
+        let iptc = parse_iim(&iim);
+        assert_eq!(iptc.datasets.len(), 1);
+        assert_eq!(iptc.datasets[0].value.len(), 256);
+        assert_eq!(iptc.trailing_data, Some((9 + 256, 6)));
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert!(IptcAnalyzer::analyze(b"\x89PNG\r\n\x1a\n".to_vec()).is_err());
+    }
+}
@@ -1,10 +1,56 @@
+pub mod adaptive_lsb_analyzer;
+pub mod binwalk_extractor;
+pub mod bpcs_analyzer;
+pub mod calibration_analyzer;
+pub mod channel_correlation_analyzer;
+pub mod content_classifier;
+pub mod content_store;
+pub mod cover_baseline;
+pub mod deflate_hunter;
+pub mod demodulator;
+pub mod differential_analyzer;
+pub mod ebook_analyzer;
 pub mod exif_analyzer;
+pub mod external_plugin;
+pub mod frame_pipeline;
+pub mod frame_sampler;
+pub mod gif_extension_analyzer;
 pub mod id3_analyzer;
 pub mod image_filter;
+pub mod iptc_analyzer;
 pub mod lsb_analyzer;
 pub mod magic_bytes_analyzer;
+pub mod makernote_analyzer;
+pub mod model_analyzer;
+pub mod mp4_tag_analyzer;
+pub mod office_analyzer;
+pub mod ole_analyzer;
+pub mod page_analyzer;
+pub mod pattern_set;
+pub mod payload_carver;
+pub mod payload_sniffer;
+pub mod phase_analyzer;
+pub mod png_text_analyzer;
+pub mod region_overlay;
+pub mod scene_detector;
+pub mod shared;
+pub mod slack_space_analyzer;
+pub mod source_code_analyzer;
 pub mod spectrogram_analyzer;
+pub mod spectrogram_render;
+pub mod structured_text_analyzer;
+#[cfg(test)]
+mod test_rng;
+pub mod text_stego_analyzer;
+pub mod timeline;
+pub mod trailing_tag_analyzer;
+pub mod ultrasonic_analyzer;
+pub mod versions;
 pub mod video_frame_analyzer;
+#[cfg(feature = "wasm")]
+pub mod wasm_plugin;
+pub mod webp_structure_analyzer;
+pub mod ws_analyzer;
 pub trait Analyzer {
     type Output;
     type Input;
@@ -1,9 +1,20 @@
 use crate::Analyzer;
-use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use crate::content_classifier::{ContentClass, ContentClassification};
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba, RgbaImage};
 use std::fmt::Display;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
 pub struct LsbAnalyzer;
 
+// Detection thresholds for the per-channel LSB statistics
+pub const CHI_SQUARE_THRESHOLD: f64 = 100.0;
+pub const ENTROPY_THRESHOLD: f64 = 0.9;
+
+pub const RGB_CHANNELS: [&str; 3] = ["Red", "Green", "Blue"];
+pub const GRAY_CHANNELS: [&str; 1] = ["Gray"];
+
 #[derive(Debug)]
 pub enum LsbAnalyzerError {
     ImageProcessing(String),
@@ -25,6 +36,51 @@ pub struct LsbAnalysis {
     pub chi_square_scores: Vec<f64>,
     pub entropy_scores: Vec<f64>,
     pub suspicious: bool,
+    // Bits per channel the planes were taken from (8, 16, or 32 for float HDR)
+    pub bit_depth: u8,
+    // Native channel each score belongs to, e.g. Red/Green/Blue, Gray, or C/M/Y/K
+    pub channel_names: Vec<String>,
+}
+
+impl LsbAnalysis {
+    // Re-evaluate the channel scores against caller-supplied thresholds
+    pub fn is_suspicious_at(&self, chi_square_threshold: f64, entropy_threshold: f64) -> bool {
+        exceeds_lsb_thresholds(
+            &self.chi_square_scores,
+            &self.entropy_scores,
+            chi_square_threshold,
+            entropy_threshold,
+        )
+    }
+
+    // Verdict against the baseline of the image's content class: the plane-wide statistics
+    // for photos, LSB disagreement inside flat runs for screenshots and other synthetic
+    // images, whose flat regions would otherwise trip the chi-square test
+    pub fn is_suspicious_for(
+        &self,
+        content: &ContentClassification,
+        chi_square_threshold: f64,
+        entropy_threshold: f64,
+        flat_lsb_noise_threshold: f64,
+    ) -> bool {
+        match content.class {
+            ContentClass::Photo => self.is_suspicious_at(chi_square_threshold, entropy_threshold),
+            ContentClass::Synthetic => content.has_flat_lsb_noise(flat_lsb_noise_threshold),
+        }
+    }
+}
+
+// True when any channel's chi-square or entropy score is above its threshold
+pub fn exceeds_lsb_thresholds(
+    chi_square_scores: &[f64],
+    entropy_scores: &[f64],
+    chi_square_threshold: f64,
+    entropy_threshold: f64,
+) -> bool {
+    chi_square_scores
+        .iter()
+        .any(|&score| score > chi_square_threshold)
+        || entropy_scores.iter().any(|&ent| ent > entropy_threshold)
 }
 
 impl Analyzer for LsbAnalyzer {
@@ -33,49 +89,153 @@ impl Analyzer for LsbAnalyzer {
     type Error = LsbAnalyzerError;
 
     fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
-        let rgba = input.to_rgba8();
+        // Keep the source precision: to_rgba8() would drop the real low-order bits of
+        // 16-bit and float images and analyze bit 8 instead
+        let bit_depth = channel_bit_depth(&input);
+        // Grayscale images are analyzed as their single native channel instead of three
+        // identical RGB copies
+        let grayscale = !input.color().has_color();
+        let channel_planes: Vec<Vec<u8>> = match (bit_depth, grayscale) {
+            (16, true) => vec![extract_lsb_plane(&input.to_luma16(), 0)],
+            (16, false) => {
+                let rgba = input.to_rgba16();
+                (0..3).map(|c| extract_lsb_plane(&rgba, c)).collect()
+            }
+            (32, true) => vec![extract_lsb_plane(&input.to_luma32f(), 0)],
+            (32, false) => {
+                let rgba = input.to_rgba32f();
+                (0..3).map(|c| extract_lsb_plane(&rgba, c)).collect()
+            }
+            (_, true) => vec![extract_lsb_plane(&input.to_luma8(), 0)],
+            (_, false) => {
+                let rgba = input.to_rgba8();
+                (0..3).map(|c| extract_lsb_plane(&rgba, c)).collect()
+            }
+        };
+        let channel_names: &[&str] = if grayscale {
+            &GRAY_CHANNELS
+        } else {
+            &RGB_CHANNELS
+        };
 
-        let mut lsb_planes = Vec::new();
-        let mut chi_square_scores = Vec::new();
-        let mut entropy_scores = Vec::new();
+        Ok(analyze_planes(channel_names, channel_planes, bit_depth))
+    }
+}
 
-        // Extract LSB from each color channel (R, G, B)
-        for channel in 0..3 {
-            // Extract LSB plane
-            let lsb_plane = extract_lsb_plane(&rgba, channel);
+impl LsbAnalyzer {
+    // Analyze 8-bit samples interleaved in `channel_names` order, for color models
+    // DynamicImage can't hold (e.g. the native C, M, Y, K channels of a CMYK JPEG)
+    pub fn analyze_interleaved(
+        data: &[u8],
+        channel_names: &[&str],
+    ) -> Result<LsbAnalysis, LsbAnalyzerError> {
+        let channels = channel_names.len();
+        if channels == 0 || data.is_empty() || !data.len().is_multiple_of(channels) {
+            return Err(LsbAnalyzerError::ImageProcessing(format!(
+                "{} bytes is not a whole number of {}-channel pixels",
+                data.len(),
+                channels
+            )));
+        }
 
-            // Calculate chi-square test for randomness
-            let chi_square = calculate_chi_square(&lsb_plane, channel);
-            chi_square_scores.push(chi_square);
+        let channel_planes = (0..channels)
+            .map(|c| {
+                data.iter()
+                    .skip(c)
+                    .step_by(channels)
+                    .map(|v| v & 1)
+                    .collect()
+            })
+            .collect();
+
+        Ok(analyze_planes(channel_names, channel_planes, 8))
+    }
+}
 
-            // Calculate entropy
-            let entropy = calculate_entropy(&lsb_plane, channel);
-            entropy_scores.push(entropy);
+fn analyze_planes(
+    channel_names: &[&str],
+    channel_planes: Vec<Vec<u8>>,
+    bit_depth: u8,
+) -> LsbAnalysis {
+    let mut lsb_planes = Vec::new();
+    let mut chi_square_scores = Vec::new();
+    let mut entropy_scores = Vec::new();
+
+    // Analyze the LSB plane of each native channel
+    for (channel, lsb_plane) in channel_planes.iter().enumerate() {
+        // Calculate chi-square test for randomness
+        let chi_square = calculate_chi_square(lsb_plane, channel);
+        chi_square_scores.push(chi_square);
+
+        // Calculate entropy
+        let entropy = calculate_entropy(lsb_plane, channel);
+        entropy_scores.push(entropy);
+
+        // Create visualization of LSB plane (amplified for visibility)
+        let visualized = visualize_lsb_plane(lsb_plane, channel_names[channel]);
+        lsb_planes.push(visualized);
+    }
 
-            // Create visualization of LSB plane (amplified for visibility)
-            let visualized = visualize_lsb_plane(&lsb_plane, channel);
-            lsb_planes.push(visualized);
-        }
+    // Determine if image is suspicious
+    // High chi-square or low entropy suggests hidden data
+    let suspicious = exceeds_lsb_thresholds(
+        &chi_square_scores,
+        &entropy_scores,
+        CHI_SQUARE_THRESHOLD,
+        ENTROPY_THRESHOLD,
+    );
+
+    LsbAnalysis {
+        lsb_planes,
+        chi_square_scores,
+        entropy_scores,
+        suspicious,
+        bit_depth,
+        channel_names: channel_names.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+// Bits per channel of the decoded image, before any conversion
+pub fn channel_bit_depth(image: &DynamicImage) -> u8 {
+    let color = image.color();
+    color.bytes_per_pixel() / color.channel_count() * 8
+}
 
-        // Determine if image is suspicious
-        // High chi-square or low entropy suggests hidden data
-        let suspicious = chi_square_scores.iter().any(|&score| score > 100.0)
-            || entropy_scores.iter().any(|&ent| ent > 0.9);
+// Lowest stored bit of a channel sample; for floats this is the mantissa LSB
+trait LeastSignificantBit {
+    fn lsb(self) -> u8;
+}
 
-        Ok(LsbAnalysis {
-            lsb_planes,
-            chi_square_scores,
-            entropy_scores,
-            suspicious,
-        })
+impl LeastSignificantBit for u8 {
+    fn lsb(self) -> u8 {
+        self & 1
     }
 }
 
-fn extract_lsb_plane(image: &RgbaImage, channel: usize) -> Vec<u8> {
-    image.pixels().map(|pixel| pixel[channel] & 1).collect()
+impl LeastSignificantBit for u16 {
+    fn lsb(self) -> u8 {
+        (self & 1) as u8
+    }
 }
 
-fn visualize_lsb_plane(lsb_data: &[u8], channel: usize) -> RgbaImage {
+impl LeastSignificantBit for f32 {
+    fn lsb(self) -> u8 {
+        (self.to_bits() & 1) as u8
+    }
+}
+
+fn extract_lsb_plane<P>(image: &ImageBuffer<P, Vec<P::Subpixel>>, channel: usize) -> Vec<u8>
+where
+    P: Pixel,
+    P::Subpixel: LeastSignificantBit,
+{
+    image
+        .pixels()
+        .map(|pixel| pixel.channels()[channel].lsb())
+        .collect()
+}
+
+fn visualize_lsb_plane(lsb_data: &[u8], channel_name: &str) -> RgbaImage {
     let width = (lsb_data.len() as f64).sqrt().ceil() as u32;
     let height = (lsb_data.len() as u32 + width - 1) / width;
 
@@ -83,11 +243,11 @@ fn visualize_lsb_plane(lsb_data: &[u8], channel: usize) -> RgbaImage {
         let idx = (y * width + x) as usize;
         if idx < lsb_data.len() {
             let val = if lsb_data[idx] == 1 { 255 } else { 0 };
-            match channel {
-                0 => Rgba([val, 0, 0, 255]),     // Red channel
-                1 => Rgba([0, val, 0, 255]),     // Green channel
-                2 => Rgba([0, 0, val, 255]),     // Blue channel
-                _ => Rgba([val, val, val, 255]), // Grayscale fallback
+            match channel_name {
+                "Red" => Rgba([val, 0, 0, 255]),   // Red channel
+                "Green" => Rgba([0, val, 0, 255]), // Green channel
+                "Blue" => Rgba([0, 0, val, 255]),  // Blue channel
+                _ => Rgba([val, val, val, 255]),   // Gray, CMYK and other native channels
             }
         } else {
             Rgba([0, 0, 0, 255])
@@ -168,4 +328,35 @@ mod tests {
         let entropy = calculate_entropy(&data, 0);
         assert!(entropy > 0.9);
     }
+
+    #[test]
+    fn test_16bit_uses_true_lsb() {
+        // Every 16-bit sample is even, but the high bytes (what to_rgba8 keeps) vary
+        let img: ImageBuffer<Rgba<u16>, Vec<u16>> = ImageBuffer::from_fn(32, 32, |x, y| {
+            let v = (((x * 31 + y * 17) % 256) as u16) << 8;
+            Rgba([v, v, v, u16::MAX])
+        });
+
+        let analysis = LsbAnalyzer::analyze(DynamicImage::ImageRgba16(img)).unwrap();
+        assert_eq!(analysis.bit_depth, 16);
+        assert!(analysis.entropy_scores.iter().all(|&e| e < 0.1));
+    }
+
+    #[test]
+    fn test_native_channel_labels() {
+        let gray = image::GrayImage::from_fn(16, 16, |x, y| image::Luma([(x * y) as u8]));
+        let analysis = LsbAnalyzer::analyze(DynamicImage::ImageLuma8(gray)).unwrap();
+        assert_eq!(analysis.channel_names, vec!["Gray"]);
+        assert_eq!(analysis.entropy_scores.len(), 1);
+
+        // K carries random-looking LSBs, C/M/Y are constant
+        let cmyk: Vec<u8> = (0..256u32)
+            .flat_map(|i| [10, 20, 30, (i * 37 % 256) as u8])
+            .collect();
+        let analysis = LsbAnalyzer::analyze_interleaved(&cmyk, &["C", "M", "Y", "K"]).unwrap();
+        assert_eq!(analysis.channel_names, vec!["C", "M", "Y", "K"]);
+        assert!(analysis.entropy_scores[0] < 0.1);
+        assert!(analysis.entropy_scores[3] > 0.9);
+        assert!(LsbAnalyzer::analyze_interleaved(&cmyk[..5], &["C", "M", "Y", "K"]).is_err());
+    }
 }
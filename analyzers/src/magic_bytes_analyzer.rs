@@ -1,7 +1,19 @@
 use crate::Analyzer;
-use binwalk::Binwalk;
+use crate::pattern_set::PatternSet;
+use crate::shared;
+use binwalk::signatures::common::Signature;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
 
 pub struct MagicBytesAnalyzer;
 
@@ -9,6 +21,7 @@ pub struct MagicBytesAnalyzer;
 pub enum MagicBytesError {
     IO(std::io::Error),
     Analysis(String),
+    InvalidSignatures(String),
 }
 
 impl Display for MagicBytesError {
@@ -16,6 +29,9 @@ impl Display for MagicBytesError {
         match self {
             MagicBytesError::IO(e) => write!(f, "IO error: {}", e),
             MagicBytesError::Analysis(e) => write!(f, "Analysis error: {}", e),
+            MagicBytesError::InvalidSignatures(e) => {
+                write!(f, "Invalid signature definitions: {}", e)
+            }
         }
     }
 }
@@ -59,20 +75,258 @@ pub struct FormatSummary {
     pub other_files: usize,
 }
 
+// Categories a custom signature can be filed under, matching the format summary
+pub const SIGNATURE_CATEGORIES: [&str; 7] = [
+    "Image",
+    "Audio",
+    "Video",
+    "Text/Document",
+    "Archive",
+    "Executable",
+    "Other",
+];
+
+// A user-supplied magic-byte signature, merged into the manual scan
+#[derive(Debug, Clone)]
+pub struct CustomSignature {
+    pub description: String,
+    pub category: String,
+    pub pattern: Vec<u8>,
+    // ANDed with the file bytes before comparing, same length as the pattern
+    pub mask: Option<Vec<u8>>,
+    // Only match at exactly this offset
+    pub offset: Option<usize>,
+    // Only match at or before this offset
+    pub max_offset: Option<usize>,
+}
+
+// On-disk form of a signature; patterns and masks are hex strings such as "4D 5A ?? 00"
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureDefinition {
+    description: String,
+    #[serde(default = "default_category")]
+    category: String,
+    pattern: String,
+    mask: Option<String>,
+    offset: Option<usize>,
+    max_offset: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SignatureFile {
+    signatures: Vec<SignatureDefinition>,
+}
+
+fn default_category() -> String {
+    "Other".to_string()
+}
+
+impl CustomSignature {
+    // Load definitions from a YAML file, or JSON when the extension is .json:
+    //
+    //   signatures:
+    //     - description: Acme firmware header
+    //       category: Executable
+    //       pattern: "41 43 4D 45 ?? 01"
+    //       max_offset: 4096
+    pub fn load(path: &Path) -> Result<Vec<Self>, MagicBytesError> {
+        let text = std::fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let file: SignatureFile = if is_json {
+            serde_json::from_str(&text)
+                .map_err(|e| MagicBytesError::InvalidSignatures(e.to_string()))?
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| MagicBytesError::InvalidSignatures(e.to_string()))?
+        };
+
+        file.signatures
+            .into_iter()
+            .map(Self::from_definition)
+            .collect()
+    }
+
+    fn from_definition(def: SignatureDefinition) -> Result<Self, MagicBytesError> {
+        let invalid = |reason: String| {
+            MagicBytesError::InvalidSignatures(format!("{}: {}", def.description, reason))
+        };
+
+        // "??" in the pattern is a wildcard byte, shorthand for a 00 mask byte
+        let tokens = hex_tokens(&def.pattern).map_err(invalid)?;
+        if tokens.is_empty() {
+            return Err(invalid("empty pattern".to_string()));
+        }
+        let pattern: Vec<u8> = tokens.iter().map(|t| t.unwrap_or(0)).collect();
+        let wildcard_mask = tokens.iter().any(|t| t.is_none()).then(|| {
+            tokens
+                .iter()
+                .map(|t| if t.is_some() { 0xFF } else { 0x00 })
+                .collect()
+        });
+
+        let mask = match &def.mask {
+            Some(mask) => {
+                let mask: Vec<u8> = hex_tokens(mask)
+                    .map_err(invalid)?
+                    .into_iter()
+                    .map(|t| t.unwrap_or(0))
+                    .collect();
+                if mask.len() != pattern.len() {
+                    return Err(invalid(format!(
+                        "mask is {} bytes but pattern is {}",
+                        mask.len(),
+                        pattern.len()
+                    )));
+                }
+                Some(mask)
+            }
+            None => wildcard_mask,
+        };
+
+        if !SIGNATURE_CATEGORIES.contains(&def.category.as_str()) {
+            return Err(invalid(format!(
+                "unknown category {:?} (expected one of: {})",
+                def.category,
+                SIGNATURE_CATEGORIES.join(", ")
+            )));
+        }
+
+        Ok(Self {
+            description: def.description,
+            category: def.category,
+            pattern,
+            mask,
+            offset: def.offset,
+            max_offset: def.max_offset,
+        })
+    }
+
+    // The longest run of bytes the mask leaves whole, which a match must contain verbatim
+    fn anchor(&self) -> Option<Range<usize>> {
+        let exact = |i: usize| self.mask.as_ref().is_none_or(|mask| mask[i] == 0xFF);
+        let mut best: Option<Range<usize>> = None;
+        let mut run_start = 0;
+        for i in 0..=self.pattern.len() {
+            if i < self.pattern.len() && exact(i) {
+                continue;
+            }
+            if i > run_start && best.as_ref().is_none_or(|b| i - run_start > b.len()) {
+                best = Some(run_start..i);
+            }
+            run_start = i + 1;
+        }
+        best
+    }
+
+    fn matches_at(&self, data: &[u8], pos: usize) -> bool {
+        let Some(window) = data.get(pos..pos + self.pattern.len()) else {
+            return false;
+        };
+        match &self.mask {
+            Some(mask) => window
+                .iter()
+                .zip(mask)
+                .zip(&self.pattern)
+                .all(|((b, m), p)| b & m == p & m),
+            None => window == self.pattern.as_slice(),
+        }
+    }
+}
+
+// Whitespace-separated or packed hex bytes; "??" is returned as None
+fn hex_tokens(text: &str) -> Result<Vec<Option<u8>>, String> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in {:?}", text));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| match &digits[i..i + 2] {
+            "??" => Ok(None),
+            byte => u8::from_str_radix(byte, 16)
+                .map(Some)
+                .map_err(|_| format!("invalid hex byte {:?} in {:?}", byte, text)),
+        })
+        .collect()
+}
+
+// How much evidence a manual-scan hit past offset 0 needs before it is reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    // Padding/alignment heuristic or a valid header structure
+    Lenient,
+    // A valid header structure, falling back to the heuristic for formats without a validator
+    #[default]
+    Normal,
+    // A valid header structure; formats without a validator are dropped
+    Strict,
+}
+
+impl FromStr for Strictness {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "lenient" => Ok(Self::Lenient),
+            "normal" => Ok(Self::Normal),
+            "strict" => Ok(Self::Strict),
+            other => Err(format!(
+                "unknown strictness {:?} (expected lenient, normal or strict)",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for Strictness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strictness::Lenient => write!(f, "lenient"),
+            Strictness::Normal => write!(f, "normal"),
+            Strictness::Strict => write!(f, "strict"),
+        }
+    }
+}
+
 pub struct MagicBytesAnalyzerWithPath<'a> {
     path: &'a Path,
+    custom_signatures: &'a [CustomSignature],
+    strictness: Strictness,
 }
 
 impl<'a> MagicBytesAnalyzerWithPath<'a> {
     pub fn new(path: &'a Path) -> Self {
-        Self { path }
+        Self {
+            path,
+            custom_signatures: &[],
+            strictness: Strictness::default(),
+        }
+    }
+
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    pub fn with_custom_signatures(mut self, signatures: &'a [CustomSignature]) -> Self {
+        self.custom_signatures = signatures;
+        self
     }
 
     pub fn analyze(&self) -> Result<MagicBytesAnalysis, MagicBytesError> {
-        use std::fs;
+        use std::fs::File;
+        use std::io::BufReader;
 
-        // Read file data
-        let file_data = fs::read(self.path)?;
+        // Binwalk and the custom signatures need their input in memory, so they only see the
+        // start of a large file; the manual scan streams through all of it
+        let mut file_data = Vec::new();
+        File::open(self.path)?
+            .take(WHOLE_BUFFER_SCAN_BYTES as u64)
+            .read_to_end(&mut file_data)?;
 
         if file_data.is_empty() {
             return Err(MagicBytesError::Analysis("Empty file".to_string()));
@@ -86,8 +340,7 @@ impl<'a> MagicBytesAnalyzerWithPath<'a> {
             .map(|ext| ext.to_uppercase());
 
         // Run binwalk analysis
-        let binwalk = Binwalk::new();
-        let binwalk_results = binwalk.scan(&file_data);
+        let binwalk_results = shared::binwalk().scan(&file_data);
 
         // Extract signature results from binwalk
         let mut all_results = Vec::new();
@@ -107,7 +360,8 @@ impl<'a> MagicBytesAnalyzerWithPath<'a> {
         }
 
         // Also do our own basic signature detection for common formats binwalk might miss
-        let manual_results = manual_signature_scan(&file_data);
+        let manual_results =
+            manual_signature_scan(BufReader::new(File::open(self.path)?), self.strictness)?;
 
         // Merge manual results
         for manual_result in manual_results {
@@ -117,17 +371,31 @@ impl<'a> MagicBytesAnalyzerWithPath<'a> {
             }
         }
 
+        // User-supplied signatures, for formats neither scan knows about
+        let mut suspicious_findings = Vec::new();
+        for custom_result in custom_signature_scan(&file_data, self.custom_signatures) {
+            if all_results.iter().any(|r| r.offset == custom_result.offset) {
+                continue;
+            }
+            if custom_result.offset > 0 {
+                suspicious_findings.push(format!(
+                    "Custom signature matched at offset 0x{:X}: {}",
+                    custom_result.offset, custom_result.description
+                ));
+            }
+            all_results.push(custom_result);
+        }
+
         // Sort by offset
         all_results.sort_by_key(|r| r.offset);
 
         // Process results
         let mut format_summary = FormatSummary::default();
-        let mut suspicious_findings = Vec::new();
 
         // Determine primary format (usually at offset 0)
         let primary_format = if let Some(first_result) = all_results.first() {
             if first_result.offset == 0 {
-                categorize_file_type(&first_result.description, &mut format_summary);
+                categorize_file_type(&first_result.file_type, &mut format_summary);
                 first_result.description.clone()
             } else {
                 // Check file start manually if binwalk didn't find it
@@ -141,7 +409,7 @@ impl<'a> MagicBytesAnalyzerWithPath<'a> {
         // Process all signatures found
         for result in &all_results {
             // Categorize for summary (only once per signature)
-            categorize_file_type(&result.description, &mut format_summary);
+            categorize_file_type(&result.file_type, &mut format_summary);
 
             // Check for suspicious patterns
             if result.offset > 0 {
@@ -158,8 +426,7 @@ impl<'a> MagicBytesAnalyzerWithPath<'a> {
         // Adjust format summary - primary format was already counted, remove the duplicate
         if !all_results.is_empty() && all_results[0].offset == 0 {
             // The primary format was counted, but we don't want to double-count it
-            let first_type = determine_file_category(&all_results[0].description);
-            match first_type {
+            match all_results[0].file_type.as_str() {
                 "Image" => {
                     format_summary.image_files = format_summary.image_files.saturating_sub(1)
                 }
@@ -310,8 +577,8 @@ fn determine_file_category(description: &str) -> &str {
     }
 }
 
-fn categorize_file_type(description: &str, summary: &mut FormatSummary) {
-    match determine_file_category(description) {
+fn categorize_file_type(category: &str, summary: &mut FormatSummary) {
+    match category {
         "Image" => summary.image_files += 1,
         "Audio" => summary.audio_files += 1,
         "Video" => summary.video_files += 1,
@@ -322,7 +589,7 @@ fn categorize_file_type(description: &str, summary: &mut FormatSummary) {
     }
 }
 
-fn is_complete_file_signature(description: &str) -> bool {
+pub(crate) fn is_complete_file_signature(description: &str) -> bool {
     let desc_lower = description.to_lowercase();
 
     // These indicate a complete file header, not just a fragment
@@ -337,123 +604,393 @@ fn is_complete_file_signature(description: &str) -> bool {
         || desc_lower.contains("video")
 }
 
+// Only search for complete file headers at reasonable boundaries
+// Skip short signatures that could be random data (like 0xFF 0xFB for MP3)
+const MANUAL_SIGNATURES: [(&[u8], &str); 21] = [
+    // Audio (only look for complete headers)
+    (&[0x52, 0x49, 0x46, 0x46], "RIFF container"), // Need to verify WAVE header
+    (&[0x49, 0x44, 0x33], "ID3 tag"),
+    (&[0x66, 0x4C, 0x61, 0x43], "FLAC audio"),
+    (&[0x4F, 0x67, 0x67, 0x53], "OGG audio"),
+    // Images (complete headers only)
+    (&[0xFF, 0xD8, 0xFF, 0xE0], "JPEG image (JFIF)"),
+    (&[0xFF, 0xD8, 0xFF, 0xE1], "JPEG image (Exif)"),
+    (
+        &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
+        "PNG image",
+    ),
+    (&[0x47, 0x49, 0x46, 0x38, 0x37, 0x61], "GIF87a image"),
+    (&[0x47, 0x49, 0x46, 0x38, 0x39, 0x61], "GIF89a image"),
+    // Documents
+    (&[0x25, 0x50, 0x44, 0x46, 0x2D], "PDF document"),
+    (&[0x50, 0x4B, 0x03, 0x04], "ZIP archive"),
+    // Archives (complete headers)
+    (&[0x52, 0x61, 0x72, 0x21, 0x1A, 0x07], "RAR archive"),
+    (&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C], "7-Zip archive"),
+    (&[0x1A, 0x45, 0xDF, 0xA3], "Webm/mkv"),
+    (&[0x66, 0x74, 0x79, 0x70], "Mp4"),
+    // Portable image formats (PGM, PBM, PPM, PNM)
+    (&[0x50, 0x35, 0x0A], "PGM image (P5 - binary)"), // P5\n
+    (&[0x50, 0x32, 0x0A], "PGM image (P2 - ASCII)"),  // P2\n
+    (&[0x50, 0x36, 0x0A], "PPM image (P6 - binary)"), // P6\n
+    (&[0x50, 0x33, 0x0A], "PPM image (P3 - ASCII)"),  // P3\n
+    (&[0x50, 0x34, 0x0A], "PBM image (P4 - binary)"), // P4\n
+    (&[0x50, 0x31, 0x0A], "PBM image (P1 - ASCII)"),  // P1\n
+];
+
+// The built-in signature database: binwalk's signatures and the manual scan's. Reports record
+// it so magic bytes findings can be traced to the signatures that made them; the fingerprint
+// changes with any signature added, removed or altered, e.g. by a binwalk upgrade.
+#[derive(Debug, Clone)]
+pub struct SignatureDatabase {
+    // Start of the SHA-256 over every signature's name, description and magic bytes
+    pub fingerprint: String,
+    pub signatures: usize,
+}
+
+pub fn signature_database() -> &'static SignatureDatabase {
+    static DATABASE: OnceLock<SignatureDatabase> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        let binwalk = shared::binwalk();
+        // A signature with several patterns is in the table once per pattern
+        let binwalk_signatures: BTreeMap<&str, &Signature> = binwalk
+            .short_signatures
+            .iter()
+            .chain(binwalk.pattern_signature_table.values())
+            .map(|signature| (signature.name.as_str(), signature))
+            .collect();
+
+        let mut hasher = Sha256::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        for signature in binwalk_signatures.values() {
+            field(signature.name.as_bytes());
+            field(signature.description.as_bytes());
+            field(&(signature.magic_offset as u64).to_le_bytes());
+            for magic in &signature.magic {
+                field(magic);
+            }
+        }
+        for (pattern, description) in MANUAL_SIGNATURES {
+            field(description.as_bytes());
+            field(pattern);
+        }
+
+        SignatureDatabase {
+            fingerprint: shared::hex(&hasher.finalize()[..8]),
+            signatures: binwalk_signatures.len() + MANUAL_SIGNATURES.len(),
+        }
+    })
+}
+
+// The manual scan reads its input this much at a time, so memory stays flat on huge files
+const SCAN_CHUNK_SIZE: usize = 1 << 20;
+// How much of the start of the file the binwalk and custom signature passes, which work on
+// one in-memory buffer, look at
+const WHOLE_BUFFER_SCAN_BYTES: usize = 64 << 20;
+// Bytes before a hit the checks look at: the MP4 box size and padding before a header
+const SCAN_LOOKBEHIND: usize = 4;
+// Bytes after a hit validation may read. The longest is a ZIP local file header with a
+// 1024-byte file name; every signature is shorter than this too.
+const SCAN_LOOKAHEAD: usize = 30 + 1024;
+
+fn manual_patterns() -> &'static PatternSet<&'static str> {
+    static PATTERNS: OnceLock<PatternSet<&'static str>> = OnceLock::new();
+    PATTERNS.get_or_init(|| PatternSet::new(MANUAL_SIGNATURES))
+}
+
 // Manual signature detection for formats binwalk might miss
 // This is more conservative to avoid false positives from compressed data
-fn manual_signature_scan(data: &[u8]) -> Vec<EmbeddedFile> {
+fn manual_signature_scan<R: Read>(
+    reader: R,
+    strictness: Strictness,
+) -> Result<Vec<EmbeddedFile>, MagicBytesError> {
+    scan_in_chunks(reader, strictness, SCAN_CHUNK_SIZE)
+}
+
+// Consecutive chunks overlap by the lookbehind and lookahead, so a signature straddling a
+// chunk boundary is still found and validated with all the bytes around it
+fn scan_in_chunks<R: Read>(
+    mut reader: R,
+    strictness: Strictness,
+    chunk_size: usize,
+) -> Result<Vec<EmbeddedFile>, MagicBytesError> {
+    let patterns = manual_patterns();
     let mut results = Vec::new();
+    // Per signature, where its next hit may start; a hit skips past itself like the
+    // byte-by-byte scan did
+    let mut next_allowed = [0usize; MANUAL_SIGNATURES.len()];
+    let mut buffer = Vec::with_capacity(chunk_size + SCAN_LOOKBEHIND + SCAN_LOOKAHEAD);
+    // File offset of buffer[0], and of the first byte whose hits haven't been looked at
+    let mut base = 0;
+    let mut searched = 0;
+
+    loop {
+        let wanted = buffer.len() + chunk_size;
+        reader
+            .by_ref()
+            .take(chunk_size as u64)
+            .read_to_end(&mut buffer)?;
+        let at_end = buffer.len() < wanted;
+
+        // Hits this close to the end wait for the next chunk, where validation can see past them
+        let limit = if at_end {
+            buffer.len()
+        } else {
+            buffer.len().saturating_sub(SCAN_LOOKAHEAD)
+        };
 
-    // Only search for complete file headers at reasonable boundaries
-    // Skip short signatures that could be random data (like 0xFF 0xFB for MP3)
-    let signatures: Vec<(Vec<u8>, &str, bool)> = vec![
-        // Audio (only look for complete headers)
-        (vec![0x52, 0x49, 0x46, 0x46], "RIFF container", true), // Need to verify WAVE header
-        (vec![0x49, 0x44, 0x33], "ID3 tag", true),              // Too short, skip
-        (vec![0x66, 0x4C, 0x61, 0x43], "FLAC audio", true),
-        (vec![0x4F, 0x67, 0x67, 0x53], "OGG audio", true),
-        // Images (complete headers only)
-        (vec![0xFF, 0xD8, 0xFF, 0xE0], "JPEG image (JFIF)", true),
-        (vec![0xFF, 0xD8, 0xFF, 0xE1], "JPEG image (Exif)", true),
-        (
-            vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
-            "PNG image",
-            true,
-        ),
-        (
-            vec![0x47, 0x49, 0x46, 0x38, 0x37, 0x61],
-            "GIF87a image",
-            true,
-        ),
-        (
-            vec![0x47, 0x49, 0x46, 0x38, 0x39, 0x61],
-            "GIF89a image",
-            true,
-        ),
-        // Documents
-        (vec![0x25, 0x50, 0x44, 0x46, 0x2D], "PDF document", true),
-        (vec![0x50, 0x4B, 0x03, 0x04], "ZIP archive", true),
-        // Archives (complete headers)
-        (
-            vec![0x52, 0x61, 0x72, 0x21, 0x1A, 0x07],
-            "RAR archive",
-            true,
-        ),
-        (
-            vec![0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C],
-            "7-Zip archive",
-            true,
-        ),
-        (vec![0x1A, 0x45, 0xDF, 0xA3], "Webm/mkv", true),
-        (vec![0x66, 0x74, 0x79, 0x70], "Mp4", true),
-        // Add these after the existing image signatures:
-        // Portable image formats (PGM, PBM, PPM, PNM)
-        (vec![0x50, 0x35, 0x0A], "PGM image (P5 - binary)", true), // P5\n
-        (vec![0x50, 0x32, 0x0A], "PGM image (P2 - ASCII)", true),  // P2\n
-        (vec![0x50, 0x36, 0x0A], "PPM image (P6 - binary)", true), // P6\n
-        (vec![0x50, 0x33, 0x0A], "PPM image (P3 - ASCII)", true),  // P3\n
-        (vec![0x50, 0x34, 0x0A], "PBM image (P4 - binary)", true), // P4\n
-        (vec![0x50, 0x31, 0x0A], "PBM image (P1 - ASCII)", true),  // P1\n
-    ];
-
-    // Search for signatures, but be smart about it
-    for (signature, description, should_scan) in &signatures {
-        if !should_scan {
-            continue; // Skip signatures prone to false positives
-        }
-
-        let mut pos = 0;
-        while pos <= data.len().saturating_sub(signature.len()) {
-            if data[pos..].starts_with(signature) {
-                // Additional validation for RIFF containers
-                if description.contains("RIFF") {
-                    if pos + 12 <= data.len() {
-                        let riff_type = &data[pos + 8..pos + 12];
-                        if riff_type == b"WAVE" {
-                            results.push(EmbeddedFile {
-                                offset: pos,
-                                description: "WAV audio (RIFF/WAVE)".to_string(),
-                                file_type: "Audio".to_string(),
-                                confidence: "high".to_string(),
-                            });
-                        } else if riff_type == b"AVI " {
-                            results.push(EmbeddedFile {
-                                offset: pos,
-                                description: "AVI video (RIFF)".to_string(),
-                                file_type: "Video".to_string(),
-                                confidence: "high".to_string(),
-                            });
-                        } else if riff_type == b"WEBP" {
-                            results.push(EmbeddedFile {
-                                offset: pos,
-                                description: "WebP image (RIFF)".to_string(),
-                                file_type: "Image".to_string(),
-                                confidence: "high".to_string(),
-                            });
-                        }
-                    }
-                } else {
-                    // For other signatures, only report if at a reasonable offset
-                    // Skip if it's in the middle of compressed data (likely false positive)
-                    if pos == 0 || is_likely_real_file(data, pos, signature.len()) {
-                        results.push(EmbeddedFile {
-                            offset: pos,
-                            description: description.to_string(),
-                            file_type: determine_file_category(description).to_string(),
-                            confidence: "medium".to_string(),
-                        });
-                    }
-                }
-                pos += signature.len();
-            } else {
-                pos += 1;
+        for hit in patterns.find_all(&buffer) {
+            let pos = hit.start;
+            let offset = base + pos;
+            if offset < searched || pos >= limit || offset < next_allowed[hit.index] {
+                continue;
+            }
+            next_allowed[hit.index] = base + hit.end;
+
+            if let Some(result) = check_hit(&buffer, pos, offset, hit.value, strictness) {
+                results.push(result);
+            }
+        }
+
+        if at_end {
+            break;
+        }
+        searched = base + limit;
+        let keep_from = limit.saturating_sub(SCAN_LOOKBEHIND);
+        buffer.drain(..keep_from);
+        base += keep_from;
+    }
+
+    Ok(results)
+}
+
+// `pos` is where the hit sits in `window` and `offset` where it sits in the file
+fn check_hit(
+    window: &[u8],
+    pos: usize,
+    offset: usize,
+    description: &str,
+    strictness: Strictness,
+) -> Option<EmbeddedFile> {
+    // Additional validation for RIFF containers
+    if description.contains("RIFF") {
+        let (description, file_type) = match window.get(pos + 8..pos + 12)? {
+            b"WAVE" => ("WAV audio (RIFF/WAVE)", "Audio"),
+            b"AVI " => ("AVI video (RIFF)", "Video"),
+            b"WEBP" => ("WebP image (RIFF)", "Image"),
+            _ => return None,
+        };
+        return Some(EmbeddedFile {
+            offset,
+            description: description.to_string(),
+            file_type: file_type.to_string(),
+            confidence: "high".to_string(),
+        });
+    }
+
+    // For other signatures, only report if the header holds up; magic bytes turn up by
+    // chance inside compressed data
+    if offset != 0 && !accept_hit(window, pos, offset, description, strictness) {
+        return None;
+    }
+    Some(EmbeddedFile {
+        offset,
+        description: description.to_string(),
+        file_type: determine_file_category(description).to_string(),
+        confidence: "medium".to_string(),
+    })
+}
+
+// Each signature is looked up by its longest run of exact bytes in one pass over the file and
+// checked in full wherever that run turns up. A signature pinned to one offset is checked there
+// directly, and one that is all wildcards, with nothing to look up, at every offset it allows.
+fn custom_signature_scan(data: &[u8], signatures: &[CustomSignature]) -> Vec<EmbeddedFile> {
+    let mut matches = Vec::new();
+    let mut anchors = Vec::new();
+
+    for (index, signature) in signatures.iter().enumerate() {
+        let last = data.len().saturating_sub(signature.pattern.len());
+        let (start, end) = match signature.offset {
+            Some(offset) => (offset, offset),
+            None => (0, signature.max_offset.unwrap_or(last).min(last)),
+        };
+
+        match signature.anchor() {
+            Some(anchor) if signature.offset.is_none() => {
+                let bytes = &signature.pattern[anchor.clone()];
+                anchors.push((bytes, (index, anchor.start, end)));
             }
+            _ => matches.extend(
+                (start..=end)
+                    .filter(|&pos| signature.matches_at(data, pos))
+                    .map(|pos| (index, pos)),
+            ),
+        }
+    }
+
+    let patterns = PatternSet::new(anchors);
+    for hit in patterns.find_all(data) {
+        let (index, anchor_start, end) = *hit.value;
+        let Some(pos) = hit.start.checked_sub(anchor_start) else {
+            continue;
+        };
+        if pos <= end && signatures[index].matches_at(data, pos) {
+            matches.push((index, pos));
         }
     }
 
-    results
+    // Same order as checking one signature after another, so the first of two signatures
+    // matching at one offset still wins
+    matches.sort_unstable();
+    matches.dedup();
+    matches
+        .into_iter()
+        .map(|(index, pos)| EmbeddedFile {
+            offset: pos,
+            description: signatures[index].description.clone(),
+            file_type: signatures[index].category.clone(),
+            confidence: "medium".to_string(),
+        })
+        .collect()
+}
+
+fn accept_hit(
+    data: &[u8],
+    pos: usize,
+    offset: usize,
+    description: &str,
+    strictness: Strictness,
+) -> bool {
+    let structure = validate_structure(data, pos, description);
+    match (strictness, structure) {
+        (Strictness::Lenient, Some(true)) => true,
+        (Strictness::Lenient, _) => is_likely_real_file(data, pos, offset),
+        (Strictness::Normal, Some(valid)) => valid,
+        (Strictness::Normal, None) => is_likely_real_file(data, pos, offset),
+        (Strictness::Strict, structure) => structure == Some(true),
+    }
+}
+
+// Parse the first few header fields after a manual-scan hit and check they are sane.
+// None when there is no validator for the format.
+fn validate_structure(data: &[u8], offset: usize, description: &str) -> Option<bool> {
+    let bytes = &data[offset..];
+    let u16_le = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u16_be = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let u32_be = |at: usize| u32_be_at(bytes, at);
+    let u32_le = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    let valid = match description {
+        // IHDR must come first, be 13 bytes long, hold sane fields and carry a matching CRC
+        "PNG image" => {
+            bytes.get(12..16) == Some(b"IHDR")
+                && u32_be(8) == Some(13)
+                && u32_be(16).is_some_and(|w| w > 0)
+                && u32_be(20).is_some_and(|h| h > 0)
+                && bytes.get(24).is_some_and(|d| [1, 2, 4, 8, 16].contains(d))
+                && bytes.get(25).is_some_and(|c| [0, 2, 3, 4, 6].contains(c))
+                && bytes
+                    .get(12..29)
+                    .zip(u32_be(29))
+                    .is_some_and(|(chunk, crc)| crc32fast::hash(chunk) == crc)
+        }
+        "JPEG image (JFIF)" => {
+            u16_be(4).is_some_and(|len| len >= 16) && bytes.get(6..11) == Some(b"JFIF\0")
+        }
+        "JPEG image (Exif)" => {
+            u16_be(4).is_some_and(|len| len >= 8)
+                && matches!(bytes.get(6..10), Some(b"Exif") | Some(b"http"))
+        }
+        "GIF87a image" | "GIF89a image" => {
+            u16_le(6).is_some_and(|w| w > 0) && u16_le(8).is_some_and(|h| h > 0)
+        }
+        "PDF document" => matches!(
+            bytes.get(5..8),
+            Some([major, b'.', minor]) if major.is_ascii_digit() && minor.is_ascii_digit()
+        ),
+        // Local file header: known compression method and a short, printable file name
+        "ZIP archive" => {
+            u16_le(4).is_some_and(|version| version <= 63)
+                && u16_le(8)
+                    .is_some_and(|method| [0, 1, 6, 8, 9, 12, 14, 93, 95, 98, 99].contains(&method))
+                && u16_le(26).is_some_and(|name_len| {
+                    let name_len = name_len as usize;
+                    name_len > 0
+                        && name_len <= 1024
+                        && bytes
+                            .get(30..30 + name_len)
+                            .is_some_and(|name| name.iter().all(|&b| b >= 0x20 && b != 0x7F))
+                })
+        }
+        // RAR 4 has a 0x00 after the marker, RAR 5 has 0x01 0x00
+        "RAR archive" => matches!(bytes.get(6..8), Some([0x00, _]) | Some([0x01, 0x00])),
+        // The start header CRC covers the 20 bytes after it
+        "7-Zip archive" => {
+            bytes.get(6) == Some(&0)
+                && bytes
+                    .get(12..32)
+                    .zip(u32_le(8))
+                    .is_some_and(|(header, crc)| crc32fast::hash(header) == crc)
+        }
+        // STREAMINFO must be the first metadata block and is always 34 bytes
+        "FLAC audio" => {
+            bytes.get(4).is_some_and(|b| b & 0x7F == 0)
+                && bytes.get(5..8) == Some(&[0x00, 0x00, 0x22])
+        }
+        "OGG audio" => bytes.get(4) == Some(&0) && bytes.get(5).is_some_and(|&t| t <= 7),
+        // Major version 2-4 and a syncsafe size (no byte with the high bit set)
+        "ID3 tag" => {
+            bytes.get(3).is_some_and(|v| (2..=4).contains(v))
+                && bytes.get(4).is_some_and(|&r| r != 0xFF)
+                && bytes
+                    .get(6..10)
+                    .is_some_and(|size| size.iter().all(|&b| b < 0x80))
+        }
+        // The EBML header size that follows is a variable-length integer, never 0x00
+        "Webm/mkv" => bytes.get(4).is_some_and(|&b| b != 0),
+        // The hit is on "ftyp"; the box size sits in the 4 bytes before it
+        "Mp4" => {
+            offset >= 4
+                && u32_be_at(data, offset - 4).is_some_and(|size| (8..=4096).contains(&size))
+                && bytes.get(4..8).is_some_and(|brand| {
+                    brand
+                        .iter()
+                        .all(|&b| b.is_ascii_alphanumeric() || b == b' ')
+                })
+        }
+        // Netpbm headers continue with a width in ASCII digits or a comment
+        d if d.starts_with("PGM") || d.starts_with("PPM") || d.starts_with("PBM") => bytes
+            .get(3)
+            .is_some_and(|&b| b.is_ascii_digit() || b == b'#'),
+        _ => return None,
+    };
+
+    Some(valid)
+}
+
+fn u32_be_at(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
 }
 
-// Check if a signature at this offset is likely a real file, not random compressed data
-fn is_likely_real_file(data: &[u8], offset: usize, _sig_len: usize) -> bool {
+// Check if a signature at this offset is likely a real file, not random compressed data.
+// `data` may be a window of the file: `pos` indexes it, `offset` is the position in the file.
+fn is_likely_real_file(data: &[u8], pos: usize, offset: usize) -> bool {
     // If it's at the very start, it's likely real
     if offset == 0 {
         return true;
@@ -461,8 +998,8 @@ fn is_likely_real_file(data: &[u8], offset: usize, _sig_len: usize) -> bool {
 
     // Check if the data before this offset looks like padding or alignment
     // Real embedded files are often aligned or have recognizable patterns before them
-    if offset >= 4 {
-        let before = &data[offset.saturating_sub(4)..offset];
+    if pos >= 4 {
+        let before = &data[pos - 4..pos];
         // Check for common padding patterns
         if before.iter().all(|&b| b == 0x00) {
             return true; // Null padding before file
@@ -558,6 +1095,13 @@ fn detect_format_at_offset(data: &[u8], offset: usize) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_signature_database() {
+        let database = signature_database();
+        assert_eq!(database.fingerprint.len(), 16);
+        assert!(database.signatures > MANUAL_SIGNATURES.len());
+    }
+
     #[test]
     fn test_file_categorization() {
         assert_eq!(determine_file_category("JPEG image data"), "Image");
@@ -568,6 +1112,166 @@ mod tests {
         assert_eq!(determine_file_category("ZIP archive"), "Archive");
     }
 
+    #[test]
+    fn test_custom_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let definitions = dir.path().join("signatures.yaml");
+        std::fs::write(
+            &definitions,
+            r#"
+signatures:
+  - description: Acme firmware header
+    category: Executable
+    pattern: "41 43 4D 45 ?? 01"
+  - description: Acme config block
+    pattern: "C0 DE"
+    offset: 0
+"#,
+        )
+        .unwrap();
+        let signatures = CustomSignature::load(&definitions).unwrap();
+        assert_eq!(
+            signatures[0].mask,
+            Some(vec![0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF])
+        );
+        assert_eq!(signatures[1].category, "Other");
+
+        let mut data = vec![0x11u8; 64];
+        data[20..26].copy_from_slice(b"ACME\x07\x01");
+        data[40..42].copy_from_slice(&[0xC0, 0xDE]);
+        let results = custom_signature_scan(&data, &signatures);
+        // The config block only counts at offset 0
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].offset, 20);
+        assert_eq!(results[0].file_type, "Executable");
+
+        let bad = dir.path().join("bad.json");
+        std::fs::write(
+            &bad,
+            r#"{"signatures": [{"description": "x", "pattern": "ABC"}]}"#,
+        )
+        .unwrap();
+        assert!(CustomSignature::load(&bad).is_err());
+    }
+
+    #[test]
+    fn test_custom_signatures_anchor_on_exact_bytes() {
+        let signature = |pattern: &[u8], mask: Option<Vec<u8>>, max_offset| CustomSignature {
+            description: "test".to_string(),
+            category: "Other".to_string(),
+            pattern: pattern.to_vec(),
+            mask,
+            offset: None,
+            max_offset,
+        };
+        let signatures = [
+            signature(
+                b"AB?CDE",
+                Some(vec![0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF]),
+                None,
+            ),
+            signature(b"\x10\x20", Some(vec![0xF0, 0xF0]), None),
+            signature(b"XYZ", None, Some(30)),
+        ];
+        assert_eq!(signatures[0].anchor(), Some(3..6));
+        assert_eq!(signatures[1].anchor(), None);
+        assert_eq!(signatures[2].anchor(), Some(0..3));
+
+        let mut data = vec![0u8; 64];
+        data[0..2].copy_from_slice(b"DE");
+        data[5..11].copy_from_slice(b"AB!CDE");
+        data[20..22].copy_from_slice(&[0x1F, 0x2A]);
+        data[25..28].copy_from_slice(b"XYZ");
+        data[40..43].copy_from_slice(b"XYZ");
+        let found: Vec<_> = custom_signature_scan(&data, &signatures)
+            .iter()
+            .map(|r| r.offset)
+            .collect();
+        assert_eq!(found, vec![5, 20, 25]);
+    }
+
+    #[test]
+    fn test_structural_validation() {
+        let ihdr = [b"IHDR".as_slice(), &[0, 0, 0, 4, 0, 0, 0, 4, 8, 2, 0, 0, 0]].concat();
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d".to_vec();
+        png.extend_from_slice(&ihdr);
+        png.extend_from_slice(&crc32fast::hash(&ihdr).to_be_bytes());
+
+        // A real PNG at an unaligned offset, after bytes that aren't padding
+        let mut data = vec![0x5Au8; 1000];
+        data[333..333 + png.len()].copy_from_slice(&png);
+        // PNG magic with a broken IHDR, at a 512-byte boundary the old heuristic trusted
+        data[512..520].copy_from_slice(b"\x89PNG\r\n\x1a\n");
+
+        let offsets = |data: &[u8], strictness| {
+            manual_signature_scan(data, strictness)
+                .unwrap()
+                .iter()
+                .map(|r| r.offset)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(&data, Strictness::Normal), vec![333]);
+        assert_eq!(offsets(&data, Strictness::Strict), vec![333]);
+        assert_eq!(offsets(&data, Strictness::Lenient), vec![333, 512]);
+
+        // Unaligned "P5\n" with no validator-approved header behind it
+        data[700..704].copy_from_slice(b"P5\nx");
+        assert!(!offsets(&data, Strictness::Normal).contains(&700));
+        assert_eq!("Strict".parse::<Strictness>(), Ok(Strictness::Strict));
+    }
+
+    #[test]
+    fn test_chunked_scan_matches_across_boundaries() {
+        let ihdr = [b"IHDR".as_slice(), &[0, 0, 0, 4, 0, 0, 0, 4, 8, 2, 0, 0, 0]].concat();
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d".to_vec();
+        png.extend_from_slice(&ihdr);
+        png.extend_from_slice(&crc32fast::hash(&ihdr).to_be_bytes());
+
+        let mut data = vec![0x5Au8; 5000];
+        for offset in [1195, 2047, 3500] {
+            data[offset..offset + png.len()].copy_from_slice(&png);
+        }
+        data[4096..4104].copy_from_slice(b"\0\0\0\0RIFF");
+        data[4108..4112].copy_from_slice(b"WAVE");
+        // Cut short, so the IHDR check runs out of bytes at the end of the input
+        data[4990..4998].copy_from_slice(b"\x89PNG\r\n\x1a\n");
+
+        let offsets = |chunk_size| {
+            scan_in_chunks(data.as_slice(), Strictness::Normal, chunk_size)
+                .unwrap()
+                .iter()
+                .map(|r| r.offset)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(data.len()), vec![1195, 2047, 3500, 4100]);
+        for chunk_size in [1, 7, 100, 1024, 2048] {
+            assert_eq!(
+                offsets(chunk_size),
+                offsets(data.len()),
+                "chunk {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_path_scan_streams_the_file() {
+        let ihdr = [b"IHDR".as_slice(), &[0, 0, 0, 4, 0, 0, 0, 4, 8, 2, 0, 0, 0]].concat();
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d".to_vec();
+        png.extend_from_slice(&ihdr);
+        png.extend_from_slice(&crc32fast::hash(&ihdr).to_be_bytes());
+        let mut data = vec![0x5Au8; 3 * SCAN_CHUNK_SIZE];
+        let offset = 2 * SCAN_CHUNK_SIZE - 8;
+        data[offset..offset + png.len()].copy_from_slice(&png);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+        let analysis = MagicBytesAnalyzerWithPath::new(file.path())
+            .analyze()
+            .unwrap();
+        assert!(analysis.embedded_files.iter().any(|f| f.offset == offset));
+    }
+
     #[test]
     fn test_complete_signature_detection() {
         assert!(is_complete_file_signature("JPEG image data"));
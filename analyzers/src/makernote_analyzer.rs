@@ -0,0 +1,454 @@
+use std::fmt::Display;
+
+// MakerNote walker. The MakerNote is an opaque EXIF blob that each camera vendor fills
+// with its own IFD, so it is rarely validated and a favored hiding spot. The Canon, Nikon
+// and Sony layouts are walked like a TIFF IFD and any MakerNote bytes that no entry
+// accounts for, entries pointing outside it and oversized notes are reported with their
+// offsets. Offsets are relative to the start of the EXIF (TIFF) block.
+
+// A MakerNote larger than this, not counting an embedded preview image, is reported
+pub const MAX_MAKER_NOTE_BYTES: usize = 128 * 1024;
+
+// Unreferenced regions shorter than this are treated as alignment padding
+pub const MIN_GAP_BYTES: usize = 64;
+
+// Sanity limit on entries per IFD; real MakerNotes have at most a few hundred
+const MAX_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MakerNoteLayout {
+    Canon,
+    // "Nikon\0\x01": IFD after an 8-byte header, offsets relative to the EXIF block
+    NikonType1,
+    // "Nikon\0\x02": an embedded TIFF header, offsets relative to that header
+    NikonType3,
+    Sony,
+    // Unknown vendor whose MakerNote still parses as a plain IFD
+    Generic,
+    // Unknown vendor and not an IFD; only its size is checked
+    Opaque,
+}
+
+impl Display for MakerNoteLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MakerNoteLayout::Canon => "Canon",
+            MakerNoteLayout::NikonType1 => "Nikon (type 1)",
+            MakerNoteLayout::NikonType3 => "Nikon (type 3)",
+            MakerNoteLayout::Sony => "Sony",
+            MakerNoteLayout::Generic => "generic IFD",
+            MakerNoteLayout::Opaque => "opaque",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MakerNoteEntry {
+    pub tag: u16,
+    pub type_code: u16,
+    pub count: u32,
+    // Where the value is stored when it does not fit in the entry itself
+    pub value_offset: Option<usize>,
+    pub value_size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct MakerNoteFinding {
+    pub offset: usize,
+    pub size: usize,
+    pub description: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MakerNoteAnalysis {
+    pub layout: MakerNoteLayout,
+    pub offset: usize,
+    pub size: usize,
+    pub entries: Vec<MakerNoteEntry>,
+    // MakerNote bytes covered by headers, IFDs and entry values
+    pub accounted_bytes: usize,
+    pub preview_bytes: usize,
+    pub findings: Vec<MakerNoteFinding>,
+    pub suspicious: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Reader<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(at..at.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+}
+
+// Bytes per component of a TIFF field type, None for unknown types
+fn type_size(type_code: u16) -> Option<usize> {
+    match type_code {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+struct Walk<'a> {
+    reader: Reader<'a>,
+    layout: MakerNoteLayout,
+    // MakerNote bounds within the EXIF block
+    start: usize,
+    end: usize,
+    covered: Vec<(usize, usize)>,
+    preview_bytes: usize,
+    entries: Vec<MakerNoteEntry>,
+    findings: Vec<MakerNoteFinding>,
+}
+
+impl Walk<'_> {
+    fn finding(&mut self, offset: usize, size: usize, description: String) {
+        self.findings.push(MakerNoteFinding {
+            offset,
+            size,
+            description,
+        });
+    }
+
+    fn inside(&self, offset: usize, size: usize) -> bool {
+        offset >= self.start && offset.saturating_add(size) <= self.end
+    }
+
+    // Whether `at` looks like the start of an IFD: a sane entry count, entries that fit
+    // in the MakerNote and mostly known field types
+    fn plausible_ifd(&self, at: usize) -> bool {
+        let Some(count) = self.reader.u16(at).map(usize::from) else {
+            return false;
+        };
+        if count == 0 || count > MAX_ENTRIES || !self.inside(at, 2 + 12 * count) {
+            return false;
+        }
+        let known = (0..count)
+            .filter_map(|i| self.reader.u16(at + 2 + 12 * i + 2))
+            .filter(|&type_code| type_size(type_code).is_some())
+            .count();
+        known * 5 >= count * 4
+    }
+
+    // Walk one IFD at `at`, resolving value offsets against `base`
+    fn ifd(&mut self, at: usize, base: usize, depth: usize) {
+        let Some(count) = self.reader.u16(at).map(usize::from) else {
+            self.finding(
+                at,
+                2,
+                "IFD starts past the end of the EXIF block".to_string(),
+            );
+            return;
+        };
+        if count > MAX_ENTRIES {
+            self.finding(at, 2, format!("IFD declares {} entries", count));
+            return;
+        }
+        let ifd_size = 2 + 12 * count + 4;
+        if !self.inside(at, ifd_size) {
+            self.finding(
+                at,
+                ifd_size,
+                format!("IFD with {} entries runs past the MakerNote", count),
+            );
+            return;
+        }
+        self.covered.push((at, ifd_size));
+
+        let mut preview_start = None;
+        let mut preview_length = None;
+        for i in 0..count {
+            let entry_at = at + 2 + 12 * i;
+            let tag = self.reader.u16(entry_at).unwrap_or(0);
+            let type_code = self.reader.u16(entry_at + 2).unwrap_or(0);
+            let components = self.reader.u32(entry_at + 4).unwrap_or(0);
+            let raw_value = self.reader.u32(entry_at + 8).unwrap_or(0);
+
+            let Some(value_size) =
+                type_size(type_code).and_then(|size| size.checked_mul(components as usize))
+            else {
+                self.finding(
+                    entry_at,
+                    12,
+                    format!("tag 0x{:04X} has unknown field type {}", tag, type_code),
+                );
+                continue;
+            };
+            let value_offset = (value_size > 4).then(|| base.saturating_add(raw_value as usize));
+            self.entries.push(MakerNoteEntry {
+                tag,
+                type_code,
+                count: components,
+                value_offset,
+                value_size,
+            });
+
+            if let Some(offset) = value_offset {
+                if offset.saturating_add(value_size) > self.reader.data.len() {
+                    self.finding(
+                        offset,
+                        value_size,
+                        format!(
+                            "tag 0x{:04X} value runs past the end of the EXIF block",
+                            tag
+                        ),
+                    );
+                } else if !self.inside(offset, value_size) {
+                    self.finding(
+                        offset,
+                        value_size,
+                        format!("tag 0x{:04X} value is stored outside the MakerNote", tag),
+                    );
+                } else {
+                    self.covered.push((offset, value_size));
+                }
+            }
+
+            match (self.layout, depth, tag) {
+                // Nikon keeps its JPEG preview behind a sub-IFD inside the MakerNote
+                (MakerNoteLayout::NikonType3, 0, 0x0011) => {
+                    self.ifd(base.saturating_add(raw_value as usize), base, depth + 1);
+                }
+                (MakerNoteLayout::NikonType3, 1, 0x0201) => preview_start = Some(raw_value),
+                (MakerNoteLayout::NikonType3, 1, 0x0202) => preview_length = Some(raw_value),
+                _ => {}
+            }
+        }
+
+        if let (Some(start), Some(length)) = (preview_start, preview_length) {
+            let offset = base.saturating_add(start as usize);
+            if self.inside(offset, length as usize) {
+                self.covered.push((offset, length as usize));
+                self.preview_bytes += length as usize;
+            } else {
+                self.finding(
+                    offset,
+                    length as usize,
+                    "preview image lies outside the MakerNote".to_string(),
+                );
+            }
+        }
+    }
+
+    // MakerNote regions not covered by any header, IFD or value
+    fn gaps(&self) -> Vec<(usize, usize)> {
+        let mut covered = self.covered.clone();
+        covered.sort_unstable();
+        let mut gaps = Vec::new();
+        let mut cursor = self.start;
+        for (offset, size) in covered {
+            if offset > cursor {
+                gaps.push((cursor, offset - cursor));
+            }
+            cursor = cursor.max(offset + size);
+        }
+        if cursor < self.end {
+            gaps.push((cursor, self.end - cursor));
+        }
+        gaps
+    }
+
+    fn accounted_bytes(&self) -> usize {
+        let gap_bytes: usize = self.gaps().iter().map(|(_, size)| size).sum();
+        (self.end - self.start) - gap_bytes
+    }
+}
+
+// Walk the MakerNote stored at `offset..offset + size` of an EXIF (TIFF) block. `make` is
+// the camera's Make tag and picks the layout when the note has no vendor header.
+pub fn analyze_maker_note(
+    tiff: &[u8],
+    little_endian: bool,
+    offset: usize,
+    size: usize,
+    make: &str,
+) -> MakerNoteAnalysis {
+    let end = offset.saturating_add(size).min(tiff.len());
+    let start = offset.min(end);
+    let note = &tiff[start..end];
+    let mut walk = Walk {
+        reader: Reader {
+            data: tiff,
+            little_endian,
+        },
+        layout: MakerNoteLayout::Opaque,
+        start,
+        end,
+        covered: Vec::new(),
+        preview_bytes: 0,
+        entries: Vec::new(),
+        findings: Vec::new(),
+    };
+
+    let make = make.trim().to_ascii_lowercase();
+    if note.starts_with(b"Nikon\0\x02") {
+        walk.layout = MakerNoteLayout::NikonType3;
+        // 10-byte header, then a TIFF header with its own byte order
+        let base = start + 10;
+        match note.get(10..12) {
+            Some(b"II") => walk.reader.little_endian = true,
+            Some(b"MM") => walk.reader.little_endian = false,
+            _ => walk.finding(
+                start,
+                note.len().min(18),
+                "bad embedded TIFF header".to_string(),
+            ),
+        }
+        if let Some(ifd) = walk.reader.u32(base + 4) {
+            walk.covered.push((start, 18));
+            walk.ifd(base.saturating_add(ifd as usize), base, 0);
+        }
+    } else if note.starts_with(b"Nikon\0\x01") {
+        walk.layout = MakerNoteLayout::NikonType1;
+        walk.covered.push((start, 8));
+        walk.ifd(start + 8, 0, 0);
+    } else if note.starts_with(b"SONY DSC \0\0\0") || note.starts_with(b"SONY CAM \0\0\0") {
+        walk.layout = MakerNoteLayout::Sony;
+        walk.covered.push((start, 12));
+        walk.ifd(start + 12, 0, 0);
+    } else if make.starts_with("canon") {
+        walk.layout = MakerNoteLayout::Canon;
+        walk.ifd(start, 0, 0);
+    } else if make.starts_with("sony") {
+        walk.layout = MakerNoteLayout::Sony;
+        walk.ifd(start, 0, 0);
+    } else if walk.plausible_ifd(start) {
+        walk.layout = MakerNoteLayout::Generic;
+        walk.ifd(start, 0, 0);
+    }
+
+    let layout = walk.layout;
+    if layout != MakerNoteLayout::Opaque {
+        for (gap_offset, gap_size) in walk.gaps() {
+            let gap = &tiff[gap_offset..gap_offset + gap_size];
+            // Zero-filled gaps are padding left by the camera
+            if gap_size >= MIN_GAP_BYTES && gap.iter().any(|&b| b != 0) {
+                walk.finding(
+                    gap_offset,
+                    gap_size,
+                    "data not referenced by any MakerNote entry".to_string(),
+                );
+            }
+        }
+    }
+
+    let own_bytes = note.len() - walk.preview_bytes.min(note.len());
+    if own_bytes > MAX_MAKER_NOTE_BYTES {
+        walk.finding(
+            start,
+            note.len(),
+            format!(
+                "oversized {} MakerNote ({} bytes without preview)",
+                layout, own_bytes
+            ),
+        );
+    }
+
+    let accounted_bytes = if layout == MakerNoteLayout::Opaque {
+        0
+    } else {
+        walk.accounted_bytes()
+    };
+    let suspicious = !walk.findings.is_empty();
+    MakerNoteAnalysis {
+        layout,
+        offset: start,
+        size: note.len(),
+        entries: walk.entries,
+        accounted_bytes,
+        preview_bytes: walk.preview_bytes,
+        findings: walk.findings,
+        suspicious,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Little-endian IFD with the given (tag, type, count, value) entries
+    fn ifd(entries: &[(u16, u16, u32, u32)]) -> Vec<u8> {
+        let mut out = (entries.len() as u16).to_le_bytes().to_vec();
+        for &(tag, type_code, count, value) in entries {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&type_code.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn test_canon_clean_and_hidden_gap() {
+        // The MakerNote starts at offset 100 of the EXIF block; one 8-byte ASCII value
+        // follows the IFD
+        let mut tiff = vec![0u8; 100];
+        let mut note = ifd(&[(0x0001, 3, 1, 7), (0x0006, 2, 8, 100 + 30)]);
+        note.extend_from_slice(b"EOS 5D\0\0");
+        tiff.extend_from_slice(&note);
+        let clean = analyze_maker_note(&tiff, true, 100, note.len(), "Canon");
+        assert_eq!(clean.layout, MakerNoteLayout::Canon);
+        assert_eq!(clean.entries.len(), 2);
+        assert_eq!(clean.entries[1].value_offset, Some(130));
+        assert_eq!(clean.accounted_bytes, note.len());
+        assert!(!clean.suspicious);
+
+        tiff.extend_from_slice(&[0xA5; 200]);
+        let hidden = analyze_maker_note(&tiff, true, 100, note.len() + 200, "Canon");
+        assert!(hidden.suspicious);
+        assert_eq!(hidden.findings.len(), 1);
+        assert_eq!(hidden.findings[0].offset, 100 + note.len());
+        assert_eq!(hidden.findings[0].size, 200);
+    }
+
+    #[test]
+    fn test_nikon_type3_preview_and_bad_pointer() {
+        // Header, embedded little-endian TIFF header with the IFD right after it
+        let mut note = b"Nikon\0\x02\x10\0\0II\x2a\0\x08\0\0\0".to_vec();
+        // Main IFD: preview IFD pointer and a value pointing far past the block
+        let main = ifd(&[(0x0011, 4, 1, 8 + 30), (0x0099, 7, 64, 0x10000)]);
+        note.extend_from_slice(&main);
+        // Preview IFD with a 100-byte JPEG right after it
+        let preview = ifd(&[(0x0201, 4, 1, 8 + 30 + 30), (0x0202, 4, 1, 100)]);
+        note.extend_from_slice(&preview);
+        note.extend_from_slice(&[0xFF; 100]);
+
+        let analysis = analyze_maker_note(&note, false, 0, note.len(), "NIKON CORPORATION");
+        assert_eq!(analysis.layout, MakerNoteLayout::NikonType3);
+        assert_eq!(analysis.preview_bytes, 100);
+        assert_eq!(analysis.findings.len(), 1);
+        assert_eq!(analysis.findings[0].offset, 10 + 0x10000);
+        assert_eq!(analysis.findings[0].size, 64);
+    }
+
+    #[test]
+    fn test_unknown_vendor_blob_is_opaque() {
+        let blob = vec![0x42; MAX_MAKER_NOTE_BYTES + 1];
+        let analysis = analyze_maker_note(&blob, true, 0, blob.len(), "Acme");
+        assert_eq!(analysis.layout, MakerNoteLayout::Opaque);
+        assert_eq!(analysis.findings.len(), 1);
+        assert!(analysis.findings[0].description.starts_with("oversized"));
+    }
+}
@@ -0,0 +1,687 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::pattern_set::PatternSet;
+use crate::payload_sniffer::sniff;
+use serde_json::Value;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// Data 3D asset files carry besides the model. glTF keeps geometry in buffers that buffer
+// views slice up, and a GLB packs the JSON and the first buffer into chunks of one file:
+// bytes no view covers, buffers nothing uses, chunks of a type loaders skip and blobs in the
+// free-form `extras` objects all go unread. A binary STL is a run of 50-byte triangle records
+// whose last two bytes, the "attribute byte count", nothing but a few colour extensions reads,
+// and data past the last record or an ASCII model's `endsolid` is ignored too.
+//
+// Buffers a .gltf file references by path aren't opened; their URIs are listed.
+pub struct ModelAnalyzer;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_HEADER_BYTES: usize = 12;
+const GLB_CHUNK_HEADER_BYTES: usize = 8;
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+const STL_HEADER_BYTES: usize = 80;
+const STL_TRIANGLE_BYTES: usize = 50;
+// Offset of the attribute byte count within a triangle record
+const STL_ATTRIBUTE_OFFSET: usize = 48;
+// VisCAM and SolidView store a 15-bit colour in the attribute bytes and set bit 15 when it's
+// valid; Materialise Magics says "COLOR=" in the header instead
+const STL_COLOR_VALID: u16 = 0x8000;
+const STL_COLOR_HEADER: &[u8] = b"COLOR=";
+
+// Strings in extras shorter than this aren't decoded
+pub const MIN_BLOB_CHARS: usize = 64;
+
+#[derive(Debug)]
+pub enum ModelError {
+    Malformed(String),
+    Json(String),
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelError::Malformed(e) => write!(f, "Malformed model: {}", e),
+            ModelError::Json(e) => write!(f, "Invalid glTF JSON: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    Gltf,
+    Glb,
+    Stl,
+}
+
+impl Display for ModelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelFormat::Gltf => write!(f, "glTF"),
+            ModelFormat::Glb => write!(f, "GLB"),
+            ModelFormat::Stl => write!(f, "STL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HiddenRegion {
+    // Where the bytes were found: "buffer 0 bytes 96..160", "/nodes/2/extras/blob" and so on
+    pub location: String,
+    // File offset of the bytes when they are stored as they are, not encoded in JSON or
+    // spread over records
+    pub offset: Option<u64>,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelAnalysis {
+    pub format: ModelFormat,
+    // Triangles of an STL model
+    pub triangles: u64,
+    // Buffers a glTF model declares
+    pub buffers: usize,
+    // Structure that disagrees with itself: lengths past the end of the file and the like
+    pub anomalies: Vec<String>,
+    pub regions: Vec<HiddenRegion>,
+    // STL triangles whose attribute byte count isn't zero
+    pub attributed_triangles: u64,
+    // Buffers referenced by path, which aren't analyzed
+    pub external_buffers: Vec<String>,
+    pub suspicious: bool,
+}
+
+// The model format of a file, from the GLB signature, the extension or an ASCII STL's
+// keywords
+pub fn model_format(data: &[u8], extension: &str) -> Option<ModelFormat> {
+    if data.starts_with(GLB_MAGIC) {
+        return Some(ModelFormat::Glb);
+    }
+    match extension.to_ascii_lowercase().as_str() {
+        "gltf" => Some(ModelFormat::Gltf),
+        "glb" => Some(ModelFormat::Glb),
+        "stl" => Some(ModelFormat::Stl),
+        _ => is_ascii_stl(data).then_some(ModelFormat::Stl),
+    }
+}
+
+impl Analyzer for ModelAnalyzer {
+    type Input = (Vec<u8>, ModelFormat);
+    type Output = ModelAnalysis;
+    type Error = ModelError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (data, format) = input;
+        let mut analysis = ModelAnalysis {
+            format,
+            triangles: 0,
+            buffers: 0,
+            anomalies: Vec::new(),
+            regions: Vec::new(),
+            attributed_triangles: 0,
+            external_buffers: Vec::new(),
+            suspicious: false,
+        };
+        match format {
+            ModelFormat::Glb => analyze_glb(&data, &mut analysis)?,
+            ModelFormat::Gltf => {
+                let (root, end) = parse_json(&data)?;
+                push_region(
+                    &mut analysis.regions,
+                    "after the JSON".to_string(),
+                    Some(end),
+                    trim_padding(&data[end..]),
+                );
+                check_gltf(&root, None, &mut analysis);
+            }
+            ModelFormat::Stl if is_ascii_stl(&data) => analyze_ascii_stl(&data, &mut analysis),
+            ModelFormat::Stl => analyze_binary_stl(&data, &mut analysis)?,
+        }
+        analysis.suspicious = !analysis.regions.is_empty();
+        Ok(analysis)
+    }
+}
+
+fn analyze_glb(data: &[u8], analysis: &mut ModelAnalysis) -> Result<(), ModelError> {
+    if data.len() < GLB_HEADER_BYTES || !data.starts_with(GLB_MAGIC) {
+        return Err(ModelError::Malformed("no GLB header".to_string()));
+    }
+    let version = u32_le(data, 4);
+    if version != 2 {
+        analysis
+            .anomalies
+            .push(format!("GLB version {} (only 2 is defined)", version));
+    }
+    let declared = u32_le(data, 8) as usize;
+    let end = if declared > data.len() {
+        analysis.anomalies.push(format!(
+            "header declares {} bytes but the file has {}",
+            declared,
+            data.len()
+        ));
+        data.len()
+    } else {
+        push_region(
+            &mut analysis.regions,
+            "after the GLB".to_string(),
+            Some(declared),
+            &data[declared..],
+        );
+        declared
+    };
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = GLB_HEADER_BYTES;
+    let mut index = 0;
+    while offset + GLB_CHUNK_HEADER_BYTES <= end {
+        let length = u32_le(data, offset) as usize;
+        let kind = u32_le(data, offset + 4);
+        let start = offset + GLB_CHUNK_HEADER_BYTES;
+        let Some(stop) = start.checked_add(length).filter(|&stop| stop <= end) else {
+            analysis
+                .anomalies
+                .push(format!("chunk {} runs past the end of the file", index));
+            offset = end;
+            break;
+        };
+        if !length.is_multiple_of(4) {
+            analysis
+                .anomalies
+                .push(format!("chunk {} isn't padded to 4 bytes", index));
+        }
+        let chunk = &data[start..stop];
+        match (index, kind) {
+            (0, CHUNK_JSON) => json = Some((start, chunk)),
+            (0, _) => {
+                return Err(ModelError::Malformed(
+                    "the first chunk isn't JSON".to_string(),
+                ));
+            }
+            (1, CHUNK_BIN) => bin = Some((start, chunk)),
+            // Loaders skip chunks after the first two, whatever they claim to be
+            _ => push_region(
+                &mut analysis.regions,
+                format!("chunk {} (type 0x{:08X})", index, kind),
+                Some(start),
+                chunk,
+            ),
+        }
+        offset = stop;
+        index += 1;
+    }
+    push_region(
+        &mut analysis.regions,
+        "after the last chunk".to_string(),
+        Some(offset),
+        &data[offset.min(end)..end],
+    );
+
+    let Some((json_start, json)) = json else {
+        return Err(ModelError::Malformed("no JSON chunk".to_string()));
+    };
+    let (root, json_end) = parse_json(json)?;
+    // The JSON chunk is padded with spaces
+    push_region(
+        &mut analysis.regions,
+        "JSON chunk padding".to_string(),
+        Some(json_start + json_end),
+        trim_padding(&json[json_end..]),
+    );
+    check_gltf(&root, bin, analysis);
+    Ok(())
+}
+
+// The JSON document at the start of `data` and the offset where it ends
+fn parse_json(data: &[u8]) -> Result<(Value, usize), ModelError> {
+    let mut stream = serde_json::Deserializer::from_slice(data).into_iter::<Value>();
+    match stream.next() {
+        Some(Ok(root)) if root.is_object() => Ok((root, stream.byte_offset())),
+        Some(Ok(_)) => Err(ModelError::Json("the root isn't an object".to_string())),
+        Some(Err(e)) => Err(ModelError::Json(e.to_string())),
+        None => Err(ModelError::Json("empty document".to_string())),
+    }
+}
+
+// Buffer bytes no buffer view covers, buffers nothing uses and blobs in extras. `bin` is the
+// GLB's BIN chunk and where its data starts.
+fn check_gltf(root: &Value, bin: Option<(usize, &[u8])>, analysis: &mut ModelAnalysis) {
+    let empty = Vec::new();
+    let buffers = root["buffers"].as_array().unwrap_or(&empty);
+    let views = root["bufferViews"].as_array().unwrap_or(&empty);
+    analysis.buffers = buffers.len();
+
+    let mut bin_used = false;
+    for (index, buffer) in buffers.iter().enumerate() {
+        let declared = buffer["byteLength"].as_u64().unwrap_or(0) as usize;
+        let (bytes, offset) = match buffer["uri"].as_str() {
+            None => match bin {
+                Some((start, chunk)) if index == 0 => {
+                    bin_used = true;
+                    (chunk.to_vec(), Some(start))
+                }
+                _ => {
+                    analysis
+                        .anomalies
+                        .push(format!("buffer {} has no URI and no BIN chunk", index));
+                    continue;
+                }
+            },
+            Some(uri) => match decode_data_uri(uri) {
+                Some(bytes) => (bytes, None),
+                None if uri.starts_with("data:") => {
+                    analysis
+                        .anomalies
+                        .push(format!("buffer {} has an undecodable data URI", index));
+                    continue;
+                }
+                None => {
+                    analysis.external_buffers.push(uri.to_string());
+                    continue;
+                }
+            },
+        };
+        if bytes.len() < declared {
+            analysis.anomalies.push(format!(
+                "buffer {} declares {} bytes but holds {}",
+                index,
+                declared,
+                bytes.len()
+            ));
+        }
+
+        let mut covered: Vec<(usize, usize)> = views
+            .iter()
+            .filter(|view| view["buffer"].as_u64() == Some(index as u64))
+            .map(|view| {
+                let start = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+                let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+                (start, start.saturating_add(length))
+            })
+            .collect();
+        if covered.is_empty() {
+            push_region(
+                &mut analysis.regions,
+                format!("buffer {} (no buffer view uses it)", index),
+                offset,
+                &bytes,
+            );
+            continue;
+        }
+        covered.sort_unstable();
+        let mut gaps = Vec::new();
+        let mut position = 0;
+        for (start, stop) in covered {
+            if start > position {
+                gaps.push((position, start));
+            }
+            position = position.max(stop);
+        }
+        gaps.push((position, bytes.len()));
+        for (start, stop) in gaps {
+            let (start, stop) = (start.min(bytes.len()), stop.min(bytes.len()));
+            push_region(
+                &mut analysis.regions,
+                format!("buffer {} bytes {}..{}", index, start, stop),
+                offset.map(|offset| offset + start),
+                &bytes[start..stop],
+            );
+        }
+    }
+    if let Some((start, chunk)) = bin
+        && !bin_used
+    {
+        push_region(
+            &mut analysis.regions,
+            "BIN chunk (no buffer uses it)".to_string(),
+            Some(start),
+            chunk,
+        );
+    }
+
+    find_extras_blobs(root, "", false, &mut analysis.regions);
+}
+
+// Strings anywhere inside an `extras` object that decode as a data URI, hex or base64,
+// located by JSON pointer
+fn find_extras_blobs(
+    value: &Value,
+    pointer: &str,
+    in_extras: bool,
+    regions: &mut Vec<HiddenRegion>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let pointer = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                find_extras_blobs(value, &pointer, in_extras || key == "extras", regions);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                find_extras_blobs(value, &format!("{}/{}", pointer, index), in_extras, regions);
+            }
+        }
+        Value::String(text) if in_extras => {
+            if let Some(data) = decode_blob(text) {
+                push_region(regions, pointer.to_string(), None, &data);
+            }
+        }
+        _ => {}
+    }
+}
+
+// A data URI, hex or base64 string of at least MIN_BLOB_CHARS, decoded
+pub(crate) fn decode_blob(text: &str) -> Option<Vec<u8>> {
+    use base64::Engine;
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+
+    let text = text.trim();
+    if text.len() < MIN_BLOB_CHARS {
+        return None;
+    }
+    if let Some(data) = decode_data_uri(text) {
+        return Some(data);
+    }
+    if text.len().is_multiple_of(2) && text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+            .collect();
+    }
+    STANDARD
+        .decode(text)
+        .or_else(|_| STANDARD_NO_PAD.decode(text.trim_end_matches('=')))
+        .ok()
+}
+
+pub(crate) fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let (meta, payload) = uri.strip_prefix("data:")?.split_once(',')?;
+    if meta.ends_with(";base64") {
+        STANDARD.decode(payload.trim()).ok()
+    } else {
+        Some(payload.as_bytes().to_vec())
+    }
+}
+
+// ASCII STL starts with "solid" like many binary headers do, so the binary layout wins when
+// the size matches it exactly
+fn is_ascii_stl(data: &[u8]) -> bool {
+    let exact_binary = data.len() >= STL_HEADER_BYTES + 4
+        && (data.len() - STL_HEADER_BYTES - 4) as u64
+            == u32_le(data, STL_HEADER_BYTES) as u64 * STL_TRIANGLE_BYTES as u64;
+    !exact_binary
+        && data.trim_ascii_start().starts_with(b"solid")
+        && find(data, b"endsolid").is_some()
+}
+
+fn analyze_ascii_stl(data: &[u8], analysis: &mut ModelAnalysis) {
+    // One pass counts the facets and finds the last solid's end. Models may hold several
+    // solids; only what follows the last one is past the model
+    let mut last = None;
+    for hit in stl_keywords().find_all(data) {
+        match hit.value {
+            StlKeyword::EndFacet => analysis.triangles += 1,
+            StlKeyword::EndSolid => last = Some(hit.start),
+        }
+    }
+    let Some(last) = last else {
+        return;
+    };
+    let end = data[last..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |newline| last + newline + 1);
+    if !data[end..].trim_ascii().is_empty() {
+        push_region(
+            &mut analysis.regions,
+            "after endsolid".to_string(),
+            Some(end),
+            &data[end..],
+        );
+    }
+}
+
+fn analyze_binary_stl(data: &[u8], analysis: &mut ModelAnalysis) -> Result<(), ModelError> {
+    let records_start = STL_HEADER_BYTES + 4;
+    if data.len() < records_start {
+        return Err(ModelError::Malformed(
+            "too short for a binary STL".to_string(),
+        ));
+    }
+    let declared = u32_le(data, STL_HEADER_BYTES) as usize;
+    let room = (data.len() - records_start) / STL_TRIANGLE_BYTES;
+    let records = declared.min(room);
+    if declared > room {
+        analysis.anomalies.push(format!(
+            "header declares {} triangles but the file only holds {}",
+            declared, room
+        ));
+    }
+    let end = records_start + records * STL_TRIANGLE_BYTES;
+    push_region(
+        &mut analysis.regions,
+        "after the last triangle".to_string(),
+        Some(end),
+        &data[end..],
+    );
+    analysis.triangles = records as u64;
+
+    let attributes: Vec<u16> = (0..records)
+        .map(|i| {
+            let at = records_start + i * STL_TRIANGLE_BYTES + STL_ATTRIBUTE_OFFSET;
+            u16::from_le_bytes([data[at], data[at + 1]])
+        })
+        .collect();
+    analysis.attributed_triangles = attributes.iter().filter(|&&a| a != 0).count() as u64;
+    if analysis.attributed_triangles == 0 {
+        return Ok(());
+    }
+    let stream: Vec<u8> = attributes.iter().flat_map(|a| a.to_le_bytes()).collect();
+    let payload = sniff(&stream);
+    let colored = find(&data[..STL_HEADER_BYTES], STL_COLOR_HEADER).is_some()
+        || attributes
+            .iter()
+            .all(|&a| a == 0 || a & STL_COLOR_VALID != 0);
+    if payload.is_recognized() {
+        push_region(
+            &mut analysis.regions,
+            "attribute byte counts".to_string(),
+            None,
+            &payload.data,
+        );
+    } else if !colored {
+        push_region(
+            &mut analysis.regions,
+            "attribute byte counts".to_string(),
+            None,
+            &stream,
+        );
+    }
+    Ok(())
+}
+
+// Adds the bytes as a region unless they are empty or all zero, which is only padding
+fn push_region(
+    regions: &mut Vec<HiddenRegion>,
+    location: String,
+    offset: Option<usize>,
+    data: &[u8],
+) {
+    if data.iter().all(|&b| b == 0) {
+        return;
+    }
+    regions.push(HiddenRegion {
+        location,
+        offset: offset.map(|offset| offset as u64),
+        classification: classify_payload(data),
+        data: data.to_vec(),
+    });
+}
+
+// What follows the JSON once the spaces, line breaks and NULs writers pad with are dropped
+fn trim_padding(data: &[u8]) -> &[u8] {
+    let start = data
+        .iter()
+        .position(|&b| !b.is_ascii_whitespace() && b != 0)
+        .unwrap_or(data.len());
+    &data[start..]
+}
+
+fn u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[derive(Clone, Copy)]
+enum StlKeyword {
+    EndFacet,
+    EndSolid,
+}
+
+fn stl_keywords() -> &'static PatternSet<StlKeyword> {
+    static KEYWORDS: OnceLock<PatternSet<StlKeyword>> = OnceLock::new();
+    KEYWORDS.get_or_init(|| {
+        PatternSet::new([
+            (b"endfacet", StlKeyword::EndFacet),
+            (b"endsolid", StlKeyword::EndSolid),
+        ])
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glb(json: &str, bin: &[u8], extra_chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut chunks = vec![(CHUNK_JSON, json.as_slice()), (CHUNK_BIN, bin)];
+        chunks.extend_from_slice(extra_chunks);
+        let mut body = Vec::new();
+        for (kind, data) in chunks {
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(&kind.to_le_bytes());
+            body.extend_from_slice(data);
+        }
+        let mut file = b"glTF".to_vec();
+        file.extend_from_slice(&2u32.to_le_bytes());
+        file.extend_from_slice(&((body.len() + GLB_HEADER_BYTES) as u32).to_le_bytes());
+        file.extend(body);
+        file
+    }
+
+    fn binary_stl(header: &[u8], attributes: &[u16]) -> Vec<u8> {
+        let mut file = header.to_vec();
+        file.resize(STL_HEADER_BYTES, 0);
+        file.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+        for attribute in attributes {
+            file.extend_from_slice(&[0; STL_ATTRIBUTE_OFFSET]);
+            file.extend_from_slice(&attribute.to_le_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn test_glb_hidden_regions() {
+        use base64::Engine;
+        let blob = base64::engine::general_purpose::STANDARD
+            .encode(b"the vault code is 7421, do not lose it, burn after reading");
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},"buffers":[{{"byteLength":16}}],"bufferViews":[{{"buffer":0,"byteLength":8}}],"nodes":[{{"extras":{{"note":"short","blob":"{}"}}}}]}}"#,
+            blob
+        );
+        let mut bin = vec![1u8; 8];
+        bin.extend_from_slice(b"SECRET!!");
+        let file = glb(&json, &bin, &[(0x5A5A5A5A, b"hidden chunk")]);
+
+        let analysis = ModelAnalyzer::analyze((file, ModelFormat::Glb)).unwrap();
+        assert!(analysis.suspicious);
+        assert!(analysis.anomalies.is_empty());
+        assert_eq!(analysis.buffers, 1);
+        let locations: Vec<&str> = analysis
+            .regions
+            .iter()
+            .map(|r| r.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                "chunk 2 (type 0x5A5A5A5A)",
+                "buffer 0 bytes 8..16",
+                "/nodes/0/extras/blob"
+            ]
+        );
+        assert_eq!(analysis.regions[1].data, b"SECRET!!");
+        assert_eq!(analysis.regions[2].classification, "text");
+
+        let clean = glb(
+            r#"{"asset":{"version":"2.0"},"buffers":[{"byteLength":5}],"bufferViews":[{"buffer":0,"byteLength":5}]}"#,
+            &[1, 2, 3, 4, 5, 0, 0, 0],
+            &[],
+        );
+        assert!(
+            !ModelAnalyzer::analyze((clean, ModelFormat::Glb))
+                .unwrap()
+                .suspicious
+        );
+    }
+
+    #[test]
+    fn test_stl_attribute_bytes_and_trailing_data() {
+        let message = b"meet me at the usual place";
+        let attributes: Vec<u16> = message
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+            .collect();
+        let mut file = binary_stl(b"solid exported", &attributes);
+        file.extend_from_slice(b"appended");
+
+        assert_eq!(model_format(&file, "stl"), Some(ModelFormat::Stl));
+        let analysis = ModelAnalyzer::analyze((file, ModelFormat::Stl)).unwrap();
+        assert_eq!(analysis.triangles, 13);
+        assert_eq!(analysis.attributed_triangles, 13);
+        assert_eq!(analysis.regions.len(), 2);
+        assert_eq!(analysis.regions[0].location, "after the last triangle");
+        assert_eq!(analysis.regions[0].data, b"appended");
+        assert_eq!(analysis.regions[1].data, message);
+
+        // Colours with the valid bit set are what the bytes are for
+        let colored = binary_stl(b"binary", &[0x801F, 0x83E0, 0xFC00, 0]);
+        let analysis = ModelAnalyzer::analyze((colored, ModelFormat::Stl)).unwrap();
+        assert_eq!(analysis.attributed_triangles, 3);
+        assert!(!analysis.suspicious);
+    }
+
+    #[test]
+    fn test_ascii_stl_trailing_data() {
+        let file =
+            b"solid cube\n  facet normal 0 0 1\n  endfacet\nendsolid cube\nZXhmaWx0cmF0ZWQ=\n";
+        assert_eq!(model_format(file, "txt"), Some(ModelFormat::Stl));
+        let analysis = ModelAnalyzer::analyze((file.to_vec(), ModelFormat::Stl)).unwrap();
+        assert_eq!(analysis.triangles, 1);
+        assert_eq!(analysis.regions[0].location, "after endsolid");
+        assert_eq!(analysis.regions[0].offset, Some(57));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::payload_carver::embedded_file_end;
+use crate::trailing_tag_analyzer::{MAX_COVER_ART_BYTES, TagItem, check_text};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// iTunes-style metadata of MP4, M4A and other ISO-BMFF files: the item list (ilst) in
+// moov/udta/meta. Every item is read; text over MAX_TEXT_ITEM_BYTES or that isn't text, cover
+// art that isn't the image it's declared as, is oversized or has data after the image, and
+// binary values too large to be the numbers and flags players store are flagged. Freeform
+// ("----") items are keyed "----:<mean>:<name>".
+pub struct Mp4TagAnalyzer;
+
+// Largest binary value a player stores, e.g. an 8 byte track number
+const MAX_BINARY_ITEM_BYTES: usize = 16;
+
+#[derive(Debug)]
+pub enum Mp4TagError {
+    NotMp4,
+    NoTags,
+}
+
+impl Display for Mp4TagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mp4TagError::NotMp4 => write!(f, "Not an ISO-BMFF file"),
+            Mp4TagError::NoTags => write!(f, "No iTunes metadata item list found"),
+        }
+    }
+}
+
+impl std::error::Error for Mp4TagError {}
+
+#[derive(Debug, Clone)]
+pub struct Mp4TagAnalysis {
+    // The ilst box, header included
+    pub offset: usize,
+    pub size: usize,
+    pub items: Vec<TagItem>,
+    // Problems with the item list as a whole
+    pub findings: Vec<String>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for Mp4TagAnalyzer {
+    type Input = Vec<u8>;
+    type Output = Mp4TagAnalysis;
+    type Error = Mp4TagError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if input.get(4..8) != Some(b"ftyp") {
+            return Err(Mp4TagError::NotMp4);
+        }
+        let (offset, start, end) = find_ilst(&input).ok_or(Mp4TagError::NoTags)?;
+
+        let mut items = Vec::new();
+        let mut findings = Vec::new();
+        let mut covered = start;
+        for (atom, atom_start, atom_end) in boxes(&input, start, end) {
+            read_item(
+                &input,
+                atom,
+                atom_start,
+                atom_end,
+                &mut items,
+                &mut findings,
+            );
+            covered = atom_end;
+        }
+        if covered < end {
+            findings.push(format!(
+                "{} bytes after the last item at offset {}",
+                end - covered,
+                covered
+            ));
+        }
+
+        let suspicious = !findings.is_empty() || items.iter().any(|i| !i.findings.is_empty());
+        Ok(Mp4TagAnalysis {
+            offset,
+            size: end - offset,
+            items,
+            findings,
+            suspicious,
+        })
+    }
+}
+
+// ISO-BMFF box: (type, payload start, end)
+fn boxes(data: &[u8], start: usize, end: usize) -> Vec<([u8; 4], usize, usize)> {
+    let mut found = Vec::new();
+    let mut offset = start;
+    while offset + 8 <= end {
+        let size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let box_type: [u8; 4] = data[offset + 4..offset + 8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, end - offset),
+            1 if offset + 16 <= end => (
+                16,
+                u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap()) as usize,
+            ),
+            _ => (8, size),
+        };
+        if size < header || offset.saturating_add(size) > end {
+            break;
+        }
+        found.push((box_type, offset + header, offset + size));
+        offset += size;
+    }
+    found
+}
+
+fn child(data: &[u8], start: usize, end: usize, box_type: &[u8; 4]) -> Option<(usize, usize)> {
+    boxes(data, start, end)
+        .into_iter()
+        .find(|(found, _, _)| found == box_type)
+        .map(|(_, start, end)| (start, end))
+}
+
+// The ilst box (offset, payload start, end) in moov/udta/meta, or in moov/meta
+fn find_ilst(data: &[u8]) -> Option<(usize, usize, usize)> {
+    let (moov_start, moov_end) = child(data, 0, data.len(), b"moov")?;
+    let (meta_start, meta_end) = child(data, moov_start, moov_end, b"udta")
+        .and_then(|(start, end)| child(data, start, end, b"meta"))
+        .or_else(|| child(data, moov_start, moov_end, b"meta"))?;
+    // meta is a full box in MP4 but a plain box in QuickTime files
+    let children = if data.get(meta_start + 4..meta_start + 8) == Some(b"hdlr") {
+        meta_start
+    } else {
+        meta_start + 4
+    };
+    boxes(data, children, meta_end)
+        .into_iter()
+        .find(|(box_type, _, _)| box_type == b"ilst")
+        .map(|(_, start, end)| (start - 8, start, end))
+}
+
+// An item atom's data boxes, each read as one item
+fn read_item(
+    data: &[u8],
+    atom: [u8; 4],
+    start: usize,
+    end: usize,
+    items: &mut Vec<TagItem>,
+    findings: &mut Vec<String>,
+) {
+    // Atom names such as ©nam are Latin-1
+    let mut key: String = atom.iter().map(|&b| b as char).collect();
+    let children = boxes(data, start, end);
+    if &atom == b"----" {
+        let field = |box_type: &[u8; 4]| {
+            children
+                .iter()
+                .find(|(found, _, _)| found == box_type)
+                .and_then(|&(_, start, end)| data.get(start + 4..end))
+                .map(|value| String::from_utf8_lossy(value).to_string())
+                .unwrap_or_default()
+        };
+        key = format!("----:{}:{}", field(b"mean"), field(b"name"));
+    }
+
+    let values: Vec<(usize, usize)> = children
+        .iter()
+        .filter(|(box_type, _, _)| box_type == b"data")
+        .map(|&(_, start, end)| (start, end))
+        .collect();
+    if values.is_empty() {
+        findings.push(format!("Item {:?} has no data", key));
+    }
+    for (value_start, value_end) in values {
+        if value_end < value_start + 8 {
+            findings.push(format!("Item {:?} has a truncated data box", key));
+            continue;
+        }
+        // The low 24 bits of the first word give the value's type; a locale follows
+        let data_type =
+            u32::from_be_bytes(data[value_start..value_start + 4].try_into().unwrap()) & 0xFF_FFFF;
+        let offset = value_start + 8;
+        let value = data[offset..value_end].to_vec();
+        let mut item_findings = Vec::new();
+        let binary = !matches!(data_type, 1 | 2);
+        let classification = match data_type {
+            1 => {
+                let classification = classify_payload(&value);
+                check_text(&value, &classification, &mut item_findings);
+                classification
+            }
+            2 => {
+                let units: Vec<u16> = value
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                let text = String::from_utf16_lossy(&units);
+                let classification = classify_payload(text.as_bytes());
+                check_text(text.as_bytes(), &classification, &mut item_findings);
+                classification
+            }
+            13 | 14 | 27 => check_image(&value, data_type, &mut item_findings),
+            // Implicit (0), integer (21, 22) and other binary types
+            _ => {
+                let classification = classify_payload(&value);
+                if &atom == b"covr" {
+                    check_image(&value, data_type, &mut item_findings);
+                } else if value.len() > MAX_BINARY_ITEM_BYTES {
+                    item_findings.push(format!(
+                        "Binary value of {} bytes ({})",
+                        value.len(),
+                        classification
+                    ));
+                }
+                classification
+            }
+        };
+        items.push(TagItem {
+            key: key.clone(),
+            offset,
+            value,
+            binary,
+            classification,
+            findings: item_findings,
+        });
+    }
+}
+
+// Cover art, declared JPEG (13), PNG (14) or BMP (27)
+fn check_image(value: &[u8], data_type: u32, findings: &mut Vec<String>) -> String {
+    let classification = classify_payload(value);
+    let declared = match data_type {
+        13 => Some("image/jpeg"),
+        14 => Some("image/png"),
+        27 => Some("image/bmp"),
+        _ => None,
+    };
+    if !classification.starts_with("image/") {
+        findings.push(format!("Cover art holds {}, not an image", classification));
+    } else if let Some(declared) = declared.filter(|&declared| declared != classification) {
+        findings.push(format!(
+            "Cover art declared {} holds {}",
+            declared, classification
+        ));
+    } else if value.len() > MAX_COVER_ART_BYTES {
+        findings.push(format!("Oversized cover art: {} bytes", value.len()));
+    } else if let Some(end) = embedded_file_end(value, 0).filter(|&end| end < value.len()) {
+        findings.push(format!(
+            "{} bytes after the end of the cover image",
+            value.len() - end
+        ));
+    }
+    classification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bmff_box(box_type: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn data_box(data_type: u32, value: &[u8]) -> Vec<u8> {
+        let payload = [&data_type.to_be_bytes()[..], &[0; 4], value].concat();
+        bmff_box(b"data", &payload)
+    }
+
+    fn png(trailer: &[u8]) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (chunk_type, body) in [(&b"IHDR"[..], &[0u8; 13][..]), (&b"IEND"[..], &[][..])] {
+            png.extend((body.len() as u32).to_be_bytes());
+            png.extend(chunk_type);
+            png.extend(body);
+            png.extend([0; 4]);
+        }
+        png.extend_from_slice(trailer);
+        png
+    }
+
+    fn m4a(items: &[Vec<u8>]) -> Vec<u8> {
+        let ilst = bmff_box(b"ilst", &items.concat());
+        let hdlr = bmff_box(b"hdlr", &[0; 25]);
+        let meta = bmff_box(b"meta", &[&[0; 4][..], &hdlr, &ilst].concat());
+        let moov = bmff_box(b"moov", &bmff_box(b"udta", &meta));
+        [
+            bmff_box(b"ftyp", b"M4A \0\0\0\0"),
+            moov,
+            bmff_box(b"mdat", &[0; 64]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_ilst_items() {
+        let freeform = [
+            bmff_box(b"mean", b"\0\0\0\0com.apple.iTunes"),
+            bmff_box(b"name", b"\0\0\0\0NOTES"),
+            data_box(1, &vec![b'a'; 20_000]),
+        ]
+        .concat();
+        let file = m4a(&[
+            bmff_box(b"\xA9nam", &data_box(1, b"Song")),
+            bmff_box(b"trkn", &data_box(0, &[0, 0, 0, 3, 0, 12, 0, 0])),
+            bmff_box(b"covr", &data_box(14, &png(b""))),
+            bmff_box(b"covr", &data_box(13, &png(b"secret"))),
+            bmff_box(b"----", &freeform),
+            bmff_box(b"xtra", &data_box(0, &[0xAB; 64])),
+        ]);
+
+        let analysis = Mp4TagAnalyzer::analyze(file).unwrap();
+        assert!(analysis.suspicious);
+        assert!(analysis.findings.is_empty());
+        let keys: Vec<&str> = analysis.items.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "©nam",
+                "trkn",
+                "covr",
+                "covr",
+                "----:com.apple.iTunes:NOTES",
+                "xtra"
+            ]
+        );
+        assert_eq!(analysis.items[0].value, b"Song");
+        assert!(analysis.items[0].findings.is_empty());
+        assert!(analysis.items[1].findings.is_empty());
+        assert_eq!(analysis.items[2].classification, "image/png");
+        assert!(analysis.items[2].findings.is_empty());
+        // Declared JPEG
+        assert_eq!(analysis.items[3].findings.len(), 1);
+        assert_eq!(analysis.items[4].findings.len(), 1);
+        assert_eq!(analysis.items[5].findings.len(), 1);
+
+        let clean = m4a(&[bmff_box(b"covr", &data_box(14, &png(b"")))]);
+        assert!(!Mp4TagAnalyzer::analyze(clean).unwrap().suspicious);
+        let trailing = m4a(&[bmff_box(b"covr", &data_box(14, &png(b"payload")))]);
+        let analysis = Mp4TagAnalyzer::analyze(trailing).unwrap();
+        assert_eq!(
+            analysis.items[0].findings,
+            vec!["7 bytes after the end of the cover image"]
+        );
+
+        let untagged = [bmff_box(b"ftyp", b"isom\0\0\0\0"), bmff_box(b"moov", &[])].concat();
+        assert!(matches!(
+            Mp4TagAnalyzer::analyze(untagged),
+            Err(Mp4TagError::NoTags)
+        ));
+        assert!(Mp4TagAnalyzer::analyze(vec![0; 64]).is_err());
+    }
+}
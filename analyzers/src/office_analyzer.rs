@@ -0,0 +1,282 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::ole_analyzer::{
+    CFB_SIGNATURE, EmbeddedObject, MacroModule, OleAnalyzer, OleEntry, OleError,
+};
+use std::fmt::Display;
+use std::io::{Cursor, Read};
+
+// Macros and embedded objects of Office documents in either format. Legacy documents are
+// compound files read by the OLE analyzer; OOXML documents are zip packages carrying the VBA
+// project as a compound file of its own (vbaProject.bin) and objects under */embeddings/.
+// Objects that are Office documents themselves are opened in turn, so an executable packaged
+// inside a worksheet embedded in a document still comes out.
+pub struct OfficeAnalyzer;
+
+// Objects nested deeper than this are listed but not opened
+pub const MAX_NESTING_DEPTH: usize = 3;
+
+// Package parts larger than this are skipped
+const MAX_PART_BYTES: u64 = 64 * 1024 * 1024;
+
+const ZIP_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+
+#[derive(Debug)]
+pub enum OfficeError {
+    NotOfficeDocument,
+    Ole(OleError),
+    Package(String),
+}
+
+impl Display for OfficeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OfficeError::NotOfficeDocument => write!(f, "Not an OLE2 or OOXML document"),
+            OfficeError::Ole(e) => write!(f, "{}", e),
+            OfficeError::Package(e) => write!(f, "Unreadable OOXML package: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OfficeError {}
+
+impl From<OleError> for OfficeError {
+    fn from(e: OleError) -> Self {
+        OfficeError::Ole(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfficeFormat {
+    Ole2,
+    Ooxml,
+}
+
+impl Display for OfficeFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OfficeFormat::Ole2 => write!(f, "OLE2"),
+            OfficeFormat::Ooxml => write!(f, "OOXML"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OfficeObject {
+    pub object: EmbeddedObject,
+    // Analysis of the object when it is an Office document too
+    pub nested: Option<Box<OfficeAnalysis>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OfficeAnalysis {
+    pub format: OfficeFormat,
+    // Storages and streams of a compound file; empty for OOXML packages
+    pub entries: Vec<OleEntry>,
+    pub macros: Vec<MacroModule>,
+    pub embedded_objects: Vec<OfficeObject>,
+    pub suspicious: bool,
+}
+
+impl OfficeAnalysis {
+    pub fn unusual_entries(&self) -> impl Iterator<Item = &OleEntry> {
+        self.entries.iter().filter(|e| e.unusual.is_some())
+    }
+}
+
+// Whether the data is a compound file or an OOXML package
+pub fn is_office_document(data: &[u8]) -> bool {
+    data.starts_with(&CFB_SIGNATURE) || (data.starts_with(&ZIP_SIGNATURE) && is_ooxml(data))
+}
+
+impl Analyzer for OfficeAnalyzer {
+    type Input = Vec<u8>;
+    type Output = OfficeAnalysis;
+    type Error = OfficeError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        analyze_at(input, 0)
+    }
+}
+
+fn analyze_at(data: Vec<u8>, depth: usize) -> Result<OfficeAnalysis, OfficeError> {
+    let (format, entries, macros, objects, suspicious) = if data.starts_with(&CFB_SIGNATURE) {
+        let ole = OleAnalyzer::analyze(data)?;
+        (
+            OfficeFormat::Ole2,
+            ole.entries,
+            ole.macros,
+            ole.embedded_objects,
+            ole.suspicious,
+        )
+    } else if data.starts_with(&ZIP_SIGNATURE) {
+        let (macros, objects) = read_package(data)?;
+        let suspicious = !macros.is_empty() || objects.iter().any(EmbeddedObject::is_executable);
+        (OfficeFormat::Ooxml, Vec::new(), macros, objects, suspicious)
+    } else {
+        return Err(OfficeError::NotOfficeDocument);
+    };
+
+    let embedded_objects: Vec<OfficeObject> = objects
+        .into_iter()
+        .map(|object| {
+            let nested = if depth < MAX_NESTING_DEPTH && is_office_document(&object.data) {
+                analyze_at(object.data.clone(), depth + 1)
+                    .ok()
+                    .map(Box::new)
+            } else {
+                None
+            };
+            OfficeObject { object, nested }
+        })
+        .collect();
+    let suspicious = suspicious
+        || embedded_objects
+            .iter()
+            .any(|o| o.nested.as_ref().is_some_and(|nested| nested.suspicious));
+
+    Ok(OfficeAnalysis {
+        format,
+        entries,
+        macros,
+        embedded_objects,
+        suspicious,
+    })
+}
+
+fn is_ooxml(data: &[u8]) -> bool {
+    zip::ZipArchive::new(Cursor::new(data))
+        .is_ok_and(|archive| archive.index_for_name("[Content_Types].xml").is_some())
+}
+
+// Macros from the package's VBA projects and the parts stored under an embeddings folder
+fn read_package(data: Vec<u8>) -> Result<(Vec<MacroModule>, Vec<EmbeddedObject>), OfficeError> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).map_err(|e| OfficeError::Package(e.to_string()))?;
+    if archive.index_for_name("[Content_Types].xml").is_none() {
+        return Err(OfficeError::NotOfficeDocument);
+    }
+
+    let mut macros = Vec::new();
+    let mut objects = Vec::new();
+    for index in 0..archive.len() {
+        let Ok(mut part) = archive.by_index(index) else {
+            continue;
+        };
+        let name = part.name().to_string();
+        let file_name = name.rsplit('/').next().unwrap_or_default();
+        let is_vba_project = file_name.eq_ignore_ascii_case("vbaProject.bin");
+        let is_embedding = name.contains("/embeddings/") && !part.is_dir();
+        if !is_vba_project && !is_embedding {
+            continue;
+        }
+        let mut data = Vec::new();
+        if part.size() > MAX_PART_BYTES
+            || (&mut part)
+                .take(MAX_PART_BYTES)
+                .read_to_end(&mut data)
+                .is_err()
+        {
+            continue;
+        }
+
+        if is_vba_project {
+            // A project that fails to parse says nothing about the rest of the package
+            if let Ok(project) = OleAnalyzer::analyze(data) {
+                macros.extend(project.macros.into_iter().map(|module| MacroModule {
+                    stream: format!("{}/{}", name, module.stream),
+                    ..module
+                }));
+            }
+        } else if !data.is_empty() {
+            objects.push(EmbeddedObject {
+                stream: name,
+                file_name: None,
+                classification: classify_payload(&data),
+                data,
+            });
+        }
+    }
+    Ok((macros, objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn packaged_file(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut file = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        let mut native = Vec::new();
+        native.extend_from_slice(&0u32.to_le_bytes());
+        native.extend_from_slice(&2u16.to_le_bytes());
+        native.extend_from_slice(format!("{}\0C:\\{}\0", name, name).as_bytes());
+        native.extend_from_slice(&[0; 4]);
+        native.extend_from_slice(format!("C:\\Temp\\{}\0", name).as_bytes());
+        native.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        native.extend_from_slice(data);
+        file.create_stream("/\u{1}Ole10Native")
+            .unwrap()
+            .write_all(&native)
+            .unwrap();
+        file.into_inner().into_inner()
+    }
+
+    fn package(parts: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in parts {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_opens_objects_nested_in_embedded_packages() {
+        let executable = b"MZ\x90\x00\x03\x00\x00\x00\x04\x00\x00\x00\xFF\xFF\x00\x00";
+        let worksheet = package(&[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("xl/workbook.xml", b"<workbook/>"),
+            (
+                "xl/embeddings/oleObject1.bin",
+                &packaged_file("setup.exe", executable),
+            ),
+        ]);
+        let document = package(&[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("word/document.xml", b"<document/>"),
+            ("word/embeddings/Microsoft_Excel_Worksheet.xlsx", &worksheet),
+        ]);
+
+        let analysis = OfficeAnalyzer::analyze(document).unwrap();
+        assert_eq!(analysis.format, OfficeFormat::Ooxml);
+        assert!(analysis.macros.is_empty());
+        assert_eq!(analysis.embedded_objects.len(), 1);
+        let sheet = &analysis.embedded_objects[0];
+        assert_eq!(
+            sheet.object.stream,
+            "word/embeddings/Microsoft_Excel_Worksheet.xlsx"
+        );
+
+        let sheet = sheet.nested.as_ref().unwrap();
+        assert_eq!(sheet.format, OfficeFormat::Ooxml);
+        let ole_object = sheet.embedded_objects[0].nested.as_ref().unwrap();
+        assert_eq!(ole_object.format, OfficeFormat::Ole2);
+        let packaged = &ole_object.embedded_objects[0].object;
+        assert_eq!(packaged.file_name.as_deref(), Some("setup.exe"));
+        assert_eq!(packaged.data, executable);
+        assert!(packaged.is_executable());
+        assert!(analysis.suspicious);
+    }
+
+    #[test]
+    fn test_rejects_plain_zip_archives() {
+        let archive = package(&[("notes.txt", b"hello")]);
+        assert!(!is_office_document(&archive));
+        assert!(matches!(
+            OfficeAnalyzer::analyze(archive),
+            Err(OfficeError::NotOfficeDocument)
+        ));
+    }
+}
@@ -0,0 +1,617 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use std::fmt::Display;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+// Structure of OLE2 compound files (CFB): legacy .doc, .xls and .ppt documents, MSI packages
+// and the like. A compound file is a small file system of storages and streams, so besides
+// the streams a document format defines it can carry any number of others that no viewer
+// ever opens. Every entry is listed, names and sizes a document wouldn't have are flagged,
+// VBA macro source is decompressed out of its module streams and embedded OLE objects are
+// unpacked so they can be analyzed in turn.
+pub struct OleAnalyzer;
+
+pub const CFB_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+// Entries the Office formats put at the root of the file. Only the root is checked: the
+// storages below it hold module streams and object IDs named by the application.
+const KNOWN_ROOT_ENTRIES: [&str; 24] = [
+    "WordDocument",
+    "0Table",
+    "1Table",
+    "Data",
+    "Workbook",
+    "Book",
+    "PowerPoint Document",
+    "Current User",
+    "Pictures",
+    "\u{5}SummaryInformation",
+    "\u{5}DocumentSummaryInformation",
+    "\u{1}CompObj",
+    "\u{1}Ole",
+    "\u{1}Ole10Native",
+    "\u{3}ObjInfo",
+    "ObjectPool",
+    "Macros",
+    "_VBA_PROJECT_CUR",
+    "MsoDataStore",
+    "_xmlsignatures",
+    "_signatures",
+    "\u{6}DataSpaces",
+    "EncryptedPackage",
+    "CONTENTS",
+];
+
+// Property sets and class streams are a few hundred bytes in practice; these leave a wide
+// margin before a size is called out
+const MAX_PROPERTY_SET_BYTES: u64 = 64 * 1024;
+const MAX_CLASS_STREAM_BYTES: u64 = 4 * 1024;
+
+// Decompressed VBA is cut off at this size
+const MAX_MACRO_SOURCE_BYTES: usize = 1 << 20;
+
+// Procedures Word and Excel run by themselves when a document is opened, created or closed
+const AUTO_EXEC_PROCEDURES: [&str; 17] = [
+    "AutoExec",
+    "AutoOpen",
+    "AutoNew",
+    "AutoClose",
+    "AutoExit",
+    "Document_Open",
+    "Document_New",
+    "Document_Close",
+    "DocumentOpen",
+    "DocumentBeforeClose",
+    "Auto_Open",
+    "Auto_Close",
+    "Auto_Activate",
+    "Workbook_Open",
+    "Workbook_Activate",
+    "Workbook_BeforeClose",
+    "Workbook_Deactivate",
+];
+
+#[derive(Debug)]
+pub enum OleError {
+    NotCompoundFile,
+    Corrupt(String),
+}
+
+impl Display for OleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OleError::NotCompoundFile => write!(f, "Not an OLE2 compound file"),
+            OleError::Corrupt(e) => write!(f, "Unreadable compound file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OleError {}
+
+#[derive(Debug, Clone)]
+pub struct OleEntry {
+    // Names joined with '/', control characters escaped
+    pub path: String,
+    pub is_storage: bool,
+    pub size: u64,
+    // Why the name or size is out of place for a document
+    pub unusual: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroModule {
+    pub name: String,
+    pub stream: String,
+    // Decompressed source, None when the module stream couldn't be decoded
+    pub source: Option<String>,
+    // Auto-exec procedures the module defines
+    pub auto_exec: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddedObject {
+    pub stream: String,
+    // File name recorded by the Packager for Ole10Native objects
+    pub file_name: Option<String>,
+    pub data: Vec<u8>,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+}
+
+impl EmbeddedObject {
+    // File extension for writing the object out: the packaged file's own, or one for its type
+    pub fn extension(&self) -> String {
+        let packaged = self
+            .file_name
+            .as_deref()
+            .and_then(|name| Path::new(name).extension())
+            .map(|ext| ext.to_string_lossy().to_string());
+        match (packaged, infer::get(&self.data)) {
+            (Some(ext), _) => ext,
+            (None, Some(kind)) => kind.extension().to_string(),
+            (None, None) if self.classification == "text" => "txt".to_string(),
+            (None, None) => "bin".to_string(),
+        }
+    }
+
+    // Programs: PE, ELF and Mach-O binaries, DEX, Java classes and the like
+    pub fn is_executable(&self) -> bool {
+        infer::get(&self.data).is_some_and(|kind| kind.matcher_type() == infer::MatcherType::App)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OleAnalysis {
+    pub entries: Vec<OleEntry>,
+    pub macros: Vec<MacroModule>,
+    pub embedded_objects: Vec<EmbeddedObject>,
+    pub suspicious: bool,
+}
+
+impl OleAnalysis {
+    pub fn unusual_entries(&self) -> impl Iterator<Item = &OleEntry> {
+        self.entries.iter().filter(|e| e.unusual.is_some())
+    }
+}
+
+impl Analyzer for OleAnalyzer {
+    type Input = Vec<u8>;
+    type Output = OleAnalysis;
+    type Error = OleError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if !input.starts_with(&CFB_SIGNATURE) {
+            return Err(OleError::NotCompoundFile);
+        }
+        let mut file = cfb::CompoundFile::open(Cursor::new(input))
+            .map_err(|e| OleError::Corrupt(e.to_string()))?;
+
+        let walked: Vec<(Vec<String>, bool, u64)> = file
+            .walk()
+            .filter(|entry| !entry.is_root())
+            .map(|entry| (names(entry.path()), entry.is_storage(), entry.len()))
+            .collect();
+
+        let mut entries = Vec::new();
+        let mut vba_storages = Vec::new();
+        let mut object_streams = Vec::new();
+        for (names, is_storage, size) in &walked {
+            let name = names.last().map(String::as_str).unwrap_or_default();
+            if !is_storage && name == "dir" && names.len() >= 2 && names[names.len() - 2] == "VBA" {
+                vba_storages.push(names[..names.len() - 1].to_vec());
+            }
+            if !is_storage && is_object_stream(name) {
+                object_streams.push(names.clone());
+            }
+            entries.push(OleEntry {
+                path: display_path(names),
+                is_storage: *is_storage,
+                size: *size,
+                unusual: unusual(names, *is_storage, *size),
+            });
+        }
+
+        let mut macros = Vec::new();
+        for storage in &vba_storages {
+            macros.extend(read_vba(&mut file, storage));
+        }
+
+        let mut embedded_objects = Vec::new();
+        for names in &object_streams {
+            let Some(data) = read_stream(&mut file, names) else {
+                continue;
+            };
+            let (file_name, data) = if names.last().is_some_and(|n| n == "\u{1}Ole10Native") {
+                unpack_ole10_native(&data)
+            } else {
+                (None, data)
+            };
+            if data.is_empty() {
+                continue;
+            }
+            embedded_objects.push(EmbeddedObject {
+                stream: display_path(names),
+                file_name,
+                classification: classify_payload(&data),
+                data,
+            });
+        }
+
+        let suspicious = entries.iter().any(|e| e.unusual.is_some())
+            || !macros.is_empty()
+            || embedded_objects.iter().any(EmbeddedObject::is_executable);
+        Ok(OleAnalysis {
+            entries,
+            macros,
+            embedded_objects,
+            suspicious,
+        })
+    }
+}
+
+fn names(path: &Path) -> Vec<String> {
+    path.iter()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| name != "/" && name != "\\")
+        .collect()
+}
+
+fn display_path(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| name.escape_debug().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn cfb_path(names: &[String]) -> String {
+    format!("/{}", names.join("/"))
+}
+
+fn read_stream(file: &mut cfb::CompoundFile<Cursor<Vec<u8>>>, names: &[String]) -> Option<Vec<u8>> {
+    let mut stream = file.open_stream(cfb_path(names)).ok()?;
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+// Native data of embedded objects: what the Packager wraps a dropped-in file in, and the
+// contents streams of objects stored in the ObjectPool
+fn is_object_stream(name: &str) -> bool {
+    matches!(
+        name,
+        "\u{1}Ole10Native" | "CONTENTS" | "Contents" | "Package"
+    )
+}
+
+fn unusual(names: &[String], is_storage: bool, size: u64) -> Option<String> {
+    let name = names.last()?;
+    // The first character of a name may be 0x01-0x06 to mark it as reserved; any other
+    // control character is never written by an application
+    let body = name.strip_prefix(|c: char| ('\u{1}'..='\u{6}').contains(&c));
+    if body.unwrap_or(name).chars().any(char::is_control) {
+        return Some("control characters in the name".to_string());
+    }
+    if name.is_empty() {
+        return Some("empty name".to_string());
+    }
+
+    let limit = match name.as_str() {
+        "\u{5}SummaryInformation" | "\u{5}DocumentSummaryInformation" => {
+            Some(MAX_PROPERTY_SET_BYTES)
+        }
+        "\u{1}CompObj" | "\u{1}Ole" | "\u{3}ObjInfo" => Some(MAX_CLASS_STREAM_BYTES),
+        _ => None,
+    };
+    if let Some(limit) = limit.filter(|&limit| !is_storage && size > limit) {
+        return Some(format!(
+            "{} bytes where at most {} are expected",
+            size, limit
+        ));
+    }
+
+    if names.len() == 1 && !KNOWN_ROOT_ENTRIES.contains(&name.as_str()) {
+        return Some(if is_storage {
+            "storage not defined by the document format".to_string()
+        } else {
+            "stream not defined by the document format".to_string()
+        });
+    }
+    None
+}
+
+// The modules a VBA project lists in its dir stream, with their source decompressed from the
+// offset the dir stream gives
+fn read_vba(file: &mut cfb::CompoundFile<Cursor<Vec<u8>>>, storage: &[String]) -> Vec<MacroModule> {
+    let path = |name: &str| {
+        let mut names = storage.to_vec();
+        names.push(name.to_string());
+        names
+    };
+    let Some(dir) = read_stream(file, &path("dir")).and_then(|dir| decompress_vba(&dir)) else {
+        return Vec::new();
+    };
+
+    dir_modules(&dir)
+        .into_iter()
+        .map(|(name, stream_name, offset)| {
+            let names = path(&stream_name);
+            let source = read_stream(file, &names)
+                .and_then(|data| data.get(offset..).and_then(decompress_vba))
+                .map(|source| String::from_utf8_lossy(&source).to_string());
+            MacroModule {
+                name,
+                stream: display_path(&names),
+                auto_exec: source
+                    .as_deref()
+                    .map(auto_exec_procedures)
+                    .unwrap_or_default(),
+                source,
+            }
+        })
+        .collect()
+}
+
+// Names of the auto-exec procedures declared in VBA source
+pub fn auto_exec_procedures(source: &str) -> Vec<String> {
+    let is_modifier = |word: &str| {
+        ["Public", "Private", "Friend", "Static"]
+            .iter()
+            .any(|m| word.eq_ignore_ascii_case(m))
+    };
+    let mut found = Vec::new();
+    for line in source.lines() {
+        let mut words = line.split_whitespace();
+        let mut word = words.next();
+        while word.is_some_and(is_modifier) {
+            word = words.next();
+        }
+        if !word
+            .is_some_and(|w| w.eq_ignore_ascii_case("Sub") || w.eq_ignore_ascii_case("Function"))
+        {
+            continue;
+        }
+        let name = words
+            .next()
+            .and_then(|w| {
+                w.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .next()
+            })
+            .unwrap_or_default();
+        if let Some(known) = AUTO_EXEC_PROCEDURES
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(name))
+            && !found.iter().any(|f| f == known)
+        {
+            found.push(known.to_string());
+        }
+    }
+    found
+}
+
+// (module name, stream name, source offset) from the decompressed dir stream's records
+fn dir_modules(dir: &[u8]) -> Vec<(String, String, usize)> {
+    let mut modules = Vec::new();
+    let mut name = None;
+    let mut stream_name = None;
+    let mut offset = 0;
+    let mut pos = 0;
+    while let Some(header) = dir.get(pos..pos + 6) {
+        let id = u16::from_le_bytes([header[0], header[1]]);
+        let size = u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as usize;
+        // PROJECTVERSION declares 4 bytes but is followed by 6
+        let size = if id == 0x0009 { 6 } else { size };
+        let Some(data) = dir.get(pos + 6..pos + 6 + size) else {
+            break;
+        };
+        match id {
+            0x0019 => name = Some(String::from_utf8_lossy(data).to_string()),
+            0x001A => stream_name = Some(String::from_utf8_lossy(data).to_string()),
+            0x0031 if size >= 4 => {
+                offset = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize
+            }
+            // End of a module record
+            0x002B => {
+                if let Some(name) = name.take() {
+                    let stream_name = stream_name.take().unwrap_or_else(|| name.clone());
+                    modules.push((name, stream_name, offset));
+                }
+                offset = 0;
+            }
+            _ => {}
+        }
+        pos += 6 + size;
+    }
+    modules
+}
+
+// MS-OVBA compression: a signature byte, then chunks of up to 4096 decompressed bytes, each
+// either stored or a run of flag bytes followed by literals and copy tokens
+pub fn decompress_vba(data: &[u8]) -> Option<Vec<u8>> {
+    if data.first() != Some(&1) {
+        return None;
+    }
+    let mut out = Vec::new();
+    let mut pos = 1;
+    while pos + 2 <= data.len() && out.len() < MAX_MACRO_SOURCE_BYTES {
+        let header = u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let chunk_end = (pos + (header & 0x0FFF) as usize + 3).min(data.len());
+        pos += 2;
+        let chunk_start = out.len();
+        if header & 0x8000 == 0 {
+            let end = (pos + 4096).min(data.len());
+            out.extend_from_slice(&data[pos..end]);
+            pos = end;
+            continue;
+        }
+        while pos < chunk_end {
+            let flags = data[pos];
+            pos += 1;
+            for bit in 0..8 {
+                if pos >= chunk_end {
+                    break;
+                }
+                if flags & (1 << bit) == 0 {
+                    out.push(data[pos]);
+                    pos += 1;
+                    continue;
+                }
+                let token = u16::from_le_bytes([data[pos], *data.get(pos + 1)?]);
+                pos += 2;
+                let mut bit_count = 4;
+                while (1 << bit_count) < out.len() - chunk_start {
+                    bit_count += 1;
+                }
+                let length_mask = 0xFFFF >> bit_count;
+                let length = (token & length_mask) as usize + 3;
+                let distance = (token >> (16 - bit_count)) as usize + 1;
+                let start = out.len().checked_sub(distance)?;
+                if start < chunk_start {
+                    return None;
+                }
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+        pos = chunk_end;
+    }
+    Some(out)
+}
+
+// Ole10Native: total size, then for Packager objects a label, the original path, a temporary
+// path and the file itself. Data in any other layout is returned whole, past the size.
+fn unpack_ole10_native(data: &[u8]) -> (Option<String>, Vec<u8>) {
+    let whole = data.get(4..).unwrap_or_default().to_vec();
+    let package = || {
+        let mut pos = 4 + 2;
+        let cstr = |pos: &mut usize| {
+            let len = data.get(*pos..)?.iter().position(|&b| b == 0)?;
+            let text = String::from_utf8_lossy(&data[*pos..*pos + len]).to_string();
+            *pos += len + 1;
+            Some(text)
+        };
+        let label = cstr(&mut pos)?;
+        let _source_path = cstr(&mut pos)?;
+        pos += 4;
+        let _temp_path = cstr(&mut pos)?;
+        let size = u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let file = data.get(pos + 4..pos + 4 + size)?;
+        Some((label, file.to_vec()))
+    };
+    match package() {
+        Some((label, file)) => (Some(label).filter(|l| !l.is_empty()), file),
+        None => (None, whole),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Compressed container of literal tokens only, as long as `data` fits one chunk
+    fn literal_container(data: &[u8]) -> Vec<u8> {
+        let mut chunk = Vec::new();
+        for group in data.chunks(8) {
+            chunk.push(0);
+            chunk.extend_from_slice(group);
+        }
+        let header = 0xB000 | (chunk.len() as u16 + 2 - 3);
+        let mut container = vec![1];
+        container.extend_from_slice(&header.to_le_bytes());
+        container.extend(chunk);
+        container
+    }
+
+    fn record(id: u16, data: &[u8]) -> Vec<u8> {
+        let mut record = id.to_le_bytes().to_vec();
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    #[test]
+    fn test_decompress_vba_copy_tokens() {
+        // Example from MS-OVBA 3.2.3
+        let compressed = [
+            0x01, 0x2F, 0xB0, 0x00, 0x23, 0x61, 0x61, 0x61, 0x62, 0x63, 0x64, 0x65, 0x82, 0x66,
+            0x00, 0x70, 0x61, 0x67, 0x68, 0x69, 0x6A, 0x01, 0x38, 0x08, 0x61, 0x6B, 0x6C, 0x00,
+            0x30, 0x6D, 0x6E, 0x6F, 0x70, 0x06, 0x71, 0x02, 0x70, 0x04, 0x10, 0x72, 0x73, 0x74,
+            0x75, 0x76, 0x10, 0x77, 0x78, 0x79, 0x7A, 0x00, 0x3C,
+        ];
+        assert_eq!(
+            decompress_vba(&compressed).unwrap(),
+            b"#aaabcdefaaaaghijaaaaaklaaamnopqaaaaaaaaaaaarstuvwxyzaaa"
+        );
+    }
+
+    #[test]
+    fn test_auto_exec_procedures() {
+        let source = "Attribute VB_Name = \"ThisWorkbook\"\r\n\
+                      Private Sub workbook_open()\r\n\
+                      Call Helper ' Sub AutoOpen() in a comment isn't a declaration\r\n\
+                      End Sub\r\n\
+                      Public Function Helper() As Long\r\n\
+                      End Function\r\n\
+                      Sub AutoClose(): End Sub\r\n";
+        assert_eq!(auto_exec_procedures(source), ["Workbook_Open", "AutoClose"]);
+    }
+
+    #[test]
+    fn test_lists_macros_objects_and_unusual_streams() {
+        let mut file = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        file.create_stream("/WordDocument")
+            .unwrap()
+            .write_all(&[0; 512])
+            .unwrap();
+        file.create_stream("/payload")
+            .unwrap()
+            .write_all(b"hidden")
+            .unwrap();
+
+        // PROJECTVERSION, whose size field is 4 but is followed by 6 bytes
+        let mut dir = 0x0009u16.to_le_bytes().to_vec();
+        dir.extend_from_slice(&4u32.to_le_bytes());
+        dir.extend_from_slice(&[0; 6]);
+        dir.extend(record(0x0019, b"Module1"));
+        dir.extend(record(0x001A, b"Module1"));
+        dir.extend(record(0x0031, &8u32.to_le_bytes()));
+        dir.extend(record(0x002B, &[]));
+        dir.extend(record(0x0010, &[]));
+        let source = b"Sub AutoOpen()\r\nEnd Sub\r\n";
+        let mut module = vec![0xAA; 8];
+        module.extend(literal_container(source));
+        file.create_storage_all("/Macros/VBA").unwrap();
+        file.create_stream("/Macros/VBA/dir")
+            .unwrap()
+            .write_all(&literal_container(&dir))
+            .unwrap();
+        file.create_stream("/Macros/VBA/Module1")
+            .unwrap()
+            .write_all(&module)
+            .unwrap();
+
+        let mut native = Vec::new();
+        let gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;";
+        native.extend_from_slice(&0u32.to_le_bytes());
+        native.extend_from_slice(&2u16.to_le_bytes());
+        native.extend_from_slice(b"dot.gif\0C:\\dot.gif\0");
+        native.extend_from_slice(&[0; 4]);
+        native.extend_from_slice(b"C:\\Temp\\dot.gif\0");
+        native.extend_from_slice(&(gif.len() as u32).to_le_bytes());
+        native.extend_from_slice(gif);
+        file.create_storage_all("/ObjectPool/_1234").unwrap();
+        file.create_stream("/ObjectPool/_1234/\u{1}Ole10Native")
+            .unwrap()
+            .write_all(&native)
+            .unwrap();
+        let data = file.into_inner().into_inner();
+
+        let analysis = OleAnalyzer::analyze(data).unwrap();
+        assert!(analysis.suspicious);
+        let unusual: Vec<&str> = analysis
+            .unusual_entries()
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(unusual, ["payload"]);
+
+        assert_eq!(analysis.macros.len(), 1);
+        assert_eq!(analysis.macros[0].name, "Module1");
+        assert_eq!(analysis.macros[0].stream, "Macros/VBA/Module1");
+        assert_eq!(
+            analysis.macros[0].source.as_deref(),
+            Some("Sub AutoOpen()\r\nEnd Sub\r\n")
+        );
+        assert_eq!(analysis.macros[0].auto_exec, ["AutoOpen"]);
+
+        assert_eq!(analysis.embedded_objects.len(), 1);
+        let object = &analysis.embedded_objects[0];
+        assert_eq!(object.stream, "ObjectPool/_1234/\\u{1}Ole10Native");
+        assert_eq!(object.file_name.as_deref(), Some("dot.gif"));
+        assert_eq!(object.data, gif);
+        assert_eq!(object.classification, "image/gif");
+        assert!(!object.is_executable());
+    }
+}
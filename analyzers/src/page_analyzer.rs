@@ -0,0 +1,190 @@
+use crate::Analyzer;
+use crate::video_frame_analyzer::{VideoFrameAnalysis, VideoFrameAnalyzer};
+use image::DynamicImage;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Compares the sub-images of a multi-page file (TIFF pages, ICO entries) against each
+// other. Pages of one document or sizes of one icon share their statistics closely, so a
+// page whose LSB entropy, chi-square or edge density sits far from the rest is likely to
+// carry a payload or to have been added later.
+pub struct PageAnalyzer;
+
+// Robust z-score (median / MAD) past which a page is an outlier
+pub const OUTLIER_SCORE_THRESHOLD: f64 = 3.5;
+
+// Pages needed before "the rest" is meaningful
+pub const MIN_PAGES_FOR_OUTLIERS: usize = 3;
+
+// Pages smaller than this are too noisy to score and are left out of the comparison
+pub const MIN_PAGE_PIXELS: u64 = 256;
+
+// 1.4826 * MAD estimates the standard deviation of normally distributed values
+const MAD_SCALE: f64 = 1.4826;
+
+#[derive(Debug)]
+pub enum PageAnalyzerError {
+    NoPages,
+    Page(usize, String),
+}
+
+impl Display for PageAnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageAnalyzerError::NoPages => write!(f, "No decodable pages"),
+            PageAnalyzerError::Page(idx, e) => write!(f, "Page {} analysis failed: {}", idx, e),
+        }
+    }
+}
+
+impl std::error::Error for PageAnalyzerError {}
+
+#[derive(Debug, Clone)]
+pub struct PageStats {
+    pub width: u32,
+    pub height: u32,
+    pub frame: VideoFrameAnalysis,
+    pub mean_lsb_entropy: f64,
+    // Chi-square divided by the number of LSB pairs, so pages of different sizes compare
+    pub chi_square_per_pair: f64,
+    // Largest robust z-score over the three statistics, None when the page isn't scored
+    pub outlier_score: Option<f64>,
+    pub outlier: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PageAnalysis {
+    // In input order
+    pub pages: Vec<PageStats>,
+    // Input positions of outlier pages
+    pub outliers: Vec<usize>,
+}
+
+impl Analyzer for PageAnalyzer {
+    type Input = Vec<DynamicImage>;
+    type Output = PageAnalysis;
+    type Error = PageAnalyzerError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if input.is_empty() {
+            return Err(PageAnalyzerError::NoPages);
+        }
+
+        let mut pages = Vec::new();
+        for (idx, image) in input.into_iter().enumerate() {
+            let (width, height) = (image.width(), image.height());
+            let mut frame = VideoFrameAnalyzer::analyze(image)
+                .map_err(|e| PageAnalyzerError::Page(idx, e.to_string()))?;
+            frame.frame_index = idx;
+
+            let pairs = ((width as u64 * height as u64) / 2).max(1) as f64;
+            let channels = frame.entropy_scores.len().max(1) as f64;
+            pages.push(PageStats {
+                width,
+                height,
+                mean_lsb_entropy: frame.entropy_scores.iter().sum::<f64>() / channels,
+                chi_square_per_pair: frame.chi_square_scores.iter().sum::<f64>() / channels / pairs,
+                frame,
+                outlier_score: None,
+                outlier: false,
+            });
+        }
+
+        let scored: Vec<usize> = (0..pages.len())
+            .filter(|&i| pages[i].width as u64 * pages[i].height as u64 >= MIN_PAGE_PIXELS)
+            .collect();
+        if scored.len() >= MIN_PAGES_FOR_OUTLIERS {
+            let features: [fn(&PageStats) -> f64; 3] = [
+                |p| p.mean_lsb_entropy,
+                |p| p.chi_square_per_pair,
+                |p| p.frame.edge_density,
+            ];
+            for feature in features {
+                let values: Vec<f64> = scored.iter().map(|&i| feature(&pages[i])).collect();
+                for (&i, z) in scored.iter().zip(robust_z_scores(&values)) {
+                    let score = pages[i].outlier_score.get_or_insert(0.0);
+                    *score = score.max(z);
+                }
+            }
+        }
+
+        let mut outliers = Vec::new();
+        for (idx, page) in pages.iter_mut().enumerate() {
+            page.outlier = page
+                .outlier_score
+                .is_some_and(|score| score > OUTLIER_SCORE_THRESHOLD);
+            if page.outlier {
+                outliers.push(idx);
+            }
+        }
+
+        Ok(PageAnalysis { pages, outliers })
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+// Distance of each value from the median in units of the scaled median absolute
+// deviation. When most values are identical the MAD is zero, so the scale is floored at
+// a small fraction of the median to keep float noise from counting as an outlier.
+fn robust_z_scores(values: &[f64]) -> Vec<f64> {
+    let center = median(&mut values.to_vec());
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    let scale = (median(&mut deviations) * MAD_SCALE)
+        .max(center.abs() * 0.05)
+        .max(1e-3);
+    values.iter().map(|v| (v - center).abs() / scale).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // Smooth gradient whose LSBs follow the pixel values
+    fn clean_page(seed: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            let v = ((x + y + seed) / 4) as u8;
+            Rgb([v, v.wrapping_add(10), v.wrapping_add(20)])
+        }))
+    }
+
+    #[test]
+    fn test_flags_page_with_random_lsbs() {
+        let mut pages: Vec<DynamicImage> = (0..4).map(clean_page).collect();
+        let mut state = 12345u32;
+        let mut stego = clean_page(1).to_rgb8();
+        for pixel in stego.pixels_mut() {
+            for channel in pixel.0.iter_mut() {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                *channel = (*channel & !1) | ((state >> 16) & 1) as u8;
+            }
+        }
+        pages.insert(2, DynamicImage::ImageRgb8(stego));
+
+        let analysis = PageAnalyzer::analyze(pages).unwrap();
+        assert_eq!(analysis.outliers, vec![2]);
+        assert!(analysis.pages.iter().all(|p| p.outlier_score.is_some()));
+    }
+
+    #[test]
+    fn test_too_few_pages_are_not_scored() {
+        let analysis = PageAnalyzer::analyze(vec![clean_page(0), clean_page(1)]).unwrap();
+        assert!(analysis.outliers.is_empty());
+        assert!(analysis.pages.iter().all(|p| p.outlier_score.is_none()));
+        assert!(matches!(
+            PageAnalyzer::analyze(Vec::new()),
+            Err(PageAnalyzerError::NoPages)
+        ));
+    }
+}
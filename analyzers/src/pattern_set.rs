@@ -0,0 +1,96 @@
+use aho_corasick::AhoCorasick;
+
+// Many byte patterns searched for in one pass. Scans that check each pattern on its own walk
+// the input once per pattern, so a signature list that grows to hundreds of entries makes
+// them that many times slower; an Aho-Corasick automaton looks at each byte once however
+// many patterns there are.
+//
+// Every pattern carries a value of the caller's choosing, handed back with each hit.
+pub struct PatternSet<T> {
+    matcher: AhoCorasick,
+    entries: Vec<(usize, T)>,
+}
+
+#[derive(Debug)]
+pub struct PatternHit<'a, T> {
+    pub start: usize,
+    pub end: usize,
+    // Position of the pattern in the list the set was built from
+    pub index: usize,
+    pub value: &'a T,
+}
+
+impl<T> PatternSet<T> {
+    pub fn new<P: AsRef<[u8]>>(patterns: impl IntoIterator<Item = (P, T)>) -> Self {
+        let (patterns, values): (Vec<P>, Vec<T>) = patterns.into_iter().unzip();
+        let matcher = AhoCorasick::new(&patterns).expect("byte patterns are always valid");
+        let entries = patterns
+            .iter()
+            .map(|p| p.as_ref().len())
+            .zip(values)
+            .collect();
+        Self { matcher, entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn pattern_len(&self, index: usize) -> usize {
+        self.entries[index].0
+    }
+
+    pub fn value(&self, index: usize) -> &T {
+        &self.entries[index].1
+    }
+
+    // Every occurrence of every pattern, overlapping ones included, in order of where they end
+    pub fn find_all<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = PatternHit<'a, T>> {
+        self.matcher.find_overlapping_iter(haystack).map(|hit| {
+            let index = hit.pattern().as_usize();
+            PatternHit {
+                start: hit.start(),
+                end: hit.end(),
+                index,
+                value: &self.entries[index].1,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_overlapping_and_duplicate_patterns() {
+        let set = PatternSet::new([
+            (b"PK\x03\x04".as_slice(), "zip"),
+            (b"\x03\x04", "tail"),
+            (b"PK\x03\x04", "zip again"),
+        ]);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.pattern_len(1), 2);
+
+        let mut hits: Vec<_> = set
+            .find_all(b"xxPK\x03\x04PK\x03\x04")
+            .map(|hit| (hit.start, *hit.value))
+            .collect();
+        hits.sort();
+        assert_eq!(
+            hits,
+            vec![
+                (2, "zip"),
+                (2, "zip again"),
+                (4, "tail"),
+                (6, "zip"),
+                (6, "zip again"),
+                (8, "tail"),
+            ]
+        );
+    }
+}
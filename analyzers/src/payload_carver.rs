@@ -0,0 +1,268 @@
+use crate::Analyzer;
+use crate::magic_bytes_analyzer::{MagicBytesAnalysis, is_complete_file_signature};
+use std::fmt::Display;
+
+pub struct PayloadCarver;
+
+#[derive(Debug)]
+pub enum PayloadCarverError {
+    InvalidOffset(usize),
+}
+
+impl Display for PayloadCarverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayloadCarverError::InvalidOffset(offset) => {
+                write!(f, "Embedded file offset 0x{:X} is outside the file", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PayloadCarverError {}
+
+#[derive(Debug, Clone)]
+pub struct CarvedPayload {
+    pub offset: usize,
+    pub description: String,
+    pub file_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Analyzer for PayloadCarver {
+    // Raw file bytes plus the magic bytes result describing where payloads start
+    type Input = (Vec<u8>, MagicBytesAnalysis);
+    type Output = Vec<CarvedPayload>;
+    type Error = PayloadCarverError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (data, analysis) = input;
+
+        // Only complete file headers past the primary file are worth carving
+        let mut starts: Vec<_> = analysis
+            .embedded_files
+            .iter()
+            .filter(|f| f.offset > 0 && is_complete_file_signature(&f.description))
+            .collect();
+        starts.sort_by_key(|f| f.offset);
+        starts.dedup_by_key(|f| f.offset);
+
+        let mut payloads = Vec::new();
+        for (idx, file) in starts.iter().enumerate() {
+            if file.offset >= data.len() {
+                return Err(PayloadCarverError::InvalidOffset(file.offset));
+            }
+
+            // A PNG, JPEG or ZIP runs to its own end marker, which also keeps files nested
+            // inside it whole; anything else runs until the next carved signature, or the
+            // end of the file
+            let end = embedded_file_end(&data, file.offset).unwrap_or_else(|| {
+                starts
+                    .get(idx + 1)
+                    .map(|next| next.offset)
+                    .unwrap_or(data.len())
+            });
+
+            payloads.push(CarvedPayload {
+                offset: file.offset,
+                description: file.description.clone(),
+                file_type: file.file_type.clone(),
+                data: data[file.offset..end].to_vec(),
+            });
+        }
+
+        Ok(payloads)
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_EOCD: &[u8] = b"PK\x05\x06";
+// End of central directory record without its trailing comment
+const ZIP_EOCD_LEN: usize = 22;
+
+// Where the PNG, JPEG or ZIP file starting at `offset` ends: past the IEND chunk, the EOI
+// marker or the end of central directory record and its comment. None for other formats
+// and for files cut short before their end marker.
+pub fn embedded_file_end(data: &[u8], offset: usize) -> Option<usize> {
+    let file = data.get(offset..)?;
+    let len = if file.starts_with(PNG_SIGNATURE) {
+        png_len(file)
+    } else if file.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        jpeg_len(file)
+    } else if file.starts_with(ZIP_LOCAL_HEADER) {
+        zip_len(file)
+    } else {
+        None
+    }?;
+    Some(offset + len)
+}
+
+// Walk the chunks up to and including IEND
+fn png_len(file: &[u8]) -> Option<usize> {
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= file.len() {
+        let length = u32::from_be_bytes(file[pos..pos + 4].try_into().ok()?) as usize;
+        let end = pos.checked_add(12)?.checked_add(length)?;
+        if end > file.len() {
+            return None;
+        }
+        if &file[pos + 4..pos + 8] == b"IEND" {
+            return Some(end);
+        }
+        pos = end;
+    }
+    None
+}
+
+// Walk the marker segments, skipping the entropy-coded data after each SOS, up to EOI.
+// Searching for the first FF D9 instead would stop early inside an embedded thumbnail.
+fn jpeg_len(file: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *file.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of FF fill bytes may come before a marker
+        while *file.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = file[pos + 1];
+        pos += 2;
+        match marker {
+            0xD9 => return Some(pos),
+            // Standalone markers carry no length
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([*file.get(pos)?, *file.get(pos + 1)?]) as usize;
+        if length < 2 {
+            return None;
+        }
+        pos += length;
+        if marker == 0xDA {
+            // Scan data ends at the first marker other than a stuffed FF 00 or a restart
+            loop {
+                let ff = pos + file.get(pos..)?.iter().position(|&b| b == 0xFF)?;
+                match *file.get(ff + 1)? {
+                    0x00 | 0xD0..=0xD7 => pos = ff + 2,
+                    _ => {
+                        pos = ff;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The first end of central directory record whose directory offset and size point back at
+// itself, so an EOCD belonging to a ZIP stored inside this one is passed over
+fn zip_len(file: &[u8]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(found) = file[from..]
+        .windows(ZIP_EOCD.len())
+        .position(|window| window == ZIP_EOCD)
+    {
+        let eocd = from + found;
+        let record = file.get(eocd..eocd + ZIP_EOCD_LEN)?;
+        let directory_size = u32::from_le_bytes(record[12..16].try_into().ok()?) as usize;
+        let directory_offset = u32::from_le_bytes(record[16..20].try_into().ok()?) as usize;
+        let comment_len = u16::from_le_bytes(record[20..22].try_into().ok()?) as usize;
+        // ZIP64 archives keep the real values in their own record
+        let zip64 = directory_offset == u32::MAX as usize || directory_size == u32::MAX as usize;
+        if zip64 || directory_offset + directory_size == eocd {
+            let end = eocd + ZIP_EOCD_LEN + comment_len;
+            return (end <= file.len()).then_some(end);
+        }
+        from = eocd + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::magic_bytes_analyzer::{EmbeddedFile, FormatSummary};
+
+    fn embedded(offset: usize, description: &str) -> EmbeddedFile {
+        EmbeddedFile {
+            offset,
+            description: description.to_string(),
+            file_type: "Image".to_string(),
+            confidence: "medium".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_carve_between_signatures() {
+        let data: Vec<u8> = (0..100).collect();
+        let analysis = MagicBytesAnalysis {
+            primary_format: "PNG image".to_string(),
+            expected_format: None,
+            total_signatures_found: 3,
+            embedded_files: vec![
+                embedded(0, "PNG image"),
+                embedded(40, "JPEG image (JFIF)"),
+                embedded(70, "ZIP archive"),
+            ],
+            has_multiple_formats: true,
+            has_suspicious_data: true,
+            suspicious_findings: Vec::new(),
+            format_summary: FormatSummary::default(),
+        };
+
+        let payloads = PayloadCarver::analyze((data, analysis)).unwrap();
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].offset, 40);
+        assert_eq!(payloads[0].data.len(), 30);
+        assert_eq!(payloads[1].data.len(), 30);
+        assert_eq!(payloads[1].data[0], 70);
+    }
+
+    #[test]
+    fn test_end_markers() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (chunk_type, body) in [(&b"IHDR"[..], &[0u8; 13][..]), (&b"IEND"[..], &[][..])] {
+            png.extend((body.len() as u32).to_be_bytes());
+            png.extend(chunk_type);
+            png.extend(body);
+            png.extend([0; 4]);
+        }
+
+        // An APP segment holding FF D9, then scan data with a stuffed byte and a restart
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x04, 0xFF, 0xD9];
+        jpeg.extend([
+            0xFF, 0xDA, 0x00, 0x02, 0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD0, 0x56,
+        ]);
+        jpeg.extend([0xFF, 0xD9]);
+
+        let mut zip = ZIP_LOCAL_HEADER.to_vec();
+        zip.extend([0; 26]);
+        let directory_offset = zip.len() as u32;
+        zip.extend(b"PK\x01\x02");
+        zip.extend([0; 42]);
+        let directory_size = zip.len() as u32 - directory_offset;
+        zip.extend(ZIP_EOCD);
+        zip.extend([0; 8]);
+        zip.extend(directory_size.to_le_bytes());
+        zip.extend(directory_offset.to_le_bytes());
+        zip.extend(3u16.to_le_bytes());
+        zip.extend(b"abc");
+
+        let mut data = vec![0xAA; 16];
+        for file in [&png, &jpeg, &zip] {
+            let start = data.len();
+            data.extend(file.iter());
+            data.extend([0xAA; 16]);
+            assert_eq!(embedded_file_end(&data, start), Some(start + file.len()));
+            // Cut short before the end marker
+            assert_eq!(
+                embedded_file_end(&data[..start + file.len() - 1], start),
+                None
+            );
+        }
+        assert_eq!(embedded_file_end(&data, 0), None);
+        assert_eq!(embedded_file_end(&data, data.len() + 1), None);
+    }
+}
@@ -0,0 +1,403 @@
+use crate::id3_analyzer::classify_payload;
+use flate2::{Decompress, FlushDecompress, Status};
+
+// What a byte stream pulled out of LSBs most likely holds. Extraction hands over bits with no
+// framing, so the stream is read both bit orders round, with and without the 32-bit length
+// header many embedding tools write first, and each reading is checked for zlib or gzip
+// compression, a known file signature and text. The reading that explains the bytes best is
+// reported rather than the raw bits.
+//
+// Embedders don't always write payload bits into consecutive LSBs. sniff_bits also undoes the
+// common codings before reading: every n-th sample, one bit as the parity of a block, and the
+// (1, 2^k - 1, k) Hamming matrix embedding of F5, where a block's syndrome carries k bits.
+// Keyed permutations such as steghide's can't be undone without the passphrase.
+
+// Bytes inflated from a compressed candidate at most
+pub const MAX_INFLATED_BYTES: usize = 1 << 20;
+
+// Share of printable ASCII at which a candidate reads as text
+pub const TEXT_RATIO: f64 = 0.9;
+
+// Largest stride, parity block and Hamming k tried by sniff_bits
+pub const MAX_STRIDE: usize = 4;
+pub const MAX_PARITY_BLOCK: usize = 4;
+pub const MAX_MATRIX_K: u32 = 7;
+
+// Text shorter than this is as likely to be chance
+const MIN_TEXT_BYTES: usize = 4;
+
+// Output a deflate stream has to produce before it counts; random bytes can pass the
+// two-byte zlib header check and inflate a byte or two before failing
+const MIN_INFLATED_BYTES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    // First extracted bit is the top bit of the first byte
+    MsbFirst,
+    LsbFirst,
+}
+
+impl std::fmt::Display for BitOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitOrder::MsbFirst => write!(f, "msb-first"),
+            BitOrder::LsbFirst => write!(f, "lsb-first"),
+        }
+    }
+}
+
+// How payload bits were spread over the LSBs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodingScheme {
+    // One payload bit per LSB, in order
+    Plain,
+    // Every `step`th LSB from `start`
+    Stride { step: usize, start: usize },
+    // One bit per block of `block` LSBs: their parity
+    Parity { block: usize },
+    // k bits per block of 2^k - 1 LSBs: the XOR of the 1-based positions of the set LSBs
+    Matrix { k: u32 },
+}
+
+impl CodingScheme {
+    // Every scheme sniff_bits tries, plainest first
+    pub fn all() -> Vec<CodingScheme> {
+        let mut schemes = vec![CodingScheme::Plain];
+        for step in 2..=MAX_STRIDE {
+            schemes.extend((0..step).map(|start| CodingScheme::Stride { step, start }));
+        }
+        schemes.extend((2..=MAX_PARITY_BLOCK).map(|block| CodingScheme::Parity { block }));
+        schemes.extend((2..=MAX_MATRIX_K).map(|k| CodingScheme::Matrix { k }));
+        schemes
+    }
+
+    // The payload bits the LSBs carry under this scheme, most significant first
+    pub fn decode(self, lsbs: &[bool]) -> Vec<bool> {
+        match self {
+            CodingScheme::Plain => lsbs.to_vec(),
+            CodingScheme::Stride { step, start } => {
+                lsbs.iter().skip(start).step_by(step).copied().collect()
+            }
+            CodingScheme::Parity { block } => lsbs
+                .chunks_exact(block)
+                .map(|bits| bits.iter().filter(|&&bit| bit).count() % 2 == 1)
+                .collect(),
+            CodingScheme::Matrix { k } => lsbs
+                .chunks_exact((1 << k) - 1)
+                .flat_map(|bits| {
+                    let syndrome = bits
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &bit)| bit)
+                        .fold(0usize, |acc, (i, _)| acc ^ (i + 1));
+                    (0..k).rev().map(move |i| syndrome >> i & 1 == 1)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for CodingScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodingScheme::Plain => write!(f, "plain"),
+            CodingScheme::Stride { step, start } => {
+                write!(f, "every {} from {}", step, start)
+            }
+            CodingScheme::Parity { block } => write!(f, "parity of {}", block),
+            CodingScheme::Matrix { k } => write!(f, "matrix (1, {}, {})", (1 << k) - 1, k),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadInterpretation {
+    pub scheme: CodingScheme,
+    pub bit_order: BitOrder,
+    // Payload length a leading 32-bit big-endian header gave, when the reading used one
+    pub length_header: Option<u32>,
+    // "zlib" or "gzip" when the payload had to be inflated first
+    pub compression: Option<&'static str>,
+    // MIME type of a known file signature, "text" or "binary", as classify_payload names them
+    pub classification: String,
+    // Share of printable ASCII in the payload
+    pub printable_ratio: f64,
+    // The payload after header removal and inflation; text is cut at its first NUL
+    pub data: Vec<u8>,
+}
+
+impl PayloadInterpretation {
+    // Anything but binary: a file, text, or a stream that inflated
+    pub fn is_recognized(&self) -> bool {
+        self.classification != "binary" || self.compression.is_some()
+    }
+
+    pub fn text(&self) -> Option<String> {
+        (self.classification == "text").then(|| String::from_utf8_lossy(&self.data).to_string())
+    }
+
+    // Inflated streams and file signatures explain the bytes better than text does
+    fn score(&self) -> u8 {
+        match (self.compression, self.classification.as_str()) {
+            (Some(_), _) => 3,
+            (None, "text") => 1,
+            (None, "binary") => 0,
+            (None, _) => 2,
+        }
+    }
+}
+
+// The best reading of `bytes`, packed most significant bit first as extracted. Candidates are
+// tried plainest first and only a better score replaces one, so ties keep the simpler reading.
+pub fn sniff(bytes: &[u8]) -> PayloadInterpretation {
+    let reversed: Vec<u8> = bytes.iter().map(|b| b.reverse_bits()).collect();
+    let mut best = interpret(bytes, BitOrder::MsbFirst, None);
+    for (stream, bit_order) in [
+        (bytes, BitOrder::MsbFirst),
+        (&reversed[..], BitOrder::LsbFirst),
+    ] {
+        let mut candidates = vec![interpret(stream, bit_order, None)];
+        if let Some((length, payload)) = length_prefixed(stream) {
+            candidates.push(interpret(payload, bit_order, Some(length)));
+        }
+        for candidate in candidates {
+            if candidate.score() > best.score() {
+                best = candidate;
+            }
+        }
+    }
+    best
+}
+
+// The best reading of extracted LSBs under any of the codings, reporting the one that
+// succeeded. More schemes mean more chances for noise to pass as a signature, so only a
+// better score than the plain reading's replaces it.
+pub fn sniff_bits(lsbs: &[bool]) -> PayloadInterpretation {
+    let mut best: Option<PayloadInterpretation> = None;
+    for scheme in CodingScheme::all() {
+        let mut candidate = sniff(&pack(&scheme.decode(lsbs)));
+        candidate.scheme = scheme;
+        if best
+            .as_ref()
+            .is_none_or(|best| candidate.score() > best.score())
+        {
+            best = Some(candidate);
+        }
+    }
+    best.unwrap_or_else(|| interpret(&[], BitOrder::MsbFirst, None))
+}
+
+fn pack(bits: &[bool]) -> Vec<u8> {
+    bits.chunks_exact(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8))
+        .collect()
+}
+
+fn interpret(
+    stream: &[u8],
+    bit_order: BitOrder,
+    length_header: Option<u32>,
+) -> PayloadInterpretation {
+    let (compression, data) = match inflate(stream) {
+        Some((format, inflated)) => (Some(format), inflated),
+        None => (None, stream.to_vec()),
+    };
+
+    let mut classification = classify_payload(&data);
+    let mut data = data;
+    if classification == "binary" {
+        // Embedders usually leave the LSBs after a message untouched or zeroed
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        if end >= MIN_TEXT_BYTES && printable_ratio(&data[..end]) >= TEXT_RATIO {
+            data.truncate(end);
+            classification = "text".to_string();
+        }
+    } else if classification == "text" && data.len() < MIN_TEXT_BYTES {
+        classification = "binary".to_string();
+    }
+
+    PayloadInterpretation {
+        scheme: CodingScheme::Plain,
+        bit_order,
+        length_header,
+        compression,
+        printable_ratio: printable_ratio(&data),
+        classification,
+        data,
+    }
+}
+
+// A plausible 32-bit big-endian length followed by at least some of that many bytes
+fn length_prefixed(stream: &[u8]) -> Option<(u32, &[u8])> {
+    let header: [u8; 4] = stream.get(..4)?.try_into().ok()?;
+    let length = u32::from_be_bytes(header);
+    let rest = &stream[4..];
+    // Streams are often cut short of the whole payload, but a length past any LSB capacity is
+    // just the first four bytes of something else
+    (length > 0 && length <= 1 << 24 && !rest.is_empty())
+        .then(|| (length, &rest[..rest.len().min(length as usize)]))
+}
+
+// Inflate a zlib or gzip stream, keeping what decodes before the stream is cut short
+fn inflate(stream: &[u8]) -> Option<(&'static str, Vec<u8>)> {
+    let (format, body, zlib_header) = if stream.starts_with(&[0x1f, 0x8b, 0x08]) {
+        ("gzip", gzip_body(stream)?, false)
+    } else if stream.len() >= 2
+        && stream[0] & 0x0f == 8
+        && (u16::from(stream[0]) << 8 | u16::from(stream[1])) % 31 == 0
+    {
+        ("zlib", stream, true)
+    } else {
+        return None;
+    };
+
+    let mut decompress = Decompress::new(zlib_header);
+    let mut out = Vec::new();
+    loop {
+        out.reserve(4096);
+        let (read, written) = (decompress.total_in(), decompress.total_out());
+        let status = decompress
+            .decompress_vec(&body[read as usize..], &mut out, FlushDecompress::None)
+            .ok()?;
+        // A cut stream stops making progress rather than failing
+        let stalled = decompress.total_in() == read && decompress.total_out() == written;
+        if status == Status::StreamEnd || stalled || out.len() >= MAX_INFLATED_BYTES {
+            break;
+        }
+    }
+    out.truncate(MAX_INFLATED_BYTES);
+    (out.len() >= MIN_INFLATED_BYTES).then_some((format, out))
+}
+
+// The deflate data of a gzip member, past the header and its optional fields
+pub(crate) fn gzip_body(stream: &[u8]) -> Option<&[u8]> {
+    let flags = *stream.get(3)?;
+    let mut pos = 10;
+    if flags & 0x04 != 0 {
+        let extra = u16::from_le_bytes(stream.get(pos..pos + 2)?.try_into().ok()?) as usize;
+        pos += 2 + extra;
+    }
+    // Name and comment are NUL terminated
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            pos += stream.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        pos += 2;
+    }
+    stream.get(pos..)
+}
+
+fn printable_ratio(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let printable = data
+        .iter()
+        .filter(|&&b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        .count();
+    printable as f64 / data.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_reads_text_after_length_header_lsb_first() {
+        let message = b"meet at the docks";
+        let mut stream = (message.len() as u32).to_be_bytes().to_vec();
+        stream.extend_from_slice(message);
+        stream.extend_from_slice(&[0x5a; 16]);
+        let extracted: Vec<u8> = stream.iter().map(|b| b.reverse_bits()).collect();
+
+        let payload = sniff(&extracted);
+        assert_eq!(payload.bit_order, BitOrder::LsbFirst);
+        assert_eq!(payload.length_header, Some(message.len() as u32));
+        assert_eq!(payload.text().as_deref(), Some("meet at the docks"));
+    }
+
+    #[test]
+    fn test_inflates_truncated_zlib() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(b"PK\x03\x04 a zip entry, repeated a zip entry, repeated")
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // Cut before the checksum, as an extraction cap would
+        let payload = sniff(&compressed[..compressed.len() - 4]);
+        assert_eq!(payload.compression, Some("zlib"));
+        assert_eq!(payload.classification, "application/zip");
+        assert!(payload.is_recognized());
+    }
+
+    #[test]
+    fn test_undoes_matrix_embedding() {
+        let message = b"F5 says hello";
+        let bits: Vec<bool> = message
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .collect();
+
+        // Embed 3 bits per 7 cover LSBs by flipping at most one of them
+        let mut state = 0x1234_5678u32;
+        let mut lsbs = Vec::new();
+        for chunk in bits.chunks(3) {
+            let mut block: Vec<bool> = (0..7)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state & 1 == 1
+                })
+                .collect();
+            let wanted = chunk
+                .iter()
+                .fold(0usize, |acc, &bit| acc << 1 | bit as usize)
+                << (3 - chunk.len());
+            let syndrome = CodingScheme::Matrix { k: 3 }.decode(&block);
+            let current = syndrome
+                .iter()
+                .fold(0usize, |acc, &bit| acc << 1 | bit as usize);
+            if current != wanted {
+                let flip = current ^ wanted;
+                block[flip - 1] = !block[flip - 1];
+            }
+            lsbs.extend(block);
+        }
+
+        let payload = sniff_bits(&lsbs);
+        assert_eq!(payload.scheme, CodingScheme::Matrix { k: 3 });
+        assert_eq!(payload.text().as_deref(), Some("F5 says hello"));
+        assert_eq!(payload.scheme.to_string(), "matrix (1, 7, 3)");
+    }
+
+    #[test]
+    fn test_noise_stays_binary() {
+        let mut state = 0x2545f491u32;
+        let noise: Vec<u8> = (0..256)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect();
+        let payload = sniff(&noise);
+        assert_eq!(payload.classification, "binary");
+        assert!(!payload.is_recognized());
+        assert_eq!(payload.bit_order, BitOrder::MsbFirst);
+
+        let lsbs: Vec<bool> = noise
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| byte >> i & 1 == 1))
+            .collect();
+        let payload = sniff_bits(&lsbs);
+        assert_eq!(payload.scheme, CodingScheme::Plain);
+        assert!(!payload.is_recognized());
+    }
+}
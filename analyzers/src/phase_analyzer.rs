@@ -0,0 +1,335 @@
+use crate::Analyzer;
+use crate::demodulator::{decoded_text, pack_bits};
+use crate::shared::fft_forward;
+use rustfft::num_complex::Complex;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Looks for phase coding, which hides bits in the phase spectrum where the magnitude-only
+// spectrogram can't see them. The encoder splits the audio into fixed-length segments, sets
+// the phases of the first segment to +pi/2 or -pi/2 per bit, and shifts every later segment
+// by the same per-bin amount so the phase differences between segments are kept. That leaves
+// a run of bins at exactly +-pi/2 in the first segment, and a jump at every segment boundary
+// where the independently shifted segments meet.
+pub struct PhaseAnalyzer;
+
+// Segment lengths tried, covering the usual implementations
+pub const SEGMENT_LENGTHS: [usize; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+// Mean sample jump across segment boundaries relative to the mean jump everywhere
+pub const PHASE_DISCONTINUITY_THRESHOLD: f64 = 3.0;
+
+// How close to +-pi/2 a phase has to be to count as quantized, in radians. Natural phases
+// land there about 3% of the time.
+const PHASE_TOLERANCE: f32 = 0.05;
+
+// Consecutive quantized bins needed to count as coded data; two bytes
+pub const MIN_QUANTIZED_RUN: usize = 16;
+
+// The rarer of the two phases has to fill at least 1 in this many bins of a run
+const MIN_MINORITY_PHASE: usize = 4;
+
+// Bins under this share of the segment's strongest bin carry no reliable phase
+const MIN_BIN_MAGNITUDE: f32 = 0.001;
+
+// Boundaries needed before a jump ratio means anything
+const MIN_BOUNDARIES: usize = 8;
+
+#[derive(Debug)]
+pub enum PhaseAnalyzerError {
+    TooShort(usize),
+}
+
+impl Display for PhaseAnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhaseAnalyzerError::TooShort(len) => write!(
+                f,
+                "{} samples is too short for {} segments of {}",
+                len,
+                MIN_BOUNDARIES + 1,
+                SEGMENT_LENGTHS[0]
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PhaseAnalyzerError {}
+
+#[derive(Debug, Clone)]
+pub struct PhaseAnalysis {
+    // The segment length that best fits the findings
+    pub segment_length: usize,
+    // Mean absolute sample jump at segment boundaries over the mean jump everywhere,
+    // around 1 for unmodified audio
+    pub boundary_jump_ratio: f64,
+    // Longest run of consecutive first-segment bins whose phase sits at +-pi/2
+    pub quantized_run: usize,
+    pub quantized_first_bin: usize,
+    // Share of the first segment's usable bins at +-pi/2
+    pub quantized_share: f64,
+    // The run read as bits, +pi/2 as 0 and -pi/2 as 1, and packed most significant first
+    pub bits: Vec<bool>,
+    pub decoded: Vec<u8>,
+    pub suspicious: bool,
+}
+
+impl PhaseAnalysis {
+    // Re-evaluate the verdict against a caller-supplied discontinuity threshold
+    pub fn is_suspicious_at(&self, discontinuity_threshold: f64) -> bool {
+        self.boundary_jump_ratio > discontinuity_threshold
+            || self.quantized_run >= MIN_QUANTIZED_RUN
+    }
+
+    pub fn decoded_text(&self) -> Option<String> {
+        decoded_text(&self.decoded)
+    }
+}
+
+// Findings for one candidate segment length
+struct SegmentScore {
+    segment_length: usize,
+    boundary_jump_ratio: f64,
+    run: (usize, usize),
+    share: f64,
+    bits: Vec<bool>,
+}
+
+impl Analyzer for PhaseAnalyzer {
+    type Input = Vec<f32>; // Mono samples
+    type Output = PhaseAnalysis;
+    type Error = PhaseAnalyzerError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let min_len = SEGMENT_LENGTHS[0] * (MIN_BOUNDARIES + 1);
+        if input.len() < min_len {
+            return Err(PhaseAnalyzerError::TooShort(input.len()));
+        }
+
+        let mean_jump = input
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs() as f64)
+            .sum::<f64>()
+            / (input.len() - 1) as f64;
+        let scores: Vec<SegmentScore> = SEGMENT_LENGTHS
+            .iter()
+            .filter(|&&n| n <= input.len())
+            .map(|&n| score_segment_length(&input, n, mean_jump))
+            .collect();
+
+        // A coded first segment only shows at the encoder's own length. Otherwise the jumps
+        // decide, preferring the shortest length close to the best, since multiples of the
+        // true length land on the same boundaries.
+        let best_run = scores.iter().max_by_key(|s| s.run.1).unwrap();
+        let chosen = if best_run.run.1 >= MIN_QUANTIZED_RUN {
+            best_run
+        } else {
+            let best_ratio = scores
+                .iter()
+                .map(|s| s.boundary_jump_ratio)
+                .fold(0.0, f64::max);
+            scores
+                .iter()
+                .find(|s| s.boundary_jump_ratio >= 0.8 * best_ratio)
+                .unwrap()
+        };
+
+        let mut analysis = PhaseAnalysis {
+            segment_length: chosen.segment_length,
+            boundary_jump_ratio: chosen.boundary_jump_ratio,
+            quantized_run: chosen.run.1,
+            quantized_first_bin: chosen.run.0,
+            quantized_share: chosen.share,
+            decoded: pack_bits(&chosen.bits),
+            bits: chosen.bits.clone(),
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.is_suspicious_at(PHASE_DISCONTINUITY_THRESHOLD);
+        Ok(analysis)
+    }
+}
+
+fn score_segment_length(samples: &[f32], segment_length: usize, mean_jump: f64) -> SegmentScore {
+    let boundaries: Vec<usize> = (1..samples.len() / segment_length)
+        .map(|k| k * segment_length)
+        .collect();
+    let boundary_jump_ratio = if boundaries.len() >= MIN_BOUNDARIES && mean_jump > 0.0 {
+        boundaries
+            .iter()
+            .map(|&b| (samples[b] - samples[b - 1]).abs() as f64)
+            .sum::<f64>()
+            / boundaries.len() as f64
+            / mean_jump
+    } else {
+        0.0
+    };
+
+    // No window: the encoder wrote these phases with a plain FFT of the segment
+    let mut spectrum: Vec<Complex<f32>> = samples[..segment_length]
+        .iter()
+        .map(|&s| Complex::new(s, 0.0))
+        .collect();
+    fft_forward(segment_length).process(&mut spectrum);
+    let bins = &spectrum[1..segment_length / 2];
+    let loudest = bins.iter().map(|c| c.norm()).fold(0.0f32, f32::max);
+
+    // Per usable bin, Some(bit) when its phase is quantized
+    let quantized: Vec<Option<Option<bool>>> = bins
+        .iter()
+        .map(|c| {
+            (loudest > 0.0 && c.norm() >= MIN_BIN_MAGNITUDE * loudest).then(|| {
+                let phase = c.arg();
+                if (phase - std::f32::consts::FRAC_PI_2).abs() <= PHASE_TOLERANCE {
+                    Some(false)
+                } else if (phase + std::f32::consts::FRAC_PI_2).abs() <= PHASE_TOLERANCE {
+                    Some(true)
+                } else {
+                    None
+                }
+            })
+        })
+        .collect();
+
+    let usable = quantized.iter().flatten().count();
+    let share = if usable > 0 {
+        quantized.iter().flatten().flatten().count() as f64 / usable as f64
+    } else {
+        0.0
+    };
+
+    // Longest run as (first bin, length); an unusable bin breaks it
+    let (mut run, mut start) = ((0, 0), 0);
+    for (i, bin) in quantized.iter().enumerate() {
+        if matches!(bin, Some(Some(_))) {
+            if i + 1 - start > run.1 {
+                run = (start + 1, i + 1 - start);
+            }
+        } else {
+            start = i + 1;
+        }
+    }
+    let mut bits: Vec<bool> = quantized[run.0.saturating_sub(1)..][..run.1]
+        .iter()
+        .map(|bin| bin.flatten().unwrap())
+        .collect();
+
+    // The leakage of a tone that starts at zero phase sits at one of the two phases; coded
+    // data uses both
+    let ones = bits.iter().filter(|&&bit| bit).count();
+    if ones.min(bits.len() - ones) * MIN_MINORITY_PHASE < bits.len() {
+        run = (0, 0);
+        bits.clear();
+    }
+
+    SegmentScore {
+        segment_length,
+        boundary_jump_ratio,
+        run,
+        share,
+        bits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::fft_inverse;
+
+    const SEGMENT: usize = 1024;
+
+    // Low tones over a little noise, so sample-to-sample jumps are small
+    fn music(len: usize) -> Vec<f32> {
+        let mut seed = 12345u32;
+        (0..len)
+            .map(|i| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let noise = (seed >> 16) as f32 / 32768.0 - 1.0;
+                let t = i as f32 / 44100.0;
+                [200.0, 330.0, 500.0]
+                    .iter()
+                    .map(|f| 0.2 * (2.0 * std::f32::consts::PI * f * t).sin())
+                    .sum::<f32>()
+                    + 0.02 * noise
+            })
+            .collect()
+    }
+
+    // Textbook phase coding of the message into bins 1.. of the first segment
+    fn phase_code(samples: &[f32], message: &[u8]) -> Vec<f32> {
+        let bits: Vec<bool> = message
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .collect();
+        let forward = fft_forward(SEGMENT);
+        let inverse = fft_inverse(SEGMENT);
+
+        let mut previous_original: Vec<f32> = Vec::new();
+        let mut previous_coded: Vec<f32> = Vec::new();
+        let mut out = Vec::new();
+        for segment in samples.chunks_exact(SEGMENT) {
+            let mut spectrum: Vec<Complex<f32>> =
+                segment.iter().map(|&s| Complex::new(s, 0.0)).collect();
+            forward.process(&mut spectrum);
+            let original: Vec<f32> = spectrum.iter().map(|c| c.arg()).collect();
+            let coded: Vec<f32> = if previous_coded.is_empty() {
+                let mut phases = original.clone();
+                for (i, &bit) in bits.iter().enumerate() {
+                    let phase = if bit {
+                        -std::f32::consts::FRAC_PI_2
+                    } else {
+                        std::f32::consts::FRAC_PI_2
+                    };
+                    phases[i + 1] = phase;
+                    phases[SEGMENT - i - 1] = -phase;
+                }
+                phases
+            } else {
+                (0..SEGMENT)
+                    .map(|k| previous_coded[k] + original[k] - previous_original[k])
+                    .collect()
+            };
+
+            let mut coded_spectrum: Vec<Complex<f32>> = spectrum
+                .iter()
+                .zip(&coded)
+                .map(|(c, &phase)| Complex::from_polar(c.norm(), phase))
+                .collect();
+            inverse.process(&mut coded_spectrum);
+            out.extend(coded_spectrum.iter().map(|c| c.re / SEGMENT as f32));
+            previous_original = original;
+            previous_coded = coded;
+        }
+        out
+    }
+
+    #[test]
+    fn test_detects_phase_coding() {
+        let coded = phase_code(&music(SEGMENT * 32), b"HI");
+
+        let analysis = PhaseAnalyzer::analyze(coded).unwrap();
+        assert_eq!(analysis.segment_length, SEGMENT);
+        assert!(analysis.quantized_run >= 16);
+        assert_eq!(analysis.quantized_first_bin, 1);
+        assert!(analysis.boundary_jump_ratio > PHASE_DISCONTINUITY_THRESHOLD);
+        assert_eq!(analysis.decoded_text().as_deref(), Some("HI"));
+        assert!(analysis.suspicious);
+    }
+
+    #[test]
+    fn test_clean_audio_is_not_flagged() {
+        let analysis = PhaseAnalyzer::analyze(music(SEGMENT * 32)).unwrap();
+        assert!(analysis.quantized_run < MIN_QUANTIZED_RUN);
+        assert!(analysis.boundary_jump_ratio < 1.5);
+        assert!(!analysis.suspicious);
+
+        // A bare tone starting at zero phase leaks into long runs at -pi/2
+        let tone: Vec<f32> = (0..SEGMENT * 32)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 17000.0 * i as f32 / 44100.0).sin())
+            .collect();
+        assert!(!PhaseAnalyzer::analyze(tone).unwrap().suspicious);
+
+        assert!(PhaseAnalyzer::analyze(vec![0.0; 1000]).is_err());
+    }
+}
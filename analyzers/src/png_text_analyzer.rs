@@ -0,0 +1,443 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Read;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Checks the tEXt, zTXt and iTXt chunks of a PNG against the keyword namespace. The PNG
+// specification predefines a handful of keywords and tools add a few well-known ones;
+// anything else, a keyword used twice, or a value that decodes to binary data is reported.
+// Viewers never show these chunks, so they can hold a payload of any size.
+pub struct PngTextAnalyzer;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Predefined keywords from the PNG specification (section 11.3.4.2)
+const STANDARD_KEYWORDS: [&str; 10] = [
+    "Title",
+    "Author",
+    "Description",
+    "Copyright",
+    "Creation Time",
+    "Software",
+    "Disclaimer",
+    "Warning",
+    "Source",
+    "Comment",
+];
+
+// Keywords written by common tools: XMP packets, ImageMagick profiles and timestamps, and
+// the generation parameters of image generators
+const WELL_KNOWN_KEYWORDS: [&str; 8] = [
+    "XML:com.adobe.xmp",
+    "date:create",
+    "date:modify",
+    "date:timestamp",
+    "parameters",
+    "prompt",
+    "workflow",
+    "Label",
+];
+
+// ImageMagick stores metadata profiles as hex under "Raw profile type <name>"
+const RAW_PROFILE_PREFIX: &str = "Raw profile type ";
+
+// Shortest value decoded as base64 or hex; shorter runs are too often ordinary words
+const MIN_ENCODED_VALUE: usize = 16;
+
+// Characters of a value kept for display
+const PREVIEW_CHARS: usize = 80;
+
+// Cap on the inflated size of a compressed value
+const MAX_INFLATED_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum PngTextError {
+    NotPng,
+}
+
+impl Display for PngTextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PngTextError::NotPng => write!(f, "Not a PNG file"),
+        }
+    }
+}
+
+impl std::error::Error for PngTextError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeywordKind {
+    Standard,
+    WellKnown,
+    Unknown,
+    // Not a valid keyword: empty, too long, or with non-printable characters or stray spaces
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct PngTextChunk {
+    pub offset: usize,
+    // tEXt, zTXt or iTXt
+    pub chunk_type: String,
+    pub keyword: String,
+    pub keyword_kind: KeywordKind,
+    // iTXt language tag
+    pub language: Option<String>,
+    pub compressed: bool,
+    // Length of the chunk data as stored
+    pub size: usize,
+    // Start of the text, control characters escaped
+    pub preview: String,
+    // "base64" or "hex" when the value was an encoding and `decoded` holds the decoded bytes
+    pub encoding: Option<String>,
+    pub decoded: Vec<u8>,
+    // MIME type of the decoded bytes, or "text"/"binary" when unrecognized
+    pub classification: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PngTextAnalysis {
+    pub chunks: Vec<PngTextChunk>,
+    pub duplicate_keywords: Vec<String>,
+    pub anomalies: Vec<String>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for PngTextAnalyzer {
+    type Input = Vec<u8>;
+    type Output = PngTextAnalysis;
+    type Error = PngTextError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if !input.starts_with(PNG_SIGNATURE) {
+            return Err(PngTextError::NotPng);
+        }
+
+        let mut chunks = Vec::new();
+        let mut anomalies = Vec::new();
+        let mut offset = PNG_SIGNATURE.len();
+        while offset + 8 <= input.len() {
+            let length = u32::from_be_bytes(input[offset..offset + 4].try_into().unwrap()) as usize;
+            let chunk_type = &input[offset + 4..offset + 8];
+            let Some(data) = input.get(offset + 8..offset + 8 + length) else {
+                if matches!(chunk_type, b"tEXt" | b"zTXt" | b"iTXt") {
+                    anomalies.push(format!(
+                        "{} chunk at offset {} runs past the end of the file",
+                        String::from_utf8_lossy(chunk_type),
+                        offset
+                    ));
+                }
+                break;
+            };
+            match chunk_type {
+                b"tEXt" | b"zTXt" | b"iTXt" => match text_chunk(offset, chunk_type, data) {
+                    Ok(chunk) => chunks.push(chunk),
+                    Err(problem) => anomalies.push(format!(
+                        "{} chunk at offset {}: {}",
+                        String::from_utf8_lossy(chunk_type),
+                        offset,
+                        problem
+                    )),
+                },
+                b"IEND" => break,
+                _ => {}
+            }
+            // Length, type, data and CRC
+            offset += 12 + length;
+        }
+
+        // iTXt may repeat a keyword once per language
+        let mut seen: HashMap<(&str, Option<&str>), usize> = HashMap::new();
+        for chunk in &chunks {
+            *seen
+                .entry((chunk.keyword.as_str(), chunk.language.as_deref()))
+                .or_default() += 1;
+        }
+        let mut duplicate_keywords: Vec<String> = seen
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|((keyword, _), _)| keyword.to_string())
+            .collect();
+        duplicate_keywords.sort();
+        duplicate_keywords.dedup();
+
+        for chunk in &chunks {
+            let keyword = chunk.keyword.escape_debug();
+            match chunk.keyword_kind {
+                KeywordKind::Invalid => anomalies.push(format!(
+                    "{} chunk at offset {} has an invalid keyword '{}'",
+                    chunk.chunk_type, chunk.offset, keyword
+                )),
+                KeywordKind::Unknown => anomalies.push(format!(
+                    "{} chunk at offset {} uses non-standard keyword '{}' ({} bytes, {})",
+                    chunk.chunk_type, chunk.offset, keyword, chunk.size, chunk.classification
+                )),
+                KeywordKind::Standard | KeywordKind::WellKnown => {}
+            }
+            // Hex is how ImageMagick stores its raw profiles, so only a stray encoding counts
+            let raw_profile = chunk.keyword.starts_with(RAW_PROFILE_PREFIX);
+            match chunk.encoding.as_deref() {
+                Some("hex") if raw_profile => {}
+                Some(encoding) => anomalies.push(format!(
+                    "'{}' at offset {} holds {} decoding to {} bytes ({})",
+                    keyword,
+                    chunk.offset,
+                    encoding,
+                    chunk.decoded.len(),
+                    chunk.classification
+                )),
+                None if chunk.classification != "text" => anomalies.push(format!(
+                    "'{}' at offset {} holds {} bytes of {} data",
+                    keyword,
+                    chunk.offset,
+                    chunk.decoded.len(),
+                    chunk.classification
+                )),
+                None => {}
+            }
+        }
+        for keyword in &duplicate_keywords {
+            anomalies.push(format!(
+                "Keyword '{}' appears in more than one text chunk",
+                keyword.escape_debug()
+            ));
+        }
+
+        let suspicious = !anomalies.is_empty();
+        Ok(PngTextAnalysis {
+            chunks,
+            duplicate_keywords,
+            anomalies,
+            suspicious,
+        })
+    }
+}
+
+fn text_chunk(offset: usize, chunk_type: &[u8], data: &[u8]) -> Result<PngTextChunk, String> {
+    let keyword_end = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or("keyword is not null-terminated")?;
+    let raw_keyword = &data[..keyword_end];
+    let rest = &data[keyword_end + 1..];
+
+    let (language, compressed, value) = match chunk_type {
+        b"tEXt" => (None, false, rest.to_vec()),
+        b"zTXt" => {
+            let (&method, compressed) = rest.split_first().ok_or("missing compression method")?;
+            if method != 0 {
+                return Err(format!("unknown compression method {}", method));
+            }
+            (None, true, inflate(compressed)?)
+        }
+        _ => {
+            // Compression flag and method, language tag, translated keyword, text
+            let [flag, method, ref tail @ ..] = *rest else {
+                return Err("truncated iTXt header".to_string());
+            };
+            let mut parts = tail.splitn(3, |&b| b == 0);
+            let language = parts.next().unwrap_or_default();
+            let _translated = parts.next().ok_or("missing translated keyword")?;
+            let text = parts.next().ok_or("missing translated keyword")?;
+            let value = match (flag, method) {
+                (0, _) => text.to_vec(),
+                (1, 0) => inflate(text)?,
+                _ => {
+                    return Err(format!(
+                        "unknown compression flag {} method {}",
+                        flag, method
+                    ));
+                }
+            };
+            let language = String::from_utf8_lossy(language).to_string();
+            (Some(language).filter(|l| !l.is_empty()), flag == 1, value)
+        }
+    };
+
+    // tEXt and zTXt are Latin-1; iTXt is UTF-8
+    let keyword: String = raw_keyword.iter().map(|&b| b as char).collect();
+    let text: String = if chunk_type == b"iTXt" {
+        String::from_utf8_lossy(&value).to_string()
+    } else {
+        value.iter().map(|&b| b as char).collect()
+    };
+    let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+
+    let (encoding, decoded) = match decode_value(&text) {
+        Some((encoding, decoded)) => (Some(encoding.to_string()), decoded),
+        None => (None, value),
+    };
+    Ok(PngTextChunk {
+        offset,
+        chunk_type: String::from_utf8_lossy(chunk_type).to_string(),
+        keyword_kind: keyword_kind(raw_keyword),
+        keyword,
+        language,
+        compressed,
+        size: data.len(),
+        preview: preview.escape_debug().to_string(),
+        encoding,
+        classification: classify_payload(&decoded),
+        decoded,
+    })
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    flate2::read::ZlibDecoder::new(data)
+        .take(MAX_INFLATED_BYTES)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("compressed text does not inflate: {}", e))?;
+    Ok(out)
+}
+
+fn keyword_kind(keyword: &[u8]) -> KeywordKind {
+    // 1-79 printable Latin-1 characters without leading, trailing or consecutive spaces
+    let printable = keyword
+        .iter()
+        .all(|&b| (0x20..=0x7E).contains(&b) || b >= 0xA1);
+    if keyword.is_empty()
+        || keyword.len() > 79
+        || !printable
+        || keyword.starts_with(b" ")
+        || keyword.ends_with(b" ")
+        || keyword.windows(2).any(|pair| pair == b"  ")
+    {
+        return KeywordKind::Invalid;
+    }
+    let keyword = String::from_utf8_lossy(keyword);
+    if STANDARD_KEYWORDS.contains(&keyword.as_ref()) {
+        KeywordKind::Standard
+    } else if WELL_KNOWN_KEYWORDS.contains(&keyword.as_ref())
+        || keyword.starts_with(RAW_PROFILE_PREFIX)
+    {
+        KeywordKind::WellKnown
+    } else {
+        KeywordKind::Unknown
+    }
+}
+
+// Base64 wrapped over lines, or hex text ignoring whitespace. ImageMagick raw profiles put
+// the profile name and length on the first lines, before the hex digits.
+fn decode_value(text: &str) -> Option<(&'static str, Vec<u8>)> {
+    use base64::Engine;
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+
+    let hex: String = text.split_whitespace().collect();
+    if let Some(decoded) = decode_hex(&hex) {
+        return Some(("hex", decoded));
+    }
+    let hex_body = text
+        .trim_start()
+        .splitn(3, '\n')
+        .nth(2)
+        .map(|body| body.split_whitespace().collect::<String>());
+    if let Some(decoded) = hex_body.and_then(|body| decode_hex(&body)) {
+        return Some(("hex", decoded));
+    }
+
+    // Spaces are kept so ordinary words run together never pass as base64
+    let lines: String = text.trim().lines().map(str::trim_end).collect();
+    if lines.len() < MIN_ENCODED_VALUE {
+        return None;
+    }
+    STANDARD
+        .decode(&lines)
+        .or_else(|_| STANDARD_NO_PAD.decode(lines.trim_end_matches('=')))
+        .ok()
+        .map(|decoded| ("base64", decoded))
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() < MIN_ENCODED_VALUE || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(chunk_type);
+        out.extend_from_slice(data);
+        // CRC is not checked
+        out.extend_from_slice(&[0; 4]);
+        out
+    }
+
+    fn png(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = PNG_SIGNATURE.to_vec();
+        out.extend(chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+        for c in chunks {
+            out.extend_from_slice(c);
+        }
+        out.extend(chunk(b"IEND", &[]));
+        out
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_standard_keywords_are_clean() {
+        let mut itxt = b"Description\0\x01\0en\0\0".to_vec();
+        itxt.extend(deflate("A photo of the harbour at dusk".as_bytes()));
+        let mut raw_profile = b"Raw profile type exif\0\nexif\n      12\n".to_vec();
+        raw_profile.extend_from_slice(b"457869660000000000000000\n");
+        let analysis = PngTextAnalyzer::analyze(png(&[
+            chunk(b"tEXt", b"Software\0GIMP 2.10"),
+            chunk(b"iTXt", &itxt),
+            chunk(b"tEXt", &raw_profile),
+        ]))
+        .unwrap();
+
+        assert_eq!(analysis.chunks.len(), 3);
+        assert!(analysis.chunks[1].compressed);
+        assert_eq!(analysis.chunks[1].language.as_deref(), Some("en"));
+        assert_eq!(analysis.chunks[2].encoding.as_deref(), Some("hex"));
+        assert_eq!(&analysis.chunks[2].decoded[..4], b"Exif");
+        assert!(!analysis.suspicious, "{:?}", analysis.anomalies);
+    }
+
+    #[test]
+    fn test_policy_violations() {
+        let zip = b"PK\x03\x04\x14\x00\x00\x00\x08\x00secret payload";
+        let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, zip);
+        let mut ztxt = b"Comment\0\0".to_vec();
+        ztxt.extend(deflate(encoded.as_bytes()));
+        let analysis = PngTextAnalyzer::analyze(png(&[
+            chunk(b"zTXt", &ztxt),
+            chunk(b"tEXt", b"Comment\0second comment"),
+            chunk(b"tEXt", b"stegkey\0hunter2"),
+            chunk(b"tEXt", b" bad  key\0x"),
+        ]))
+        .unwrap();
+
+        assert_eq!(analysis.chunks[0].encoding.as_deref(), Some("base64"));
+        assert_eq!(analysis.chunks[0].classification, "application/zip");
+        assert_eq!(analysis.chunks[2].keyword_kind, KeywordKind::Unknown);
+        assert_eq!(analysis.chunks[3].keyword_kind, KeywordKind::Invalid);
+        assert_eq!(analysis.duplicate_keywords, vec!["Comment".to_string()]);
+        assert_eq!(analysis.anomalies.len(), 4, "{:?}", analysis.anomalies);
+    }
+
+    #[test]
+    fn test_rejects_non_png() {
+        assert!(PngTextAnalyzer::analyze(b"GIF89a".to_vec()).is_err());
+    }
+}
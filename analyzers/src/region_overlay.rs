@@ -0,0 +1,110 @@
+use image::{Rgba, RgbaImage};
+
+// Renders where block-wise analyzers found something: the original image, dimmed, with each
+// flagged region heat-shaded by how strongly it was flagged and outlined so even faint ones
+// stand out.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlaggedRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // 0.0 to 1.0; stronger regions are shaded more opaquely
+    pub intensity: f64,
+}
+
+// How much of the original shows through outside flagged regions
+const BACKGROUND_BRIGHTNESS: f32 = 0.6;
+
+// Shading opacity for the faintest and the strongest regions
+const MIN_SHADE: f32 = 0.25;
+const MAX_SHADE: f32 = 0.7;
+
+const SHADE_COLOR: [f32; 3] = [255.0, 32.0, 0.0];
+const OUTLINE_COLOR: Rgba<u8> = Rgba([255, 255, 0, 255]);
+
+pub fn render_overlay(image: &RgbaImage, regions: &[FlaggedRegion]) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut overlay = RgbaImage::from_fn(width, height, |x, y| {
+        let pixel = image.get_pixel(x, y);
+        Rgba([
+            (pixel[0] as f32 * BACKGROUND_BRIGHTNESS) as u8,
+            (pixel[1] as f32 * BACKGROUND_BRIGHTNESS) as u8,
+            (pixel[2] as f32 * BACKGROUND_BRIGHTNESS) as u8,
+            255,
+        ])
+    });
+
+    for region in regions {
+        // Regions are clipped to the image, a block hanging off the edge still shows
+        let x1 = region.x.saturating_add(region.width).min(width);
+        let y1 = region.y.saturating_add(region.height).min(height);
+        if region.x >= x1 || region.y >= y1 {
+            continue;
+        }
+        let shade = MIN_SHADE + (MAX_SHADE - MIN_SHADE) * region.intensity.clamp(0.0, 1.0) as f32;
+
+        for y in region.y..y1 {
+            for x in region.x..x1 {
+                let original = image.get_pixel(x, y);
+                let pixel = overlay.get_pixel_mut(x, y);
+                for channel in 0..3 {
+                    pixel[channel] = (original[channel] as f32 * (1.0 - shade)
+                        + SHADE_COLOR[channel] * shade) as u8;
+                }
+            }
+        }
+        for x in region.x..x1 {
+            overlay.put_pixel(x, region.y, OUTLINE_COLOR);
+            overlay.put_pixel(x, y1 - 1, OUTLINE_COLOR);
+        }
+        for y in region.y..y1 {
+            overlay.put_pixel(region.x, y, OUTLINE_COLOR);
+            overlay.put_pixel(x1 - 1, y, OUTLINE_COLOR);
+        }
+    }
+
+    overlay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shades_and_outlines_flagged_regions() {
+        let image = RgbaImage::from_pixel(32, 32, Rgba([100, 100, 100, 255]));
+        let overlay = render_overlay(
+            &image,
+            &[
+                FlaggedRegion {
+                    x: 8,
+                    y: 8,
+                    width: 8,
+                    height: 8,
+                    intensity: 1.0,
+                },
+                // Partly outside the image
+                FlaggedRegion {
+                    x: 28,
+                    y: 28,
+                    width: 8,
+                    height: 8,
+                    intensity: 0.0,
+                },
+            ],
+        );
+
+        assert_eq!(overlay.dimensions(), (32, 32));
+        assert_eq!(*overlay.get_pixel(0, 0), Rgba([60, 60, 60, 255]));
+        assert_eq!(*overlay.get_pixel(8, 8), OUTLINE_COLOR);
+        assert_eq!(*overlay.get_pixel(15, 12), OUTLINE_COLOR);
+        assert_eq!(*overlay.get_pixel(31, 31), OUTLINE_COLOR);
+
+        let strong = overlay.get_pixel(12, 12);
+        let faint = overlay.get_pixel(30, 30);
+        assert!(strong[0] > faint[0] && faint[0] > 100);
+        assert!(strong[2] < faint[2] && faint[2] < 100);
+    }
+}
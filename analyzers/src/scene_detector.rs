@@ -0,0 +1,251 @@
+use crate::frame_sampler::{histogram_distance, luma_histogram};
+use crate::video_frame_analyzer::VideoFrameAnalysis;
+use image::RgbaImage;
+
+// Splits a video into scenes from the luma histograms of consecutive frames and keeps LSB
+// statistics per scene. Frames of one scene look alike, so their LSB statistics should agree
+// too; a frame that looks like the rest of its scene but whose LSBs behave differently is a
+// likely payload carrier. A per-scene budget also stops long static shots from soaking up
+// the analysis time of a long video.
+pub struct SceneDetector {
+    threshold: f64,
+    // 0 for no limit
+    max_frames_per_scene: usize,
+    previous: Option<Histogram>,
+    scenes: Vec<SceneBuilder>,
+}
+
+type Histogram = [f64; 32];
+
+// How far a frame's mean chi-square may sit from its scene's median, as a ratio either way,
+// before its LSBs count as out of line with what the picture looks like
+pub const LSB_DIVERGENCE_RATIO: f64 = 4.0;
+
+// Analyzed frames a scene needs before its frames can be compared with each other
+pub const MIN_FRAMES_PER_COMPARISON: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub start_frame: usize,
+    pub end_frame: usize,
+    pub frames_analyzed: usize,
+    // Sampled frames left out because the scene was over its budget
+    pub frames_skipped: usize,
+    pub median_chi_square: Option<f64>,
+    // Largest ratio between a visually matching frame's mean chi-square and the median
+    pub lsb_divergence: f64,
+    pub divergent_frames: Vec<usize>,
+    pub suspicious: bool,
+}
+
+struct SceneBuilder {
+    start_frame: usize,
+    end_frame: usize,
+    key_histogram: Histogram,
+    frames_skipped: usize,
+    // Frame index, mean chi-square and visual distance to the scene's first frame
+    frames: Vec<(usize, f64, f64)>,
+    // Admitted frames whose analysis hasn't been recorded yet, with their visual distance
+    pending: Vec<(usize, f64)>,
+    current_histogram: Histogram,
+}
+
+impl SceneDetector {
+    pub fn new(threshold: f64, max_frames_per_scene: usize) -> Self {
+        Self {
+            threshold,
+            max_frames_per_scene,
+            previous: None,
+            scenes: Vec::new(),
+        }
+    }
+
+    // Feed every decoded frame in order. Returns true when the frame starts a new scene.
+    pub fn observe(&mut self, index: usize, frame: &RgbaImage) -> bool {
+        let histogram = luma_histogram(frame);
+        let cut = self
+            .previous
+            .is_none_or(|previous| histogram_distance(&previous, &histogram) > self.threshold);
+        self.previous = Some(histogram);
+
+        if cut {
+            self.scenes.push(SceneBuilder {
+                start_frame: index,
+                end_frame: index,
+                key_histogram: histogram,
+                frames_skipped: 0,
+                frames: Vec::new(),
+                pending: Vec::new(),
+                current_histogram: histogram,
+            });
+        } else if let Some(scene) = self.scenes.last_mut() {
+            scene.end_frame = index;
+            scene.current_histogram = histogram;
+        }
+        cut
+    }
+
+    // Whether the scene of the last observed frame still has analysis budget. A frame that
+    // is refused counts as skipped; one that is admitted takes up budget straight away, so
+    // frames still being analyzed can't push a scene over it.
+    pub fn admit(&mut self) -> bool {
+        let Some(scene) = self.scenes.last_mut() else {
+            return true;
+        };
+        let taken = scene.frames.len() + scene.pending.len();
+        if self.max_frames_per_scene > 0 && taken >= self.max_frames_per_scene {
+            scene.frames_skipped += 1;
+            return false;
+        }
+        let visual_distance = histogram_distance(&scene.key_histogram, &scene.current_histogram);
+        scene.pending.push((scene.end_frame, visual_distance));
+        true
+    }
+
+    // Record the analysis of an admitted frame or of the last observed one. An admitted
+    // frame's analysis may arrive after later frames have been observed.
+    pub fn record(&mut self, analysis: &VideoFrameAnalysis) {
+        let index = analysis.frame_index;
+        // The frame belongs to the last scene starting at or before it
+        let Some(scene) = self
+            .scenes
+            .partition_point(|scene| scene.start_frame <= index)
+            .checked_sub(1)
+            .map(|at| &mut self.scenes[at])
+        else {
+            return;
+        };
+        let visual_distance = match scene.pending.iter().position(|&(frame, _)| frame == index) {
+            Some(at) => scene.pending.swap_remove(at).1,
+            None => histogram_distance(&scene.key_histogram, &scene.current_histogram),
+        };
+        let scores = &analysis.chi_square_scores;
+        let mean_chi_square = scores.iter().sum::<f64>() / scores.len().max(1) as f64;
+        scene
+            .frames
+            .push((analysis.frame_index, mean_chi_square, visual_distance));
+    }
+
+    pub fn finish(self) -> Vec<Scene> {
+        let threshold = self.threshold;
+        self.scenes
+            .into_iter()
+            .map(|scene| scene.build(threshold))
+            .collect()
+    }
+}
+
+impl SceneBuilder {
+    fn build(self, threshold: f64) -> Scene {
+        let mut scores: Vec<f64> = self.frames.iter().map(|&(_, chi, _)| chi).collect();
+        scores.sort_by(f64::total_cmp);
+        let median = (!scores.is_empty()).then(|| {
+            let middle = scores.len() / 2;
+            if scores.len().is_multiple_of(2) {
+                (scores[middle - 1] + scores[middle]) / 2.0
+            } else {
+                scores[middle]
+            }
+        });
+
+        let mut lsb_divergence = 1.0f64;
+        let mut divergent_frames = Vec::new();
+        if let Some(median) = median.filter(|_| self.frames.len() >= MIN_FRAMES_PER_COMPARISON) {
+            // Visually matching frames only: drift within a long scene can change the picture
+            // enough that the LSBs are expected to change with it
+            for &(index, chi_square, distance) in &self.frames {
+                if distance > threshold {
+                    continue;
+                }
+                let ratio = (chi_square + 1.0) / (median + 1.0);
+                let divergence = ratio.max(1.0 / ratio);
+                lsb_divergence = lsb_divergence.max(divergence);
+                if divergence > LSB_DIVERGENCE_RATIO {
+                    divergent_frames.push(index);
+                }
+            }
+        }
+
+        Scene {
+            start_frame: self.start_frame,
+            end_frame: self.end_frame,
+            frames_analyzed: self.frames.len(),
+            frames_skipped: self.frames_skipped,
+            median_chi_square: median,
+            lsb_divergence,
+            suspicious: !divergent_frames.is_empty(),
+            divergent_frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Analyzer;
+    use crate::frame_sampler::SCENE_CHANGE_THRESHOLD;
+    use crate::video_frame_analyzer::VideoFrameAnalyzer;
+    use image::{DynamicImage, Rgba};
+
+    // A smooth gradient, with its LSBs replaced by pseudo-random bits when `payload` is set
+    fn frame(base: u8, payload: bool) -> RgbaImage {
+        let mut state = 0x2545_F491u32;
+        RgbaImage::from_fn(64, 64, |x, y| {
+            let value = base.saturating_add(((x + y) / 8) as u8);
+            let pixel = if payload {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let bits = state.to_le_bytes();
+                [
+                    value & !1 | bits[0] & 1,
+                    value & !1 | bits[1] & 1,
+                    value & !1 | bits[2] & 1,
+                ]
+            } else {
+                [value, value, value]
+            };
+            Rgba([pixel[0], pixel[1], pixel[2], 255])
+        })
+    }
+
+    #[test]
+    fn test_flags_divergent_frame_and_bounds_scenes() {
+        // Scene one: 6 frames, the fourth carrying random LSBs. Scene two: 6 clean frames
+        // under a budget of 4.
+        let frames: Vec<RgbaImage> = (0..12)
+            .map(|i| {
+                if i < 6 {
+                    frame(40, i == 3)
+                } else {
+                    frame(180, false)
+                }
+            })
+            .collect();
+
+        let mut detector = SceneDetector::new(SCENE_CHANGE_THRESHOLD, 0);
+        let mut bounded = SceneDetector::new(SCENE_CHANGE_THRESHOLD, 4);
+        for (index, image) in frames.iter().enumerate() {
+            let mut analysis =
+                VideoFrameAnalyzer::analyze(DynamicImage::ImageRgba8(image.clone())).unwrap();
+            analysis.frame_index = index;
+            detector.observe(index, image);
+            detector.record(&analysis);
+            bounded.observe(index, image);
+            if bounded.admit() {
+                bounded.record(&analysis);
+            }
+        }
+
+        let scenes = detector.finish();
+        assert_eq!(scenes.len(), 2);
+        assert_eq!((scenes[0].start_frame, scenes[0].end_frame), (0, 5));
+        assert_eq!(scenes[0].divergent_frames, vec![3]);
+        assert!(scenes[0].suspicious);
+        assert!(!scenes[1].suspicious);
+
+        let bounded = bounded.finish();
+        assert_eq!(bounded[1].frames_analyzed, 4);
+        assert_eq!(bounded[1].frames_skipped, 2);
+    }
+}
@@ -0,0 +1,54 @@
+use binwalk::Binwalk;
+use infer::Infer;
+use rustfft::{Fft, FftPlanner};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Process-wide helpers that are expensive to build, created on first use and shared from then
+// on. A Binwalk instance compiles all of its signatures when constructed and an FFT plan
+// precomputes its twiddle factors, so a batch scan that built them for every file paid that
+// cost again on each one.
+
+pub fn binwalk() -> &'static Binwalk {
+    static BINWALK: OnceLock<Binwalk> = OnceLock::new();
+    BINWALK.get_or_init(Binwalk::new)
+}
+
+pub fn infer() -> &'static Infer {
+    static INFER: OnceLock<Infer> = OnceLock::new();
+    INFER.get_or_init(Infer::new)
+}
+
+// The planner keeps every plan it has made, so asking again for a length returns the same plan
+fn planner() -> &'static Mutex<FftPlanner<f32>> {
+    static PLANNER: OnceLock<Mutex<FftPlanner<f32>>> = OnceLock::new();
+    PLANNER.get_or_init(|| Mutex::new(FftPlanner::new()))
+}
+
+pub fn fft_forward(len: usize) -> Arc<dyn Fft<f32>> {
+    // A panic while planning leaves nothing half-built, so a poisoned planner is still usable
+    let mut planner = planner().lock().unwrap_or_else(|e| e.into_inner());
+    planner.plan_fft_forward(len)
+}
+
+pub fn fft_inverse(len: usize) -> Arc<dyn Fft<f32>> {
+    let mut planner = planner().lock().unwrap_or_else(|e| e.into_inner());
+    planner.plan_fft_inverse(len)
+}
+
+// Lowercase hex, the form every digest in a report, manifest or cache index is written in
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instances_are_reused() {
+        assert!(std::ptr::eq(binwalk(), binwalk()));
+        assert!(std::ptr::eq(infer(), infer()));
+        assert!(Arc::ptr_eq(&fft_forward(1024), &fft_forward(1024)));
+        assert!(!Arc::ptr_eq(&fft_forward(1024), &fft_inverse(1024)));
+    }
+}
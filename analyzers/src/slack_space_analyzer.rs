@@ -0,0 +1,728 @@
+use crate::Analyzer;
+use crate::trailing_tag_analyzer::locate_trailing_tags;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Cross-format "slack space" detector. Walks the length-prefixed structure of common
+// containers (PNG chunks, RIFF chunks, ISO-BMFF boxes, JPEG segments, MPEG audio frames,
+// FLAC metadata blocks and frames) and reports the bytes that no declared structure
+// accounts for, such as data appended after the end marker or gaps a parser would
+// silently skip.
+pub struct SlackSpaceAnalyzer;
+
+// Regions shorter than this are treated as alignment noise
+pub const MIN_SLACK_BYTES: usize = 16;
+
+// How far past an ID3v2 tag the first MPEG frame may start
+const MP3_SYNC_SEARCH: usize = 4096;
+
+#[derive(Debug)]
+pub enum SlackSpaceError {
+    UnknownContainer,
+}
+
+impl Display for SlackSpaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlackSpaceError::UnknownContainer => {
+                write!(f, "No length-prefixed container structure recognized")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlackSpaceError {}
+
+#[derive(Debug, Clone)]
+pub struct SlackRegion {
+    pub offset: usize,
+    pub length: usize,
+    // Shannon entropy in bits per byte (0-8)
+    pub entropy: f64,
+    pub all_zero: bool,
+    pub description: String,
+    // MIME type of a file signature at the start of the region
+    pub detected_type: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlackSpaceAnalysis {
+    pub container: String,
+    pub file_size: usize,
+    // Bytes covered by the declared structure
+    pub accounted_bytes: usize,
+    pub regions: Vec<SlackRegion>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for SlackSpaceAnalyzer {
+    type Input = Vec<u8>;
+    type Output = SlackSpaceAnalysis;
+    type Error = SlackSpaceError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        Self::analyze_bytes(&input)
+    }
+}
+
+impl SlackSpaceAnalyzer {
+    // For callers that still need the bytes afterwards, e.g. to carve the regions found
+    pub fn analyze_bytes(input: &[u8]) -> Result<SlackSpaceAnalysis, SlackSpaceError> {
+        let (container, raw_regions) = if input.starts_with(b"\x89PNG\r\n\x1a\n") {
+            ("PNG", walk_png(input))
+        } else if input.starts_with(b"RIFF") && input.len() >= 12 {
+            ("RIFF", walk_riff(input))
+        } else if input.get(4..8) == Some(b"ftyp") {
+            ("ISO-BMFF", walk_bmff(input))
+        } else if input.starts_with(&[0xFF, 0xD8]) {
+            ("JPEG", walk_jpeg(input))
+        } else {
+            // FLAC and MP3 may both start with an ID3v2 tag
+            let body = id3v2_end(input);
+            if input[body..].starts_with(b"fLaC") {
+                ("FLAC", walk_flac(input, body + 4))
+            } else if let Some(regions) = walk_mp3(input, body) {
+                ("MP3", regions)
+            } else {
+                return Err(SlackSpaceError::UnknownContainer);
+            }
+        };
+
+        let regions: Vec<SlackRegion> = raw_regions
+            .into_iter()
+            .filter(|(start, end, _)| end > start)
+            .map(|(start, end, description)| {
+                let bytes = &input[start..end];
+                SlackRegion {
+                    offset: start,
+                    length: end - start,
+                    entropy: shannon_entropy(bytes),
+                    all_zero: bytes.iter().all(|&b| b == 0),
+                    description,
+                    detected_type: infer::get(bytes).map(|kind| kind.mime_type().to_string()),
+                }
+            })
+            .collect();
+
+        let slack: usize = regions.iter().map(|r| r.length).sum();
+        // Zero-filled regions are usually padding; anything else big enough is worth a look
+        let suspicious = regions
+            .iter()
+            .any(|r| r.length >= MIN_SLACK_BYTES && !r.all_zero);
+
+        Ok(SlackSpaceAnalysis {
+            container: container.to_string(),
+            file_size: input.len(),
+            accounted_bytes: input.len() - slack,
+            regions,
+            suspicious,
+        })
+    }
+}
+
+// Each walker returns (start, end, description) for the bytes it could not account for
+
+fn walk_png(data: &[u8]) -> Vec<(usize, usize, String)> {
+    let mut offset = 8;
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let chunk_type = &data[offset + 4..offset + 8];
+        let end = offset + 12 + length;
+        if end > data.len() {
+            return vec![(
+                offset,
+                data.len(),
+                format!(
+                    "Truncated {} chunk declares {} bytes",
+                    String::from_utf8_lossy(chunk_type),
+                    length
+                ),
+            )];
+        }
+        offset = end;
+        if chunk_type == b"IEND" {
+            return vec![(offset, data.len(), "Data after IEND".to_string())];
+        }
+    }
+    vec![(
+        offset,
+        data.len(),
+        "Data after last complete chunk".to_string(),
+    )]
+}
+
+fn walk_riff(data: &[u8]) -> Vec<(usize, usize, String)> {
+    let declared = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    let riff_end = (8 + declared).min(data.len());
+    let is_wave = &data[8..12] == b"WAVE";
+    let mut after_data = false;
+    let mut regions = Vec::new();
+
+    let mut offset = 12;
+    while offset + 8 <= riff_end {
+        // Chunk IDs are printable ASCII; anything else is data posing as a chunk header
+        let id = &data[offset..offset + 4];
+        if !id.iter().all(|b| (0x20..0x7F).contains(b)) {
+            break;
+        }
+        let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let next = offset + 8 + size + (size & 1);
+        if next > riff_end {
+            break;
+        }
+        after_data |= id == b"data";
+        offset = next;
+    }
+    if offset < riff_end {
+        let description = if is_wave && after_data {
+            "Bytes after WAV data chunk that don't form a chunk"
+        } else {
+            "Bytes inside RIFF that don't form a chunk"
+        };
+        regions.push((offset, riff_end, description.to_string()));
+    }
+    regions.push((
+        riff_end,
+        data.len(),
+        "Data after RIFF container".to_string(),
+    ));
+    regions
+}
+
+fn walk_bmff(data: &[u8]) -> Vec<(usize, usize, String)> {
+    let mut regions = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as u64;
+        let box_type = String::from_utf8_lossy(&data[offset + 4..offset + 8]).to_string();
+        let (header, size) = match size {
+            // Box extends to the end of the file
+            0 => (8, (data.len() - offset) as u64),
+            1 if offset + 16 <= data.len() => (
+                16,
+                u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap()),
+            ),
+            _ => (8, size),
+        };
+        if size < header as u64 || offset as u64 + size > data.len() as u64 {
+            break;
+        }
+        let end = offset + size as usize;
+        // free/skip boxes are declared filler, so their contents are slack too
+        if box_type == "free" || box_type == "skip" {
+            regions.push((
+                offset + header,
+                end,
+                format!("Contents of '{}' box", box_type),
+            ));
+        }
+        offset = end;
+    }
+    regions.push((
+        offset,
+        data.len(),
+        "Data after last complete box".to_string(),
+    ));
+    regions
+}
+
+fn walk_jpeg(data: &[u8]) -> Vec<(usize, usize, String)> {
+    let mut offset = 2;
+    while offset + 4 <= data.len() {
+        if data[offset] != 0xFF {
+            break;
+        }
+        let marker = data[offset + 1];
+        match marker {
+            // Fill bytes before a marker
+            0xFF => offset += 1,
+            0xD9 => {
+                return vec![(offset + 2, data.len(), "Data after EOI".to_string())];
+            }
+            0xD0..=0xD7 | 0x01 => offset += 2,
+            _ => {
+                let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+                offset += 2 + length;
+                if marker == 0xDA {
+                    offset = skip_entropy_coded(data, offset);
+                }
+            }
+        }
+    }
+    vec![(
+        offset.min(data.len()),
+        data.len(),
+        "Data after last segment".to_string(),
+    )]
+}
+
+// Entropy-coded scan data runs until the next marker other than a stuffed 0xFF00 or a
+// restart marker
+fn skip_entropy_coded(data: &[u8], mut offset: usize) -> usize {
+    while offset + 1 < data.len() {
+        if data[offset] == 0xFF && !matches!(data[offset + 1], 0x00 | 0xD0..=0xD7) {
+            return offset;
+        }
+        offset += 1;
+    }
+    data.len()
+}
+
+// End of a leading ID3v2 tag, 0 when there is none
+fn id3v2_end(data: &[u8]) -> usize {
+    if data.len() < 10 || !data.starts_with(b"ID3") {
+        return 0;
+    }
+    // Synchsafe size: 7 bits per byte, excluding the header and the optional footer
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |acc, &b| (acc << 7) | (b & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    (10 + size + footer).min(data.len())
+}
+
+// Start of the ID3v1, APEv2 and Lyrics3 tags that players expect after the last audio frame,
+// searching back from the end of the file but never before `from`
+fn trailing_tags_start(data: &[u8], from: usize) -> usize {
+    locate_trailing_tags(data, from).start
+}
+
+// Length of the MPEG audio frame whose header starts `h`, None when it isn't a valid
+// header. Free-format bitrates are treated as invalid since their length isn't declared.
+pub(crate) fn mpeg_frame_len(h: &[u8]) -> Option<usize> {
+    if h.len() < 4 || h[0] != 0xFF || h[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    // Version: 0 = MPEG 2.5, 2 = MPEG 2, 3 = MPEG 1. Layer: 1 = III, 2 = II, 3 = I.
+    let version = (h[1] >> 3) & 3;
+    let layer = (h[1] >> 1) & 3;
+    let bitrate_index = (h[2] >> 4) as usize;
+    let rate_index = ((h[2] >> 2) & 3) as usize;
+    let padding = ((h[2] >> 1) & 1) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+
+    const BITRATES: [[usize; 14]; 5] = [
+        [
+            32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+        [
+            32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ];
+    let mpeg1 = version == 3;
+    let table = match (mpeg1, layer) {
+        (true, 3) => 0,
+        (true, 2) => 1,
+        (true, _) => 2,
+        (false, 3) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = BITRATES[table][bitrate_index - 1] * 1000;
+    let sample_rate = [44100, 48000, 32000][rate_index] >> (3 - version.max(1)) as usize;
+
+    Some(match layer {
+        3 => (12 * bitrate / sample_rate + padding) * 4,
+        1 if !mpeg1 => 72 * bitrate / sample_rate + padding,
+        _ => 144 * bitrate / sample_rate + padding,
+    })
+}
+
+// Version, layer and sample rate bits, which stay the same for every frame of a stream
+fn mpeg_stream_bits(h: &[u8]) -> (u8, u8) {
+    (h[1] & 0xFE, h[2] & 0x0C)
+}
+
+// None when no run of MPEG frames starts near `start`, so the file isn't MP3
+fn walk_mp3(data: &[u8], start: usize) -> Option<Vec<(usize, usize, String)>> {
+    // The first frame must be followed by a second one (or the end of the file) so a
+    // stray sync pattern isn't mistaken for the stream. Without an ID3v2 tag the file has
+    // to open with a frame.
+    let search_end = if start == 0 {
+        1.min(data.len())
+    } else {
+        (start + MP3_SYNC_SEARCH).min(data.len())
+    };
+    let first = (start..search_end).find(|&i| {
+        mpeg_frame_len(&data[i..]).is_some_and(|len| {
+            i + len == data.len()
+                || mpeg_frame_len(&data[(i + len).min(data.len())..]).is_some_and(|_| {
+                    mpeg_stream_bits(&data[i..]) == mpeg_stream_bits(&data[i + len..])
+                })
+        })
+    })?;
+
+    let mut regions = vec![(
+        start,
+        first,
+        "Bytes between ID3v2 tag and first MPEG frame".to_string(),
+    )];
+    let stream = mpeg_stream_bits(&data[first..]);
+    let mut offset = first;
+    while let Some(len) = mpeg_frame_len(&data[offset..]) {
+        if offset + len > data.len() || mpeg_stream_bits(&data[offset..]) != stream {
+            break;
+        }
+        offset += len;
+    }
+    regions.push((
+        offset,
+        trailing_tags_start(data, offset),
+        "Data after last MPEG frame".to_string(),
+    ));
+    Some(regions)
+}
+
+struct FlacFrameHeader {
+    variable_block_size: bool,
+    // Frame number for fixed block sizes, first sample number for variable ones
+    number: u64,
+    block_size: u64,
+    header_len: usize,
+}
+
+fn parse_flac_frame_header(h: &[u8]) -> Option<FlacFrameHeader> {
+    if h.len() < 6 || h[0] != 0xFF || h[1] & 0xFE != 0xF8 {
+        return None;
+    }
+    let block_code = h[2] >> 4;
+    let rate_code = h[2] & 0x0F;
+    let channels = h[3] >> 4;
+    let sample_size = (h[3] >> 1) & 7;
+    if block_code == 0 || rate_code == 15 || channels > 10 || sample_size == 3 || h[3] & 1 != 0 {
+        return None;
+    }
+
+    // UTF-8 style coded number
+    let extra = match h[4].leading_ones() {
+        0 => 0,
+        n @ 2..=7 => n as usize - 1,
+        _ => return None,
+    };
+    let mut number = if extra == 0 {
+        h[4] as u64
+    } else {
+        (h[4] & (0x3F >> extra)) as u64
+    };
+    let mut i = 5;
+    for _ in 0..extra {
+        let b = *h.get(i)?;
+        if b & 0xC0 != 0x80 {
+            return None;
+        }
+        number = (number << 6) | (b & 0x3F) as u64;
+        i += 1;
+    }
+
+    let block_size = match block_code {
+        1 => 192,
+        2..=5 => 576 << (block_code - 2),
+        6 => {
+            i += 1;
+            *h.get(i - 1)? as u64 + 1
+        }
+        7 => {
+            i += 2;
+            u16::from_be_bytes([*h.get(i - 2)?, *h.get(i - 1)?]) as u64 + 1
+        }
+        _ => 256 << (block_code - 8),
+    };
+    i += match rate_code {
+        12 => 1,
+        13 | 14 => 2,
+        _ => 0,
+    };
+
+    if *h.get(i)? != crc8(&h[..i]) {
+        return None;
+    }
+    Some(FlacFrameHeader {
+        variable_block_size: h[1] & 1 == 1,
+        number,
+        block_size,
+        header_len: i + 1,
+    })
+}
+
+// CRC-8 with polynomial 0x07, as used by FLAC frame headers
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+// CRC-16 with polynomial 0x8005, as used by whole FLAC frames
+fn crc16_update(mut crc: u16, byte: u8) -> u16 {
+    crc ^= (byte as u16) << 8;
+    for _ in 0..8 {
+        crc = if crc & 0x8000 != 0 {
+            (crc << 1) ^ 0x8005
+        } else {
+            crc << 1
+        };
+    }
+    crc
+}
+
+// FLAC frames don't declare their length. A frame ends where the CRC-16 over its bytes
+// (including the CRC itself) comes to zero and the next frame header continues the
+// numbering; the last frame ends at the first zero once STREAMINFO's sample count is
+// reached.
+fn walk_flac(data: &[u8], start: usize) -> Vec<(usize, usize, String)> {
+    let mut regions = Vec::new();
+    let (mut min_frame, mut max_frame, mut total_samples) = (0, 0, 0u64);
+
+    let mut offset = start;
+    loop {
+        if offset + 4 > data.len() {
+            return vec![(offset, data.len(), "Truncated FLAC metadata".to_string())];
+        }
+        let header = data[offset];
+        let length =
+            u32::from_be_bytes([0, data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+        let body = offset + 4;
+        if body + length > data.len() {
+            return vec![(
+                offset,
+                data.len(),
+                format!("Truncated FLAC metadata block declares {} bytes", length),
+            )];
+        }
+        match header & 0x7F {
+            0 if length >= 18 => {
+                let field = |from: usize| {
+                    u32::from_be_bytes([0, data[from], data[from + 1], data[from + 2]]) as usize
+                };
+                min_frame = field(body + 4);
+                max_frame = field(body + 7);
+                total_samples = ((data[body + 13] & 0x0F) as u64) << 32
+                    | u32::from_be_bytes(data[body + 14..body + 18].try_into().unwrap()) as u64;
+            }
+            // PADDING is declared filler, so its contents are slack too
+            1 => regions.push((body, body + length, "Contents of PADDING block".to_string())),
+            _ => {}
+        }
+        offset = body + length;
+        if header & 0x80 != 0 {
+            break;
+        }
+    }
+
+    let continues = |h: &FlacFrameHeader, variable: bool, expected: u64| {
+        h.variable_block_size == variable && h.number == expected
+    };
+    let mut variable = None;
+    let (mut frames, mut samples) = (0u64, 0u64);
+    while let Some(frame) = parse_flac_frame_header(&data[offset..]) {
+        let variable = *variable.get_or_insert(frame.variable_block_size);
+        let expected = if variable { samples } else { frames };
+        if !continues(&frame, variable, expected) {
+            break;
+        }
+        frames += 1;
+        samples += frame.block_size;
+        let expected = if variable { samples } else { frames };
+        let last = total_samples > 0 && samples >= total_samples;
+
+        let search_end = if max_frame > 0 {
+            (offset + max_frame).min(data.len())
+        } else {
+            data.len()
+        };
+        let min_end = offset + min_frame.max(frame.header_len + 2);
+        let mut crc = 0u16;
+        let mut first_zero = None;
+        let mut next = None;
+        for end in offset + 1..=search_end {
+            crc = crc16_update(crc, data[end - 1]);
+            if crc != 0 || end < min_end {
+                continue;
+            }
+            first_zero.get_or_insert(end);
+            if last {
+                break;
+            }
+            if parse_flac_frame_header(&data[end..])
+                .is_some_and(|h| continues(&h, variable, expected))
+            {
+                next = Some(end);
+                break;
+            }
+        }
+
+        match (next, first_zero) {
+            (Some(end), _) => offset = end,
+            // Last frame, or the stream breaks off after this one
+            (None, Some(end)) => {
+                offset = end;
+                break;
+            }
+            (None, None) => break,
+        }
+    }
+
+    regions.push((
+        offset,
+        trailing_tags_start(data, offset),
+        "Data after FLAC stream end".to_string(),
+    ));
+    regions
+}
+
+fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+    let total = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(chunk_type);
+        out.extend_from_slice(data);
+        out.extend_from_slice(&[0; 4]);
+        out
+    }
+
+    #[test]
+    fn test_png_trailing_slack() {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend(png_chunk(b"IHDR", &[0; 13]));
+        png.extend(png_chunk(b"IEND", &[]));
+        let clean_len = png.len();
+
+        let clean = SlackSpaceAnalyzer::analyze(png.clone()).unwrap();
+        assert!(clean.regions.is_empty());
+        assert!(!clean.suspicious);
+
+        png.extend((0..64u8).map(|i| i.wrapping_mul(37)));
+        let dirty = SlackSpaceAnalyzer::analyze(png).unwrap();
+        assert!(dirty.suspicious);
+        assert_eq!(dirty.regions[0].offset, clean_len);
+        assert_eq!(dirty.regions[0].length, 64);
+        assert!(dirty.regions[0].entropy > 5.0);
+    }
+
+    #[test]
+    fn test_jpeg_after_eoi() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        // SOS header, scan data with a stuffed 0xFF00, then EOI
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD9]);
+        jpeg.extend_from_slice(b"hidden payload appended after EOI");
+
+        let analysis = SlackSpaceAnalyzer::analyze(jpeg).unwrap();
+        assert_eq!(analysis.container, "JPEG");
+        assert_eq!(analysis.regions.len(), 1);
+        assert_eq!(analysis.regions[0].offset, 18);
+        assert!(analysis.suspicious);
+    }
+
+    #[test]
+    fn test_mp3_data_after_last_frame() {
+        // ID3v2 tag with 20 bytes of frames
+        let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec();
+        mp3.extend_from_slice(&[0; 20]);
+        // MPEG-1 Layer III, 128 kbps, 44.1 kHz: 417-byte frames
+        for _ in 0..3 {
+            let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+            frame.resize(417, 0x55);
+            mp3.extend(frame);
+        }
+        let stream_end = mp3.len();
+        mp3.extend_from_slice(b"PK\x03\x04hidden archive appended after the audio");
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        mp3.extend(id3v1);
+
+        let analysis = SlackSpaceAnalyzer::analyze(mp3).unwrap();
+        assert_eq!(analysis.container, "MP3");
+        assert_eq!(analysis.regions.len(), 1);
+        let region = &analysis.regions[0];
+        assert_eq!(region.offset, stream_end);
+        assert_eq!(region.length, 43);
+        assert_eq!(region.description, "Data after last MPEG frame");
+        assert_eq!(region.detected_type.as_deref(), Some("application/zip"));
+        assert!(analysis.suspicious);
+    }
+
+    fn flac_frame(number: u8) -> Vec<u8> {
+        // 4096-sample block, 44.1 kHz, mono, 16-bit
+        let mut frame = vec![0xFF, 0xF8, 0xC9, 0x08, number];
+        frame.push(crc8(&frame));
+        frame.extend((0..100u8).map(|i| i.wrapping_mul(7).wrapping_add(number)));
+        let crc = frame.iter().fold(0, |crc, &b| crc16_update(crc, b));
+        frame.extend_from_slice(&crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_flac_data_after_stream_end() {
+        let mut streaminfo = vec![0u8; 34];
+        streaminfo[16] = 0x20; // 8192 total samples
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        flac.extend(streaminfo);
+        flac.extend(flac_frame(0));
+        flac.extend(flac_frame(1));
+        let stream_end = flac.len();
+
+        let clean = SlackSpaceAnalyzer::analyze(flac.clone()).unwrap();
+        assert_eq!(clean.container, "FLAC");
+        assert!(clean.regions.is_empty());
+
+        flac.extend((0..64u8).map(|i| i.wrapping_mul(91)));
+        let dirty = SlackSpaceAnalyzer::analyze(flac).unwrap();
+        assert_eq!(dirty.regions[0].offset, stream_end);
+        assert_eq!(dirty.regions[0].length, 64);
+        assert!(dirty.suspicious);
+    }
+
+    #[test]
+    fn test_wav_bytes_after_data_chunk() {
+        let mut wav = b"RIFF\x00\x00\x00\x00WAVEdata\x04\x00\x00\x00".to_vec();
+        wav.extend_from_slice(&[1, 2, 3, 4]);
+        wav.extend((0..32u8).map(|i| 0x80 | i));
+        let riff_size = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let analysis = SlackSpaceAnalyzer::analyze(wav).unwrap();
+        assert_eq!(analysis.regions[0].offset, 24);
+        assert_eq!(
+            analysis.regions[0].description,
+            "Bytes after WAV data chunk that don't form a chunk"
+        );
+    }
+}
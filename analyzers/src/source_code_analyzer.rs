@@ -0,0 +1,440 @@
+use crate::Analyzer;
+use crate::structured_text_analyzer::{comment_payload, describe};
+use std::fmt::Display;
+
+// Comments of source files, read with each language's comment and string syntax so markers
+// inside string literals don't count. Runs of line comments are read as one comment, as a blob
+// dropped into code is usually wrapped over many of them. Comments that decode as base64, hex
+// or zero-width characters are reported, as is a file that is mostly comment.
+pub struct SourceCodeAnalyzer;
+
+// Share of a file's non-blank characters in comments above which it is mostly comment
+pub const COMMENT_RATIO_THRESHOLD: f64 = 0.8;
+
+// Comment characters a file needs before its ratio counts; a short file is easily all licence
+// header
+pub const MIN_RATIO_COMMENT_CHARS: usize = 4096;
+
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+    // Characters that open and close a string literal
+    quotes: &'static [char],
+}
+
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+const C_QUOTES: &[char] = &['"', '\''];
+const SCRIPT_QUOTES: &[char] = &['"', '\'', '`'];
+
+const LANGUAGES: [Language; 21] = [
+    Language {
+        name: "C/C++",
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hpp", "hh", "m", "mm"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Java",
+        extensions: &["java", "kt", "kts", "scala", "groovy", "gradle", "dart"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: &['"'],
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: SCRIPT_QUOTES,
+    },
+    // Lifetimes would read as unterminated character literals
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: &['"'],
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx", "mts", "cts"],
+        line: &["//"],
+        block: C_BLOCK,
+        quotes: SCRIPT_QUOTES,
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php", "phtml"],
+        line: &["//", "#"],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        line: &[],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        line: &["#"],
+        block: &[],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        line: &["#"],
+        block: &[("=begin", "=end")],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "ksh", "fish"],
+        line: &["#"],
+        block: &[],
+        quotes: SCRIPT_QUOTES,
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        line: &["#"],
+        block: &[],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "PowerShell",
+        extensions: &["ps1", "psm1", "psd1"],
+        line: &["#"],
+        block: &[("<#", "#>")],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "R",
+        extensions: &["r"],
+        line: &["#"],
+        block: &[],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line: &["--"],
+        block: C_BLOCK,
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        line: &["--"],
+        block: &[("--[[", "]]")],
+        quotes: C_QUOTES,
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        line: &["--"],
+        block: &[("{-", "-}")],
+        quotes: &['"'],
+    },
+    Language {
+        name: "Visual Basic",
+        extensions: &["vb", "vbs", "bas", "cls", "frm"],
+        line: &["'", "REM ", "Rem ", "rem "],
+        block: &[],
+        quotes: &['"'],
+    },
+    // Apostrophes in page text aren't quotes
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xhtml", "vue", "svelte"],
+        line: &[],
+        block: &[("<!--", "-->")],
+        quotes: &[],
+    },
+    Language {
+        name: "Assembly",
+        extensions: &["asm", "s", "nasm"],
+        line: &[";"],
+        block: &[],
+        quotes: &['"'],
+    },
+];
+
+// The language a file extension belongs to
+pub fn language_for(extension: &str) -> Option<&'static Language> {
+    let extension = extension.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+}
+
+#[derive(Debug)]
+pub enum SourceCodeError {
+    Empty,
+}
+
+impl Display for SourceCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceCodeError::Empty => write!(f, "No source code to read"),
+        }
+    }
+}
+
+impl std::error::Error for SourceCodeError {}
+
+#[derive(Debug, Clone)]
+pub struct CommentPayload {
+    // Lines the comment spans
+    pub line: usize,
+    pub end_line: usize,
+    // Characters of the comment
+    pub length: usize,
+    pub data: Vec<u8>,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    // The start of the decoded text, or its size and type
+    pub preview: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceCodeAnalysis {
+    pub language: &'static str,
+    // Comments after runs of line comments are joined
+    pub comments: usize,
+    // Non-blank characters in comments and in everything else
+    pub comment_chars: usize,
+    pub code_chars: usize,
+    pub comment_ratio: f64,
+    pub payloads: Vec<CommentPayload>,
+    // The file is mostly comment
+    pub abnormal_ratio: bool,
+    pub suspicious: bool,
+}
+
+impl Analyzer for SourceCodeAnalyzer {
+    type Input = (String, &'static Language);
+    type Output = SourceCodeAnalysis;
+    type Error = SourceCodeError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (text, language) = input;
+        if text.trim().is_empty() {
+            return Err(SourceCodeError::Empty);
+        }
+        let (comments, code_chars) = extract_comments(&text, language);
+        let comment_chars = comments
+            .iter()
+            .map(|c| c.text.chars().filter(|c| !c.is_whitespace()).count())
+            .sum::<usize>();
+        let comment_ratio = comment_chars as f64 / (comment_chars + code_chars).max(1) as f64;
+        let abnormal_ratio =
+            comment_chars >= MIN_RATIO_COMMENT_CHARS && comment_ratio > COMMENT_RATIO_THRESHOLD;
+
+        let payloads: Vec<CommentPayload> = comments
+            .iter()
+            .filter_map(|comment| {
+                let (data, classification) = comment_payload(&comment.text)?;
+                Some(CommentPayload {
+                    line: comment.line,
+                    end_line: comment.end_line,
+                    length: comment.text.chars().count(),
+                    preview: describe(&data, &classification),
+                    data,
+                    classification,
+                })
+            })
+            .collect();
+
+        Ok(SourceCodeAnalysis {
+            language: language.name,
+            comments: comments.len(),
+            comment_chars,
+            code_chars,
+            comment_ratio,
+            suspicious: !payloads.is_empty() || abnormal_ratio,
+            payloads,
+            abnormal_ratio,
+        })
+    }
+}
+
+struct Comment {
+    line: usize,
+    end_line: usize,
+    text: String,
+    // A line comment on a line of its own, which the next line's may continue
+    line_comment: bool,
+}
+
+// The comments of `text` with consecutive line comments joined, and the count of non-blank
+// characters outside them
+fn extract_comments(text: &str, language: &Language) -> (Vec<Comment>, usize) {
+    let mut comments: Vec<Comment> = Vec::new();
+    let mut code_chars = 0;
+    let mut line = 1;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        // A # only opens a comment at the start of a word: $# and ${#var} are shell syntax
+        let word_start = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        if let Some(marker) = language
+            .line
+            .iter()
+            .find(|marker| rest.starts_with(**marker) && (**marker != "#" || word_start))
+        {
+            let end = rest.find('\n').map_or(text.len(), |newline| i + newline);
+            let body = text[i + marker.len()..end].to_string();
+            let line_start = text[..i].rfind('\n').map_or(0, |newline| newline + 1);
+            let own_line = text[line_start..i].trim().is_empty();
+            match comments.last_mut() {
+                Some(last) if own_line && last.line_comment && last.end_line + 1 == line => {
+                    last.text.push('\n');
+                    last.text.push_str(&body);
+                    last.end_line = line;
+                }
+                _ => comments.push(Comment {
+                    line,
+                    end_line: line,
+                    text: body,
+                    line_comment: own_line,
+                }),
+            }
+            i = end;
+            continue;
+        }
+        if let Some((open, close)) = language
+            .block
+            .iter()
+            .find(|(open, _)| rest.starts_with(open))
+        {
+            let body_start = i + open.len();
+            let (body_end, end) = match text[body_start..].find(close) {
+                Some(offset) => (body_start + offset, body_start + offset + close.len()),
+                None => (text.len(), text.len()),
+            };
+            let end_line = line + text[i..end].matches('\n').count();
+            comments.push(Comment {
+                line,
+                end_line,
+                text: text[body_start..body_end].to_string(),
+                line_comment: false,
+            });
+            line = end_line;
+            i = end;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        if language.quotes.contains(&c) {
+            // String literals count as code up to the closing quote
+            let mut escaped = false;
+            let mut end = text.len();
+            for (offset, d) in rest.char_indices().skip(1) {
+                if d == '\n' {
+                    line += 1;
+                } else if !d.is_whitespace() {
+                    code_chars += 1;
+                }
+                if escaped {
+                    escaped = false;
+                } else if d == '\\' {
+                    escaped = true;
+                } else if d == c {
+                    end = i + offset + d.len_utf8();
+                    break;
+                }
+            }
+            code_chars += 1;
+            i = end;
+            continue;
+        }
+        if c == '\n' {
+            line += 1;
+        } else if !c.is_whitespace() {
+            code_chars += 1;
+        }
+        i += c.len_utf8();
+    }
+    (comments, code_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    #[test]
+    fn test_wrapped_blob_in_line_comments() {
+        let secret = STANDARD
+            .encode("AWS_SECRET_ACCESS_KEY=wJalrXUtnFEMI/K7MDENG copied from the build server");
+        let wrapped: String = secret
+            .as_bytes()
+            .chunks(40)
+            .map(|chunk| format!("    // {}\n", String::from_utf8_lossy(chunk)))
+            .collect();
+        let source = format!(
+            "fn main() {{\n    let url = \"http://example.com/*not a comment*/\";\n{}    \
+             println!(\"{{}}\", url); // done\n}}\n",
+            wrapped
+        );
+
+        let language = language_for("rs").unwrap();
+        let analysis = SourceCodeAnalyzer::analyze((source, language)).unwrap();
+        assert_eq!(analysis.language, "Rust");
+        assert_eq!(analysis.comments, 2);
+        assert_eq!(analysis.payloads.len(), 1);
+        let payload = &analysis.payloads[0];
+        assert_eq!((payload.line, payload.end_line), (3, 5));
+        assert!(payload.preview.starts_with("AWS_SECRET_ACCESS_KEY"));
+        assert!(!analysis.abnormal_ratio);
+        assert!(analysis.suspicious);
+    }
+
+    #[test]
+    fn test_comment_ratio() {
+        let prose = "Lorem ipsum dolor sit amet, consectetur adipiscing elit.\n".repeat(100);
+        let mostly_comment = format!("\"\"\"docs\"\"\"\n{}x = 1\n", {
+            prose
+                .lines()
+                .map(|line| format!("# {}\n", line))
+                .collect::<String>()
+        });
+        let language = language_for("py").unwrap();
+        let analysis = SourceCodeAnalyzer::analyze((mostly_comment, language)).unwrap();
+        assert_eq!(analysis.comments, 1);
+        assert!(analysis.payloads.is_empty());
+        assert!(analysis.abnormal_ratio);
+        assert!(analysis.comment_ratio > COMMENT_RATIO_THRESHOLD);
+
+        let script = "echo $# ${#items[@]} 'it''s # not a comment'\n# a short note\n";
+        let analysis =
+            SourceCodeAnalyzer::analyze((script.to_string(), language_for("sh").unwrap())).unwrap();
+        assert_eq!(analysis.comments, 1);
+        assert!(!analysis.suspicious);
+    }
+}
@@ -1,9 +1,24 @@
 use crate::Analyzer;
-use image::{ImageBuffer, Luma};
+use crate::spectrogram_render::{self, Axes, ColorMap};
+use crate::timeline::TimeRange;
+use image::{ImageBuffer, Luma, RgbImage};
 use std::fmt::Display;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
 pub struct SpectrogramAnalyzer;
 
+// Share of spectral energy above the cutoff that marks a file as suspicious
+pub const HIGH_FREQUENCY_ENERGY_THRESHOLD: f64 = 0.1;
+pub const HIGH_FREQUENCY_CUTOFF_HZ: f32 = 15000.0;
+
+const WINDOW_SIZE: usize = 2048;
+const HOP_SIZE: usize = 512;
+
+// Recordings longer than this many seconds are analyzed in tiles of this length
+pub const DEFAULT_TILE_SECONDS: u32 = 60;
+
 #[derive(Debug)]
 pub enum SpectrogramAnalyzerError {
     AudioProcessing(String),
@@ -28,47 +43,178 @@ pub struct SpectrogramData {
     pub spectrogram_image: ImageBuffer<Luma<u8>, Vec<u8>>,
     pub high_frequency_energy: f64,
     pub suspicious_patterns: Vec<String>,
+    // The tones behind the "persistent high-frequency tone" patterns
+    pub persistent_tones: Vec<PersistentTone>,
     pub has_hidden_message: bool,
+    pub sample_rate: u32,
+    // Where the analyzed samples start in the recording
+    pub start_seconds: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PersistentTone {
+    pub frequency_hz: f32,
+    // The first run of frames long enough to count as persistent
+    pub range: TimeRange,
+}
+
+impl SpectrogramData {
+    // Re-evaluate the hidden message verdict against a caller-supplied energy threshold
+    pub fn has_hidden_message_at(&self, high_frequency_energy_threshold: f64) -> bool {
+        self.high_frequency_energy > high_frequency_energy_threshold
+            || !self.suspicious_patterns.is_empty()
+    }
+
+    // The spectrogram with time and frequency axes, for saving
+    pub fn render(&self, color_map: ColorMap) -> RgbImage {
+        let rate = self.sample_rate.max(1) as f64;
+        spectrogram_render::render(
+            &self.spectrogram_image,
+            Axes {
+                start_seconds: self.start_seconds,
+                seconds_per_column: HOP_SIZE as f64 / rate,
+                hz_per_row: rate / WINDOW_SIZE as f64,
+            },
+            color_map,
+        )
+    }
+
+    // Centre frequencies of the persistent tones
+    pub fn persistent_tone_frequencies(&self) -> Vec<f32> {
+        self.persistent_tones
+            .iter()
+            .map(|tone| tone.frequency_hz)
+            .collect()
+    }
 }
 
 impl Analyzer for SpectrogramAnalyzer {
-    type Input = Vec<f32>; // Audio samples
+    // Mono samples and their sample rate
+    type Input = (Vec<f32>, u32);
     type Output = SpectrogramData;
     type Error = SpectrogramAnalyzerError;
 
     fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
-        if input.is_empty() {
-            return Err(SpectrogramAnalyzerError::AudioProcessing(
-                "Empty audio input".to_string(),
-            ));
-        }
+        let (samples, sample_rate) = input;
+        analyze_segment(&samples, sample_rate, 0.0)
+    }
+}
 
-        // Parameters for spectrogram generation
-        let window_size = 2048;
-        let hop_size = 512;
-        let sample_rate = 44100.0;
+// Findings are timed from `offset_seconds`, where the samples start in the recording
+fn analyze_segment(
+    input: &[f32],
+    sample_rate: u32,
+    offset_seconds: f64,
+) -> Result<SpectrogramData, SpectrogramAnalyzerError> {
+    if input.is_empty() {
+        return Err(SpectrogramAnalyzerError::AudioProcessing(
+            "Empty audio input".to_string(),
+        ));
+    }
 
-        // Generate spectrogram
-        let spectrogram = generate_spectrogram(&input, window_size, hop_size)?;
+    // Parameters for spectrogram generation
+    let window_size = WINDOW_SIZE;
+    let hop_size = HOP_SIZE;
+
+    // Generate spectrogram
+    let spectrogram = generate_spectrogram(input, window_size, hop_size)?;
+
+    // Analyze high frequency content (where messages are often hidden)
+    let high_freq_energy = analyze_high_frequency_energy(&spectrogram, sample_rate as f32);
+
+    // Detect suspicious patterns
+    let timing = Timing {
+        sample_rate,
+        offset_seconds,
+    };
+    let (suspicious_patterns, persistent_tones) = detect_patterns(&spectrogram, timing);
+
+    // Create visualization
+    let spectrogram_image = create_spectrogram_image(&spectrogram);
+
+    // Determine if there might be a hidden message
+    let has_hidden_message =
+        high_freq_energy > HIGH_FREQUENCY_ENERGY_THRESHOLD || !suspicious_patterns.is_empty();
+
+    Ok(SpectrogramData {
+        spectrogram_image,
+        high_frequency_energy: high_freq_energy,
+        suspicious_patterns,
+        persistent_tones,
+        has_hidden_message,
+        sample_rate,
+        start_seconds: offset_seconds,
+    })
+}
 
-        // Analyze high frequency content (where messages are often hidden)
-        let high_freq_energy = analyze_high_frequency_energy(&spectrogram, sample_rate);
+// One time window of a long recording, analyzed on its own
+#[derive(Debug, Clone)]
+pub struct SpectrogramTile {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub data: SpectrogramData,
+}
 
-        // Detect suspicious patterns
-        let suspicious_patterns = detect_patterns(&spectrogram);
+impl SpectrogramTile {
+    // e.g. "00:01:00.0-00:02:00.0"
+    pub fn label(&self) -> String {
+        TimeRange {
+            start_seconds: self.start_seconds,
+            end_seconds: self.end_seconds,
+        }
+        .to_string()
+    }
+}
 
-        // Create visualization
-        let spectrogram_image = create_spectrogram_image(&spectrogram);
+// Analyze a recording in windows of `tile_seconds`, each with its own image, energy and
+// patterns, so a burst of a few seconds isn't averaged away over hours of audio. Findings are
+// timed from the start of the recording, not of their window. The last
+// window may be shorter; one too short for a single FFT frame is left out.
+pub fn analyze_tiles(
+    samples: &[f32],
+    sample_rate: u32,
+    tile_seconds: u32,
+) -> Result<Vec<SpectrogramTile>, SpectrogramAnalyzerError> {
+    let tile_len = sample_rate as usize * tile_seconds as usize;
+    if tile_len == 0 {
+        return Err(SpectrogramAnalyzerError::AudioProcessing(
+            "Tile length and sample rate must be positive".to_string(),
+        ));
+    }
 
-        // Determine if there might be a hidden message
-        let has_hidden_message = high_freq_energy > 0.1 || !suspicious_patterns.is_empty();
+    let mut tiles = Vec::new();
+    for (index, chunk) in samples.chunks(tile_len).enumerate() {
+        if chunk.len() < WINDOW_SIZE {
+            continue;
+        }
+        let start = index * tile_len;
+        let start_seconds = start as f64 / sample_rate as f64;
+        tiles.push(SpectrogramTile {
+            start_seconds,
+            end_seconds: (start + chunk.len()) as f64 / sample_rate as f64,
+            data: analyze_segment(chunk, sample_rate, start_seconds)?,
+        });
+    }
 
-        Ok(SpectrogramData {
-            spectrogram_image,
-            high_frequency_energy: high_freq_energy,
-            suspicious_patterns,
-            has_hidden_message,
-        })
+    if tiles.is_empty() {
+        return Err(SpectrogramAnalyzerError::AudioProcessing(
+            "Audio too short for a spectrogram".to_string(),
+        ));
+    }
+    Ok(tiles)
+}
+
+// Turns frame indices into times in the recording
+#[derive(Debug, Clone, Copy)]
+struct Timing {
+    sample_rate: u32,
+    offset_seconds: f64,
+}
+
+impl Timing {
+    fn range(&self, first: usize, last: usize) -> TimeRange {
+        TimeRange::from_frames(first, last, HOP_SIZE, WINDOW_SIZE, self.sample_rate)
+            .offset(self.offset_seconds)
     }
 }
 
@@ -77,10 +223,9 @@ fn generate_spectrogram(
     window_size: usize,
     hop_size: usize,
 ) -> Result<Vec<Vec<f32>>, SpectrogramAnalyzerError> {
-    use rustfft::{FftPlanner, num_complex::Complex};
+    use rustfft::num_complex::Complex;
 
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(window_size);
+    let fft = crate::shared::fft_forward(window_size);
 
     let mut spectrogram = Vec::new();
     let num_frames = (samples.len() - window_size) / hop_size + 1;
@@ -132,8 +277,7 @@ fn analyze_high_frequency_energy(spectrogram: &[Vec<f32>], sample_rate: f32) ->
     let freq_per_bin = sample_rate / (2.0 * num_bins as f32);
 
     // Focus on frequencies above 15 kHz (where messages are often hidden)
-    let high_freq_threshold = 15000.0;
-    let start_bin = (high_freq_threshold / freq_per_bin) as usize;
+    let start_bin = (HIGH_FREQUENCY_CUTOFF_HZ / freq_per_bin) as usize;
 
     let mut total_energy = 0.0;
     let mut high_freq_energy = 0.0;
@@ -156,11 +300,14 @@ fn analyze_high_frequency_energy(spectrogram: &[Vec<f32>], sample_rate: f32) ->
     }
 }
 
-fn detect_patterns(spectrogram: &[Vec<f32>]) -> Vec<String> {
+// Returns the pattern descriptions, each with the time range it covers, and the persistent
+// tones
+fn detect_patterns(spectrogram: &[Vec<f32>], timing: Timing) -> (Vec<String>, Vec<PersistentTone>) {
     let mut patterns = Vec::new();
+    let mut tones = Vec::new();
 
     if spectrogram.is_empty() {
-        return patterns;
+        return (patterns, tones);
     }
 
     let num_bins = spectrogram[0].len();
@@ -168,50 +315,88 @@ fn detect_patterns(spectrogram: &[Vec<f32>]) -> Vec<String> {
 
     // Check for unusual horizontal lines (constant frequencies)
     for bin in num_bins / 2..num_bins {
-        let mut consecutive_high = 0;
-        for frame in spectrogram {
-            if frame[bin] > 0.5 {
-                consecutive_high += 1;
-            } else {
-                consecutive_high = 0;
-            }
-
-            if consecutive_high > num_frames / 4 {
-                patterns.push(format!(
-                    "Persistent high-frequency tone at bin {} (possible hidden data)",
-                    bin
-                ));
-                break;
-            }
+        if let Some((first, last)) = persistent_run(spectrogram, bin, num_frames / 4) {
+            let frequency_hz = bin as f32 * timing.sample_rate as f32 / WINDOW_SIZE as f32;
+            let range = timing.range(first, last);
+            patterns.push(format!(
+                "Persistent {:.1} kHz tone at {} (possible hidden data)",
+                frequency_hz / 1000.0,
+                range
+            ));
+            tones.push(PersistentTone {
+                frequency_hz,
+                range,
+            });
         }
     }
 
     // Check for geometric patterns (text/images in spectrogram)
-    let edge_count = detect_edges(spectrogram);
-    if edge_count > (num_frames * num_bins) / 20 {
-        patterns.push("High edge density detected (possible hidden image/text)".to_string());
+    let edges = detect_edges(spectrogram);
+    if edges.iter().sum::<usize>() > (num_frames * num_bins) / 20 {
+        // Where the edges concentrate, falling back to everything that has any
+        let dense: Vec<usize> = (0..edges.len())
+            .filter(|&i| edges[i] > num_bins / 20)
+            .collect();
+        let busy: Vec<usize> = if dense.is_empty() {
+            (0..edges.len()).filter(|&i| edges[i] > 0).collect()
+        } else {
+            dense
+        };
+        if let (Some(&first), Some(&last)) = (busy.first(), busy.last()) {
+            patterns.push(format!(
+                "High edge density at {} (possible hidden image/text)",
+                timing.range(first, last)
+            ));
+        }
     }
 
     // Check for unusual energy distribution
-    let mut high_energy_frames = 0;
-    for frame in spectrogram {
+    let mut high_energy_frames = Vec::new();
+    for (i, frame) in spectrogram.iter().enumerate() {
         let max_magnitude = frame.iter().fold(0.0f32, |a, &b| a.max(b));
         let avg_magnitude = frame.iter().sum::<f32>() / frame.len() as f32;
 
         if max_magnitude > 5.0 * avg_magnitude {
-            high_energy_frames += 1;
+            high_energy_frames.push(i);
         }
     }
 
-    if high_energy_frames > num_frames / 10 {
-        patterns.push("Unusual energy spikes detected".to_string());
+    if high_energy_frames.len() > num_frames / 10
+        && let (Some(&first), Some(&last)) = (high_energy_frames.first(), high_energy_frames.last())
+    {
+        patterns.push(format!(
+            "Unusual energy spikes in {} frames at {}",
+            high_energy_frames.len(),
+            timing.range(first, last)
+        ));
     }
 
-    patterns
+    (patterns, tones)
+}
+
+// First and last frame of the first run of loud frames in the bin longer than `min_frames`
+fn persistent_run(
+    spectrogram: &[Vec<f32>],
+    bin: usize,
+    min_frames: usize,
+) -> Option<(usize, usize)> {
+    let mut run_start = None;
+    for (i, frame) in spectrogram.iter().enumerate() {
+        if frame[bin] > 0.5 {
+            run_start.get_or_insert(i);
+        } else if let Some(start) = run_start.take()
+            && i - start > min_frames
+        {
+            return Some((start, i - 1));
+        }
+    }
+    let start = run_start?;
+    (spectrogram.len() - start > min_frames).then_some((start, spectrogram.len() - 1))
 }
 
-fn detect_edges(spectrogram: &[Vec<f32>]) -> usize {
-    let mut edge_count = 0;
+// Edge count of every frame
+fn detect_edges(spectrogram: &[Vec<f32>]) -> Vec<usize> {
+    let mut edges = vec![0; spectrogram.len()];
 
     for i in 1..spectrogram.len() {
         for j in 1..spectrogram[0].len() {
@@ -221,12 +406,12 @@ fn detect_edges(spectrogram: &[Vec<f32>]) -> usize {
 
             // Simple edge detection (large gradient)
             if (current - left).abs() > 0.3 || (current - top).abs() > 0.3 {
-                edge_count += 1;
+                edges[i] += 1;
             }
         }
     }
 
-    edge_count
+    edges
 }
 
 fn create_spectrogram_image(spectrogram: &[Vec<f32>]) -> ImageBuffer<Luma<u8>, Vec<u8>> {
@@ -283,10 +468,53 @@ mod tests {
             })
             .collect();
 
-        let result = SpectrogramAnalyzer::analyze(samples);
+        let result = SpectrogramAnalyzer::analyze((samples, sample_rate as u32));
         assert!(result.is_ok());
 
         let data = result.unwrap();
         assert!(!data.spectrogram_image.dimensions().0 == 0);
     }
+
+    #[test]
+    fn test_tiles_localize_high_frequency_burst() {
+        let sample_rate = 44100;
+        // 2.5 s of a low tone with a tone above the cutoff in the second second only
+        let samples: Vec<f32> = (0..sample_rate * 5 / 2)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let frequency = if (sample_rate..2 * sample_rate).contains(&i) {
+                    17000.0
+                } else {
+                    200.0
+                };
+                (2.0 * std::f32::consts::PI * frequency * t).sin()
+            })
+            .collect();
+
+        let tiles = analyze_tiles(&samples, sample_rate as u32, 1).unwrap();
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[2].start_seconds, 2.0);
+        assert_eq!(tiles[2].end_seconds, 2.5);
+        assert_eq!(tiles[1].label(), "00:00:01.0-00:00:02.0");
+        assert!(tiles[1].data.high_frequency_energy > HIGH_FREQUENCY_ENERGY_THRESHOLD);
+        assert!(tiles[0].data.high_frequency_energy < HIGH_FREQUENCY_ENERGY_THRESHOLD);
+        assert!(analyze_tiles(&samples, sample_rate as u32, 0).is_err());
+
+        // The tone is timed from the start of the recording, not of its tile
+        let tone = tiles[1]
+            .data
+            .persistent_tones
+            .iter()
+            .find(|tone| (tone.frequency_hz - 17000.0).abs() < 25.0)
+            .unwrap();
+        assert!((1.0..1.1).contains(&tone.range.start_seconds));
+        assert!((1.9..2.0).contains(&tone.range.end_seconds));
+        assert!(
+            tiles[1]
+                .data
+                .suspicious_patterns
+                .iter()
+                .any(|pattern| pattern.starts_with("Persistent 17.0 kHz tone at 00:00:01.0"))
+        );
+    }
 }
@@ -0,0 +1,268 @@
+use image::{GrayImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Turns a raw spectrogram, one column per frame and one row per bin with the highest bin on
+// top, into an image an analyst can read: time in seconds along the bottom, frequency in kHz
+// up the side, and optionally the viridis colour map, which shows faint patterns better than
+// grey levels do.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMap {
+    #[default]
+    Grayscale,
+    Viridis,
+}
+
+impl FromStr for ColorMap {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "grayscale" | "greyscale" | "gray" | "grey" => Ok(Self::Grayscale),
+            "viridis" => Ok(Self::Viridis),
+            other => Err(format!(
+                "unknown color map {:?} (expected grayscale or viridis)",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for ColorMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMap::Grayscale => write!(f, "grayscale"),
+            ColorMap::Viridis => write!(f, "viridis"),
+        }
+    }
+}
+
+impl ColorMap {
+    pub fn color(&self, value: u8) -> Rgb<u8> {
+        match self {
+            ColorMap::Grayscale => Rgb([value, value, value]),
+            ColorMap::Viridis => viridis(value),
+        }
+    }
+}
+
+// How the spectrogram's pixels map to time and frequency
+#[derive(Debug, Clone, Copy)]
+pub struct Axes {
+    // Time of the first column
+    pub start_seconds: f64,
+    pub seconds_per_column: f64,
+    // Frequency of each row above the bottom one
+    pub hz_per_row: f64,
+}
+
+// Glyphs are 3x5 pixels drawn at this scale
+const SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+const TICK: u32 = 4;
+const LEFT_MARGIN: u32 = 48;
+const BOTTOM_MARGIN: u32 = TICK + 4 * GLYPH_HEIGHT * SCALE / 2 + 4;
+const TOP_MARGIN: u32 = GLYPH_HEIGHT * SCALE + 6;
+const RIGHT_MARGIN: u32 = 4 * (GLYPH_WIDTH + 1) * SCALE;
+const AXIS: Rgb<u8> = Rgb([255, 255, 255]);
+
+pub fn render(spectrogram: &GrayImage, axes: Axes, color_map: ColorMap) -> RgbImage {
+    let (width, height) = spectrogram.dimensions();
+    let mut image = RgbImage::from_pixel(
+        LEFT_MARGIN + width + RIGHT_MARGIN,
+        TOP_MARGIN + height + BOTTOM_MARGIN,
+        Rgb([0, 0, 0]),
+    );
+    for (x, y, pixel) in spectrogram.enumerate_pixels() {
+        image.put_pixel(LEFT_MARGIN + x, TOP_MARGIN + y, color_map.color(pixel[0]));
+    }
+
+    // Frequency axis, bottom row at 0 Hz
+    let bottom = TOP_MARGIN + height;
+    for y in TOP_MARGIN..bottom + TICK {
+        image.put_pixel(LEFT_MARGIN - 1, y, AXIS);
+    }
+    let top_khz = axes.hz_per_row * height.saturating_sub(1) as f64 / 1000.0;
+    let step = nice_step(top_khz, (height / 40).max(1));
+    let mut khz = 0.0;
+    while step > 0.0 && khz <= top_khz + 1e-9 {
+        let row = (khz * 1000.0 / axes.hz_per_row).round() as u32;
+        let y = bottom - 1 - row.min(height - 1);
+        for x in LEFT_MARGIN - 1 - TICK..LEFT_MARGIN - 1 {
+            image.put_pixel(x, y, AXIS);
+        }
+        let label = format_number(khz, step);
+        let x = (LEFT_MARGIN - 2 - TICK).saturating_sub(text_width(&label) + 2);
+        draw_text(
+            &mut image,
+            x,
+            y.saturating_sub(GLYPH_HEIGHT * SCALE / 2),
+            &label,
+        );
+        khz += step;
+    }
+    draw_text(&mut image, 2, 2, "kHz");
+
+    // Time axis
+    for x in LEFT_MARGIN - 1..LEFT_MARGIN + width {
+        image.put_pixel(x, bottom, AXIS);
+    }
+    let span = axes.seconds_per_column * width as f64;
+    let step = nice_step(span, (width / 80).max(1));
+    // The first tick at a multiple of the step, so tiles of a long recording line up
+    let mut seconds = (axes.start_seconds / step).ceil() * step;
+    while step > 0.0 && seconds <= axes.start_seconds + span + 1e-9 {
+        let column = ((seconds - axes.start_seconds) / axes.seconds_per_column).round() as u32;
+        if column < width {
+            let x = LEFT_MARGIN + column;
+            for y in bottom..bottom + TICK {
+                image.put_pixel(x, y, AXIS);
+            }
+            let label = format_number(seconds, step);
+            let label_x = x.saturating_sub(text_width(&label) / 2);
+            draw_text(&mut image, label_x, bottom + TICK + 2, &label);
+        }
+        seconds += step;
+    }
+    draw_text(&mut image, LEFT_MARGIN + width + 4, bottom + TICK + 2, "s");
+
+    image
+}
+
+// The smallest of 1, 2 and 5 times a power of ten that splits `range` into at most
+// `max_ticks` steps
+fn nice_step(range: f64, max_ticks: u32) -> f64 {
+    if range <= 0.0 || !range.is_finite() {
+        return 0.0;
+    }
+    let rough = range / max_ticks as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(10.0 * magnitude)
+}
+
+// As many decimals as the step needs
+fn format_number(value: f64, step: f64) -> String {
+    let decimals = if step >= 1.0 {
+        0
+    } else {
+        (-step.log10().floor()) as usize
+    };
+    format!("{:.*}", decimals, value)
+}
+
+fn text_width(text: &str) -> u32 {
+    (text.chars().count() as u32 * (GLYPH_WIDTH + 1)).saturating_sub(1) * SCALE
+}
+
+fn draw_text(image: &mut RgbImage, x: u32, y: u32, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let rows = glyph(c);
+        let left = x + i as u32 * (GLYPH_WIDTH + 1) * SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits >> (GLYPH_WIDTH - 1 - column) & 1 == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let (px, py) = (left + column * SCALE + dx, y + row as u32 * SCALE + dy);
+                        if px < image.width() && py < image.height() {
+                            image.put_pixel(px, py, AXIS);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Rows of a 3x5 glyph, most significant bit on the left
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'k' => [0b100, 0b101, 0b110, 0b101, 0b101],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'z' => [0b000, 0b111, 0b001, 0b010, 0b111],
+        's' => [0b000, 0b011, 0b110, 0b001, 0b110],
+        _ => [0; 5],
+    }
+}
+
+// Matplotlib's viridis, sampled at nine points and interpolated
+fn viridis(value: u8) -> Rgb<u8> {
+    const STOPS: [[f32; 3]; 9] = [
+        [68.0, 1.0, 84.0],
+        [71.0, 45.0, 123.0],
+        [59.0, 82.0, 139.0],
+        [44.0, 114.0, 142.0],
+        [33.0, 145.0, 140.0],
+        [40.0, 174.0, 128.0],
+        [94.0, 201.0, 98.0],
+        [173.0, 220.0, 48.0],
+        [253.0, 231.0, 37.0],
+    ];
+    let position = value as f32 / 255.0 * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    Rgb([0, 1, 2].map(|i| (a[i] + (b[i] - a[i]) * t).round() as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_adds_axes_and_colors() {
+        let spectrogram = GrayImage::from_fn(200, 100, |x, _| image::Luma([x as u8]));
+        let axes = Axes {
+            start_seconds: 60.0,
+            seconds_per_column: 0.05,
+            hz_per_row: 220.5,
+        };
+
+        let rendered = render(&spectrogram, axes, ColorMap::Viridis);
+        assert_eq!(
+            rendered.dimensions(),
+            (
+                LEFT_MARGIN + 200 + RIGHT_MARGIN,
+                TOP_MARGIN + 100 + BOTTOM_MARGIN
+            )
+        );
+        // The darkest and brightest values take the ends of the map
+        assert_eq!(
+            *rendered.get_pixel(LEFT_MARGIN, TOP_MARGIN),
+            Rgb([68, 1, 84])
+        );
+        assert_eq!(viridis(255), Rgb([253, 231, 37]));
+        // Axis lines and labels are drawn in the margins
+        assert_eq!(*rendered.get_pixel(LEFT_MARGIN - 1, TOP_MARGIN + 50), AXIS);
+        assert!(
+            (0..LEFT_MARGIN - 1)
+                .any(|x| (TOP_MARGIN..TOP_MARGIN + 100).any(|y| *rendered.get_pixel(x, y) == AXIS))
+        );
+
+        assert_eq!(nice_step(10.0, 4), 5.0);
+        assert_eq!(nice_step(22.05, 10), 5.0);
+        assert_eq!(format_number(1.5, 0.5), "1.5");
+        assert_eq!("Viridis".parse::<ColorMap>(), Ok(ColorMap::Viridis));
+    }
+}
@@ -0,0 +1,626 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use crate::model_analyzer::{MIN_BLOB_CHARS, decode_blob};
+use crate::text_stego_analyzer::{analyze_text, mixes_scripts};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::fmt::Display;
+
+// Places a JSON, XML or YAML document can carry data its consumers never look at: comments,
+// attribute values far longer than any setting needs, base64 under keys that have no reason to
+// hold binary data, and keys made unreadable with zero-width, bidirectional or tag characters
+// or lookalike letters. Tag characters spell ASCII invisibly, so keys using them are decoded.
+pub struct StructuredTextAnalyzer;
+
+// String values longer than this are reported wherever they appear
+pub const MAX_VALUE_CHARS: usize = 2048;
+
+// Characters of a decoded preview
+const PREVIEW_CHARS: usize = 80;
+
+// Keys whose values are expected to be encoded binary data
+const BINARY_KEYS: [&str; 24] = [
+    "data",
+    "base64",
+    "b64",
+    "blob",
+    "image",
+    "img",
+    "icon",
+    "logo",
+    "avatar",
+    "thumbnail",
+    "picture",
+    "photo",
+    "font",
+    "cert",
+    "key",
+    "signature",
+    "hash",
+    "digest",
+    "checksum",
+    "token",
+    "secret",
+    "nonce",
+    "salt",
+    "integrity",
+];
+
+// Zero-width and bidirectional control characters, the soft hyphen and the byte order mark
+const INVISIBLE: [char; 18] = [
+    '\u{00AD}', '\u{180E}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{200E}', '\u{200F}', '\u{202A}',
+    '\u{202B}', '\u{202C}', '\u{202D}', '\u{202E}', '\u{2060}', '\u{2066}', '\u{2067}', '\u{2068}',
+    '\u{2069}', '\u{FEFF}',
+];
+
+// Unicode tag characters mirror printable ASCII and render as nothing
+const TAG_ASCII: std::ops::RangeInclusive<char> = '\u{E0020}'..='\u{E007E}';
+
+#[derive(Debug)]
+pub enum StructuredTextError {
+    Parse(String),
+}
+
+impl Display for StructuredTextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructuredTextError::Parse(e) => write!(f, "Unreadable document: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StructuredTextError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredFormat {
+    Json,
+    Xml,
+    Yaml,
+}
+
+impl Display for StructuredFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructuredFormat::Json => write!(f, "JSON"),
+            StructuredFormat::Xml => write!(f, "XML"),
+            StructuredFormat::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    // A comment carrying encoded data or zero-width characters
+    Comment,
+    LongValue,
+    // Encoded data under a key that doesn't call for it
+    Blob,
+    InvisibleKey,
+}
+
+impl Display for FindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FindingKind::Comment => write!(f, "comment"),
+            FindingKind::LongValue => write!(f, "long value"),
+            FindingKind::Blob => write!(f, "blob"),
+            FindingKind::InvisibleKey => write!(f, "invisible key"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructuredFinding {
+    pub kind: FindingKind,
+    // Path of the key or value, "/servers/0/name" or "/config/item/@id", or the comment's line
+    pub location: String,
+    // Characters of the value, key or comment
+    pub length: usize,
+    // What the finding decodes to, when it decodes
+    pub decoded: Option<Vec<u8>>,
+    // MIME type, "text" or "binary" of the decoded data
+    pub classification: Option<String>,
+    // The start of the decoded text, or of the value itself when nothing decoded
+    pub preview: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructuredTextAnalysis {
+    pub format: StructuredFormat,
+    pub comments: usize,
+    // Keys, attributes and string values read
+    pub values: usize,
+    pub findings: Vec<StructuredFinding>,
+    pub suspicious: bool,
+}
+
+// The structured format of a file, from its extension or an XML declaration
+pub fn structured_format(text: &str, extension: &str) -> Option<StructuredFormat> {
+    match extension.to_ascii_lowercase().as_str() {
+        "json" | "jsonc" | "json5" | "geojson" | "webmanifest" => Some(StructuredFormat::Json),
+        "xml" | "xsd" | "xsl" | "xslt" | "xaml" | "plist" | "rss" | "atom" | "config" => {
+            Some(StructuredFormat::Xml)
+        }
+        "yaml" | "yml" => Some(StructuredFormat::Yaml),
+        _ => text
+            .trim_start_matches('\u{FEFF}')
+            .trim_start()
+            .starts_with("<?xml")
+            .then_some(StructuredFormat::Xml),
+    }
+}
+
+impl Analyzer for StructuredTextAnalyzer {
+    type Input = (String, StructuredFormat);
+    type Output = StructuredTextAnalysis;
+    type Error = StructuredTextError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (text, format) = input;
+        let mut scan = Scan::default();
+        match format {
+            StructuredFormat::Json => scan_json(&text, &mut scan)?,
+            StructuredFormat::Xml => scan_xml(&text, &mut scan)?,
+            StructuredFormat::Yaml => scan_yaml(&text, &mut scan)?,
+        }
+        Ok(StructuredTextAnalysis {
+            format,
+            comments: scan.comments,
+            values: scan.values,
+            suspicious: !scan.findings.is_empty(),
+            findings: scan.findings,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Scan {
+    comments: usize,
+    values: usize,
+    findings: Vec<StructuredFinding>,
+}
+
+impl Scan {
+    fn key(&mut self, location: &str, key: &str) {
+        self.values += 1;
+        let tagged: String = key
+            .chars()
+            .filter(|c| TAG_ASCII.contains(c))
+            .map(|c| (c as u32 - 0xE0000) as u8 as char)
+            .collect();
+        let unreadable = key.trim().is_empty()
+            || !tagged.is_empty()
+            || key.chars().any(|c| INVISIBLE.contains(&c))
+            || key.split(|c: char| !c.is_alphabetic()).any(mixes_scripts);
+        if !unreadable {
+            return;
+        }
+        let (decoded, classification, preview) = if tagged.is_empty() {
+            (None, None, key.escape_debug().to_string())
+        } else {
+            (
+                Some(tagged.clone().into_bytes()),
+                Some("text".to_string()),
+                preview(&tagged),
+            )
+        };
+        self.findings.push(StructuredFinding {
+            kind: FindingKind::InvisibleKey,
+            location: location.to_string(),
+            length: key.chars().count(),
+            decoded,
+            classification,
+            preview,
+        });
+    }
+
+    // A string value under `key`. Values that are prose in their format, XML element text,
+    // aren't held to the length limit.
+    fn value(&mut self, location: &str, key: &str, value: &str, length_limited: bool) {
+        self.values += 1;
+        let key = key.to_ascii_lowercase();
+        if BINARY_KEYS.iter().any(|expected| key.contains(expected)) {
+            return;
+        }
+        let length = value.chars().count();
+        if let Some((data, classification)) = decode(value) {
+            self.findings.push(StructuredFinding {
+                kind: FindingKind::Blob,
+                location: location.to_string(),
+                length,
+                preview: describe(&data, &classification),
+                decoded: Some(data),
+                classification: Some(classification),
+            });
+        } else if length_limited && length > MAX_VALUE_CHARS {
+            self.findings.push(StructuredFinding {
+                kind: FindingKind::LongValue,
+                location: location.to_string(),
+                length,
+                decoded: None,
+                classification: None,
+                preview: preview(value),
+            });
+        }
+    }
+
+    fn comment(&mut self, line: usize, comment: &str) {
+        self.comments += 1;
+        if let Some((data, classification)) = comment_payload(comment) {
+            self.findings.push(StructuredFinding {
+                kind: FindingKind::Comment,
+                location: format!("line {}", line),
+                length: comment.chars().count(),
+                preview: describe(&data, &classification),
+                decoded: Some(data),
+                classification: Some(classification),
+            });
+        }
+    }
+}
+
+// Encoded data in a comment: base64 wrapped over several lines is read as one run, then each
+// word on its own, then zero-width characters as bits
+pub(crate) fn comment_payload(comment: &str) -> Option<(Vec<u8>, String)> {
+    let mut candidates: Vec<String> = Vec::new();
+    let mut run = String::new();
+    for line in comment.lines() {
+        let line = line.trim().trim_start_matches(['*', '#']).trim();
+        if !line.is_empty() && line.bytes().all(is_base64_byte) {
+            run.push_str(line);
+        } else if !run.is_empty() {
+            candidates.push(std::mem::take(&mut run));
+        }
+    }
+    candidates.push(run);
+    candidates.extend(comment.split_whitespace().map(str::to_string));
+    candidates
+        .iter()
+        .find_map(|candidate| decode(candidate))
+        .or_else(|| {
+            let payload = analyze_text(comment).zero_width_payload?;
+            Some((payload.data, payload.classification))
+        })
+}
+
+// Encoded data in a value; base64 may be wrapped over lines. Hex that doesn't decode to text
+// or a known format is taken for a digest or an identifier.
+fn decode(value: &str) -> Option<(Vec<u8>, String)> {
+    let compact: String = value.lines().map(str::trim).collect();
+    if compact.len() < MIN_BLOB_CHARS {
+        return None;
+    }
+    let data = decode_blob(&compact)?;
+    let classification = classify_payload(&data);
+    let hex = compact.bytes().all(|b| b.is_ascii_hexdigit());
+    (!hex || classification != "binary").then_some((data, classification))
+}
+
+fn is_base64_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'='
+}
+
+// The decoded text's start, or the size and type of anything else
+pub(crate) fn describe(data: &[u8], classification: &str) -> String {
+    if classification == "text" {
+        preview(&String::from_utf8_lossy(data))
+    } else {
+        format!("{} bytes of {}", data.len(), classification)
+    }
+}
+
+fn preview(text: &str) -> String {
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if text.chars().nth(PREVIEW_CHARS).is_some() {
+        preview.push('…');
+    }
+    preview.escape_debug().to_string()
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+// Path segment for a key, with the separators a key may contain escaped as in JSON pointers
+fn segment(key: &str) -> String {
+    key.replace('~', "~0")
+        .replace('/', "~1")
+        .escape_debug()
+        .to_string()
+}
+
+// JSONC and JSON5 allow comments, which serde_json doesn't; they are blanked out first
+fn scan_json(text: &str, scan: &mut Scan) -> Result<(), StructuredTextError> {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    let mut in_string = false;
+    while let Some((start, c)) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            if c == '\\' {
+                if let Some((_, escaped)) = chars.next() {
+                    stripped.push(escaped);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        let block = match (c, chars.peek()) {
+            ('/', Some((_, '/'))) => false,
+            ('/', Some((_, '*'))) => true,
+            _ => {
+                in_string = c == '"';
+                stripped.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        let mut end = text.len();
+        while let Some((index, c)) = chars.next() {
+            if !block && c == '\n' {
+                end = index;
+                stripped.push('\n');
+                break;
+            }
+            if block && c == '*' && chars.peek().is_some_and(|(_, c)| *c == '/') {
+                chars.next();
+                end = index;
+                break;
+            }
+        }
+        let comment = &text[start + 2..end];
+        // Keep line numbers of what follows
+        stripped.extend(std::iter::repeat_n(' ', 4));
+        stripped.extend(comment.chars().filter(|&c| c == '\n'));
+        scan.comment(line_of(text, start), comment);
+    }
+
+    let root: serde_json::Value =
+        serde_json::from_str(&stripped).map_err(|e| StructuredTextError::Parse(e.to_string()))?;
+    walk_json(&root, "", "", scan);
+    Ok(())
+}
+
+fn walk_json(value: &serde_json::Value, path: &str, key: &str, scan: &mut Scan) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (child_key, child) in map {
+                let child_path = format!("{}/{}", path, segment(child_key));
+                scan.key(&child_path, child_key);
+                walk_json(child, &child_path, child_key, scan);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                walk_json(item, &format!("{}/{}", path, index), key, scan);
+            }
+        }
+        serde_json::Value::String(text) => scan.value(path, key, text, true),
+        _ => {}
+    }
+}
+
+fn scan_xml(text: &str, scan: &mut Scan) -> Result<(), StructuredTextError> {
+    let mut reader = Reader::from_str(text);
+    let mut path: Vec<String> = Vec::new();
+    loop {
+        let position = reader.buffer_position() as usize;
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = element(&e, &path, scan);
+                path.push(format!("/{}", segment(&name)));
+            }
+            Ok(Event::Empty(e)) => {
+                element(&e, &path, scan);
+            }
+            Ok(Event::End(_)) => {
+                path.pop();
+            }
+            Ok(Event::Text(e)) => {
+                let value = String::from_utf8_lossy(&e).to_string();
+                if !value.trim().is_empty() {
+                    let key = path.last().map_or("", |last| &last[1..]).to_string();
+                    scan.value(&path.join(""), &key, &value, false);
+                }
+            }
+            Ok(Event::CData(e)) => {
+                let value = String::from_utf8_lossy(&e).to_string();
+                let key = path.last().map_or("", |last| &last[1..]).to_string();
+                scan.value(&path.join(""), &key, &value, false);
+            }
+            Ok(Event::Comment(e)) => {
+                scan.comment(line_of(text, position), &String::from_utf8_lossy(&e));
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(StructuredTextError::Parse(e.to_string())),
+        }
+    }
+    Ok(())
+}
+
+// Checks an element's name and attributes and returns the name
+fn element(e: &quick_xml::events::BytesStart, path: &[String], scan: &mut Scan) -> String {
+    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+    let location = format!("{}/{}", path.join(""), segment(&name));
+    scan.key(&location, &name);
+    for attribute in e.attributes().with_checks(false).flatten() {
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let value = attribute
+            .unescape_value()
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).to_string());
+        let attribute_location = format!("{}/@{}", location, segment(&key));
+        scan.key(&attribute_location, &key);
+        scan.value(&attribute_location, &key, &value, true);
+    }
+    name
+}
+
+fn scan_yaml(text: &str, scan: &mut Scan) -> Result<(), StructuredTextError> {
+    use serde::Deserialize;
+
+    for (index, line) in text.lines().enumerate() {
+        if let Some(comment) = yaml_comment(line) {
+            scan.comment(index + 1, comment);
+        }
+    }
+    for (index, document) in serde_yaml::Deserializer::from_str(text).enumerate() {
+        let root = serde_yaml::Value::deserialize(document)
+            .map_err(|e| StructuredTextError::Parse(e.to_string()))?;
+        // Documents after the first are told apart by their index
+        let path = if index == 0 {
+            String::new()
+        } else {
+            format!("/---{}", index)
+        };
+        walk_yaml(&root, &path, "", scan);
+    }
+    Ok(())
+}
+
+// The comment on a YAML line: a # at its start or after whitespace, outside quotes
+fn yaml_comment(line: &str) -> Option<&str> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '#') if previous.is_whitespace() => return Some(&line[index + 1..]),
+            _ => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+fn walk_yaml(value: &serde_yaml::Value, path: &str, key: &str, scan: &mut Scan) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (child_key, child) in map {
+                let child_key = match child_key {
+                    serde_yaml::Value::String(text) => text.clone(),
+                    other => serde_yaml::to_string(other)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                };
+                let child_path = format!("{}/{}", path, segment(&child_key));
+                scan.key(&child_path, &child_key);
+                walk_yaml(child, &child_path, &child_key, scan);
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                walk_yaml(item, &format!("{}/{}", path, index), key, scan);
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => walk_yaml(&tagged.value, path, key, scan),
+        serde_yaml::Value::String(text) => scan.value(path, key, text, true),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    fn analyze(text: &str, format: StructuredFormat) -> StructuredTextAnalysis {
+        StructuredTextAnalyzer::analyze((text.to_string(), format)).unwrap()
+    }
+
+    // Sorted by location, as object keys may come back in any order
+    fn findings(analysis: &StructuredTextAnalysis) -> Vec<(FindingKind, &str)> {
+        let mut found: Vec<(FindingKind, &str)> = analysis
+            .findings
+            .iter()
+            .map(|f| (f.kind, f.location.as_str()))
+            .collect();
+        found.sort_by_key(|(_, location)| *location);
+        found
+    }
+
+    #[test]
+    fn test_json_blobs_keys_and_comments() {
+        let secret = STANDARD.encode("transfer the funds to account 55-1234 before friday noon");
+        let tag: String = "hi"
+            .chars()
+            .map(|c| char::from_u32(0xE0000 + c as u32).unwrap())
+            .collect();
+        let text = format!(
+            r#"{{
+  // build {secret}
+  "name": "service",
+  "na{zw}me": "shadow",
+  "x{tag}": 1,
+  "retries": "{secret}",
+  "icon": "{secret}",
+  "commit": "3f786850e387550fdab836ed7e6dc881de23001b3f786850e387550fdab836ed",
+  "notes": "{long}"
+}}"#,
+            zw = '\u{200B}',
+            long = "a ".repeat(MAX_VALUE_CHARS),
+        );
+
+        let analysis = analyze(&text, StructuredFormat::Json);
+        assert_eq!(analysis.comments, 1);
+        let found = findings(&analysis);
+        assert_eq!(
+            found,
+            [
+                (FindingKind::InvisibleKey, "/na\\u{200b}me"),
+                (FindingKind::LongValue, "/notes"),
+                (FindingKind::Blob, "/retries"),
+                (FindingKind::InvisibleKey, "/x\\u{e0068}\\u{e0069}"),
+                (FindingKind::Comment, "line 2"),
+            ]
+        );
+        let comment = &analysis.findings[0];
+        assert!(comment.preview.starts_with("transfer the funds"));
+        let tagged = analysis
+            .findings
+            .iter()
+            .find(|f| f.location.starts_with("/x"));
+        assert_eq!(tagged.unwrap().preview, "hi");
+    }
+
+    #[test]
+    fn test_xml_and_yaml() {
+        let secret = STANDARD.encode("the backup server password is hunter2, rotate it monthly");
+        let xml = format!(
+            "<?xml version=\"1.0\"?>\n<config>\n  <!-- {secret} -->\n  <item id=\"7\" \
+             label=\"{secret}\"/>\n  <thumbnail>{secret}</thumbnail>\n  <note>{secret}</note>\n</config>\n"
+        );
+        let analysis = analyze(&xml, StructuredFormat::Xml);
+        let found = findings(&analysis);
+        assert_eq!(
+            found,
+            [
+                (FindingKind::Blob, "/config/item/@label"),
+                (FindingKind::Blob, "/config/note"),
+                (FindingKind::Comment, "line 3"),
+            ]
+        );
+
+        let yaml = format!(
+            "# deployment\nservice: web # {secret}\nlabels:\n  - plain\n  - \"{secret}\"\nurl: \"http://host/#anchor\"\n"
+        );
+        let analysis = analyze(&yaml, StructuredFormat::Yaml);
+        assert_eq!(analysis.comments, 2);
+        let found = findings(&analysis);
+        assert_eq!(
+            found,
+            [
+                (FindingKind::Blob, "/labels/1"),
+                (FindingKind::Comment, "line 2"),
+            ]
+        );
+
+        let clean = analyze("name: web\nreplicas: 3\n", StructuredFormat::Yaml);
+        assert!(!clean.suspicious);
+    }
+}
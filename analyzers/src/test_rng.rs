@@ -0,0 +1,15 @@
+// Small deterministic xorshift for the image tests, so they don't need a rand dependency
+pub(crate) struct XorShift(pub(crate) u64);
+
+impl XorShift {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub(crate) fn bit(&mut self) -> u8 {
+        (self.next() & 1) as u8
+    }
+}
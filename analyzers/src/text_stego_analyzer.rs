@@ -0,0 +1,140 @@
+use crate::payload_sniffer::{PayloadInterpretation, sniff};
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Carriers text offers without changing how it reads: zero-width characters between the
+// visible ones, whitespace appended to line ends (SNOW and its kin encode bits as runs of
+// spaces and tabs) and Cyrillic or Greek letters standing in for the Latin ones they look
+// like. Zero-width characters are read back as bits, one code point for 0 and another for 1.
+
+// Characters that take up no space when rendered. A byte order mark at the very start of the
+// text is just that and isn't counted.
+const ZERO_WIDTH: [char; 6] = [
+    '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}', '\u{180E}',
+];
+
+// Cyrillic and Greek letters rendered the same as a Latin letter in common fonts
+const CONFUSABLES: [char; 27] = [
+    'а', 'е', 'о', 'р', 'с', 'у', 'х', 'і', 'ј', 'ѕ', 'А', 'В', 'Е', 'К', 'М', 'Н', 'О', 'Р', 'С',
+    'Т', 'Х', 'ο', 'α', 'ι', 'Α', 'Β', 'Ο',
+];
+
+// Lines ending in mixed spaces and tabs before the text counts as whitespace-encoded;
+// editors leave the odd one behind
+pub const MIN_WHITESPACE_LINES: usize = 3;
+
+// Mixed-script words before the text counts as carrying homoglyphs
+pub const MIN_HOMOGLYPH_WORDS: usize = 2;
+
+// Zero-width characters one payload byte needs
+const BITS_PER_BYTE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct TextStegoAnalysis {
+    pub zero_width_chars: usize,
+    // What the zero-width characters decode to, when that is text, a file or a compressed
+    // stream
+    pub zero_width_payload: Option<PayloadInterpretation>,
+    // Lines whose trailing whitespace mixes spaces and tabs
+    pub whitespace_lines: usize,
+    // Words spelled in Latin letters with a Cyrillic or Greek lookalike among them
+    pub homoglyph_words: Vec<String>,
+    pub suspicious: bool,
+}
+
+pub fn analyze_text(text: &str) -> TextStegoAnalysis {
+    let body = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let zero_width: Vec<char> = body.chars().filter(|c| ZERO_WIDTH.contains(c)).collect();
+    let zero_width_payload = decode_zero_width(&zero_width);
+
+    let whitespace_lines = body
+        .lines()
+        .filter(|line| {
+            let content = line.trim_end_matches([' ', '\t']);
+            let trailing = &line[content.len()..];
+            !content.is_empty() && trailing.contains(' ') && trailing.contains('\t')
+        })
+        .count();
+
+    let mut homoglyph_words: Vec<String> = Vec::new();
+    for word in body.split(|c: char| !c.is_alphabetic()) {
+        if mixes_scripts(word) && !homoglyph_words.iter().any(|w| w == word) {
+            homoglyph_words.push(word.to_string());
+        }
+    }
+
+    let suspicious = zero_width_payload.is_some()
+        || zero_width.len() >= BITS_PER_BYTE
+        || whitespace_lines >= MIN_WHITESPACE_LINES
+        || homoglyph_words.len() >= MIN_HOMOGLYPH_WORDS;
+    TextStegoAnalysis {
+        zero_width_chars: zero_width.len(),
+        zero_width_payload,
+        whitespace_lines,
+        homoglyph_words,
+        suspicious,
+    }
+}
+
+// A Latin word with a Cyrillic or Greek lookalike among its letters
+pub(crate) fn mixes_scripts(word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_alphabetic()) && word.chars().any(|c| CONFUSABLES.contains(&c))
+}
+
+// Two distinct zero-width characters read as bits, the lower code point as 0 first and then
+// the other way round. Encoders that use more symbols aren't decoded.
+fn decode_zero_width(chars: &[char]) -> Option<PayloadInterpretation> {
+    let mut symbols: Vec<char> = chars.to_vec();
+    symbols.sort_unstable();
+    symbols.dedup();
+    if symbols.len() != 2 || chars.len() < BITS_PER_BYTE {
+        return None;
+    }
+    [false, true].into_iter().find_map(|inverted| {
+        let bytes: Vec<u8> = chars
+            .chunks_exact(BITS_PER_BYTE)
+            .map(|byte| {
+                byte.iter().fold(0u8, |acc, &c| {
+                    (acc << 1) | ((c == symbols[1]) != inverted) as u8
+                })
+            })
+            .collect();
+        Some(sniff(&bytes)).filter(PayloadInterpretation::is_recognized)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decodes_zero_width_bits() {
+        let hidden: String = "key=42"
+            .bytes()
+            .flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1))
+            .map(|bit| if bit == 1 { '\u{200C}' } else { '\u{200B}' })
+            .collect();
+        let text = format!("\u{FEFF}Nothing to{} see here.", hidden);
+
+        let analysis = analyze_text(&text);
+        assert!(analysis.suspicious);
+        assert_eq!(analysis.zero_width_chars, 48);
+        let payload = analysis.zero_width_payload.unwrap();
+        assert_eq!(payload.text().as_deref(), Some("key=42"));
+    }
+
+    #[test]
+    fn test_whitespace_and_homoglyphs() {
+        let text = "One \t \nTwo\t  \nThree  \t\nplain line   \nThe pаssword is in the сellar\n";
+        let analysis = analyze_text(text);
+        assert_eq!(analysis.zero_width_chars, 0);
+        assert_eq!(analysis.whitespace_lines, 3);
+        assert_eq!(analysis.homoglyph_words, ["pаssword", "сellar"]);
+        assert!(analysis.suspicious);
+
+        let clean = analyze_text("An ordinary paragraph.  \nНовости дня\n");
+        assert!(!clean.suspicious);
+        assert!(clean.homoglyph_words.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::Display;
+
+// Where in a recording an audio finding is, worked out from the frames an analyzer flagged, so
+// an analyst can jump straight to the segment
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+
+impl TimeRange {
+    // From the start of frame `first` to the end of frame `last`
+    pub fn from_frames(
+        first: usize,
+        last: usize,
+        hop_size: usize,
+        frame_size: usize,
+        sample_rate: u32,
+    ) -> Self {
+        let rate = sample_rate.max(1) as f64;
+        Self {
+            start_seconds: (first * hop_size) as f64 / rate,
+            end_seconds: (last * hop_size + frame_size) as f64 / rate,
+        }
+    }
+
+    // The same range in a recording whose analyzed part starts `seconds` in
+    pub fn offset(self, seconds: f64) -> Self {
+        Self {
+            start_seconds: self.start_seconds + seconds,
+            end_seconds: self.end_seconds + seconds,
+        }
+    }
+}
+
+// e.g. "00:01:23.0-00:02:10.5"
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            format_timestamp(self.start_seconds),
+            format_timestamp(self.end_seconds)
+        )
+    }
+}
+
+// Seconds as HH:MM:SS.s
+pub fn format_timestamp(seconds: f64) -> String {
+    let tenths = (seconds.max(0.0) * 10.0) as u64;
+    let total = tenths / 10;
+    format!(
+        "{:02}:{:02}:{:02}.{}",
+        total / 3600,
+        total / 60 % 60,
+        total % 60,
+        tenths % 10
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_to_time_range() {
+        // Frames 0..=9 at a 512 hop with 2048-sample frames, 44.1 kHz
+        let range = TimeRange::from_frames(0, 9, 512, 2048, 44100);
+        assert_eq!(range.start_seconds, 0.0);
+        assert!((range.end_seconds - 6656.0 / 44100.0).abs() < 1e-12);
+
+        let range = TimeRange::from_frames(86, 172, 512, 2048, 44100).offset(3600.0);
+        assert_eq!(range.to_string(), "01:00:00.9-01:00:02.0");
+        assert_eq!(format_timestamp(3725.0), "01:02:05.0");
+    }
+}
@@ -0,0 +1,449 @@
+use crate::Analyzer;
+use crate::id3_analyzer::classify_payload;
+use std::fmt::Display;
+use std::ops::Range;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// APEv2 and Lyrics3 tags, which older MP3s (and APE, Musepack and WavPack files) carry after
+// the last audio frame, in front of the ID3v1 tag when there is one. Every item is read; values
+// over MAX_TEXT_ITEM_BYTES, binary values other than cover art, cover art that isn't an image and
+// text that isn't text are flagged, as is a tag whose items don't add up to its declared size.
+pub struct TrailingTagAnalyzer;
+
+// Largest text value expected in a tag; a long set of lyrics is a few KiB
+pub const MAX_TEXT_ITEM_BYTES: usize = 16 * 1024;
+
+// Largest cover art expected in an APEv2 tag
+pub const MAX_COVER_ART_BYTES: usize = 5_000_000;
+
+// Lyrics3v1 lyrics may not exceed 5100 bytes
+const LYRICS3V1_MAX_BYTES: usize = 5100;
+
+// Lyrics3v2 fields
+const LYRICS3_FIELDS: [&str; 8] = ["IND", "LYR", "INF", "AUT", "EAL", "EAR", "ETT", "IMG"];
+
+#[derive(Debug)]
+pub enum TrailingTagError {
+    NoTags,
+}
+
+impl Display for TrailingTagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrailingTagError::NoTags => write!(f, "No APEv2 or Lyrics3 tag found"),
+        }
+    }
+}
+
+impl std::error::Error for TrailingTagError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingTagKind {
+    Ape,
+    Lyrics3v1,
+    Lyrics3v2,
+}
+
+impl Display for TrailingTagKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrailingTagKind::Ape => write!(f, "APEv2"),
+            TrailingTagKind::Lyrics3v1 => write!(f, "Lyrics3v1"),
+            TrailingTagKind::Lyrics3v2 => write!(f, "Lyrics3v2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TagItem {
+    // APEv2 key or Lyrics3v2 field ID; "LYRICS" for the body of a Lyrics3v1 tag
+    pub key: String,
+    // Where the value starts in the file
+    pub offset: usize,
+    pub value: Vec<u8>,
+    // Declared binary by the tag
+    pub binary: bool,
+    // MIME type of the value, or "text"/"binary" when unrecognized
+    pub classification: String,
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrailingTag {
+    pub kind: TrailingTagKind,
+    // Header (or footer-less start) to the end of the tag
+    pub offset: usize,
+    pub size: usize,
+    pub items: Vec<TagItem>,
+    // Problems with the tag as a whole
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrailingTagAnalysis {
+    pub tags: Vec<TrailingTag>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for TrailingTagAnalyzer {
+    type Input = Vec<u8>;
+    type Output = TrailingTagAnalysis;
+    type Error = TrailingTagError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let layout = locate_trailing_tags(&input, 0);
+        let mut tags: Vec<TrailingTag> = layout
+            .tags
+            .iter()
+            .map(|(kind, range)| match kind {
+                TrailingTagKind::Ape => read_ape(&input, range.clone()),
+                TrailingTagKind::Lyrics3v1 => read_lyrics3v1(&input, range.clone()),
+                TrailingTagKind::Lyrics3v2 => read_lyrics3v2(&input, range.clone()),
+            })
+            .collect();
+        if tags.is_empty() {
+            return Err(TrailingTagError::NoTags);
+        }
+        // Tags are found back from the end; report them in file order
+        tags.reverse();
+
+        let suspicious = tags.iter().any(|tag| {
+            !tag.findings.is_empty() || tag.items.iter().any(|i| !i.findings.is_empty())
+        });
+        Ok(TrailingTagAnalysis { tags, suspicious })
+    }
+}
+
+// The tags players expect after the last audio frame, from the end of the file back
+pub(crate) struct TrailingTags {
+    // Where the first of them starts
+    pub start: usize,
+    pub tags: Vec<(TrailingTagKind, Range<usize>)>,
+}
+
+// Finds the ID3v1 tag and, in front of it in either order, an APEv2 and a Lyrics3 tag,
+// searching back from the end of the file but never before `from`
+pub(crate) fn locate_trailing_tags(data: &[u8], from: usize) -> TrailingTags {
+    let mut end = data.len();
+    let has_id3v1 = end >= from + 128 && &data[end - 128..end - 125] == b"TAG";
+    if has_id3v1 {
+        end -= 128;
+    }
+
+    let mut tags = Vec::new();
+    loop {
+        let found = ape_range(data, end)
+            .map(|range| (TrailingTagKind::Ape, range))
+            // Lyrics3 is only valid in front of an ID3v1 tag
+            .or_else(|| has_id3v1.then(|| lyrics3_range(data, end)).flatten());
+        match found {
+            Some((kind, range)) if range.start >= from && !tags.iter().any(|(k, _)| *k == kind) => {
+                end = range.start;
+                tags.push((kind, range));
+            }
+            _ => break,
+        }
+    }
+    TrailingTags { start: end, tags }
+}
+
+// An APEv2 (or APEv1) tag whose footer ends at `end`
+fn ape_range(data: &[u8], end: usize) -> Option<Range<usize>> {
+    let footer = data.get(end.checked_sub(32)?..end)?;
+    if &footer[..8] != b"APETAGEX" {
+        return None;
+    }
+    // The size covers the items and footer, not the header
+    let size = u32::from_le_bytes(footer[12..16].try_into().unwrap()) as usize;
+    let flags = u32::from_le_bytes(footer[20..24].try_into().unwrap());
+    let tag = size + if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+    (tag >= 32 && tag <= end).then(|| end - tag..end)
+}
+
+// A Lyrics3v2 tag ("LYRICS200" after a six-digit size) or a Lyrics3v1 tag ("LYRICSEND") ending
+// at `end`
+fn lyrics3_range(data: &[u8], end: usize) -> Option<(TrailingTagKind, Range<usize>)> {
+    let marker = data.get(end.checked_sub(9)?..end)?;
+    if marker == b"LYRICS200" {
+        let digits = std::str::from_utf8(data.get(end.checked_sub(15)?..end - 9)?).ok()?;
+        let size: usize = digits.parse().ok()?;
+        let start = (end - 15).checked_sub(size)?;
+        return data[start..]
+            .starts_with(b"LYRICSBEGIN")
+            .then_some((TrailingTagKind::Lyrics3v2, start..end));
+    }
+    if marker == b"LYRICSEND" {
+        let window = end.saturating_sub(9 + LYRICS3V1_MAX_BYTES + 11);
+        let start = data[window..end]
+            .windows(11)
+            .rposition(|w| w == b"LYRICSBEGIN")?;
+        return Some((TrailingTagKind::Lyrics3v1, window + start..end));
+    }
+    None
+}
+
+fn read_ape(data: &[u8], range: Range<usize>) -> TrailingTag {
+    let footer = &data[range.end - 32..range.end];
+    let version = u32::from_le_bytes(footer[8..12].try_into().unwrap());
+    let size = u32::from_le_bytes(footer[12..16].try_into().unwrap()) as usize;
+    let declared_items = u32::from_le_bytes(footer[16..20].try_into().unwrap()) as usize;
+    let mut findings = Vec::new();
+    if version != 1000 && version != 2000 {
+        findings.push(format!("Unknown APE tag version {}", version));
+    }
+
+    let mut items = Vec::new();
+    let items_end = range.end - 32;
+    let mut offset = range.end - size;
+    while offset + 8 < items_end {
+        let value_size = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let flags = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+        let key_start = offset + 8;
+        let Some(key_length) = data[key_start..items_end].iter().position(|&b| b == 0) else {
+            findings.push(format!("Unterminated item key at offset {}", key_start));
+            break;
+        };
+        let key = String::from_utf8_lossy(&data[key_start..key_start + key_length]).to_string();
+        let value_start = key_start + key_length + 1;
+        let Some(value_end) = value_start
+            .checked_add(value_size)
+            .filter(|&value_end| value_end <= items_end)
+        else {
+            findings.push(format!(
+                "Item {:?} declares {} bytes, past the end of the tag",
+                key, value_size
+            ));
+            break;
+        };
+
+        // Bits 1-2: 0 is UTF-8 text, 1 binary, 2 an external locator
+        let binary = (flags >> 1) & 3 == 1;
+        let value = data[value_start..value_end].to_vec();
+        let mut item_findings = Vec::new();
+        if !key.bytes().all(|b| (0x20..0x7F).contains(&b)) || !(2..=255).contains(&key_length) {
+            item_findings.push("Key isn't 2 to 255 printable ASCII characters".to_string());
+        }
+        let classification = if binary && key.starts_with("Cover Art") {
+            // A file name, a NUL, then the image
+            let image = value
+                .iter()
+                .position(|&b| b == 0)
+                .map_or(&value[..], |nul| &value[nul + 1..]);
+            let classification = classify_payload(image);
+            if !classification.starts_with("image/") {
+                item_findings.push(format!("Cover art holds {}, not an image", classification));
+            } else if image.len() > MAX_COVER_ART_BYTES {
+                item_findings.push(format!("Oversized cover art: {} bytes", image.len()));
+            }
+            classification
+        } else {
+            let classification = classify_payload(&value);
+            if binary {
+                item_findings.push(format!(
+                    "Binary value of {} bytes ({})",
+                    value.len(),
+                    classification
+                ));
+            } else {
+                check_text(&value, &classification, &mut item_findings);
+            }
+            classification
+        };
+        items.push(TagItem {
+            key,
+            offset: value_start,
+            value,
+            binary,
+            classification,
+            findings: item_findings,
+        });
+        offset = value_end;
+    }
+    if items.len() != declared_items {
+        findings.push(format!(
+            "Declares {} items but holds {}",
+            declared_items,
+            items.len()
+        ));
+    }
+
+    TrailingTag {
+        kind: TrailingTagKind::Ape,
+        offset: range.start,
+        size: range.len(),
+        items,
+        findings,
+    }
+}
+
+fn read_lyrics3v1(data: &[u8], range: Range<usize>) -> TrailingTag {
+    let value_start = range.start + 11;
+    let value = data[value_start..range.end - 9].to_vec();
+    let classification = classify_payload(&value);
+    let mut findings = Vec::new();
+    check_text(&value, &classification, &mut findings);
+    TrailingTag {
+        kind: TrailingTagKind::Lyrics3v1,
+        offset: range.start,
+        size: range.len(),
+        items: vec![TagItem {
+            key: "LYRICS".to_string(),
+            offset: value_start,
+            value,
+            binary: false,
+            classification,
+            findings,
+        }],
+        findings: Vec::new(),
+    }
+}
+
+fn read_lyrics3v2(data: &[u8], range: Range<usize>) -> TrailingTag {
+    let mut items = Vec::new();
+    let mut findings = Vec::new();
+    // Fields follow "LYRICSBEGIN" up to the size and "LYRICS200"
+    let fields_end = range.end - 15;
+    let mut offset = range.start + 11;
+    while offset < fields_end {
+        let Some(header) = data
+            .get(offset..offset + 8)
+            .filter(|_| offset + 8 <= fields_end)
+        else {
+            findings.push(format!("Truncated field at offset {}", offset));
+            break;
+        };
+        let id = String::from_utf8_lossy(&header[..3]).to_string();
+        let Some(size) = std::str::from_utf8(&header[3..])
+            .ok()
+            .and_then(|digits| digits.parse::<usize>().ok())
+        else {
+            findings.push(format!("Field {:?} has no valid size", id));
+            break;
+        };
+        let value_start = offset + 8;
+        if value_start + size > fields_end {
+            findings.push(format!(
+                "Field {:?} declares {} bytes, past the end of the tag",
+                id, size
+            ));
+            break;
+        }
+
+        let value = data[value_start..value_start + size].to_vec();
+        let classification = classify_payload(&value);
+        let mut item_findings = Vec::new();
+        if !LYRICS3_FIELDS.contains(&id.as_str()) {
+            item_findings.push(format!("Unknown field ID {:?}", id));
+        }
+        check_text(&value, &classification, &mut item_findings);
+        items.push(TagItem {
+            key: id,
+            offset: value_start,
+            value,
+            binary: false,
+            classification,
+            findings: item_findings,
+        });
+        offset = value_start + size;
+    }
+
+    TrailingTag {
+        kind: TrailingTagKind::Lyrics3v2,
+        offset: range.start,
+        size: range.len(),
+        items,
+        findings,
+    }
+}
+
+// A value the tag says is text
+pub(crate) fn check_text(value: &[u8], classification: &str, findings: &mut Vec<String>) {
+    if classification != "text" {
+        findings.push(format!(
+            "Text value holds {} bytes of {}",
+            value.len(),
+            classification
+        ));
+    } else if value.len() > MAX_TEXT_ITEM_BYTES {
+        findings.push(format!("Oversized text value: {} bytes", value.len()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ape_item(key: &str, value: &[u8], flags: u32) -> Vec<u8> {
+        let mut item = (value.len() as u32).to_le_bytes().to_vec();
+        item.extend_from_slice(&flags.to_le_bytes());
+        item.extend_from_slice(key.as_bytes());
+        item.push(0);
+        item.extend_from_slice(value);
+        item
+    }
+
+    fn ape_tag(items: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = items.concat();
+        let mut footer = b"APETAGEX".to_vec();
+        footer.extend_from_slice(&2000u32.to_le_bytes());
+        footer.extend_from_slice(&((body.len() + 32) as u32).to_le_bytes());
+        footer.extend_from_slice(&(items.len() as u32).to_le_bytes());
+        footer.extend_from_slice(&[0; 12]);
+        [body, footer].concat()
+    }
+
+    #[test]
+    fn test_ape_and_lyrics3() {
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        png.extend_from_slice(&[0; 32]);
+        let ape = ape_tag(&[
+            ape_item("Artist", b"The Band", 0),
+            ape_item(
+                "Cover Art (Front)",
+                &[b"cover.png\0".as_slice(), &png].concat(),
+                2,
+            ),
+            ape_item("Notes", &[0x00, 0x01, 0x02, 0xFF, 0xFE], 2),
+        ]);
+        let mut lyrics = b"LYRICSBEGININD0000210".to_vec();
+        lyrics.extend_from_slice(b"LYR00011Hello world");
+        lyrics.extend_from_slice(b"XYZ00005\x01\x02\x03\x04\x05");
+        let size = format!("{:06}", lyrics.len());
+        lyrics.extend_from_slice(size.as_bytes());
+        lyrics.extend_from_slice(b"LYRICS200");
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+
+        let audio = vec![0u8; 1000];
+        let file = [audio, ape, lyrics, id3v1].concat();
+        let layout = locate_trailing_tags(&file, 0);
+        assert_eq!(layout.start, 1000);
+
+        let analysis = TrailingTagAnalyzer::analyze(file).unwrap();
+        assert!(analysis.suspicious);
+        let [ape, lyrics] = analysis.tags.as_slice() else {
+            panic!("expected two tags");
+        };
+        assert_eq!(ape.kind, TrailingTagKind::Ape);
+        assert_eq!(ape.offset, 1000);
+        assert!(ape.findings.is_empty());
+        assert!(ape.items[0].findings.is_empty());
+        assert_eq!(ape.items[1].classification, "image/png");
+        assert!(ape.items[1].findings.is_empty());
+        assert_eq!(ape.items[2].findings.len(), 1);
+
+        assert_eq!(lyrics.kind, TrailingTagKind::Lyrics3v2);
+        let keys: Vec<&str> = lyrics.items.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["IND", "LYR", "XYZ"]);
+        assert_eq!(lyrics.items[1].value, b"Hello world");
+        assert!(lyrics.items[1].findings.is_empty());
+        assert_eq!(lyrics.items[2].findings.len(), 2);
+
+        let plain = [vec![0u8; 200], ape_tag(&[ape_item("Title", b"Song", 0)])].concat();
+        let analysis = TrailingTagAnalyzer::analyze(plain).unwrap();
+        assert!(!analysis.suspicious);
+        assert!(TrailingTagAnalyzer::analyze(vec![0u8; 200]).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::Analyzer;
+use crate::demodulator::{FskSignal, demodulate_fsk, frame_peak, tone_clusters};
+use crate::shared::{fft_forward, fft_inverse};
+use crate::timeline::TimeRange;
+use rustfft::num_complex::Complex;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Isolates the band above the audible range of a recording, shifts it down to 0 Hz and
+// resamples it, so the 20-48 kHz content of a 96 kHz file can be measured, listened to and
+// demodulated on its own rather than folded into a single high-frequency energy number.
+// Two-tone keying (FSK), the usual carrier of ultrasonic beacons and covert audio
+// channels, is demodulated into bits with the demodulator's FSK reader.
+pub struct UltrasonicAnalyzer;
+
+pub const ULTRASONIC_CUTOFF_HZ: f32 = 20000.0;
+
+// Share of the total energy above the cutoff that marks a file as suspicious
+pub const ULTRASONIC_ENERGY_THRESHOLD: f64 = 0.01;
+
+const FRAME_SIZE: usize = 1024;
+const HOP_SIZE: usize = FRAME_SIZE / 2;
+
+#[derive(Debug)]
+pub enum UltrasonicAnalyzerError {
+    TooShort(usize),
+    // Sample rate whose Nyquist frequency doesn't reach past the cutoff
+    NoUltrasonicBand(u32, f32),
+}
+
+impl Display for UltrasonicAnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UltrasonicAnalyzerError::TooShort(len) => {
+                write!(f, "{} samples is shorter than one analysis frame", len)
+            }
+            UltrasonicAnalyzerError::NoUltrasonicBand(rate, cutoff) => write!(
+                f,
+                "A {} Hz sample rate can't hold content above {} Hz",
+                rate, cutoff
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UltrasonicAnalyzerError {}
+
+#[derive(Debug, Clone)]
+pub struct UltrasonicTone {
+    pub frequency_hz: f32,
+    // Share of the tonal frames whose peak is this tone
+    pub frame_share: f64,
+    // From the first to the last frame whose peak is this tone
+    pub range: TimeRange,
+}
+
+#[derive(Debug, Clone)]
+pub struct UltrasonicAnalysis {
+    pub sample_rate: u32,
+    pub band_low_hz: f32,
+    pub band_high_hz: f32,
+    // Share of the total spectral energy inside the band
+    pub band_energy_ratio: f64,
+    // The band moved down by baseband_offset_hz and resampled to baseband_rate, which is
+    // rounded to whole Hz
+    pub baseband: Vec<f32>,
+    pub baseband_rate: u32,
+    pub baseband_offset_hz: f32,
+    // Strongest first
+    pub tones: Vec<UltrasonicTone>,
+    pub fsk: Option<FskSignal>,
+    pub suspicious: bool,
+}
+
+impl UltrasonicAnalysis {
+    // Re-evaluate the verdict against a caller-supplied energy threshold
+    pub fn is_suspicious_at(&self, energy_threshold: f64) -> bool {
+        self.band_energy_ratio > energy_threshold || self.fsk.is_some()
+    }
+}
+
+impl Analyzer for UltrasonicAnalyzer {
+    // Mono samples, their sample rate and the cutoff frequency in Hz
+    type Input = (Vec<f32>, u32, f32);
+    type Output = UltrasonicAnalysis;
+    type Error = UltrasonicAnalyzerError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let (samples, sample_rate, cutoff_hz) = input;
+        let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+        let low_bin = (cutoff_hz.max(0.0) / bin_hz).ceil() as usize;
+        let high_bin = FRAME_SIZE / 2;
+        if low_bin >= high_bin {
+            return Err(UltrasonicAnalyzerError::NoUltrasonicBand(
+                sample_rate,
+                cutoff_hz,
+            ));
+        }
+        if samples.len() < FRAME_SIZE {
+            return Err(UltrasonicAnalyzerError::TooShort(samples.len()));
+        }
+
+        // Band bins are copied to bins 1.. of a smaller spectrum, whose inverse transform is
+        // the band at a sample rate just high enough to hold it
+        let band_bins = high_bin - low_bin;
+        let out_size = 2 * band_bins + 2;
+        let out_hop = out_size / 2;
+
+        let forward = fft_forward(FRAME_SIZE);
+        let inverse = fft_inverse(out_size);
+
+        // Periodic Hann windows at 50% overlap sum to one, so overlap-add needs no rescaling
+        let window: Vec<f32> = (0..FRAME_SIZE)
+            .map(|i| {
+                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos())
+            })
+            .collect();
+
+        let num_frames = (samples.len() - FRAME_SIZE) / HOP_SIZE + 1;
+        let mut baseband = vec![0.0f32; (num_frames - 1) * out_hop + out_size];
+        let mut peaks = Vec::with_capacity(num_frames);
+        let (mut total_energy, mut band_energy) = (0.0f64, 0.0f64);
+
+        for frame in 0..num_frames {
+            let start = frame * HOP_SIZE;
+            let mut buffer: Vec<Complex<f32>> = samples[start..start + FRAME_SIZE]
+                .iter()
+                .zip(&window)
+                .map(|(&s, &w)| Complex::new(s * w, 0.0))
+                .collect();
+            forward.process(&mut buffer);
+
+            let power: Vec<f32> = buffer[..high_bin].iter().map(|c| c.norm_sqr()).collect();
+            let frame_total: f32 = power.iter().sum();
+            total_energy += frame_total as f64;
+            band_energy += power[low_bin..].iter().sum::<f32>() as f64;
+            peaks.push(frame_peak(&power, low_bin, high_bin, frame_total));
+
+            let mut shifted = vec![Complex::new(0.0, 0.0); out_size];
+            for (k, value) in buffer[low_bin..high_bin].iter().enumerate() {
+                shifted[k + 1] = *value;
+                shifted[out_size - k - 1] = value.conj();
+            }
+            inverse.process(&mut shifted);
+            let out_start = frame * out_hop;
+            for (out, value) in baseband[out_start..out_start + out_size]
+                .iter_mut()
+                .zip(&shifted)
+            {
+                *out += value.re / FRAME_SIZE as f32;
+            }
+        }
+
+        let band_energy_ratio = if total_energy > 0.0 {
+            band_energy / total_energy
+        } else {
+            0.0
+        };
+        let clusters = tone_clusters(&peaks);
+        let tonal_frames = peaks.iter().flatten().count();
+        let tones: Vec<UltrasonicTone> = clusters
+            .iter()
+            .map(|c| {
+                let frames: Vec<usize> = (0..peaks.len())
+                    .filter(|&i| peaks[i].is_some_and(|bin| c.contains(bin)))
+                    .collect();
+                UltrasonicTone {
+                    frequency_hz: c.center * bin_hz,
+                    frame_share: c.frames as f64 / tonal_frames as f64,
+                    range: TimeRange::from_frames(
+                        frames.first().copied().unwrap_or_default(),
+                        frames.last().copied().unwrap_or_default(),
+                        HOP_SIZE,
+                        FRAME_SIZE,
+                        sample_rate,
+                    ),
+                }
+            })
+            .collect();
+        let fsk = demodulate_fsk(
+            &peaks,
+            &clusters,
+            bin_hz,
+            sample_rate as f32 / HOP_SIZE as f32,
+        );
+
+        let mut analysis = UltrasonicAnalysis {
+            sample_rate,
+            band_low_hz: low_bin as f32 * bin_hz,
+            band_high_hz: sample_rate as f32 / 2.0,
+            band_energy_ratio,
+            baseband,
+            baseband_rate: (sample_rate as f64 * out_size as f64 / FRAME_SIZE as f64).round()
+                as u32,
+            baseband_offset_hz: (low_bin - 1) as f32 * bin_hz,
+            tones,
+            fsk,
+            suspicious: false,
+        };
+        analysis.suspicious = analysis.is_suspicious_at(ULTRASONIC_ENERGY_THRESHOLD);
+        Ok(analysis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 96000;
+
+    fn sine(frequency: f32, amplitude: f32, i: usize) -> f32 {
+        amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / RATE as f32).sin()
+    }
+
+    #[test]
+    fn test_demodulates_ultrasonic_fsk() {
+        let message = b"HELLO";
+        let bits: Vec<bool> = message
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .collect();
+        // 50 baud, 24 kHz mark and 22 kHz space under an audible 1 kHz tone
+        let samples_per_bit = RATE as usize / 50;
+        let samples: Vec<f32> = (0..bits.len() * samples_per_bit)
+            .map(|i| {
+                let tone = if bits[i / samples_per_bit] {
+                    24000.0
+                } else {
+                    22000.0
+                };
+                sine(1000.0, 0.5, i) + sine(tone, 0.1, i)
+            })
+            .collect();
+
+        let analysis = UltrasonicAnalyzer::analyze((samples, RATE, ULTRASONIC_CUTOFF_HZ)).unwrap();
+        assert!(analysis.band_low_hz >= ULTRASONIC_CUTOFF_HZ);
+        assert_eq!(analysis.tones.len(), 2);
+        let fsk = analysis.fsk.as_ref().unwrap();
+        assert!((fsk.mark_hz - 24000.0).abs() < 100.0);
+        assert!((fsk.space_hz - 22000.0).abs() < 100.0);
+        assert!((fsk.baud - 50.0).abs() < 5.0);
+        assert_eq!(fsk.decoded_text().as_deref(), Some("HELLO"));
+        assert!(analysis.suspicious);
+    }
+
+    #[test]
+    fn test_baseband_holds_shifted_band() {
+        let samples: Vec<f32> = (0..RATE as usize)
+            .map(|i| sine(440.0, 0.5, i) + sine(30000.0, 0.2, i))
+            .collect();
+
+        let analysis = UltrasonicAnalyzer::analyze((samples, RATE, ULTRASONIC_CUTOFF_HZ)).unwrap();
+        assert!(analysis.baseband_rate < RATE);
+        assert!(analysis.fsk.is_none());
+        // The tone is there from the first frame to the last
+        assert_eq!(analysis.tones[0].range.start_seconds, 0.0);
+        assert!(analysis.tones[0].range.end_seconds > 0.99);
+
+        // The 30 kHz tone comes out at 30 kHz minus the offset, without the 440 Hz one
+        let expected = 30000.0 - analysis.baseband_offset_hz;
+        let middle =
+            &analysis.baseband[analysis.baseband.len() / 4..][..analysis.baseband.len() / 2];
+        let crossings = middle
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        let measured =
+            crossings as f32 / 2.0 / (middle.len() as f32 / analysis.baseband_rate as f32);
+        assert!((measured - expected).abs() < expected * 0.02);
+    }
+
+    #[test]
+    fn test_audible_only_and_low_rates() {
+        let samples: Vec<f32> = (0..RATE as usize).map(|i| sine(1000.0, 0.5, i)).collect();
+        let analysis = UltrasonicAnalyzer::analyze((samples, RATE, ULTRASONIC_CUTOFF_HZ)).unwrap();
+        assert!(analysis.band_energy_ratio < ULTRASONIC_ENERGY_THRESHOLD);
+        assert!(analysis.tones.is_empty());
+        assert!(!analysis.suspicious);
+
+        assert!(matches!(
+            UltrasonicAnalyzer::analyze((vec![0.0; 4096], 32000, ULTRASONIC_CUTOFF_HZ)),
+            Err(UltrasonicAnalyzerError::NoUltrasonicBand(32000, _))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{
+    adaptive_lsb_analyzer, bpcs_analyzer, calibration_analyzer, channel_correlation_analyzer,
+    deflate_hunter, demodulator, exif_analyzer, gif_extension_analyzer, id3_analyzer, image_filter,
+    lsb_analyzer, magic_bytes_analyzer, mp4_tag_analyzer, page_analyzer, phase_analyzer,
+    png_text_analyzer, slack_space_analyzer, spectrogram_analyzer, text_stego_analyzer,
+    trailing_tag_analyzer, ultrasonic_analyzer, video_frame_analyzer, webp_structure_analyzer,
+    ws_analyzer,
+};
+
+// Version of each analyzer, recorded in reports so a finding can be traced to the detection
+// logic that made it. The crate version changes with every release; the REVISION constant
+// next to each analyzer's code only when a change makes it report differently on the same
+// input, so reports from scanners that differ only in other analyzers stay comparable.
+const REVISIONS: [(&str, u32); 24] = [
+    ("magic_bytes", magic_bytes_analyzer::REVISION),
+    ("slack_space", slack_space_analyzer::REVISION),
+    ("deflate", deflate_hunter::REVISION),
+    ("exif", exif_analyzer::REVISION),
+    ("lsb", lsb_analyzer::REVISION),
+    ("ws", ws_analyzer::REVISION),
+    (
+        "channel_correlation",
+        channel_correlation_analyzer::REVISION,
+    ),
+    ("adaptive_lsb", adaptive_lsb_analyzer::REVISION),
+    ("bpcs", bpcs_analyzer::REVISION),
+    ("calibration", calibration_analyzer::REVISION),
+    ("webp", webp_structure_analyzer::REVISION),
+    ("gif", gif_extension_analyzer::REVISION),
+    ("png_text", png_text_analyzer::REVISION),
+    ("pages", page_analyzer::REVISION),
+    ("filters", image_filter::REVISION),
+    ("id3", id3_analyzer::REVISION),
+    ("trailing_tags", trailing_tag_analyzer::REVISION),
+    ("mp4_tags", mp4_tag_analyzer::REVISION),
+    ("spectrogram", spectrogram_analyzer::REVISION),
+    ("ultrasonic", ultrasonic_analyzer::REVISION),
+    ("demodulation", demodulator::REVISION),
+    ("phase", phase_analyzer::REVISION),
+    ("video", video_frame_analyzer::REVISION),
+    ("text", text_stego_analyzer::REVISION),
+];
+
+// "<crate version>+r<revision>", None for names that aren't analyzers
+pub fn analyzer_version(name: &str) -> Option<String> {
+    REVISIONS
+        .iter()
+        .find(|(analyzer, _)| *analyzer == name)
+        .map(|(_, revision)| format!("{}+r{}", env!("CARGO_PKG_VERSION"), revision))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyzer_version() {
+        assert_eq!(
+            analyzer_version("lsb"),
+            Some(format!(
+                "{}+r{}",
+                env!("CARGO_PKG_VERSION"),
+                lsb_analyzer::REVISION
+            ))
+        );
+        assert_eq!(analyzer_version("summary"), None);
+    }
+}
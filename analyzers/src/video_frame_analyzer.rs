@@ -1,7 +1,18 @@
 use crate::Analyzer;
+use crate::lsb_analyzer::{CHI_SQUARE_THRESHOLD, ENTROPY_THRESHOLD, exceeds_lsb_thresholds};
 use image::{DynamicImage, RgbaImage};
+use std::cell::RefCell;
 use std::fmt::Display;
 
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+thread_local! {
+    // LSB plane of one channel, reused for every channel of every frame analyzed on this
+    // thread rather than allocated three times a frame
+    static LSB_PLANE: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
 pub struct VideoFrameAnalyzer;
 
 #[derive(Debug)]
@@ -31,50 +42,75 @@ pub struct VideoFrameAnalysis {
     pub edge_density: f64,
 }
 
+impl VideoFrameAnalysis {
+    // Re-evaluate the frame's LSB scores against caller-supplied thresholds
+    pub fn is_lsb_suspicious_at(&self, chi_square_threshold: f64, entropy_threshold: f64) -> bool {
+        exceeds_lsb_thresholds(
+            &self.chi_square_scores,
+            &self.entropy_scores,
+            chi_square_threshold,
+            entropy_threshold,
+        )
+    }
+}
+
 impl Analyzer for VideoFrameAnalyzer {
     type Input = DynamicImage;
     type Output = VideoFrameAnalysis;
     type Error = VideoFrameAnalyzerError;
 
     fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
-        let rgba = input.to_rgba8();
+        // Decoded video frames are RGBA already and are analyzed in place; other images are
+        // converted once
+        match input.as_rgba8() {
+            Some(rgba) => Ok(analyze_frame(rgba)),
+            None => Ok(analyze_frame(&input.to_rgba8())),
+        }
+    }
+}
 
-        let mut chi_square_scores = Vec::new();
-        let mut entropy_scores = Vec::new();
+// Analyze a frame without taking ownership of it, so its buffer can be reused afterwards
+pub fn analyze_frame(rgba: &RgbaImage) -> VideoFrameAnalysis {
+    let mut chi_square_scores = Vec::new();
+    let mut entropy_scores = Vec::new();
 
-        // Analyze each color channel
+    // Analyze each color channel
+    LSB_PLANE.with(|plane| {
+        let mut lsb_plane = plane.borrow_mut();
         for channel in 0..3 {
-            let lsb_plane = extract_lsb_plane(&rgba, channel);
-            let chi_square = calculate_chi_square(&lsb_plane);
-            let entropy = calculate_entropy(&lsb_plane);
-
-            chi_square_scores.push(chi_square);
-            entropy_scores.push(entropy);
+            extract_lsb_plane(rgba, channel, &mut lsb_plane);
+            chi_square_scores.push(calculate_chi_square(&lsb_plane));
+            entropy_scores.push(calculate_entropy(&lsb_plane));
         }
-
-        // Check for LSB anomalies
-        let lsb_suspicious = chi_square_scores.iter().any(|&score| score > 100.0)
-            || entropy_scores.iter().any(|&ent| ent > 0.9);
-
-        // Check histogram anomalies
-        let histogram_anomalies = detect_histogram_anomalies(&rgba);
-
-        // Calculate edge density
-        let edge_density = calculate_edge_density(&rgba);
-
-        Ok(VideoFrameAnalysis {
-            frame_index: 0, // Will be set by caller
-            lsb_suspicious,
-            chi_square_scores,
-            entropy_scores,
-            histogram_anomalies,
-            edge_density,
-        })
+    });
+
+    // Check for LSB anomalies
+    let lsb_suspicious = exceeds_lsb_thresholds(
+        &chi_square_scores,
+        &entropy_scores,
+        CHI_SQUARE_THRESHOLD,
+        ENTROPY_THRESHOLD,
+    );
+
+    // Check histogram anomalies
+    let histogram_anomalies = detect_histogram_anomalies(rgba);
+
+    // Calculate edge density
+    let edge_density = calculate_edge_density(rgba);
+
+    VideoFrameAnalysis {
+        frame_index: 0, // Will be set by caller
+        lsb_suspicious,
+        chi_square_scores,
+        entropy_scores,
+        histogram_anomalies,
+        edge_density,
     }
 }
 
-fn extract_lsb_plane(image: &RgbaImage, channel: usize) -> Vec<u8> {
-    image.pixels().map(|pixel| pixel[channel] & 1).collect()
+fn extract_lsb_plane(image: &RgbaImage, channel: usize, plane: &mut Vec<u8>) {
+    plane.clear();
+    plane.extend(image.pixels().map(|pixel| pixel[channel] & 1));
 }
 
 fn calculate_chi_square(lsb_data: &[u8]) -> f64 {
@@ -192,7 +228,8 @@ mod tests {
     #[test]
     fn test_lsb_extraction() {
         let img = ImageBuffer::from_fn(10, 10, |x, y| Rgba([(x + y) as u8, 128, 64, 255]));
-        let lsb_data = extract_lsb_plane(&img, 0);
+        let mut lsb_data = vec![1; 7];
+        extract_lsb_plane(&img, 0, &mut lsb_data);
         assert_eq!(lsb_data.len(), 100);
     }
 
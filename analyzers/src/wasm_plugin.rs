@@ -0,0 +1,154 @@
+use crate::external_plugin::{PluginError, PluginOutput, PluginSpec};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+// Runs a WebAssembly analyzer in-process. The module gets no imports at all, so it can't
+// touch the filesystem, network or clock; it only sees the bytes it is handed. It must
+// export:
+//
+//   memory                              its linear memory
+//   alloc(len: i32) -> i32              a buffer of `len` bytes for the input
+//   analyze(ptr: i32, len: i32) -> i64  the output location, packed as (ptr << 32) | len
+//
+// The output is the same JSON object external process plugins print. Modules may be
+// binary .wasm or text .wat.
+
+// Linear memory a plugin may grow to
+pub const WASM_MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+
+pub fn run(spec: &PluginSpec, file_path: &Path) -> Result<PluginOutput, PluginError> {
+    let data = std::fs::read(file_path)?;
+    let wasm_error = |e: wasmtime::Error| {
+        if e.downcast_ref::<Trap>() == Some(&Trap::Interrupt) {
+            PluginError::Timeout(spec.name.clone(), spec.timeout_secs)
+        } else {
+            PluginError::Wasm(spec.name.clone(), e.to_string())
+        }
+    };
+
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).map_err(wasm_error)?;
+    let module = Module::from_file(&engine, &spec.command).map_err(wasm_error)?;
+
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(WASM_MAX_MEMORY_BYTES)
+        .build();
+    let mut store: Store<StoreLimits> = Store::new(&engine, limits);
+    store.limiter(|limits| limits);
+    // Interrupt once the timer thread below bumps the epoch
+    store.set_epoch_deadline(1);
+
+    // Dropping `_finished` when this function returns wakes the timer early
+    let (_finished, wait) = mpsc::channel::<()>();
+    let timer_engine = engine.clone();
+    let timeout = Duration::from_secs(spec.timeout_secs);
+    std::thread::spawn(move || {
+        if wait.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+            timer_engine.increment_epoch();
+        }
+    });
+
+    let linker = Linker::new(&engine);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(wasm_error)?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| PluginError::Wasm(spec.name.clone(), "no exported memory".to_string()))?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut store, "alloc")
+        .map_err(wasm_error)?;
+    let analyze = instance
+        .get_typed_func::<(i32, i32), i64>(&mut store, "analyze")
+        .map_err(wasm_error)?;
+
+    let len = i32::try_from(data.len()).map_err(|_| {
+        PluginError::Wasm(spec.name.clone(), "file too large for wasm32".to_string())
+    })?;
+    let input = alloc.call(&mut store, len).map_err(wasm_error)?;
+    memory
+        .write(&mut store, input as u32 as usize, &data)
+        .map_err(|e| PluginError::Wasm(spec.name.clone(), e.to_string()))?;
+
+    let packed = analyze.call(&mut store, (input, len)).map_err(wasm_error)? as u64;
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+    let mut output = vec![0; len];
+    memory
+        .read(&store, ptr, &mut output)
+        .map_err(|e| PluginError::Wasm(spec.name.clone(), e.to_string()))?;
+
+    serde_json::from_slice(&output)
+        .map_err(|e| PluginError::InvalidOutput(spec.name.clone(), e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_plugin::{PluginInput, PluginKind};
+
+    // Flags inputs whose first byte is 'S'. The two possible answers live in a data
+    // segment: the suspicious one at 0 (44 bytes) and the clean one at 64 (2 bytes).
+    const FLAG_FIRST_BYTE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{\"suspicious\":true,\"findings\":[{\"description\":\"S\"}]}")
+          (data (i32.const 64) "{}")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "analyze") (param $ptr i32) (param $len i32) (result i64)
+            (if (result i64) (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 83))
+              (then (i64.const 52))
+              (else (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 2))))))
+    "#;
+
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "analyze") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    fn wasm_plugin(dir: &Path, source: &str, timeout_secs: u64) -> PluginSpec {
+        let module = dir.join("plugin.wat");
+        std::fs::write(&module, source).unwrap();
+        PluginSpec {
+            name: "wasm-test".to_string(),
+            kind: PluginKind::Wasm,
+            command: module,
+            args: Vec::new(),
+            input: PluginInput::Path,
+            timeout_secs,
+        }
+    }
+
+    #[test]
+    fn test_wasm_abi() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = wasm_plugin(dir.path(), FLAG_FIRST_BYTE, 10);
+
+        let input = dir.path().join("input.bin");
+        std::fs::write(&input, b"Secret").unwrap();
+        let output = plugin.run(&input).unwrap();
+        assert!(output.suspicious);
+        assert_eq!(output.findings[0].description, "S");
+
+        std::fs::write(&input, b"plain").unwrap();
+        assert!(!plugin.run(&input).unwrap().suspicious);
+    }
+
+    #[test]
+    fn test_wasm_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = wasm_plugin(dir.path(), SPIN, 1);
+        let input = dir.path().join("plugin.wat");
+
+        assert!(matches!(
+            plugin.run(&input),
+            Err(PluginError::Timeout(_, 1))
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::Analyzer;
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Walks the RIFF chunk tree of a WebP file, including the sub-chunks inside each ANMF
+// frame, and reports anything a decoder would skip over: unknown chunks, data past the
+// end of the container, non-zero padding and mismatched animation flags.
+pub struct WebpStructureAnalyzer;
+
+const KNOWN_CHUNKS: [&str; 9] = [
+    "VP8 ", "VP8L", "VP8X", "ANIM", "ANMF", "ALPH", "ICCP", "EXIF", "XMP ",
+];
+
+// Chunks allowed inside an ANMF frame payload
+const FRAME_CHUNKS: [&str; 3] = ["ALPH", "VP8 ", "VP8L"];
+
+// ANMF payloads start with a 16-byte frame header before their sub-chunks
+const ANMF_HEADER_SIZE: usize = 16;
+
+// VP8X flag bit announcing an animation
+const VP8X_ANIMATION_FLAG: u8 = 0x02;
+
+#[derive(Debug)]
+pub enum WebpStructureError {
+    NotWebp,
+}
+
+impl Display for WebpStructureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebpStructureError::NotWebp => write!(f, "Not a RIFF/WEBP file"),
+        }
+    }
+}
+
+impl std::error::Error for WebpStructureError {}
+
+#[derive(Debug, Clone)]
+pub struct WebpChunk {
+    pub fourcc: String,
+    pub offset: usize,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebpStructureAnalysis {
+    pub riff_size: u32,
+    pub file_size: usize,
+    // Top-level chunks in file order
+    pub chunks: Vec<WebpChunk>,
+    pub animated: bool,
+    pub frame_chunks: usize,
+    // Bytes after the end the RIFF header declares
+    pub trailing_bytes: usize,
+    pub anomalies: Vec<String>,
+    pub suspicious: bool,
+}
+
+impl Analyzer for WebpStructureAnalyzer {
+    type Input = Vec<u8>;
+    type Output = WebpStructureAnalysis;
+    type Error = WebpStructureError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        if input.len() < 12 || &input[0..4] != b"RIFF" || &input[8..12] != b"WEBP" {
+            return Err(WebpStructureError::NotWebp);
+        }
+
+        let riff_size = read_u32(&input, 4);
+        let declared_end = 8 + riff_size as usize;
+        let mut anomalies = Vec::new();
+
+        if declared_end > input.len() {
+            anomalies.push(format!(
+                "RIFF header declares {} bytes but only {} are present",
+                declared_end,
+                input.len()
+            ));
+        }
+        let riff_end = declared_end.min(input.len());
+        let trailing_bytes = input.len() - riff_end;
+        if trailing_bytes > 0 {
+            anomalies.push(format!(
+                "{} bytes after the end of the RIFF container at offset {}",
+                trailing_bytes, riff_end
+            ));
+        }
+
+        let chunks = walk_chunks(&input, 12, riff_end, &KNOWN_CHUNKS, &mut anomalies);
+
+        let mut frame_chunks = 0;
+        for chunk in chunks.iter().filter(|c| c.fourcc == "ANMF") {
+            frame_chunks += 1;
+            let payload = chunk.offset + 8;
+            let end = (payload + chunk.size as usize).min(riff_end);
+            if payload + ANMF_HEADER_SIZE > end {
+                anomalies.push(format!(
+                    "ANMF chunk at offset {} is too short for a frame header",
+                    chunk.offset
+                ));
+                continue;
+            }
+            walk_chunks(
+                &input,
+                payload + ANMF_HEADER_SIZE,
+                end,
+                &FRAME_CHUNKS,
+                &mut anomalies,
+            );
+        }
+
+        let has_anim = chunks.iter().any(|c| c.fourcc == "ANIM");
+        if frame_chunks > 0 && !has_anim {
+            anomalies.push("ANMF frames present without an ANIM chunk".to_string());
+        }
+        if let Some(vp8x) = chunks.iter().find(|c| c.fourcc == "VP8X") {
+            let flagged = input
+                .get(vp8x.offset + 8)
+                .is_some_and(|flags| flags & VP8X_ANIMATION_FLAG != 0);
+            if flagged != (frame_chunks > 0) {
+                anomalies.push(format!(
+                    "VP8X animation flag is {} but {} ANMF frames were found",
+                    if flagged { "set" } else { "clear" },
+                    frame_chunks
+                ));
+            }
+        }
+
+        Ok(WebpStructureAnalysis {
+            riff_size,
+            file_size: input.len(),
+            chunks,
+            animated: frame_chunks > 0,
+            frame_chunks,
+            trailing_bytes,
+            suspicious: !anomalies.is_empty(),
+            anomalies,
+        })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+// Chunks are a fourcc, a little-endian size and a payload padded to an even length
+fn walk_chunks(
+    data: &[u8],
+    start: usize,
+    end: usize,
+    known: &[&str],
+    anomalies: &mut Vec<String>,
+) -> Vec<WebpChunk> {
+    let mut chunks = Vec::new();
+    let mut offset = start;
+
+    while offset + 8 <= end {
+        let fourcc = String::from_utf8_lossy(&data[offset..offset + 4]).to_string();
+        let size = read_u32(data, offset + 4);
+        let payload_end = offset + 8 + size as usize;
+
+        if !known.contains(&fourcc.as_str()) {
+            anomalies.push(format!(
+                "Unknown chunk '{}' at offset {} ({} bytes)",
+                fourcc.escape_debug(),
+                offset,
+                size
+            ));
+        }
+        if payload_end > end {
+            anomalies.push(format!(
+                "Chunk '{}' at offset {} overruns its container by {} bytes",
+                fourcc.escape_debug(),
+                offset,
+                payload_end - end
+            ));
+        } else if size % 2 == 1 && data.get(payload_end).is_some_and(|&pad| pad != 0) {
+            anomalies.push(format!(
+                "Non-zero padding byte after chunk '{}' at offset {}",
+                fourcc.escape_debug(),
+                payload_end
+            ));
+        }
+
+        chunks.push(WebpChunk {
+            fourcc,
+            offset,
+            size,
+        });
+        offset = payload_end + (size as usize & 1);
+    }
+
+    if offset < end {
+        anomalies.push(format!(
+            "{} stray bytes at offset {} that don't form a chunk",
+            end - offset,
+            offset
+        ));
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = fourcc.to_vec();
+        out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        out.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn webp(chunks: &[Vec<u8>], trailing: &[u8]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        out.extend_from_slice(b"WEBP");
+        out.extend_from_slice(&body);
+        out.extend_from_slice(trailing);
+        out
+    }
+
+    #[test]
+    fn test_animated_structure() {
+        let frame = [vec![0u8; ANMF_HEADER_SIZE], chunk(b"VP8L", &[1, 2, 3])].concat();
+        let mut vp8x = vec![0u8; 10];
+        vp8x[0] = VP8X_ANIMATION_FLAG;
+        let clean = webp(
+            &[
+                chunk(b"VP8X", &vp8x),
+                chunk(b"ANIM", &[0; 6]),
+                chunk(b"ANMF", &frame),
+                chunk(b"ANMF", &frame),
+            ],
+            &[],
+        );
+
+        let analysis = WebpStructureAnalyzer::analyze(clean).unwrap();
+        assert!(analysis.animated);
+        assert_eq!(analysis.frame_chunks, 2);
+        assert!(analysis.anomalies.is_empty(), "{:?}", analysis.anomalies);
+
+        let hidden_frame = [frame.clone(), chunk(b"DATA", b"secret")].concat();
+        let tampered = webp(
+            &[
+                chunk(b"VP8X", &vp8x),
+                chunk(b"ANIM", &[0; 6]),
+                chunk(b"ANMF", &hidden_frame),
+            ],
+            b"appended",
+        );
+
+        let analysis = WebpStructureAnalyzer::analyze(tampered).unwrap();
+        assert!(analysis.suspicious);
+        assert_eq!(analysis.trailing_bytes, 8);
+        assert!(analysis.anomalies.iter().any(|a| a.contains("'DATA'")));
+    }
+
+    #[test]
+    fn test_rejects_non_webp() {
+        assert!(WebpStructureAnalyzer::analyze(b"\x89PNG\r\n\x1a\n".to_vec()).is_err());
+    }
+}
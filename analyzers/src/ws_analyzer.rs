@@ -0,0 +1,236 @@
+use crate::Analyzer;
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Display;
+
+// Analyzer revision, see versions.rs
+pub const REVISION: u32 = 1;
+
+// Order-independent LSB replacement estimators. Both look at local pixel statistics
+// rather than the order bits were written in, so they still work when the payload is
+// scattered along a PRNG-seeded path instead of written sequentially.
+pub struct WsAnalyzer;
+
+// Estimated payload (bits per pixel) above which the image is flagged
+pub const PAYLOAD_RATE_THRESHOLD: f64 = 0.1;
+
+#[derive(Debug)]
+pub enum WsAnalyzerError {
+    ImageTooSmall(u32, u32),
+}
+
+impl Display for WsAnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WsAnalyzerError::ImageTooSmall(w, h) => {
+                write!(f, "Image too small for pair analysis: {}x{}", w, h)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WsAnalyzerError {}
+
+#[derive(Debug, Clone)]
+pub struct WsChannelEstimate {
+    // Payload rate from sample pair analysis (global LSB pair symmetry)
+    pub sample_pair_rate: f64,
+    // Payload rate from weighted-stego analysis
+    pub weighted_stego_rate: f64,
+}
+
+impl WsChannelEstimate {
+    pub fn payload_rate(&self) -> f64 {
+        (self.sample_pair_rate + self.weighted_stego_rate) / 2.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WsAnalysis {
+    // One estimate per R, G, B channel
+    pub channels: Vec<WsChannelEstimate>,
+    // Highest per-channel payload rate, in bits per pixel
+    pub estimated_payload_rate: f64,
+    // Fraction of LSBs actually flipped; random payload bits match the cover half the time
+    pub estimated_change_rate: f64,
+    pub suspicious: bool,
+}
+
+impl WsAnalysis {
+    pub fn is_suspicious_at(&self, payload_rate_threshold: f64) -> bool {
+        self.estimated_payload_rate > payload_rate_threshold
+    }
+}
+
+impl Analyzer for WsAnalyzer {
+    type Input = DynamicImage;
+    type Output = WsAnalysis;
+    type Error = WsAnalyzerError;
+
+    fn analyze(input: Self::Input) -> Result<Self::Output, Self::Error> {
+        let rgba = input.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width < 3 || height < 3 {
+            return Err(WsAnalyzerError::ImageTooSmall(width, height));
+        }
+
+        let channels: Vec<WsChannelEstimate> = (0..3)
+            .map(|channel| WsChannelEstimate {
+                sample_pair_rate: sample_pair_rate(&rgba, channel),
+                weighted_stego_rate: weighted_stego_rate(&rgba, channel),
+            })
+            .collect();
+
+        let estimated_payload_rate = channels
+            .iter()
+            .map(|c| c.payload_rate())
+            .fold(0.0, f64::max);
+
+        Ok(WsAnalysis {
+            channels,
+            estimated_payload_rate,
+            estimated_change_rate: estimated_payload_rate / 2.0,
+            suspicious: estimated_payload_rate > PAYLOAD_RATE_THRESHOLD,
+        })
+    }
+}
+
+// Sample pair analysis (Dumitrescu, Wu and Wang). In a clean image, horizontally
+// adjacent pairs split evenly between the X and Y trace sets; LSB replacement pushes
+// them apart by an amount that depends only on the payload rate.
+fn sample_pair_rate(image: &RgbaImage, channel: usize) -> f64 {
+    let (width, height) = image.dimensions();
+    let (mut x, mut y, mut z, mut w, mut pairs) = (0f64, 0f64, 0f64, 0f64, 0f64);
+
+    for row in 0..height {
+        for col in 0..width - 1 {
+            let u = image.get_pixel(col, row)[channel];
+            let v = image.get_pixel(col + 1, row)[channel];
+            pairs += 1.0;
+
+            if u == v {
+                z += 1.0;
+                continue;
+            }
+            if u >> 1 == v >> 1 {
+                w += 1.0;
+            }
+            if (v & 1 == 0 && u < v) || (v & 1 == 1 && u > v) {
+                x += 1.0;
+            } else {
+                y += 1.0;
+            }
+        }
+    }
+
+    // Smaller root of 0.5(W+Z)p^2 + (2X - P)p + (Y - X) = 0
+    let a = 0.5 * (w + z);
+    let b = 2.0 * x - pairs;
+    let c = y - x;
+
+    let rate = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON { 0.0 } else { -c / b }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            // No real root means the statistics are far from the model; saturate
+            1.0
+        } else {
+            let sqrt = discriminant.sqrt();
+            ((-b - sqrt) / (2.0 * a)).min((-b + sqrt) / (2.0 * a))
+        }
+    };
+
+    rate.clamp(0.0, 1.0)
+}
+
+// Weighted-stego analysis (Fridrich and Goljan, with Ker's weights). Each pixel is
+// predicted from its four neighbours; the residual correlated with the direction its
+// LSB would flip estimates the payload. Flat regions predict well and get more weight.
+fn weighted_stego_rate(image: &RgbaImage, channel: usize) -> f64 {
+    let (width, height) = image.dimensions();
+    let at = |x: u32, y: u32| image.get_pixel(x, y)[channel] as f64;
+
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+
+    for row in 1..height - 1 {
+        for col in 1..width - 1 {
+            let neighbours = [
+                at(col - 1, row),
+                at(col + 1, row),
+                at(col, row - 1),
+                at(col, row + 1),
+            ];
+            let prediction = neighbours.iter().sum::<f64>() / 4.0;
+            let variance = neighbours
+                .iter()
+                .map(|n| (n - prediction).powi(2))
+                .sum::<f64>()
+                / 4.0;
+            let weight = 1.0 / (5.0 + variance);
+
+            let value = at(col, row);
+            // value - value_with_lsb_flipped: +1 for odd values, -1 for even ones
+            let flip_direction = if value as u8 % 2 == 1 { 1.0 } else { -1.0 };
+
+            weighted_sum += weight * flip_direction * (value - prediction);
+            weight_total += weight;
+        }
+    }
+
+    if weight_total == 0.0 {
+        return 0.0;
+    }
+
+    (2.0 * weighted_sum / weight_total).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use image::Rgba;
+
+    fn smooth_image(rng: &mut XorShift) -> RgbaImage {
+        RgbaImage::from_fn(128, 128, |x, y| {
+            let base = 60.0 + 40.0 * ((x as f64) / 20.0).sin() + 30.0 * ((y as f64) / 15.0).cos();
+            let noise = (rng.next() % 3) as f64;
+            let v = (base + noise) as u8;
+            Rgba([v, v.saturating_add(20), v.saturating_add(40), 255])
+        })
+    }
+
+    #[test]
+    fn test_scattered_embedding_raises_estimate() {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        let cover = smooth_image(&mut rng);
+        let clean = WsAnalyzer::analyze(DynamicImage::ImageRgba8(cover.clone())).unwrap();
+
+        // Replace the LSB of a randomly chosen 60% of pixels with random bits
+        let mut stego = cover;
+        for pixel in stego.pixels_mut() {
+            if rng.next() % 100 < 60 {
+                for channel in 0..3 {
+                    pixel[channel] = (pixel[channel] & !1) | rng.bit();
+                }
+            }
+        }
+        let dirty = WsAnalyzer::analyze(DynamicImage::ImageRgba8(stego)).unwrap();
+
+        assert!(
+            !clean.suspicious,
+            "clean estimate {}",
+            clean.estimated_payload_rate
+        );
+        assert!(
+            dirty.suspicious,
+            "stego estimate {}",
+            dirty.estimated_payload_rate
+        );
+        assert!(
+            (dirty.estimated_payload_rate - 0.6).abs() < 0.2,
+            "stego estimate {}",
+            dirty.estimated_payload_rate
+        );
+    }
+}
@@ -10,11 +10,15 @@ use symphonia::core::probe::Hint;
 
 pub struct AudioParser;
 
+// Used when the container does not declare a sample rate
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
 #[derive(Debug)]
 pub enum AudioParserError {
     IO(std::io::Error),
     Symphonia(String),
     Decode(String),
+    Encode(String),
 }
 
 impl Display for AudioParserError {
@@ -23,6 +27,7 @@ impl Display for AudioParserError {
             AudioParserError::IO(e) => write!(f, "IO error: {}", e),
             AudioParserError::Symphonia(e) => write!(f, "Symphonia error: {}", e),
             AudioParserError::Decode(e) => write!(f, "Decode error: {}", e),
+            AudioParserError::Encode(e) => write!(f, "Encode error: {}", e),
         }
     }
 }
@@ -40,6 +45,17 @@ impl Parser for AudioParser {
     type Error = AudioParserError;
 
     fn parse_path<P>(file_path: &P) -> Result<Self::Output, Self::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let (samples, _sample_rate) = Self::parse_with_sample_rate(file_path)?;
+        Ok(samples)
+    }
+}
+
+impl AudioParser {
+    // Decode the first channel to normalized f32 samples and report the source sample rate
+    pub fn parse_with_sample_rate<P>(file_path: &P) -> Result<(Vec<f32>, u32), AudioParserError>
     where
         P: AsRef<Path>,
     {
@@ -73,6 +89,10 @@ impl Parser for AudioParser {
             .map_err(|e| AudioParserError::Decode(format!("{:?}", e)))?;
 
         let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .unwrap_or(DEFAULT_SAMPLE_RATE);
         let mut samples = Vec::new();
 
         loop {
@@ -156,7 +176,38 @@ impl Parser for AudioParser {
             }
         }
 
-        Ok(samples)
+        Ok((samples, sample_rate))
+    }
+
+    // Write samples exactly as the analyzers consume them (mono, 32-bit float) so
+    // findings can be reproduced in external tools
+    pub fn export_wav<P>(
+        samples: &[f32],
+        sample_rate: u32,
+        file_path: &P,
+    ) -> Result<(), AudioParserError>
+    where
+        P: AsRef<Path>,
+    {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        let mut writer = hound::WavWriter::create(file_path.as_ref(), spec)
+            .map_err(|e| AudioParserError::Encode(format!("{:?}", e)))?;
+        for &sample in samples {
+            writer
+                .write_sample(sample)
+                .map_err(|e| AudioParserError::Encode(format!("{:?}", e)))?;
+        }
+        writer
+            .finalize()
+            .map_err(|e| AudioParserError::Encode(format!("{:?}", e)))?;
+
+        Ok(())
     }
 }
 
@@ -170,4 +221,18 @@ mod tests {
         // Just verify the parser compiles
         assert!(true);
     }
+
+    #[test]
+    fn test_export_wav_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.wav");
+        let samples: Vec<f32> = (0..4800).map(|i| (i as f32 / 100.0).sin() * 0.5).collect();
+
+        AudioParser::export_wav(&samples, 48000, &path).unwrap();
+        let (decoded, sample_rate) = AudioParser::parse_with_sample_rate(&path).unwrap();
+
+        assert_eq!(sample_rate, 48000);
+        assert_eq!(decoded.len(), samples.len());
+        assert!((decoded[123] - samples[123]).abs() < 1e-6);
+    }
 }
@@ -3,6 +3,10 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path};
 
+use zune_jpeg::JpegDecoder;
+use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
+
 use crate::Parser;
 
 pub struct ImageParser;
@@ -11,6 +15,49 @@ pub struct ImageParser;
 pub enum ImageParserError {
     IO(std::io::Error),
     Parse(image::error::ImageError),
+    Jpeg(String),
+}
+
+// Color model the file stores its samples in, before any conversion to RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorModel {
+    Grayscale,
+    Rgb,
+    Cmyk,
+    Ycck,
+}
+
+impl ColorModel {
+    pub fn channel_names(&self) -> &'static [&'static str] {
+        match self {
+            ColorModel::Grayscale => &["Gray"],
+            ColorModel::Rgb => &["Red", "Green", "Blue"],
+            ColorModel::Cmyk => &["Cyan", "Magenta", "Yellow", "Key"],
+            ColorModel::Ycck => &["Y", "Cb", "Cr", "Key"],
+        }
+    }
+}
+
+impl Display for ColorModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorModel::Grayscale => "Grayscale",
+            ColorModel::Rgb => "RGB",
+            ColorModel::Cmyk => "CMYK",
+            ColorModel::Ycck => "YCCK",
+        };
+        f.write_str(name)
+    }
+}
+
+// Four-channel JPEG samples as stored in the file; `image` only hands out the RGB
+// conversion, which mixes the K channel into every output channel
+pub struct NativeJpeg {
+    pub color_model: ColorModel,
+    pub width: u32,
+    pub height: u32,
+    // Interleaved samples in `color_model.channel_names()` order
+    pub data: Vec<u8>,
 }
 
 impl Display for ImageParserError {
@@ -31,6 +78,64 @@ impl From<image::error::ImageError> for ImageParserError {
     }
 }
 
+impl ImageParser {
+    pub fn color_model(image: &image::DynamicImage) -> ColorModel {
+        if image.color().has_color() {
+            ColorModel::Rgb
+        } else {
+            ColorModel::Grayscale
+        }
+    }
+
+    // Decode a CMYK or YCCK JPEG without color conversion. Returns None for any other
+    // file, which should go through `parse_path` as usual.
+    pub fn parse_native_jpeg<P>(file_path: &P) -> Result<Option<NativeJpeg>, ImageParserError>
+    where
+        P: AsRef<Path> + ?Sized,
+    {
+        let data = std::fs::read(file_path)?;
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Ok(None);
+        }
+
+        let mut decoder = JpegDecoder::new(data.as_slice());
+        decoder
+            .decode_headers()
+            .map_err(|e| ImageParserError::Jpeg(format!("{:?}", e)))?;
+        let (color_model, colorspace) = match decoder.get_input_colorspace() {
+            Some(ColorSpace::CMYK) => (ColorModel::Cmyk, ColorSpace::CMYK),
+            Some(ColorSpace::YCCK) => (ColorModel::Ycck, ColorSpace::YCCK),
+            _ => return Ok(None),
+        };
+
+        // Asking for the input colorspace makes the decoder copy samples through as-is
+        let options = DecoderOptions::default().jpeg_set_out_colorspace(colorspace);
+        let mut decoder = JpegDecoder::new_with_options(data.as_slice(), options);
+        let samples = decoder
+            .decode()
+            .map_err(|e| ImageParserError::Jpeg(format!("{:?}", e)))?;
+        let (width, height) = decoder
+            .info()
+            .map(|info| (info.width as u32, info.height as u32))
+            .unwrap_or_default();
+
+        Ok(Some(NativeJpeg {
+            color_model,
+            width,
+            height,
+            data: samples,
+        }))
+    }
+
+    // Bits per channel of a decoded image. `image::load` keeps 16-bit PNG/TIFF and
+    // float HDR data at full precision, so analyzers should check this before
+    // converting down to 8 bits.
+    pub fn channel_bit_depth(image: &image::DynamicImage) -> u8 {
+        let color = image.color();
+        color.bytes_per_pixel() / color.channel_count() * 8
+    }
+}
+
 impl Parser for ImageParser {
     type Output = image::DynamicImage;
 
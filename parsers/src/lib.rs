@@ -1,7 +1,9 @@
 pub mod audio_parser;
 pub mod image_parser;
+pub mod multi_image_parser;
 pub mod text_parser;
 pub mod video_parser;
+pub mod webp_parser;
 use std::path::Path;
 
 pub trait Parser {
@@ -0,0 +1,531 @@
+use std::fmt::Display;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::DynamicImage;
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+
+use crate::Parser;
+
+// Enumerates every sub-image of a container that can hold more than one: the IFDs of a
+// multi-page TIFF, the entries of an ICO/CUR directory and the image items of a HEIF/AVIF.
+// ImageParser goes through `image::load`, which only ever returns the first (or, for ICO,
+// the largest) of them.
+pub struct MultiImageParser;
+
+#[derive(Debug)]
+pub enum MultiImageParserError {
+    IO(std::io::Error),
+    Tiff(tiff::TiffError),
+    // Not a TIFF, ICO or HEIF file
+    Unsupported,
+}
+
+impl Display for MultiImageParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl From<std::io::Error> for MultiImageParserError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl From<tiff::TiffError> for MultiImageParserError {
+    fn from(value: tiff::TiffError) -> Self {
+        Self::Tiff(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiImageFormat {
+    Tiff,
+    Ico,
+    Heif,
+}
+
+impl Display for MultiImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MultiImageFormat::Tiff => "TIFF",
+            MultiImageFormat::Ico => "ICO",
+            MultiImageFormat::Heif => "HEIF",
+        };
+        f.write_str(name)
+    }
+}
+
+pub struct ImagePage {
+    pub index: usize,
+    // Where the page came from, e.g. "IFD 2" or "item 3 (hvc1, thumbnail of item 1)"
+    pub label: String,
+    // Stored in the file but never shown by a viewer: HEIF image items that are not the
+    // primary item and not referenced as a thumbnail, auxiliary image or grid tile
+    pub hidden: bool,
+    // Why the page couldn't be decoded, e.g. an unsupported color type or HEVC coding
+    pub image: Result<DynamicImage, String>,
+}
+
+pub struct MultiImage {
+    pub format: MultiImageFormat,
+    pub pages: Vec<ImagePage>,
+}
+
+// HEIF/AVIF brands in the ftyp box
+const HEIF_BRANDS: [&[u8; 4]; 10] = [
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1", b"avif", b"avis",
+];
+
+// HEIF item types that hold pictures, as opposed to Exif, XMP and other metadata items
+const HEIF_IMAGE_ITEMS: [&[u8; 4]; 9] = [
+    b"hvc1", b"av01", b"avc1", b"jpeg", b"j2k1", b"unci", b"grid", b"iovl", b"iden",
+];
+
+impl MultiImageParser {
+    pub fn detect(data: &[u8]) -> Option<MultiImageFormat> {
+        if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+            return Some(MultiImageFormat::Tiff);
+        }
+        // Type 1 is an icon, type 2 a cursor; both need at least one entry
+        if data.len() >= 6
+            && data[0..2] == [0, 0]
+            && matches!(data[2..4], [1, 0] | [2, 0])
+            && read_u16_le(data, 4) > 0
+        {
+            return Some(MultiImageFormat::Ico);
+        }
+        if data.len() >= 12 && &data[4..8] == b"ftyp" {
+            let size = (read_u32_be(data, 0) as usize).clamp(12, data.len());
+            let major = data[8..12].try_into().ok()?;
+            let compatible = data[16.min(size)..size].chunks_exact(4);
+            let mut brands = std::iter::once(major).chain(compatible);
+            if brands.any(|brand| HEIF_BRANDS.iter().any(|known| known.as_slice() == brand)) {
+                return Some(MultiImageFormat::Heif);
+            }
+        }
+        None
+    }
+
+    pub fn parse_bytes(data: &[u8]) -> Result<MultiImage, MultiImageParserError> {
+        let format = Self::detect(data).ok_or(MultiImageParserError::Unsupported)?;
+        let pages = match format {
+            MultiImageFormat::Tiff => tiff_pages(data)?,
+            MultiImageFormat::Ico => ico_pages(data),
+            MultiImageFormat::Heif => heif_pages(data),
+        };
+        Ok(MultiImage { format, pages })
+    }
+}
+
+impl Parser for MultiImageParser {
+    type Output = MultiImage;
+
+    type Error = MultiImageParserError;
+
+    fn parse_path<P>(file_path: &P) -> Result<Self::Output, Self::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse_bytes(&std::fs::read(file_path)?)
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn tiff_pages(data: &[u8]) -> Result<Vec<ImagePage>, MultiImageParserError> {
+    let mut decoder = Decoder::new(Cursor::new(data))?;
+    let mut pages = Vec::new();
+    loop {
+        let index = pages.len();
+        pages.push(ImagePage {
+            index,
+            label: format!("IFD {}", index),
+            hidden: false,
+            image: decode_tiff_page(&mut decoder),
+        });
+        if !decoder.more_images() {
+            break;
+        }
+        // A broken IFD chain ends the page list; the pages read so far are still useful
+        if decoder.next_image().is_err() {
+            break;
+        }
+    }
+    Ok(pages)
+}
+
+fn decode_tiff_page(decoder: &mut Decoder<Cursor<&[u8]>>) -> Result<DynamicImage, String> {
+    let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+    let color = decoder.colortype().map_err(|e| e.to_string())?;
+    let samples = decoder.read_image().map_err(|e| e.to_string())?;
+    let too_short = || {
+        format!(
+            "{:?} page has fewer samples than {}x{}",
+            color, width, height
+        )
+    };
+
+    let image = match (color, samples) {
+        (ColorType::Gray(8), DecodingResult::U8(s)) => {
+            image::GrayImage::from_raw(width, height, s).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(s)) => {
+            image::GrayAlphaImage::from_raw(width, height, s).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(s)) => {
+            image::RgbImage::from_raw(width, height, s).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(s)) => {
+            image::RgbaImage::from_raw(width, height, s).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(s)) => {
+            image::ImageBuffer::from_raw(width, height, s).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(s)) => {
+            image::ImageBuffer::from_raw(width, height, s).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(s)) => {
+            image::ImageBuffer::from_raw(width, height, s).map(DynamicImage::ImageRgba16)
+        }
+        (other, _) => return Err(format!("Unsupported TIFF color type {:?}", other)),
+    };
+    image.ok_or_else(too_short)
+}
+
+// ICO directory entries are 16 bytes after a 6-byte header; each points at a PNG or a
+// headerless BMP. Every entry is decoded on its own by wrapping it in a one-entry ICO,
+// so `image` can't pick a different one.
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+
+fn ico_pages(data: &[u8]) -> Vec<ImagePage> {
+    let count = read_u16_le(data, 4) as usize;
+    let mut pages = Vec::new();
+    for index in 0..count {
+        let entry = ICO_HEADER_SIZE + index * ICO_ENTRY_SIZE;
+        if entry + ICO_ENTRY_SIZE > data.len() {
+            break;
+        }
+        // 0 means 256 pixels
+        let dimension = |b: u8| if b == 0 { 256 } else { b as u32 };
+        let (width, height) = (dimension(data[entry]), dimension(data[entry + 1]));
+        let size = read_u32_le(data, entry + 8) as usize;
+        let offset = read_u32_le(data, entry + 12) as usize;
+
+        let image = match data.get(offset..offset.saturating_add(size)) {
+            Some(payload) => {
+                let mut single = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE + size);
+                single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+                single.extend_from_slice(&data[entry..entry + 12]);
+                single
+                    .extend_from_slice(&((ICO_HEADER_SIZE + ICO_ENTRY_SIZE) as u32).to_le_bytes());
+                single.extend_from_slice(payload);
+                image::load_from_memory_with_format(&single, image::ImageFormat::Ico)
+                    .map_err(|e| e.to_string())
+            }
+            None => Err(format!(
+                "Entry data at offset {} ({} bytes) runs past the end of the file",
+                offset, size
+            )),
+        };
+
+        let coding = if data.get(offset..offset + 4) == Some(b"\x89PNG".as_slice()) {
+            "PNG"
+        } else {
+            "BMP"
+        };
+        pages.push(ImagePage {
+            index,
+            label: format!("entry {} ({}x{} {})", index, width, height, coding),
+            hidden: false,
+            image,
+        });
+    }
+    pages
+}
+
+// ISO-BMFF box: (type, payload start, end)
+fn boxes(data: &[u8], start: usize, end: usize) -> Vec<([u8; 4], usize, usize)> {
+    let mut found = Vec::new();
+    let mut offset = start;
+    while offset + 8 <= end {
+        let size = read_u32_be(data, offset) as usize;
+        let box_type: [u8; 4] = data[offset + 4..offset + 8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, end - offset),
+            1 if offset + 16 <= end => (
+                16,
+                u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap()) as usize,
+            ),
+            _ => (8, size),
+        };
+        if size < header || offset.saturating_add(size) > end {
+            break;
+        }
+        found.push((box_type, offset + header, offset + size));
+        offset += size;
+    }
+    found
+}
+
+// Reads item IDs that are 16 bits in version 0 boxes and 32 bits otherwise
+struct ItemIdReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    end: usize,
+    wide: bool,
+}
+
+impl ItemIdReader<'_> {
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.data.get(self.offset..self.offset + 2)?;
+        (self.offset + 2 <= self.end).then_some(())?;
+        self.offset += 2;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn id(&mut self) -> Option<u32> {
+        if !self.wide {
+            return self.u16().map(u32::from);
+        }
+        (self.offset + 4 <= self.end).then_some(())?;
+        let id = read_u32_be(self.data, self.offset);
+        self.offset += 4;
+        Some(id)
+    }
+}
+
+fn heif_pages(data: &[u8]) -> Vec<ImagePage> {
+    let Some(&(_, meta_start, meta_end)) = boxes(data, 0, data.len())
+        .iter()
+        .find(|(box_type, ..)| box_type == b"meta")
+    else {
+        return Vec::new();
+    };
+
+    let mut primary = None;
+    // (item ID, item type)
+    let mut items: Vec<(u32, [u8; 4])> = Vec::new();
+    // (reference type, from item, to items)
+    let mut references: Vec<([u8; 4], u32, Vec<u32>)> = Vec::new();
+
+    // meta is a full box: skip its version and flags
+    for (box_type, start, end) in boxes(data, meta_start + 4, meta_end) {
+        if start + 4 > end {
+            continue;
+        }
+        let version = data[start];
+        match &box_type {
+            b"pitm" => {
+                let mut reader = ItemIdReader {
+                    data,
+                    offset: start + 4,
+                    end,
+                    wide: version != 0,
+                };
+                primary = reader.id();
+            }
+            b"iinf" => {
+                let entries_at = start + 4 + if version == 0 { 2 } else { 4 };
+                for (entry_type, entry_start, entry_end) in boxes(data, entries_at, end) {
+                    // Only infe version 2 and 3 carry an item type
+                    let infe_version = data.get(entry_start).copied().unwrap_or(0);
+                    if entry_type != *b"infe" || infe_version < 2 {
+                        continue;
+                    }
+                    let mut reader = ItemIdReader {
+                        data,
+                        offset: entry_start + 4,
+                        end: entry_end,
+                        wide: infe_version == 3,
+                    };
+                    let (Some(id), Some(_protection)) = (reader.id(), reader.u16()) else {
+                        continue;
+                    };
+                    if let Some(item_type) = data
+                        .get(reader.offset..reader.offset + 4)
+                        .filter(|_| reader.offset + 4 <= entry_end)
+                    {
+                        items.push((id, item_type.try_into().unwrap()));
+                    }
+                }
+            }
+            b"iref" => {
+                for (ref_type, ref_start, ref_end) in boxes(data, start + 4, end) {
+                    let mut reader = ItemIdReader {
+                        data,
+                        offset: ref_start,
+                        end: ref_end,
+                        wide: version != 0,
+                    };
+                    let (Some(from), Some(count)) = (reader.id(), reader.u16()) else {
+                        continue;
+                    };
+                    let to = (0..count).map_while(|_| reader.id()).collect();
+                    references.push((ref_type, from, to));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut pages = Vec::new();
+    for (id, item_type) in items
+        .iter()
+        .filter(|(_, item_type)| HEIF_IMAGE_ITEMS.contains(&item_type))
+    {
+        let role = if primary == Some(*id) {
+            Some("primary".to_string())
+        } else {
+            references
+                .iter()
+                .find_map(|(ref_type, from, to)| match ref_type {
+                    b"thmb" if from == id => Some(format!("thumbnail of item {}", to.first()?)),
+                    b"auxl" if from == id => {
+                        Some(format!("auxiliary image of item {}", to.first()?))
+                    }
+                    b"dimg" if to.contains(id) => Some(format!("tile of item {}", from)),
+                    b"base" if to.contains(id) => Some(format!("base of item {}", from)),
+                    _ => None,
+                })
+        };
+
+        let item_type = String::from_utf8_lossy(item_type);
+        pages.push(ImagePage {
+            index: pages.len(),
+            label: format!(
+                "item {} ({}, {})",
+                id,
+                item_type,
+                role.as_deref().unwrap_or("unreferenced")
+            ),
+            hidden: role.is_none(),
+            image: Err(format!("{} coded items are not decoded", item_type)),
+        });
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    #[test]
+    fn test_tiff_pages() {
+        let mut file = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut file).unwrap();
+        encoder
+            .write_image::<colortype::RGB8>(4, 2, &[7u8; 4 * 2 * 3])
+            .unwrap();
+        encoder
+            .write_image::<colortype::Gray8>(3, 3, &[1u8; 9])
+            .unwrap();
+        encoder
+            .write_image::<colortype::CMYK8>(1, 1, &[0u8; 4])
+            .unwrap();
+
+        let parsed = MultiImageParser::parse_bytes(file.get_ref()).unwrap();
+        assert_eq!(parsed.format, MultiImageFormat::Tiff);
+        assert_eq!(parsed.pages.len(), 3);
+        assert_eq!(parsed.pages[1].label, "IFD 1");
+        let second = parsed.pages[1].image.as_ref().unwrap();
+        assert_eq!((second.width(), second.height()), (3, 3));
+        assert!(parsed.pages[2].image.is_err());
+    }
+
+    #[test]
+    fn test_ico_pages() {
+        let png = |size: u32| {
+            let mut bytes = Vec::new();
+            DynamicImage::new_rgba8(size, size)
+                .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let (small, large) = (png(16), png(32));
+
+        let mut ico = vec![0, 0, 1, 0, 2, 0];
+        let mut offset = ICO_HEADER_SIZE + 2 * ICO_ENTRY_SIZE;
+        for (size, payload) in [(16u8, &small), (32u8, &large)] {
+            ico.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0]);
+            ico.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += payload.len();
+        }
+        ico.extend_from_slice(&small);
+        ico.extend_from_slice(&large);
+
+        let parsed = MultiImageParser::parse_bytes(&ico).unwrap();
+        assert_eq!(parsed.format, MultiImageFormat::Ico);
+        assert_eq!(parsed.pages[0].label, "entry 0 (16x16 PNG)");
+        // image's own ICO decoder would only ever return the 32x32 entry
+        assert_eq!(parsed.pages[0].image.as_ref().unwrap().width(), 16);
+        assert_eq!(parsed.pages[1].image.as_ref().unwrap().width(), 32);
+    }
+
+    fn bmff_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut b = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(box_type);
+        b.extend_from_slice(payload);
+        b
+    }
+
+    fn infe(id: u16, item_type: &[u8; 4]) -> Vec<u8> {
+        let mut payload = vec![2, 0, 0, 0];
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&[0, 0]);
+        payload.extend_from_slice(item_type);
+        payload.push(0);
+        bmff_box(b"infe", &payload)
+    }
+
+    #[test]
+    fn test_heif_item_roles() {
+        let mut iinf = vec![0, 0, 0, 0, 0, 4];
+        for (id, item_type) in [(1, b"hvc1"), (2, b"hvc1"), (3, b"Exif"), (4, b"hvc1")] {
+            iinf.extend(infe(id, item_type));
+        }
+        // Item 2 is the thumbnail of item 1; item 4 is referenced by nothing
+        let thmb = bmff_box(b"thmb", &[0, 2, 0, 1, 0, 1]);
+        let mut iref = vec![0, 0, 0, 0];
+        iref.extend(thmb);
+
+        let mut meta = vec![0, 0, 0, 0];
+        meta.extend(bmff_box(b"pitm", &[0, 0, 0, 0, 0, 1]));
+        meta.extend(bmff_box(b"iinf", &iinf));
+        meta.extend(bmff_box(b"iref", &iref));
+
+        let mut file = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        file.extend(bmff_box(b"meta", &meta));
+
+        let parsed = MultiImageParser::parse_bytes(&file).unwrap();
+        assert_eq!(parsed.format, MultiImageFormat::Heif);
+        let labels: Vec<&str> = parsed.pages.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "item 1 (hvc1, primary)",
+                "item 2 (hvc1, thumbnail of item 1)",
+                "item 4 (hvc1, unreferenced)"
+            ]
+        );
+        assert_eq!(
+            parsed.pages.iter().map(|p| p.hidden).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+    }
+}
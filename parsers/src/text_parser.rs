@@ -1,7 +1,7 @@
 use crate::Parser;
 use std::fmt::Display;
 use std::fs;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::path::Path;
 
 pub struct TextParser;
@@ -11,6 +11,8 @@ pub enum TextParserError {
     IO(std::io::Error),
     Pdf(String),
     Docx(String),
+    Doc(String),
+    Epub(String),
     Unsupported(String),
 }
 
@@ -20,6 +22,8 @@ impl Display for TextParserError {
             TextParserError::IO(e) => write!(f, "IO error: {}", e),
             TextParserError::Pdf(e) => write!(f, "PDF parsing error: {}", e),
             TextParserError::Docx(e) => write!(f, "DOCX parsing error: {}", e),
+            TextParserError::Doc(e) => write!(f, "DOC parsing error: {}", e),
+            TextParserError::Epub(e) => write!(f, "EPUB parsing error: {}", e),
             TextParserError::Unsupported(e) => write!(f, "Unsupported format: {}", e),
         }
     }
@@ -86,10 +90,12 @@ impl Parser for TextParser {
 
         match extension.as_str() {
             "pdf" => parse_pdf(path),
-            "docx" => parse_docx(path),
-            "doc" => parse_doc(path),
+            "docx" | "docm" | "dotx" | "dotm" => parse_docx(path),
+            "doc" | "dot" => parse_doc(path),
             "rtf" => parse_rtf(path),
             "odt" => parse_odt(path),
+            "epub" => parse_epub(path),
+            "chm" => parse_chm(path),
             _ => parse_plain_text(path, &extension),
         }
     }
@@ -136,20 +142,122 @@ fn parse_docx(path: &Path) -> Result<TextContent, TextParserError> {
 }
 
 fn parse_doc(path: &Path) -> Result<TextContent, TextParserError> {
-    // .doc files (old Word format) are complex binary format
-    // Try to extract as much readable text as possible
     let bytes = fs::read(path)?;
 
-    // Try to extract ASCII/UTF-8 strings from binary
-    let text = extract_strings_from_binary(&bytes);
+    match word_document_text(&bytes) {
+        Ok(text) => Ok(TextContent::new(text, "DOC".to_string())),
+        // Word 6/95 files and files that only carry the extension have no piece table to
+        // follow; what reads as text is still worth having
+        Err(e) => {
+            let text = extract_strings_from_binary(&bytes);
+            if text.is_empty() {
+                Err(TextParserError::Doc(e))
+            } else {
+                Ok(TextContent::new(text, "DOC (partial)".to_string()))
+            }
+        }
+    }
+}
 
-    if text.is_empty() {
-        Err(TextParserError::Unsupported(
-            "Legacy .doc format requires external tool (antiword/catdoc)".to_string(),
-        ))
+// Word 97-2003 keeps the document text in the WordDocument stream of an OLE2 compound file,
+// split into pieces of 8-bit or UTF-16 characters. The piece table lives in the table stream
+// the FIB names, at the offset the FIB gives for the CLX.
+fn word_document_text(bytes: &[u8]) -> Result<String, String> {
+    let mut file = cfb::CompoundFile::open(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut read_stream = |name: &str| -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        file.open_stream(name)
+            .and_then(|mut stream| stream.read_to_end(&mut data))
+            .map_err(|e| format!("{}: {}", name, e))?;
+        Ok(data)
+    };
+    let word = read_stream("/WordDocument")?;
+
+    let u16_at = |data: &[u8], at: usize| {
+        data.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |data: &[u8], at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+    if u16_at(&word, 0) != Some(0xA5EC) {
+        return Err("not a Word 97-2003 document".to_string());
+    }
+    let flags = u16_at(&word, 0x0A).unwrap_or_default();
+    if flags & 0x0100 != 0 {
+        return Err("document is encrypted".to_string());
+    }
+    let table = read_stream(if flags & 0x0200 != 0 {
+        "/1Table"
     } else {
-        Ok(TextContent::new(text, "DOC (partial)".to_string()))
+        "/0Table"
+    })?;
+    let (Some(fc_clx), Some(lcb_clx)) = (u32_at(&word, 0x01A2), u32_at(&word, 0x01A6)) else {
+        return Err("FIB too short".to_string());
+    };
+    let clx = table
+        .get(fc_clx..fc_clx + lcb_clx)
+        .ok_or("piece table outside the table stream")?;
+
+    // Property modifiers come first, then the piece table itself
+    let mut pos = 0;
+    while clx.get(pos) == Some(&0x01) {
+        pos += 3 + u16_at(clx, pos + 1).ok_or("truncated CLX")? as usize;
+    }
+    if clx.get(pos) != Some(&0x02) {
+        return Err("no piece table in the CLX".to_string());
+    }
+    let lcb = u32_at(clx, pos + 1).ok_or("truncated CLX")?;
+    let plc = clx
+        .get(pos + 5..pos + 5 + lcb)
+        .ok_or("truncated piece table")?;
+    let pieces = lcb.saturating_sub(4) / 12;
+
+    let mut text = String::new();
+    for i in 0..pieces {
+        let (Some(start_cp), Some(end_cp), Some(fc)) = (
+            u32_at(plc, i * 4),
+            u32_at(plc, (i + 1) * 4),
+            u32_at(plc, (pieces + 1) * 4 + i * 8 + 2),
+        ) else {
+            break;
+        };
+        let chars = end_cp.saturating_sub(start_cp);
+        if fc & 0x4000_0000 != 0 {
+            let start = (fc & 0x3FFF_FFFF) / 2;
+            let piece = word
+                .get(start..start + chars)
+                .ok_or("piece outside the stream")?;
+            text.push_str(&encoding_rs::WINDOWS_1252.decode(piece).0);
+        } else {
+            let piece = word
+                .get(fc..fc + chars * 2)
+                .ok_or("piece outside the stream")?;
+            text.push_str(&encoding_rs::UTF_16LE.decode(piece).0);
+        }
+    }
+    Ok(word_plain_text(&text))
+}
+
+// Paragraph, cell and page marks become line breaks and tabs; field instructions are dropped
+// and their results kept
+fn word_plain_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut instruction_depth = 0;
+    for ch in text.chars() {
+        match ch {
+            '\u{13}' => instruction_depth += 1,
+            '\u{14}' => instruction_depth = 0,
+            '\u{15}' => {}
+            _ if instruction_depth > 0 => {}
+            '\r' | '\u{b}' | '\u{c}' => result.push('\n'),
+            '\u{7}' => result.push('\t'),
+            ch if ch.is_control() && ch != '\t' => {}
+            ch => result.push(ch),
+        }
     }
+    result
 }
 
 fn parse_rtf(path: &Path) -> Result<TextContent, TextParserError> {
@@ -307,6 +415,162 @@ fn parse_odt(path: &Path) -> Result<TextContent, TextParserError> {
     Ok(TextContent::new(text, "ODT".to_string()))
 }
 
+// Chapters in reading order: the spine of the package document META-INF/container.xml names,
+// each XHTML file reduced to its text
+fn parse_epub(path: &Path) -> Result<TextContent, TextParserError> {
+    use std::collections::HashMap;
+    use zip::ZipArchive;
+
+    let mut archive = ZipArchive::new(fs::File::open(path)?)
+        .map_err(|e| TextParserError::Epub(format!("Not a valid EPUB file: {}", e)))?;
+    let mut read = |name: &str| -> Result<String, TextParserError> {
+        let mut member = archive
+            .by_name(name)
+            .map_err(|e| TextParserError::Epub(format!("Missing {}: {}", name, e)))?;
+        let mut bytes = Vec::new();
+        member.read_to_end(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    };
+
+    let container = read("META-INF/container.xml")?;
+    let opf_path = xml_elements(&container, b"rootfile")
+        .into_iter()
+        .find_map(|mut attributes| attributes.remove("full-path"))
+        .ok_or_else(|| TextParserError::Epub("No package document".to_string()))?;
+    let opf = read(&opf_path)?;
+    let base = opf_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+    let manifest: HashMap<String, String> = xml_elements(&opf, b"item")
+        .into_iter()
+        .filter_map(|mut item| Some((item.remove("id")?, item.remove("href")?)))
+        .collect();
+    let mut text = String::new();
+    for idref in xml_elements(&opf, b"itemref")
+        .into_iter()
+        .filter_map(|mut itemref| itemref.remove("idref"))
+    {
+        let Some(href) = manifest.get(&idref) else {
+            continue;
+        };
+        let href = href
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .replace("%20", " ");
+        let member = if base.is_empty() {
+            href
+        } else {
+            format!("{}/{}", base, href)
+        };
+        // A chapter the spine lists but the package lacks leaves the rest readable
+        if let Ok(chapter) = read(&member) {
+            text.push_str(&markup_text(&chapter));
+            text.push('\n');
+        }
+    }
+
+    Ok(TextContent::new(text, "EPUB".to_string()))
+}
+
+// Attributes of every element with the given local name
+fn xml_elements(xml: &str, name: &[u8]) -> Vec<std::collections::HashMap<String, String>> {
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    let mut reader = Reader::from_str(xml);
+    let mut elements = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == name => {
+                elements.push(
+                    e.attributes()
+                        .flatten()
+                        .filter_map(|a| {
+                            let key =
+                                String::from_utf8_lossy(a.key.local_name().as_ref()).to_string();
+                            Some((key, a.unescape_value().ok()?.to_string()))
+                        })
+                        .collect(),
+                );
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    elements
+}
+
+// Text of an XHTML document, one line per block element; scripts and styles are dropped
+fn markup_text(xhtml: &str) -> String {
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    const BLOCKS: [&[u8]; 12] = [
+        b"p",
+        b"div",
+        b"br",
+        b"li",
+        b"tr",
+        b"h1",
+        b"h2",
+        b"h3",
+        b"h4",
+        b"h5",
+        b"h6",
+        b"blockquote",
+    ];
+    let mut reader = Reader::from_str(xhtml);
+    let mut text = String::new();
+    let mut skip_depth = 0;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) if matches!(e.local_name().as_ref(), b"script" | b"style") => {
+                skip_depth += 1;
+            }
+            Ok(Event::End(e)) if matches!(e.local_name().as_ref(), b"script" | b"style") => {
+                skip_depth -= 1;
+            }
+            Ok(Event::End(e)) if BLOCKS.contains(&e.local_name().as_ref()) => text.push('\n'),
+            Ok(Event::Empty(e)) if e.local_name().as_ref() == b"br" => text.push('\n'),
+            Ok(Event::Text(e)) if skip_depth == 0 => {
+                text.push_str(&String::from_utf8_lossy(&e.into_inner()));
+            }
+            Ok(Event::GeneralRef(e)) if skip_depth == 0 => {
+                let resolved = match e.resolve_char_ref() {
+                    Ok(Some(c)) => Some(c),
+                    _ => match e.as_ref() {
+                        b"amp" => Some('&'),
+                        b"lt" => Some('<'),
+                        b"gt" => Some('>'),
+                        b"quot" => Some('"'),
+                        b"apos" => Some('\''),
+                        b"nbsp" => Some(' '),
+                        _ => None,
+                    },
+                };
+                text.extend(resolved);
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    text
+}
+
+// CHM pages are almost always LZX-compressed, which isn't decoded; what reads as text in the
+// stored parts is still worth having
+fn parse_chm(path: &Path) -> Result<TextContent, TextParserError> {
+    let bytes = fs::read(path)?;
+    let text = extract_strings_from_binary(&bytes);
+    if text.trim().is_empty() {
+        Err(TextParserError::Unsupported(
+            "No readable text in the CHM file".to_string(),
+        ))
+    } else {
+        Ok(TextContent::new(text, "CHM (partial)".to_string()))
+    }
+}
+
 fn parse_plain_text(path: &Path, extension: &str) -> Result<TextContent, TextParserError> {
     // First try UTF-8
     if let Ok(content) = fs::read_to_string(path) {
@@ -396,6 +660,43 @@ mod tests {
         assert!(result.content.contains("Hello World"));
     }
 
+    #[test]
+    fn test_parse_doc_follows_the_piece_table() {
+        // One compressed piece at byte 0x800 of the WordDocument stream
+        let body = b"Hello \x13 HYPERLINK x \x14Word\x15\rSecond line\r";
+        let mut word = vec![0u8; 0x800];
+        word[0..2].copy_from_slice(&0xA5ECu16.to_le_bytes());
+        word[0x01A2..0x01A6].copy_from_slice(&0u32.to_le_bytes());
+        word.extend_from_slice(body);
+
+        let mut clx = vec![0x02];
+        clx.extend_from_slice(&16u32.to_le_bytes());
+        clx.extend_from_slice(&0u32.to_le_bytes());
+        clx.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        clx.extend_from_slice(&[0, 0]);
+        clx.extend_from_slice(&(0x4000_0000u32 | (0x800 * 2)).to_le_bytes());
+        clx.extend_from_slice(&[0, 0]);
+        word[0x01A6..0x01AA].copy_from_slice(&(clx.len() as u32).to_le_bytes());
+
+        let mut compound = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        compound
+            .create_stream("/WordDocument")
+            .unwrap()
+            .write_all(&word)
+            .unwrap();
+        compound
+            .create_stream("/0Table")
+            .unwrap()
+            .write_all(&clx)
+            .unwrap();
+        let mut file = NamedTempFile::with_suffix(".doc").unwrap();
+        file.write_all(&compound.into_inner().into_inner()).unwrap();
+
+        let result = TextParser::parse_path(&file.path()).unwrap();
+        assert_eq!(result.file_type, "DOC");
+        assert_eq!(result.content, "Hello Word\nSecond line\n");
+    }
+
     #[test]
     fn test_text_content_stats() {
         let content = TextContent::new("Hello World\nTest".to_string(), "TXT".to_string());
@@ -403,4 +704,41 @@ mod tests {
         assert_eq!(content.word_count, 3);
         assert_eq!(content.char_count, 16);
     }
+
+    #[test]
+    fn test_parse_epub_reads_the_spine_in_order() {
+        let mut file = NamedTempFile::with_suffix(".epub").unwrap();
+        let mut zip = zip::ZipWriter::new(file.as_file_mut());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, data) in [
+            ("mimetype", "application/epub+zip"),
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OPS/book.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OPS/book.opf",
+                r#"<package><manifest>
+                    <item id="a" href="one.xhtml" media-type="application/xhtml+xml"/>
+                    <item id="b" href="two%20b.xhtml" media-type="application/xhtml+xml"/>
+                </manifest><spine><itemref idref="b"/><itemref idref="a"/></spine></package>"#,
+            ),
+            (
+                "OPS/one.xhtml",
+                "<html><head><style>p {}</style></head><body><p>Second &amp; last</p></body></html>",
+            ),
+            (
+                "OPS/two b.xhtml",
+                "<html><body><h1>First</h1><p>Opening&#33;</p></body></html>",
+            ),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let result = TextParser::parse_path(&file.path()).unwrap();
+        assert_eq!(result.file_type, "EPUB");
+        assert_eq!(result.content, "First\nOpening!\n\nSecond & last\n\n");
+    }
 }
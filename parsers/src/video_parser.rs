@@ -1,11 +1,105 @@
 use crate::Parser;
+use ffmpeg::ffi;
 use ffmpeg_next as ffmpeg;
 use image::{ImageBuffer, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::fmt::Display;
+use std::mem::ManuallyDrop;
 use std::path::Path;
+use std::ptr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 pub struct VideoParser;
 
+// AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX: the codec decodes on a device context set up by the
+// caller. bindgen puts the constant in an anonymous enum, so it has no usable Rust name.
+const HW_CONFIG_METHOD_HW_DEVICE_CTX: i32 = 0x01;
+
+thread_local! {
+    // Scaler of the last video decoded on this thread, handed to the next one. Batch scans
+    // mostly see videos of a handful of sizes, and a scaler whose format and size already
+    // match is reused as is instead of being set up again.
+    static SPARE_SCALER: RefCell<Option<ffmpeg::software::scaling::Context>> =
+        const { RefCell::new(None) };
+}
+
+// Hardware decoder to try. Decoding falls back to software when the device can't be opened
+// or the codec has no hardware decoder on it, so asking for one is always safe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    #[default]
+    None,
+    // The first device that works, in the platform's preferred order
+    Auto,
+    Vaapi,
+    VideoToolbox,
+    // NVIDIA NVDEC through a CUDA device
+    Nvdec,
+}
+
+impl HwAccel {
+    fn candidates(self) -> &'static [HwAccel] {
+        match self {
+            HwAccel::None => &[],
+            HwAccel::Auto if cfg!(target_os = "macos") => &[HwAccel::VideoToolbox],
+            HwAccel::Auto => &[HwAccel::Nvdec, HwAccel::Vaapi],
+            HwAccel::Vaapi => &[HwAccel::Vaapi],
+            HwAccel::VideoToolbox => &[HwAccel::VideoToolbox],
+            HwAccel::Nvdec => &[HwAccel::Nvdec],
+        }
+    }
+
+    fn device_type(self) -> ffi::AVHWDeviceType {
+        match self {
+            HwAccel::Vaapi => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI,
+            HwAccel::VideoToolbox => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX,
+            HwAccel::Nvdec => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_CUDA,
+            HwAccel::None | HwAccel::Auto => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE,
+        }
+    }
+}
+
+impl FromStr for HwAccel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "none" | "software" => Ok(Self::None),
+            "auto" => Ok(Self::Auto),
+            "vaapi" => Ok(Self::Vaapi),
+            "videotoolbox" => Ok(Self::VideoToolbox),
+            "nvdec" | "cuda" => Ok(Self::Nvdec),
+            other => Err(format!(
+                "unknown hardware decoder {:?} (expected none, auto, vaapi, videotoolbox or nvdec)",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for HwAccel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HwAccel::None => write!(f, "none"),
+            HwAccel::Auto => write!(f, "auto"),
+            HwAccel::Vaapi => write!(f, "vaapi"),
+            HwAccel::VideoToolbox => write!(f, "videotoolbox"),
+            HwAccel::Nvdec => write!(f, "nvdec"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoDecodeOptions {
+    // Only decode keyframes. Keyframes decode on their own, so skipping the packets in
+    // between is much faster than decoding every frame and discarding most of them.
+    pub keyframes_only: bool,
+    pub hwaccel: HwAccel,
+}
+
 #[derive(Debug)]
 pub enum VideoParserError {
     IO(std::io::Error),
@@ -33,20 +127,65 @@ impl From<ffmpeg::Error> for VideoParserError {
     }
 }
 
+// Most buffers kept for reuse; frames handed back beyond this are freed
+const MAX_SPARE_BUFFERS: usize = 32;
+
+// Pixel buffers of frames the caller is done with, which the iterator decodes later frames
+// into instead of allocating a fresh buffer per frame. Clones share one pool, so frames
+// analyzed on other threads can be handed back from there.
+#[derive(Clone, Default)]
+pub struct FrameBuffers {
+    spare: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl FrameBuffers {
+    pub fn recycle(&self, frame: RgbaImage) {
+        // Buffers are plain bytes, so one left behind by a panicking thread is still fine
+        let mut spare = self.spare.lock().unwrap_or_else(|e| e.into_inner());
+        if spare.len() < MAX_SPARE_BUFFERS {
+            spare.push(frame.into_raw());
+        }
+    }
+
+    fn take(&self) -> Vec<u8> {
+        let mut spare = self.spare.lock().unwrap_or_else(|e| e.into_inner());
+        spare.pop().unwrap_or_default()
+    }
+}
+
 pub struct VideoFrameIterator {
     input: ffmpeg::format::context::Input,
     decoder: ffmpeg::decoder::Video,
-    scaler: ffmpeg::software::scaling::Context,
+    // Handed back to SPARE_SCALER on drop
+    scaler: ManuallyDrop<ffmpeg::software::scaling::Context>,
     video_stream_index: usize,
     decoded: ffmpeg::frame::Video,
+    // RGBA output of the scaler, reused from frame to frame
+    rgba: ffmpeg::frame::Video,
+    // Decoded frames copied back from the hardware device
+    transferred: ffmpeg::frame::Video,
+    hardware: Option<HwAccel>,
     packet_buffer: Vec<(usize, ffmpeg::codec::packet::Packet)>,
     packet_index: usize,
     packets_exhausted: bool,
     flushing: bool,
+    keyframes_only: bool,
+    time_base: f64,
+    frame_rate: f64,
+    frames_returned: usize,
+    last_timestamp: Option<f64>,
+    buffers: FrameBuffers,
 }
 
 impl VideoFrameIterator {
     pub fn new<P: AsRef<Path>>(file_path: &P) -> Result<Self, VideoParserError> {
+        Self::with_options(file_path, VideoDecodeOptions::default())
+    }
+
+    pub fn with_options<P: AsRef<Path>>(
+        file_path: &P,
+        options: VideoDecodeOptions,
+    ) -> Result<Self, VideoParserError> {
         ffmpeg::init()?;
 
         let input = ffmpeg::format::input(file_path.as_ref())?;
@@ -58,35 +197,62 @@ impl VideoFrameIterator {
                     "No video stream found".to_string(),
                 ))?;
         let video_stream_index = video_stream.index();
+        let time_base = f64::from(video_stream.time_base());
+        let frame_rate = f64::from(video_stream.avg_frame_rate());
 
-        let context = ffmpeg::codec::context::Context::from_parameters(video_stream.parameters())?;
+        let mut context =
+            ffmpeg::codec::context::Context::from_parameters(video_stream.parameters())?;
+        let hardware = attach_hardware_device(&mut context, options.hwaccel);
         let decoder = context.decoder().video()?;
 
-        let scaler = ffmpeg::software::scaling::Context::get(
-            decoder.format(),
-            decoder.width(),
-            decoder.height(),
-            ffmpeg::format::Pixel::RGBA,
-            decoder.width(),
-            decoder.height(),
-            ffmpeg::software::scaling::Flags::BILINEAR,
-        )?;
+        let scaler = rgba_scaler(decoder.format(), decoder.width(), decoder.height())?;
 
         let decoded = ffmpeg::frame::Video::empty();
 
         Ok(Self {
             input,
             decoder,
-            scaler,
+            scaler: ManuallyDrop::new(scaler),
             video_stream_index,
             decoded,
+            rgba: ffmpeg::frame::Video::empty(),
+            transferred: ffmpeg::frame::Video::empty(),
+            hardware,
             packet_buffer: Vec::new(),
             packet_index: 0,
             packets_exhausted: false,
             flushing: false,
+            keyframes_only: options.keyframes_only,
+            time_base,
+            frame_rate,
+            frames_returned: 0,
+            last_timestamp: None,
+            buffers: FrameBuffers::default(),
         })
     }
 
+    // Only decode keyframes, see `VideoDecodeOptions::keyframes_only`
+    pub fn keyframes_only(mut self) -> Self {
+        self.keyframes_only = true;
+        self
+    }
+
+    // Hardware device the decoder runs on, or None for software decoding
+    pub fn hardware_decoder(&self) -> Option<HwAccel> {
+        self.hardware
+    }
+
+    // Pool that frames can be handed back to once the caller is done with them
+    pub fn buffers(&self) -> FrameBuffers {
+        self.buffers.clone()
+    }
+
+    // Presentation time in seconds of the frame last returned by the iterator. Falls back to
+    // the frame's position at the average frame rate when the stream has no timestamps.
+    pub fn last_timestamp(&self) -> Option<f64> {
+        self.last_timestamp
+    }
+
     fn load_packets(&mut self, count: usize) {
         if self.packets_exhausted {
             return;
@@ -94,7 +260,9 @@ impl VideoFrameIterator {
 
         let mut loaded = 0;
         for (stream, packet) in self.input.packets() {
-            if stream.index() == self.video_stream_index {
+            if stream.index() == self.video_stream_index
+                && (!self.keyframes_only || packet.is_key())
+            {
                 self.packet_buffer.push((stream.index(), packet));
                 loaded += 1;
                 if loaded >= count {
@@ -107,23 +275,165 @@ impl VideoFrameIterator {
 
     fn decode_frame(&mut self) -> Result<Option<RgbaImage>, VideoParserError> {
         if self.decoder.receive_frame(&mut self.decoded).is_ok() {
-            let mut rgba_frame = ffmpeg::frame::Video::empty();
-            self.scaler.run(&self.decoded, &mut rgba_frame)?;
+            // Frames decoded on a hardware device live in its memory until copied back, and
+            // come back in the device's own layout (usually NV12) rather than the stream's
+            let on_device = unsafe { !(*self.decoded.as_ptr()).hw_frames_ctx.is_null() };
+            let frame = if on_device {
+                // Unref first so a resolution change mid-stream gets fresh buffers
+                let status = unsafe {
+                    ffi::av_frame_unref(self.transferred.as_mut_ptr());
+                    ffi::av_hwframe_transfer_data(
+                        self.transferred.as_mut_ptr(),
+                        self.decoded.as_ptr(),
+                        0,
+                    )
+                };
+                if status < 0 {
+                    return Err(ffmpeg::Error::from(status).into());
+                }
+                self.scaler.cached(
+                    self.transferred.format(),
+                    self.transferred.width(),
+                    self.transferred.height(),
+                    ffmpeg::format::Pixel::RGBA,
+                    self.transferred.width(),
+                    self.transferred.height(),
+                    ffmpeg::software::scaling::Flags::BILINEAR,
+                );
+                &self.transferred
+            } else {
+                &self.decoded
+            };
+
+            // `run` allocates the output itself when it is empty, so a size change mid-stream
+            // only needs the old frame dropped
+            let output = *self.scaler.output();
+            if self.rgba.width() != output.width || self.rgba.height() != output.height {
+                self.rgba = ffmpeg::frame::Video::empty();
+            }
+            self.scaler.run(frame, &mut self.rgba)?;
 
-            let width = rgba_frame.width();
-            let height = rgba_frame.height();
-            let data = rgba_frame.data(0);
+            let width = self.rgba.width();
+            let height = self.rgba.height();
+            let row_len = width as usize * 4;
+            let stride = self.rgba.stride(0);
+            let data = self.rgba.data(0);
 
-            let img = ImageBuffer::from_raw(width, height, data.to_vec()).ok_or_else(|| {
+            // Rows can be padded out to the scaler's alignment, so they are copied one by one
+            let mut pixels = self.buffers.take();
+            pixels.clear();
+            pixels.reserve(row_len * height as usize);
+            for row in data.chunks(stride).take(height as usize) {
+                pixels.extend_from_slice(&row[..row_len]);
+            }
+
+            let img = ImageBuffer::from_raw(width, height, pixels).ok_or_else(|| {
                 VideoParserError::Decode("Failed to create RGBA buffer".to_string())
             })?;
 
+            let by_position = if self.frame_rate > 0.0 && self.frame_rate.is_finite() {
+                Some(self.frames_returned as f64 / self.frame_rate)
+            } else {
+                None
+            };
+            self.last_timestamp = self
+                .decoded
+                .timestamp()
+                .map(|pts| pts as f64 * self.time_base)
+                .filter(|time| time.is_finite() && self.time_base > 0.0)
+                .or(by_position);
+            self.frames_returned += 1;
+
             return Ok(Some(img));
         }
         Ok(None)
     }
 }
 
+impl Drop for VideoFrameIterator {
+    fn drop(&mut self) {
+        // SAFETY: the scaler is not used again after this
+        let scaler = unsafe { ManuallyDrop::take(&mut self.scaler) };
+        // While the thread is shutting down its locals are gone and the scaler is freed instead
+        let _ = SPARE_SCALER.try_with(|spare| *spare.borrow_mut() = Some(scaler));
+    }
+}
+
+// Scaler from a decoded frame to RGBA at the same size. Takes over this thread's spare scaler
+// when there is one, which `cached` only rebuilds if the format or size differ.
+fn rgba_scaler(
+    format: ffmpeg::format::Pixel,
+    width: u32,
+    height: u32,
+) -> Result<ffmpeg::software::scaling::Context, VideoParserError> {
+    if let Some(mut scaler) = SPARE_SCALER.with(|spare| spare.borrow_mut().take()) {
+        scaler.cached(
+            format,
+            width,
+            height,
+            ffmpeg::format::Pixel::RGBA,
+            width,
+            height,
+            ffmpeg::software::scaling::Flags::BILINEAR,
+        );
+        // A failed rebuild leaves a null context, which is freed harmlessly on drop
+        if unsafe { !scaler.as_ptr().is_null() } {
+            return Ok(scaler);
+        }
+    }
+
+    Ok(ffmpeg::software::scaling::Context::get(
+        format,
+        width,
+        height,
+        ffmpeg::format::Pixel::RGBA,
+        width,
+        height,
+        ffmpeg::software::scaling::Flags::BILINEAR,
+    )?)
+}
+
+// Attach the first device `hwaccel` allows that can be opened and that the stream's codec
+// decodes on. Returns the device in use, or None when decoding stays in software.
+fn attach_hardware_device(
+    context: &mut ffmpeg::codec::context::Context,
+    hwaccel: HwAccel,
+) -> Option<HwAccel> {
+    let codec = ffmpeg::codec::decoder::find(context.id())?;
+
+    for &candidate in hwaccel.candidates() {
+        let device_type = candidate.device_type();
+        if !decodes_on(codec, device_type) {
+            continue;
+        }
+
+        let mut device = ptr::null_mut();
+        let status = unsafe {
+            ffi::av_hwdevice_ctx_create(&mut device, device_type, ptr::null(), ptr::null_mut(), 0)
+        };
+        if status < 0 {
+            continue;
+        }
+        // The codec context keeps its own reference and releases it when freed
+        unsafe {
+            (*context.as_mut_ptr()).hw_device_ctx = ffi::av_buffer_ref(device);
+            ffi::av_buffer_unref(&mut device);
+        }
+        return Some(candidate);
+    }
+
+    None
+}
+
+fn decodes_on(codec: ffmpeg::Codec, device_type: ffi::AVHWDeviceType) -> bool {
+    (0..)
+        .map_while(|index| unsafe { ffi::avcodec_get_hw_config(codec.as_ptr(), index).as_ref() })
+        .any(|config| {
+            config.device_type == device_type
+                && config.methods & HW_CONFIG_METHOD_HW_DEVICE_CTX != 0
+        })
+}
+
 impl Iterator for VideoFrameIterator {
     type Item = Result<RgbaImage, VideoParserError>;
 
@@ -178,6 +488,26 @@ impl Iterator for VideoFrameIterator {
     }
 }
 
+impl VideoParser {
+    // Iterate over the keyframes of the video only
+    pub fn parse_keyframes<P>(file_path: &P) -> Result<VideoFrameIterator, VideoParserError>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(VideoFrameIterator::new(file_path)?.keyframes_only())
+    }
+
+    pub fn parse_with_options<P>(
+        file_path: &P,
+        options: VideoDecodeOptions,
+    ) -> Result<VideoFrameIterator, VideoParserError>
+    where
+        P: AsRef<Path>,
+    {
+        VideoFrameIterator::with_options(file_path, options)
+    }
+}
+
 impl Parser for VideoParser {
     type Output = VideoFrameIterator;
     type Error = VideoParserError;
@@ -0,0 +1,88 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage};
+
+use crate::Parser;
+
+// Decodes every frame of a WebP. ImageParser goes through `image::load`, which only
+// returns the first frame of an animation.
+pub struct WebpParser;
+
+#[derive(Debug)]
+pub enum WebpParserError {
+    IO(std::io::Error),
+    Parse(image::error::ImageError),
+}
+
+impl Display for WebpParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl From<std::io::Error> for WebpParserError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl From<image::error::ImageError> for WebpParserError {
+    fn from(value: image::error::ImageError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+pub struct WebpFrame {
+    // Full canvas after compositing this frame, as a viewer would show it
+    pub image: DynamicImage,
+    pub delay_ms: u32,
+}
+
+pub struct WebpImage {
+    pub animated: bool,
+    pub frames: Vec<WebpFrame>,
+}
+
+impl Parser for WebpParser {
+    type Output = WebpImage;
+
+    type Error = WebpParserError;
+
+    fn parse_path<P>(file_path: &P) -> Result<Self::Output, Self::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(file_path)?;
+        let decoder = WebPDecoder::new(BufReader::new(file))?;
+
+        if !decoder.has_animation() {
+            let image = DynamicImage::from_decoder(decoder)?;
+            return Ok(WebpImage {
+                animated: false,
+                frames: vec![WebpFrame { image, delay_ms: 0 }],
+            });
+        }
+
+        let frames = decoder
+            .into_frames()
+            .collect_frames()?
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                WebpFrame {
+                    delay_ms: numer.checked_div(denom).unwrap_or(0),
+                    image: DynamicImage::ImageRgba8(frame.into_buffer()),
+                }
+            })
+            .collect();
+
+        Ok(WebpImage {
+            animated: true,
+            frames,
+        })
+    }
+}
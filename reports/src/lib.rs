@@ -0,0 +1,1885 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// The report sections shared by every entry point. The CLI and the API wrap them in their own
+// top-level report, but a section describes the same analysis the same way in both, so it is
+// defined once here. Fields only one entry point can fill (files written to the output
+// directory, for instance) are optional and left out of the JSON when unset.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginReport {
+    pub name: String,
+    pub is_suspicious: bool,
+    pub findings: Vec<PluginFindingReport>,
+    pub details: Option<serde_json::Value>,
+    // Set when the plugin failed to run; the other fields are then empty
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginFindingReport {
+    pub description: String,
+    pub offset: Option<usize>,
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub detected_type: String,
+    pub extension: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MagicBytesReport {
+    pub primary_format: String,
+    pub expected_format: Option<String>,
+    pub total_signatures_found: usize,
+    pub has_multiple_formats: bool,
+    pub has_suspicious_data: bool,
+    pub format_summary: FormatSummary,
+    pub embedded_files: Vec<EmbeddedFileInfo>,
+    pub suspicious_findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatSummary {
+    pub images: usize,
+    pub audio: usize,
+    pub video: usize,
+    pub text_documents: usize,
+    pub archives: usize,
+    pub executables: usize,
+    pub other: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedFileInfo {
+    pub offset: usize,
+    pub offset_hex: String,
+    pub description: String,
+    pub file_type: String,
+    pub confidence: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlackSpaceReport {
+    pub container: String,
+    pub accounted_bytes: usize,
+    pub slack_bytes: usize,
+    pub is_suspicious: bool,
+    pub regions: Vec<SlackRegionReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlackRegionReport {
+    pub offset: usize,
+    pub offset_hex: String,
+    pub length: usize,
+    pub entropy: f64,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_type: Option<String>,
+    // Set when the region was carved out with --extract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carved_file: Option<String>,
+    // Content hash of the carved region, naming it in the payload store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Other files the identical payload was carved from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_carved_from: Vec<String>,
+}
+
+// Compressed streams found by trying every offset, outside the container's own compressed data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeflateStreamsReport {
+    pub searched_bytes: usize,
+    // Bytes of the container's own streams that were skipped
+    pub container_bytes: usize,
+    pub is_suspicious: bool,
+    pub streams: Vec<DeflateStreamReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeflateStreamReport {
+    pub offset: usize,
+    pub offset_hex: String,
+    // zlib, gzip or deflate
+    pub format: String,
+    pub compressed_length: usize,
+    pub inflated_length: usize,
+    // False when the stream was cut off by the end of the file or the inflate limit
+    pub complete: bool,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_preview: Option<String>,
+    // Set when the inflated stream was written out with --extract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carved_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_carved_from: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FormatSpecificAnalysis {
+    Image(Box<ImageAnalysis>),
+    Audio(Box<AudioAnalysis>),
+    Video(VideoAnalysis),
+    Text(Box<TextAnalysis>),
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageAnalysis {
+    pub exif_metadata: Option<ExifReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_metadata: Option<MissingMetadataReport>,
+    pub lsb_analysis: Option<LsbReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_analysis: Option<WsReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_correlation: Option<ChannelCorrelationReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive_lsb: Option<AdaptiveLsbReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bpcs: Option<BpcsReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<CalibrationReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webp_analysis: Option<WebpReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gif_analysis: Option<GifReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub png_text: Option<PngTextReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_analysis: Option<PagesReport>,
+    // Filtered renderings written to the output directory by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_analysis: Option<FilterAnalysisReport>,
+    // 0 x 0 when no decoder could read the pixels
+    #[serde(default)]
+    pub dimensions: ImageDimensions,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExifReport {
+    pub fields_found: usize,
+    pub has_thumbnail: bool,
+    pub thumbnail_size_bytes: Option<usize>,
+    pub comment_fields: Vec<String>,
+    pub suspicious_fields: Vec<String>,
+    pub metadata: Vec<MetadataField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maker_note: Option<MakerNoteReport>,
+}
+
+// Metadata expected of what the file looks like, e.g. camera tags in a JPEG photo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingMetadataReport {
+    pub expected_source: String,
+    pub missing_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MakerNoteReport {
+    pub layout: String,
+    // Offsets are relative to the start of the EXIF (TIFF) block
+    pub offset: usize,
+    pub size_bytes: usize,
+    pub entries: usize,
+    pub accounted_bytes: usize,
+    pub preview_bytes: usize,
+    pub findings: Vec<MakerNoteFindingReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MakerNoteFindingReport {
+    pub offset: usize,
+    pub size_bytes: usize,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataField {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsbReport {
+    pub is_suspicious: bool,
+    // Bits per channel in the source image; LSBs are taken at this depth
+    #[serde(default = "default_bit_depth")]
+    pub bit_depth: u8,
+    // Native color model the channels below come from (RGB, Grayscale, CMYK, YCCK)
+    #[serde(default = "default_color_model")]
+    pub color_model: String,
+    pub channels: Vec<LsbChannelAnalysis>,
+    // Photo or synthetic, which picks the statistics the verdict is based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<ContentClassReport>,
+    // Distance from clean covers of the same format and class, when there is a baseline for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<LsbBaselineReport>,
+    // LSB planes written to the output directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_files: Vec<String>,
+}
+
+fn default_bit_depth() -> u8 {
+    8
+}
+
+fn default_color_model() -> String {
+    "RGB".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsbChannelAnalysis {
+    pub channel_name: String,
+    pub chi_square_score: f64,
+    pub entropy_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsbBaselineReport {
+    // jpeg, png, raw or other
+    pub format: String,
+    // photo or synthetic
+    pub content: String,
+    // Standard deviations from the baseline mean at which a channel is flagged
+    pub sigma: f64,
+    pub channels: Vec<LsbChannelDeviation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsbChannelDeviation {
+    pub channel_name: String,
+    pub chi_square_per_pair: f64,
+    // Signed standard deviations from the baseline mean
+    pub chi_square_z: f64,
+    pub entropy_z: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentClassReport {
+    // photo or synthetic
+    pub class: String,
+    pub flat_ratio: f64,
+    pub unique_colors: usize,
+    pub flat_lsb_noise: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsReport {
+    pub is_suspicious: bool,
+    pub estimated_payload_rate: f64,
+    pub estimated_change_rate: f64,
+    pub channels: Vec<WsChannelReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsChannelReport {
+    pub channel_name: String,
+    pub sample_pair_rate: f64,
+    pub weighted_stego_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelCorrelationReport {
+    pub is_suspicious: bool,
+    pub block_size: u32,
+    pub blocks_evaluated: usize,
+    pub collapsed_block_count: usize,
+    pub collapsed_ratio: f64,
+    pub mean_lsb_correlation: f64,
+    pub mean_reference_correlation: f64,
+    pub collapsed_blocks: Vec<CollapsedBlockReport>,
+    // Copy of the image with the collapsed blocks shaded, written when the image is flagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollapsedBlockReport {
+    pub x: u32,
+    pub y: u32,
+    pub lsb_correlation: f64,
+    pub reference_correlation: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveLsbReport {
+    pub is_suspicious: bool,
+    pub block_size: u32,
+    pub smooth: RegionLsbReport,
+    pub textured: RegionLsbReport,
+    pub pov_contrast: f64,
+    // Copy of the image with the textured blocks shaded, written when the image is flagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionLsbReport {
+    pub blocks: usize,
+    pub pov_score: f64,
+    pub lsb_ones_ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BpcsReport {
+    pub is_suspicious: bool,
+    pub block_size: u32,
+    // Structured planes whose complex blocks look like random data
+    pub noise_planes: usize,
+    pub planes: Vec<BpcsPlaneReport>,
+    pub flagged_blocks: usize,
+    // Copy of the image with the flagged blocks shaded, written when the image is flagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BpcsPlaneReport {
+    pub channel: String,
+    pub plane: u8,
+    pub complex_blocks: usize,
+    pub simple_ratio: f64,
+    pub mean_complexity: f64,
+    pub transition_ratio: f64,
+    pub noise_like: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub is_suspicious: bool,
+    pub blocks: usize,
+    // Share of DCT coefficients estimated to have been changed by embedding
+    pub estimated_change_rate: f64,
+    // Mean distance between the file's and the calibrated reference's histograms, 0 to 1
+    pub divergence: f64,
+    pub modes: Vec<CalibrationModeReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationModeReport {
+    pub row: u8,
+    pub column: u8,
+    // Coefficients with magnitude 0, 1 and 2
+    pub observed: [usize; 3],
+    pub reference: [usize; 3],
+    pub divergence: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebpReport {
+    pub animated: bool,
+    pub riff_size: u32,
+    pub chunks: Vec<WebpChunkReport>,
+    pub trailing_bytes: usize,
+    pub structure_anomalies: Vec<String>,
+    pub frame_count: usize,
+    pub frames: Vec<WebpFrameReport>,
+    pub suspicious_frames: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebpChunkReport {
+    pub fourcc: String,
+    pub offset: usize,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebpFrameReport {
+    pub index: usize,
+    pub delay_ms: u32,
+    pub lsb_suspicious: bool,
+    pub histogram_anomalies: bool,
+    pub chi_square_scores: Vec<f64>,
+    pub entropy_scores: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GifReport {
+    pub version: String,
+    pub frame_count: usize,
+    pub comments: Vec<GifCommentReport>,
+    pub applications: Vec<GifApplicationReport>,
+    pub plain_text_blocks: usize,
+    pub trailing_bytes: usize,
+    pub structure_anomalies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GifCommentReport {
+    pub offset: usize,
+    pub size: usize,
+    pub preview: String,
+    pub base64: bool,
+    pub decoded_size: usize,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GifApplicationReport {
+    pub offset: usize,
+    pub identifier: String,
+    pub size: usize,
+    pub known: bool,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PngTextReport {
+    pub chunks: Vec<PngTextChunkReport>,
+    pub duplicate_keywords: Vec<String>,
+    pub anomalies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PngTextChunkReport {
+    pub offset: usize,
+    pub chunk_type: String,
+    pub keyword: String,
+    // standard, well_known, unknown or invalid
+    pub keyword_kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub compressed: bool,
+    pub size: usize,
+    pub preview: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    pub decoded_size: usize,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagesReport {
+    // TIFF, ICO or HEIF
+    pub format: String,
+    pub page_count: usize,
+    pub pages: Vec<PageReport>,
+    pub outlier_pages: Vec<usize>,
+    pub hidden_pages: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageReport {
+    pub index: usize,
+    pub label: String,
+    pub hidden: bool,
+    // Why the page wasn't decoded; the statistics below are absent when set
+    pub error: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub lsb_suspicious: bool,
+    pub histogram_anomalies: bool,
+    pub mean_lsb_entropy: Option<f64>,
+    pub chi_square_per_pair: Option<f64>,
+    pub edge_density: Option<f64>,
+    pub outlier_score: Option<f64>,
+    pub outlier: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterAnalysisReport {
+    pub filters_generated: usize,
+    pub output_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAnalysis {
+    pub sample_count: usize,
+    #[serde(default)]
+    pub sample_rate: u32,
+    // Written with --export-samples
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples_file: Option<String>,
+    pub id3_analysis: Option<Id3Report>,
+    pub spectrogram_analysis: Option<SpectrogramReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ultrasonic_analysis: Option<UltrasonicReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub demodulation: Option<DemodulationReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase_analysis: Option<PhaseReport>,
+    // Comparison with a clean reference recording (--compare-with)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub differential: Option<DifferentialReport>,
+    // APEv2 and Lyrics3 tags after the last audio frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailing_tags: Option<TrailingTagsReport>,
+    // iTunes-style metadata of an M4A
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mp4_tags: Option<Mp4TagsReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrailingTagsReport {
+    pub is_suspicious: bool,
+    // In file order
+    pub tags: Vec<TrailingTagReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrailingTagReport {
+    // "APEv2", "Lyrics3v1" or "Lyrics3v2"
+    pub kind: String,
+    pub offset: usize,
+    pub size_bytes: usize,
+    pub items: Vec<TagItemReport>,
+    // Problems with the tag as a whole
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mp4TagsReport {
+    pub is_suspicious: bool,
+    // The ilst box in moov/udta/meta
+    pub offset: usize,
+    pub size_bytes: usize,
+    // Freeform items are keyed "----:<mean>:<name>"
+    pub items: Vec<TagItemReport>,
+    // Problems with the item list as a whole
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagItemReport {
+    pub key: String,
+    pub offset: usize,
+    pub size_bytes: usize,
+    // Declared binary by the tag
+    pub binary: bool,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<String>,
+    // Where the CLI saved a flagged value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3Report {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<i32>,
+    pub comments_count: usize,
+    pub pictures_count: usize,
+    pub private_frames_count: usize,
+    pub suspicious_frames: Vec<String>,
+    // APIC pictures, each analyzed as an image of its own. The CLI saves them next to the
+    // report; the API delivers the bytes through carved_payloads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pictures: Vec<Id3PictureReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<Id3ChapterReport>,
+    // PCST, WFED, TGID, TDES, TKWD and TCAT
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub podcast_frames: Vec<MetadataField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lyrics_payloads: Vec<Id3LyricsPayloadReport>,
+    // ID3v2 tags at the start of the file; readers only use the first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub v2_tags: Vec<Id3v2TagReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v1_tag: Option<Id3v1TagReport>,
+    // Data between the ID3v2 tags and the first audio frame
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_gaps: Vec<Id3TagGapReport>,
+    // Fields the ID3v1 and ID3v2 tags disagree on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_mismatches: Vec<Id3TagMismatchReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3v2TagReport {
+    pub offset: usize,
+    // "2.4.0" and the like
+    pub version: String,
+    pub size_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3v1TagReport {
+    pub offset: usize,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    pub track: Option<u8>,
+    pub genre: u8,
+    // The "TAG+" extension in front of the tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extended_offset: Option<usize>,
+    // Fields with bytes after their terminating NUL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3TagGapReport {
+    pub offset: usize,
+    pub size_bytes: usize,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    // Where the CLI saved the bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3TagMismatchReport {
+    pub field: String,
+    pub v1: String,
+    pub v2: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3LyricsPayloadReport {
+    // USLT or SYLT
+    pub frame: String,
+    pub timestamp: Option<u32>,
+    // "base64" or "binary"
+    pub kind: String,
+    pub length: usize,
+    pub decoded_size: usize,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3ChapterReport {
+    pub element_id: String,
+    pub start_time_ms: u32,
+    pub end_time_ms: u32,
+    pub title: Option<String>,
+    pub sub_frames: Vec<String>,
+    pub urls: Vec<String>,
+    pub picture_count: usize,
+    pub largest_binary_bytes: usize,
+    // Also listed in suspicious_frames
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3PictureReport {
+    pub picture_type: String,
+    pub mime_type: String,
+    pub description: String,
+    pub size_bytes: usize,
+    pub offset: Option<usize>,
+    // Where the CLI saved the picture
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Other files the identical picture was found in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_carved_from: Vec<String>,
+    pub magic_bytes_analysis: Option<MagicBytesReport>,
+    pub exif_metadata: Option<ExifReport>,
+    pub lsb_analysis: Option<LsbReport>,
+    pub is_suspicious: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpectrogramReport {
+    pub high_frequency_energy: f64,
+    pub hidden_message_detected: bool,
+    pub suspicious_patterns: Vec<String>,
+    // The rendered spectrogram image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+    // Long recordings are analyzed per time window; the fields above then hold the highest
+    // energy and every window's patterns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiles: Vec<SpectrogramTileReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub persistent_tones: Vec<AudioToneReport>,
+}
+
+// A tone and when it sounds, in seconds from the start of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioToneReport {
+    pub frequency_hz: f32,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpectrogramTileReport {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub high_frequency_energy: f64,
+    pub hidden_message_detected: bool,
+    pub suspicious_patterns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UltrasonicReport {
+    pub band_low_hz: f32,
+    pub band_high_hz: f32,
+    pub band_energy_ratio: f64,
+    // The band shifted down by baseband_offset_hz and resampled to baseband_rate
+    pub baseband_rate: u32,
+    pub baseband_offset_hz: f32,
+    // Written with --export-samples
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseband_file: Option<String>,
+    pub tones: Vec<UltrasonicToneReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fsk: Option<FskReport>,
+    pub is_suspicious: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UltrasonicToneReport {
+    pub frequency_hz: f32,
+    pub frame_share: f64,
+    // First and last frame the tone is the peak of, in seconds
+    #[serde(default)]
+    pub start_seconds: f64,
+    #[serde(default)]
+    pub end_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FskReport {
+    pub mark_hz: f32,
+    pub space_hz: f32,
+    pub baud: f32,
+    pub bit_count: usize,
+    // The leading bits as 0s and 1s
+    pub bitstream: String,
+    pub decoded_hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseReport {
+    pub segment_length: usize,
+    // Mean sample jump at segment boundaries over the mean jump everywhere
+    pub boundary_jump_ratio: f64,
+    // Consecutive first-segment bins at +-pi/2, starting at quantized_first_bin
+    pub quantized_run: usize,
+    pub quantized_first_bin: usize,
+    pub quantized_share: f64,
+    pub decoded_hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_text: Option<String>,
+    pub is_suspicious: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DifferentialReport {
+    pub reference_file: String,
+    // Samples this file lags the reference by
+    pub offset_samples: i64,
+    pub alignment_correlation: f64,
+    pub gain: f64,
+    pub compared_samples: usize,
+    // None when the audio has no integer sample grid, so changes aren't counted in LSBs
+    pub bit_depth: Option<u32>,
+    // Residual energy relative to the reference
+    pub residual_db: f64,
+    pub changed_samples: usize,
+    pub lsb_only_share: f64,
+    pub residual_peak_hz: f64,
+    // Near 1 for white residuals such as LSB embedding, near 0 for injected tones
+    pub residual_flatness: f64,
+    pub regions: Vec<ResidualRegionReport>,
+    pub estimated_bits: Option<u64>,
+    pub estimated_bitrate_bps: Option<f64>,
+    // LSBs from the start of the first region
+    pub lsb_decoded_hex: String,
+    pub lsb_decoded_text: Option<String>,
+    // What a longer run of those LSBs turned out to be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lsb_payload: Option<LsbPayloadReport>,
+    pub is_suspicious: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsbPayloadReport {
+    // How the payload bits were spread over the LSBs, e.g. "plain" or "matrix (1, 7, 3)"
+    #[serde(default = "default_coding_scheme")]
+    pub scheme: String,
+    // msb-first or lsb-first
+    pub bit_order: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length_header: Option<u32>,
+    // zlib or gzip when the LSBs had to be inflated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    pub printable_ratio: f64,
+    pub size_bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    // Start of the payload after header removal and inflation
+    pub hex_preview: String,
+}
+
+fn default_coding_scheme() -> String {
+    "plain".to_string()
+}
+
+impl LsbPayloadReport {
+    pub fn is_recognized(&self) -> bool {
+        self.classification != "binary" || self.compression.is_some()
+    }
+
+    // e.g. "application/zip, zlib compressed, matrix (1, 7, 3) decoded, lsb-first after a
+    // 32-bit length header"
+    pub fn describe(&self) -> String {
+        let mut description = self.classification.clone();
+        if let Some(compression) = &self.compression {
+            description.push_str(&format!(", {} compressed", compression));
+        }
+        if self.scheme != "plain" {
+            description.push_str(&format!(", {} decoded", self.scheme));
+        }
+        description.push_str(&format!(", {}", self.bit_order));
+        if self.length_header.is_some() {
+            description.push_str(" after a 32-bit length header");
+        }
+        description
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResidualRegionReport {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub changed_samples: usize,
+    pub estimated_bits: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemodulationReport {
+    // Persistent spectrogram tones and ultrasonic tones, neighbours merged
+    pub carriers: Vec<CarrierReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarrierReport {
+    pub carrier_hz: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fsk: Option<FskReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub psk: Option<PskReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PskReport {
+    pub baud: f32,
+    // Mean |cos| of the symbol phases around the decision axis
+    pub constellation: f32,
+    pub bit_count: usize,
+    // May be inverted, as BPSK can't tell which phase is 0
+    pub bitstream: String,
+    pub decoded_hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoAnalysis {
+    pub frames_processed: usize,
+    pub errors_encountered: usize,
+    // Analyzed frames flagged by the per-frame LSB or histogram checks
+    #[serde(default)]
+    pub suspicious_frames: Vec<usize>,
+    // Frame sampling strategy, so a run can be reproduced
+    #[serde(default)]
+    pub sampling: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<SceneReport>,
+    // iTunes-style metadata of an MP4
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mp4_tags: Option<Mp4TagsReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneReport {
+    pub start_frame: usize,
+    pub end_frame: usize,
+    pub frames_analyzed: usize,
+    // Sampled frames left out once the scene used up its analysis budget
+    pub frames_skipped: usize,
+    pub median_chi_square: Option<f64>,
+    // Largest ratio between a frame's mean chi-square and the scene median
+    pub lsb_divergence: f64,
+    // Frames that look like the rest of the scene but whose LSBs don't
+    pub divergent_frames: Vec<usize>,
+    pub suspicious: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextAnalysis {
+    pub file_type: String,
+    pub line_count: usize,
+    pub word_count: usize,
+    pub character_count: usize,
+    pub size_bytes: usize,
+    // Macros and embedded objects of an Office document: the storages and streams of a legacy
+    // compound file (.doc, .xls, .ppt), or the VBA project and embeddings of an OOXML package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ole: Option<OleReport>,
+    // Members, packaging and chapters of an EPUB or CHM ebook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ebook: Option<EbookReport>,
+    // Buffers, chunks and records of a glTF, GLB or STL model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelReport>,
+    // Comments, keys and values of a JSON, XML or YAML document that carry data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<StructuredTextReport>,
+    // Comments of a source file that decode to data, and its comment-to-code ratio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_code: Option<SourceCodeReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceCodeReport {
+    pub language: String,
+    pub is_suspicious: bool,
+    // Runs of line comments count as one
+    pub comments: usize,
+    // Non-blank characters in comments and in code
+    pub comment_chars: usize,
+    pub code_chars: usize,
+    pub comment_ratio: f64,
+    // The file is mostly comment
+    pub abnormal_ratio: bool,
+    pub payloads: Vec<CommentPayloadReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentPayloadReport {
+    // First and last line of the comment
+    pub line: usize,
+    pub end_line: usize,
+    // Characters of the comment
+    pub length: usize,
+    // MIME type, "text" or "binary" of what the comment decodes to
+    pub classification: String,
+    pub preview: String,
+    // Decoded data saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredTextReport {
+    // "JSON", "XML" or "YAML"
+    pub format: String,
+    pub is_suspicious: bool,
+    pub comments: usize,
+    // Keys, attributes and string values read
+    pub values: usize,
+    pub findings: Vec<StructuredFindingReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredFindingReport {
+    // "comment", "long value", "blob" or "invisible key"
+    pub kind: String,
+    // Path of the key or value, or the comment's line
+    pub location: String,
+    // Characters of the value, key or comment
+    pub length: usize,
+    // MIME type, "text" or "binary" of what the finding decodes to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<String>,
+    // The start of the decoded text, or of the value when nothing decoded
+    pub preview: String,
+    // Decoded data saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelReport {
+    // "glTF", "GLB" or "STL"
+    pub format: String,
+    pub is_suspicious: bool,
+    // Triangles of an STL model
+    #[serde(default)]
+    pub triangles: u64,
+    // Buffers a glTF model declares
+    #[serde(default)]
+    pub buffers: usize,
+    // Structure that disagrees with itself
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<String>,
+    // Bytes the model carries that no loader reads
+    pub regions: Vec<ModelRegionReport>,
+    // STL triangles whose attribute byte count isn't zero
+    #[serde(default)]
+    pub attributed_triangles: u64,
+    // Buffers referenced by path, which aren't analyzed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_buffers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRegionReport {
+    // Chunk, buffer range, JSON pointer into extras or STL record field
+    pub location: String,
+    // File offset, when the bytes are stored as they are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub size_bytes: usize,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    // The region as text, when it is text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    // Region saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbookReport {
+    // "EPUB" or "CHM"
+    pub format: String,
+    pub is_suspicious: bool,
+    pub members: Vec<EbookMemberReport>,
+    // Packaging that breaks the format's rules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<String>,
+    pub chapters: Vec<EbookChapterReport>,
+    // Pages in compressed CHM sections, which aren't analyzed
+    #[serde(default)]
+    pub unanalyzed_chapters: usize,
+}
+
+impl TrailingTagsReport {
+    // "<kind> <key>" for each flagged item and "<kind> tag" for each tag with problems of its own
+    pub fn flagged(&self) -> Vec<String> {
+        let mut places = Vec::new();
+        for tag in &self.tags {
+            if !tag.findings.is_empty() {
+                places.push(format!("{} tag", tag.kind));
+            }
+            for item in tag.items.iter().filter(|i| !i.findings.is_empty()) {
+                places.push(format!("{} {}", tag.kind, item.key));
+            }
+        }
+        places
+    }
+}
+
+impl Mp4TagsReport {
+    // Keys of the flagged items, and "ilst" when the item list has problems of its own
+    pub fn flagged(&self) -> Vec<String> {
+        let mut places = Vec::new();
+        if !self.findings.is_empty() {
+            places.push("ilst".to_string());
+        }
+        for item in self.items.iter().filter(|i| !i.findings.is_empty()) {
+            places.push(item.key.clone());
+        }
+        places
+    }
+}
+
+impl EbookReport {
+    // Members neither the package document nor the format accounts for
+    pub fn extraneous(&self) -> impl Iterator<Item = &EbookMemberReport> {
+        self.members.iter().filter(|m| !m.declared)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbookMemberReport {
+    pub path: String,
+    pub size_bytes: u64,
+    pub compressed: bool,
+    pub declared: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    // MIME type, "text" or "binary"; absent when the member couldn't be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<String>,
+    #[serde(default)]
+    pub executable: bool,
+    // Extraneous member saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbookChapterReport {
+    pub path: String,
+    pub is_suspicious: bool,
+    pub zero_width_chars: usize,
+    // Text the zero-width characters decode to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zero_width_text: Option<String>,
+    // Classification of a zero-width payload that isn't text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zero_width_payload: Option<String>,
+    // Lines ending in mixed spaces and tabs
+    pub whitespace_lines: usize,
+    // Latin words with Cyrillic or Greek lookalike letters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub homoglyph_words: Vec<String>,
+    pub hidden_elements: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OleReport {
+    // "OLE2" or "OOXML"
+    #[serde(default = "ole2_format")]
+    pub format: String,
+    pub is_suspicious: bool,
+    pub entries: Vec<OleEntryReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub macros: Vec<OleMacroReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embedded_objects: Vec<OleObjectReport>,
+}
+
+fn ole2_format() -> String {
+    "OLE2".to_string()
+}
+
+impl OleReport {
+    // Macros of this document and of every Office document embedded in it
+    pub fn all_macros(&self) -> Vec<&OleMacroReport> {
+        let mut macros: Vec<&OleMacroReport> = self.macros.iter().collect();
+        for object in &self.embedded_objects {
+            if let Some(nested) = &object.nested {
+                macros.extend(nested.all_macros());
+            }
+        }
+        macros
+    }
+
+    // Embedded objects at any depth, outermost first
+    pub fn all_objects(&self) -> Vec<&OleObjectReport> {
+        let mut objects = Vec::new();
+        for object in &self.embedded_objects {
+            objects.push(object);
+            if let Some(nested) = &object.nested {
+                objects.extend(nested.all_objects());
+            }
+        }
+        objects
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OleEntryReport {
+    // Storage names joined with '/', control characters escaped
+    pub path: String,
+    pub is_storage: bool,
+    pub size_bytes: u64,
+    // Why the name or size is out of place, when it is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unusual: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OleMacroReport {
+    pub module: String,
+    pub stream: String,
+    // Decompressed source length; None when the module couldn't be decompressed
+    pub source_bytes: Option<usize>,
+    // Procedures Office runs without the user asking, such as AutoOpen or Workbook_Open
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_exec: Vec<String>,
+    // Source saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OleObjectReport {
+    pub stream: String,
+    // Name of the file the Packager wrapped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    pub size_bytes: usize,
+    // MIME type, "text" or "binary"
+    pub classification: String,
+    // A program rather than a document or media file
+    #[serde(default)]
+    pub executable: bool,
+    // Object saved to the payload store by the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_carved_from: Vec<String>,
+    // Signature scan of the object itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magic_bytes_analysis: Option<MagicBytesReport>,
+    // The object's own macros and objects, when it is an Office document too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nested: Option<Box<OleReport>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisSummary {
+    pub steganography_detected: bool,
+    pub confidence_level: String, // "low", "medium", "high", or "confirmed" with a payload
+    pub threat_indicators: Vec<String>,
+    pub recommendations: Vec<String>,
+    #[serde(default)]
+    pub verdict: SummaryVerdict,
+    // What an analyzer actually recovered, behind a ConfirmedPayloadExtracted verdict
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extracted_payloads: Vec<RecoveredPayloadReport>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummaryVerdict {
+    #[default]
+    Clean,
+    Suspicious,
+    // A payload was recovered, so the heuristics' confidence no longer matters
+    ConfirmedPayloadExtracted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredPayloadReport {
+    // The analyzer or extraction step that recovered it
+    pub source: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    // The file or download the bytes were written to; decoded text is only in the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+}
+
+impl AnalysisSummary {
+    // Heuristic findings are only ever as strong as their thresholds, but a recovered payload
+    // settles the question, so it overrides the confidence model
+    pub fn confirm_payloads(&mut self, payloads: Vec<RecoveredPayloadReport>) {
+        if payloads.is_empty() {
+            return;
+        }
+        self.steganography_detected = true;
+        self.confidence_level = "confirmed".to_string();
+        self.verdict = SummaryVerdict::ConfirmedPayloadExtracted;
+        self.extracted_payloads = payloads;
+    }
+}
+
+// An analyst's judgement of one finding in a stored report. Kept with the report, so exports
+// carry the review and labelled findings can be counted per rule when tuning thresholds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    // Rule ID of the finding; absent for notes on the scan as a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub label: AnnotationLabel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analyst: Option<String>,
+    // RFC 3339
+    pub added: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLabel {
+    TruePositive,
+    FalsePositive,
+    Comment,
+}
+
+impl std::str::FromStr for AnnotationLabel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().replace('_', "-").as_str() {
+            "true-positive" | "tp" => Ok(Self::TruePositive),
+            "false-positive" | "fp" => Ok(Self::FalsePositive),
+            "comment" => Ok(Self::Comment),
+            _ => Err(format!(
+                "unknown label {:?} (expected true-positive, false-positive or comment)",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AnnotationLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnnotationLabel::TruePositive => write!(f, "true positive"),
+            AnnotationLabel::FalsePositive => write!(f, "false positive"),
+            AnnotationLabel::Comment => write!(f, "comment"),
+        }
+    }
+}
+
+impl Annotation {
+    // A comment label says nothing without its text
+    pub fn is_complete(&self) -> bool {
+        self.label != AnnotationLabel::Comment
+            || self
+                .comment
+                .as_deref()
+                .is_some_and(|comment| !comment.trim().is_empty())
+    }
+}
+
+// How the scan was run: the scanner, the version and parameters of every analyzer that ran, and
+// the signature database. Two reports are only comparable when these match, and a report can be
+// reproduced by rerunning the same scanner with them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMetadata {
+    // "stegascan 0.1.0" or "stegascan-api 0.1.0"
+    pub scanner: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub analyzers: Vec<AnalyzerRunReport>,
+    pub signature_database: SignatureDatabaseReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerRunReport {
+    pub name: String,
+    // Unset for steps that aren't built-in analyzers, such as plugins and extraction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureDatabaseReport {
+    // Fingerprint of the built-in signatures
+    pub version: String,
+    pub signatures: usize,
+    // A signatures file loaded on top of them, and the SHA-256 of its contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_sha256: Option<String>,
+}
+
+// Payloads the shared sections recovered: data carved from slack space, compressed streams that
+// inflate to a file or text, files decoded out of GIF comments, PNG text chunks and ID3 lyrics,
+// files packaged into OLE documents, flagged tag items, and text demodulated or read from LSBs.
+// Carving driven by the magic bytes scan is entry point specific and added by the caller.
+pub fn recovered_payloads(
+    slack_space: Option<&SlackSpaceReport>,
+    deflate_streams: Option<&DeflateStreamsReport>,
+    analysis: &FormatSpecificAnalysis,
+) -> Vec<RecoveredPayloadReport> {
+    let mut payloads = Vec::new();
+    let mut push = |source: &str, description: String, offset, artifact| {
+        payloads.push(RecoveredPayloadReport {
+            source: source.to_string(),
+            description,
+            offset,
+            artifact,
+        })
+    };
+
+    if let Some(slack) = slack_space.filter(|slack| slack.is_suspicious) {
+        for region in &slack.regions {
+            if let Some(file) = &region.carved_file {
+                let description = match &region.detected_type {
+                    Some(mime) => {
+                        format!("{} ({} bytes, {})", region.description, region.length, mime)
+                    }
+                    None => format!("{} ({} bytes)", region.description, region.length),
+                };
+                push(
+                    "slack_space",
+                    description,
+                    Some(region.offset),
+                    Some(file.clone()),
+                );
+            }
+        }
+    }
+
+    if let Some(deflate) = deflate_streams {
+        for stream in deflate
+            .streams
+            .iter()
+            .filter(|s| is_file_type(&s.classification) || s.classification == "text")
+        {
+            push(
+                "deflate_stream",
+                format!(
+                    "{} in a {} stream ({} bytes inflated)",
+                    stream.classification, stream.format, stream.inflated_length
+                ),
+                Some(stream.offset),
+                stream.carved_file.clone(),
+            );
+        }
+    }
+
+    // M4As and MP4s keep their iTunes-style metadata in the same item list
+    let mp4_tags = match analysis {
+        FormatSpecificAnalysis::Audio(audio) => audio.mp4_tags.as_ref(),
+        FormatSpecificAnalysis::Video(video) => video.mp4_tags.as_ref(),
+        _ => None,
+    };
+    for item in mp4_tags
+        .iter()
+        .flat_map(|tags| &tags.items)
+        .filter(|item| !item.findings.is_empty() && is_file_type(&item.classification))
+    {
+        push(
+            "mp4_tag",
+            format!(
+                "{} in ilst item {:?} ({} bytes)",
+                item.classification, item.key, item.size_bytes
+            ),
+            Some(item.offset),
+            item.file.clone(),
+        );
+    }
+
+    match analysis {
+        FormatSpecificAnalysis::Image(image) => {
+            if let Some(gif) = &image.gif_analysis {
+                for comment in gif
+                    .comments
+                    .iter()
+                    .filter(|c| is_file_type(&c.classification))
+                {
+                    push(
+                        "gif_comment",
+                        format!(
+                            "{} ({} bytes)",
+                            comment.classification, comment.decoded_size
+                        ),
+                        Some(comment.offset),
+                        None,
+                    );
+                }
+                for app in gif
+                    .applications
+                    .iter()
+                    .filter(|a| is_file_type(&a.classification))
+                {
+                    push(
+                        "gif_application",
+                        format!(
+                            "{} in {} ({} bytes)",
+                            app.classification, app.identifier, app.size
+                        ),
+                        Some(app.offset),
+                        None,
+                    );
+                }
+            }
+            if let Some(png_text) = &image.png_text {
+                for chunk in png_text
+                    .chunks
+                    .iter()
+                    .filter(|c| is_file_type(&c.classification))
+                {
+                    push(
+                        "png_text",
+                        format!(
+                            "{} in {} {:?} ({} bytes)",
+                            chunk.classification,
+                            chunk.chunk_type,
+                            chunk.keyword,
+                            chunk.decoded_size
+                        ),
+                        Some(chunk.offset),
+                        None,
+                    );
+                }
+            }
+        }
+        FormatSpecificAnalysis::Audio(audio) => {
+            if let Some(id3) = &audio.id3_analysis {
+                for lyrics in id3
+                    .lyrics_payloads
+                    .iter()
+                    .filter(|l| is_file_type(&l.classification))
+                {
+                    push(
+                        "id3_lyrics",
+                        format!(
+                            "{} in {} ({} bytes)",
+                            lyrics.classification, lyrics.frame, lyrics.decoded_size
+                        ),
+                        None,
+                        None,
+                    );
+                }
+                for gap in &id3.tag_gaps {
+                    push(
+                        "id3_tag_gap",
+                        format!(
+                            "{} between ID3v2 tags at offset {} ({} bytes)",
+                            gap.classification, gap.offset, gap.size_bytes
+                        ),
+                        Some(gap.offset),
+                        gap.file.clone(),
+                    );
+                }
+            }
+            if let Some(trailing) = &audio.trailing_tags {
+                for tag in &trailing.tags {
+                    for item in tag
+                        .items
+                        .iter()
+                        .filter(|i| !i.findings.is_empty() && is_file_type(&i.classification))
+                    {
+                        push(
+                            "trailing_tag",
+                            format!(
+                                "{} in {} item {:?} ({} bytes)",
+                                item.classification, tag.kind, item.key, item.size_bytes
+                            ),
+                            Some(item.offset),
+                            item.file.clone(),
+                        );
+                    }
+                }
+            }
+            let fsk = audio
+                .ultrasonic_analysis
+                .as_ref()
+                .and_then(|u| u.fsk.as_ref());
+            if let Some(text) = fsk.and_then(|fsk| fsk.decoded_text.as_ref()) {
+                push("ultrasonic_fsk", format!("text {:?}", text), None, None);
+            }
+            if let Some(demodulation) = &audio.demodulation {
+                for carrier in &demodulation.carriers {
+                    let texts = [
+                        carrier
+                            .fsk
+                            .as_ref()
+                            .and_then(|fsk| fsk.decoded_text.as_ref())
+                            .map(|text| ("fsk", text)),
+                        carrier
+                            .psk
+                            .as_ref()
+                            .and_then(|psk| psk.decoded_text.as_ref())
+                            .map(|text| ("psk", text)),
+                    ];
+                    for (scheme, text) in texts.into_iter().flatten() {
+                        push(
+                            scheme,
+                            format!("text {:?} from the {:.0} Hz tone", text, carrier.carrier_hz),
+                            None,
+                            None,
+                        );
+                    }
+                }
+            }
+            if let Some(phase) = audio.phase_analysis.as_ref().filter(|p| p.is_suspicious)
+                && let Some(text) = &phase.decoded_text
+            {
+                push("phase", format!("text {:?}", text), None, None);
+            }
+            if let Some(differential) = audio.differential.as_ref().filter(|d| d.is_suspicious) {
+                match differential
+                    .lsb_payload
+                    .as_ref()
+                    .filter(|payload| payload.is_recognized())
+                {
+                    Some(payload) => push(
+                        "differential",
+                        match &payload.text {
+                            Some(text) => format!("LSB text {:?} ({})", text, payload.describe()),
+                            None => format!(
+                                "LSB data: {} ({} bytes)",
+                                payload.describe(),
+                                payload.size_bytes
+                            ),
+                        },
+                        None,
+                        None,
+                    ),
+                    None => {
+                        if let Some(text) = &differential.lsb_decoded_text {
+                            push("differential", format!("LSB text {:?}", text), None, None);
+                        }
+                    }
+                }
+            }
+        }
+        FormatSpecificAnalysis::Text(text) => {
+            if let Some(ole) = &text.ole {
+                for object in ole
+                    .all_objects()
+                    .into_iter()
+                    .filter(|o| o.file_name.is_some() || is_file_type(&o.classification))
+                {
+                    let description = match &object.file_name {
+                        Some(name) => format!("{} packaged as {:?}", object.classification, name),
+                        None => object.classification.clone(),
+                    };
+                    push(
+                        "ole_object",
+                        format!(
+                            "{} in {} ({} bytes)",
+                            description, object.stream, object.size_bytes
+                        ),
+                        None,
+                        object.file.clone(),
+                    );
+                }
+            }
+            if let Some(ebook) = &text.ebook {
+                for member in ebook.extraneous() {
+                    let Some(classification) = &member.classification else {
+                        continue;
+                    };
+                    push(
+                        "ebook_member",
+                        format!(
+                            "{} not listed in the package ({} bytes, {})",
+                            member.path, member.size_bytes, classification
+                        ),
+                        None,
+                        member.file.clone(),
+                    );
+                }
+                for chapter in &ebook.chapters {
+                    let description = match (&chapter.zero_width_text, &chapter.zero_width_payload)
+                    {
+                        (Some(text), _) => format!("text {:?}", text),
+                        (None, Some(classification)) => classification.clone(),
+                        (None, None) => continue,
+                    };
+                    push(
+                        "zero_width",
+                        format!("{} in {}", description, chapter.path),
+                        None,
+                        None,
+                    );
+                }
+            }
+            if let Some(structured) = &text.structured {
+                for finding in &structured.findings {
+                    let Some(classification) = &finding.classification else {
+                        continue;
+                    };
+                    push(
+                        "structured_text",
+                        format!(
+                            "{} {} at {} ({}): {}",
+                            structured.format,
+                            finding.kind,
+                            finding.location,
+                            classification,
+                            finding.preview
+                        ),
+                        None,
+                        finding.file.clone(),
+                    );
+                }
+            }
+            if let Some(source_code) = &text.source_code {
+                for payload in &source_code.payloads {
+                    push(
+                        "source_comment",
+                        format!(
+                            "{} comment at lines {}-{} ({}): {}",
+                            source_code.language,
+                            payload.line,
+                            payload.end_line,
+                            payload.classification,
+                            payload.preview
+                        ),
+                        None,
+                        payload.file.clone(),
+                    );
+                }
+            }
+            if let Some(model) = &text.model {
+                for region in &model.regions {
+                    let description = match &region.text {
+                        Some(text) => format!("text {:?}", text),
+                        None => format!("{} bytes, {}", region.size_bytes, region.classification),
+                    };
+                    push(
+                        "model_region",
+                        format!("{} in {} {}", description, model.format, region.location),
+                        region.offset.map(|offset| offset as usize),
+                        region.file.clone(),
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+
+    payloads
+}
+
+// A MIME type rather than "text" or "binary": the bytes start like a known file format
+fn is_file_type(classification: &str) -> bool {
+    classification.contains('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_point_fields_are_optional() {
+        // An image section as the API stored it before the models were shared
+        let stored = r#"{
+            "type": "Image",
+            "exif_metadata": null,
+            "lsb_analysis": {
+                "is_suspicious": false,
+                "bit_depth": 8,
+                "color_model": "RGB",
+                "channels": []
+            },
+            "dimensions": { "width": 640, "height": 480 }
+        }"#;
+        let analysis: FormatSpecificAnalysis = serde_json::from_str(stored).unwrap();
+        let FormatSpecificAnalysis::Image(image) = &analysis else {
+            panic!("expected an image section");
+        };
+        assert_eq!(image.dimensions.width, 640);
+        assert!(image.filter_analysis.is_none());
+        assert!(image.lsb_analysis.as_ref().unwrap().output_files.is_empty());
+
+        // Unset optional fields stay out of the JSON
+        let json = serde_json::to_value(&analysis).unwrap();
+        assert!(json.get("filter_analysis").is_none());
+        assert!(json["lsb_analysis"].get("output_files").is_none());
+    }
+
+    #[test]
+    fn test_video_round_trip() {
+        let video = FormatSpecificAnalysis::Video(VideoAnalysis {
+            frames_processed: 120,
+            errors_encountered: 0,
+            suspicious_frames: vec![30, 90],
+            sampling: "one frame in every 30".to_string(),
+            scenes: Vec::new(),
+            mp4_tags: None,
+        });
+        let json = serde_json::to_string(&video).unwrap();
+        let FormatSpecificAnalysis::Video(parsed) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected a video section");
+        };
+        assert_eq!(parsed.suspicious_frames, vec![30, 90]);
+        assert_eq!(parsed.frames_processed, 120);
+    }
+
+    #[test]
+    fn test_annotation_labels() {
+        assert_eq!(
+            "false_positive".parse::<AnnotationLabel>(),
+            Ok(AnnotationLabel::FalsePositive)
+        );
+        assert_eq!(
+            "TP".parse::<AnnotationLabel>(),
+            Ok(AnnotationLabel::TruePositive)
+        );
+        assert!("maybe".parse::<AnnotationLabel>().is_err());
+
+        let annotation = Annotation {
+            rule: Some("lsb.chi_square".to_string()),
+            label: AnnotationLabel::Comment,
+            comment: None,
+            analyst: None,
+            added: "2024-03-01T00:00:00Z".to_string(),
+        };
+        assert!(!annotation.is_complete());
+        let json = serde_json::to_value(&annotation).unwrap();
+        assert_eq!(json["label"], "comment");
+        assert!(json.get("analyst").is_none());
+    }
+
+    #[test]
+    fn test_run_metadata_round_trip() {
+        let run = RunMetadata {
+            scanner: "stegascan 0.1.0".to_string(),
+            profile: None,
+            analyzers: vec![
+                AnalyzerRunReport {
+                    name: "lsb".to_string(),
+                    version: Some("0.1.0+r1".to_string()),
+                    parameters: BTreeMap::from([(
+                        "chi_square_threshold".to_string(),
+                        "0.05".to_string(),
+                    )]),
+                },
+                AnalyzerRunReport {
+                    name: "my_plugin".to_string(),
+                    version: None,
+                    parameters: BTreeMap::new(),
+                },
+            ],
+            signature_database: SignatureDatabaseReport {
+                version: "0123456789abcdef".to_string(),
+                signatures: 120,
+                custom_file: None,
+                custom_sha256: None,
+            },
+        };
+        let json = serde_json::to_value(&run).unwrap();
+        assert!(json.get("profile").is_none());
+        assert!(json["analyzers"][1].get("version").is_none());
+        assert!(json["analyzers"][1].get("parameters").is_none());
+        assert!(json["signature_database"].get("custom_file").is_none());
+
+        let parsed: RunMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            parsed.analyzers[0].parameters["chi_square_threshold"],
+            "0.05"
+        );
+        assert!(parsed.analyzers[1].parameters.is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// A member of a ZIP archive addressed as `<archive>!<member>` for --inner. Analyzers need a
+// file path, so the member is streamed out of the archive into a scratch directory that is
+// removed once the scan is done; nothing is extracted next to the archive.
+
+#[derive(Debug)]
+pub enum ArchiveMemberError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Syntax(String),
+    MissingMember(String),
+    NotAFile(String),
+    TooLarge(u64),
+}
+
+impl std::fmt::Display for ArchiveMemberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveMemberError::Io(e) => write!(f, "Archive I/O error: {}", e),
+            ArchiveMemberError::Zip(e) => write!(f, "Archive ZIP error: {}", e),
+            ArchiveMemberError::Syntax(spec) => {
+                write!(f, "Expected <archive>!<member>, got {}", spec)
+            }
+            ArchiveMemberError::MissingMember(name) => {
+                write!(f, "The archive has no member named {}", name)
+            }
+            ArchiveMemberError::NotAFile(name) => write!(f, "{} is a directory", name),
+            ArchiveMemberError::TooLarge(limit) => {
+                write!(f, "The member is over the configured limit of {} MB", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArchiveMemberError {}
+
+impl From<std::io::Error> for ArchiveMemberError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveMemberError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Zip(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerPath {
+    pub archive: PathBuf,
+    pub member: String,
+}
+
+impl FromStr for InnerPath {
+    type Err = ArchiveMemberError;
+
+    // Archive and member names may both contain `!`, so the split goes after the first
+    // prefix that names an existing file, falling back to the first `!`
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let splits: Vec<usize> = spec.match_indices('!').map(|(i, _)| i).collect();
+        let split = splits
+            .iter()
+            .copied()
+            .find(|&i| Path::new(&spec[..i]).is_file())
+            .or_else(|| splits.first().copied())
+            .ok_or_else(|| ArchiveMemberError::Syntax(spec.to_string()))?;
+
+        let (archive, member) = (&spec[..split], &spec[split + 1..]);
+        if archive.is_empty() || member.is_empty() {
+            return Err(ArchiveMemberError::Syntax(spec.to_string()));
+        }
+        Ok(Self {
+            archive: PathBuf::from(archive),
+            member: member.trim_start_matches('/').to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for InnerPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}!{}", self.archive.display(), self.member)
+    }
+}
+
+// The member streamed to a scratch file named like it, so parsers still pick the decoder
+// from the extension. The scratch directory goes away with the value.
+pub struct StagedMember {
+    _dir: tempfile::TempDir,
+    pub path: PathBuf,
+}
+
+// Stream the member out of the archive. A limit of 0 MB means no limit.
+pub fn stage(inner: &InnerPath, max_size_mb: u64) -> Result<StagedMember, ArchiveMemberError> {
+    let mut archive = zip::ZipArchive::new(File::open(&inner.archive)?)?;
+    let entry = match archive.by_name(&inner.member) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(ArchiveMemberError::MissingMember(inner.member.clone()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if entry.is_dir() {
+        return Err(ArchiveMemberError::NotAFile(inner.member.clone()));
+    }
+
+    // Only the last component is kept; member names can hold `..` and absolute paths
+    let file_name = Path::new(&inner.member)
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "member".into());
+    let dir = tempfile::Builder::new()
+        .prefix("stegascan-inner-")
+        .tempdir()?;
+    let staged = StagedMember {
+        path: dir.path().join(file_name),
+        _dir: dir,
+    };
+
+    // The size in the archive's headers is only the archive's claim, so the limit applies to
+    // the bytes actually decompressed
+    let limit = match max_size_mb {
+        0 => u64::MAX,
+        mb => mb.saturating_mul(1024 * 1024),
+    };
+    let written = std::io::copy(
+        &mut entry.take(limit.saturating_add(1)),
+        &mut File::create(&staged.path)?,
+    )?;
+    if written > limit {
+        return Err(ArchiveMemberError::TooLarge(max_size_mb));
+    }
+    Ok(staged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_stage_streams_member_and_cleans_up() {
+        let archive =
+            std::env::temp_dir().join(format!("stegascan-inner-test-{}.zip", std::process::id()));
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("evidence/../cover.png", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"\x89PNG\r\n\x1a\n not really").unwrap();
+        zip.finish().unwrap();
+
+        let inner: InnerPath = format!("{}!/evidence/../cover.png", archive.display())
+            .parse()
+            .unwrap();
+        assert_eq!(inner.archive, archive);
+        assert_eq!(inner.member, "evidence/../cover.png");
+
+        let staged = stage(&inner, 0).unwrap();
+        let path = staged.path.clone();
+        assert_eq!(path.file_name().unwrap(), "cover.png");
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"\x89PNG\r\n\x1a\n not really"
+        );
+        drop(staged);
+        assert!(!path.exists());
+
+        let missing: InnerPath = format!("{}!other.png", archive.display()).parse().unwrap();
+        assert!(matches!(
+            stage(&missing, 0),
+            Err(ArchiveMemberError::MissingMember(_))
+        ));
+        assert!("no-separator.zip".parse::<InnerPath>().is_err());
+
+        std::fs::remove_file(archive).unwrap();
+    }
+
+    #[test]
+    fn test_stage_stops_at_limit_whatever_the_header_says() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bomb.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("zeros.bin", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&vec![0u8; 1024 * 1024 + 1]).unwrap();
+        zip.finish().unwrap();
+
+        let inner: InnerPath = format!("{}!zeros.bin", archive.display()).parse().unwrap();
+        assert!(matches!(
+            stage(&inner, 1),
+            Err(ArchiveMemberError::TooLarge(1))
+        ));
+        assert_eq!(
+            stage(&inner, 2).unwrap().path.metadata().unwrap().len(),
+            1024 * 1024 + 1
+        );
+    }
+}
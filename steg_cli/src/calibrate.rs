@@ -0,0 +1,148 @@
+use crate::config::{DEFAULT_CALIBRATION_FILE, Settings};
+use crate::walker;
+use analyzers::Analyzer;
+use analyzers::content_classifier::ContentClassifier;
+use analyzers::cover_baseline::{
+    AudioBaseline, Calibration, CoverBaselines, CoverFormat, CoverSample,
+};
+use analyzers::lsb_analyzer::LsbAnalyzer;
+use analyzers::shared;
+use analyzers::spectrogram_analyzer::{SpectrogramAnalyzer, analyze_tiles};
+use parsers::audio_parser::AudioParser;
+use parsers::{Parser as _, image_parser::ImageParser};
+use std::path::{Path, PathBuf};
+
+// `stegascan calibrate`: what a site's known-clean files look like to the statistical
+// analyzers, so scans flag files that stray from them rather than from thresholds tuned on
+// someone else's content. Images give LSB chi-square and entropy per format and content class,
+// one sample per channel; recordings give high-frequency spectral energy, one sample per
+// spectrogram window. Statistics the corpus has no files for are kept from the existing
+// calibration, so one can be built up a corpus at a time.
+
+pub fn run(
+    clean_dir: &Path,
+    path: Option<&Path>,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files = walker::walk(clean_dir, &[], &[], None)?;
+
+    let mut cover_samples = Vec::new();
+    let mut energies = Vec::new();
+    let (mut images, mut recordings, mut skipped) = (0, 0, 0);
+    for file in &files {
+        let mime = match shared::infer().get_from_path(file) {
+            Ok(Some(kind)) => kind.mime_type(),
+            _ => "",
+        };
+        let measured = if mime.starts_with("image/") {
+            measure_image(file, settings).map(|samples| {
+                images += 1;
+                cover_samples.extend(samples);
+            })
+        } else if mime.starts_with("audio/") {
+            measure_recording(file, settings).map(|samples| {
+                recordings += 1;
+                energies.extend(samples);
+            })
+        } else {
+            None
+        };
+        if measured.is_none() {
+            skipped += 1;
+        }
+    }
+    if cover_samples.is_empty() && energies.is_empty() {
+        return Err(format!(
+            "No images or recordings could be decoded in {}",
+            clean_dir.display()
+        )
+        .into());
+    }
+
+    let measured = Calibration {
+        baselines: CoverBaselines::measure(&cover_samples),
+        audio: AudioBaseline::measure(&energies),
+    };
+    println!(
+        "Measured {} images and {} recordings in {} ({} other files skipped)",
+        images,
+        recordings,
+        clean_dir.display(),
+        skipped
+    );
+    for baseline in &measured.baselines {
+        println!(
+            "  {}/{}: {} channels, chi-square per pair {:.4} ± {:.4}, entropy {:.4} ± {:.4}",
+            baseline.format,
+            baseline.content,
+            baseline.samples,
+            baseline.chi_square.mean,
+            baseline.chi_square.std_dev,
+            baseline.entropy.mean,
+            baseline.entropy.std_dev
+        );
+    }
+    if let Some(audio) = &measured.audio {
+        println!(
+            "  audio: {} windows, high frequency energy {:.4} ± {:.4}, flagged above {:.4}",
+            audio.samples,
+            audio.high_frequency_energy.mean,
+            audio.high_frequency_energy.std_dev,
+            audio.high_frequency_energy_threshold(settings.thresholds.calibration_sigma)
+        );
+    }
+
+    let path = path
+        .map(Path::to_path_buf)
+        .or_else(|| settings.thresholds.calibration_file.clone())
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CALIBRATION_FILE));
+    let mut calibration = if path.exists() {
+        Calibration::read(&path)?
+    } else {
+        Calibration::default()
+    };
+    calibration.update(measured);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, calibration.to_yaml()?)?;
+    println!("Calibration written to {}", path.display());
+    Ok(())
+}
+
+// The channels of a clean image, analyzed the way a scan analyzes them
+fn measure_image(file: &Path, settings: &Settings) -> Option<Vec<CoverSample>> {
+    let image = ImageParser::parse_path(&file).ok()?;
+    // CMYK/YCCK JPEGs on their stored channels
+    let lsb = match ImageParser::parse_native_jpeg(file).ok().flatten() {
+        Some(native) => {
+            LsbAnalyzer::analyze_interleaved(&native.data, native.color_model.channel_names())
+        }
+        None => LsbAnalyzer::analyze(image.clone()),
+    }
+    .ok()?;
+    let content = ContentClassifier::analyze(image).ok()?;
+    let class = content.class_at(settings.thresholds.content_flat_ratio);
+    Some(CoverSample::from_analysis(
+        CoverFormat::from_path(file),
+        class,
+        &lsb,
+    ))
+}
+
+// High-frequency energy of each spectrogram window of a clean recording
+fn measure_recording(file: &Path, settings: &Settings) -> Option<Vec<f64>> {
+    let (samples, sample_rate) = AudioParser::parse_with_sample_rate(&file).ok()?;
+    let tile_seconds = settings.audio.spectrogram_tile_seconds;
+    if tile_seconds > 0 && samples.len() > sample_rate as usize * tile_seconds as usize {
+        let tiles = analyze_tiles(&samples, sample_rate, tile_seconds).ok()?;
+        return Some(
+            tiles
+                .iter()
+                .map(|tile| tile.data.high_frequency_energy)
+                .collect(),
+        );
+    }
+    let spectrogram = SpectrogramAnalyzer::analyze((samples, sample_rate)).ok()?;
+    Some(vec![spectrogram.high_frequency_energy])
+}
@@ -0,0 +1,1293 @@
+use analyzers::shared;
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+// Packet captures for --pcap: the TCP streams of a pcap or pcapng file are reassembled,
+// HTTP request and response bodies (multipart uploads included) and SMTP messages are
+// decoded, and the media files among them are written out to be scanned one by one. Each
+// carved file keeps a link to the flow that carried it, so the results can be read per
+// conversation.
+//
+// Only what is needed for carving is parsed: Ethernet, Linux cooked and raw IP captures,
+// IPv4 and IPv6, and TCP. Fragmented IP packets and non-TCP traffic are counted and left
+// out.
+
+// pcap link types
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+// Gaps in a stream larger than this are garbage sequence numbers, not lost packets
+const MAX_SEQUENCE_GAP: u64 = 1 << 30;
+
+// Nesting limit for multipart bodies and messages inside messages
+const MAX_MIME_DEPTH: usize = 8;
+
+// Decompressed HTTP bodies are cut off here
+const MAX_DECODED_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    Http,
+    Smtp,
+    Other,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Http => write!(f, "HTTP"),
+            Protocol::Smtp => write!(f, "SMTP"),
+            Protocol::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    // Uploads, form posts and sent mail
+    ClientToServer,
+    // Downloads
+    ServerToClient,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Flow {
+    pub index: usize,
+    // The side that opened the connection, or the one on the higher port when the capture
+    // started after the handshake
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub protocol: Protocol,
+    pub first_packet: String,
+    pub last_packet: String,
+    pub packets: usize,
+    pub client_bytes: usize,
+    pub server_bytes: usize,
+    // Stream bytes no captured segment carried; carved files may be damaged when non-zero
+    pub missing_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarvedObject {
+    pub flow: usize,
+    pub direction: Direction,
+    // File name from the upload, attachment or URL
+    pub name: String,
+    pub content_type: Option<String>,
+    pub size_bytes: usize,
+    pub extracted: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Capture {
+    pub packets: usize,
+    // Packets that weren't TCP over IP, or were IP fragments
+    pub skipped_packets: usize,
+    pub flows: Vec<Flow>,
+    pub objects: Vec<CarvedObject>,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "Packet capture I/O error: {}", e),
+            CaptureError::Invalid(e) => write!(f, "Invalid packet capture: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// Reassemble the capture's TCP flows and write the media files carried over HTTP and SMTP
+// below `dest`, as flow<N>/<index>_<name>
+pub fn carve(capture: &Path, dest: &Path) -> Result<Capture, CaptureError> {
+    let data = std::fs::read(capture)?;
+    let packets = read_packets(&data)?;
+
+    let mut skipped_packets = 0;
+    let mut builders: Vec<FlowBuilder> = Vec::new();
+    let mut by_endpoints: HashMap<(SocketAddr, SocketAddr), usize> = HashMap::new();
+    for packet in &packets {
+        let Some(segment) = tcp_segment(packet) else {
+            skipped_packets += 1;
+            continue;
+        };
+        let key = if segment.source <= segment.destination {
+            (segment.source, segment.destination)
+        } else {
+            (segment.destination, segment.source)
+        };
+        let index = *by_endpoints.entry(key).or_insert_with(|| {
+            builders.push(FlowBuilder::default());
+            builders.len() - 1
+        });
+        builders[index].add(segment);
+    }
+
+    let mut flows = Vec::new();
+    let mut objects = Vec::new();
+    for (index, builder) in builders.into_iter().enumerate() {
+        let (flow, client_stream, server_stream) = builder.finish(index);
+        let carved = match flow.protocol {
+            Protocol::Http => http_objects(&client_stream, &server_stream),
+            Protocol::Smtp => smtp_objects(&client_stream),
+            Protocol::Other => Vec::new(),
+        };
+
+        let flow_dir = dest.join(format!("flow{}", index));
+        for (number, object) in carved.into_iter().filter(is_media).enumerate() {
+            std::fs::create_dir_all(&flow_dir)?;
+            let extracted = flow_dir.join(format!("{:03}_{}", number, file_name(&object)));
+            std::fs::write(&extracted, &object.data)?;
+            objects.push(CarvedObject {
+                flow: index,
+                direction: object.direction,
+                name: object.name,
+                content_type: object.content_type,
+                size_bytes: object.data.len(),
+                extracted,
+            });
+        }
+        flows.push(flow);
+    }
+
+    Ok(Capture {
+        packets: packets.len(),
+        skipped_packets,
+        flows,
+        objects,
+    })
+}
+
+struct Packet<'a> {
+    // Seconds since the epoch
+    timestamp: f64,
+    link_type: u32,
+    data: &'a [u8],
+}
+
+fn read_packets(data: &[u8]) -> Result<Vec<Packet<'_>>, CaptureError> {
+    if data.len() < 24 {
+        return Err(CaptureError::Invalid("file too short".to_string()));
+    }
+    match data[0..4] {
+        [0x0A, 0x0D, 0x0D, 0x0A] => pcapng_packets(data),
+        _ => classic_packets(data),
+    }
+}
+
+fn classic_packets(data: &[u8]) -> Result<Vec<Packet<'_>>, CaptureError> {
+    // Written in the capturing machine's byte order, with micro- or nanosecond timestamps
+    let (big_endian, nanoseconds) = match data[0..4] {
+        [0xD4, 0xC3, 0xB2, 0xA1] => (false, false),
+        [0xA1, 0xB2, 0xC3, 0xD4] => (true, false),
+        [0x4D, 0x3C, 0xB2, 0xA1] => (false, true),
+        [0xA1, 0xB2, 0x3C, 0x4D] => (true, true),
+        _ => {
+            return Err(CaptureError::Invalid(
+                "not a pcap or pcapng file".to_string(),
+            ));
+        }
+    };
+    let read_u32 = |at: usize| {
+        let bytes: [u8; 4] = data[at..at + 4].try_into().unwrap();
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let link_type = read_u32(20) & 0xFFFF;
+
+    let mut packets = Vec::new();
+    let mut position = 24;
+    while position + 16 <= data.len() {
+        let fraction = read_u32(position + 4) as f64;
+        let timestamp = read_u32(position) as f64 + fraction / if nanoseconds { 1e9 } else { 1e6 };
+        let length = read_u32(position + 8) as usize;
+        let start = position + 16;
+        if start + length > data.len() {
+            log::warn!("Capture truncated in the packet at byte {}", position);
+            break;
+        }
+        packets.push(Packet {
+            timestamp,
+            link_type,
+            data: &data[start..start + length],
+        });
+        position = start + length;
+    }
+    Ok(packets)
+}
+
+fn pcapng_packets(data: &[u8]) -> Result<Vec<Packet<'_>>, CaptureError> {
+    let mut packets = Vec::new();
+    let mut big_endian = false;
+    // Link type and timestamp units per second of each interface in the current section
+    let mut interfaces: Vec<(u32, f64)> = Vec::new();
+    let mut position = 0;
+    while position + 12 <= data.len() {
+        let block = &data[position..];
+        if block[0..4] == [0x0A, 0x0D, 0x0D, 0x0A] {
+            big_endian = match block[8..12] {
+                [0x1A, 0x2B, 0x3C, 0x4D] => true,
+                [0x4D, 0x3C, 0x2B, 0x1A] => false,
+                _ => {
+                    return Err(CaptureError::Invalid(
+                        "bad pcapng byte-order magic".to_string(),
+                    ));
+                }
+            };
+            interfaces.clear();
+        }
+        let read_u32 = |at: usize| {
+            let bytes: [u8; 4] = block[at..at + 4].try_into().unwrap();
+            if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            }
+        };
+        let kind = read_u32(0);
+        let length = read_u32(4) as usize;
+        if length < 12 || length > block.len() {
+            log::warn!("Capture truncated in the block at byte {}", position);
+            break;
+        }
+        let body = &block[8..length - 4];
+
+        match kind {
+            // Interface description
+            1 if body.len() >= 8 => {
+                let link_type = if big_endian {
+                    u16::from_be_bytes([body[0], body[1]])
+                } else {
+                    u16::from_le_bytes([body[0], body[1]])
+                };
+                interfaces.push((
+                    link_type as u32,
+                    interface_resolution(&body[8..], big_endian),
+                ));
+            }
+            // Enhanced packet
+            6 if body.len() >= 20 => {
+                let interface = read_u32(8) as usize;
+                let ticks = ((read_u32(12) as u64) << 32) | read_u32(16) as u64;
+                let captured = (read_u32(20) as usize).min(body.len() - 20);
+                if let Some(&(link_type, resolution)) = interfaces.get(interface) {
+                    packets.push(Packet {
+                        timestamp: ticks as f64 / resolution,
+                        link_type,
+                        data: &body[20..20 + captured],
+                    });
+                }
+            }
+            // Simple packet, always from the first interface and without a timestamp
+            3 if body.len() >= 4 => {
+                if let Some(&(link_type, _)) = interfaces.first() {
+                    let original = read_u32(8) as usize;
+                    packets.push(Packet {
+                        timestamp: 0.0,
+                        link_type,
+                        data: &body[4..4 + original.min(body.len() - 4)],
+                    });
+                }
+            }
+            _ => {}
+        }
+        position += length;
+    }
+    Ok(packets)
+}
+
+// Timestamp units per second from an interface's if_tsresol option, microseconds if unset
+fn interface_resolution(mut options: &[u8], big_endian: bool) -> f64 {
+    while options.len() >= 4 {
+        let (code, length) = if big_endian {
+            (
+                u16::from_be_bytes([options[0], options[1]]),
+                u16::from_be_bytes([options[2], options[3]]) as usize,
+            )
+        } else {
+            (
+                u16::from_le_bytes([options[0], options[1]]),
+                u16::from_le_bytes([options[2], options[3]]) as usize,
+            )
+        };
+        if code == 0 || options.len() < 4 + length {
+            break;
+        }
+        if code == 9 && length >= 1 {
+            let value = options[4];
+            // High bit set: a power of two instead of ten
+            return if value & 0x80 != 0 {
+                2f64.powi((value & 0x7F) as i32)
+            } else {
+                10f64.powi(value as i32)
+            };
+        }
+        options = &options[4 + length.div_ceil(4) * 4..];
+    }
+    1e6
+}
+
+struct Segment<'a> {
+    timestamp: f64,
+    source: SocketAddr,
+    destination: SocketAddr,
+    sequence: u32,
+    // SYN without ACK: the sender is the client
+    opening: bool,
+    syn: bool,
+    payload: &'a [u8],
+}
+
+fn tcp_segment<'a>(packet: &Packet<'a>) -> Option<Segment<'a>> {
+    let (ethertype, ip) = match packet.link_type {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = u16::from_be_bytes([*packet.data.get(12)?, *packet.data.get(13)?]);
+            let mut offset = 14;
+            // VLAN tags, possibly stacked
+            while matches!(ethertype, 0x8100 | 0x88A8) {
+                ethertype = u16::from_be_bytes([
+                    *packet.data.get(offset + 2)?,
+                    *packet.data.get(offset + 3)?,
+                ]);
+                offset += 4;
+            }
+            (ethertype, packet.data.get(offset..)?)
+        }
+        LINKTYPE_LINUX_SLL => (
+            u16::from_be_bytes([*packet.data.get(14)?, *packet.data.get(15)?]),
+            packet.data.get(16..)?,
+        ),
+        LINKTYPE_LINUX_SLL2 => (
+            u16::from_be_bytes([*packet.data.first()?, *packet.data.get(1)?]),
+            packet.data.get(20..)?,
+        ),
+        LINKTYPE_RAW => match packet.data.first()? >> 4 {
+            4 => (0x0800, packet.data),
+            6 => (0x86DD, packet.data),
+            _ => return None,
+        },
+        // Loopback, with the address family in host byte order
+        LINKTYPE_NULL => match packet.data.get(0..4)? {
+            [2, 0, 0, 0] | [0, 0, 0, 2] => (0x0800, packet.data.get(4..)?),
+            _ => (0x86DD, packet.data.get(4..)?),
+        },
+        _ => return None,
+    };
+
+    let (source, destination, tcp) = match ethertype {
+        0x0800 => {
+            let header_length = ((ip.first()? & 0x0F) as usize) * 4;
+            let total_length = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            // More fragments, or an offset into the datagram
+            if fragment & 0x3FFF != 0 || *ip.get(9)? != 6 || header_length < 20 {
+                return None;
+            }
+            let source: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            // Ethernet pads short frames; the IP length says where the packet really ends
+            let tcp = ip.get(header_length..)?;
+            let end = total_length.saturating_sub(header_length).min(tcp.len());
+            (
+                IpAddr::V4(Ipv4Addr::from(source)),
+                IpAddr::V4(Ipv4Addr::from(destination)),
+                &tcp[..end],
+            )
+        }
+        0x86DD => {
+            let payload_length = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            let source: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let mut next = *ip.get(6)?;
+            let mut offset = 40;
+            // Hop-by-hop, routing and destination options headers sit before TCP
+            while matches!(next, 0 | 43 | 60) {
+                next = *ip.get(offset)?;
+                offset += (*ip.get(offset + 1)? as usize + 1) * 8;
+            }
+            if next != 6 {
+                return None;
+            }
+            let tcp = ip.get(offset..)?;
+            let end = (40 + payload_length).saturating_sub(offset).min(tcp.len());
+            (
+                IpAddr::V6(Ipv6Addr::from(source)),
+                IpAddr::V6(Ipv6Addr::from(destination)),
+                &tcp[..end],
+            )
+        }
+        _ => return None,
+    };
+
+    let data_offset = ((tcp.get(12)? >> 4) as usize) * 4;
+    let flags = *tcp.get(13)?;
+    let syn = flags & 0x02 != 0;
+    Some(Segment {
+        timestamp: packet.timestamp,
+        source: SocketAddr::new(source, u16::from_be_bytes([tcp[0], tcp[1]])),
+        destination: SocketAddr::new(destination, u16::from_be_bytes([tcp[2], tcp[3]])),
+        sequence: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        opening: syn && flags & 0x10 == 0,
+        syn,
+        payload: tcp.get(data_offset.max(20)..)?,
+    })
+}
+
+#[derive(Default)]
+struct HalfStream<'a> {
+    initial_sequence: Option<u32>,
+    segments: Vec<(u32, &'a [u8])>,
+    bytes: usize,
+}
+
+impl HalfStream<'_> {
+    // The payload in sequence order, each byte once, and how many bytes were never seen
+    fn reassemble(mut self) -> (Vec<u8>, u64) {
+        self.segments.retain(|(_, payload)| !payload.is_empty());
+        let Some(&(first, _)) = self.segments.first() else {
+            return (Vec::new(), 0);
+        };
+        // Offsets relative to the first sequence number, allowing for wrap-around
+        let relative = |sequence: u32| sequence.wrapping_sub(first) as i32 as i64;
+        let base = match self.initial_sequence {
+            Some(isn) => relative(isn.wrapping_add(1)),
+            None => self
+                .segments
+                .iter()
+                .map(|&(sequence, _)| relative(sequence))
+                .min()
+                .unwrap_or(0),
+        };
+        let mut ordered: Vec<(i64, &[u8])> = self
+            .segments
+            .iter()
+            .map(|&(sequence, payload)| (relative(sequence) - base, payload))
+            .filter(|&(offset, _)| offset >= 0)
+            .collect();
+        ordered.sort_by_key(|&(offset, _)| offset);
+
+        let mut stream = Vec::new();
+        let mut covered: u64 = 0;
+        let mut missing = 0;
+        for (offset, payload) in ordered {
+            let offset = offset as u64;
+            let end = offset + payload.len() as u64;
+            if end <= covered {
+                // Retransmission of data already placed
+                continue;
+            }
+            if offset > covered {
+                if offset - covered > MAX_SEQUENCE_GAP {
+                    continue;
+                }
+                missing += offset - covered;
+                stream.extend_from_slice(payload);
+            } else {
+                stream.extend_from_slice(&payload[(covered - offset) as usize..]);
+            }
+            covered = end;
+        }
+        (stream, missing)
+    }
+}
+
+#[derive(Default)]
+struct FlowBuilder<'a> {
+    first_packet: f64,
+    last_packet: f64,
+    packets: usize,
+    opener: Option<SocketAddr>,
+    halves: Vec<(SocketAddr, HalfStream<'a>)>,
+}
+
+impl<'a> FlowBuilder<'a> {
+    fn add(&mut self, segment: Segment<'a>) {
+        if self.packets == 0 {
+            self.first_packet = segment.timestamp;
+        }
+        self.packets += 1;
+        self.last_packet = self.last_packet.max(segment.timestamp);
+        if segment.opening && self.opener.is_none() {
+            self.opener = Some(segment.source);
+        }
+
+        let position = match self
+            .halves
+            .iter()
+            .position(|(source, _)| *source == segment.source)
+        {
+            Some(position) => position,
+            None => {
+                self.halves.push((segment.source, HalfStream::default()));
+                self.halves.len() - 1
+            }
+        };
+        let half = &mut self.halves[position].1;
+        if segment.syn {
+            half.initial_sequence = Some(segment.sequence);
+        }
+        half.bytes += segment.payload.len();
+        half.segments.push((segment.sequence, segment.payload));
+    }
+
+    // The flow and its client and server streams
+    fn finish(mut self, index: usize) -> (Flow, Vec<u8>, Vec<u8>) {
+        let endpoints: Vec<SocketAddr> = self.halves.iter().map(|(source, _)| *source).collect();
+        let (client, server) = match (self.opener, endpoints.as_slice()) {
+            (Some(opener), _) => {
+                let other = endpoints.iter().find(|&&e| e != opener).copied();
+                (opener, other.unwrap_or(opener))
+            }
+            (None, [a, b]) if a.port() < b.port() => (*b, *a),
+            (None, [a, b]) => (*a, *b),
+            (None, [a]) => (*a, *a),
+            _ => unreachable!("a flow has one or two endpoints"),
+        };
+
+        let mut take = |endpoint: SocketAddr| {
+            self.halves
+                .iter()
+                .position(|(source, _)| *source == endpoint)
+                .map(|position| self.halves.swap_remove(position).1)
+                .unwrap_or_default()
+        };
+        let client_half = take(client);
+        let server_half = if server == client {
+            HalfStream::default()
+        } else {
+            take(server)
+        };
+        let (client_bytes, server_bytes) = (client_half.bytes, server_half.bytes);
+        let (client_stream, client_missing) = client_half.reassemble();
+        let (server_stream, server_missing) = server_half.reassemble();
+
+        let flow = Flow {
+            index,
+            client,
+            server,
+            protocol: detect_protocol(&client_stream, &server_stream),
+            first_packet: timestamp(self.first_packet),
+            last_packet: timestamp(self.last_packet),
+            packets: self.packets,
+            client_bytes,
+            server_bytes,
+            missing_bytes: client_missing + server_missing,
+        };
+        (flow, client_stream, server_stream)
+    }
+}
+
+fn timestamp(seconds: f64) -> String {
+    chrono::DateTime::from_timestamp(seconds.floor() as i64, (seconds.fract() * 1e9) as u32)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default()
+}
+
+// By what is said rather than by port, so HTTP on 8080 or mail on 2525 is still found
+fn detect_protocol(client: &[u8], server: &[u8]) -> Protocol {
+    const METHODS: [&[u8]; 7] = [
+        b"GET ",
+        b"POST ",
+        b"PUT ",
+        b"HEAD ",
+        b"PATCH ",
+        b"DELETE ",
+        b"OPTIONS ",
+    ];
+    if METHODS.iter().any(|method| client.starts_with(method)) {
+        return Protocol::Http;
+    }
+    let commands = String::from_utf8_lossy(&client[..client.len().min(4096)]).to_uppercase();
+    if server.starts_with(b"220") && commands.contains("MAIL FROM") {
+        return Protocol::Smtp;
+    }
+    Protocol::Other
+}
+
+// A file carried by a flow, before it is known whether it is worth scanning
+struct Carved {
+    direction: Direction,
+    name: String,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+fn is_media(object: &Carved) -> bool {
+    let sniffed = shared::infer()
+        .get(&object.data)
+        .map(|kind| kind.mime_type())
+        .or(object.content_type.as_deref());
+    sniffed.is_some_and(|mime| {
+        mime.starts_with("image/") || mime.starts_with("audio/") || mime.starts_with("video/")
+    })
+}
+
+// A name that is safe as a file name, with the sniffed extension when the original has none
+fn file_name(object: &Carved) -> String {
+    let mut name: String = object
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(100)
+        .collect();
+    name = name.trim_start_matches('.').to_string();
+    if name.is_empty() {
+        name = "object".to_string();
+    }
+    if !name.contains('.')
+        && let Some(kind) = shared::infer().get(&object.data)
+    {
+        name = format!("{}.{}", name, kind.extension());
+    }
+    name
+}
+
+struct HttpMessage {
+    start_line: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpMessage {
+    fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn http_objects(client: &[u8], server: &[u8]) -> Vec<Carved> {
+    let requests = http_messages(client, &[]);
+    let methods: Vec<String> = requests
+        .iter()
+        .map(|request| {
+            request
+                .start_line
+                .split(' ')
+                .next()
+                .unwrap_or("")
+                .to_string()
+        })
+        .collect();
+    let responses = http_messages(server, &methods);
+
+    let mut objects = Vec::new();
+    let mut targets = Vec::new();
+    for request in &requests {
+        let target = request.start_line.split(' ').nth(1).unwrap_or("");
+        targets.push(url_file_name(target));
+        if request.body.is_empty() {
+            continue;
+        }
+        let content_type = request.header("Content-Type").map(str::to_string);
+        let name = targets.last().cloned().unwrap_or_default();
+        objects.extend(body_objects(
+            Direction::ClientToServer,
+            &name,
+            content_type,
+            &request.body,
+        ));
+    }
+
+    // Interim 1xx responses don't answer a request, so they don't advance the pairing
+    let mut answered = 0;
+    for response in &responses {
+        let status = response.start_line.split(' ').nth(1).unwrap_or("");
+        if status.starts_with('1') {
+            continue;
+        }
+        let name = targets.get(answered).cloned().unwrap_or_default();
+        answered += 1;
+        if response.body.is_empty() {
+            continue;
+        }
+        let content_type = response.header("Content-Type").map(str::to_string);
+        objects.extend(body_objects(
+            Direction::ServerToClient,
+            &name,
+            content_type,
+            &response.body,
+        ));
+    }
+    objects
+}
+
+// Last path segment of a request target, without the query
+fn url_file_name(target: &str) -> String {
+    let path = target.split(['?', '#']).next().unwrap_or("");
+    path.rsplit('/').next().unwrap_or("").to_string()
+}
+
+// A body as one object, or one per part for multipart bodies
+fn body_objects(
+    direction: Direction,
+    name: &str,
+    content_type: Option<String>,
+    body: &[u8],
+) -> Vec<Carved> {
+    let is_multipart = content_type
+        .as_deref()
+        .is_some_and(|t| t.trim_start().to_lowercase().starts_with("multipart/"));
+    if is_multipart {
+        let headers = vec![("Content-Type".to_string(), content_type.unwrap_or_default())];
+        return mime_leaves(&headers, body, 0)
+            .into_iter()
+            .map(|leaf| Carved {
+                direction,
+                name: leaf.name.unwrap_or_else(|| name.to_string()),
+                content_type: leaf.content_type,
+                data: leaf.data,
+            })
+            .collect();
+    }
+    vec![Carved {
+        direction,
+        name: name.to_string(),
+        content_type,
+        data: body.to_vec(),
+    }]
+}
+
+// Consecutive HTTP/1.x messages in one direction of a flow. For responses, `request_methods`
+// are the methods of the requests they answer, since a reply to HEAD has no body.
+fn http_messages(stream: &[u8], request_methods: &[String]) -> Vec<HttpMessage> {
+    let is_response = stream.starts_with(b"HTTP/");
+    let mut messages = Vec::new();
+    let mut position = 0;
+    let mut answered = 0;
+    while position < stream.len() {
+        let Some(header_end) = find(&stream[position..], b"\r\n\r\n") else {
+            break;
+        };
+        let head = String::from_utf8_lossy(&stream[position..position + header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next().unwrap_or("").to_string();
+        let headers = parse_headers(lines);
+        position += header_end + 4;
+
+        let status = start_line.split(' ').nth(1).unwrap_or("");
+        let interim = is_response && status.starts_with('1');
+        let bodiless = is_response
+            && (interim
+                || status == "204"
+                || status == "304"
+                || request_methods
+                    .get(answered)
+                    .is_some_and(|method| method == "HEAD"));
+        if is_response && !interim {
+            answered += 1;
+        }
+
+        let chunked = header(&headers, "Transfer-Encoding")
+            .is_some_and(|value| value.to_lowercase().contains("chunked"));
+        let length: Option<usize> =
+            header(&headers, "Content-Length").and_then(|v| v.trim().parse().ok());
+        let mut body = if bodiless {
+            Vec::new()
+        } else if chunked {
+            let (body, consumed) = dechunk(&stream[position..]);
+            position += consumed;
+            body
+        } else if let Some(length) = length {
+            let end = position.saturating_add(length).min(stream.len());
+            let body = stream[position..end].to_vec();
+            position = end;
+            body
+        } else if is_response {
+            // Delimited by the server closing the connection
+            let body = stream[position..].to_vec();
+            position = stream.len();
+            body
+        } else {
+            Vec::new()
+        };
+
+        if let Some(encoding) = header(&headers, "Content-Encoding") {
+            body = decode_content(encoding, body);
+        }
+        messages.push(HttpMessage {
+            start_line,
+            headers,
+            body,
+        });
+    }
+    messages
+}
+
+// The body and the number of stream bytes it took, trailers included
+fn dechunk(stream: &[u8]) -> (Vec<u8>, usize) {
+    let mut body = Vec::new();
+    let mut position = 0;
+    while let Some(line_end) = find(&stream[position..], b"\r\n") {
+        let line = String::from_utf8_lossy(&stream[position..position + line_end]).to_string();
+        let size_field = line.split(';').next().unwrap_or("").trim();
+        let Ok(size) = usize::from_str_radix(size_field, 16) else {
+            break;
+        };
+        position += line_end + 2;
+        if size == 0 {
+            // Trailers, if any, end with an empty line
+            position = if stream[position..].starts_with(b"\r\n") {
+                position + 2
+            } else {
+                find(&stream[position..], b"\r\n\r\n")
+                    .map(|end| position + end + 4)
+                    .unwrap_or(stream.len())
+            };
+            break;
+        }
+        let end = position.saturating_add(size).min(stream.len());
+        body.extend_from_slice(&stream[position..end]);
+        position = (end + 2).min(stream.len());
+    }
+    (body, position)
+}
+
+fn decode_content(encoding: &str, body: Vec<u8>) -> Vec<u8> {
+    let encoding = encoding.trim().to_lowercase();
+    let mut decoded = Vec::new();
+    let result = match encoding.as_str() {
+        "gzip" | "x-gzip" => GzDecoder::new(body.as_slice())
+            .take(MAX_DECODED_BYTES)
+            .read_to_end(&mut decoded),
+        "deflate" => ZlibDecoder::new(body.as_slice())
+            .take(MAX_DECODED_BYTES)
+            .read_to_end(&mut decoded),
+        _ => return body,
+    };
+    match result {
+        Ok(_) => decoded,
+        // A body cut short by lost packets still decodes up to the damage
+        Err(_) if !decoded.is_empty() => decoded,
+        Err(e) => {
+            log::warn!("Could not decode {} body: {}", encoding, e);
+            body
+        }
+    }
+}
+
+fn smtp_objects(client: &[u8]) -> Vec<Carved> {
+    let mut objects = Vec::new();
+    let mut position = 0;
+    while let Some(line_end) = find(&client[position..], b"\r\n") {
+        let line = &client[position..position + line_end];
+        position += line_end + 2;
+        if !line.eq_ignore_ascii_case(b"DATA") {
+            continue;
+        }
+        // The message runs to a line holding a single dot
+        let end = find(&client[position..], b"\r\n.\r\n")
+            .map(|end| position + end + 2)
+            .unwrap_or(client.len());
+        let message = unstuff(&client[position..end]);
+        position = (end + 3).min(client.len());
+
+        let (headers, body) = split_entity(&message);
+        for (number, leaf) in mime_leaves(&headers, body, 0).into_iter().enumerate() {
+            objects.push(Carved {
+                direction: Direction::ClientToServer,
+                name: leaf.name.unwrap_or_else(|| format!("part{}", number)),
+                content_type: leaf.content_type,
+                data: leaf.data,
+            });
+        }
+    }
+    objects
+}
+
+// Undo SMTP dot-stuffing: a line starting with a dot was sent with a second one
+fn unstuff(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len());
+    let mut line_start = true;
+    for (index, &byte) in message.iter().enumerate() {
+        if line_start && byte == b'.' && message.get(index + 1) == Some(&b'.') {
+            line_start = false;
+            continue;
+        }
+        out.push(byte);
+        line_start = byte == b'\n';
+    }
+    out
+}
+
+struct MimeLeaf {
+    name: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+// Headers and body of a MIME entity
+fn split_entity(entity: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let (head, body) = match find(entity, b"\r\n\r\n") {
+        Some(end) => (&entity[..end], &entity[end + 4..]),
+        None => match find(entity, b"\n\n") {
+            Some(end) => (&entity[..end], &entity[end + 2..]),
+            None => (entity, &entity[entity.len()..]),
+        },
+    };
+    let head = String::from_utf8_lossy(head).replace("\r\n", "\n");
+    // Unfold continuation lines
+    let unfolded = head.replace("\n ", " ").replace("\n\t", " ");
+    (parse_headers(unfolded.split('\n')), body)
+}
+
+fn parse_headers<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+// The non-multipart parts of an entity, decoded, depth first
+fn mime_leaves(headers: &[(String, String)], body: &[u8], depth: usize) -> Vec<MimeLeaf> {
+    let content_type = header(headers, "Content-Type");
+    let media_type =
+        content_type.map(|value| value.split(';').next().unwrap_or("").trim().to_lowercase());
+    let boundary = content_type.and_then(|value| parameter(value, "boundary"));
+
+    if let (Some(media_type), Some(boundary)) = (&media_type, boundary)
+        && media_type.starts_with("multipart/")
+        && depth < MAX_MIME_DEPTH
+    {
+        let delimiter = format!("--{}", boundary);
+        let mut leaves = Vec::new();
+        let mut parts = split_on(body, delimiter.as_bytes());
+        // Preamble before the first delimiter
+        parts.remove(0);
+        for part in parts {
+            // The closing delimiter is followed by "--"
+            if part.starts_with(b"--") {
+                break;
+            }
+            let part = part
+                .strip_prefix(b"\r\n")
+                .or_else(|| part.strip_prefix(b"\n"))
+                .unwrap_or(part);
+            let part = part
+                .strip_suffix(b"\r\n")
+                .or_else(|| part.strip_suffix(b"\n"))
+                .unwrap_or(part);
+            let (part_headers, part_body) = split_entity(part);
+            leaves.extend(mime_leaves(&part_headers, part_body, depth + 1));
+        }
+        return leaves;
+    }
+
+    let name = header(headers, "Content-Disposition")
+        .and_then(|value| parameter(value, "filename"))
+        .or_else(|| content_type.and_then(|value| parameter(value, "name")));
+    let encoding = header(headers, "Content-Transfer-Encoding")
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_default();
+    let data = match encoding.as_str() {
+        "base64" => {
+            let text: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|byte| !byte.is_ascii_whitespace())
+                .collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(&text)
+                .unwrap_or_else(|_| body.to_vec())
+        }
+        "quoted-printable" => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    };
+    vec![MimeLeaf {
+        name,
+        content_type: media_type,
+        data,
+    }]
+}
+
+// A parameter of a header value such as `attachment; filename="a.png"`
+fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn decode_quoted_printable(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut index = 0;
+    while index < body.len() {
+        if body[index] != b'=' {
+            out.push(body[index]);
+            index += 1;
+            continue;
+        }
+        let rest = &body[index + 1..];
+        if rest.starts_with(b"\r\n") {
+            // Soft line break
+            index += 3;
+        } else if rest.starts_with(b"\n") {
+            index += 2;
+        } else if let Some(byte) = rest
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            index += 3;
+        } else {
+            out.push(b'=');
+            index += 1;
+        }
+    }
+    out
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn split_on<'a>(haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut rest = haystack;
+    while let Some(position) = find(rest, delimiter) {
+        parts.push(&rest[..position]);
+        rest = &rest[position + delimiter.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x02\0\0\0";
+
+    // Ethernet + IPv4 + TCP frame
+    fn frame(source: u16, destination: u16, sequence: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 14];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        let mut ip = vec![0u8; 20];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&((40 + payload.len()) as u16).to_be_bytes());
+        ip[9] = 6;
+        let (a, b) = if source < destination {
+            ([10, 0, 0, 1], [10, 0, 0, 2])
+        } else {
+            ([10, 0, 0, 2], [10, 0, 0, 1])
+        };
+        ip[12..16].copy_from_slice(&a);
+        ip[16..20].copy_from_slice(&b);
+        let mut tcp = vec![0u8; 20];
+        tcp[0..2].copy_from_slice(&source.to_be_bytes());
+        tcp[2..4].copy_from_slice(&destination.to_be_bytes());
+        tcp[4..8].copy_from_slice(&sequence.to_be_bytes());
+        tcp[12] = 5 << 4;
+        tcp[13] = flags;
+        frame.extend(ip);
+        frame.extend(tcp);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0];
+        file.extend([0u8; 8]);
+        file.extend(65535u32.to_le_bytes());
+        file.extend(LINKTYPE_ETHERNET.to_le_bytes());
+        for (index, frame) in frames.iter().enumerate() {
+            file.extend((1_700_000_000 + index as u32).to_le_bytes());
+            file.extend(0u32.to_le_bytes());
+            file.extend((frame.len() as u32).to_le_bytes());
+            file.extend((frame.len() as u32).to_le_bytes());
+            file.extend(frame);
+        }
+        file
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("stegascan_capture_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_carves_http_upload_and_download() {
+        let mut upload = b"POST /upload HTTP/1.1\r\nHost: x\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: "
+            .to_vec();
+        let mut form = b"--XyZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"cat.png\"\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        form.extend_from_slice(PNG);
+        form.extend_from_slice(b"\r\n--XyZ--\r\n");
+        upload.extend(format!("{}\r\n\r\n", form.len()).as_bytes());
+        upload.extend(&form);
+        let request = b"GET /img/dog.png?size=1 HTTP/1.1\r\nHost: x\r\n\r\n";
+
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec();
+        response.extend(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        response.extend(format!("{:x}\r\n", 10).as_bytes());
+        response.extend_from_slice(&PNG[..10]);
+        response.extend(format!("\r\n{:x}\r\n", PNG.len() - 10).as_bytes());
+        response.extend_from_slice(&PNG[10..]);
+        response.extend(b"\r\n0\r\n\r\n");
+
+        // Request split in two segments delivered out of order, plus a retransmission
+        let (first, second) = upload.split_at(30);
+        let frames = vec![
+            frame(40000, 80, 999, 0x02, b""),
+            frame(80, 40000, 5000, 0x12, b""),
+            frame(40000, 80, 1000 + 30, 0x18, second),
+            frame(40000, 80, 1000, 0x18, first),
+            frame(40000, 80, 1000, 0x18, first),
+            frame(
+                40000,
+                80,
+                1000 + upload.len() as u32,
+                0x18,
+                request.as_slice(),
+            ),
+            frame(80, 40000, 5001, 0x18, &response),
+        ];
+        let dir = temp_dir("http");
+        let file = dir.join("traffic.pcap");
+        std::fs::write(&file, pcap(&frames)).unwrap();
+
+        let capture = carve(&file, &dir.join("out")).unwrap();
+        assert_eq!(capture.flows.len(), 1);
+        let flow = &capture.flows[0];
+        assert_eq!(flow.protocol, Protocol::Http);
+        assert_eq!(flow.server.port(), 80);
+        assert_eq!(flow.missing_bytes, 0);
+
+        assert_eq!(capture.objects.len(), 2, "{:?}", capture.objects);
+        assert_eq!(capture.objects[0].name, "cat.png");
+        assert_eq!(capture.objects[0].direction, Direction::ClientToServer);
+        assert_eq!(std::fs::read(&capture.objects[0].extracted).unwrap(), PNG);
+        assert_eq!(capture.objects[1].name, "dog.png");
+        assert_eq!(capture.objects[1].direction, Direction::ServerToClient);
+        assert_eq!(std::fs::read(&capture.objects[1].extracted).unwrap(), PNG);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_carves_smtp_attachment() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(PNG);
+        let message = format!(
+            "EHLO me\r\nMAIL FROM:<a@x>\r\nRCPT TO:<b@y>\r\nDATA\r\nSubject: hi\r\nContent-Type: multipart/mixed; boundary=\"b1\"\r\n\r\n--b1\r\nContent-Type: text/plain\r\n\r\n..dotted\r\n--b1\r\nContent-Type: image/png; name=\"x.png\"\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n{}\r\n--b1--\r\n.\r\nQUIT\r\n",
+            &encoded[..20],
+            &encoded[20..]
+        );
+        let frames = vec![
+            frame(25, 50000, 1, 0x18, b"220 mail ready\r\n"),
+            frame(50000, 25, 1, 0x18, message.as_bytes()),
+        ];
+        let dir = temp_dir("smtp");
+        let file = dir.join("mail.pcap");
+        std::fs::write(&file, pcap(&frames)).unwrap();
+
+        let capture = carve(&file, &dir.join("out")).unwrap();
+        assert_eq!(capture.flows[0].protocol, Protocol::Smtp);
+        assert_eq!(capture.flows[0].client.port(), 50000);
+        assert_eq!(capture.objects.len(), 1);
+        assert_eq!(capture.objects[0].name, "x.png");
+        assert_eq!(std::fs::read(&capture.objects[0].extracted).unwrap(), PNG);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reassembly_counts_missing_bytes() {
+        let half = HalfStream {
+            initial_sequence: Some(u32::MAX - 1),
+            segments: vec![(u32::MAX, b"abc"), (5, b"xyz"), (1, b"cde")],
+            bytes: 9,
+        };
+        let (stream, missing) = half.reassemble();
+        // abc at 0, cde at 2 overlaps it by one byte, xyz at 6 after a 1-byte hole
+        assert_eq!(stream, b"abcdexyz");
+        assert_eq!(missing, 1);
+    }
+
+    #[test]
+    fn test_ignores_header_lengths_past_packet() {
+        // IHL of 15 claims a 60-byte header in a 20-byte packet
+        let mut ip = vec![0u8; 20];
+        ip[0] = 0x4F;
+        ip[2..4].copy_from_slice(&20u16.to_be_bytes());
+        ip[9] = 6;
+        let packet = Packet {
+            timestamp: 0.0,
+            link_type: LINKTYPE_RAW,
+            data: &ip,
+        };
+        assert!(tcp_segment(&packet).is_none());
+
+        // IPv6 hop-by-hop header whose length runs past the packet
+        let mut ip = vec![0u8; 48];
+        ip[0] = 0x60;
+        ip[4..6].copy_from_slice(&8u16.to_be_bytes());
+        ip[6] = 0;
+        ip[40] = 6;
+        ip[41] = 1;
+        let packet = Packet {
+            timestamp: 0.0,
+            link_type: LINKTYPE_RAW,
+            data: &ip,
+        };
+        assert!(tcp_segment(&packet).is_none());
+    }
+
+    #[test]
+    fn test_oversized_body_lengths_stop_at_stream_end() {
+        let stream = b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nabc";
+        let messages = http_messages(stream, &[]);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, b"abc");
+
+        let (body, consumed) = dechunk(b"ffffffffffffffff\r\nabc");
+        assert_eq!(body, b"abc");
+        assert_eq!(consumed, 21);
+    }
+
+    #[test]
+    fn test_rejects_non_capture() {
+        assert!(read_packets(&[0u8; 64]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::json_report::{Annotation, RecoveredPayloadReport, SteganalysisReport, SummaryVerdict};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// A case groups the scans of related files the way an investigation does. It is a directory:
+//
+//   case.json          name, description, the scans added so far and the analyst's notes
+//   scans/<id>/        report.json and the artifacts of one scan
+//   evidence/<id>.zip  the scan's evidence package, when added with --evidence-key
+//   case-report.json   written by `stegascan case report`: every scan's summary in one place
+//
+// Scan IDs number the files in the order they were added, so the directory listing reads
+// like the case history.
+
+pub const CASE_FILE: &str = "case.json";
+pub const CASE_REPORT: &str = "case-report.json";
+pub const SCANS_DIR: &str = "scans";
+pub const EVIDENCE_DIR: &str = "evidence";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    pub created: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub scans: Vec<CaseScan>,
+    #[serde(default)]
+    pub notes: Vec<CaseNote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseScan {
+    pub id: String,
+    // As given to `case add`
+    pub file: String,
+    pub sha256: String,
+    pub added: String,
+    // Relative to the case directory
+    pub report: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<String>,
+    pub steganography_detected: bool,
+    pub confidence_level: String,
+    #[serde(default)]
+    pub verdict: SummaryVerdict,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseNote {
+    pub added: String,
+    // The scan the note is about; absent for notes on the case as a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaseReport {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created: String,
+    pub generated: String,
+    pub scan_count: usize,
+    pub detected_count: usize,
+    pub confirmed_count: usize,
+    pub scans: Vec<CaseReportEntry>,
+    pub notes: Vec<CaseNote>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaseReportEntry {
+    #[serde(flatten)]
+    pub scan: CaseScan,
+    pub threat_indicators: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extracted_payloads: Vec<RecoveredPayloadReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    // Set when the scan's report could not be read back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum CaseError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Exists(PathBuf),
+    NotACase(PathBuf),
+    UnknownScan(String),
+}
+
+impl std::fmt::Display for CaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaseError::Io(e) => write!(f, "Case I/O error: {}", e),
+            CaseError::Json(e) => write!(f, "Invalid case file: {}", e),
+            CaseError::Exists(dir) => write!(f, "{} already holds a case", dir.display()),
+            CaseError::NotACase(dir) => write!(
+                f,
+                "{} is not a case, create it with `stegascan case new`",
+                dir.display()
+            ),
+            CaseError::UnknownScan(id) => write!(f, "The case has no scan {}", id),
+        }
+    }
+}
+
+impl std::error::Error for CaseError {}
+
+impl From<std::io::Error> for CaseError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CaseError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl Case {
+    // Start a case in `dir`, named after the directory
+    pub fn create(dir: &Path, description: Option<String>) -> Result<Self, CaseError> {
+        if dir.join(CASE_FILE).exists() {
+            return Err(CaseError::Exists(dir.to_path_buf()));
+        }
+        std::fs::create_dir_all(dir.join(SCANS_DIR))?;
+        let name = dir
+            .canonicalize()?
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "case".to_string());
+        let case = Self {
+            name,
+            created: chrono::Utc::now().to_rfc3339(),
+            description,
+            scans: Vec::new(),
+            notes: Vec::new(),
+        };
+        case.save(dir)?;
+        Ok(case)
+    }
+
+    pub fn open(dir: &Path) -> Result<Self, CaseError> {
+        let path = dir.join(CASE_FILE);
+        if !path.is_file() {
+            return Err(CaseError::NotACase(dir.to_path_buf()));
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), CaseError> {
+        std::fs::write(dir.join(CASE_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // ID for the next file added: its position in the case and its name, e.g. 003_photo.jpg
+    pub fn next_id(&self, file: &Path) -> String {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "file".to_string());
+        format!("{:03}_{}", self.scans.len() + 1, name)
+    }
+
+    // An earlier scan of the same content
+    pub fn find_content(&self, sha256: &str) -> Option<&CaseScan> {
+        self.scans.iter().find(|scan| scan.sha256 == sha256)
+    }
+
+    pub fn add_note(&mut self, text: &str, scan: Option<&str>) -> Result<(), CaseError> {
+        if let Some(id) = scan
+            && !self.scans.iter().any(|s| s.id == id)
+        {
+            return Err(CaseError::UnknownScan(id.to_string()));
+        }
+        self.notes.push(CaseNote {
+            added: chrono::Utc::now().to_rfc3339(),
+            scan: scan.map(str::to_string),
+            text: text.to_string(),
+        });
+        Ok(())
+    }
+
+    // Every scan's summary read back from its report, with the case's notes
+    pub fn report(&self, dir: &Path) -> CaseReport {
+        let scans: Vec<CaseReportEntry> = self
+            .scans
+            .iter()
+            .map(|scan| {
+                let report = std::fs::read_to_string(dir.join(&scan.report))
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| {
+                        serde_json::from_str::<SteganalysisReport>(&json).map_err(|e| e.to_string())
+                    });
+                match report {
+                    Ok(report) => CaseReportEntry {
+                        scan: scan.clone(),
+                        threat_indicators: report.summary.threat_indicators,
+                        extracted_payloads: report.summary.extracted_payloads,
+                        annotations: report.annotations,
+                        error: None,
+                    },
+                    Err(e) => CaseReportEntry {
+                        scan: scan.clone(),
+                        threat_indicators: Vec::new(),
+                        extracted_payloads: Vec::new(),
+                        annotations: Vec::new(),
+                        error: Some(e),
+                    },
+                }
+            })
+            .collect();
+
+        CaseReport {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            created: self.created.clone(),
+            generated: chrono::Utc::now().to_rfc3339(),
+            scan_count: scans.len(),
+            detected_count: scans
+                .iter()
+                .filter(|entry| entry.scan.steganography_detected)
+                .count(),
+            confirmed_count: scans
+                .iter()
+                .filter(|entry| entry.scan.verdict == SummaryVerdict::ConfirmedPayloadExtracted)
+                .count(),
+            scans,
+            notes: self.notes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Locale;
+
+    #[test]
+    fn test_case_collects_scans_and_notes() {
+        let dir =
+            std::env::temp_dir().join(format!("stegascan_case_{}/ransom", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut case = Case::create(&dir, Some("Leaked screenshots".to_string())).unwrap();
+        assert_eq!(case.name, "ransom");
+        assert!(matches!(
+            Case::create(&dir, None),
+            Err(CaseError::Exists(_))
+        ));
+
+        let id = case.next_id(Path::new("/evidence/photo.png"));
+        assert_eq!(id, "001_photo.png");
+        let mut report =
+            SteganalysisReport::new(&PathBuf::from("photo.png"), 10, "Image".to_string());
+        report.finalize_summary(Locale::En);
+        let report_path = format!("{}/{}/report.json", SCANS_DIR, id);
+        std::fs::create_dir_all(dir.join(SCANS_DIR).join(&id)).unwrap();
+        std::fs::write(dir.join(&report_path), report.to_json().unwrap()).unwrap();
+        case.scans.push(CaseScan {
+            id: id.clone(),
+            file: "/evidence/photo.png".to_string(),
+            sha256: "ab".repeat(32),
+            added: chrono::Utc::now().to_rfc3339(),
+            report: report_path,
+            evidence: None,
+            steganography_detected: false,
+            confidence_level: "low".to_string(),
+            verdict: SummaryVerdict::Clean,
+        });
+        case.add_note("Sent by the suspect on 3 March", Some(&id))
+            .unwrap();
+        assert!(case.add_note("Missing", Some("002_other.png")).is_err());
+        case.save(&dir).unwrap();
+
+        let case = Case::open(&dir).unwrap();
+        assert!(case.find_content(&"ab".repeat(32)).is_some());
+        let combined = case.report(&dir);
+        assert_eq!(combined.scan_count, 1);
+        assert_eq!(combined.detected_count, 0);
+        assert!(combined.scans[0].error.is_none());
+        assert_eq!(combined.notes[0].scan.as_deref(), Some("001_photo.png"));
+
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Progress of a batch scan, one JSON line per finished file, appended and flushed as each file
+// is done so a crashed or interrupted batch loses at most the file it was on. With --resume the
+// lines are read back and files whose content an earlier run already scanned are skipped.
+
+pub const CHECKPOINT_FILE: &str = "checkpoint.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointEntry {
+    pub file: String,
+    // Hex SHA-256 of the content, absent when the file couldn't be read
+    #[serde(default)]
+    pub sha256: Option<String>,
+    // scanned, skipped, duplicate, failed or triaged
+    pub outcome: String,
+    pub report: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steganography_detected: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub finished_at: String,
+}
+
+impl CheckpointEntry {
+    // Skipped and failed files are tried again on resume; a timeout or a missing
+    // dependency may not happen twice
+    fn is_done(&self) -> bool {
+        matches!(self.outcome.as_str(), "scanned" | "duplicate" | "triaged")
+    }
+}
+
+pub struct Checkpoint {
+    file: File,
+    done: HashMap<String, CheckpointEntry>,
+}
+
+impl Checkpoint {
+    // Start a new checkpoint, or with `resume` pick up the one a previous run left behind
+    pub fn open(path: &Path, resume: bool) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        if !resume {
+            file.set_len(0)?;
+            return Ok(Self {
+                file,
+                done: HashMap::new(),
+            });
+        }
+
+        let mut done = HashMap::new();
+        for line in BufReader::new(&file).lines() {
+            // The last line is cut short when the previous run died mid-write
+            let Ok(entry) = serde_json::from_str::<CheckpointEntry>(&line?) else {
+                continue;
+            };
+            if let Some(sha256) = entry.sha256.clone()
+                && entry.is_done()
+            {
+                done.insert(sha256, entry);
+            }
+        }
+
+        // Don't glue the next entry onto a cut-short line
+        let len = file.metadata()?.len();
+        if len > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        Ok(Self { file, done })
+    }
+
+    // Number of distinct contents an earlier run finished
+    pub fn finished_count(&self) -> usize {
+        self.done.len()
+    }
+
+    // What an earlier run made of a file with this content, if it finished it
+    pub fn finished(&self, sha256: &str) -> Option<&CheckpointEntry> {
+        self.done.get(sha256)
+    }
+
+    pub fn record(&mut self, entry: &CheckpointEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(file: &str, sha256: &str, outcome: &str) -> CheckpointEntry {
+        CheckpointEntry {
+            file: file.to_string(),
+            sha256: Some(sha256.to_string()),
+            outcome: outcome.to_string(),
+            report: format!("{}.json", file),
+            steganography_detected: Some(false),
+            confidence_level: Some("Low".to_string()),
+            detail: None,
+            finished_at: "2025-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resume_skips_finished_and_survives_torn_line() {
+        let path =
+            std::env::temp_dir().join(format!("stegascan-checkpoint-{}.jsonl", std::process::id()));
+
+        let mut checkpoint = Checkpoint::open(&path, false).unwrap();
+        checkpoint.record(&entry("a.png", "aa", "scanned")).unwrap();
+        checkpoint.record(&entry("b.png", "bb", "failed")).unwrap();
+        drop(checkpoint);
+        // A run killed halfway through writing its next line
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"file\":\"c.png\",\"sha").unwrap();
+        drop(file);
+
+        let mut checkpoint = Checkpoint::open(&path, true).unwrap();
+        assert_eq!(checkpoint.finished_count(), 1);
+        assert_eq!(checkpoint.finished("aa").unwrap().file, "a.png");
+        assert!(checkpoint.finished("bb").is_none());
+        checkpoint.record(&entry("b.png", "bb", "scanned")).unwrap();
+        drop(checkpoint);
+
+        let checkpoint = Checkpoint::open(&path, true).unwrap();
+        assert_eq!(checkpoint.finished_count(), 2);
+        drop(checkpoint);
+
+        // Without --resume the batch starts over
+        let checkpoint = Checkpoint::open(&path, false).unwrap();
+        assert_eq!(checkpoint.finished_count(), 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,908 @@
+use crate::i18n::Locale;
+use analyzers::adaptive_lsb_analyzer::POV_CONTRAST_THRESHOLD;
+use analyzers::bpcs_analyzer::NOISE_PLANE_THRESHOLD;
+use analyzers::calibration_analyzer::CHANGE_RATE_THRESHOLD;
+use analyzers::channel_correlation_analyzer::COLLAPSED_BLOCK_THRESHOLD;
+use analyzers::content_classifier::{FLAT_LSB_NOISE_THRESHOLD, FLAT_RATIO_THRESHOLD};
+use analyzers::content_store::ContentStore;
+use analyzers::cover_baseline::{BASELINE_SIGMA, Calibration, CoverBaselineError, CoverBaselines};
+use analyzers::external_plugin::PluginSpec;
+use analyzers::frame_sampler::{SCENE_CHANGE_THRESHOLD, SamplingMode, SamplingStrategy};
+use analyzers::lsb_analyzer::{CHI_SQUARE_THRESHOLD, ENTROPY_THRESHOLD};
+use analyzers::magic_bytes_analyzer::Strictness;
+use analyzers::phase_analyzer::PHASE_DISCONTINUITY_THRESHOLD;
+use analyzers::spectrogram_analyzer::{DEFAULT_TILE_SECONDS, HIGH_FREQUENCY_ENERGY_THRESHOLD};
+use analyzers::spectrogram_render::ColorMap;
+use analyzers::ultrasonic_analyzer::{ULTRASONIC_CUTOFF_HZ, ULTRASONIC_ENERGY_THRESHOLD};
+use analyzers::ws_analyzer::PAYLOAD_RATE_THRESHOLD;
+use parsers::video_parser::HwAccel;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Config file picked up from the working directory when no other is given
+pub const DEFAULT_CONFIG_FILE: &str = "stegascan.toml";
+
+// Likewise for the calibration `stegascan calibrate` writes
+pub const DEFAULT_CALIBRATION_FILE: &str = "stegascan-calibration.yaml";
+
+// Scan depth presets. A profile replaces the built-in defaults, so anything set in the
+// config file, environment or on the command line still takes precedence over it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    // Skips the image filters, only decodes video keyframes and analyzes at most 3 frames
+    // per scene
+    Fast,
+    #[default]
+    Standard,
+    // Samples every 5th video frame
+    Deep,
+}
+
+impl Profile {
+    pub fn parse(value: &str) -> Option<Self> {
+        <Self as clap::ValueEnum>::from_str(value.trim(), true).ok()
+    }
+}
+
+// Effective settings, layered as defaults < profile < config file < environment < CLI flags
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub profile: Profile,
+    pub thresholds: Thresholds,
+    pub output: OutputSettings,
+    pub analyzers: AnalyzerToggles,
+    pub magic_bytes: MagicBytesSettings,
+    pub audio: AudioSettings,
+    pub video: VideoSettings,
+    pub limits: Limits,
+    pub api: ApiSettings,
+    // External analyzers, one [[plugins]] table each
+    pub plugins: Vec<PluginSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub lsb_chi_square: f64,
+    pub lsb_entropy: f64,
+    // Standard deviations from clean files at which a calibrated statistic is flagged: LSB
+    // channels against covers of the same format and content class, where the fixed
+    // thresholds above apply when there is no baseline, and high-frequency audio energy
+    pub calibration_sigma: f64,
+    // Statistics of known-clean files written by `stegascan calibrate`
+    // [default: ./stegascan-calibration.yaml if it exists]
+    pub calibration_file: Option<PathBuf>,
+    // Share of neighbouring pixels equal above the LSB from which an image counts as synthetic
+    pub content_flat_ratio: f64,
+    // LSB disagreement inside flat runs that flags a screenshot or other synthetic image
+    pub synthetic_lsb_noise: f64,
+    pub spectrogram_high_frequency_energy: f64,
+    // Share of spectral energy above the ultrasonic cutoff
+    pub ultrasonic_energy: f64,
+    // Mean sample jump at phase-coding segment boundaries over the mean jump everywhere
+    pub phase_discontinuity: f64,
+    // Estimated bits per pixel from sample pair / weighted-stego analysis
+    pub ws_payload_rate: f64,
+    // Fraction of smooth blocks whose inter-channel LSB correlation collapsed
+    pub channel_correlation_collapsed_ratio: f64,
+    // Smooth / textured pairs-of-values score ratio from content-adaptive analysis
+    pub adaptive_lsb_contrast: f64,
+    // Bit planes whose complex blocks look like random data before BPCS embedding is reported
+    pub bpcs_noise_planes: usize,
+    // Share of JPEG DCT coefficients changed, estimated against a calibrated reference
+    pub calibration_change_rate: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            lsb_chi_square: CHI_SQUARE_THRESHOLD,
+            lsb_entropy: ENTROPY_THRESHOLD,
+            calibration_sigma: BASELINE_SIGMA,
+            calibration_file: None,
+            content_flat_ratio: FLAT_RATIO_THRESHOLD,
+            synthetic_lsb_noise: FLAT_LSB_NOISE_THRESHOLD,
+            spectrogram_high_frequency_energy: HIGH_FREQUENCY_ENERGY_THRESHOLD,
+            ultrasonic_energy: ULTRASONIC_ENERGY_THRESHOLD,
+            phase_discontinuity: PHASE_DISCONTINUITY_THRESHOLD,
+            ws_payload_rate: PAYLOAD_RATE_THRESHOLD,
+            channel_correlation_collapsed_ratio: COLLAPSED_BLOCK_THRESHOLD,
+            adaptive_lsb_contrast: POV_CONTRAST_THRESHOLD,
+            bpcs_noise_planes: NOISE_PLANE_THRESHOLD,
+            calibration_change_rate: CHANGE_RATE_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    // Directory for generated artifacts (LSB planes, filters, spectrograms)
+    pub dir: PathBuf,
+    // Path of the JSON report
+    pub report: PathBuf,
+    // Language of the summary, recommendations and explanations
+    pub locale: Locale,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("outputs"),
+            report: PathBuf::from("outputs/report.json"),
+            locale: Locale::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyzerToggles {
+    pub magic_bytes: bool,
+    pub slack_space: bool,
+    pub deflate: bool,
+    pub exif: bool,
+    pub lsb: bool,
+    pub ws: bool,
+    pub channel_correlation: bool,
+    pub adaptive_lsb: bool,
+    pub bpcs: bool,
+    pub calibration: bool,
+    pub webp: bool,
+    pub gif: bool,
+    pub png_text: bool,
+    pub pages: bool,
+    pub filters: bool,
+    pub id3: bool,
+    pub spectrogram: bool,
+    pub ultrasonic: bool,
+    pub demodulation: bool,
+    pub phase: bool,
+    pub video: bool,
+    pub text: bool,
+}
+
+impl Default for AnalyzerToggles {
+    fn default() -> Self {
+        Self {
+            magic_bytes: true,
+            slack_space: true,
+            deflate: true,
+            exif: true,
+            lsb: true,
+            ws: true,
+            channel_correlation: true,
+            adaptive_lsb: true,
+            bpcs: true,
+            calibration: true,
+            webp: true,
+            gif: true,
+            png_text: true,
+            pages: true,
+            filters: true,
+            id3: true,
+            spectrogram: true,
+            ultrasonic: true,
+            demodulation: true,
+            phase: true,
+            video: true,
+            text: true,
+        }
+    }
+}
+
+impl AnalyzerToggles {
+    pub const NAMES: [&'static str; 22] = [
+        "magic_bytes",
+        "slack_space",
+        "deflate",
+        "exif",
+        "lsb",
+        "ws",
+        "channel_correlation",
+        "adaptive_lsb",
+        "bpcs",
+        "calibration",
+        "webp",
+        "gif",
+        "png_text",
+        "pages",
+        "filters",
+        "id3",
+        "spectrogram",
+        "ultrasonic",
+        "demodulation",
+        "phase",
+        "video",
+        "text",
+    ];
+
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), ConfigError> {
+        *self.toggle_mut(name)? = enabled;
+        Ok(())
+    }
+
+    // Analyzers cheap enough to run over a whole corpus: signature carving, which finds appended
+    // and embedded files, and the entropy of bytes outside the container structure
+    pub const TRIAGE: [&'static str; 2] = ["magic_bytes", "slack_space"];
+
+    // These toggles with everything but the triage analyzers turned off
+    pub fn triage(&self) -> Self {
+        let mut toggles = self.clone();
+        for name in Self::NAMES {
+            let enabled = Self::TRIAGE.contains(&name) && self.is_enabled(name);
+            *toggles
+                .toggle_mut(name)
+                .expect("NAMES lists known analyzers") = enabled;
+        }
+        toggles
+    }
+
+    // Unknown names are reported as disabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.clone().toggle_mut(name).is_ok_and(|toggle| *toggle)
+    }
+
+    fn toggle_mut(&mut self, name: &str) -> Result<&mut bool, ConfigError> {
+        let toggle = match name.trim() {
+            "magic_bytes" | "magic" => &mut self.magic_bytes,
+            "slack_space" | "slack" => &mut self.slack_space,
+            "deflate" | "zlib" => &mut self.deflate,
+            "exif" => &mut self.exif,
+            "lsb" => &mut self.lsb,
+            "ws" => &mut self.ws,
+            "channel_correlation" => &mut self.channel_correlation,
+            "adaptive_lsb" => &mut self.adaptive_lsb,
+            "bpcs" => &mut self.bpcs,
+            "calibration" => &mut self.calibration,
+            "webp" => &mut self.webp,
+            "gif" => &mut self.gif,
+            "png_text" => &mut self.png_text,
+            "pages" | "sub_images" => &mut self.pages,
+            "filters" => &mut self.filters,
+            "id3" => &mut self.id3,
+            "spectrogram" => &mut self.spectrogram,
+            "ultrasonic" => &mut self.ultrasonic,
+            "demodulation" => &mut self.demodulation,
+            "phase" => &mut self.phase,
+            "video" => &mut self.video,
+            "text" => &mut self.text,
+            other => return Err(ConfigError::UnknownAnalyzer(other.to_string())),
+        };
+        Ok(toggle)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MagicBytesSettings {
+    // Header validation required before a manual-scan hit is reported
+    pub strictness: Strictness,
+    // YAML or JSON file of extra signatures merged into the manual scan
+    pub signatures_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    // Content above this frequency is isolated, resampled and demodulated on its own
+    pub ultrasonic_cutoff_hz: f32,
+    // Recordings longer than this many seconds get a spectrogram per window of this length;
+    // 0 keeps one spectrogram for the whole recording
+    pub spectrogram_tile_seconds: u32,
+    // Colors of the saved spectrogram images
+    pub spectrogram_colormap: ColorMap,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            ultrasonic_cutoff_hz: ULTRASONIC_CUTOFF_HZ,
+            spectrogram_tile_seconds: DEFAULT_TILE_SECONDS,
+            spectrogram_colormap: ColorMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    // Which frames get analyzed: stride, random, scene or time
+    pub sampling: SamplingMode,
+    // Analyze every Nth frame, or 1 in N on average with random sampling
+    pub sample_rate: usize,
+    // Seed for random sampling; the same seed picks the same frames
+    pub seed: u64,
+    // Luma histogram distance between consecutive frames that counts as a cut
+    pub scene_threshold: f64,
+    // Seconds between frames with time sampling
+    pub interval_seconds: f64,
+    // Decode keyframes only; the sample rate then counts keyframes
+    pub keyframes_only: bool,
+    // Most sampled frames analyzed per scene, 0 for no limit. Long static shots otherwise
+    // take up most of the analysis time of a long video.
+    pub max_frames_per_scene: usize,
+    // Hardware decoder to try: none, auto, vaapi, videotoolbox or nvdec. Falls back to
+    // software decoding when it isn't available.
+    pub hwaccel: HwAccel,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            sampling: SamplingMode::default(),
+            sample_rate: 30,
+            seed: 0,
+            scene_threshold: SCENE_CHANGE_THRESHOLD,
+            interval_seconds: 1.0,
+            keyframes_only: false,
+            max_frames_per_scene: 0,
+            hwaccel: HwAccel::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    // Files larger than this are refused, 0 for no limit
+    pub max_file_size_mb: u64,
+    // Seconds a batch file's size and modification time must hold still before it is scanned,
+    // 0 to scan at once
+    pub settle_seconds: f64,
+}
+
+// Settings for the REST API server, which reads the same STEGASCAN_* variables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub artifact_dir: Option<PathBuf>,
+    pub history_dir: Option<PathBuf>,
+    pub inline_payload_limit: usize,
+    pub rate_limit_per_minute: u32,
+    pub rate_limit_burst: u32,
+    pub daily_quota: u32,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            artifact_dir: None,
+            history_dir: None,
+            inline_payload_limit: 1024 * 1024,
+            rate_limit_per_minute: 30,
+            rate_limit_burst: 10,
+            daily_quota: 1000,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    InvalidEnv(String, String),
+    UnknownAnalyzer(String),
+    Serialize(String),
+    Calibration(PathBuf, CoverBaselineError),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Invalid config {}: {}", path.display(), e),
+            ConfigError::InvalidEnv(name, value) => {
+                write!(f, "Invalid value for {}: {:?}", name, value)
+            }
+            ConfigError::UnknownAnalyzer(name) => write!(
+                f,
+                "Unknown analyzer {:?} (expected one of: {})",
+                name,
+                AnalyzerToggles::NAMES.join(", ")
+            ),
+            ConfigError::Serialize(e) => write!(f, "Failed to serialize config: {}", e),
+            ConfigError::Calibration(path, e) => write!(f, "{} ({})", e, path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Settings {
+    pub fn for_profile(profile: Profile) -> Self {
+        let mut settings = Self {
+            profile,
+            ..Self::default()
+        };
+
+        match profile {
+            Profile::Fast => {
+                settings.analyzers.filters = false;
+                settings.analyzers.deflate = false;
+                settings.video.keyframes_only = true;
+                settings.video.sample_rate = 1;
+                settings.video.max_frames_per_scene = 3;
+            }
+            Profile::Standard => {}
+            Profile::Deep => {
+                settings.video.sample_rate = 5;
+            }
+        }
+
+        settings
+    }
+
+    // Explicit path first, then STEGASCAN_CONFIG, then ./stegascan.toml if it exists
+    pub fn config_path(explicit: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = explicit {
+            return Some(path.to_path_buf());
+        }
+        if let Ok(path) = std::env::var("STEGASCAN_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let local = PathBuf::from(DEFAULT_CONFIG_FILE);
+        local.exists().then_some(local)
+    }
+
+    // Profile defaults, overlaid with the config file and then the environment. The profile
+    // comes from `profile` (the --profile flag), then STEGASCAN_PROFILE, then the config file.
+    pub fn load(explicit: Option<&Path>, profile: Option<Profile>) -> Result<Self, ConfigError> {
+        let file = match Self::config_path(explicit) {
+            Some(path) => Some((Self::read_table(&path)?, path)),
+            None => None,
+        };
+
+        let profile = match profile {
+            Some(profile) => profile,
+            None => match std::env::var("STEGASCAN_PROFILE") {
+                Ok(value) => Profile::parse(&value).ok_or(ConfigError::InvalidEnv(
+                    "STEGASCAN_PROFILE".to_string(),
+                    value,
+                ))?,
+                Err(_) => file
+                    .as_ref()
+                    .and_then(|(table, _)| table.get("profile"))
+                    .and_then(|v| v.as_str())
+                    .and_then(Profile::parse)
+                    .unwrap_or_default(),
+            },
+        };
+
+        let mut settings = Self::for_profile(profile);
+        if let Some((table, path)) = file {
+            settings = settings.overlay(table, &path)?;
+        }
+        settings.profile = profile;
+        settings.apply_env(|name| std::env::var(name).ok())?;
+        Ok(settings)
+    }
+
+    fn read_table(path: &Path) -> Result<toml::Table, ConfigError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        text.parse()
+            .map_err(|e: toml::de::Error| ConfigError::Parse(path.to_path_buf(), e.to_string()))
+    }
+
+    // Apply the keys present in `table` on top of these settings, leaving the rest alone
+    fn overlay(&self, table: toml::Table, path: &Path) -> Result<Self, ConfigError> {
+        fn merge(base: &mut toml::Table, top: toml::Table) {
+            for (key, value) in top {
+                match (base.get_mut(&key), value) {
+                    (Some(toml::Value::Table(base)), toml::Value::Table(top)) => merge(base, top),
+                    (_, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        let mut base =
+            toml::Table::try_from(self).map_err(|e| ConfigError::Serialize(e.to_string()))?;
+        merge(&mut base, table);
+        base.try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse(path.to_path_buf(), e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(|e| ConfigError::Serialize(e.to_string()))
+    }
+
+    // `lookup` is std::env::var in practice; taking it as a closure keeps this testable
+    pub fn apply_env(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(name: &str, value: String) -> Result<T, ConfigError> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::InvalidEnv(name.to_string(), value))
+        }
+
+        macro_rules! env {
+            ($name:literal => $field:expr) => {
+                if let Some(value) = lookup($name) {
+                    $field = parse($name, value)?;
+                }
+            };
+        }
+
+        env!("STEGASCAN_LSB_CHI_SQUARE_THRESHOLD" => self.thresholds.lsb_chi_square);
+        env!("STEGASCAN_LSB_ENTROPY_THRESHOLD" => self.thresholds.lsb_entropy);
+        env!("STEGASCAN_CALIBRATION_SIGMA" => self.thresholds.calibration_sigma);
+        env!("STEGASCAN_CONTENT_FLAT_RATIO" => self.thresholds.content_flat_ratio);
+        env!("STEGASCAN_SYNTHETIC_LSB_NOISE_THRESHOLD" => self.thresholds.synthetic_lsb_noise);
+        env!("STEGASCAN_SPECTROGRAM_HF_THRESHOLD" => self.thresholds.spectrogram_high_frequency_energy);
+        env!("STEGASCAN_ULTRASONIC_ENERGY_THRESHOLD" => self.thresholds.ultrasonic_energy);
+        env!("STEGASCAN_PHASE_DISCONTINUITY_THRESHOLD" => self.thresholds.phase_discontinuity);
+        env!("STEGASCAN_WS_PAYLOAD_RATE_THRESHOLD" => self.thresholds.ws_payload_rate);
+        env!("STEGASCAN_CHANNEL_CORRELATION_THRESHOLD" => self.thresholds.channel_correlation_collapsed_ratio);
+        env!("STEGASCAN_ADAPTIVE_LSB_CONTRAST_THRESHOLD" => self.thresholds.adaptive_lsb_contrast);
+        env!("STEGASCAN_BPCS_NOISE_PLANES" => self.thresholds.bpcs_noise_planes);
+        env!("STEGASCAN_CALIBRATION_CHANGE_RATE_THRESHOLD" => self.thresholds.calibration_change_rate);
+        env!("STEGASCAN_SIGNATURE_STRICTNESS" => self.magic_bytes.strictness);
+        env!("STEGASCAN_OUTPUT_DIR" => self.output.dir);
+        env!("STEGASCAN_REPORT" => self.output.report);
+        env!("STEGASCAN_LOCALE" => self.output.locale);
+        env!("STEGASCAN_ULTRASONIC_CUTOFF_HZ" => self.audio.ultrasonic_cutoff_hz);
+        env!("STEGASCAN_SPECTROGRAM_TILE_SECONDS" => self.audio.spectrogram_tile_seconds);
+        env!("STEGASCAN_SPECTROGRAM_COLORMAP" => self.audio.spectrogram_colormap);
+        env!("STEGASCAN_VIDEO_SAMPLE_RATE" => self.video.sample_rate);
+        env!("STEGASCAN_VIDEO_SAMPLING" => self.video.sampling);
+        env!("STEGASCAN_VIDEO_SEED" => self.video.seed);
+        env!("STEGASCAN_VIDEO_SCENE_THRESHOLD" => self.video.scene_threshold);
+        env!("STEGASCAN_VIDEO_INTERVAL_SECONDS" => self.video.interval_seconds);
+        env!("STEGASCAN_VIDEO_MAX_FRAMES_PER_SCENE" => self.video.max_frames_per_scene);
+        env!("STEGASCAN_VIDEO_HWACCEL" => self.video.hwaccel);
+        env!("STEGASCAN_MAX_FILE_SIZE_MB" => self.limits.max_file_size_mb);
+        env!("STEGASCAN_SETTLE_SECONDS" => self.limits.settle_seconds);
+        env!("STEGASCAN_INLINE_PAYLOAD_LIMIT" => self.api.inline_payload_limit);
+        env!("STEGASCAN_RATE_LIMIT_PER_MINUTE" => self.api.rate_limit_per_minute);
+        env!("STEGASCAN_RATE_LIMIT_BURST" => self.api.rate_limit_burst);
+        env!("STEGASCAN_DAILY_QUOTA" => self.api.daily_quota);
+
+        if let Some(file) = lookup("STEGASCAN_SIGNATURES_FILE") {
+            self.magic_bytes.signatures_file = Some(PathBuf::from(file));
+        }
+        if let Some(file) = lookup("STEGASCAN_CALIBRATION_FILE") {
+            self.thresholds.calibration_file = Some(PathBuf::from(file));
+        }
+        if let Some(dir) = lookup("STEGASCAN_ARTIFACT_DIR") {
+            self.api.artifact_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = lookup("STEGASCAN_HISTORY_DIR") {
+            self.api.history_dir = Some(PathBuf::from(dir));
+        }
+        // YAML or JSON plugin definitions, added to those from the config file
+        if let Some(file) = lookup("STEGASCAN_PLUGINS_FILE") {
+            let path = PathBuf::from(file);
+            let plugins =
+                PluginSpec::load(&path).map_err(|e| ConfigError::Parse(path, e.to_string()))?;
+            self.plugins.extend(plugins);
+        }
+
+        // Comma-separated analyzer names to switch off, e.g. "filters,video"
+        if let Some(disabled) = lookup("STEGASCAN_DISABLE_ANALYZERS") {
+            for name in disabled.split(',').filter(|n| !n.trim().is_empty()) {
+                self.analyzers.set(name, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn video_sample_rate(&self) -> usize {
+        // A rate of 0 would divide by zero when sampling frames
+        self.video.sample_rate.max(1)
+    }
+
+    pub fn video_sampling(&self) -> SamplingStrategy {
+        match self.video.sampling {
+            SamplingMode::Stride => SamplingStrategy::EveryNth(self.video_sample_rate()),
+            SamplingMode::Random => SamplingStrategy::Random {
+                one_in: self.video_sample_rate(),
+                seed: self.video.seed,
+            },
+            SamplingMode::Scene => SamplingStrategy::SceneChange {
+                threshold: self.video.scene_threshold,
+            },
+            SamplingMode::Time => SamplingStrategy::UniformTime {
+                interval_seconds: self.video.interval_seconds,
+            },
+        }
+    }
+
+    // The configured calibration, or the default one if a calibration run left it here
+    pub fn calibration_path(&self) -> Option<PathBuf> {
+        self.thresholds.calibration_file.clone().or_else(|| {
+            let local = PathBuf::from(DEFAULT_CALIBRATION_FILE);
+            local.exists().then_some(local)
+        })
+    }
+
+    // Shipped LSB baselines, with the calibrated ones in place of those they cover
+    pub fn cover_baselines(&self) -> Result<CoverBaselines, ConfigError> {
+        match self.calibration_path() {
+            Some(path) => {
+                CoverBaselines::load(&path).map_err(|e| ConfigError::Calibration(path, e))
+            }
+            None => Ok(CoverBaselines::builtin()),
+        }
+    }
+
+    // A calibration with clean recordings replaces the high-frequency energy threshold, from
+    // whatever layer it came, with one that far above the site's own audio
+    pub fn apply_calibration(&mut self) -> Result<(), ConfigError> {
+        let Some(path) = self.calibration_path() else {
+            return Ok(());
+        };
+        let calibration =
+            Calibration::read(&path).map_err(|e| ConfigError::Calibration(path, e))?;
+        if let Some(audio) = calibration.audio {
+            self.thresholds.spectrogram_high_frequency_energy =
+                audio.high_frequency_energy_threshold(self.thresholds.calibration_sigma);
+        }
+        Ok(())
+    }
+
+    pub fn artifact_path(&self, file_name: &str) -> PathBuf {
+        long_path(self.output.dir.join(file_name))
+    }
+
+    // Payloads carved out of a file's bytes (slack regions, embedded pictures and the like) are
+    // content-addressed under the output directory, so a payload carved from many files is
+    // written once. Rendered outputs such as LSB planes and spectrograms, and the signature
+    // scan's `<file>_extracted` trees, keep their per-file names in the output directory.
+    pub fn payload_store(&self) -> ContentStore {
+        ContentStore::new(self.output.dir.join("payloads"))
+    }
+}
+
+// Longest scanned file name kept whole in artifact names. Artifacts add a suffix to it and
+// sit in the output directory, so this leaves room under Windows' 255-character name limit.
+const MAX_ARTIFACT_STEM_CHARS: usize = 96;
+
+// The scanned file's name as artifact names start with it. Names that aren't valid Unicode,
+// have characters some filesystems reject, or are too long are cleaned up and tagged with a
+// hash of the full path, so that two such files still get artifacts of their own.
+pub fn artifact_stem(file: &Path) -> String {
+    let Some(name) = file.file_name() else {
+        return format!("file_{}", path_hash(file));
+    };
+    let lossy = name.to_string_lossy();
+    let mut altered = name.to_str().is_none();
+    let mut stem: String = lossy
+        .chars()
+        .map(|c| {
+            if c.is_control()
+                || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+                || c == char::REPLACEMENT_CHARACTER
+            {
+                altered = true;
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if stem.chars().count() > MAX_ARTIFACT_STEM_CHARS {
+        stem = stem.chars().take(MAX_ARTIFACT_STEM_CHARS).collect();
+        altered = true;
+    }
+    // Windows drops trailing dots and spaces from names
+    if stem.ends_with(['.', ' ']) {
+        stem = stem.trim_end_matches(['.', ' ']).to_string();
+        altered = true;
+    }
+    if altered {
+        format!("{}_{}", stem, path_hash(file))
+    } else {
+        stem
+    }
+}
+
+// First 8 hex digits of the SHA-256 of the path's raw bytes
+fn path_hash(file: &Path) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(file.as_os_str().as_encoded_bytes());
+    digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Windows refuses paths of MAX_PATH characters or more unless they are absolute and carry the
+// \\?\ prefix
+#[cfg(windows)]
+pub fn long_path(path: PathBuf) -> PathBuf {
+    use std::path::{Component, Prefix};
+    const MAX_PATH: usize = 260;
+    if path.as_os_str().len() < MAX_PATH {
+        return path;
+    }
+    let Ok(absolute) = std::path::absolute(&path) else {
+        return path;
+    };
+    let mut components = absolute.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return absolute;
+    };
+    let mut verbatim = std::ffi::OsString::from(r"\\?\");
+    match prefix.kind() {
+        Prefix::Disk(_) => verbatim.push(prefix.as_os_str()),
+        Prefix::UNC(server, share) => {
+            verbatim.push(r"UNC\");
+            verbatim.push(server);
+            verbatim.push(r"\");
+            verbatim.push(share);
+        }
+        _ => return absolute,
+    }
+    // A rooted path joined to a prefix keeps the prefix
+    PathBuf::from(verbatim).join(components.as_path())
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_layering_file_then_env() {
+        let table: toml::Table = r#"
+            [thresholds]
+            lsb_chi_square = 250.0
+
+            [video]
+            sample_rate = 10
+            "#
+        .parse()
+        .unwrap();
+        let mut settings = Settings::default()
+            .overlay(table, Path::new("stegascan.toml"))
+            .unwrap();
+
+        // Unset keys keep their defaults
+        assert_eq!(settings.thresholds.lsb_entropy, ENTROPY_THRESHOLD);
+        assert_eq!(settings.thresholds.lsb_chi_square, 250.0);
+
+        let env = HashMap::from([
+            ("STEGASCAN_VIDEO_SAMPLE_RATE", "5"),
+            ("STEGASCAN_VIDEO_SAMPLING", "random"),
+            ("STEGASCAN_VIDEO_SEED", "42"),
+            ("STEGASCAN_VIDEO_MAX_FRAMES_PER_SCENE", "4"),
+            ("STEGASCAN_VIDEO_HWACCEL", "VAAPI"),
+            ("STEGASCAN_DISABLE_ANALYZERS", "filters, video"),
+        ]);
+        settings
+            .apply_env(|name| env.get(name).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(settings.thresholds.lsb_chi_square, 250.0);
+        assert_eq!(settings.video.sample_rate, 5);
+        assert_eq!(settings.video.max_frames_per_scene, 4);
+        assert_eq!(settings.video.hwaccel, HwAccel::Vaapi);
+        assert_eq!(
+            settings.video_sampling(),
+            SamplingStrategy::Random {
+                one_in: 5,
+                seed: 42
+            }
+        );
+        assert!(!settings.analyzers.filters);
+        assert!(!settings.analyzers.video);
+        assert!(settings.analyzers.lsb);
+
+        let bad = HashMap::from([("STEGASCAN_VIDEO_SAMPLE_RATE", "often")]);
+        assert!(matches!(
+            settings.apply_env(|name| bad.get(name).map(|v| v.to_string())),
+            Err(ConfigError::InvalidEnv(..))
+        ));
+    }
+
+    #[test]
+    fn test_plugins_table() {
+        let table: toml::Table = r#"
+            [[plugins]]
+            name = "acme"
+            command = "/opt/acme/detect"
+            input = "stdin"
+            "#
+        .parse()
+        .unwrap();
+        let settings = Settings::default()
+            .overlay(table, Path::new("stegascan.toml"))
+            .unwrap();
+
+        assert_eq!(settings.plugins.len(), 1);
+        assert_eq!(
+            settings.plugins[0].command,
+            PathBuf::from("/opt/acme/detect")
+        );
+        assert_eq!(settings.plugins[0].timeout_secs, 60);
+        // Round-trips through the serialized form used for --print-config
+        assert!(settings.to_toml().unwrap().contains("[[plugins]]"));
+    }
+
+    #[test]
+    fn test_file_overrides_profile() {
+        let table: toml::Table = "[video]\nsample_rate = 10\n".parse().unwrap();
+        let settings = Settings::for_profile(Profile::Fast)
+            .overlay(table, Path::new("stegascan.toml"))
+            .unwrap();
+
+        // The file's sample rate wins, the rest of the fast preset stays
+        assert_eq!(settings.video.sample_rate, 10);
+        assert!(settings.video.keyframes_only);
+        assert!(!settings.analyzers.filters);
+    }
+
+    #[test]
+    fn test_triage_keeps_only_cheap_enabled_analyzers() {
+        let mut toggles = AnalyzerToggles::default();
+        toggles.set("slack", false).unwrap();
+        let triage = toggles.triage();
+
+        assert!(triage.magic_bytes);
+        // Triage doesn't turn back on what the user turned off
+        assert!(!triage.slack_space);
+        assert!(!triage.lsb && !triage.video && !triage.spectrogram);
+    }
+
+    #[test]
+    fn test_calibration_replaces_high_frequency_threshold() {
+        let path =
+            std::env::temp_dir().join(format!("stegascan-calibration-{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            "audio:\n  high_frequency_energy: { mean: 0.02, std_dev: 0.005 }\n  samples: 40\n",
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        settings.thresholds.calibration_file = Some(path.clone());
+        settings.thresholds.calibration_sigma = 4.0;
+        settings.apply_calibration().unwrap();
+        assert!((settings.thresholds.spectrogram_high_frequency_energy - 0.04).abs() < 1e-12);
+        // No image baselines in it, so the shipped ones stay
+        assert_eq!(
+            settings.cover_baselines().unwrap().baselines().len(),
+            CoverBaselines::builtin().baselines().len()
+        );
+
+        std::fs::write(&path, "audio: 1\n").unwrap();
+        assert!(matches!(
+            settings.apply_calibration(),
+            Err(ConfigError::Calibration(..))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_stem_keeps_plain_names_and_hashes_others() {
+        assert_eq!(artifact_stem(Path::new("/evidence/photo.png")), "photo.png");
+        assert_eq!(artifact_stem(Path::new("фото.png")), "фото.png");
+
+        let odd = artifact_stem(Path::new("/evidence/a|b?.png"));
+        assert!(odd.starts_with("a_b_.png_"));
+        assert_eq!(odd.len(), "a_b_.png_".len() + 8);
+        assert_ne!(odd, artifact_stem(Path::new("/other/a|b?.png")));
+
+        let long = artifact_stem(&Path::new("/evidence").join("x".repeat(300)));
+        assert_eq!(long.chars().count(), MAX_ARTIFACT_STEM_CHARS + 9);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"photo\xff.png");
+            let stem = artifact_stem(Path::new(name));
+            assert!(stem.starts_with("photo_.png_"));
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::config::Thresholds;
+use crate::explain;
+use crate::feedback::csv_field;
+use crate::i18n::{Locale, tr};
+use crate::json_report::{FindingExplanation, SteganalysisReport, SummaryVerdict};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::io::Write;
+
+// `stegascan report convert`: a stored JSON report rendered again in another format, without
+// scanning the file again. Findings are worked out from the report's sections with the current
+// thresholds, so a report saved without --explain converts with its findings all the same.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    // A standalone page for sharing with people who don't read JSON
+    Html,
+    // One row per finding, for spreadsheets
+    Csv,
+    // SARIF 2.1.0, for code scanning dashboards
+    Sarif,
+}
+
+impl ReportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Sarif => "sarif",
+        }
+    }
+}
+
+const CSV_HEADER: &str = "file,detected_type,scanned,verdict,confidence_level,rule_id,analyzer,measured_value,threshold,description";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+pub fn write(
+    report: &SteganalysisReport,
+    format: ReportFormat,
+    thresholds: &Thresholds,
+    locale: Locale,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let findings = explain::report_findings(report, thresholds, locale);
+    match format {
+        ReportFormat::Html => out.write_all(html(report, &findings, locale)?.as_bytes())?,
+        ReportFormat::Csv => write_csv(report, &findings, out)?,
+        ReportFormat::Sarif => {
+            serde_json::to_writer_pretty(&mut *out, &sarif(report, &findings))?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+fn verdict_name(verdict: SummaryVerdict) -> String {
+    serde_json::to_value(verdict)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// A clean report still gets a row, with the finding columns left empty
+fn write_csv(
+    report: &SteganalysisReport,
+    findings: &[FindingExplanation],
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    let file = [
+        report.file_info.path.clone(),
+        report.file_info.detected_type.clone(),
+        report.timestamp.clone(),
+        verdict_name(report.summary.verdict),
+        report.summary.confidence_level.clone(),
+    ];
+    let rows: Vec<[String; 5]> = if findings.is_empty() {
+        vec![Default::default()]
+    } else {
+        findings
+            .iter()
+            .map(|finding| {
+                [
+                    finding.rule_id.clone(),
+                    finding.analyzer.clone(),
+                    finding
+                        .measured_value
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                    finding.threshold.map(|v| v.to_string()).unwrap_or_default(),
+                    finding.description.clone(),
+                ]
+            })
+            .collect()
+    };
+    for row in rows {
+        let fields: Vec<String> = file.iter().chain(&row).map(|f| csv_field(f)).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+fn sarif(report: &SteganalysisReport, findings: &[FindingExplanation]) -> serde_json::Value {
+    // A recovered payload makes every finding of the file an error rather than a warning
+    let level = if report.summary.verdict == SummaryVerdict::ConfirmedPayloadExtracted {
+        "error"
+    } else {
+        "warning"
+    };
+    let mut rules: Vec<serde_json::Value> = Vec::new();
+    for finding in findings {
+        if !rules
+            .iter()
+            .any(|rule| rule["id"] == finding.rule_id.as_str())
+        {
+            rules.push(json!({
+                "id": finding.rule_id,
+                "shortDescription": { "text": finding.technique },
+                "properties": { "analyzer": finding.analyzer },
+            }));
+        }
+    }
+    let results: Vec<serde_json::Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "ruleId": finding.rule_id,
+                "level": level,
+                "message": { "text": finding.description },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": report.file_info.path },
+                    },
+                }],
+                "properties": {
+                    "measured_value": finding.measured_value,
+                    "threshold": finding.threshold,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "stegascan",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "invocations": [{
+                "executionSuccessful": true,
+                "endTimeUtc": report.timestamp,
+            }],
+            "results": results,
+            "properties": {
+                "verdict": verdict_name(report.summary.verdict),
+                "confidence_level": report.summary.confidence_level,
+                "steganography_detected": report.summary.steganography_detected,
+            },
+        }],
+    })
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Section titles come from the CLI's catalog, without the colon they end in there
+fn heading(locale: Locale, key: &str) -> String {
+    escape(tr(locale, key, &[]).trim_end_matches(':'))
+}
+
+fn html(
+    report: &SteganalysisReport,
+    findings: &[FindingExplanation],
+    locale: Locale,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let summary = &report.summary;
+    let mut page = String::new();
+    writeln!(
+        page,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n<title>stegascan: {}</title>",
+        serde_json::to_value(locale)?.as_str().unwrap_or("en"),
+        escape(&report.file_info.path)
+    )?;
+    page.push_str(
+        "<style>body{font-family:sans-serif;max-width:60em;margin:2em auto}\
+         table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.3em .6em;text-align:left}\
+         .detected{color:#b00}.clean{color:#070}</style>\n</head>\n<body>\n",
+    );
+    writeln!(page, "<h1>{}</h1>", escape(&report.file_info.path))?;
+    writeln!(
+        page,
+        "<p>{}, {} bytes, scanned {}</p>",
+        escape(&report.file_info.detected_type),
+        report.file_info.size_bytes,
+        escape(&report.timestamp)
+    )?;
+    if report.modified_during_scan {
+        page.push_str("<p>The file changed while it was being scanned.</p>\n");
+    }
+
+    writeln!(page, "<h2>{}</h2>", heading(locale, "cli.summary_title"))?;
+    let detected = if summary.steganography_detected {
+        "cli.true"
+    } else {
+        "cli.false"
+    };
+    writeln!(
+        page,
+        "<p class=\"{}\">{}<br>{}</p>",
+        if summary.steganography_detected {
+            "detected"
+        } else {
+            "clean"
+        },
+        escape(&tr(
+            locale,
+            "cli.steganography_detected",
+            &[("value", tr(locale, detected, &[]))]
+        )),
+        escape(&tr(
+            locale,
+            "cli.confidence_level",
+            &[(
+                "value",
+                tr(
+                    locale,
+                    &format!("confidence.{}", summary.confidence_level),
+                    &[]
+                )
+            )]
+        ))
+    )?;
+
+    let lists = [
+        ("cli.threat_indicators", &summary.threat_indicators),
+        ("cli.recommendations", &summary.recommendations),
+    ];
+    for (key, items) in lists {
+        if items.is_empty() {
+            continue;
+        }
+        writeln!(page, "<h2>{}</h2>\n<ul>", heading(locale, key))?;
+        for item in items {
+            writeln!(page, "<li>{}</li>", escape(item))?;
+        }
+        page.push_str("</ul>\n");
+    }
+
+    if !summary.extracted_payloads.is_empty() {
+        writeln!(
+            page,
+            "<h2>{}</h2>\n<ul>",
+            heading(locale, "cli.extracted_payloads")
+        )?;
+        for payload in &summary.extracted_payloads {
+            write!(
+                page,
+                "<li>{}: {}",
+                escape(&payload.source),
+                escape(&payload.description)
+            )?;
+            if let Some(artifact) = &payload.artifact {
+                write!(page, " &rarr; <code>{}</code>", escape(artifact))?;
+            }
+            page.push_str("</li>\n");
+        }
+        page.push_str("</ul>\n");
+    }
+
+    if !findings.is_empty() {
+        writeln!(
+            page,
+            "<h2>{}</h2>\n<table>\n<tr><th>Rule</th><th>Measured</th><th>Threshold</th><th>Technique</th><th>Description</th></tr>",
+            heading(locale, "cli.explanations")
+        )?;
+        for finding in findings {
+            writeln!(
+                page,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&finding.rule_id),
+                finding
+                    .measured_value
+                    .map(|v| format!("{:.4}", v))
+                    .unwrap_or_default(),
+                finding
+                    .threshold
+                    .map(|v| format!("{:.4}", v))
+                    .unwrap_or_default(),
+                escape(&finding.technique),
+                escape(&finding.description)
+            )?;
+        }
+        page.push_str("</table>\n");
+    }
+
+    if !report.annotations.is_empty() {
+        page.push_str("<h2>Annotations</h2>\n<ul>\n");
+        for annotation in &report.annotations {
+            write!(
+                page,
+                "<li><code>{}</code> {}",
+                escape(annotation.rule.as_deref().unwrap_or("scan")),
+                escape(&annotation.label.to_string())
+            )?;
+            if let Some(comment) = &annotation.comment {
+                write!(page, ": {}", escape(comment))?;
+            }
+            write!(page, " ({}", escape(&annotation.added))?;
+            if let Some(analyst) = &annotation.analyst {
+                write!(page, ", {}", escape(analyst))?;
+            }
+            page.push_str(")</li>\n");
+        }
+        page.push_str("</ul>\n");
+    }
+
+    writeln!(
+        page,
+        "<details><summary>Full report</summary>\n<pre>{}</pre>\n</details>\n</body>\n</html>",
+        escape(&report.to_json()?)
+    )?;
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::{SlackRegionReport, SlackSpaceReport};
+    use std::path::PathBuf;
+
+    fn report() -> SteganalysisReport {
+        let mut report =
+            SteganalysisReport::new(&PathBuf::from("<cover>.png"), 4096, "Image".to_string());
+        report.set_slack_space(SlackSpaceReport {
+            container: "PNG".to_string(),
+            accounted_bytes: 3840,
+            slack_bytes: 256,
+            is_suspicious: true,
+            regions: vec![SlackRegionReport {
+                offset: 3840,
+                offset_hex: "0xF00".to_string(),
+                length: 256,
+                entropy: 7.9,
+                description: "Data after IEND".to_string(),
+                detected_type: None,
+                carved_file: None,
+                sha256: None,
+                also_carved_from: Vec::new(),
+            }],
+        });
+        report.finalize_summary(Locale::En);
+        report
+    }
+
+    fn convert(report: &SteganalysisReport, format: ReportFormat) -> String {
+        let mut out = Vec::new();
+        write(report, format, &Thresholds::default(), Locale::En, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_converts_to_each_format() {
+        let report = report();
+
+        let html = convert(&report, ReportFormat::Html);
+        assert!(html.contains("<h1>&lt;cover&gt;.png</h1>"));
+        assert!(!html.contains("<cover>"));
+
+        assert!(html.contains("<code>slack_space.unaccounted_region</code>"));
+
+        let csv = convert(&report, ReportFormat::Csv);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("<cover>.png,Image,"));
+        assert!(row.contains(",slack_space.unaccounted_region,slack_space,"));
+
+        let sarif: serde_json::Value =
+            serde_json::from_str(&convert(&report, ReportFormat::Sarif)).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["runs"][0]["tool"]["driver"]["name"], "stegascan");
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "slack_space.unaccounted_region");
+        assert_eq!(result["level"], "warning");
+    }
+}
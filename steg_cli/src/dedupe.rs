@@ -0,0 +1,173 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Duplicate detection for --dedupe: a planned file that is a hard link to, or a byte-for-byte
+// copy of, an earlier one is not analyzed again. Files are grouped by size first, so only
+// files that share a size with another one are ever hashed.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    HardLink,
+    SameContent,
+}
+
+impl std::fmt::Display for DuplicateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicateKind::HardLink => write!(f, "hard link"),
+            DuplicateKind::SameContent => write!(f, "identical content"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    // Index of the first file with the same content
+    pub canonical: usize,
+    pub kind: DuplicateKind,
+}
+
+// Written at a duplicate's report path in place of a full report
+#[derive(Debug, Serialize)]
+pub struct DuplicateReference {
+    pub file: String,
+    pub duplicate_of: String,
+    pub canonical_report: String,
+    pub kind: DuplicateKind,
+}
+
+// For each file, the earlier file it duplicates. Files that can't be read are left alone
+// for the scan to report.
+pub fn find_duplicates(files: &[&Path]) -> Vec<Option<Duplicate>> {
+    let mut duplicates = vec![None; files.len()];
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        if let Ok(metadata) = std::fs::metadata(file) {
+            by_size.entry(metadata.len()).or_default().push(index);
+        }
+    }
+
+    for group in by_size.values().filter(|group| group.len() > 1) {
+        let mut by_id: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut by_hash: HashMap<[u8; 32], usize> = HashMap::new();
+        for &index in group {
+            let id = file_id(files[index]);
+            if let Some(&canonical) = id.and_then(|id| by_id.get(&id)) {
+                duplicates[index] = Some(Duplicate {
+                    canonical,
+                    kind: DuplicateKind::HardLink,
+                });
+                continue;
+            }
+            if let Some(id) = id {
+                by_id.insert(id, index);
+            }
+
+            let Ok(hash) = sha256_file(files[index]) else {
+                continue;
+            };
+            match by_hash.get(&hash) {
+                Some(&canonical) => {
+                    duplicates[index] = Some(Duplicate {
+                        canonical,
+                        kind: DuplicateKind::SameContent,
+                    })
+                }
+                None => {
+                    by_hash.insert(hash, index);
+                }
+            }
+        }
+    }
+
+    duplicates
+}
+
+impl DuplicateReference {
+    pub fn new(
+        file: &Path,
+        canonical: &Path,
+        canonical_report: &Path,
+        kind: DuplicateKind,
+    ) -> Self {
+        Self {
+            file: file.display().to_string(),
+            duplicate_of: canonical.display().to_string(),
+            canonical_report: canonical_report.display().to_string(),
+            kind,
+        }
+    }
+
+    pub fn write(&self, report: &Path) -> std::io::Result<()> {
+        if let Some(dir) = report.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(report, json)
+    }
+}
+
+// Device and inode, shared by every hard link to a file
+#[cfg(unix)]
+fn file_id(file: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(file).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+// Hard links still hash the same, they're just reported as identical content
+#[cfg(not(unix))]
+fn file_id(_file: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+pub fn sha256_file(file: &Path) -> std::io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(file)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_finds_copies_and_hard_links() {
+        let dir = std::env::temp_dir().join(format!("stegascan_dedupe_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name);
+        std::fs::write(path("a.png"), b"same bytes").unwrap();
+        std::fs::write(path("b.png"), b"same bytes").unwrap();
+        std::fs::write(path("c.png"), b"diff bytes").unwrap();
+        std::fs::write(path("d.png"), b"short").unwrap();
+        std::fs::hard_link(path("c.png"), path("e.png")).unwrap();
+
+        let files = ["a.png", "b.png", "c.png", "d.png", "e.png", "missing.png"].map(path);
+        let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let duplicates = find_duplicates(&files);
+
+        assert_eq!(
+            duplicates[1],
+            Some(Duplicate {
+                canonical: 0,
+                kind: DuplicateKind::SameContent
+            })
+        );
+        assert_eq!(duplicates[0], None);
+        assert_eq!(duplicates[2], None);
+        assert_eq!(duplicates[3], None);
+        assert_eq!(duplicates[4].as_ref().map(|d| d.canonical), Some(2));
+        #[cfg(unix)]
+        assert_eq!(
+            duplicates[4].as_ref().unwrap().kind,
+            DuplicateKind::HardLink
+        );
+        assert_eq!(duplicates[5], None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
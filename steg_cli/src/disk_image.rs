@@ -0,0 +1,791 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Disk images for --disk-image: ISO files, dd dumps of whole disks or single partitions.
+// The image is split into volumes, from an MBR or GPT partition table when there is one,
+// and every file of a volume whose file system is understood (ISO 9660, with Joliet names
+// when present, and FAT12/16/32) is copied out so it can be scanned like any other file.
+//
+// The image is only ever opened for reading; FAT volumes are mounted through a slice that
+// refuses writes, so not even the dirty flag can be set on evidence.
+
+const SECTOR: u64 = 512;
+const ISO_SECTOR: u64 = 2048;
+
+// Volume descriptors start at sector 16 of an ISO 9660 volume
+const ISO_DESCRIPTORS: u64 = 16;
+
+// Directory trees deeper than this are cut off rather than followed, guarding against
+// crafted images whose directories contain themselves
+const MAX_DEPTH: usize = 64;
+
+// No sane directory is larger; a bigger size field is corrupt or hostile
+const MAX_DIRECTORY_BYTES: u64 = 16 * 1024 * 1024;
+
+// GPT entries are a multiple of 128 bytes; larger than this is corrupt or hostile
+const MAX_GPT_ENTRY_BYTES: u64 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSystemKind {
+    Iso9660,
+    Fat,
+    Unrecognized,
+}
+
+impl std::fmt::Display for FileSystemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileSystemKind::Iso9660 => write!(f, "ISO 9660"),
+            FileSystemKind::Fat => write!(f, "FAT"),
+            FileSystemKind::Unrecognized => write!(f, "unrecognized"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Volume {
+    pub index: usize,
+    // Byte range of the volume within the image
+    pub offset: u64,
+    pub size_bytes: u64,
+    pub file_system: FileSystemKind,
+    // Partition table entry the volume came from, None for an unpartitioned image
+    pub partition: Option<String>,
+}
+
+impl Volume {
+    // Directory the volume's files are extracted to, below the image's directory
+    pub fn dir_name(&self) -> String {
+        format!("volume{}", self.index)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedFile {
+    pub volume: usize,
+    // Path inside the volume, '/'-separated
+    pub path: String,
+    pub size_bytes: u64,
+    pub extracted: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum DiskImageError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for DiskImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiskImageError::Io(e) => write!(f, "Disk image I/O error: {}", e),
+            DiskImageError::Invalid(e) => write!(f, "Invalid disk image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DiskImageError {}
+
+impl From<std::io::Error> for DiskImageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// The volumes of an image: the image itself when it holds a file system directly,
+// otherwise one per partition table entry. Empty for a raw dump with neither.
+pub fn find_volumes(image: &Path) -> Result<Vec<Volume>, DiskImageError> {
+    let mut file = File::open(image)?;
+    let size = file.metadata()?.len();
+
+    // Checked before the partition table: hybrid ISOs carry an MBR for USB booting, and a
+    // FAT boot sector ends in the same 0x55AA signature as an MBR
+    if let Some(file_system) = probe(&mut file, 0)? {
+        return Ok(vec![Volume {
+            index: 0,
+            offset: 0,
+            size_bytes: size,
+            file_system,
+            partition: None,
+        }]);
+    }
+
+    let mut partitions = Vec::new();
+    let mbr = read_at(&mut file, 0, SECTOR as usize)?;
+    if mbr.len() < SECTOR as usize || mbr[510..512] != [0x55, 0xAA] {
+        return Ok(Vec::new());
+    }
+    for entry in mbr[446..510].chunks(16) {
+        let kind = entry[4];
+        let start = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as u64;
+        let sectors = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as u64;
+        match kind {
+            0x00 => {}
+            // Protective entry covering the disk: the real table is the GPT behind it
+            0xEE => partitions.extend(gpt_partitions(&mut file)?),
+            0x05 | 0x0F | 0x85 => {
+                log::warn!("Extended partition at sector {} is not followed", start)
+            }
+            _ => partitions.push((
+                start * SECTOR,
+                sectors * SECTOR,
+                format!("MBR type 0x{:02x}", kind),
+            )),
+        }
+    }
+
+    let mut volumes = Vec::new();
+    for (index, (offset, length, partition)) in partitions.into_iter().enumerate() {
+        if offset >= size {
+            log::warn!("{} starts past the end of the image", partition);
+            continue;
+        }
+        volumes.push(Volume {
+            index,
+            offset,
+            size_bytes: length.min(size - offset),
+            file_system: probe(&mut file, offset)?.unwrap_or(FileSystemKind::Unrecognized),
+            partition: Some(partition),
+        });
+    }
+    Ok(volumes)
+}
+
+// Copy every file of `volume` below `dest`, keeping the directory layout
+pub fn extract(
+    image: &Path,
+    volume: &Volume,
+    dest: &Path,
+) -> Result<Vec<ExtractedFile>, DiskImageError> {
+    let file = File::open(image)?;
+    let mut extractor = Extractor {
+        volume: volume.index,
+        dest: dest.to_path_buf(),
+        files: Vec::new(),
+    };
+    match volume.file_system {
+        FileSystemKind::Iso9660 => extract_iso(file, volume.offset, &mut extractor)?,
+        FileSystemKind::Fat => {
+            let slice = VolumeSlice {
+                file,
+                offset: volume.offset,
+                size: volume.size_bytes,
+                position: 0,
+            };
+            let fs = fatfs::FileSystem::new(slice, fatfs::FsOptions::new())?;
+            extract_fat(fs.root_dir(), "", 0, &mut extractor)?;
+        }
+        FileSystemKind::Unrecognized => {}
+    }
+    Ok(extractor.files)
+}
+
+fn probe(file: &mut File, offset: u64) -> std::io::Result<Option<FileSystemKind>> {
+    let descriptor = read_at(file, offset + ISO_DESCRIPTORS * ISO_SECTOR, 6)?;
+    if descriptor.len() == 6 && &descriptor[1..6] == b"CD001" {
+        return Ok(Some(FileSystemKind::Iso9660));
+    }
+    let boot = read_at(file, offset, SECTOR as usize)?;
+    if is_fat_boot_sector(&boot) {
+        return Ok(Some(FileSystemKind::Fat));
+    }
+    Ok(None)
+}
+
+// A BIOS parameter block with sane values; an MBR has boot code where these fields sit
+fn is_fat_boot_sector(boot: &[u8]) -> bool {
+    if boot.len() < SECTOR as usize || boot[510..512] != [0x55, 0xAA] {
+        return false;
+    }
+    let bytes_per_sector = u16::from_le_bytes([boot[11], boot[12]]);
+    let sectors_per_cluster = boot[13];
+    let reserved_sectors = u16::from_le_bytes([boot[14], boot[15]]);
+    let fats = boot[16];
+    matches!(boot[0], 0xEB | 0xE9)
+        && matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+        && sectors_per_cluster.is_power_of_two()
+        && reserved_sectors > 0
+        && (1..=4).contains(&fats)
+}
+
+// (offset, size, description) of every used GPT entry
+fn gpt_partitions(file: &mut File) -> std::io::Result<Vec<(u64, u64, String)>> {
+    let header = read_at(file, SECTOR, 92)?;
+    if header.len() < 92 || &header[0..8] != b"EFI PART" {
+        log::warn!("Protective MBR without a GPT header behind it");
+        return Ok(Vec::new());
+    }
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+    let count = u32::from_le_bytes(header[80..84].try_into().unwrap()).min(1024) as u64;
+    let entry_size = u32::from_le_bytes(header[84..88].try_into().unwrap()) as u64;
+    if !(128..=MAX_GPT_ENTRY_BYTES).contains(&entry_size) || !entry_size.is_multiple_of(128) {
+        log::warn!("GPT entries of {} bytes are not valid", entry_size);
+        return Ok(Vec::new());
+    }
+    let Some(table_offset) = entries_lba.checked_mul(SECTOR) else {
+        log::warn!(
+            "GPT partition entries at LBA {} lie past any disk",
+            entries_lba
+        );
+        return Ok(Vec::new());
+    };
+
+    let table = read_at(file, table_offset, (count * entry_size) as usize)?;
+    let mut partitions = Vec::new();
+    for entry in table.chunks_exact(entry_size as usize) {
+        let type_guid = &entry[0..16];
+        if type_guid.iter().all(|&b| b == 0) {
+            continue;
+        }
+        let first = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+        let range = last
+            .checked_sub(first)
+            .and_then(|sectors| sectors.checked_add(1))
+            .and_then(|sectors| sectors.checked_mul(SECTOR))
+            .zip(first.checked_mul(SECTOR));
+        let Some((size, offset)) = range else {
+            log::warn!("Skipping GPT entry with sectors {} to {}", first, last);
+            continue;
+        };
+        let name: Vec<u16> = entry[56..128]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        let name = String::from_utf16_lossy(&name);
+        partitions.push((
+            offset,
+            size,
+            if name.is_empty() {
+                format!("GPT type {}", guid(type_guid))
+            } else {
+                format!("GPT \"{}\" type {}", name, guid(type_guid))
+            },
+        ));
+    }
+    Ok(partitions)
+}
+
+// Mixed-endian textual form of an on-disk GUID
+fn guid(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}",
+        u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10..16]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<String>()
+    )
+}
+
+// Up to `length` bytes from `offset`, fewer at the end of the file
+fn read_at(file: &mut File, offset: u64, length: usize) -> std::io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::with_capacity(length);
+    (&mut *file).take(length as u64).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+struct Extractor {
+    volume: usize,
+    dest: PathBuf,
+    files: Vec<ExtractedFile>,
+}
+
+impl Extractor {
+    // Where a file at `path` inside the volume goes, or None when no component of it is
+    // usable as a file name
+    fn target(&self, path: &str) -> Option<PathBuf> {
+        let mut target = self.dest.clone();
+        for component in path.split('/') {
+            // Names come from the image, so they must not climb out of the destination
+            let safe: String = component
+                .chars()
+                .map(|c| if matches!(c, '\\' | '\0') { '_' } else { c })
+                .collect();
+            if safe.is_empty() || safe == "." || safe == ".." {
+                return None;
+            }
+            target.push(safe);
+        }
+        Some(target)
+    }
+
+    fn write(&mut self, path: &str, contents: &mut dyn Read) -> std::io::Result<()> {
+        let Some(target) = self.target(path) else {
+            log::warn!("Skipping {:?}: not a usable file name", path);
+            return Ok(());
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = File::create(&target)?;
+        let size_bytes = std::io::copy(contents, &mut out)?;
+        self.files.push(ExtractedFile {
+            volume: self.volume,
+            path: path.to_string(),
+            size_bytes,
+            extracted: target,
+        });
+        Ok(())
+    }
+}
+
+fn extract_fat<T: fatfs::ReadWriteSeek>(
+    dir: fatfs::Dir<'_, T>,
+    prefix: &str,
+    depth: usize,
+    extractor: &mut Extractor,
+) -> std::io::Result<()> {
+    if depth > MAX_DEPTH {
+        log::warn!("Directory tree too deep, not descending into {}", prefix);
+        return Ok(());
+    }
+    for entry in dir.iter() {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "." || name == ".." {
+            continue;
+        }
+        let path = format!("{}{}", prefix, name);
+        if entry.is_dir() {
+            extract_fat(entry.to_dir(), &format!("{}/", path), depth + 1, extractor)?;
+        } else {
+            extractor.write(&path, &mut entry.to_file())?;
+        }
+    }
+    Ok(())
+}
+
+struct IsoRecord {
+    extent: u64,
+    size: u64,
+    directory: bool,
+    // More extents of the same file follow in the next record
+    continued: bool,
+    name: String,
+}
+
+fn extract_iso(
+    mut file: File,
+    offset: u64,
+    extractor: &mut Extractor,
+) -> Result<(), DiskImageError> {
+    // The Joliet supplementary descriptor, when there is one, has the full Unicode names
+    let mut primary = None;
+    let mut joliet = None;
+    for sector in ISO_DESCRIPTORS..ISO_DESCRIPTORS + 64 {
+        let descriptor = read_at(&mut file, offset + sector * ISO_SECTOR, ISO_SECTOR as usize)?;
+        if descriptor.len() < ISO_SECTOR as usize || &descriptor[1..6] != b"CD001" {
+            break;
+        }
+        match descriptor[0] {
+            1 => primary = parse_record(&descriptor[156..190], false),
+            2 if matches!(&descriptor[88..91], b"%/@" | b"%/C" | b"%/E") => {
+                joliet = parse_record(&descriptor[156..190], true)
+            }
+            255 => break,
+            _ => {}
+        }
+    }
+    let (root, unicode) = match (joliet, primary) {
+        (Some(root), _) => (root, true),
+        (None, Some(root)) => (root, false),
+        (None, None) => {
+            return Err(DiskImageError::Invalid(
+                "ISO 9660 volume without a primary volume descriptor".to_string(),
+            ));
+        }
+    };
+
+    let mut visited = HashSet::new();
+    extract_iso_dir(
+        &mut file,
+        offset,
+        &root,
+        "",
+        unicode,
+        0,
+        &mut visited,
+        extractor,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn extract_iso_dir(
+    file: &mut File,
+    offset: u64,
+    dir: &IsoRecord,
+    prefix: &str,
+    unicode: bool,
+    depth: usize,
+    visited: &mut HashSet<u64>,
+    extractor: &mut Extractor,
+) -> Result<(), DiskImageError> {
+    if depth > MAX_DEPTH || !visited.insert(dir.extent) {
+        log::warn!("Directory loop or tree too deep at {}", prefix);
+        return Ok(());
+    }
+    if dir.size > MAX_DIRECTORY_BYTES {
+        return Err(DiskImageError::Invalid(format!(
+            "directory {} claims {} bytes",
+            prefix, dir.size
+        )));
+    }
+    let data = read_at(file, offset + dir.extent * ISO_SECTOR, dir.size as usize)?;
+
+    let mut records = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let length = data[position] as usize;
+        // Records never straddle a sector; a zero length pads out the rest of one
+        if length == 0 {
+            position = (position / ISO_SECTOR as usize + 1) * ISO_SECTOR as usize;
+            continue;
+        }
+        if position + length > data.len() {
+            break;
+        }
+        let raw = &data[position..position + length];
+        // Skip the "." and ".." entries, whose names are the single bytes 0 and 1
+        let self_or_parent = raw.len() > 33 && raw[32] == 1 && raw[33] <= 1;
+        if let Some(record) = parse_record(raw, unicode).filter(|_| !self_or_parent) {
+            records.push(record);
+        }
+        position += length;
+    }
+
+    // A file over 4 GiB is stored as several records of the same name, in order
+    let mut extents: Vec<(u64, u64)> = Vec::new();
+    for record in records {
+        let path = format!("{}{}", prefix, record.name);
+        if record.directory {
+            extract_iso_dir(
+                file,
+                offset,
+                &record,
+                &format!("{}/", path),
+                unicode,
+                depth + 1,
+                visited,
+                extractor,
+            )?;
+            continue;
+        }
+        extents.push((record.extent, record.size));
+        if record.continued {
+            continue;
+        }
+        let mut parts: Vec<Box<dyn Read>> = Vec::new();
+        for (extent, size) in extents.drain(..) {
+            let mut part = file.try_clone()?;
+            part.seek(SeekFrom::Start(offset + extent * ISO_SECTOR))?;
+            parts.push(Box::new(part.take(size)));
+        }
+        let mut contents = parts
+            .into_iter()
+            .fold(Box::new(std::io::empty()) as Box<dyn Read>, |all, part| {
+                Box::new(all.chain(part))
+            });
+        extractor.write(&path, &mut contents)?;
+    }
+    Ok(())
+}
+
+// A directory record; None for records too short to hold their own name
+fn parse_record(record: &[u8], unicode: bool) -> Option<IsoRecord> {
+    if record.len() < 34 {
+        return None;
+    }
+    let name_length = record[32] as usize;
+    let raw_name = record.get(33..33 + name_length)?;
+    let mut name = if unicode {
+        let units: Vec<u16> = raw_name
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(raw_name).to_string()
+    };
+    // "NAME.EXT;1": drop the version, and the dot of a name without an extension
+    if let Some(semicolon) = name.rfind(';') {
+        name.truncate(semicolon);
+    }
+    if name.ends_with('.') {
+        name.pop();
+    }
+    let flags = record[25];
+    Some(IsoRecord {
+        extent: u32::from_le_bytes(record[2..6].try_into().unwrap()) as u64,
+        size: u32::from_le_bytes(record[10..14].try_into().unwrap()) as u64,
+        directory: flags & 0x02 != 0,
+        continued: flags & 0x80 != 0,
+        name,
+    })
+}
+
+// One volume of the image as a stream of its own, for the FAT driver. Writes fail.
+struct VolumeSlice {
+    file: File,
+    offset: u64,
+    size: u64,
+    position: u64,
+}
+
+impl Read for VolumeSlice {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.size.saturating_sub(self.position);
+        let wanted = (buf.len() as u64).min(left) as usize;
+        if wanted == 0 {
+            return Ok(0);
+        }
+        self.file
+            .seek(SeekFrom::Start(self.offset + self.position))?;
+        let read = self.file.read(&mut buf[..wanted])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for VolumeSlice {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+        };
+        let Some(position) = position else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before the start of the volume",
+            ));
+        };
+        self.position = position;
+        Ok(position)
+    }
+}
+
+impl Write for VolumeSlice {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "disk images are opened read-only",
+        ))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stegascan_disk_image_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fat_volume() -> Vec<u8> {
+        let mut disk = std::io::Cursor::new(vec![0u8; 2 * 1024 * 1024]);
+        fatfs::format_volume(&mut disk, fatfs::FormatVolumeOptions::new()).unwrap();
+        {
+            let fs = fatfs::FileSystem::new(&mut disk, fatfs::FsOptions::new()).unwrap();
+            let root = fs.root_dir();
+            root.create_file("cover.png")
+                .unwrap()
+                .write_all(b"\x89PNG fake")
+                .unwrap();
+            root.create_dir("docs").unwrap();
+            root.create_file("docs/notes.txt")
+                .unwrap()
+                .write_all(b"hello")
+                .unwrap();
+        }
+        disk.into_inner()
+    }
+
+    fn directory_record(extent: u32, size: u32, flags: u8, name: &[u8]) -> Vec<u8> {
+        let mut record = vec![0u8; 33];
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[6..10].copy_from_slice(&extent.to_be_bytes());
+        record[10..14].copy_from_slice(&size.to_le_bytes());
+        record[14..18].copy_from_slice(&size.to_be_bytes());
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record.extend_from_slice(name);
+        if record.len() % 2 == 1 {
+            record.push(0);
+        }
+        record[0] = record.len() as u8;
+        record
+    }
+
+    // Primary descriptor, root directory in sector 18 and one file in sector 19
+    fn iso_volume() -> Vec<u8> {
+        let sector = ISO_SECTOR as usize;
+        let mut iso = vec![0u8; 20 * sector];
+        let pvd = &mut iso[16 * sector..17 * sector];
+        pvd[0] = 1;
+        pvd[1..6].copy_from_slice(b"CD001");
+        let root = directory_record(18, ISO_SECTOR as u32, 0x02, &[0]);
+        pvd[156..156 + root.len()].copy_from_slice(&root);
+        let terminator = &mut iso[17 * sector..18 * sector];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+
+        let mut entries = directory_record(18, ISO_SECTOR as u32, 0x02, &[0]);
+        entries.extend(directory_record(18, ISO_SECTOR as u32, 0x02, &[1]));
+        entries.extend(directory_record(19, 11, 0, b"SECRET.JPG;1"));
+        iso[18 * sector..18 * sector + entries.len()].copy_from_slice(&entries);
+        iso[19 * sector..19 * sector + 11].copy_from_slice(b"jpeg stuffs");
+        iso
+    }
+
+    #[test]
+    fn test_extracts_unpartitioned_fat() {
+        let dir = temp_dir("fat");
+        let image = dir.join("usb.dd");
+        std::fs::write(&image, fat_volume()).unwrap();
+
+        let volumes = find_volumes(&image).unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].file_system, FileSystemKind::Fat);
+        assert!(volumes[0].partition.is_none());
+
+        let files = extract(&image, &volumes[0], &dir.join("out")).unwrap();
+        let mut paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["cover.png", "docs/notes.txt"]);
+        assert_eq!(
+            std::fs::read(dir.join("out/docs/notes.txt")).unwrap(),
+            b"hello"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_finds_fat_partition_behind_mbr() {
+        let dir = temp_dir("mbr");
+        let image = dir.join("disk.dd");
+        let mut disk = vec![0u8; 2048 * SECTOR as usize];
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+        let fat = fat_volume();
+        let entry = &mut disk[446..462];
+        entry[4] = 0x0C;
+        entry[8..12].copy_from_slice(&2048u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&((fat.len() as u64 / SECTOR) as u32).to_le_bytes());
+        disk.extend_from_slice(&fat);
+        std::fs::write(&image, disk).unwrap();
+
+        let volumes = find_volumes(&image).unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].offset, 2048 * SECTOR);
+        assert_eq!(volumes[0].file_system, FileSystemKind::Fat);
+        assert_eq!(volumes[0].partition.as_deref(), Some("MBR type 0x0c"));
+        let files = extract(&image, &volumes[0], &dir.join("out")).unwrap();
+        assert_eq!(files.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_skips_malformed_gpt() {
+        let dir = temp_dir("gpt");
+        let image = dir.join("disk.dd");
+        // Protective MBR, GPT header in sector 1 and its entries in sector 2
+        let gpt = |entry_size: u32, entries_lba: u64, first: u64, last: u64| {
+            let mut disk = vec![0u8; 4 * SECTOR as usize];
+            disk[510] = 0x55;
+            disk[511] = 0xAA;
+            disk[446 + 4] = 0xEE;
+            let header = &mut disk[SECTOR as usize..];
+            header[0..8].copy_from_slice(b"EFI PART");
+            header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            header[80..84].copy_from_slice(&1024u32.to_le_bytes());
+            header[84..88].copy_from_slice(&entry_size.to_le_bytes());
+            let entry = &mut disk[2 * SECTOR as usize..];
+            entry[0] = 0xAF;
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&last.to_le_bytes());
+            disk
+        };
+
+        std::fs::write(&image, gpt(128, 2, 1, 3)).unwrap();
+        let volumes = find_volumes(&image).unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].offset, SECTOR);
+
+        for disk in [
+            // Would have the table read allocate 4 TiB
+            gpt(u32::MAX, 2, 1, 3),
+            gpt(200, 2, 1, 3),
+            gpt(128, u64::MAX, 1, 3),
+            gpt(128, 2, u64::MAX / 2, u64::MAX),
+            gpt(128, 2, 0, u64::MAX),
+            gpt(128, 2, 3, 1),
+        ] {
+            std::fs::write(&image, disk).unwrap();
+            assert!(find_volumes(&image).unwrap().is_empty());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extracts_iso9660() {
+        let dir = temp_dir("iso");
+        let image = dir.join("cd.iso");
+        std::fs::write(&image, iso_volume()).unwrap();
+
+        let volumes = find_volumes(&image).unwrap();
+        assert_eq!(volumes[0].file_system, FileSystemKind::Iso9660);
+        let files = extract(&image, &volumes[0], &dir.join("out")).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "SECRET.JPG");
+        assert_eq!(
+            std::fs::read(dir.join("out/SECRET.JPG")).unwrap(),
+            b"jpeg stuffs"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_raw_dump_has_no_volumes() {
+        let dir = temp_dir("raw");
+        let image = dir.join("raw.bin");
+        std::fs::write(&image, vec![0x41u8; 64 * 1024]).unwrap();
+        assert!(find_volumes(&image).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_names_cannot_escape_destination() {
+        let extractor = Extractor {
+            volume: 0,
+            dest: PathBuf::from("/tmp/out"),
+            files: Vec::new(),
+        };
+        assert!(extractor.target("../etc/passwd").is_none());
+        assert_eq!(
+            extractor.target("a\\b/c").unwrap(),
+            PathBuf::from("/tmp/out/a_b/c")
+        );
+    }
+}
@@ -0,0 +1,493 @@
+pub use analyzers::shared::hex;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+// Evidence package for --export-evidence: one ZIP with everything a scan produced, so the
+// result can be archived or handed to another analyst as a single item.
+//
+//   manifest.json     what the package holds, with the size and SHA-256 of every file
+//   manifest.sig      hex HMAC-SHA256 of manifest.json under the --evidence-key key
+//   original/<name>   the scanned file
+//   report.json       the scan report
+//   artifacts/...     images, carved payloads and extracted files the scan wrote
+//
+// The manifest pins every file by hash and the signature pins the manifest, so anyone
+// holding the key can tell whether anything in the package was changed. `stegascan open`
+// checks both before it unpacks a package or re-runs analyzers on its original.
+
+pub const MANIFEST: &str = "manifest.json";
+pub const SIGNATURE: &str = "manifest.sig";
+pub const REPORT: &str = "report.json";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvidenceManifest {
+    pub format_version: u32,
+    pub tool: String,
+    pub created: String,
+    // Package path of the scanned file
+    pub original: String,
+    pub files: Vec<PackagedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackagedFile {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug)]
+pub enum EvidenceError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Json(serde_json::Error),
+    Key(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for EvidenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvidenceError::Io(e) => write!(f, "Evidence package I/O error: {}", e),
+            EvidenceError::Zip(e) => write!(f, "Evidence package ZIP error: {}", e),
+            EvidenceError::Json(e) => write!(f, "Evidence manifest error: {}", e),
+            EvidenceError::Key(e) => write!(f, "Evidence key error: {}", e),
+            EvidenceError::Invalid(e) => write!(f, "Invalid evidence package: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EvidenceError {}
+
+impl From<std::io::Error> for EvidenceError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for EvidenceError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Zip(e)
+    }
+}
+
+impl From<serde_json::Error> for EvidenceError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+// The key file's contents, surrounding whitespace ignored
+pub fn load_key(path: &Path) -> Result<Vec<u8>, EvidenceError> {
+    let key = std::fs::read(path)?;
+    let key = key.trim_ascii();
+    if key.is_empty() {
+        return Err(EvidenceError::Key(format!("{} is empty", path.display())));
+    }
+    Ok(key.to_vec())
+}
+
+pub fn sign(manifest: &[u8], key: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(manifest);
+    hex(&mac.finalize().into_bytes())
+}
+
+// A fresh directory under `output_dir` for the artifacts of one scan. Packages take everything
+// in it, so no other scan, earlier or concurrent, may write there.
+pub fn scan_dir(output_dir: &Path, file_name: &str) -> Result<PathBuf, EvidenceError> {
+    std::fs::create_dir_all(output_dir)?;
+    Ok(tempfile::Builder::new()
+        .prefix(&format!("{}_evidence_", file_name))
+        .tempdir_in(output_dir)?
+        .keep())
+}
+
+// Artifacts of one scan: everything in the scan's own output directory except its report.
+// Directories, such as the extraction output, are packaged with everything in them. Returns
+// the file paths with their package paths, sorted.
+pub fn collect_artifacts(dir: &Path, report: &Path) -> Vec<(PathBuf, String)> {
+    let mut artifacts = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return artifacts;
+    };
+
+    for entry in entries.flatten() {
+        if entry.path() == report {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        add_tree(
+            &entry.path(),
+            &format!("artifacts/{}", name),
+            &mut artifacts,
+        );
+    }
+    artifacts.sort_by(|a, b| a.1.cmp(&b.1));
+    artifacts
+}
+
+fn add_tree(path: &Path, package_path: &str, out: &mut Vec<(PathBuf, String)>) {
+    if path.is_file() {
+        out.push((path.to_path_buf(), package_path.to_string()));
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        add_tree(&entry.path(), &format!("{}/{}", package_path, name), out);
+    }
+}
+
+// Write the package to `out` and return its manifest
+pub fn export(
+    out: &Path,
+    original: &Path,
+    report_json: &str,
+    artifacts: &[(PathBuf, String)],
+    key: &[u8],
+) -> Result<EvidenceManifest, EvidenceError> {
+    let original_name = original
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "original".to_string());
+    let original_path = format!("original/{}", original_name);
+
+    if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut zip = zip::ZipWriter::new(File::create(out)?);
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+    let mut files = Vec::new();
+
+    files.push(add_file(&mut zip, options, original, &original_path)?);
+    zip.start_file(REPORT, options)?;
+    zip.write_all(report_json.as_bytes())?;
+    files.push(PackagedFile {
+        path: REPORT.to_string(),
+        size_bytes: report_json.len() as u64,
+        sha256: hex(&Sha256::digest(report_json.as_bytes())),
+    });
+    for (path, package_path) in artifacts {
+        files.push(add_file(&mut zip, options, path, package_path)?);
+    }
+
+    let manifest = EvidenceManifest {
+        format_version: FORMAT_VERSION,
+        tool: format!("stegascan {}", env!("CARGO_PKG_VERSION")),
+        created: chrono::Utc::now().to_rfc3339(),
+        original: original_path,
+        files,
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)?;
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(manifest_json.as_bytes())?;
+    zip.start_file(SIGNATURE, options)?;
+    zip.write_all(sign(manifest_json.as_bytes(), key).as_bytes())?;
+    zip.finish()?;
+
+    Ok(manifest)
+}
+
+// Hashes the file on the way into the package, so it is read only once
+fn add_file(
+    zip: &mut zip::ZipWriter<File>,
+    options: SimpleFileOptions,
+    path: &Path,
+    package_path: &str,
+) -> Result<PackagedFile, EvidenceError> {
+    zip.start_file(package_path, options)?;
+    let mut writer = HashingWriter {
+        inner: zip,
+        hasher: Sha256::new(),
+    };
+    let size_bytes = std::io::copy(&mut File::open(path)?, &mut writer)?;
+    Ok(PackagedFile {
+        path: package_path.to_string(),
+        size_bytes,
+        sha256: hex(&writer.hasher.finalize()),
+    })
+}
+
+struct HashingWriter<'a, W: Write> {
+    inner: &'a mut W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// A package opened for reading. Nothing in it is trusted until `verify_signature` and
+// `check_files` have passed.
+pub struct EvidencePackage {
+    archive: zip::ZipArchive<File>,
+    pub manifest: EvidenceManifest,
+    manifest_json: Vec<u8>,
+    signature: String,
+}
+
+impl EvidencePackage {
+    pub fn open(path: &Path) -> Result<Self, EvidenceError> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let manifest_json = read_entry(&mut archive, MANIFEST)?;
+        let signature = String::from_utf8_lossy(&read_entry(&mut archive, SIGNATURE)?)
+            .trim()
+            .to_string();
+        let manifest: EvidenceManifest = serde_json::from_slice(&manifest_json)?;
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(EvidenceError::Invalid(format!(
+                "format version {} is newer than this build understands ({})",
+                manifest.format_version, FORMAT_VERSION
+            )));
+        }
+        Ok(Self {
+            archive,
+            manifest,
+            manifest_json,
+            signature,
+        })
+    }
+
+    pub fn verify_signature(&self, key: &[u8]) -> bool {
+        let Some(tag) = unhex(&self.signature) else {
+            return false;
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+        mac.update(&self.manifest_json);
+        mac.verify_slice(&tag).is_ok()
+    }
+
+    // Every way the package's contents differ from its manifest; empty when they match
+    pub fn check_files(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for file in &self.manifest.files {
+            match self.archive.by_name(&file.path) {
+                Ok(mut entry) => {
+                    let mut hasher = Sha256::new();
+                    match std::io::copy(&mut entry, &mut hasher) {
+                        Ok(size) if size == file.size_bytes => {
+                            if hex(&hasher.finalize()) != file.sha256 {
+                                problems.push(format!("{}: SHA-256 does not match", file.path));
+                            }
+                        }
+                        Ok(size) => problems.push(format!(
+                            "{}: {} bytes, manifest says {}",
+                            file.path, size, file.size_bytes
+                        )),
+                        Err(e) => problems.push(format!("{}: {}", file.path, e)),
+                    }
+                }
+                Err(_) => problems.push(format!("{}: missing", file.path)),
+            }
+        }
+
+        let unlisted: Vec<String> = self
+            .archive
+            .file_names()
+            .filter(|name| {
+                !name.ends_with('/')
+                    && *name != MANIFEST
+                    && *name != SIGNATURE
+                    && !self.manifest.files.iter().any(|file| file.path == *name)
+            })
+            .map(|name| format!("{}: not in the manifest", name))
+            .collect();
+        problems.extend(unlisted);
+        problems
+    }
+
+    pub fn read(&mut self, path: &str) -> Result<Vec<u8>, EvidenceError> {
+        read_entry(&mut self.archive, path)
+    }
+
+    // Unpack every file into `dir`; entries that would land outside it are refused
+    pub fn unpack(&mut self, dir: &Path) -> Result<(), EvidenceError> {
+        Ok(self.archive.extract(dir)?)
+    }
+
+    // Where the original was unpacked in `dir`. Without a verified signature the manifest is
+    // only as trustworthy as whoever made the package, so its original has to be one of the
+    // packaged files and must not lead out of the unpack directory.
+    pub fn unpacked_original(&self, dir: &Path) -> Result<PathBuf, EvidenceError> {
+        let original = &self.manifest.original;
+        let invalid =
+            || EvidenceError::Invalid(format!("original {:?} is not in the package", original));
+        if !self
+            .manifest
+            .files
+            .iter()
+            .any(|file| file.path == *original)
+            || !Path::new(original)
+                .components()
+                .all(|component| matches!(component, std::path::Component::Normal(_)))
+        {
+            return Err(invalid());
+        }
+
+        // Extraction may have created symlinks, so compare where the paths really lead
+        let path = dir.join(original).canonicalize()?;
+        if !path.starts_with(dir.canonicalize()?) || !path.is_file() {
+            return Err(invalid());
+        }
+        Ok(path)
+    }
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, path: &str) -> Result<Vec<u8>, EvidenceError> {
+    let mut entry = archive.by_name(path).map_err(|e| match e {
+        zip::result::ZipError::FileNotFound => {
+            EvidenceError::Invalid(format!("{} is missing", path))
+        }
+        e => EvidenceError::Zip(e),
+    })?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exports_signed_package() {
+        let dir = std::env::temp_dir().join(format!("stegascan_evidence_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cover.png"), b"original bytes").unwrap();
+        // Left by an earlier scan of a file with the same name
+        std::fs::write(dir.join("cover.png_lsb_0.png"), b"not ours").unwrap();
+
+        let outputs = scan_dir(&dir, "cover.png").unwrap();
+        assert_ne!(outputs, scan_dir(&dir, "cover.png").unwrap());
+        std::fs::create_dir_all(outputs.join("cover.png_extracted")).unwrap();
+        std::fs::write(outputs.join("cover.png_lsb_0.png"), b"lsb plane").unwrap();
+        std::fs::write(outputs.join("cover.png_extracted/0x40.zip"), b"carved").unwrap();
+        std::fs::write(outputs.join("report.json"), b"{}").unwrap();
+
+        let artifacts = collect_artifacts(&outputs, &outputs.join("report.json"));
+        let names: Vec<&str> = artifacts.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "artifacts/cover.png_extracted/0x40.zip",
+                "artifacts/cover.png_lsb_0.png"
+            ]
+        );
+
+        let package = dir.join("evidence.zip");
+        let manifest = export(
+            &package,
+            &dir.join("cover.png"),
+            "{}",
+            &artifacts,
+            b"secret",
+        )
+        .unwrap();
+        assert_eq!(manifest.original, "original/cover.png");
+        assert_eq!(manifest.files.len(), 4);
+        assert_eq!(
+            manifest.files[0].sha256,
+            hex(&Sha256::digest(b"original bytes"))
+        );
+
+        let mut archive = zip::ZipArchive::new(File::open(&package).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+        let manifest_json = read(MANIFEST);
+        assert_eq!(read(SIGNATURE), sign(manifest_json.as_bytes(), b"secret"));
+        assert_ne!(read(SIGNATURE), sign(manifest_json.as_bytes(), b"other"));
+        assert_eq!(read("artifacts/cover.png_extracted/0x40.zip"), "carved");
+
+        let mut opened = EvidencePackage::open(&package).unwrap();
+        assert!(opened.verify_signature(b"secret"));
+        assert!(!opened.verify_signature(b"other"));
+        assert!(opened.check_files().is_empty());
+        assert_eq!(
+            opened.read(&opened.manifest.original.clone()).unwrap(),
+            b"original bytes"
+        );
+
+        let unpacked = dir.join("unpacked");
+        opened.unpack(&unpacked).unwrap();
+        assert_eq!(
+            std::fs::read(opened.unpacked_original(&unpacked).unwrap()).unwrap(),
+            b"original bytes"
+        );
+        for original in ["../cover.png", "/etc/passwd", "original/missing.png"] {
+            opened.manifest.original = original.to_string();
+            assert!(opened.unpacked_original(&unpacked).is_err(), "{}", original);
+        }
+        // Listed in a doctored manifest, but still outside the unpack directory
+        opened.manifest.files.push(PackagedFile {
+            path: "../cover.png".to_string(),
+            size_bytes: 0,
+            sha256: String::new(),
+        });
+        opened.manifest.original = "../cover.png".to_string();
+        assert!(opened.unpacked_original(&unpacked).is_err());
+
+        // A package whose original was swapped after signing
+        let tampered = dir.join("tampered.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&tampered).unwrap());
+        for name in archive.file_names().map(str::to_string).collect::<Vec<_>>() {
+            let mut bytes = Vec::new();
+            archive
+                .by_name(&name)
+                .unwrap()
+                .read_to_end(&mut bytes)
+                .unwrap();
+            if name == "original/cover.png" {
+                bytes = b"altered bytes!".to_vec();
+            }
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&bytes).unwrap();
+        }
+        writer.finish().unwrap();
+        let mut opened = EvidencePackage::open(&tampered).unwrap();
+        assert!(opened.verify_signature(b"secret"));
+        assert_eq!(
+            opened.check_files(),
+            vec!["original/cover.png: SHA-256 does not match"]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
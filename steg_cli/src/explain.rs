@@ -0,0 +1,1202 @@
+use analyzers::model_analyzer::MIN_BLOB_CHARS;
+use analyzers::page_analyzer::OUTLIER_SCORE_THRESHOLD;
+use analyzers::phase_analyzer::MIN_QUANTIZED_RUN;
+use analyzers::scene_detector::LSB_DIVERGENCE_RATIO;
+use analyzers::slack_space_analyzer::MIN_SLACK_BYTES;
+use analyzers::source_code_analyzer::COMMENT_RATIO_THRESHOLD;
+use analyzers::spectrogram_analyzer::HIGH_FREQUENCY_CUTOFF_HZ;
+use analyzers::structured_text_analyzer::MAX_VALUE_CHARS;
+use analyzers::text_stego_analyzer::{MIN_HOMOGLYPH_WORDS, MIN_WHITESPACE_LINES};
+
+use crate::config::Thresholds;
+use crate::i18n::{Locale, tr};
+use crate::json_report::*;
+
+// Build an explanation for every finding that fired in the report, so an analyst
+// can see the measured value, the threshold it crossed and the technique behind it.
+// Technique and description are written in `locale`; rule IDs stay the same in every language.
+pub fn explain_report(
+    report: &SteganalysisReport,
+    thresholds: &Thresholds,
+    locale: Locale,
+) -> Vec<FindingExplanation> {
+    let mut explanations = Explanations {
+        locale,
+        list: Vec::new(),
+    };
+
+    if let Some(ref magic) = report.magic_bytes_analysis {
+        explain_magic_bytes(magic, &mut explanations);
+    }
+
+    if let Some(ref slack) = report.slack_space {
+        explain_slack_space(slack, &mut explanations);
+    }
+
+    if let Some(ref deflate) = report.deflate_streams {
+        explain_deflate_streams(deflate, &mut explanations);
+    }
+
+    for plugin in report.plugins.iter().filter(|p| p.is_suspicious) {
+        for finding in &plugin.findings {
+            explanations.push(
+                "plugin.finding",
+                &plugin.name,
+                finding.confidence,
+                None,
+                &[("text", finding.description.clone())],
+            );
+        }
+    }
+
+    match &report.format_specific_analysis {
+        FormatSpecificAnalysis::Image(img) => {
+            if let Some(ref lsb) = img.lsb_analysis {
+                explain_lsb(lsb, thresholds, &mut explanations);
+            }
+            if let Some(ref ws) = img.ws_analysis {
+                explain_ws(ws, thresholds, &mut explanations);
+            }
+            if let Some(ref correlation) = img.channel_correlation {
+                explain_channel_correlation(correlation, thresholds, &mut explanations);
+            }
+            if let Some(ref adaptive) = img.adaptive_lsb {
+                explain_adaptive_lsb(adaptive, thresholds, &mut explanations);
+            }
+            if let Some(ref bpcs) = img.bpcs {
+                explain_bpcs(bpcs, thresholds, &mut explanations);
+            }
+            if let Some(ref calibration) = img.calibration {
+                explain_calibration(calibration, thresholds, &mut explanations);
+            }
+            if let Some(ref pages) = img.page_analysis {
+                explain_pages(pages, &mut explanations);
+            }
+            if let Some(ref exif) = img.exif_metadata {
+                for field in &exif.suspicious_fields {
+                    explanations.push(
+                        "exif.suspicious_field",
+                        "exif",
+                        None,
+                        None,
+                        &[("text", field.clone())],
+                    );
+                }
+            }
+            if let Some(ref missing) = img.missing_metadata {
+                explanations.push(
+                    "exif.missing_metadata",
+                    "exif",
+                    None,
+                    None,
+                    &[
+                        ("source", missing.expected_source.clone()),
+                        ("fields", missing.missing_fields.join(", ")),
+                    ],
+                );
+            }
+        }
+        FormatSpecificAnalysis::Audio(audio) => {
+            if let Some(ref spec) = audio.spectrogram_analysis {
+                explain_spectrogram(spec, thresholds, &mut explanations);
+            }
+            if let Some(ref ultrasonic) = audio.ultrasonic_analysis {
+                explain_ultrasonic(ultrasonic, thresholds, &mut explanations);
+            }
+            if let Some(ref phase) = audio.phase_analysis {
+                explain_phase(phase, thresholds, &mut explanations);
+            }
+            if let Some(ref differential) = audio.differential {
+                explain_differential(differential, &mut explanations);
+            }
+            if let Some(ref demodulation) = audio.demodulation {
+                explain_demodulation(demodulation, &mut explanations);
+            }
+            if let Some(ref id3) = audio.id3_analysis {
+                for frame in &id3.suspicious_frames {
+                    explanations.push(
+                        "id3.suspicious_frame",
+                        "id3",
+                        None,
+                        None,
+                        &[("text", frame.clone())],
+                    );
+                }
+                for picture in id3.pictures.iter().filter(|p| p.is_suspicious) {
+                    explanations.push(
+                        "id3.embedded_picture",
+                        "id3",
+                        Some(picture.size_bytes as f64),
+                        None,
+                        &[
+                            ("picture_type", picture.picture_type.clone()),
+                            ("mime", picture.mime_type.clone()),
+                        ],
+                    );
+                }
+            }
+            if let Some(ref trailing) = audio.trailing_tags {
+                explain_trailing_tags(trailing, &mut explanations);
+            }
+            if let Some(ref mp4_tags) = audio.mp4_tags {
+                explain_mp4_tags(mp4_tags, &mut explanations);
+            }
+        }
+        FormatSpecificAnalysis::Video(video) => {
+            for scene in video.scenes.iter().filter(|s| s.suspicious) {
+                explanations.push(
+                    "video.scene_lsb_divergence",
+                    "video",
+                    Some(scene.lsb_divergence),
+                    Some(LSB_DIVERGENCE_RATIO),
+                    &[
+                        ("list", format!("{:?}", scene.divergent_frames)),
+                        ("start", scene.start_frame.to_string()),
+                        ("end", scene.end_frame.to_string()),
+                        ("ratio", format!("{:.1}", scene.lsb_divergence)),
+                        (
+                            "median",
+                            format!("{:.1}", scene.median_chi_square.unwrap_or_default()),
+                        ),
+                        ("limit", LSB_DIVERGENCE_RATIO.to_string()),
+                    ],
+                );
+            }
+            if let Some(ref mp4_tags) = video.mp4_tags {
+                explain_mp4_tags(mp4_tags, &mut explanations);
+            }
+        }
+        FormatSpecificAnalysis::Text(text) => {
+            if let Some(ref ole) = text.ole {
+                explain_ole(ole, &mut explanations);
+            }
+            if let Some(ref ebook) = text.ebook {
+                explain_ebook(ebook, &mut explanations);
+            }
+            if let Some(ref model) = text.model {
+                explain_model(model, &mut explanations);
+            }
+            if let Some(ref structured) = text.structured {
+                explain_structured(structured, &mut explanations);
+            }
+            if let Some(ref source_code) = text.source_code {
+                explain_source_code(source_code, &mut explanations);
+            }
+        }
+        _ => {}
+    }
+
+    explanations.list
+}
+
+// The report's findings worked out again from its sections, plus any stored with it that the
+// current rules no longer produce, for reports saved without --explain or by older versions
+pub fn report_findings(
+    report: &SteganalysisReport,
+    thresholds: &Thresholds,
+    locale: Locale,
+) -> Vec<FindingExplanation> {
+    let mut findings = explain_report(report, thresholds, locale);
+    for stored in &report.explanations {
+        if !findings.iter().any(|f| f.rule_id == stored.rule_id) {
+            findings.push(stored.clone());
+        }
+    }
+    findings
+}
+
+// Collects explanations, looking up the technique and description of each rule in the
+// message catalog under "technique.<rule_id>" and "explain.<rule_id>"
+struct Explanations {
+    locale: Locale,
+    list: Vec<FindingExplanation>,
+}
+
+impl Explanations {
+    fn push(
+        &mut self,
+        rule_id: &str,
+        analyzer: &str,
+        measured_value: Option<f64>,
+        threshold: Option<f64>,
+        args: &[(&str, String)],
+    ) {
+        self.list.push(FindingExplanation {
+            rule_id: rule_id.to_string(),
+            analyzer: analyzer.to_string(),
+            measured_value,
+            threshold,
+            technique: tr(self.locale, &format!("technique.{}", rule_id), &[]),
+            description: tr(self.locale, &format!("explain.{}", rule_id), args),
+        });
+    }
+
+    // The " ("text")" suffix for decoded payloads, empty when nothing decoded as text
+    fn decoded(&self, text: &Option<String>) -> String {
+        text.as_ref()
+            .map(|text| {
+                tr(
+                    self.locale,
+                    "explain.decoded_text",
+                    &[("text", text.clone())],
+                )
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn explain_magic_bytes(magic: &MagicBytesReport, explanations: &mut Explanations) {
+    for file in magic.embedded_files.iter().filter(|f| f.offset > 0) {
+        explanations.push(
+            "magic.embedded_signature",
+            "magic_bytes",
+            Some(file.offset as f64),
+            Some(0.0),
+            &[
+                ("description", file.description.clone()),
+                ("offset", file.offset_hex.clone()),
+            ],
+        );
+    }
+
+    for finding in magic
+        .suspicious_findings
+        .iter()
+        .filter(|f| f.starts_with("Format mismatch"))
+    {
+        explanations.push(
+            "magic.format_mismatch",
+            "magic_bytes",
+            None,
+            None,
+            &[("text", finding.clone())],
+        );
+    }
+}
+
+fn explain_slack_space(slack: &SlackSpaceReport, explanations: &mut Explanations) {
+    if !slack.is_suspicious {
+        return;
+    }
+    for region in slack.regions.iter().filter(|r| r.length >= MIN_SLACK_BYTES) {
+        let detected = region
+            .detected_type
+            .as_ref()
+            .map(|mime| {
+                tr(
+                    explanations.locale,
+                    "explain.slack_space.detected_type",
+                    &[("mime", mime.clone())],
+                )
+            })
+            .unwrap_or_default();
+        explanations.push(
+            "slack_space.unaccounted_region",
+            "slack_space",
+            Some(region.length as f64),
+            Some(MIN_SLACK_BYTES as f64),
+            &[
+                ("length", region.length.to_string()),
+                ("offset", region.offset_hex.clone()),
+                ("container", slack.container.clone()),
+                ("region", region.description.clone()),
+                ("entropy", format!("{:.2}", region.entropy)),
+                ("detected", detected),
+            ],
+        );
+    }
+}
+
+fn explain_deflate_streams(deflate: &DeflateStreamsReport, explanations: &mut Explanations) {
+    for stream in &deflate.streams {
+        let truncated = if stream.complete {
+            String::new()
+        } else {
+            tr(explanations.locale, "explain.deflate.truncated", &[])
+        };
+        explanations.push(
+            "deflate.embedded_stream",
+            "deflate",
+            Some(stream.inflated_length as f64),
+            None,
+            &[
+                ("format", stream.format.clone()),
+                ("offset", stream.offset_hex.clone()),
+                ("compressed", stream.compressed_length.to_string()),
+                ("inflated", stream.inflated_length.to_string()),
+                ("classification", stream.classification.clone()),
+                ("truncated", truncated),
+            ],
+        );
+    }
+}
+
+fn explain_ole(ole: &OleReport, explanations: &mut Explanations) {
+    for entry in &ole.entries {
+        if let Some(reason) = &entry.unusual {
+            explanations.push(
+                "ole.unusual_entry",
+                "ole",
+                Some(entry.size_bytes as f64),
+                None,
+                &[
+                    ("path", entry.path.clone()),
+                    ("size", entry.size_bytes.to_string()),
+                    ("reason", reason.clone()),
+                ],
+            );
+        }
+    }
+    for module in &ole.macros {
+        explanations.push(
+            "ole.vba_macro",
+            "ole",
+            module.source_bytes.map(|bytes| bytes as f64),
+            None,
+            &[
+                ("module", module.module.clone()),
+                ("stream", module.stream.clone()),
+            ],
+        );
+        if !module.auto_exec.is_empty() {
+            explanations.push(
+                "ole.auto_exec",
+                "ole",
+                None,
+                None,
+                &[
+                    ("module", module.module.clone()),
+                    ("procedures", module.auto_exec.join(", ")),
+                ],
+            );
+        }
+    }
+    // Office documents embedded in this one are explained in turn
+    for object in &ole.embedded_objects {
+        if object.executable {
+            explanations.push(
+                "ole.embedded_executable",
+                "ole",
+                Some(object.size_bytes as f64),
+                None,
+                &[
+                    ("stream", object.stream.clone()),
+                    (
+                        "name",
+                        object.file_name.clone().unwrap_or_else(|| "-".to_string()),
+                    ),
+                    ("classification", object.classification.clone()),
+                ],
+            );
+        }
+        if let Some(nested) = &object.nested {
+            explain_ole(nested, explanations);
+        }
+    }
+}
+
+fn explain_ebook(ebook: &EbookReport, explanations: &mut Explanations) {
+    for member in ebook.extraneous() {
+        explanations.push(
+            "ebook.extraneous_member",
+            "ebook",
+            Some(member.size_bytes as f64),
+            None,
+            &[
+                ("path", member.path.clone()),
+                ("size", member.size_bytes.to_string()),
+                (
+                    "classification",
+                    member
+                        .classification
+                        .clone()
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+            ],
+        );
+    }
+    for anomaly in &ebook.anomalies {
+        explanations.push(
+            "ebook.packaging",
+            "ebook",
+            None,
+            None,
+            &[("anomaly", anomaly.clone())],
+        );
+    }
+    for chapter in ebook.chapters.iter().filter(|c| c.is_suspicious) {
+        if chapter.zero_width_chars > 0 {
+            explanations.push(
+                "ebook.zero_width",
+                "ebook",
+                Some(chapter.zero_width_chars as f64),
+                None,
+                &[
+                    ("path", chapter.path.clone()),
+                    ("count", chapter.zero_width_chars.to_string()),
+                ],
+            );
+        }
+        if chapter.whitespace_lines >= MIN_WHITESPACE_LINES {
+            explanations.push(
+                "ebook.whitespace",
+                "ebook",
+                Some(chapter.whitespace_lines as f64),
+                Some(MIN_WHITESPACE_LINES as f64),
+                &[
+                    ("path", chapter.path.clone()),
+                    ("lines", chapter.whitespace_lines.to_string()),
+                ],
+            );
+        }
+        if chapter.homoglyph_words.len() >= MIN_HOMOGLYPH_WORDS {
+            explanations.push(
+                "ebook.homoglyphs",
+                "ebook",
+                Some(chapter.homoglyph_words.len() as f64),
+                Some(MIN_HOMOGLYPH_WORDS as f64),
+                &[
+                    ("path", chapter.path.clone()),
+                    ("list", chapter.homoglyph_words.join(", ")),
+                ],
+            );
+        }
+        if chapter.hidden_elements > 0 {
+            explanations.push(
+                "ebook.hidden_elements",
+                "ebook",
+                Some(chapter.hidden_elements as f64),
+                None,
+                &[
+                    ("path", chapter.path.clone()),
+                    ("count", chapter.hidden_elements.to_string()),
+                ],
+            );
+        }
+    }
+}
+
+fn explain_model(model: &ModelReport, explanations: &mut Explanations) {
+    for region in &model.regions {
+        explanations.push(
+            "model.hidden_region",
+            "model",
+            Some(region.size_bytes as f64),
+            None,
+            &[
+                ("format", model.format.clone()),
+                ("location", region.location.clone()),
+                ("size", region.size_bytes.to_string()),
+                ("classification", region.classification.clone()),
+            ],
+        );
+    }
+    for anomaly in &model.anomalies {
+        explanations.push(
+            "model.structure",
+            "model",
+            None,
+            None,
+            &[("anomaly", anomaly.clone())],
+        );
+    }
+}
+
+fn explain_structured(structured: &StructuredTextReport, explanations: &mut Explanations) {
+    for finding in &structured.findings {
+        let (rule, threshold) = match finding.kind.as_str() {
+            "comment" => ("structured.comment", None),
+            "long value" => ("structured.long_value", Some(MAX_VALUE_CHARS as f64)),
+            "blob" => ("structured.blob", Some(MIN_BLOB_CHARS as f64)),
+            _ => ("structured.invisible_key", None),
+        };
+        explanations.push(
+            rule,
+            "structured",
+            Some(finding.length as f64),
+            threshold,
+            &[
+                ("format", structured.format.clone()),
+                ("location", finding.location.clone()),
+                ("length", finding.length.to_string()),
+                ("preview", finding.preview.clone()),
+            ],
+        );
+    }
+}
+
+fn explain_trailing_tags(trailing: &TrailingTagsReport, explanations: &mut Explanations) {
+    for tag in &trailing.tags {
+        for finding in &tag.findings {
+            explanations.push(
+                "trailing_tag.item",
+                "trailing_tags",
+                Some(tag.size_bytes as f64),
+                None,
+                &[
+                    ("kind", tag.kind.clone()),
+                    ("key", "tag".to_string()),
+                    ("text", finding.clone()),
+                ],
+            );
+        }
+        for item in &tag.items {
+            for finding in &item.findings {
+                explanations.push(
+                    "trailing_tag.item",
+                    "trailing_tags",
+                    Some(item.size_bytes as f64),
+                    None,
+                    &[
+                        ("kind", tag.kind.clone()),
+                        ("key", item.key.clone()),
+                        ("text", finding.clone()),
+                    ],
+                );
+            }
+        }
+    }
+}
+
+fn explain_mp4_tags(mp4_tags: &Mp4TagsReport, explanations: &mut Explanations) {
+    for finding in &mp4_tags.findings {
+        explanations.push(
+            "mp4_tag.item",
+            "mp4_tags",
+            Some(mp4_tags.size_bytes as f64),
+            None,
+            &[("key", "ilst".to_string()), ("text", finding.clone())],
+        );
+    }
+    for item in &mp4_tags.items {
+        for finding in &item.findings {
+            explanations.push(
+                "mp4_tag.item",
+                "mp4_tags",
+                Some(item.size_bytes as f64),
+                None,
+                &[("key", item.key.clone()), ("text", finding.clone())],
+            );
+        }
+    }
+}
+
+fn explain_source_code(source_code: &SourceCodeReport, explanations: &mut Explanations) {
+    for payload in &source_code.payloads {
+        explanations.push(
+            "source.comment_payload",
+            "source_code",
+            Some(payload.length as f64),
+            None,
+            &[
+                ("language", source_code.language.clone()),
+                ("line", payload.line.to_string()),
+                ("end_line", payload.end_line.to_string()),
+                ("preview", payload.preview.clone()),
+            ],
+        );
+    }
+    if source_code.abnormal_ratio {
+        explanations.push(
+            "source.comment_ratio",
+            "source_code",
+            Some(source_code.comment_ratio),
+            Some(COMMENT_RATIO_THRESHOLD),
+            &[
+                ("language", source_code.language.clone()),
+                (
+                    "percent",
+                    format!("{:.1}", source_code.comment_ratio * 100.0),
+                ),
+                ("comment_chars", source_code.comment_chars.to_string()),
+            ],
+        );
+    }
+}
+
+fn explain_lsb(lsb: &LsbReport, thresholds: &Thresholds, explanations: &mut Explanations) {
+    if let Some(baseline) = &lsb.baseline {
+        explain_lsb_baseline(baseline, explanations);
+    }
+
+    // Synthetic images are judged on LSB noise in flat runs, not the plane-wide statistics
+    if let Some(content) = &lsb.content
+        && content.class == "synthetic"
+    {
+        if content.flat_lsb_noise > thresholds.synthetic_lsb_noise {
+            explanations.push(
+                "lsb.flat_noise",
+                "lsb",
+                Some(content.flat_lsb_noise),
+                Some(thresholds.synthetic_lsb_noise),
+                &[
+                    ("noise", format!("{:.2}", content.flat_lsb_noise * 100.0)),
+                    (
+                        "threshold",
+                        format!("{:.2}", thresholds.synthetic_lsb_noise * 100.0),
+                    ),
+                    ("flat", format!("{:.1}", content.flat_ratio * 100.0)),
+                ],
+            );
+        }
+        return;
+    }
+    // The fixed thresholds only apply to formats without a baseline
+    if lsb.baseline.is_some() {
+        return;
+    }
+
+    let chi_square_threshold = thresholds.lsb_chi_square;
+    let entropy_threshold = thresholds.lsb_entropy;
+
+    for channel in &lsb.channels {
+        let name = channel.channel_name.to_lowercase();
+
+        if channel.chi_square_score > chi_square_threshold {
+            explanations.push(
+                "lsb.chi_square",
+                "lsb",
+                Some(channel.chi_square_score),
+                Some(chi_square_threshold),
+                &[
+                    ("score", format!("{:.1}", channel.chi_square_score)),
+                    ("threshold", chi_square_threshold.to_string()),
+                    ("channel", name.clone()),
+                ],
+            );
+        }
+
+        if channel.entropy_score > entropy_threshold {
+            explanations.push(
+                "lsb.entropy",
+                "lsb",
+                Some(channel.entropy_score),
+                Some(entropy_threshold),
+                &[
+                    ("score", format!("{:.4}", channel.entropy_score)),
+                    ("threshold", entropy_threshold.to_string()),
+                    ("channel", name),
+                ],
+            );
+        }
+    }
+}
+
+fn explain_lsb_baseline(baseline: &LsbBaselineReport, explanations: &mut Explanations) {
+    let kind = format!("{}/{}", baseline.format, baseline.content);
+    for channel in &baseline.channels {
+        let name = channel.channel_name.to_lowercase();
+
+        if channel.chi_square_z.abs() > baseline.sigma {
+            explanations.push(
+                "lsb.baseline_chi_square",
+                "lsb",
+                Some(channel.chi_square_z),
+                Some(baseline.sigma),
+                &[
+                    ("score", format!("{:.4}", channel.chi_square_per_pair)),
+                    ("z", format!("{:+.1}", channel.chi_square_z)),
+                    ("sigma", baseline.sigma.to_string()),
+                    ("kind", kind.clone()),
+                    ("channel", name.clone()),
+                ],
+            );
+        }
+
+        if channel.entropy_z.abs() > baseline.sigma {
+            explanations.push(
+                "lsb.baseline_entropy",
+                "lsb",
+                Some(channel.entropy_z),
+                Some(baseline.sigma),
+                &[
+                    ("z", format!("{:+.1}", channel.entropy_z)),
+                    ("sigma", baseline.sigma.to_string()),
+                    ("kind", kind.clone()),
+                    ("channel", name),
+                ],
+            );
+        }
+    }
+}
+
+fn explain_ws(ws: &WsReport, thresholds: &Thresholds, explanations: &mut Explanations) {
+    if ws.estimated_payload_rate > thresholds.ws_payload_rate {
+        explanations.push(
+            "ws.payload_rate",
+            "ws",
+            Some(ws.estimated_payload_rate),
+            Some(thresholds.ws_payload_rate),
+            &[
+                ("rate", format!("{:.3}", ws.estimated_payload_rate)),
+                ("threshold", thresholds.ws_payload_rate.to_string()),
+                (
+                    "changed",
+                    format!("{:.1}", ws.estimated_change_rate * 100.0),
+                ),
+            ],
+        );
+    }
+}
+
+fn explain_channel_correlation(
+    correlation: &ChannelCorrelationReport,
+    thresholds: &Thresholds,
+    explanations: &mut Explanations,
+) {
+    if correlation.blocks_evaluated > 0
+        && correlation.collapsed_ratio > thresholds.channel_correlation_collapsed_ratio
+    {
+        explanations.push(
+            "channel_correlation.collapsed_blocks",
+            "channel_correlation",
+            Some(correlation.collapsed_ratio),
+            Some(thresholds.channel_correlation_collapsed_ratio),
+            &[
+                ("collapsed", correlation.collapsed_block_count.to_string()),
+                ("blocks", correlation.blocks_evaluated.to_string()),
+                ("ratio", format!("{:.3}", correlation.collapsed_ratio)),
+                (
+                    "threshold",
+                    thresholds.channel_correlation_collapsed_ratio.to_string(),
+                ),
+            ],
+        );
+    }
+}
+
+fn explain_adaptive_lsb(
+    adaptive: &AdaptiveLsbReport,
+    thresholds: &Thresholds,
+    explanations: &mut Explanations,
+) {
+    if adaptive.is_suspicious && adaptive.pov_contrast > thresholds.adaptive_lsb_contrast {
+        explanations.push(
+            "adaptive_lsb.region_contrast",
+            "adaptive_lsb",
+            Some(adaptive.pov_contrast),
+            Some(thresholds.adaptive_lsb_contrast),
+            &[
+                ("contrast", format!("{:.3}", adaptive.pov_contrast)),
+                ("threshold", thresholds.adaptive_lsb_contrast.to_string()),
+                ("textured", format!("{:.3}", adaptive.textured.pov_score)),
+                ("smooth", format!("{:.3}", adaptive.smooth.pov_score)),
+            ],
+        );
+    }
+}
+
+fn explain_bpcs(bpcs: &BpcsReport, thresholds: &Thresholds, explanations: &mut Explanations) {
+    if bpcs.noise_planes >= thresholds.bpcs_noise_planes.max(1) {
+        let list = bpcs
+            .planes
+            .iter()
+            .filter(|plane| plane.noise_like)
+            .map(|plane| format!("{}{}", plane.channel, plane.plane))
+            .collect::<Vec<_>>()
+            .join(", ");
+        explanations.push(
+            "bpcs.noise_planes",
+            "bpcs",
+            Some(bpcs.noise_planes as f64),
+            Some(thresholds.bpcs_noise_planes as f64),
+            &[
+                ("planes", bpcs.noise_planes.to_string()),
+                ("list", list),
+                ("threshold", thresholds.bpcs_noise_planes.to_string()),
+            ],
+        );
+    }
+}
+
+fn explain_calibration(
+    calibration: &CalibrationReport,
+    thresholds: &Thresholds,
+    explanations: &mut Explanations,
+) {
+    if calibration.estimated_change_rate > thresholds.calibration_change_rate {
+        explanations.push(
+            "calibration.change_rate",
+            "calibration",
+            Some(calibration.estimated_change_rate),
+            Some(thresholds.calibration_change_rate),
+            &[
+                (
+                    "rate",
+                    format!("{:.1}", calibration.estimated_change_rate * 100.0),
+                ),
+                (
+                    "threshold",
+                    format!("{:.1}", thresholds.calibration_change_rate * 100.0),
+                ),
+                ("divergence", format!("{:.3}", calibration.divergence)),
+                ("blocks", calibration.blocks.to_string()),
+            ],
+        );
+    }
+}
+
+fn explain_pages(pages: &PagesReport, explanations: &mut Explanations) {
+    for page in pages.pages.iter().filter(|p| p.outlier) {
+        explanations.push(
+            "pages.outlier",
+            "pages",
+            page.outlier_score,
+            Some(OUTLIER_SCORE_THRESHOLD),
+            &[
+                ("format", pages.format.clone()),
+                ("label", page.label.clone()),
+                (
+                    "score",
+                    format!("{:.2}", page.outlier_score.unwrap_or_default()),
+                ),
+                ("threshold", OUTLIER_SCORE_THRESHOLD.to_string()),
+            ],
+        );
+    }
+    for page in pages.pages.iter().filter(|p| p.hidden) {
+        explanations.push(
+            "pages.hidden_item",
+            "pages",
+            None,
+            None,
+            &[("label", page.label.clone())],
+        );
+    }
+}
+
+fn explain_spectrogram(
+    spec: &SpectrogramReport,
+    thresholds: &Thresholds,
+    explanations: &mut Explanations,
+) {
+    let energy_threshold = thresholds.spectrogram_high_frequency_energy;
+
+    if spec.high_frequency_energy > energy_threshold {
+        explanations.push(
+            "spectrogram.high_frequency_energy",
+            "spectrogram",
+            Some(spec.high_frequency_energy),
+            Some(energy_threshold),
+            &[
+                (
+                    "cutoff",
+                    format!("{:.0}", HIGH_FREQUENCY_CUTOFF_HZ / 1000.0),
+                ),
+                ("energy", format!("{:.4}", spec.high_frequency_energy)),
+                ("threshold", energy_threshold.to_string()),
+            ],
+        );
+    }
+
+    for pattern in &spec.suspicious_patterns {
+        explanations.push(
+            "spectrogram.pattern",
+            "spectrogram",
+            None,
+            None,
+            &[("text", pattern.clone())],
+        );
+    }
+}
+
+fn explain_ultrasonic(
+    ultrasonic: &UltrasonicReport,
+    thresholds: &Thresholds,
+    explanations: &mut Explanations,
+) {
+    let energy_threshold = thresholds.ultrasonic_energy;
+
+    if ultrasonic.band_energy_ratio > energy_threshold {
+        explanations.push(
+            "ultrasonic.band_energy",
+            "ultrasonic",
+            Some(ultrasonic.band_energy_ratio),
+            Some(energy_threshold),
+            &[
+                ("low", format!("{:.0}", ultrasonic.band_low_hz)),
+                ("high", format!("{:.0}", ultrasonic.band_high_hz)),
+                ("ratio", format!("{:.4}", ultrasonic.band_energy_ratio)),
+                ("threshold", energy_threshold.to_string()),
+            ],
+        );
+    }
+
+    if let Some(ref fsk) = ultrasonic.fsk {
+        let decoded = explanations.decoded(&fsk.decoded_text);
+        explanations.push(
+            "ultrasonic.fsk",
+            "ultrasonic",
+            Some(fsk.baud as f64),
+            None,
+            &[
+                ("mark", format!("{:.0}", fsk.mark_hz)),
+                ("space", format!("{:.0}", fsk.space_hz)),
+                ("baud", format!("{:.1}", fsk.baud)),
+                ("bits", fsk.bit_count.to_string()),
+                ("decoded", decoded),
+            ],
+        );
+    }
+}
+
+fn explain_phase(phase: &PhaseReport, thresholds: &Thresholds, explanations: &mut Explanations) {
+    let discontinuity_threshold = thresholds.phase_discontinuity;
+
+    if phase.quantized_run >= MIN_QUANTIZED_RUN {
+        let decoded = explanations.decoded(&phase.decoded_text);
+        explanations.push(
+            "phase.quantized",
+            "phase",
+            Some(phase.quantized_run as f64),
+            Some(MIN_QUANTIZED_RUN as f64),
+            &[
+                ("run", phase.quantized_run.to_string()),
+                ("first_bin", phase.quantized_first_bin.to_string()),
+                ("segment", phase.segment_length.to_string()),
+                ("decoded", decoded),
+            ],
+        );
+    }
+
+    if phase.boundary_jump_ratio > discontinuity_threshold {
+        explanations.push(
+            "phase.discontinuity",
+            "phase",
+            Some(phase.boundary_jump_ratio),
+            Some(discontinuity_threshold),
+            &[
+                ("segment", phase.segment_length.to_string()),
+                ("ratio", format!("{:.1}", phase.boundary_jump_ratio)),
+                ("threshold", discontinuity_threshold.to_string()),
+            ],
+        );
+    }
+}
+
+fn explain_differential(differential: &DifferentialReport, explanations: &mut Explanations) {
+    if !differential.is_suspicious {
+        return;
+    }
+
+    for (index, region) in differential.regions.iter().enumerate() {
+        // The decoded LSBs are read from the start of the first region
+        let decoded = if index == 0 {
+            explanations.decoded(&differential.lsb_decoded_text)
+        } else {
+            String::new()
+        };
+        explanations.push(
+            "differential.region",
+            "differential",
+            Some(region.changed_samples as f64),
+            None,
+            &[
+                ("changed", region.changed_samples.to_string()),
+                ("start", format!("{:.2}", region.start_seconds)),
+                ("end", format!("{:.2}", region.end_seconds)),
+                ("reference", differential.reference_file.clone()),
+                ("lsb", format!("{:.0}", differential.lsb_only_share * 100.0)),
+                ("db", format!("{:.1}", -differential.residual_db)),
+                ("flatness", format!("{:.2}", differential.residual_flatness)),
+                ("decoded", decoded),
+            ],
+        );
+    }
+
+    if let (Some(bits), Some(rate)) = (
+        differential.estimated_bits,
+        differential.estimated_bitrate_bps,
+    ) {
+        let seconds: f64 = differential
+            .regions
+            .iter()
+            .map(|r| r.end_seconds - r.start_seconds)
+            .sum();
+        explanations.push(
+            "differential.bitrate",
+            "differential",
+            Some(rate),
+            None,
+            &[
+                ("bits", bits.to_string()),
+                ("seconds", format!("{:.2}", seconds)),
+                ("rate", format!("{:.0}", rate)),
+            ],
+        );
+    }
+}
+
+fn explain_demodulation(demodulation: &DemodulationReport, explanations: &mut Explanations) {
+    for carrier in &demodulation.carriers {
+        if let Some(ref fsk) = carrier.fsk {
+            let decoded = explanations.decoded(&fsk.decoded_text);
+            explanations.push(
+                "demodulation.fsk",
+                "demodulation",
+                Some(fsk.baud as f64),
+                None,
+                &[
+                    ("mark", format!("{:.0}", fsk.mark_hz)),
+                    ("space", format!("{:.0}", fsk.space_hz)),
+                    ("baud", format!("{:.1}", fsk.baud)),
+                    ("bits", fsk.bit_count.to_string()),
+                    ("decoded", decoded),
+                ],
+            );
+        }
+        if let Some(ref psk) = carrier.psk {
+            let decoded = explanations.decoded(&psk.decoded_text);
+            explanations.push(
+                "demodulation.psk",
+                "demodulation",
+                Some(psk.constellation as f64),
+                None,
+                &[
+                    ("carrier", format!("{:.0}", carrier.carrier_hz)),
+                    ("baud", format!("{:.1}", psk.baud)),
+                    ("bits", psk.bit_count.to_string()),
+                    ("decoded", decoded),
+                ],
+            );
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_lsb_explanation_includes_value_and_threshold() {
+        let path = PathBuf::from("/test/file.png");
+        let mut report = SteganalysisReport::new(&path, 1024, "Image".to_string());
+        report.set_format_analysis(FormatSpecificAnalysis::Image(Box::new(ImageAnalysis {
+            exif_metadata: None,
+            missing_metadata: None,
+            lsb_analysis: Some(LsbReport {
+                is_suspicious: true,
+                bit_depth: 8,
+                color_model: "RGB".to_string(),
+                channels: vec![LsbChannelAnalysis {
+                    channel_name: "Green".to_string(),
+                    chi_square_score: 412.7,
+                    entropy_score: 0.5,
+                }],
+                content: None,
+                baseline: None,
+                output_files: Vec::new(),
+            }),
+            ws_analysis: None,
+            channel_correlation: None,
+            adaptive_lsb: None,
+            bpcs: None,
+            calibration: None,
+            webp_analysis: None,
+            gif_analysis: None,
+            png_text: None,
+            page_analysis: None,
+            filter_analysis: None,
+            dimensions: ImageDimensions {
+                width: 64,
+                height: 64,
+            },
+        })));
+
+        let explanations = explain_report(&report, &Thresholds::default(), Locale::En);
+        assert_eq!(explanations.len(), 1);
+        assert_eq!(explanations[0].rule_id, "lsb.chi_square");
+        assert!(
+            explanations[0]
+                .description
+                .starts_with("chi-square 412.7 > threshold 100 for green channel")
+        );
+
+        // Only the text changes with the locale
+        let spanish = explain_report(&report, &Thresholds::default(), Locale::Es);
+        assert_eq!(spanish[0].rule_id, "lsb.chi_square");
+        assert!(
+            spanish[0]
+                .description
+                .starts_with("chi-cuadrado 412.7 > umbral 100 en el canal green")
+        );
+    }
+
+    #[test]
+    fn test_synthetic_lsb_explained_by_flat_noise() {
+        let lsb = LsbReport {
+            is_suspicious: true,
+            bit_depth: 8,
+            color_model: "RGB".to_string(),
+            // Flat screenshot regions fail the chi-square test without any payload
+            channels: vec![LsbChannelAnalysis {
+                channel_name: "Red".to_string(),
+                chi_square_score: 5000.0,
+                entropy_score: 0.2,
+            }],
+            content: Some(ContentClassReport {
+                class: "synthetic".to_string(),
+                flat_ratio: 0.82,
+                unique_colors: 40,
+                flat_lsb_noise: 0.31,
+            }),
+            baseline: None,
+            output_files: Vec::new(),
+        };
+        let mut explanations = Explanations {
+            locale: Locale::En,
+            list: Vec::new(),
+        };
+        explain_lsb(&lsb, &Thresholds::default(), &mut explanations);
+
+        assert_eq!(explanations.list.len(), 1);
+        assert_eq!(explanations.list[0].rule_id, "lsb.flat_noise");
+        assert!(
+            explanations.list[0]
+                .description
+                .starts_with("synthetic image (82.0% flat neighbours) with 31.00% LSB noise")
+        );
+    }
+
+    #[test]
+    fn test_baseline_deviation_replaces_fixed_thresholds() {
+        // Above the fixed chi-square threshold, but ordinary for a JPEG photo of this size
+        let lsb = LsbReport {
+            is_suspicious: true,
+            bit_depth: 8,
+            color_model: "RGB".to_string(),
+            channels: vec![LsbChannelAnalysis {
+                channel_name: "Blue".to_string(),
+                chi_square_score: 900.0,
+                entropy_score: 0.97,
+            }],
+            content: None,
+            baseline: Some(LsbBaselineReport {
+                format: "jpeg".to_string(),
+                content: "photo".to_string(),
+                sigma: 3.0,
+                channels: vec![LsbChannelDeviation {
+                    channel_name: "Blue".to_string(),
+                    chi_square_per_pair: 0.0009,
+                    chi_square_z: -0.5,
+                    entropy_z: -14.3,
+                }],
+            }),
+            output_files: Vec::new(),
+        };
+        let mut explanations = Explanations {
+            locale: Locale::En,
+            list: Vec::new(),
+        };
+        explain_lsb(&lsb, &Thresholds::default(), &mut explanations);
+
+        assert_eq!(explanations.list.len(), 1);
+        assert_eq!(explanations.list[0].rule_id, "lsb.baseline_entropy");
+        assert_eq!(explanations.list[0].measured_value, Some(-14.3));
+        assert!(
+            explanations.list[0].description.starts_with(
+                "LSB entropy is -14.3 standard deviations from clean jpeg/photo covers"
+            )
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::config::Thresholds;
+use crate::explain;
+use crate::json_report::{AnnotationLabel, SteganalysisReport};
+use crate::walker;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// `stegascan feedback export`: the findings analysts labelled with `stegascan annotate`, one
+// row per finding, with what the analyzer measured and the threshold it was held to. Threshold
+// tuning or a classifier reads these as training data: true positives are detections to keep,
+// false positives the ones a better threshold would have dropped. A finding labelled more than
+// once counts with its latest label; comments alone don't label anything.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FeedbackFormat {
+    // One JSON object per line
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledFinding {
+    // The report the label was read from
+    pub report: String,
+    pub file: String,
+    pub detected_type: String,
+    pub scanned: String,
+    pub rule_id: String,
+    pub analyzer: String,
+    pub measured_value: Option<f64>,
+    pub threshold: Option<f64>,
+    pub label: AnnotationLabel,
+    pub comment: Option<String>,
+    pub analyst: Option<String>,
+    pub labelled: String,
+}
+
+const CSV_HEADER: &str = "report,file,detected_type,scanned,rule_id,analyzer,measured_value,threshold,label,comment,analyst,labelled";
+
+// Labelled findings of one report. Measurements come from the explanations, worked out again
+// from the stored sections in case the scan ran without --explain.
+pub fn labeled_findings(
+    report_path: &Path,
+    report: &SteganalysisReport,
+    thresholds: &Thresholds,
+) -> Vec<LabeledFinding> {
+    let mut explanations = explain::explain_report(report, thresholds, report.locale);
+    explanations.extend(report.explanations.iter().cloned());
+
+    let mut rules: Vec<&str> = report
+        .annotations
+        .iter()
+        .filter_map(|annotation| annotation.rule.as_deref())
+        .collect();
+    rules.sort_unstable();
+    rules.dedup();
+
+    rules
+        .into_iter()
+        .filter_map(|rule| {
+            let annotation = report.annotations.iter().rev().find(|annotation| {
+                annotation.rule.as_deref() == Some(rule)
+                    && annotation.label != AnnotationLabel::Comment
+            })?;
+            let explanation = explanations.iter().find(|e| e.rule_id == rule);
+            Some(LabeledFinding {
+                report: report_path.display().to_string(),
+                file: report.file_info.path.clone(),
+                detected_type: report.file_info.detected_type.clone(),
+                scanned: report.timestamp.clone(),
+                rule_id: rule.to_string(),
+                analyzer: explanation.map(|e| e.analyzer.clone()).unwrap_or_default(),
+                measured_value: explanation.and_then(|e| e.measured_value),
+                threshold: explanation.and_then(|e| e.threshold),
+                label: annotation.label,
+                comment: annotation.comment.clone(),
+                analyst: annotation.analyst.clone(),
+                labelled: annotation.added.clone(),
+            })
+        })
+        .collect()
+}
+
+// Reports given directly, and every report found below given directories, such as case
+// directories. JSON files that aren't scan reports are skipped.
+pub fn collect(
+    paths: &[PathBuf],
+    thresholds: &Thresholds,
+) -> Result<(Vec<LabeledFinding>, usize), Box<dyn std::error::Error>> {
+    let mut findings = Vec::new();
+    let mut reports = 0;
+    for path in paths {
+        let files = if path.is_dir() {
+            walker::walk(path, &["*.json".to_string()], &[], None)?
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            let report = match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|json| {
+                    serde_json::from_str::<SteganalysisReport>(&json).map_err(|e| e.to_string())
+                }) {
+                Ok(report) => report,
+                Err(e) if path.is_dir() => {
+                    log::debug!("Skipping {}: {}", file.display(), e);
+                    continue;
+                }
+                Err(e) => return Err(format!("{}: {}", file.display(), e).into()),
+            };
+            reports += 1;
+            findings.extend(labeled_findings(&file, &report, thresholds));
+        }
+    }
+    Ok((findings, reports))
+}
+
+pub fn write(
+    findings: &[LabeledFinding],
+    format: FeedbackFormat,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    match format {
+        FeedbackFormat::Jsonl => {
+            for finding in findings {
+                writeln!(out, "{}", serde_json::to_string(finding)?)?;
+            }
+        }
+        FeedbackFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for finding in findings {
+                let fields = [
+                    finding.report.clone(),
+                    finding.file.clone(),
+                    finding.detected_type.clone(),
+                    finding.scanned.clone(),
+                    finding.rule_id.clone(),
+                    finding.analyzer.clone(),
+                    finding
+                        .measured_value
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                    finding.threshold.map(|v| v.to_string()).unwrap_or_default(),
+                    serde_json::to_value(finding.label)?
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    finding.comment.clone().unwrap_or_default(),
+                    finding.analyst.clone().unwrap_or_default(),
+                    finding.labelled.clone(),
+                ];
+                let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Quoted only when it has to be, doubling any quotes inside
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::Annotation;
+
+    fn annotation(rule: &str, label: AnnotationLabel, comment: Option<&str>) -> Annotation {
+        Annotation {
+            rule: Some(rule.to_string()),
+            label,
+            comment: comment.map(str::to_string),
+            analyst: None,
+            added: "2026-03-01T00:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_latest_label_per_rule() {
+        let mut report =
+            SteganalysisReport::new(&PathBuf::from("photo.png"), 10, "Image".to_string());
+        report.annotations = vec![
+            annotation(
+                "slack_space.unaccounted_region",
+                AnnotationLabel::TruePositive,
+                None,
+            ),
+            annotation(
+                "slack_space.unaccounted_region",
+                AnnotationLabel::FalsePositive,
+                Some("Padding, \"normal\" for this camera"),
+            ),
+            annotation(
+                "lsb.chi_square",
+                AnnotationLabel::Comment,
+                Some("Look again"),
+            ),
+        ];
+
+        let findings = labeled_findings(Path::new("report.json"), &report, &Thresholds::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].label, AnnotationLabel::FalsePositive);
+
+        let mut csv = Vec::new();
+        write(&findings, FeedbackFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(CSV_HEADER));
+        assert!(csv.contains(",false_positive,\"Padding, \"\"normal\"\" for this camera\","));
+
+        let mut jsonl = Vec::new();
+        write(&findings, FeedbackFormat::Jsonl, &mut jsonl).unwrap();
+        let parsed: LabeledFinding =
+            serde_json::from_str(String::from_utf8(jsonl).unwrap().trim()).unwrap();
+        assert_eq!(parsed.rule_id, "slack_space.unaccounted_region");
+    }
+}
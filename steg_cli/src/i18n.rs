@@ -0,0 +1,1230 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Language of the human-readable report text: threat indicators, recommendations,
+// explanations and the closing summary. Rule IDs, analyzer names and JSON keys never change
+// with the locale, so tooling can match on them whatever language the text is in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    // Region suffixes are accepted and ignored, so "es-MX" and "es_ES.UTF-8" both read as "es"
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let language = value
+            .trim()
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            _ => Err(format!("unknown locale {:?} (expected en or es)", value)),
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::En => write!(f, "en"),
+            Locale::Es => write!(f, "es"),
+        }
+    }
+}
+
+impl Locale {
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => EN,
+            Locale::Es => ES,
+        }
+    }
+}
+
+// The message `id` in `locale`, with each {name} replaced by its argument. Messages missing
+// from a catalog fall back to English, and unknown IDs to the ID itself.
+pub fn tr(locale: Locale, id: &str, args: &[(&str, String)]) -> String {
+    let lookup = |catalog: &'static [(&'static str, &'static str)]| {
+        catalog
+            .iter()
+            .find(|(key, _)| *key == id)
+            .map(|(_, text)| *text)
+    };
+    let mut text = lookup(locale.catalog())
+        .or_else(|| lookup(EN))
+        .unwrap_or(id)
+        .to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+const EN: &[(&str, &str)] = &[
+    // Closing summary
+    ("cli.summary_title", "ANALYSIS SUMMARY"),
+    (
+        "cli.steganography_detected",
+        "Steganography detected: {value}",
+    ),
+    ("cli.confidence_level", "Confidence level: {value}"),
+    ("cli.threat_indicators", "Threat indicators:"),
+    ("cli.extracted_payloads", "Extracted payloads:"),
+    ("cli.explanations", "Why these findings fired:"),
+    ("cli.recommendations", "Recommendations:"),
+    ("cli.report_saved", "JSON report saved to: {path}"),
+    ("cli.true", "true"),
+    ("cli.false", "false"),
+    ("confidence.low", "low"),
+    ("confidence.medium", "medium"),
+    ("confidence.high", "high"),
+    ("confidence.confirmed", "confirmed, payload extracted"),
+    // Threat indicators
+    (
+        "indicator.suspicious_structure",
+        "Suspicious data found in file structure",
+    ),
+    (
+        "indicator.multiple_formats",
+        "Multiple file formats detected",
+    ),
+    (
+        "indicator.slack_space",
+        "{bytes} bytes of {container} slack space not accounted for by the container structure",
+    ),
+    (
+        "indicator.deflate_streams",
+        "{count} compressed stream(s) found outside the container's own compressed data",
+    ),
+    (
+        "indicator.ole_unusual",
+        "{count} OLE2 storage(s) or stream(s) a document wouldn't have: {list}",
+    ),
+    ("indicator.ole_macros", "VBA macros in {count} module(s)"),
+    (
+        "indicator.ole_auto_exec",
+        "Macros that run when the document is opened or closed: {list}",
+    ),
+    (
+        "indicator.ole_executables",
+        "{count} embedded object(s) are executables: {list}",
+    ),
+    (
+        "indicator.ebook_extraneous",
+        "{count} ebook member(s) the package doesn't list: {list}",
+    ),
+    ("indicator.ebook_anomaly", "Ebook packaging: {anomaly}"),
+    (
+        "indicator.ebook_chapters",
+        "Text steganography traces in {count} chapter(s)",
+    ),
+    (
+        "indicator.model_regions",
+        "{count} region(s) of the {format} model no loader reads: {list}",
+    ),
+    ("indicator.model_anomaly", "{format} structure: {anomaly}"),
+    (
+        "indicator.structured_findings",
+        "{count} place(s) in the {format} structure carry data: {list}",
+    ),
+    (
+        "indicator.source_comments",
+        "{count} {language} comment(s) decode to data, at lines {list}",
+    ),
+    (
+        "indicator.source_comment_ratio",
+        "{percent}% of the source file is comment",
+    ),
+    (
+        "indicator.ole_objects",
+        "Suspicious data inside {count} embedded OLE object(s)",
+    ),
+    (
+        "indicator.plugin_flagged",
+        "Plugin {plugin} flagged the file",
+    ),
+    ("indicator.plugin_finding", "Plugin {plugin}: {finding}"),
+    (
+        "indicator.lsb",
+        "LSB analysis indicates possible hidden data",
+    ),
+    (
+        "indicator.ws",
+        "Pair analysis estimates {rate}% of LSBs were changed",
+    ),
+    (
+        "indicator.channel_correlation",
+        "Inter-channel LSB correlation collapsed in {collapsed} of {blocks} smooth blocks",
+    ),
+    (
+        "indicator.webp_frames",
+        "{suspicious} of {frames} WebP frames show LSB or histogram anomalies",
+    ),
+    (
+        "indicator.page_outliers",
+        "{outliers} of {pages} {format} pages differ statistically from the rest: {list}",
+    ),
+    (
+        "indicator.hidden_pages",
+        "{hidden} {format} image items are stored but never displayed: {list}",
+    ),
+    (
+        "indicator.adaptive_lsb",
+        "Textured regions show random LSBs while smooth regions do not",
+    ),
+    (
+        "indicator.bpcs",
+        "{planes} bit planes have complex blocks that look like random data (BPCS embedding)",
+    ),
+    (
+        "indicator.calibration",
+        "About {rate}% of the JPEG's DCT coefficients differ from a calibrated reference (F5-style embedding)",
+    ),
+    ("indicator.exif", "Suspicious EXIF metadata found"),
+    (
+        "indicator.missing_metadata",
+        "Looks like a {source} but has no {fields} metadata (possibly stripped)",
+    ),
+    (
+        "indicator.spectrogram",
+        "Spectrogram analysis detected hidden patterns",
+    ),
+    (
+        "indicator.ultrasonic_fsk",
+        "Ultrasonic FSK signal at {mark}/{space} Hz carrying {bits} bits",
+    ),
+    (
+        "indicator.ultrasonic_energy",
+        "{share}% of the audio energy lies above {cutoff} Hz",
+    ),
+    (
+        "indicator.phase",
+        "Phase coding signs in {segment}-sample segments: {run} quantized bins, boundary jumps {ratio}x",
+    ),
+    (
+        "indicator.demodulated",
+        "{scheme} data demodulated from the {carrier} Hz tone: {bits} bits",
+    ),
+    ("indicator.id3", "Suspicious ID3 metadata found"),
+    (
+        "indicator.id3_pictures",
+        "{count} embedded ID3 picture(s) show signs of hidden data",
+    ),
+    (
+        "indicator.id3_tag_gaps",
+        "{bytes} bytes hidden between the ID3v2 tag(s) and the audio",
+    ),
+    (
+        "indicator.trailing_tags",
+        "Flagged APEv2/Lyrics3 tag content: {list}",
+    ),
+    (
+        "indicator.mp4_tags",
+        "Flagged MP4 metadata (ilst) items: {list}",
+    ),
+    (
+        "indicator.differential",
+        "Content added to the reference {reference}: {changed} samples changed in {regions} region(s)",
+    ),
+    (
+        "indicator.video_scene",
+        "Frames {list} of the scene at frames {start}-{end} have LSB statistics {ratio}x off the rest of the scene",
+    ),
+    // Recommendations
+    (
+        "recommendation.investigate",
+        "Further investigation recommended",
+    ),
+    (
+        "recommendation.specialized_tools",
+        "Consider using specialized steganography tools",
+    ),
+    (
+        "recommendation.verify_source",
+        "Verify file source and integrity",
+    ),
+    (
+        "recommendation.none_detected",
+        "No obvious steganography detected",
+    ),
+    ("recommendation.clean", "File appears to be clean"),
+    (
+        "recommendation.rescan_modified",
+        "The file changed while it was scanned; scan it again once it is complete",
+    ),
+    // Explanations, keyed by rule ID
+    ("explain.decoded_text", " (\"{text}\")"),
+    ("technique.plugin.finding", "external plugin"),
+    ("explain.plugin.finding", "{text}"),
+    (
+        "technique.exif.suspicious_field",
+        "metadata field size and base64 heuristics",
+    ),
+    ("explain.exif.suspicious_field", "{text}"),
+    (
+        "technique.exif.missing_metadata",
+        "metadata expected of the detected source",
+    ),
+    (
+        "explain.exif.missing_metadata",
+        "{fields} missing from what looks like a {source}",
+    ),
+    (
+        "technique.id3.suspicious_frame",
+        "ID3 frame size, tag layout and base64 heuristics",
+    ),
+    ("explain.id3.suspicious_frame", "{text}"),
+    (
+        "technique.trailing_tag.item",
+        "APEv2/Lyrics3 item size and content checks",
+    ),
+    ("explain.trailing_tag.item", "{kind} {key}: {text}"),
+    (
+        "technique.mp4_tag.item",
+        "MP4 ilst item size and content checks",
+    ),
+    ("explain.mp4_tag.item", "{key}: {text}"),
+    (
+        "technique.id3.embedded_picture",
+        "image analysis of extracted APIC frame",
+    ),
+    (
+        "explain.id3.embedded_picture",
+        "{picture_type} picture ({mime}) flagged by the image analyzers",
+    ),
+    ("technique.magic.embedded_signature", "file signature scan"),
+    (
+        "explain.magic.embedded_signature",
+        "{description} signature at offset {offset} > 0, data exists past the start of the primary file",
+    ),
+    (
+        "technique.magic.format_mismatch",
+        "extension vs. detected signature comparison",
+    ),
+    ("explain.magic.format_mismatch", "{text}"),
+    (
+        "technique.slack_space.unaccounted_region",
+        "declared length vs. bytes consumed",
+    ),
+    (
+        "explain.slack_space.unaccounted_region",
+        "{length} bytes at {offset} not covered by the {container} structure ({region}), entropy {entropy} bits/byte{detected}",
+    ),
+    ("explain.slack_space.detected_type", ", starts like {mime}"),
+    (
+        "technique.deflate.embedded_stream",
+        "bounded inflation at every offset",
+    ),
+    (
+        "explain.deflate.embedded_stream",
+        "{format} stream at {offset}: {compressed} bytes inflate to {inflated} bytes of {classification}{truncated}",
+    ),
+    ("explain.deflate.truncated", ", cut off before its end"),
+    (
+        "technique.ole.unusual_entry",
+        "compound file directory vs. the document format's streams",
+    ),
+    (
+        "explain.ole.unusual_entry",
+        "{path} ({size} bytes): {reason}",
+    ),
+    ("technique.ole.vba_macro", "VBA project dir stream parsing"),
+    (
+        "explain.ole.vba_macro",
+        "VBA module {module} stored in {stream}",
+    ),
+    ("technique.ole.auto_exec", "VBA procedure declarations"),
+    (
+        "technique.ole.embedded_executable",
+        "file signature of the embedded object",
+    ),
+    (
+        "explain.ole.embedded_executable",
+        "object {stream} (packaged as {name}) is a program: {classification}",
+    ),
+    (
+        "technique.ebook.extraneous_member",
+        "package manifest vs. the archive's members",
+    ),
+    (
+        "explain.ebook.extraneous_member",
+        "{path} ({size} bytes, {classification}) is in the package but nothing lists it",
+    ),
+    ("technique.ebook.packaging", "EPUB/CHM container rules"),
+    ("explain.ebook.packaging", "{anomaly}"),
+    (
+        "technique.ebook.zero_width",
+        "zero-width character decoding",
+    ),
+    (
+        "explain.ebook.zero_width",
+        "{count} zero-width characters in {path}",
+    ),
+    ("technique.ebook.whitespace", "trailing whitespace (SNOW)"),
+    (
+        "explain.ebook.whitespace",
+        "{lines} lines of {path} end in mixed spaces and tabs",
+    ),
+    ("technique.ebook.homoglyphs", "mixed-script words"),
+    (
+        "explain.ebook.homoglyphs",
+        "Cyrillic or Greek lookalike letters in {path}: {list}",
+    ),
+    ("technique.ebook.hidden_elements", "markup never displayed"),
+    (
+        "explain.ebook.hidden_elements",
+        "{count} hidden elements in {path}",
+    ),
+    (
+        "technique.model.hidden_region",
+        "model data outside buffer views and records",
+    ),
+    (
+        "explain.model.hidden_region",
+        "{format} {location}: {size} bytes ({classification}) the model never uses",
+    ),
+    ("technique.model.structure", "glTF/GLB/STL layout rules"),
+    ("explain.model.structure", "{anomaly}"),
+    ("technique.structured.comment", "encoded data in comments"),
+    (
+        "explain.structured.comment",
+        "{format} comment at {location} decodes to {preview}",
+    ),
+    ("technique.structured.long_value", "oversized values"),
+    (
+        "explain.structured.long_value",
+        "{format} value {location} is {length} characters long",
+    ),
+    (
+        "technique.structured.blob",
+        "base64 under keys not meant for binary data",
+    ),
+    (
+        "explain.structured.blob",
+        "{format} value {location} ({length} characters) decodes to {preview}",
+    ),
+    (
+        "technique.structured.invisible_key",
+        "invisible and lookalike characters in keys",
+    ),
+    (
+        "explain.structured.invisible_key",
+        "{format} key {location} reads as {preview}",
+    ),
+    (
+        "technique.source.comment_payload",
+        "encoded data in source comments",
+    ),
+    (
+        "explain.source.comment_payload",
+        "{language} comment at lines {line}-{end_line} decodes to {preview}",
+    ),
+    ("technique.source.comment_ratio", "comment-to-code ratio"),
+    (
+        "explain.source.comment_ratio",
+        "{percent}% of the {language} file's non-blank characters ({comment_chars}) are in comments",
+    ),
+    (
+        "explain.ole.auto_exec",
+        "VBA module {module} defines {procedures}, run by Office without the user asking",
+    ),
+    ("technique.lsb.chi_square", "pairs-of-values test"),
+    (
+        "explain.lsb.chi_square",
+        "chi-square {score} > threshold {threshold} for {channel} channel, pairs-of-values test",
+    ),
+    ("technique.lsb.entropy", "Shannon entropy of the LSB plane"),
+    (
+        "explain.lsb.entropy",
+        "LSB entropy {score} > threshold {threshold} for {channel} channel, Shannon entropy of the LSB plane",
+    ),
+    (
+        "technique.lsb.baseline_chi_square",
+        "pairs-of-values test against clean covers of the same format",
+    ),
+    (
+        "explain.lsb.baseline_chi_square",
+        "chi-square {score} per pair is {z} standard deviations from clean {kind} covers (threshold {sigma}) for {channel} channel",
+    ),
+    (
+        "technique.lsb.baseline_entropy",
+        "LSB entropy against clean covers of the same format",
+    ),
+    (
+        "explain.lsb.baseline_entropy",
+        "LSB entropy is {z} standard deviations from clean {kind} covers (threshold {sigma}) for {channel} channel",
+    ),
+    (
+        "technique.lsb.flat_noise",
+        "LSB disagreement in the flat regions of a synthetic image",
+    ),
+    (
+        "explain.lsb.flat_noise",
+        "synthetic image ({flat}% flat neighbours) with {noise}% LSB noise in flat runs > threshold {threshold}%, where a screenshot has none",
+    ),
+    (
+        "technique.ws.payload_rate",
+        "sample pair and weighted-stego analysis",
+    ),
+    (
+        "explain.ws.payload_rate",
+        "estimated payload {rate} bpp > threshold {threshold} (~{changed}% of LSBs changed), sample pair and weighted-stego analysis",
+    ),
+    (
+        "technique.channel_correlation.collapsed_blocks",
+        "inter-channel LSB correlation",
+    ),
+    (
+        "explain.channel_correlation.collapsed_blocks",
+        "{collapsed} of {blocks} smooth blocks lost LSB correlation across R/G/B (ratio {ratio} > threshold {threshold}), inter-channel LSB correlation",
+    ),
+    (
+        "technique.adaptive_lsb.region_contrast",
+        "content-adaptive pairs-of-values comparison",
+    ),
+    (
+        "explain.adaptive_lsb.region_contrast",
+        "smooth/textured pairs-of-values ratio {contrast} > threshold {threshold} (textured score {textured}, smooth {smooth}), content-adaptive pairs-of-values comparison",
+    ),
+    (
+        "technique.bpcs.noise_planes",
+        "bit-plane complexity segmentation",
+    ),
+    (
+        "explain.bpcs.noise_planes",
+        "{planes} structured bit planes ({list}) have no complex blocks near the complexity threshold, only random-looking ones (threshold {threshold} planes), bit-plane complexity segmentation",
+    ),
+    (
+        "technique.calibration.change_rate",
+        "calibration against a cropped, requantized reference",
+    ),
+    (
+        "explain.calibration.change_rate",
+        "estimated change rate {rate}% > threshold {threshold}% (histogram divergence {divergence} over {blocks} blocks), calibration against a cropped, requantized reference",
+    ),
+    (
+        "technique.pages.outlier",
+        "median/MAD comparison across pages",
+    ),
+    (
+        "explain.pages.outlier",
+        "{format} {label} robust z-score {score} > threshold {threshold} on LSB entropy, chi-square or edge density, median/MAD comparison across pages",
+    ),
+    ("technique.pages.hidden_item", "HEIF item reference graph"),
+    (
+        "explain.pages.hidden_item",
+        "{label} is neither the primary image nor referenced as a thumbnail, auxiliary image or tile",
+    ),
+    (
+        "technique.spectrogram.high_frequency_energy",
+        "STFT energy distribution",
+    ),
+    (
+        "explain.spectrogram.high_frequency_energy",
+        "energy ratio above {cutoff} kHz {energy} > threshold {threshold}, STFT energy distribution",
+    ),
+    (
+        "technique.spectrogram.pattern",
+        "spectrogram tone, edge and energy-spike heuristics",
+    ),
+    ("explain.spectrogram.pattern", "{text}"),
+    (
+        "technique.ultrasonic.band_energy",
+        "band-limited STFT energy",
+    ),
+    (
+        "explain.ultrasonic.band_energy",
+        "energy ratio in {low}-{high} Hz {ratio} > threshold {threshold}",
+    ),
+    (
+        "technique.ultrasonic.fsk",
+        "two-tone peak tracking and run-length demodulation",
+    ),
+    (
+        "explain.ultrasonic.fsk",
+        "{mark} Hz / {space} Hz keyed at {baud} baud, {bits} bits decoded{decoded}",
+    ),
+    ("technique.phase.quantized", "first-segment phase spectrum"),
+    (
+        "explain.phase.quantized",
+        "{run} consecutive bins from bin {first_bin} of the first {segment}-sample segment sit at +-pi/2{decoded}",
+    ),
+    (
+        "technique.phase.discontinuity",
+        "sample jumps at segment boundaries",
+    ),
+    (
+        "explain.phase.discontinuity",
+        "jumps every {segment} samples are {ratio}x the average > threshold {threshold}",
+    ),
+    (
+        "technique.demodulation.fsk",
+        "two-tone peak tracking around a flagged tone",
+    ),
+    (
+        "explain.demodulation.fsk",
+        "{mark} Hz / {space} Hz keyed at {baud} baud, {bits} bits decoded{decoded}",
+    ),
+    (
+        "technique.demodulation.psk",
+        "carrier mix-down and binary phase decisions",
+    ),
+    (
+        "explain.demodulation.psk",
+        "{carrier} Hz tone phase-keyed at {baud} baud, {bits} bits decoded{decoded}",
+    ),
+    (
+        "technique.differential.region",
+        "residual against an aligned reference recording",
+    ),
+    (
+        "explain.differential.region",
+        "{changed} samples between {start}s and {end}s differ from {reference}, {lsb}% by one LSB; residual {db} dB below the reference, spectral flatness {flatness}{decoded}",
+    ),
+    (
+        "technique.differential.bitrate",
+        "changed samples counted as substituted payload bits",
+    ),
+    (
+        "explain.differential.bitrate",
+        "about {bits} bits over {seconds}s of changed audio, {rate} bit/s",
+    ),
+    (
+        "technique.video.scene_lsb_divergence",
+        "per-scene LSB statistics against visual similarity",
+    ),
+    (
+        "explain.video.scene_lsb_divergence",
+        "frames {list} look like the rest of frames {start}-{end} but their chi-square is up to {ratio}x off the scene median {median} > {limit}x",
+    ),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("cli.summary_title", "RESUMEN DEL ANÁLISIS"),
+    (
+        "cli.steganography_detected",
+        "Esteganografía detectada: {value}",
+    ),
+    ("cli.confidence_level", "Nivel de confianza: {value}"),
+    ("cli.threat_indicators", "Indicadores de amenaza:"),
+    ("cli.extracted_payloads", "Cargas extraídas:"),
+    ("cli.explanations", "Por qué se activaron estos hallazgos:"),
+    ("cli.recommendations", "Recomendaciones:"),
+    ("cli.report_saved", "Informe JSON guardado en: {path}"),
+    ("cli.true", "sí"),
+    ("cli.false", "no"),
+    ("confidence.low", "baja"),
+    ("confidence.medium", "media"),
+    ("confidence.high", "alta"),
+    ("confidence.confirmed", "confirmada, carga extraída"),
+    (
+        "indicator.suspicious_structure",
+        "Datos sospechosos en la estructura del archivo",
+    ),
+    (
+        "indicator.multiple_formats",
+        "Se detectaron varios formatos de archivo",
+    ),
+    (
+        "indicator.slack_space",
+        "{bytes} bytes de espacio sobrante en {container} que la estructura del contenedor no justifica",
+    ),
+    (
+        "indicator.deflate_streams",
+        "{count} flujo(s) comprimido(s) fuera de los datos comprimidos propios del contenedor",
+    ),
+    (
+        "indicator.ole_unusual",
+        "{count} almacenamiento(s) o flujo(s) OLE2 que un documento no tendría: {list}",
+    ),
+    ("indicator.ole_macros", "Macros VBA en {count} módulo(s)"),
+    (
+        "indicator.ole_auto_exec",
+        "Macros que se ejecutan al abrir o cerrar el documento: {list}",
+    ),
+    (
+        "indicator.ole_executables",
+        "{count} objeto(s) incrustado(s) son ejecutables: {list}",
+    ),
+    (
+        "indicator.ebook_extraneous",
+        "{count} miembro(s) del libro electrónico que el paquete no declara: {list}",
+    ),
+    (
+        "indicator.ebook_anomaly",
+        "Empaquetado del libro electrónico: {anomaly}",
+    ),
+    (
+        "indicator.ebook_chapters",
+        "Rastros de esteganografía de texto en {count} capítulo(s)",
+    ),
+    (
+        "indicator.model_regions",
+        "{count} región(es) del modelo {format} que ningún cargador lee: {list}",
+    ),
+    ("indicator.model_anomaly", "Estructura {format}: {anomaly}"),
+    (
+        "indicator.structured_findings",
+        "{count} lugar(es) de la estructura {format} contienen datos: {list}",
+    ),
+    (
+        "indicator.source_comments",
+        "{count} comentario(s) {language} se decodifican como datos, en las líneas {list}",
+    ),
+    (
+        "indicator.source_comment_ratio",
+        "el {percent}% del archivo de código fuente es comentario",
+    ),
+    (
+        "indicator.ole_objects",
+        "Datos sospechosos dentro de {count} objeto(s) OLE incrustado(s)",
+    ),
+    (
+        "indicator.plugin_flagged",
+        "El complemento {plugin} marcó el archivo",
+    ),
+    (
+        "indicator.plugin_finding",
+        "Complemento {plugin}: {finding}",
+    ),
+    (
+        "indicator.lsb",
+        "El análisis LSB indica posibles datos ocultos",
+    ),
+    (
+        "indicator.ws",
+        "El análisis de pares estima que se modificó el {rate}% de los LSB",
+    ),
+    (
+        "indicator.channel_correlation",
+        "La correlación LSB entre canales se perdió en {collapsed} de {blocks} bloques lisos",
+    ),
+    (
+        "indicator.webp_frames",
+        "{suspicious} de {frames} fotogramas WebP muestran anomalías de LSB o de histograma",
+    ),
+    (
+        "indicator.page_outliers",
+        "{outliers} de {pages} páginas {format} difieren estadísticamente del resto: {list}",
+    ),
+    (
+        "indicator.hidden_pages",
+        "{hidden} imágenes {format} están almacenadas pero nunca se muestran: {list}",
+    ),
+    (
+        "indicator.adaptive_lsb",
+        "Las regiones con textura muestran LSB aleatorios y las regiones lisas no",
+    ),
+    (
+        "indicator.bpcs",
+        "{planes} planos de bits tienen bloques complejos que parecen datos aleatorios (inserción BPCS)",
+    ),
+    (
+        "indicator.calibration",
+        "Alrededor del {rate}% de los coeficientes DCT del JPEG difieren de una referencia calibrada (inserción tipo F5)",
+    ),
+    ("indicator.exif", "Metadatos EXIF sospechosos"),
+    (
+        "indicator.missing_metadata",
+        "Parece de tipo «{source}» pero no tiene metadatos {fields} (posiblemente eliminados)",
+    ),
+    (
+        "indicator.spectrogram",
+        "El análisis del espectrograma detectó patrones ocultos",
+    ),
+    (
+        "indicator.ultrasonic_fsk",
+        "Señal FSK ultrasónica en {mark}/{space} Hz con {bits} bits",
+    ),
+    (
+        "indicator.ultrasonic_energy",
+        "El {share}% de la energía del audio está por encima de {cutoff} Hz",
+    ),
+    (
+        "indicator.phase",
+        "Indicios de codificación de fase en segmentos de {segment} muestras: {run} bins cuantizados, saltos en los límites de {ratio}x",
+    ),
+    (
+        "indicator.demodulated",
+        "Datos {scheme} demodulados del tono de {carrier} Hz: {bits} bits",
+    ),
+    ("indicator.id3", "Metadatos ID3 sospechosos"),
+    (
+        "indicator.id3_pictures",
+        "{count} imagen(es) ID3 incrustada(s) muestran indicios de datos ocultos",
+    ),
+    (
+        "indicator.id3_tag_gaps",
+        "{bytes} bytes ocultos entre la(s) etiqueta(s) ID3v2 y el audio",
+    ),
+    (
+        "indicator.trailing_tags",
+        "Contenido marcado en etiquetas APEv2/Lyrics3: {list}",
+    ),
+    (
+        "indicator.mp4_tags",
+        "Elementos de metadatos MP4 (ilst) marcados: {list}",
+    ),
+    (
+        "indicator.differential",
+        "Contenido añadido a la referencia {reference}: {changed} muestras modificadas en {regions} región(es)",
+    ),
+    (
+        "indicator.video_scene",
+        "Los fotogramas {list} de la escena de los fotogramas {start}-{end} tienen estadísticas LSB {ratio}x distintas del resto de la escena",
+    ),
+    (
+        "recommendation.investigate",
+        "Se recomienda una investigación más profunda",
+    ),
+    (
+        "recommendation.specialized_tools",
+        "Considere usar herramientas especializadas de esteganografía",
+    ),
+    (
+        "recommendation.verify_source",
+        "Verifique el origen y la integridad del archivo",
+    ),
+    (
+        "recommendation.none_detected",
+        "No se detectó esteganografía evidente",
+    ),
+    ("recommendation.clean", "El archivo parece estar limpio"),
+    (
+        "recommendation.rescan_modified",
+        "El archivo cambió durante el análisis; vuelva a analizarlo cuando esté completo",
+    ),
+    ("explain.decoded_text", " (\"{text}\")"),
+    ("technique.plugin.finding", "complemento externo"),
+    ("explain.plugin.finding", "{text}"),
+    (
+        "technique.exif.suspicious_field",
+        "tamaño de campos de metadatos y heurísticas base64",
+    ),
+    ("explain.exif.suspicious_field", "{text}"),
+    (
+        "technique.exif.missing_metadata",
+        "metadatos esperados según el origen detectado",
+    ),
+    (
+        "explain.exif.missing_metadata",
+        "faltan {fields} en lo que parece de tipo «{source}»",
+    ),
+    (
+        "technique.id3.suspicious_frame",
+        "tamaño de marcos ID3, disposición de etiquetas y heurísticas base64",
+    ),
+    ("explain.id3.suspicious_frame", "{text}"),
+    (
+        "technique.trailing_tag.item",
+        "comprobación de tamaño y contenido de elementos APEv2/Lyrics3",
+    ),
+    ("explain.trailing_tag.item", "{kind} {key}: {text}"),
+    (
+        "technique.mp4_tag.item",
+        "comprobación de tamaño y contenido de elementos ilst de MP4",
+    ),
+    ("explain.mp4_tag.item", "{key}: {text}"),
+    (
+        "technique.id3.embedded_picture",
+        "análisis de imagen del marco APIC extraído",
+    ),
+    (
+        "explain.id3.embedded_picture",
+        "Imagen {picture_type} ({mime}) marcada por los analizadores de imagen",
+    ),
+    (
+        "technique.magic.embedded_signature",
+        "búsqueda de firmas de archivo",
+    ),
+    (
+        "explain.magic.embedded_signature",
+        "firma {description} en el desplazamiento {offset} > 0, hay datos después del inicio del archivo principal",
+    ),
+    (
+        "technique.magic.format_mismatch",
+        "comparación de la extensión con la firma detectada",
+    ),
+    ("explain.magic.format_mismatch", "{text}"),
+    (
+        "technique.slack_space.unaccounted_region",
+        "longitud declarada frente a bytes consumidos",
+    ),
+    (
+        "explain.slack_space.unaccounted_region",
+        "{length} bytes en {offset} fuera de la estructura {container} ({region}), entropía {entropy} bits/byte{detected}",
+    ),
+    ("explain.slack_space.detected_type", ", empieza como {mime}"),
+    (
+        "technique.deflate.embedded_stream",
+        "descompresión acotada en cada desplazamiento",
+    ),
+    (
+        "explain.deflate.embedded_stream",
+        "flujo {format} en {offset}: {compressed} bytes se descomprimen en {inflated} bytes de {classification}{truncated}",
+    ),
+    ("explain.deflate.truncated", ", cortado antes de su final"),
+    (
+        "technique.ole.unusual_entry",
+        "directorio del archivo compuesto frente a los flujos del formato del documento",
+    ),
+    (
+        "explain.ole.unusual_entry",
+        "{path} ({size} bytes): {reason}",
+    ),
+    (
+        "technique.ole.vba_macro",
+        "análisis del flujo dir del proyecto VBA",
+    ),
+    (
+        "explain.ole.vba_macro",
+        "módulo VBA {module} guardado en {stream}",
+    ),
+    (
+        "technique.ole.auto_exec",
+        "declaraciones de procedimientos VBA",
+    ),
+    (
+        "technique.ole.embedded_executable",
+        "firma de archivo del objeto incrustado",
+    ),
+    (
+        "explain.ole.embedded_executable",
+        "el objeto {stream} (empaquetado como {name}) es un programa: {classification}",
+    ),
+    (
+        "technique.ebook.extraneous_member",
+        "manifiesto del paquete frente a los miembros del archivo",
+    ),
+    (
+        "explain.ebook.extraneous_member",
+        "{path} ({size} bytes, {classification}) está en el paquete pero nada lo declara",
+    ),
+    (
+        "technique.ebook.packaging",
+        "reglas del contenedor EPUB/CHM",
+    ),
+    ("explain.ebook.packaging", "{anomaly}"),
+    (
+        "technique.ebook.zero_width",
+        "decodificación de caracteres de ancho cero",
+    ),
+    (
+        "explain.ebook.zero_width",
+        "{count} caracteres de ancho cero en {path}",
+    ),
+    (
+        "technique.ebook.whitespace",
+        "espacios en blanco al final de línea (SNOW)",
+    ),
+    (
+        "explain.ebook.whitespace",
+        "{lines} líneas de {path} terminan en espacios y tabuladores mezclados",
+    ),
+    (
+        "technique.ebook.homoglyphs",
+        "palabras con alfabetos mezclados",
+    ),
+    (
+        "explain.ebook.homoglyphs",
+        "letras cirílicas o griegas parecidas a las latinas en {path}: {list}",
+    ),
+    (
+        "technique.ebook.hidden_elements",
+        "marcado que nunca se muestra",
+    ),
+    (
+        "explain.ebook.hidden_elements",
+        "{count} elementos ocultos en {path}",
+    ),
+    (
+        "technique.model.hidden_region",
+        "datos del modelo fuera de las vistas de búfer y los registros",
+    ),
+    (
+        "explain.model.hidden_region",
+        "{format} {location}: {size} bytes ({classification}) que el modelo nunca usa",
+    ),
+    (
+        "technique.model.structure",
+        "reglas de formato glTF/GLB/STL",
+    ),
+    ("explain.model.structure", "{anomaly}"),
+    (
+        "technique.structured.comment",
+        "datos codificados en comentarios",
+    ),
+    (
+        "explain.structured.comment",
+        "el comentario {format} en {location} se decodifica como {preview}",
+    ),
+    ("technique.structured.long_value", "valores desmesurados"),
+    (
+        "explain.structured.long_value",
+        "el valor {format} {location} tiene {length} caracteres",
+    ),
+    (
+        "technique.structured.blob",
+        "base64 en claves no pensadas para datos binarios",
+    ),
+    (
+        "explain.structured.blob",
+        "el valor {format} {location} ({length} caracteres) se decodifica como {preview}",
+    ),
+    (
+        "technique.structured.invisible_key",
+        "caracteres invisibles o parecidos en claves",
+    ),
+    (
+        "explain.structured.invisible_key",
+        "la clave {format} {location} se lee como {preview}",
+    ),
+    (
+        "technique.source.comment_payload",
+        "datos codificados en comentarios de código",
+    ),
+    (
+        "explain.source.comment_payload",
+        "el comentario {language} en las líneas {line}-{end_line} se decodifica como {preview}",
+    ),
+    (
+        "technique.source.comment_ratio",
+        "proporción de comentarios frente a código",
+    ),
+    (
+        "explain.source.comment_ratio",
+        "el {percent}% de los caracteres no vacíos del archivo {language} ({comment_chars}) están en comentarios",
+    ),
+    (
+        "explain.ole.auto_exec",
+        "el módulo VBA {module} define {procedures}, que Office ejecuta sin que el usuario lo pida",
+    ),
+    ("technique.lsb.chi_square", "prueba de pares de valores"),
+    (
+        "explain.lsb.chi_square",
+        "chi-cuadrado {score} > umbral {threshold} en el canal {channel}, prueba de pares de valores",
+    ),
+    ("technique.lsb.entropy", "entropía de Shannon del plano LSB"),
+    (
+        "explain.lsb.entropy",
+        "entropía LSB {score} > umbral {threshold} en el canal {channel}, entropía de Shannon del plano LSB",
+    ),
+    (
+        "technique.lsb.baseline_chi_square",
+        "prueba de pares de valores frente a portadoras limpias del mismo formato",
+    ),
+    (
+        "explain.lsb.baseline_chi_square",
+        "chi-cuadrado {score} por par a {z} desviaciones típicas de las portadoras {kind} limpias (umbral {sigma}) en el canal {channel}",
+    ),
+    (
+        "technique.lsb.baseline_entropy",
+        "entropía LSB frente a portadoras limpias del mismo formato",
+    ),
+    (
+        "explain.lsb.baseline_entropy",
+        "entropía LSB a {z} desviaciones típicas de las portadoras {kind} limpias (umbral {sigma}) en el canal {channel}",
+    ),
+    (
+        "technique.lsb.flat_noise",
+        "discrepancia de LSB en las zonas planas de una imagen sintética",
+    ),
+    (
+        "explain.lsb.flat_noise",
+        "imagen sintética ({flat}% de vecinos planos) con {noise}% de ruido LSB en zonas planas > umbral {threshold}%, donde una captura de pantalla no tiene ninguno",
+    ),
+    (
+        "technique.ws.payload_rate",
+        "análisis de pares de muestras y weighted-stego",
+    ),
+    (
+        "explain.ws.payload_rate",
+        "carga estimada {rate} bpp > umbral {threshold} (~{changed}% de los LSB modificados), análisis de pares de muestras y weighted-stego",
+    ),
+    (
+        "technique.channel_correlation.collapsed_blocks",
+        "correlación LSB entre canales",
+    ),
+    (
+        "explain.channel_correlation.collapsed_blocks",
+        "{collapsed} de {blocks} bloques lisos perdieron la correlación LSB entre R/G/B (proporción {ratio} > umbral {threshold}), correlación LSB entre canales",
+    ),
+    (
+        "technique.adaptive_lsb.region_contrast",
+        "comparación adaptativa al contenido de pares de valores",
+    ),
+    (
+        "explain.adaptive_lsb.region_contrast",
+        "proporción de pares de valores lisas/con textura {contrast} > umbral {threshold} (puntuación con textura {textured}, lisas {smooth}), comparación adaptativa al contenido de pares de valores",
+    ),
+    (
+        "technique.bpcs.noise_planes",
+        "segmentación por complejidad de planos de bits",
+    ),
+    (
+        "explain.bpcs.noise_planes",
+        "{planes} planos de bits estructurados ({list}) no tienen bloques complejos cerca del umbral de complejidad, solo bloques de aspecto aleatorio (umbral {threshold} planos), segmentación por complejidad de planos de bits",
+    ),
+    (
+        "technique.calibration.change_rate",
+        "calibración contra una referencia recortada y recuantizada",
+    ),
+    (
+        "explain.calibration.change_rate",
+        "tasa de cambio estimada {rate}% > umbral {threshold}% (divergencia de histogramas {divergence} en {blocks} bloques), calibración contra una referencia recortada y recuantizada",
+    ),
+    (
+        "technique.pages.outlier",
+        "comparación mediana/MAD entre páginas",
+    ),
+    (
+        "explain.pages.outlier",
+        "{format} {label} puntuación z robusta {score} > umbral {threshold} en entropía LSB, chi-cuadrado o densidad de bordes, comparación mediana/MAD entre páginas",
+    ),
+    (
+        "technique.pages.hidden_item",
+        "grafo de referencias de elementos HEIF",
+    ),
+    (
+        "explain.pages.hidden_item",
+        "{label} no es la imagen principal ni está referenciada como miniatura, imagen auxiliar o mosaico",
+    ),
+    (
+        "technique.spectrogram.high_frequency_energy",
+        "distribución de energía STFT",
+    ),
+    (
+        "explain.spectrogram.high_frequency_energy",
+        "proporción de energía por encima de {cutoff} kHz {energy} > umbral {threshold}, distribución de energía STFT",
+    ),
+    (
+        "technique.spectrogram.pattern",
+        "heurísticas de tonos, bordes y picos de energía del espectrograma",
+    ),
+    ("explain.spectrogram.pattern", "{text}"),
+    (
+        "technique.ultrasonic.band_energy",
+        "energía STFT limitada a la banda",
+    ),
+    (
+        "explain.ultrasonic.band_energy",
+        "proporción de energía en {low}-{high} Hz {ratio} > umbral {threshold}",
+    ),
+    (
+        "technique.ultrasonic.fsk",
+        "seguimiento de picos de dos tonos y demodulación por longitud de rachas",
+    ),
+    (
+        "explain.ultrasonic.fsk",
+        "{mark} Hz / {space} Hz modulados a {baud} baudios, {bits} bits decodificados{decoded}",
+    ),
+    (
+        "technique.phase.quantized",
+        "espectro de fase del primer segmento",
+    ),
+    (
+        "explain.phase.quantized",
+        "{run} bins consecutivos desde el bin {first_bin} del primer segmento de {segment} muestras están en +-pi/2{decoded}",
+    ),
+    (
+        "technique.phase.discontinuity",
+        "saltos de muestra en los límites de segmento",
+    ),
+    (
+        "explain.phase.discontinuity",
+        "los saltos cada {segment} muestras son {ratio}x el promedio > umbral {threshold}",
+    ),
+    (
+        "technique.demodulation.fsk",
+        "seguimiento de picos de dos tonos alrededor de un tono marcado",
+    ),
+    (
+        "explain.demodulation.fsk",
+        "{mark} Hz / {space} Hz modulados a {baud} baudios, {bits} bits decodificados{decoded}",
+    ),
+    (
+        "technique.demodulation.psk",
+        "bajada de la portadora y decisiones binarias de fase",
+    ),
+    (
+        "explain.demodulation.psk",
+        "tono de {carrier} Hz modulado en fase a {baud} baudios, {bits} bits decodificados{decoded}",
+    ),
+    (
+        "technique.differential.region",
+        "residuo frente a una grabación de referencia alineada",
+    ),
+    (
+        "explain.differential.region",
+        "{changed} muestras entre {start}s y {end}s difieren de {reference}, el {lsb}% en un LSB; residuo {db} dB por debajo de la referencia, planitud espectral {flatness}{decoded}",
+    ),
+    (
+        "technique.differential.bitrate",
+        "muestras modificadas contadas como bits de carga sustituidos",
+    ),
+    (
+        "explain.differential.bitrate",
+        "unos {bits} bits en {seconds}s de audio modificado, {rate} bit/s",
+    ),
+    (
+        "technique.video.scene_lsb_divergence",
+        "estadísticas LSB por escena frente a la similitud visual",
+    ),
+    (
+        "explain.video.scene_lsb_divergence",
+        "los fotogramas {list} se parecen al resto de los fotogramas {start}-{end} pero su chi-cuadrado se aleja hasta {ratio}x de la mediana de la escena {median} > {limit}x",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_match() {
+        // Every message exists in both languages with the same placeholders
+        let en: BTreeSet<&str> = EN.iter().map(|(key, _)| *key).collect();
+        let es: BTreeSet<&str> = ES.iter().map(|(key, _)| *key).collect();
+        assert_eq!(en, es);
+        assert_eq!(en.len(), EN.len(), "duplicate English keys");
+        for (key, text) in ES {
+            let english = EN.iter().find(|(k, _)| k == key).unwrap().1;
+            assert_eq!(placeholders(text), placeholders(english), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_translation_and_fallback() {
+        let args = [("plugin", "acme".to_string())];
+        assert_eq!(
+            tr(Locale::Es, "indicator.plugin_flagged", &args),
+            "El complemento acme marcó el archivo"
+        );
+        assert_eq!(
+            tr(Locale::En, "indicator.plugin_flagged", &args),
+            "Plugin acme flagged the file"
+        );
+        assert_eq!(tr(Locale::Es, "no.such.message", &[]), "no.such.message");
+
+        assert_eq!("es_MX.UTF-8".parse::<Locale>(), Ok(Locale::Es));
+        assert_eq!("EN".parse::<Locale>(), Ok(Locale::En));
+        assert!("fr".parse::<Locale>().is_err());
+    }
+}
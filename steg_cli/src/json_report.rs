@@ -1,158 +1,66 @@
+use crate::i18n::{Locale, tr};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 
+pub use reports::*;
+
+// What an indicator that's weak evidence on its own, such as stripped metadata, adds to the
+// confidence level; every other indicator adds 1
+const LOW_INDICATOR_WEIGHT: f64 = 0.5;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SteganalysisReport {
     pub file_info: FileInfo,
     pub magic_bytes_analysis: Option<MagicBytesReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_space: Option<SlackSpaceReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deflate_streams: Option<DeflateStreamsReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extracted_files: Vec<ExtractedFileInfo>,
     pub format_specific_analysis: FormatSpecificAnalysis,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginReport>,
     pub timestamp: String,
+    // Language of the summary and explanation text
+    #[serde(default)]
+    pub locale: Locale,
     pub summary: AnalysisSummary,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub explanations: Vec<FindingExplanation>,
+    // The file's size or modification time changed while it was scanned, so the findings may
+    // describe a partly written file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub modified_during_scan: bool,
+    // Analyst review added afterwards with `stegascan annotate`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    // Analyzer versions and parameters; unset in reports written before they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunMetadata>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct FileInfo {
-    pub path: String,
-    pub size_bytes: u64,
-    pub detected_type: String,
-    pub extension: Option<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MagicBytesReport {
-    pub primary_format: String,
-    pub expected_format: Option<String>,
-    pub total_signatures_found: usize,
-    pub has_multiple_formats: bool,
-    pub has_suspicious_data: bool,
-    pub format_summary: FormatSummary,
-    pub embedded_files: Vec<EmbeddedFileInfo>,
-    pub suspicious_findings: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct FormatSummary {
-    pub images: usize,
-    pub audio: usize,
-    pub video: usize,
-    pub text_documents: usize,
-    pub archives: usize,
-    pub executables: usize,
-    pub other: usize,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct EmbeddedFileInfo {
+pub struct ExtractedFileInfo {
     pub offset: usize,
     pub offset_hex: String,
-    pub description: String,
-    pub file_type: String,
-    pub confidence: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "type")]
-pub enum FormatSpecificAnalysis {
-    Image(ImageAnalysis),
-    Audio(AudioAnalysis),
-    Video(VideoAnalysis),
-    Text(TextAnalysis),
-    Unknown,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ImageAnalysis {
-    pub exif_metadata: Option<ExifReport>,
-    pub lsb_analysis: Option<LsbReport>,
-    pub filter_analysis: FilterAnalysisReport,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ExifReport {
-    pub fields_found: usize,
-    pub has_thumbnail: bool,
-    pub thumbnail_size_bytes: Option<usize>,
-    pub comment_fields: Vec<String>,
-    pub suspicious_fields: Vec<String>,
-    pub metadata: Vec<MetadataField>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MetadataField {
-    pub key: String,
-    pub value: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct LsbReport {
-    pub is_suspicious: bool,
-    pub channels: Vec<LsbChannelAnalysis>,
-    pub output_files: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct LsbChannelAnalysis {
-    pub channel_name: String,
-    pub chi_square_score: f64,
-    pub entropy_score: f64,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct FilterAnalysisReport {
-    pub filters_generated: usize,
-    pub output_files: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct AudioAnalysis {
-    pub sample_count: usize,
-    pub id3_analysis: Option<Id3Report>,
-    pub spectrogram_analysis: Option<SpectrogramReport>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Id3Report {
-    pub title: Option<String>,
-    pub artist: Option<String>,
-    pub album: Option<String>,
-    pub year: Option<i32>,
-    pub comments_count: usize,
-    pub pictures_count: usize,
-    pub private_frames_count: usize,
-    pub suspicious_frames: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SpectrogramReport {
-    pub high_frequency_energy: f64,
-    pub hidden_message_detected: bool,
-    pub suspicious_patterns: Vec<String>,
-    pub output_file: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct VideoAnalysis {
-    pub frames_processed: usize,
-    pub errors_encountered: usize,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TextAnalysis {
-    pub file_type: String,
-    pub line_count: usize,
-    pub word_count: usize,
-    pub character_count: usize,
     pub size_bytes: usize,
+    pub description: String,
+    // binwalk extractor name, or "carved" for a raw byte range
+    pub method: String,
+    pub path: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct AnalysisSummary {
-    pub steganography_detected: bool,
-    pub confidence_level: String, // "low", "medium", "high"
-    pub threat_indicators: Vec<String>,
-    pub recommendations: Vec<String>,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FindingExplanation {
+    pub rule_id: String,
+    pub analyzer: String,
+    pub measured_value: Option<f64>,
+    pub threshold: Option<f64>,
+    pub technique: String,
+    pub description: String,
 }
 
 impl SteganalysisReport {
@@ -170,14 +78,25 @@ impl SteganalysisReport {
                 extension,
             },
             magic_bytes_analysis: None,
+            slack_space: None,
+            deflate_streams: None,
+            extracted_files: Vec::new(),
             format_specific_analysis: FormatSpecificAnalysis::Unknown,
+            plugins: Vec::new(),
             timestamp: chrono::Utc::now().to_rfc3339(),
+            locale: Locale::default(),
             summary: AnalysisSummary {
                 steganography_detected: false,
                 confidence_level: "low".to_string(),
                 threat_indicators: Vec::new(),
                 recommendations: Vec::new(),
+                verdict: SummaryVerdict::Clean,
+                extracted_payloads: Vec::new(),
             },
+            explanations: Vec::new(),
+            modified_during_scan: false,
+            annotations: Vec::new(),
+            run: None,
         }
     }
 
@@ -185,23 +104,91 @@ impl SteganalysisReport {
         self.magic_bytes_analysis = Some(analysis);
     }
 
+    pub fn set_slack_space(&mut self, analysis: SlackSpaceReport) {
+        self.slack_space = Some(analysis);
+    }
+
+    pub fn set_deflate_streams(&mut self, analysis: DeflateStreamsReport) {
+        self.deflate_streams = Some(analysis);
+    }
+
+    pub fn set_extracted_files(&mut self, files: Vec<ExtractedFileInfo>) {
+        self.extracted_files = files;
+    }
+
+    pub fn set_plugins(&mut self, plugins: Vec<PluginReport>) {
+        self.plugins = plugins;
+    }
+
     pub fn set_format_analysis(&mut self, analysis: FormatSpecificAnalysis) {
         self.format_specific_analysis = analysis;
     }
 
-    pub fn finalize_summary(&mut self) {
+    pub fn set_explanations(&mut self, explanations: Vec<FindingExplanation>) {
+        self.explanations = explanations;
+    }
+
+    // Names, as in the scan plan, of the analyzers that filled in a section of the report.
+    // An analyzer that was planned but failed or had nothing to read leaves its section unset.
+    pub fn reported_analyzers(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut add = |name: &str, reported: bool| {
+            if reported {
+                names.push(name.to_string());
+            }
+        };
+        add("magic_bytes", self.magic_bytes_analysis.is_some());
+        add("slack_space", self.slack_space.is_some());
+        add("deflate", self.deflate_streams.is_some());
+        match &self.format_specific_analysis {
+            FormatSpecificAnalysis::Image(img) => {
+                add("exif", img.exif_metadata.is_some());
+                add("lsb", img.lsb_analysis.is_some());
+                add("ws", img.ws_analysis.is_some());
+                add("channel_correlation", img.channel_correlation.is_some());
+                add("adaptive_lsb", img.adaptive_lsb.is_some());
+                add("bpcs", img.bpcs.is_some());
+                add("calibration", img.calibration.is_some());
+                add("webp", img.webp_analysis.is_some());
+                add("gif", img.gif_analysis.is_some());
+                add("png_text", img.png_text.is_some());
+                add("pages", img.page_analysis.is_some());
+                add("filters", img.filter_analysis.is_some());
+            }
+            FormatSpecificAnalysis::Audio(audio) => {
+                add("id3", audio.id3_analysis.is_some());
+                add("spectrogram", audio.spectrogram_analysis.is_some());
+                add("ultrasonic", audio.ultrasonic_analysis.is_some());
+                add("phase", audio.phase_analysis.is_some());
+                add("demodulation", audio.demodulation.is_some());
+            }
+            FormatSpecificAnalysis::Video(_) => add("video", true),
+            FormatSpecificAnalysis::Text(_) => add("text", true),
+            FormatSpecificAnalysis::Unknown => {}
+        }
+        add("extract", !self.extracted_files.is_empty());
+        for plugin in &self.plugins {
+            names.push(format!("plugin:{}", plugin.name));
+        }
+        names
+    }
+
+    // Indicators and recommendations are written in `locale`; steganography_detected and
+    // confidence_level are the same in every language
+    pub fn finalize_summary(&mut self, locale: Locale) {
         // Determine if steganography was detected
         let mut indicators = Vec::new();
+        let mut low_weight_indicators = 0;
         let mut steg_detected = false;
 
         // Check magic bytes analysis
         if let Some(ref magic) = self.magic_bytes_analysis {
             if magic.has_suspicious_data {
                 steg_detected = true;
-                indicators.push("Suspicious data found in file structure".to_string());
+                indicators.push(tr(locale, "indicator.suspicious_structure", &[]));
             }
             if magic.has_multiple_formats {
-                indicators.push("Multiple file formats detected".to_string());
+                indicators.push(tr(locale, "indicator.multiple_formats", &[]));
             }
             if !magic.suspicious_findings.is_empty() {
                 steg_detected = true;
@@ -209,64 +196,580 @@ impl SteganalysisReport {
             }
         }
 
+        if let Some(ref slack) = self.slack_space {
+            if slack.is_suspicious {
+                steg_detected = true;
+                indicators.push(tr(
+                    locale,
+                    "indicator.slack_space",
+                    &[
+                        ("bytes", slack.slack_bytes.to_string()),
+                        ("container", slack.container.clone()),
+                    ],
+                ));
+            }
+        }
+
+        if let Some(ref deflate) = self.deflate_streams
+            && deflate.is_suspicious
+        {
+            steg_detected = true;
+            indicators.push(tr(
+                locale,
+                "indicator.deflate_streams",
+                &[("count", deflate.streams.len().to_string())],
+            ));
+        }
+
+        for plugin in self.plugins.iter().filter(|p| p.is_suspicious) {
+            steg_detected = true;
+            if plugin.findings.is_empty() {
+                indicators.push(tr(
+                    locale,
+                    "indicator.plugin_flagged",
+                    &[("plugin", plugin.name.clone())],
+                ));
+            }
+            for finding in &plugin.findings {
+                indicators.push(tr(
+                    locale,
+                    "indicator.plugin_finding",
+                    &[
+                        ("plugin", plugin.name.clone()),
+                        ("finding", finding.description.clone()),
+                    ],
+                ));
+            }
+        }
+
         // Check format-specific analysis
         match &self.format_specific_analysis {
             FormatSpecificAnalysis::Image(img) => {
                 if let Some(ref lsb) = img.lsb_analysis {
                     if lsb.is_suspicious {
                         steg_detected = true;
-                        indicators.push("LSB analysis indicates possible hidden data".to_string());
+                        indicators.push(tr(locale, "indicator.lsb", &[]));
+                    }
+                }
+                if let Some(ref ws) = img.ws_analysis {
+                    if ws.is_suspicious {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ws",
+                            &[("rate", format!("{:.1}", ws.estimated_change_rate * 100.0))],
+                        ));
+                    }
+                }
+                if let Some(ref correlation) = img.channel_correlation {
+                    if correlation.is_suspicious {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.channel_correlation",
+                            &[
+                                ("collapsed", correlation.collapsed_block_count.to_string()),
+                                ("blocks", correlation.blocks_evaluated.to_string()),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref webp) = img.webp_analysis {
+                    if !webp.structure_anomalies.is_empty() {
+                        steg_detected = true;
+                        indicators.extend(webp.structure_anomalies.clone());
+                    }
+                    if !webp.suspicious_frames.is_empty() {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.webp_frames",
+                            &[
+                                ("suspicious", webp.suspicious_frames.len().to_string()),
+                                ("frames", webp.frame_count.to_string()),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref gif) = img.gif_analysis
+                    && !gif.structure_anomalies.is_empty()
+                {
+                    steg_detected = true;
+                    indicators.extend(gif.structure_anomalies.clone());
+                }
+                if let Some(ref png_text) = img.png_text
+                    && !png_text.anomalies.is_empty()
+                {
+                    steg_detected = true;
+                    indicators.extend(png_text.anomalies.clone());
+                }
+                if let Some(ref pages) = img.page_analysis {
+                    if !pages.outlier_pages.is_empty() {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.page_outliers",
+                            &[
+                                ("outliers", pages.outlier_pages.len().to_string()),
+                                ("pages", pages.page_count.to_string()),
+                                ("format", pages.format.clone()),
+                                ("list", format!("{:?}", pages.outlier_pages)),
+                            ],
+                        ));
+                    }
+                    if !pages.hidden_pages.is_empty() {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.hidden_pages",
+                            &[
+                                ("hidden", pages.hidden_pages.len().to_string()),
+                                ("format", pages.format.clone()),
+                                ("list", format!("{:?}", pages.hidden_pages)),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref adaptive) = img.adaptive_lsb {
+                    if adaptive.is_suspicious {
+                        steg_detected = true;
+                        indicators.push(tr(locale, "indicator.adaptive_lsb", &[]));
                     }
                 }
+                if let Some(ref bpcs) = img.bpcs
+                    && bpcs.is_suspicious
+                {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.bpcs",
+                        &[("planes", bpcs.noise_planes.to_string())],
+                    ));
+                }
+                if let Some(ref calibration) = img.calibration
+                    && calibration.is_suspicious
+                {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.calibration",
+                        &[(
+                            "rate",
+                            format!("{:.0}", calibration.estimated_change_rate * 100.0),
+                        )],
+                    ));
+                }
                 if let Some(ref exif) = img.exif_metadata {
                     if !exif.suspicious_fields.is_empty() {
-                        indicators.push("Suspicious EXIF metadata found".to_string());
+                        indicators.push(tr(locale, "indicator.exif", &[]));
                     }
                 }
+                if let Some(ref missing) = img.missing_metadata {
+                    low_weight_indicators += 1;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.missing_metadata",
+                        &[
+                            ("source", missing.expected_source.clone()),
+                            ("fields", missing.missing_fields.join(", ")),
+                        ],
+                    ));
+                }
             }
             FormatSpecificAnalysis::Audio(audio) => {
                 if let Some(ref spec) = audio.spectrogram_analysis {
                     if spec.hidden_message_detected {
                         steg_detected = true;
-                        indicators
-                            .push("Spectrogram analysis detected hidden patterns".to_string());
+                        indicators.push(tr(locale, "indicator.spectrogram", &[]));
+                    }
+                }
+                if let Some(ref ultrasonic) = audio.ultrasonic_analysis {
+                    if let Some(ref fsk) = ultrasonic.fsk {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ultrasonic_fsk",
+                            &[
+                                ("mark", format!("{:.0}", fsk.mark_hz)),
+                                ("space", format!("{:.0}", fsk.space_hz)),
+                                ("bits", fsk.bit_count.to_string()),
+                            ],
+                        ));
+                    } else if ultrasonic.is_suspicious {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ultrasonic_energy",
+                            &[
+                                (
+                                    "share",
+                                    format!("{:.1}", ultrasonic.band_energy_ratio * 100.0),
+                                ),
+                                ("cutoff", format!("{:.0}", ultrasonic.band_low_hz)),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref phase) = audio.phase_analysis {
+                    if phase.is_suspicious {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.phase",
+                            &[
+                                ("segment", phase.segment_length.to_string()),
+                                ("run", phase.quantized_run.to_string()),
+                                ("ratio", format!("{:.1}", phase.boundary_jump_ratio)),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref differential) = audio.differential {
+                    if differential.is_suspicious {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.differential",
+                            &[
+                                ("regions", differential.regions.len().to_string()),
+                                ("changed", differential.changed_samples.to_string()),
+                                ("reference", differential.reference_file.clone()),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(ref demodulation) = audio.demodulation {
+                    for carrier in &demodulation.carriers {
+                        let keyed = [
+                            carrier.fsk.as_ref().map(|fsk| ("FSK", fsk.bit_count)),
+                            carrier.psk.as_ref().map(|psk| ("PSK", psk.bit_count)),
+                        ];
+                        for (scheme, bit_count) in keyed.into_iter().flatten() {
+                            steg_detected = true;
+                            indicators.push(tr(
+                                locale,
+                                "indicator.demodulated",
+                                &[
+                                    ("scheme", scheme.to_string()),
+                                    ("carrier", format!("{:.0}", carrier.carrier_hz)),
+                                    ("bits", bit_count.to_string()),
+                                ],
+                            ));
+                        }
                     }
                 }
                 if let Some(ref id3) = audio.id3_analysis {
                     if !id3.suspicious_frames.is_empty() {
-                        indicators.push("Suspicious ID3 metadata found".to_string());
+                        indicators.push(tr(locale, "indicator.id3", &[]));
+                    }
+                    let flagged = id3.pictures.iter().filter(|p| p.is_suspicious).count();
+                    if flagged > 0 {
+                        steg_detected = true;
+                        indicators.push(tr(
+                            locale,
+                            "indicator.id3_pictures",
+                            &[("count", flagged.to_string())],
+                        ));
+                    }
+                    if !id3.tag_gaps.is_empty() {
+                        steg_detected = true;
+                        let bytes: usize = id3.tag_gaps.iter().map(|g| g.size_bytes).sum();
+                        indicators.push(tr(
+                            locale,
+                            "indicator.id3_tag_gaps",
+                            &[("bytes", bytes.to_string())],
+                        ));
+                    }
+                }
+                if let Some(trailing) = audio
+                    .trailing_tags
+                    .as_ref()
+                    .filter(|trailing| trailing.is_suspicious)
+                {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.trailing_tags",
+                        &[("list", trailing.flagged().join(", "))],
+                    ));
+                }
+                if let Some(mp4_tags) = audio.mp4_tags.as_ref().filter(|tags| tags.is_suspicious) {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.mp4_tags",
+                        &[("list", mp4_tags.flagged().join(", "))],
+                    ));
+                }
+            }
+            FormatSpecificAnalysis::Video(video) => {
+                for scene in video.scenes.iter().filter(|s| s.suspicious) {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.video_scene",
+                        &[
+                            ("start", scene.start_frame.to_string()),
+                            ("end", scene.end_frame.to_string()),
+                            ("list", format!("{:?}", scene.divergent_frames)),
+                            ("ratio", format!("{:.1}", scene.lsb_divergence)),
+                        ],
+                    ));
+                }
+                if let Some(mp4_tags) = video.mp4_tags.as_ref().filter(|tags| tags.is_suspicious) {
+                    steg_detected = true;
+                    indicators.push(tr(
+                        locale,
+                        "indicator.mp4_tags",
+                        &[("list", mp4_tags.flagged().join(", "))],
+                    ));
+                }
+            }
+            FormatSpecificAnalysis::Text(text) => {
+                if let Some(ole) = text.ole.as_ref().filter(|ole| ole.is_suspicious) {
+                    steg_detected = true;
+                    let unusual: Vec<&str> = ole
+                        .entries
+                        .iter()
+                        .filter(|e| e.unusual.is_some())
+                        .map(|e| e.path.as_str())
+                        .collect();
+                    if !unusual.is_empty() {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ole_unusual",
+                            &[
+                                ("count", unusual.len().to_string()),
+                                ("list", unusual.join(", ")),
+                            ],
+                        ));
+                    }
+                    let macros = ole.all_macros();
+                    if !macros.is_empty() {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ole_macros",
+                            &[("count", macros.len().to_string())],
+                        ));
+                    }
+                    let auto_exec: Vec<&str> = macros
+                        .iter()
+                        .flat_map(|module| module.auto_exec.iter().map(String::as_str))
+                        .collect();
+                    if !auto_exec.is_empty() {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ole_auto_exec",
+                            &[("list", auto_exec.join(", "))],
+                        ));
+                    }
+                    let executables: Vec<&str> = ole
+                        .all_objects()
+                        .into_iter()
+                        .filter(|o| o.executable)
+                        .map(|o| o.file_name.as_deref().unwrap_or(&o.stream))
+                        .collect();
+                    if !executables.is_empty() {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ole_executables",
+                            &[
+                                ("count", executables.len().to_string()),
+                                ("list", executables.join(", ")),
+                            ],
+                        ));
+                    }
+                    let flagged = ole
+                        .all_objects()
+                        .into_iter()
+                        .filter(|o| {
+                            o.magic_bytes_analysis
+                                .as_ref()
+                                .is_some_and(|magic| magic.has_suspicious_data)
+                        })
+                        .count();
+                    if flagged > 0 {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ole_objects",
+                            &[("count", flagged.to_string())],
+                        ));
+                    }
+                }
+                if let Some(ebook) = text.ebook.as_ref().filter(|ebook| ebook.is_suspicious) {
+                    steg_detected = true;
+                    let extraneous: Vec<&str> =
+                        ebook.extraneous().map(|m| m.path.as_str()).collect();
+                    if !extraneous.is_empty() {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ebook_extraneous",
+                            &[
+                                ("count", extraneous.len().to_string()),
+                                ("list", extraneous.join(", ")),
+                            ],
+                        ));
+                    }
+                    for anomaly in &ebook.anomalies {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ebook_anomaly",
+                            &[("anomaly", anomaly.clone())],
+                        ));
+                    }
+                    let chapters = ebook.chapters.iter().filter(|c| c.is_suspicious).count();
+                    if chapters > 0 {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.ebook_chapters",
+                            &[("count", chapters.to_string())],
+                        ));
+                    }
+                }
+                if let Some(structured) = text
+                    .structured
+                    .as_ref()
+                    .filter(|structured| structured.is_suspicious)
+                {
+                    steg_detected = true;
+                    let places: Vec<String> = structured
+                        .findings
+                        .iter()
+                        .map(|f| format!("{} at {}", f.kind, f.location))
+                        .collect();
+                    indicators.push(tr(
+                        locale,
+                        "indicator.structured_findings",
+                        &[
+                            ("count", places.len().to_string()),
+                            ("format", structured.format.clone()),
+                            ("list", places.join(", ")),
+                        ],
+                    ));
+                }
+                if let Some(source_code) = text
+                    .source_code
+                    .as_ref()
+                    .filter(|source_code| source_code.is_suspicious)
+                {
+                    steg_detected = true;
+                    if !source_code.payloads.is_empty() {
+                        let lines: Vec<String> = source_code
+                            .payloads
+                            .iter()
+                            .map(|p| format!("{}-{}", p.line, p.end_line))
+                            .collect();
+                        indicators.push(tr(
+                            locale,
+                            "indicator.source_comments",
+                            &[
+                                ("count", lines.len().to_string()),
+                                ("language", source_code.language.clone()),
+                                ("list", lines.join(", ")),
+                            ],
+                        ));
+                    }
+                    if source_code.abnormal_ratio {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.source_comment_ratio",
+                            &[(
+                                "percent",
+                                format!("{:.1}", source_code.comment_ratio * 100.0),
+                            )],
+                        ));
+                    }
+                }
+                if let Some(model) = text.model.as_ref().filter(|model| model.is_suspicious) {
+                    steg_detected = true;
+                    let locations: Vec<&str> =
+                        model.regions.iter().map(|r| r.location.as_str()).collect();
+                    indicators.push(tr(
+                        locale,
+                        "indicator.model_regions",
+                        &[
+                            ("count", locations.len().to_string()),
+                            ("format", model.format.clone()),
+                            ("list", locations.join(", ")),
+                        ],
+                    ));
+                    for anomaly in &model.anomalies {
+                        indicators.push(tr(
+                            locale,
+                            "indicator.model_anomaly",
+                            &[
+                                ("format", model.format.clone()),
+                                ("anomaly", anomaly.clone()),
+                            ],
+                        ));
                     }
                 }
             }
             _ => {}
         }
 
+        // Payloads the scan actually recovered. Images carved out of a file are left out, as
+        // thumbnails and previews are embedded that way routinely.
+        let mut recovered = recovered_payloads(
+            self.slack_space.as_ref(),
+            self.deflate_streams.as_ref(),
+            &self.format_specific_analysis,
+        );
+        for file in &self.extracted_files {
+            let routine = file.description.to_lowercase().contains("image");
+            if !routine && !recovered.iter().any(|p| p.offset == Some(file.offset)) {
+                recovered.push(RecoveredPayloadReport {
+                    source: format!("extraction ({})", file.method),
+                    description: format!("{} ({} bytes)", file.description, file.size_bytes),
+                    offset: Some(file.offset),
+                    artifact: Some(file.path.clone()),
+                });
+            }
+        }
+        steg_detected |= !recovered.is_empty();
+
         // Determine confidence level
-        let confidence = if indicators.len() >= 3 {
+        let weight = (indicators.len() - low_weight_indicators) as f64
+            + low_weight_indicators as f64 * LOW_INDICATOR_WEIGHT;
+        let confidence = if weight >= 3.0 {
             "high"
-        } else if indicators.len() >= 1 {
+        } else if weight >= 1.0 {
             "medium"
         } else {
             "low"
         };
 
         // Generate recommendations
-        let mut recommendations = Vec::new();
-        if steg_detected {
-            recommendations.push("Further investigation recommended".to_string());
-            recommendations.push("Consider using specialized steganography tools".to_string());
-            recommendations.push("Verify file source and integrity".to_string());
+        let recommendations: &[&str] = if steg_detected {
+            &[
+                "recommendation.investigate",
+                "recommendation.specialized_tools",
+                "recommendation.verify_source",
+            ]
         } else {
-            recommendations.push("No obvious steganography detected".to_string());
-            recommendations.push("File appears to be clean".to_string());
-        }
-
+            &["recommendation.none_detected", "recommendation.clean"]
+        };
+        let recommendations = self
+            .modified_during_scan
+            .then_some("recommendation.rescan_modified")
+            .iter()
+            .chain(recommendations)
+            .map(|id| tr(locale, id, &[]))
+            .collect();
+
+        self.locale = locale;
         self.summary = AnalysisSummary {
             steganography_detected: steg_detected,
             confidence_level: confidence.to_string(),
             threat_indicators: indicators,
             recommendations,
+            verdict: if steg_detected {
+                SummaryVerdict::Suspicious
+            } else {
+                SummaryVerdict::Clean
+            },
+            extracted_payloads: Vec::new(),
         };
+        self.summary.confirm_payloads(recovered);
     }
 
     pub fn save_to_file(&self, output_path: &str) -> std::io::Result<()> {
@@ -303,4 +806,78 @@ mod tests {
         let json = report.to_json();
         assert!(json.is_ok());
     }
+
+    #[test]
+    fn test_missing_metadata_is_a_low_weight_indicator() {
+        let path = PathBuf::from("/test/photo.jpg");
+        let mut report = SteganalysisReport::new(&path, 1024, "Image".to_string());
+        report.set_format_analysis(FormatSpecificAnalysis::Image(Box::new(ImageAnalysis {
+            exif_metadata: None,
+            missing_metadata: Some(MissingMetadataReport {
+                expected_source: "camera photo".to_string(),
+                missing_fields: vec!["Make".to_string(), "Model".to_string()],
+            }),
+            lsb_analysis: None,
+            ws_analysis: None,
+            channel_correlation: None,
+            adaptive_lsb: None,
+            bpcs: None,
+            calibration: None,
+            webp_analysis: None,
+            gif_analysis: None,
+            png_text: None,
+            page_analysis: None,
+            filter_analysis: None,
+            dimensions: ImageDimensions::default(),
+        })));
+
+        report.finalize_summary(Locale::En);
+        // Noted, but not enough on its own to raise the confidence or call it stego
+        assert_eq!(
+            report.summary.threat_indicators,
+            vec!["Looks like a camera photo but has no Make, Model metadata (possibly stripped)"]
+        );
+        assert_eq!(report.summary.confidence_level, "low");
+        assert!(!report.summary.steganography_detected);
+    }
+
+    #[test]
+    fn test_extracted_payload_overrides_confidence() {
+        let path = PathBuf::from("/test/cover.png");
+        let mut report = SteganalysisReport::new(&path, 1024, "Image".to_string());
+        report.set_extracted_files(vec![
+            ExtractedFileInfo {
+                offset: 0x200,
+                offset_hex: "0x200".to_string(),
+                size_bytes: 64,
+                description: "JPEG image data".to_string(),
+                method: "jpeg".to_string(),
+                path: "out/200.jpg".to_string(),
+            },
+            ExtractedFileInfo {
+                offset: 0x4A0,
+                offset_hex: "0x4A0".to_string(),
+                size_bytes: 310,
+                description: "ZIP archive".to_string(),
+                method: "carved".to_string(),
+                path: "out/4A0.zip".to_string(),
+            },
+        ]);
+
+        report.finalize_summary(Locale::En);
+        // No heuristic fired, but the appended ZIP was recovered; the thumbnail-like JPEG is
+        // not counted
+        assert!(report.summary.threat_indicators.is_empty());
+        assert!(report.summary.steganography_detected);
+        assert_eq!(report.summary.confidence_level, "confirmed");
+        assert_eq!(
+            report.summary.verdict,
+            SummaryVerdict::ConfirmedPayloadExtracted
+        );
+        assert_eq!(report.summary.extracted_payloads.len(), 1);
+        assert_eq!(
+            report.summary.extracted_payloads[0].artifact.as_deref(),
+            Some("out/4A0.zip")
+        );
+    }
 }
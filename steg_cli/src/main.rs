@@ -1,43 +1,601 @@
 use analyzers::{
-    Analyzer, exif_analyzer::ExifAnalyzerWithPath, id3_analyzer::Id3AnalyzerWithPath,
-    image_filter::ImageFilterAnalyzer, lsb_analyzer::LsbAnalyzer,
-    magic_bytes_analyzer::MagicBytesAnalyzerWithPath, spectrogram_analyzer::SpectrogramAnalyzer,
+    Analyzer,
+    adaptive_lsb_analyzer::{AdaptiveLsbAnalyzer, RegionStats},
+    binwalk_extractor::{BinwalkExtractorWithPath, ExtractionMethod},
+    bpcs_analyzer::{BpcsAnalysis, BpcsAnalyzer},
+    calibration_analyzer::{CalibrationAnalysis, CalibrationAnalyzer, CalibrationError},
+    channel_correlation_analyzer::ChannelCorrelationAnalyzer,
+    content_classifier::{ContentClass, ContentClassification, ContentClassifier},
+    content_store::ObjectReference,
+    cover_baseline::{CoverFormat, LsbDeviation},
+    deflate_hunter::DeflateHunter,
+    demodulator::{CarrierDemodulation, Demodulator, FskSignal, MAX_DECODED_BYTES},
+    differential_analyzer::{DifferentialAnalysis, DifferentialAnalyzer},
+    ebook_analyzer::{EbookAnalyzer, EbookChapter, is_ebook},
+    exif_analyzer::{ExifAnalyzerWithPath, ExifData, missing_metadata},
+    frame_pipeline::FramePipeline,
+    frame_sampler::{FrameSampler, SamplingMode},
+    gif_extension_analyzer::{GifExtensionAnalysis, GifExtensionAnalyzer},
+    id3_analyzer::{
+        Id3AnalyzerWithPath, Id3Data, Id3v1Tag, LyricsPayload, LyricsPayloadKind, PictureInfo,
+    },
+    image_filter::ImageFilterAnalyzer,
+    lsb_analyzer::{LsbAnalysis, LsbAnalyzer},
+    magic_bytes_analyzer::{
+        CustomSignature, MagicBytesAnalysis, MagicBytesAnalyzerWithPath, Strictness,
+    },
+    makernote_analyzer::MakerNoteAnalysis,
+    model_analyzer::{ModelAnalyzer, ModelFormat, model_format},
+    mp4_tag_analyzer::Mp4TagAnalyzer,
+    office_analyzer::{
+        OfficeAnalysis, OfficeAnalyzer, OfficeFormat, OfficeObject, is_office_document,
+    },
+    page_analyzer::PageAnalyzer,
+    payload_sniffer::PayloadInterpretation,
+    phase_analyzer::PhaseAnalyzer,
+    png_text_analyzer::{KeywordKind, PngTextAnalysis, PngTextAnalyzer},
+    region_overlay::{FlaggedRegion, render_overlay},
+    scene_detector::{Scene, SceneDetector},
+    shared,
+    slack_space_analyzer::{MIN_SLACK_BYTES, SlackSpaceAnalyzer},
+    source_code_analyzer::{SourceCodeAnalyzer, language_for},
+    spectrogram_analyzer::{PersistentTone, SpectrogramAnalyzer, SpectrogramTile, analyze_tiles},
+    spectrogram_render::ColorMap,
+    structured_text_analyzer::{StructuredTextAnalyzer, structured_format},
+    trailing_tag_analyzer::{TagItem, TrailingTagAnalyzer},
+    ultrasonic_analyzer::{UltrasonicAnalysis, UltrasonicAnalyzer},
     video_frame_analyzer::VideoFrameAnalyzer,
+    webp_structure_analyzer::WebpStructureAnalyzer,
+    ws_analyzer::WsAnalyzer,
 };
-use clap::Parser;
-use infer::Infer;
+use clap::{ArgGroup, Parser, Subcommand};
 use parsers::{
-    Parser as _, audio_parser::AudioParser, image_parser::ImageParser, text_parser::TextParser,
-    video_parser::VideoParser,
+    Parser as _,
+    audio_parser::AudioParser,
+    image_parser::ImageParser,
+    multi_image_parser::MultiImageParser,
+    text_parser::TextParser,
+    video_parser::{HwAccel, VideoDecodeOptions, VideoParser},
+    webp_parser::WebpParser,
 };
 use serde::Serialize;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
+mod archive_member;
+mod calibrate;
+mod capture;
+mod case;
+mod checkpoint;
+mod config;
+mod convert;
+mod dedupe;
+mod disk_image;
+mod evidence;
+mod explain;
+mod feedback;
+mod i18n;
 mod json_report;
+mod manifest;
+mod plan;
+mod quick;
+mod remote;
+mod settle;
+mod walker;
+use archive_member::InnerPath;
+use case::{Case, CaseScan};
+use checkpoint::{Checkpoint, CheckpointEntry};
+use config::{AnalyzerToggles, Profile, Settings, artifact_stem};
+use convert::ReportFormat;
+use dedupe::{Duplicate, DuplicateReference, find_duplicates};
+use feedback::FeedbackFormat;
+use i18n::{Locale, tr};
 use json_report::*;
+use manifest::{PlannedScan, ScanManifest, SkipFilters};
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(
     name = "stegascan",
     version = "0.1.0",
     about = "CLI to process file metadata"
 )]
 struct Args {
-    /// Path to the file to process
-    #[arg(short, long, required = true)]
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
     /// Enable verbose output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     verbose: bool,
 
-    /// Output path for JSON report
-    #[arg(short, long, default_value = "outputs/report.json")]
-    output: String,
+    /// Scan depth preset; config file, environment and flags still override it
+    #[arg(long, global = true, value_enum)]
+    profile: Option<Profile>,
+
+    /// Config file to load [default: $STEGASCAN_CONFIG, then ./stegascan.toml]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Output path for JSON report [default: outputs/report.json]
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+
+    /// Directory for generated artifacts [default: outputs]
+    #[arg(long, global = true)]
+    output_dir: Option<PathBuf>,
+
+    /// Language of the summary, recommendations and explanations: en or es [default: en]
+    #[arg(long, global = true)]
+    locale: Option<Locale>,
+
+    /// YAML or JSON file of extra magic-byte signatures to scan for
+    #[arg(long, global = true)]
+    signatures: Option<PathBuf>,
+
+    /// Statistics of known-clean files written by `stegascan calibrate`, which scans are
+    /// judged against [default: ./stegascan-calibration.yaml if it exists]
+    #[arg(long, global = true, value_name = "FILE")]
+    calibration: Option<PathBuf>,
+
+    /// Header validation for signature hits: lenient, normal or strict [default: normal]
+    #[arg(long, global = true)]
+    strictness: Option<Strictness>,
+
+    /// Number of video frames to sample (analyze every Nth frame) [default: 30]
+    #[arg(long, global = true)]
+    video_sample_rate: Option<usize>,
+
+    /// Video frame sampling: stride (every Nth), random (1 in N, seeded), scene (first frame
+    /// after each cut) or time (one frame per interval) [default: stride]
+    #[arg(long, global = true)]
+    video_sampling: Option<SamplingMode>,
+
+    /// Colors of saved spectrograms: grayscale, or viridis to bring out faint patterns
+    /// [default: grayscale]
+    #[arg(long, global = true)]
+    spectrogram_colormap: Option<ColorMap>,
+
+    /// Seed for random video frame sampling [default: 0]
+    #[arg(long, global = true)]
+    video_seed: Option<u64>,
+
+    /// Hardware video decoder: none, auto, vaapi, videotoolbox or nvdec. Falls back to
+    /// software decoding when the device or codec isn't supported [default: none]
+    #[arg(long, global = true)]
+    video_hwaccel: Option<HwAccel>,
+
+    /// Analyzers to skip (magic_bytes, slack_space, deflate, exif, lsb, ws, channel_correlation, adaptive_lsb, bpcs, calibration, webp, gif, png_text, pages, filters, id3, spectrogram, ultrasonic, demodulation, phase, video, text)
+    #[arg(long, global = true, value_delimiter = ',')]
+    disable: Vec<String>,
+
+    // Given before any subcommand, `stegascan <FILE>` and the scan flags work as `scan` does
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(clap::Args, Clone, Default)]
+#[command(group(ArgGroup::new("batch").args(["plan", "disk_image", "pcap", "dir"])))]
+struct ScanArgs {
+    /// File to scan, the same as --file
+    #[arg(value_name = "FILE", conflicts_with = "file")]
+    input: Option<PathBuf>,
+
+    /// Path to the file to process
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Member of a ZIP archive to scan without extracting it, as <ARCHIVE>!<MEMBER>, e.g.
+    /// evidence.zip!photos/cover.png
+    #[arg(long, value_name = "ARCHIVE!MEMBER", conflicts_with_all = ["input", "file"])]
+    inner: Option<InnerPath>,
+
+    /// HTTP(S) URL of a file to scan. Only its first and last MB are fetched, with Range
+    /// requests, for a quick verdict on its format, appended data and trailing tags; with
+    /// --profile deep the whole file is downloaded and fully scanned.
+    #[arg(long, value_name = "URL", conflicts_with_all = ["input", "file", "inner", "plan", "disk_image", "pcap", "dir"])]
+    url: Option<String>,
+
+    /// YAML or JSON scan plan listing files or globs to scan, with per-entry analyzer
+    /// overrides and output destinations
+    #[arg(long, conflicts_with_all = ["input", "file", "inner", "output"])]
+    plan: Option<PathBuf>,
+
+    /// ISO, dd or other raw disk image: the files of its ISO 9660 and FAT volumes are
+    /// extracted to <output-dir>/<image>_image and each is scanned. An image with no
+    /// recognized partition table or file system is scanned as a single file.
+    #[arg(long, value_name = "IMAGE", conflicts_with_all = ["input", "file", "inner", "output", "plan"])]
+    disk_image: Option<PathBuf>,
+
+    /// pcap or pcapng capture: media files sent or fetched over HTTP and SMTP are carved
+    /// to <output-dir>/<capture>_capture and scanned, with a per-flow report in flows.json
+    #[arg(long, value_name = "CAPTURE", conflicts_with_all = ["input", "file", "inner", "output", "plan", "disk_image"])]
+    pcap: Option<PathBuf>,
+
+    /// Directory to scan recursively, walked in parallel. Reports mirror the tree under
+    /// <output-dir>/reports, each next to its file's artifacts, and batch_report.json in
+    /// <output-dir> collects how every file came out.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["input", "file", "inner", "output"])]
+    dir: Option<PathBuf>,
+
+    /// Glob of files below --dir to scan, relative to it, e.g. '*.png'. Repeatable; without
+    /// any, every file is scanned.
+    #[arg(long, requires = "dir", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Glob of files or directories below --dir to leave out, e.g. 'node_modules/**'.
+    /// Repeatable; excluded directories are not descended into.
+    #[arg(long, requires = "dir", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// How many directory levels below --dir to descend; 0 scans only the files directly in it
+    #[arg(long, requires = "dir", value_name = "LEVELS")]
+    max_depth: Option<usize>,
+
+    /// Give up on a planned file after this many seconds and move on to the next. The
+    /// abandoned scan is not interrupted and may keep running until the batch finishes.
+    #[arg(long, requires = "batch", value_name = "SECONDS")]
+    timeout_per_file: Option<u64>,
+
+    /// Wait until a planned file's size and modification time hold still this long before
+    /// scanning it, and scan files that changed during their scan again, for directories
+    /// that are still being written to
+    #[arg(long, requires = "batch", value_name = "SECONDS")]
+    settle: Option<f64>,
+
+    /// File extensions a scan plan, directory, disk image or capture skips, e.g. iso,vmdk
+    #[arg(long, requires = "batch", value_delimiter = ',')]
+    skip_ext: Vec<String>,
+
+    /// Skip planned, extracted or carved files larger than this many MB
+    #[arg(long, requires = "batch", value_name = "MB")]
+    max_size: Option<u64>,
+
+    /// Skip planned files reached through a symbolic link below the plan's directory
+    #[arg(long, requires = "plan")]
+    no_follow_symlinks: bool,
+
+    /// Scan hard-linked or identical planned, extracted or carved files once; the others
+    /// get a report that points at the first one
+    #[arg(long, requires = "batch")]
+    dedupe: bool,
+
+    /// Pick up a batch where an interrupted run stopped: files whose content is already in
+    /// the output directory's checkpoint.jsonl as scanned are not scanned again
+    #[arg(long, requires = "batch")]
+    resume: bool,
+
+    /// Two-stage batch: run only the cheap analyzers (magic bytes, slack space) on every file
+    /// and the full analysis only on the files they flag
+    #[arg(long, requires = "batch")]
+    triage: bool,
+
+    /// Explain each finding with the measured value, threshold and technique
+    #[arg(long)]
+    explain: bool,
+
+    /// Export the decoded, normalized PCM samples the audio analyzers consumed as WAV, and
+    /// the ultrasonic band shifted down into the audible range
+    #[arg(long)]
+    export_samples: bool,
+
+    /// Clean reference recording to compare an audio file against: the two are aligned and
+    /// their difference analyzed for injected content
+    #[arg(long, conflicts_with = "plan")]
+    compare_with: Option<PathBuf>,
+
+    /// Recover embedded files found by the signature scan into <output-dir>/<file>_extracted,
+    /// and carve slack space regions into the payload store at <output-dir>/payloads
+    #[arg(long)]
+    extract: bool,
+
+    /// List the analyzers, what they apply to and whether they are enabled, then exit
+    #[arg(long)]
+    list_analyzers: bool,
+
+    /// Print the parsers and analyzers that would run on --file, with their parameters and
+    /// estimated cost, without running them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Bundle the scanned file, report, artifacts and their hashes into one ZIP with a
+    /// signed manifest. The scan writes its artifacts to a directory of its own,
+    /// <output-dir>/<file>_evidence_<random>, so only they end up in the package.
+    #[arg(long, value_name = "ZIP", conflicts_with_all = ["plan", "dry_run"], requires = "evidence_key")]
+    export_evidence: Option<PathBuf>,
+
+    /// File holding the key the evidence manifest is signed with (HMAC-SHA256)
+    #[arg(long, value_name = "FILE", requires = "export_evidence")]
+    evidence_key: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Scan a file, or a batch given by --plan, --dir, --disk-image or --pcap. `stegascan
+    /// <FILE>` is a shortcut for `stegascan scan <FILE>`.
+    Scan(Box<ScanArgs>),
+    /// Recover the files embedded in a file and carve its slack space, running only the
+    /// structural analyzers. `scan --extract` does the same alongside the full analysis.
+    Extract {
+        /// File to extract from
+        file: PathBuf,
+    },
+    /// Show a saved report, or render it in another format
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+    /// Run the stegascan-api HTTP server
+    Serve {
+        /// Address to listen on [default: 0.0.0.0:3000]
+        #[arg(long, value_name = "ADDR")]
+        addr: Option<SocketAddr>,
+    },
+    /// Inspect or create the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Verify an evidence package written by --export-evidence, unpack it and show its
+    /// report, optionally re-running analyzers on the packaged original
+    Open {
+        /// The evidence package
+        package: PathBuf,
+
+        /// Key the manifest was signed with; without it only the file hashes are checked
+        #[arg(long, value_name = "FILE")]
+        key: Option<PathBuf>,
+
+        /// Where to unpack the package [default: <output-dir>/<package name>]
+        #[arg(long, value_name = "DIR")]
+        unpack_to: Option<PathBuf>,
+
+        /// Analyzers to re-run on the packaged original, or "all". The new report and
+        /// artifacts are written to <unpack-to>/replay.
+        #[arg(long, value_delimiter = ',')]
+        rerun: Vec<String>,
+    },
+    /// Triage one image, from a path or the clipboard: runs the cheap pixel analyzers with
+    /// the fast profile, draws the LSB planes in the terminal and prints a one-line verdict
+    Quick {
+        /// Image to check [default: the image on the clipboard, e.g. a fresh screenshot]
+        path: Option<PathBuf>,
+
+        /// Terminal columns to draw the LSB planes in
+        #[arg(long, default_value_t = 96)]
+        width: u32,
+
+        /// Print only the verdict
+        #[arg(long)]
+        no_planes: bool,
+    },
+    /// Measure LSB chi-square and entropy of clean images, per format and content class,
+    /// and high-frequency energy of clean audio, from a directory of known-clean files.
+    /// Later scans flag files that stray from these rather than from the fixed thresholds.
+    Calibrate {
+        /// Directory of known-clean files
+        clean_dir: PathBuf,
+
+        /// Calibration file to write or update [default: --calibration, then
+        /// ./stegascan-calibration.yaml]
+        #[arg(long, value_name = "FILE")]
+        path: Option<PathBuf>,
+    },
+    /// Group the scans of related files under a case directory, with notes, evidence
+    /// packages and a combined case report
+    Case {
+        #[command(subcommand)]
+        action: CaseAction,
+    },
+    /// Label a finding of a saved report as a true or false positive, or comment on it. The
+    /// annotation is written into the report; without --label or --comment the report's
+    /// annotations are listed.
+    Annotate {
+        /// Report written by a scan
+        report: PathBuf,
+
+        /// Rule ID of the finding, as --explain lists it; omit to annotate the whole scan
+        #[arg(long)]
+        rule: Option<String>,
+
+        /// true-positive, false-positive or comment [default: comment]
+        #[arg(long)]
+        label: Option<AnnotationLabel>,
+
+        #[arg(long)]
+        comment: Option<String>,
+
+        /// Who reviewed the finding
+        #[arg(long)]
+        analyst: Option<String>,
+    },
+    /// Work with the analyst labels `stegascan annotate` wrote into reports
+    Feedback {
+        #[command(subcommand)]
+        action: FeedbackAction,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum ReportAction {
+    /// Print a saved report's summary, in --locale if given
+    Show {
+        /// Report written by a scan
+        report: PathBuf,
+
+        /// Also explain each finding with the measured value, threshold and technique
+        #[arg(long)]
+        explain: bool,
+    },
+    /// Render a saved report as HTML, CSV or SARIF without scanning the file again
+    Convert {
+        /// Report written by a scan
+        report: PathBuf,
+
+        #[arg(long, value_enum)]
+        to: ReportFormat,
+
+        /// File to write [default: the report's path with the format's extension]
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum FeedbackAction {
+    /// Export the labelled findings of reports as training data for threshold tuning: one row
+    /// per finding with its rule, measured value, threshold and label
+    Export {
+        /// Reports, or directories such as case directories to look for reports in
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = FeedbackFormat::Jsonl)]
+        format: FeedbackFormat,
+
+        /// File to write [default: standard output]
+        #[arg(long, value_name = "FILE")]
+        to: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum CaseAction {
+    /// Start a case in a new directory, named after it
+    New {
+        /// The case directory
+        case: PathBuf,
+
+        /// What the case is about
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Scan files and add their reports to a case
+    Add {
+        /// The case directory
+        case: PathBuf,
+
+        /// Files to scan
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Note to attach to each file added
+        #[arg(long)]
+        note: Option<String>,
+
+        /// Also package each scan as evidence, signed with the key in this file
+        #[arg(long, value_name = "FILE")]
+        evidence_key: Option<PathBuf>,
+    },
+    /// Add a note to a case, or to one of its scans
+    Note {
+        /// The case directory
+        case: PathBuf,
+
+        text: String,
+
+        /// ID of the scan the note is about, as `case report` lists it
+        #[arg(long)]
+        scan: Option<String>,
+    },
+    /// Write the combined case report and print an overview
+    Report {
+        /// The case directory
+        case: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum ConfigAction {
+    /// Print the effective configuration after layering file, environment and flags
+    Show,
+    /// Write a config file containing the defaults
+    Init {
+        /// Where to write the config file
+        #[arg(long, default_value = config::DEFAULT_CONFIG_FILE)]
+        path: PathBuf,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+// Layer CLI flags over the config file and environment
+fn resolve_settings(args: &Args) -> Result<Settings, config::ConfigError> {
+    let mut settings = Settings::load(args.config.as_deref(), args.profile)?;
+
+    if let Some(output) = &args.output {
+        settings.output.report = output.clone();
+    }
+    if let Some(dir) = &args.output_dir {
+        settings.output.dir = dir.clone();
+    }
+    if let Some(locale) = args.locale {
+        settings.output.locale = locale;
+    }
+    if let Some(signatures) = &args.signatures {
+        settings.magic_bytes.signatures_file = Some(signatures.clone());
+    }
+    if let Some(calibration) = &args.calibration {
+        settings.thresholds.calibration_file = Some(calibration.clone());
+    }
+    if let Some(strictness) = args.strictness {
+        settings.magic_bytes.strictness = strictness;
+    }
+    if let Some(rate) = args.video_sample_rate {
+        settings.video.sample_rate = rate;
+    }
+    if let Some(sampling) = args.video_sampling {
+        settings.video.sampling = sampling;
+    }
+    if let Some(color_map) = args.spectrogram_colormap {
+        settings.audio.spectrogram_colormap = color_map;
+    }
+    if let Some(seed) = args.video_seed {
+        settings.video.seed = seed;
+    }
+    if let Some(hwaccel) = args.video_hwaccel {
+        settings.video.hwaccel = hwaccel;
+    }
+    if let Some(seconds) = args.scan.settle {
+        settings.limits.settle_seconds = seconds;
+    }
+    for name in &args.disable {
+        settings.analyzers.set(name, false)?;
+    }
+    settings.apply_calibration()?;
+
+    Ok(settings)
+}
 
-    /// Number of video frames to sample (analyze every Nth frame)
-    #[arg(long, default_value = "30")]
-    video_sample_rate: usize,
+fn run_config_command(
+    action: &ConfigAction,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ConfigAction::Show => {
+            match Settings::config_path(args.config.as_deref()) {
+                Some(path) => println!("# Config file: {}", path.display()),
+                None => println!("# Config file: none (using defaults)"),
+            }
+            print!("{}", settings.to_toml()?);
+        }
+        ConfigAction::Init { path, force } => {
+            if path.exists() && !force {
+                return Err(format!(
+                    "{} already exists, pass --force to overwrite",
+                    path.display()
+                )
+                .into());
+            }
+            std::fs::write(path, Settings::default().to_toml()?)?;
+            println!("Wrote default configuration to {}", path.display());
+        }
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Debug)]
@@ -49,51 +607,3243 @@ enum FileType {
     Image,
 }
 
-#[derive(Serialize)]
-struct FileObject {
-    file_path: PathBuf,
-    file_size: u64,
-    file_type: FileType,
+#[derive(Serialize)]
+struct FileObject {
+    file_path: PathBuf,
+    file_size: u64,
+    file_type: FileType,
+}
+
+fn process_file(path: &PathBuf) -> Result<FileObject, Box<dyn std::error::Error>> {
+    let metadata = std::fs::metadata(&path)?;
+    let file_type = if let Ok(Some(kind)) = shared::infer().get_from_path(&path) {
+        match kind.mime_type() {
+            mime if mime.starts_with("audio/") => FileType::Audio,
+            mime if mime.starts_with("video/") => FileType::Video,
+            mime if mime.starts_with("text/") || mime.starts_with("application/") => FileType::Text,
+            mime if mime.starts_with("image/") => FileType::Image,
+            _ => {
+                if path.extension().and_then(|ext| ext.to_str()) == Some("wma") {
+                    FileType::Audio
+                } else {
+                    FileType::Text
+                }
+            }
+        }
+    } else {
+        if path.extension().and_then(|ext| ext.to_str()) == Some("wma") {
+            FileType::Audio
+        } else {
+            FileType::Text
+        }
+    };
+    Ok(FileObject {
+        file_path: path.to_path_buf(),
+        file_size: metadata.len(),
+        file_type,
+    })
+}
+
+fn magic_bytes_report(analysis: &MagicBytesAnalysis) -> MagicBytesReport {
+    MagicBytesReport {
+        primary_format: analysis.primary_format.clone(),
+        expected_format: analysis.expected_format.clone(),
+        total_signatures_found: analysis.total_signatures_found,
+        has_multiple_formats: analysis.has_multiple_formats,
+        has_suspicious_data: analysis.has_suspicious_data,
+        format_summary: FormatSummary {
+            images: analysis.format_summary.image_files,
+            audio: analysis.format_summary.audio_files,
+            video: analysis.format_summary.video_files,
+            text_documents: analysis.format_summary.text_files,
+            archives: analysis.format_summary.archive_files,
+            executables: analysis.format_summary.executable_files,
+            other: analysis.format_summary.other_files,
+        },
+        embedded_files: analysis
+            .embedded_files
+            .iter()
+            .map(|f| EmbeddedFileInfo {
+                offset: f.offset,
+                offset_hex: format!("0x{:X}", f.offset),
+                description: f.description.clone(),
+                file_type: f.file_type.clone(),
+                confidence: f.confidence.clone(),
+            })
+            .collect(),
+        suspicious_findings: analysis.suspicious_findings.clone(),
+    }
+}
+
+fn exif_report(exif_data: &ExifData) -> ExifReport {
+    ExifReport {
+        fields_found: exif_data.metadata.len(),
+        has_thumbnail: exif_data.has_thumbnail,
+        thumbnail_size_bytes: exif_data.thumbnail_size,
+        comment_fields: exif_data.comment_fields.clone(),
+        suspicious_fields: exif_data.suspicious_fields.clone(),
+        metadata: exif_data
+            .metadata
+            .iter()
+            .map(|(k, v)| MetadataField {
+                key: k.clone(),
+                value: v.clone(),
+            })
+            .collect(),
+        maker_note: exif_data.maker_note.as_ref().map(maker_note_report),
+    }
+}
+
+fn maker_note_report(maker_note: &MakerNoteAnalysis) -> MakerNoteReport {
+    MakerNoteReport {
+        layout: maker_note.layout.to_string(),
+        offset: maker_note.offset,
+        size_bytes: maker_note.size,
+        entries: maker_note.entries.len(),
+        accounted_bytes: maker_note.accounted_bytes,
+        preview_bytes: maker_note.preview_bytes,
+        findings: maker_note
+            .findings
+            .iter()
+            .map(|finding| MakerNoteFindingReport {
+                offset: finding.offset,
+                size_bytes: finding.size,
+                description: finding.description.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn lyrics_payload_report(payload: &LyricsPayload) -> Id3LyricsPayloadReport {
+    Id3LyricsPayloadReport {
+        frame: payload.frame.clone(),
+        timestamp: payload.timestamp,
+        kind: match payload.kind {
+            LyricsPayloadKind::Base64 => "base64".to_string(),
+            LyricsPayloadKind::Binary => "binary".to_string(),
+        },
+        length: payload.length,
+        decoded_size: payload.decoded.len(),
+        classification: payload.classification.clone(),
+    }
+}
+
+fn v1_tag_report(tag: &Id3v1Tag) -> Id3v1TagReport {
+    Id3v1TagReport {
+        offset: tag.offset,
+        title: tag.title.clone(),
+        artist: tag.artist.clone(),
+        album: tag.album.clone(),
+        year: tag.year.clone(),
+        comment: tag.comment.clone(),
+        track: tag.track,
+        genre: tag.genre,
+        extended_offset: tag.extended_offset,
+        hidden_fields: tag.hidden_bytes.iter().map(|(f, _)| f.clone()).collect(),
+    }
+}
+
+// Data hidden between ID3v2 tags is saved to the payload store
+fn tag_gap_reports(
+    file_path: &Path,
+    id3_data: &Id3Data,
+    settings: &Settings,
+) -> Vec<Id3TagGapReport> {
+    let store = settings.payload_store();
+    let fname = artifact_stem(file_path);
+    let mut gaps = Vec::new();
+    for (idx, gap) in id3_data.tag_gaps.iter().enumerate() {
+        let mut gap_report = Id3TagGapReport {
+            offset: gap.offset,
+            size_bytes: gap.data.len(),
+            classification: gap.classification.clone(),
+            file: None,
+            sha256: None,
+        };
+        let extension = shared::infer()
+            .get(&gap.data)
+            .map(|kind| kind.extension().to_string())
+            .unwrap_or_else(|| "bin".to_string());
+        let reference = ObjectReference {
+            source: file_path.to_string_lossy().to_string(),
+            offset: gap.offset,
+            description: "Data between ID3v2 tags".to_string(),
+            name: format!("{}_id3_gap_{}.{}", fname, idx, extension),
+        };
+        match store.put(&gap.data, &extension, reference) {
+            Ok(object) => {
+                let path = store.path(&object);
+                println!("Data between ID3v2 tags saved to {}", path.display());
+                gap_report.file = Some(path.to_string_lossy().to_string());
+                gap_report.sha256 = Some(object.sha256);
+            }
+            Err(e) => log::error!("Failed to save data between ID3v2 tags: {}", e),
+        }
+        gaps.push(gap_report);
+    }
+    gaps
+}
+
+// Items of the APEv2 and Lyrics3 tags at the end of the file; flagged values are saved to the
+// payload store. None when there are no such tags.
+fn trailing_tags_report(file_path: &Path, settings: &Settings) -> Option<TrailingTagsReport> {
+    let data = std::fs::read(file_path).ok()?;
+    let analysis = TrailingTagAnalyzer::analyze(data).ok()?;
+
+    println!("\n=== APEv2 / Lyrics3 Tag Analysis ===");
+    let mut tags = Vec::new();
+    for tag in &analysis.tags {
+        println!(
+            "{} tag at offset {} ({} bytes, {} items)",
+            tag.kind,
+            tag.offset,
+            tag.size,
+            tag.items.len()
+        );
+        for finding in &tag.findings {
+            println!("⚠️  {}: {}", tag.kind, finding);
+        }
+        let kind = tag.kind.to_string();
+        let items = tag
+            .items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| tag_item_report(file_path, &kind, idx, item, settings))
+            .collect();
+        tags.push(TrailingTagReport {
+            kind,
+            offset: tag.offset,
+            size_bytes: tag.size,
+            items,
+            findings: tag.findings.clone(),
+        });
+    }
+
+    Some(TrailingTagsReport {
+        is_suspicious: analysis.suspicious,
+        tags,
+    })
+}
+
+// Items of the iTunes-style item list (ilst) of an MP4 or M4A; flagged values are saved to the
+// payload store. None when the file has no item list.
+fn mp4_tags_report(file_path: &Path, settings: &Settings) -> Option<Mp4TagsReport> {
+    let data = std::fs::read(file_path).ok()?;
+    let analysis = Mp4TagAnalyzer::analyze(data).ok()?;
+
+    println!("\n=== MP4 Metadata (ilst) Analysis ===");
+    println!(
+        "Item list at offset {} ({} bytes, {} items)",
+        analysis.offset,
+        analysis.size,
+        analysis.items.len()
+    );
+    for finding in &analysis.findings {
+        println!("⚠️  ilst: {}", finding);
+    }
+    let items = analysis
+        .items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| tag_item_report(file_path, "ilst", idx, item, settings))
+        .collect();
+
+    Some(Mp4TagsReport {
+        is_suspicious: analysis.suspicious,
+        offset: analysis.offset,
+        size_bytes: analysis.size,
+        items,
+        findings: analysis.findings,
+    })
+}
+
+// Report of one tag item, printing its findings and saving the value of a flagged one
+fn tag_item_report(
+    file_path: &Path,
+    tag: &str,
+    idx: usize,
+    item: &TagItem,
+    settings: &Settings,
+) -> TagItemReport {
+    let mut report = TagItemReport {
+        key: item.key.clone(),
+        offset: item.offset,
+        size_bytes: item.value.len(),
+        binary: item.binary,
+        classification: item.classification.clone(),
+        findings: item.findings.clone(),
+        file: None,
+        sha256: None,
+    };
+    for finding in &item.findings {
+        println!("⚠️  {} {}: {}", tag, item.key, finding);
+    }
+    if item.findings.is_empty() {
+        return report;
+    }
+
+    let store = settings.payload_store();
+    let extension = shared::infer()
+        .get(&item.value)
+        .map(|kind| kind.extension().to_string())
+        .unwrap_or_else(|| "bin".to_string());
+    let reference = ObjectReference {
+        source: file_path.to_string_lossy().to_string(),
+        offset: item.offset,
+        description: format!("{} item {}", tag, item.key),
+        name: format!(
+            "{}_{}_{}.{}",
+            artifact_stem(file_path),
+            tag.to_lowercase(),
+            idx,
+            extension
+        ),
+    };
+    match store.put(&item.value, &extension, reference) {
+        Ok(object) => {
+            let path = store.path(&object);
+            println!("    Saved to {}", path.display());
+            report.file = Some(path.to_string_lossy().to_string());
+            report.sha256 = Some(object.sha256);
+        }
+        Err(e) => log::error!("Failed to save {} item: {}", tag, e),
+    }
+    report
+}
+
+fn bpcs_report(bpcs: &BpcsAnalysis, overlay: Option<String>) -> BpcsReport {
+    BpcsReport {
+        is_suspicious: bpcs.suspicious,
+        block_size: bpcs.block_size,
+        noise_planes: bpcs.noise_planes,
+        planes: bpcs
+            .planes
+            .iter()
+            .map(|plane| BpcsPlaneReport {
+                channel: plane.channel.clone(),
+                plane: plane.plane,
+                complex_blocks: plane.complex_blocks,
+                simple_ratio: plane.simple_ratio,
+                mean_complexity: plane.mean_complexity,
+                transition_ratio: plane.transition_ratio,
+                noise_like: plane.noise_like,
+            })
+            .collect(),
+        flagged_blocks: bpcs.flagged_regions.len(),
+        overlay,
+    }
+}
+
+fn calibration_report(calibration: CalibrationAnalysis) -> CalibrationReport {
+    CalibrationReport {
+        is_suspicious: calibration.suspicious,
+        blocks: calibration.blocks,
+        estimated_change_rate: calibration.estimated_change_rate,
+        divergence: calibration.divergence,
+        modes: calibration
+            .modes
+            .into_iter()
+            .map(|mode| CalibrationModeReport {
+                row: mode.mode.0,
+                column: mode.mode.1,
+                observed: mode.observed,
+                reference: mode.reference,
+                divergence: mode.divergence,
+                change_rate: mode.change_rate,
+            })
+            .collect(),
+    }
+}
+
+fn gif_report(gif: GifExtensionAnalysis) -> GifReport {
+    GifReport {
+        version: gif.version,
+        frame_count: gif.frame_count,
+        comments: gif
+            .comments
+            .into_iter()
+            .map(|comment| GifCommentReport {
+                offset: comment.offset,
+                size: comment.size,
+                preview: comment.preview,
+                base64: comment.base64,
+                decoded_size: comment.decoded.len(),
+                classification: comment.classification,
+            })
+            .collect(),
+        applications: gif
+            .applications
+            .into_iter()
+            .map(|app| GifApplicationReport {
+                offset: app.offset,
+                identifier: app.identifier,
+                size: app.size,
+                known: app.known,
+                classification: app.classification,
+            })
+            .collect(),
+        plain_text_blocks: gif.plain_text_blocks,
+        trailing_bytes: gif.trailing_bytes,
+        structure_anomalies: gif.anomalies,
+    }
+}
+
+// LSB verdict against clean covers of the same format and content class where there is a
+// baseline for them, the fixed thresholds otherwise
+fn lsb_verdict(
+    lsb: &LsbAnalysis,
+    content: Option<&ContentClassification>,
+    deviation: Option<&LsbDeviation>,
+    settings: &Settings,
+) -> bool {
+    let thresholds = &settings.thresholds;
+    match (content, deviation) {
+        (Some(content), Some(deviation)) => deviation.is_suspicious_for(
+            content,
+            thresholds.calibration_sigma,
+            thresholds.synthetic_lsb_noise,
+        ),
+        (Some(content), None) => lsb.is_suspicious_for(
+            content,
+            thresholds.lsb_chi_square,
+            thresholds.lsb_entropy,
+            thresholds.synthetic_lsb_noise,
+        ),
+        (None, _) => lsb.is_suspicious_at(thresholds.lsb_chi_square, thresholds.lsb_entropy),
+    }
+}
+
+fn lsb_baseline_report(deviation: &LsbDeviation, sigma: f64) -> LsbBaselineReport {
+    LsbBaselineReport {
+        format: deviation.baseline.format.to_string(),
+        content: deviation.baseline.content.to_string(),
+        sigma,
+        channels: deviation
+            .channels
+            .iter()
+            .map(|channel| LsbChannelDeviation {
+                channel_name: channel.channel.clone(),
+                chi_square_per_pair: channel.chi_square_per_pair,
+                chi_square_z: channel.chi_square_z,
+                entropy_z: channel.entropy_z,
+            })
+            .collect(),
+    }
+}
+
+fn content_class_report(content: &ContentClassification) -> ContentClassReport {
+    ContentClassReport {
+        class: content.class.to_string(),
+        flat_ratio: content.flat_ratio,
+        unique_colors: content.unique_colors,
+        flat_lsb_noise: content.flat_lsb_noise,
+    }
+}
+
+fn png_text_report(png_text: PngTextAnalysis) -> PngTextReport {
+    PngTextReport {
+        chunks: png_text
+            .chunks
+            .into_iter()
+            .map(|chunk| PngTextChunkReport {
+                offset: chunk.offset,
+                chunk_type: chunk.chunk_type,
+                keyword: chunk.keyword,
+                keyword_kind: keyword_kind_name(chunk.keyword_kind).to_string(),
+                language: chunk.language,
+                compressed: chunk.compressed,
+                size: chunk.size,
+                preview: chunk.preview,
+                encoding: chunk.encoding,
+                decoded_size: chunk.decoded.len(),
+                classification: chunk.classification,
+            })
+            .collect(),
+        duplicate_keywords: png_text.duplicate_keywords,
+        anomalies: png_text.anomalies,
+    }
+}
+
+fn keyword_kind_name(kind: KeywordKind) -> &'static str {
+    match kind {
+        KeywordKind::Standard => "standard",
+        KeywordKind::WellKnown => "well_known",
+        KeywordKind::Unknown => "unknown",
+        KeywordKind::Invalid => "invalid",
+    }
+}
+
+// Shade the flagged regions on a copy of the image and save it as an artifact
+fn save_overlay(
+    image: &image::DynamicImage,
+    regions: &[FlaggedRegion],
+    output_file: &Path,
+) -> Option<String> {
+    match render_overlay(&image.to_rgba8(), regions).save(output_file) {
+        Ok(()) => {
+            println!("Flagged regions overlay saved to {}", output_file.display());
+            Some(output_file.to_string_lossy().to_string())
+        }
+        Err(e) => {
+            log::error!("Failed to save overlay: {}", e);
+            None
+        }
+    }
+}
+
+// Save an ID3 picture to the payload store and run the magic bytes, EXIF and LSB analyzers
+// on it
+fn scan_embedded_picture(
+    picture: &PictureInfo,
+    reference: ObjectReference,
+    settings: &Settings,
+    custom_signatures: &[CustomSignature],
+) -> Id3PictureReport {
+    let mut picture_report = Id3PictureReport {
+        picture_type: picture.picture_type.clone(),
+        mime_type: picture.mime_type.clone(),
+        description: picture.description.clone(),
+        size_bytes: picture.data_size,
+        offset: picture.offset,
+        file: None,
+        sha256: None,
+        also_carved_from: Vec::new(),
+        magic_bytes_analysis: None,
+        exif_metadata: None,
+        lsb_analysis: None,
+        is_suspicious: false,
+    };
+
+    let store = settings.payload_store();
+    let source = reference.source.clone();
+    let object = match store.put(&picture.data, picture.extension(), reference) {
+        Ok(object) => object,
+        Err(e) => {
+            log::error!("Failed to save embedded picture: {}", e);
+            return picture_report;
+        }
+    };
+    let picture_file = &store.path(&object);
+    picture_report.file = Some(picture_file.to_string_lossy().to_string());
+    picture_report.also_carved_from = object.other_sources(&source);
+    picture_report.sha256 = Some(object.sha256);
+
+    if settings.analyzers.magic_bytes {
+        match MagicBytesAnalyzerWithPath::new(picture_file)
+            .with_custom_signatures(custom_signatures)
+            .with_strictness(settings.magic_bytes.strictness)
+            .analyze()
+        {
+            Ok(analysis) => {
+                picture_report.magic_bytes_analysis = Some(magic_bytes_report(&analysis))
+            }
+            Err(e) => log::warn!("Magic bytes analysis of embedded picture failed: {}", e),
+        }
+    }
+
+    if settings.analyzers.exif {
+        // Most cover art carries no EXIF block at all
+        if let Ok(exif_data) = ExifAnalyzerWithPath::new(picture_file).analyze() {
+            picture_report.exif_metadata = Some(exif_report(&exif_data));
+        }
+    }
+
+    if settings.analyzers.lsb {
+        let lsb_result = ImageParser::parse_path(&picture_file)
+            .map_err(|e| e.to_string())
+            .and_then(|image| {
+                let (color_model, lsb_result) =
+                    match ImageParser::parse_native_jpeg(picture_file).ok().flatten() {
+                        Some(native) => (
+                            native.color_model,
+                            LsbAnalyzer::analyze_interleaved(
+                                &native.data,
+                                native.color_model.channel_names(),
+                            ),
+                        ),
+                        None => (
+                            ImageParser::color_model(&image),
+                            LsbAnalyzer::analyze(image),
+                        ),
+                    };
+                lsb_result
+                    .map(|analysis| (color_model, analysis))
+                    .map_err(|e| e.to_string())
+            });
+        match lsb_result {
+            Ok((color_model, lsb_analysis)) => {
+                let suspicious = lsb_analysis.is_suspicious_at(
+                    settings.thresholds.lsb_chi_square,
+                    settings.thresholds.lsb_entropy,
+                );
+                picture_report.lsb_analysis = Some(LsbReport {
+                    is_suspicious: suspicious,
+                    bit_depth: lsb_analysis.bit_depth,
+                    color_model: color_model.to_string(),
+                    channels: lsb_analysis
+                        .channel_names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| LsbChannelAnalysis {
+                            channel_name: name.clone(),
+                            chi_square_score: lsb_analysis.chi_square_scores[i],
+                            entropy_score: lsb_analysis.entropy_scores[i],
+                        })
+                        .collect(),
+                    content: None,
+                    baseline: None,
+                    output_files: Vec::new(),
+                });
+            }
+            Err(e) => log::warn!("LSB analysis of embedded picture failed: {}", e),
+        }
+    }
+
+    picture_report.is_suspicious = picture_report
+        .magic_bytes_analysis
+        .as_ref()
+        .is_some_and(|magic| magic.has_suspicious_data)
+        || picture_report
+            .exif_metadata
+            .as_ref()
+            .is_some_and(|exif| !exif.suspicious_fields.is_empty())
+        || picture_report
+            .lsb_analysis
+            .as_ref()
+            .is_some_and(|lsb| lsb.is_suspicious);
+
+    picture_report
+}
+
+// Macros and embedded objects of a legacy or OOXML Office document. Macro source and embedded
+// objects are saved to the payload store, and each object is put through the magic bytes
+// analyzer and, when it is an Office document itself, opened in turn. None for files that
+// aren't Office documents.
+fn ole_report(
+    file_path: &Path,
+    settings: &Settings,
+    custom_signatures: &[CustomSignature],
+) -> Option<OleReport> {
+    let data = std::fs::read(file_path).ok()?;
+    if !is_office_document(&data) {
+        return None;
+    }
+    let analysis = match OfficeAnalyzer::analyze(data) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            log::error!("Error reading Office document structure: {}", e);
+            return None;
+        }
+    };
+
+    println!("\n--- Office Macros and Objects ---");
+    let source = file_path.to_string_lossy().to_string();
+    Some(office_section(
+        &analysis,
+        &source,
+        &artifact_stem(file_path),
+        "",
+        settings,
+        custom_signatures,
+    ))
+}
+
+// One document of ole_report; `stem` names its artifacts and `indent` marks how deeply it is
+// nested in the scanned file
+fn office_section(
+    analysis: &OfficeAnalysis,
+    source: &str,
+    stem: &str,
+    indent: &str,
+    settings: &Settings,
+    custom_signatures: &[CustomSignature],
+) -> OleReport {
+    if analysis.format == OfficeFormat::Ole2 {
+        println!(
+            "{}{} storages, {} streams",
+            indent,
+            analysis.entries.iter().filter(|e| e.is_storage).count(),
+            analysis.entries.iter().filter(|e| !e.is_storage).count()
+        );
+    }
+    for entry in analysis.unusual_entries() {
+        println!(
+            "{}⚠️  {} ({} bytes): {}",
+            indent,
+            entry.path,
+            entry.size,
+            entry.unusual.as_deref().unwrap_or_default()
+        );
+    }
+
+    let store = settings.payload_store();
+    let mut macros = Vec::new();
+    for module in &analysis.macros {
+        println!(
+            "{}⚠️  VBA module {} in {}",
+            indent, module.name, module.stream
+        );
+        if !module.auto_exec.is_empty() {
+            println!(
+                "{}    Runs automatically through {}",
+                indent,
+                module.auto_exec.join(", ")
+            );
+        }
+        let mut macro_report = OleMacroReport {
+            module: module.name.clone(),
+            stream: module.stream.clone(),
+            source_bytes: module.source.as_ref().map(|source| source.len()),
+            auto_exec: module.auto_exec.clone(),
+            file: None,
+            sha256: None,
+        };
+        if let Some(code) = &module.source {
+            let module_name: String = module
+                .name
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            let reference = ObjectReference {
+                source: source.to_string(),
+                offset: 0,
+                description: format!("VBA module {}", module.name),
+                name: format!("{}_vba_{}.vba", stem, module_name),
+            };
+            match store.put(code.as_bytes(), "vba", reference) {
+                Ok(object) => {
+                    let path = store.path(&object);
+                    println!("{}    Source saved to {}", indent, path.display());
+                    macro_report.file = Some(path.to_string_lossy().to_string());
+                    macro_report.sha256 = Some(object.sha256);
+                }
+                Err(e) => log::error!("Failed to save VBA source: {}", e),
+            }
+        }
+        macros.push(macro_report);
+    }
+
+    let mut embedded_objects = Vec::new();
+    for (idx, OfficeObject { object, nested }) in analysis.embedded_objects.iter().enumerate() {
+        match &object.file_name {
+            Some(name) => println!(
+                "{}Embedded object {} ({} bytes, {}) packaged as {:?}",
+                indent,
+                object.stream,
+                object.data.len(),
+                object.classification,
+                name
+            ),
+            None => println!(
+                "{}Embedded object {} ({} bytes, {})",
+                indent,
+                object.stream,
+                object.data.len(),
+                object.classification
+            ),
+        }
+        let mut object_report = OleObjectReport {
+            stream: object.stream.clone(),
+            file_name: object.file_name.clone(),
+            size_bytes: object.data.len(),
+            classification: object.classification.clone(),
+            executable: object.is_executable(),
+            file: None,
+            sha256: None,
+            also_carved_from: Vec::new(),
+            magic_bytes_analysis: None,
+            nested: None,
+        };
+        if object_report.executable {
+            println!("{}    ⚠️  The object is an executable", indent);
+        }
+        let object_stem = format!("{}_ole_{}", stem, idx);
+        if let Some(nested) = nested {
+            object_report.nested = Some(Box::new(office_section(
+                nested,
+                source,
+                &object_stem,
+                &format!("{}    ", indent),
+                settings,
+                custom_signatures,
+            )));
+        }
+
+        let extension = object.extension();
+        let reference = ObjectReference {
+            source: source.to_string(),
+            offset: 0,
+            description: format!("OLE object {}", object.stream),
+            name: format!("{}.{}", object_stem, extension),
+        };
+        let object_file = match store.put(&object.data, &extension, reference) {
+            Ok(stored) => {
+                let path = store.path(&stored);
+                println!("{}    Saved to {}", indent, path.display());
+                object_report.also_carved_from = stored.other_sources(source);
+                object_report.sha256 = Some(stored.sha256);
+                path
+            }
+            Err(e) => {
+                log::error!("Failed to save embedded object: {}", e);
+                embedded_objects.push(object_report);
+                continue;
+            }
+        };
+        object_report.file = Some(object_file.to_string_lossy().to_string());
+
+        if settings.analyzers.magic_bytes {
+            match MagicBytesAnalyzerWithPath::new(&object_file)
+                .with_custom_signatures(custom_signatures)
+                .with_strictness(settings.magic_bytes.strictness)
+                .analyze()
+            {
+                Ok(magic) => {
+                    if magic.has_suspicious_data {
+                        println!("{}    ⚠️  Suspicious data inside the object", indent);
+                    }
+                    object_report.magic_bytes_analysis = Some(magic_bytes_report(&magic));
+                }
+                Err(e) => log::warn!("Magic bytes analysis of embedded object failed: {}", e),
+            }
+        }
+        embedded_objects.push(object_report);
+    }
+
+    OleReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious
+            || embedded_objects.iter().any(|o| {
+                o.magic_bytes_analysis
+                    .as_ref()
+                    .is_some_and(|magic| magic.has_suspicious_data)
+                    || o.nested.as_ref().is_some_and(|nested| nested.is_suspicious)
+            }),
+        entries: analysis
+            .entries
+            .iter()
+            .map(|e| OleEntryReport {
+                path: e.path.clone(),
+                is_storage: e.is_storage,
+                size_bytes: e.size,
+                unusual: e.unusual.clone(),
+            })
+            .collect(),
+        macros,
+        embedded_objects,
+    }
+}
+
+// Members, packaging and chapters of an EPUB or CHM ebook. Members the package doesn't account
+// for are saved to the payload store. None for files that aren't ebooks.
+fn ebook_report(file_path: &Path, settings: &Settings) -> Option<EbookReport> {
+    let data = std::fs::read(file_path).ok()?;
+    if !is_ebook(&data) {
+        return None;
+    }
+    let analysis = match EbookAnalyzer::analyze(data) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            log::error!("Error reading ebook structure: {}", e);
+            return None;
+        }
+    };
+
+    println!("\n--- {} Package ---", analysis.format);
+    println!(
+        "{} members, {} chapters",
+        analysis.members.len(),
+        analysis.chapters.len() + analysis.unanalyzed_chapters
+    );
+    if analysis.unanalyzed_chapters > 0 {
+        println!(
+            "{} compressed pages not analyzed",
+            analysis.unanalyzed_chapters
+        );
+    }
+    for anomaly in &analysis.anomalies {
+        println!("⚠️  {}", anomaly);
+    }
+
+    let store = settings.payload_store();
+    let source = file_path.to_string_lossy().to_string();
+    let fname = artifact_stem(file_path);
+    let mut members = Vec::new();
+    for (idx, member) in analysis.members.iter().enumerate() {
+        let mut member_report = EbookMemberReport {
+            path: member.path.clone(),
+            size_bytes: member.size,
+            compressed: member.compressed,
+            declared: member.declared,
+            media_type: member.media_type.clone(),
+            classification: member.classification.clone(),
+            executable: member.executable,
+            file: None,
+            sha256: None,
+        };
+        if !member.declared {
+            println!(
+                "⚠️  {} ({} bytes) isn't listed in the package",
+                member.path, member.size
+            );
+        }
+        if let Some(data) = &member.data {
+            let extension = shared::infer()
+                .get(data)
+                .map(|kind| kind.extension().to_string())
+                .unwrap_or_else(|| "bin".to_string());
+            let reference = ObjectReference {
+                source: source.clone(),
+                offset: 0,
+                description: format!("Ebook member {}", member.path),
+                name: format!("{}_ebook_{}.{}", fname, idx, extension),
+            };
+            match store.put(data, &extension, reference) {
+                Ok(object) => {
+                    let path = store.path(&object);
+                    println!("    Saved to {}", path.display());
+                    member_report.file = Some(path.to_string_lossy().to_string());
+                    member_report.sha256 = Some(object.sha256);
+                }
+                Err(e) => log::error!("Failed to save ebook member: {}", e),
+            }
+        }
+        members.push(member_report);
+    }
+
+    let chapters: Vec<EbookChapterReport> =
+        analysis.chapters.iter().map(ebook_chapter_report).collect();
+    for chapter in chapters.iter().filter(|c| c.is_suspicious) {
+        println!("⚠️  Chapter {}:", chapter.path);
+        if chapter.zero_width_chars > 0 {
+            match &chapter.zero_width_text {
+                Some(text) => println!(
+                    "    {} zero-width characters reading {:?}",
+                    chapter.zero_width_chars, text
+                ),
+                None => println!("    {} zero-width characters", chapter.zero_width_chars),
+            }
+        }
+        if chapter.whitespace_lines > 0 {
+            println!(
+                "    {} lines end in mixed spaces and tabs",
+                chapter.whitespace_lines
+            );
+        }
+        if !chapter.homoglyph_words.is_empty() {
+            println!(
+                "    Lookalike letters in {}",
+                chapter.homoglyph_words.join(", ")
+            );
+        }
+        if chapter.hidden_elements > 0 {
+            println!("    {} hidden elements", chapter.hidden_elements);
+        }
+    }
+
+    Some(EbookReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        members,
+        anomalies: analysis.anomalies.clone(),
+        chapters,
+        unanalyzed_chapters: analysis.unanalyzed_chapters,
+    })
+}
+
+fn ebook_chapter_report(chapter: &EbookChapter) -> EbookChapterReport {
+    let payload = chapter.text.zero_width_payload.as_ref();
+    EbookChapterReport {
+        path: chapter.path.clone(),
+        is_suspicious: chapter.suspicious,
+        zero_width_chars: chapter.text.zero_width_chars,
+        zero_width_text: payload.and_then(|p| p.text()),
+        zero_width_payload: payload
+            .filter(|p| p.text().is_none())
+            .map(|p| p.classification.clone()),
+        whitespace_lines: chapter.text.whitespace_lines,
+        homoglyph_words: chapter.text.homoglyph_words.clone(),
+        hidden_elements: chapter.hidden_elements,
+    }
+}
+
+// Buffers, chunks and records of a glTF, GLB or STL model. None for other files.
+fn model_report(file_path: &Path, settings: &Settings) -> Option<ModelReport> {
+    let data = std::fs::read(file_path).ok()?;
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let format = model_format(&data, extension)?;
+    let analysis = match ModelAnalyzer::analyze((data, format)) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            log::error!("Error reading model structure: {}", e);
+            return None;
+        }
+    };
+
+    println!("\n--- {} Model ---", analysis.format);
+    match analysis.format {
+        ModelFormat::Stl => println!(
+            "{} triangles, {} with attribute bytes set",
+            analysis.triangles, analysis.attributed_triangles
+        ),
+        _ => println!("{} buffers", analysis.buffers),
+    }
+    for uri in &analysis.external_buffers {
+        println!("External buffer not analyzed: {}", uri);
+    }
+    for anomaly in &analysis.anomalies {
+        println!("⚠️  {}", anomaly);
+    }
+
+    let store = settings.payload_store();
+    let source = file_path.to_string_lossy().to_string();
+    let fname = artifact_stem(file_path);
+    let mut regions = Vec::new();
+    for (idx, region) in analysis.regions.iter().enumerate() {
+        let text = (region.classification == "text")
+            .then(|| String::from_utf8_lossy(&region.data).to_string());
+        match &text {
+            Some(text) => println!("⚠️  {}: text {:?}", region.location, text),
+            None => println!(
+                "⚠️  {}: {} bytes, {}",
+                region.location,
+                region.data.len(),
+                region.classification
+            ),
+        }
+        let mut region_report = ModelRegionReport {
+            location: region.location.clone(),
+            offset: region.offset,
+            size_bytes: region.data.len(),
+            classification: region.classification.clone(),
+            text,
+            file: None,
+            sha256: None,
+        };
+        let extension = shared::infer()
+            .get(&region.data)
+            .map(|kind| kind.extension().to_string())
+            .unwrap_or_else(|| "bin".to_string());
+        let reference = ObjectReference {
+            source: source.clone(),
+            offset: region.offset.unwrap_or(0) as usize,
+            description: format!("{} {}", analysis.format, region.location),
+            name: format!("{}_model_{}.{}", fname, idx, extension),
+        };
+        match store.put(&region.data, &extension, reference) {
+            Ok(object) => {
+                let path = store.path(&object);
+                println!("    Saved to {}", path.display());
+                region_report.file = Some(path.to_string_lossy().to_string());
+                region_report.sha256 = Some(object.sha256);
+            }
+            Err(e) => log::error!("Failed to save model region: {}", e),
+        }
+        regions.push(region_report);
+    }
+
+    Some(ModelReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        triangles: analysis.triangles,
+        buffers: analysis.buffers,
+        anomalies: analysis.anomalies.clone(),
+        regions,
+        attributed_triangles: analysis.attributed_triangles,
+        external_buffers: analysis.external_buffers.clone(),
+    })
+}
+
+// Comments, keys and values of a JSON, XML or YAML document. None for other files.
+fn structured_report(
+    file_path: &Path,
+    text: &str,
+    settings: &Settings,
+) -> Option<StructuredTextReport> {
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let format = structured_format(text, extension)?;
+    let analysis = match StructuredTextAnalyzer::analyze((text.to_string(), format)) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            log::error!("Error reading {} structure: {}", format, e);
+            return None;
+        }
+    };
+
+    println!("\n--- {} Structure ---", analysis.format);
+    println!("{} values, {} comments", analysis.values, analysis.comments);
+
+    let store = settings.payload_store();
+    let source = file_path.to_string_lossy().to_string();
+    let fname = artifact_stem(file_path);
+    let mut findings = Vec::new();
+    for (idx, finding) in analysis.findings.iter().enumerate() {
+        println!(
+            "⚠️  {} at {}: {}",
+            finding.kind, finding.location, finding.preview
+        );
+        let mut finding_report = StructuredFindingReport {
+            kind: finding.kind.to_string(),
+            location: finding.location.clone(),
+            length: finding.length,
+            classification: finding.classification.clone(),
+            preview: finding.preview.clone(),
+            file: None,
+            sha256: None,
+        };
+        if let Some(data) = &finding.decoded {
+            let extension = shared::infer()
+                .get(data)
+                .map(|kind| kind.extension().to_string())
+                .unwrap_or_else(|| "bin".to_string());
+            let reference = ObjectReference {
+                source: source.clone(),
+                offset: 0,
+                description: format!(
+                    "{} {} at {}",
+                    analysis.format, finding.kind, finding.location
+                ),
+                name: format!("{}_structured_{}.{}", fname, idx, extension),
+            };
+            match store.put(data, &extension, reference) {
+                Ok(object) => {
+                    let path = store.path(&object);
+                    println!("    Saved to {}", path.display());
+                    finding_report.file = Some(path.to_string_lossy().to_string());
+                    finding_report.sha256 = Some(object.sha256);
+                }
+                Err(e) => log::error!("Failed to save decoded value: {}", e),
+            }
+        }
+        findings.push(finding_report);
+    }
+
+    Some(StructuredTextReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        comments: analysis.comments,
+        values: analysis.values,
+        findings,
+    })
+}
+
+// Comments of a source file in a language the analyzer knows. None for other files.
+fn source_code_report(
+    file_path: &Path,
+    text: &str,
+    settings: &Settings,
+) -> Option<SourceCodeReport> {
+    let language = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(language_for)?;
+    let analysis = match SourceCodeAnalyzer::analyze((text.to_string(), language)) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            log::error!("Error reading {} comments: {}", language.name, e);
+            return None;
+        }
+    };
+
+    println!("\n--- {} Source Comments ---", analysis.language);
+    println!(
+        "{} comments, {:.1}% of non-blank characters",
+        analysis.comments,
+        analysis.comment_ratio * 100.0
+    );
+    if analysis.abnormal_ratio {
+        println!("⚠️  File is mostly comment");
+    }
+
+    let store = settings.payload_store();
+    let source = file_path.to_string_lossy().to_string();
+    let fname = artifact_stem(file_path);
+    let mut payloads = Vec::new();
+    for (idx, payload) in analysis.payloads.iter().enumerate() {
+        println!(
+            "⚠️  Comment at lines {}-{} decodes to {}",
+            payload.line, payload.end_line, payload.preview
+        );
+        let mut payload_report = CommentPayloadReport {
+            line: payload.line,
+            end_line: payload.end_line,
+            length: payload.length,
+            classification: payload.classification.clone(),
+            preview: payload.preview.clone(),
+            file: None,
+            sha256: None,
+        };
+        let extension = shared::infer()
+            .get(&payload.data)
+            .map(|kind| kind.extension().to_string())
+            .unwrap_or_else(|| "bin".to_string());
+        let reference = ObjectReference {
+            source: source.clone(),
+            offset: 0,
+            description: format!(
+                "{} comment at lines {}-{}",
+                analysis.language, payload.line, payload.end_line
+            ),
+            name: format!("{}_comment_{}.{}", fname, idx, extension),
+        };
+        match store.put(&payload.data, &extension, reference) {
+            Ok(object) => {
+                let path = store.path(&object);
+                println!("    Saved to {}", path.display());
+                payload_report.file = Some(path.to_string_lossy().to_string());
+                payload_report.sha256 = Some(object.sha256);
+            }
+            Err(e) => log::error!("Failed to save decoded comment: {}", e),
+        }
+        payloads.push(payload_report);
+    }
+
+    Some(SourceCodeReport {
+        language: analysis.language.to_string(),
+        is_suspicious: analysis.suspicious,
+        comments: analysis.comments,
+        comment_chars: analysis.comment_chars,
+        code_chars: analysis.code_chars,
+        comment_ratio: analysis.comment_ratio,
+        abnormal_ratio: analysis.abnormal_ratio,
+        payloads,
+    })
+}
+
+// Enumerate and compare the pages of a multi-page TIFF, ICO or HEIF. None for other
+// formats and for files holding a single, displayed image.
+fn page_report(file_path: &Path, settings: &Settings) -> Option<PagesReport> {
+    let multi = match MultiImageParser::parse_path(&file_path) {
+        Ok(multi) => multi,
+        Err(parsers::multi_image_parser::MultiImageParserError::Unsupported) => return None,
+        Err(e) => {
+            log::error!("Error enumerating sub-images: {}", e);
+            return None;
+        }
+    };
+    if multi.pages.len() < 2 && multi.pages.iter().all(|page| !page.hidden) {
+        return None;
+    }
+
+    let mut pages = Vec::new();
+    let mut decoded = Vec::new();
+    for page in multi.pages {
+        let (error, size) = match page.image {
+            Ok(image) => {
+                let size = (image.width(), image.height());
+                decoded.push((pages.len(), image));
+                (None, Some(size))
+            }
+            Err(e) => (Some(e), None),
+        };
+        pages.push(PageReport {
+            index: page.index,
+            label: page.label,
+            hidden: page.hidden,
+            error,
+            width: size.map(|(w, _)| w),
+            height: size.map(|(_, h)| h),
+            lsb_suspicious: false,
+            histogram_anomalies: false,
+            mean_lsb_entropy: None,
+            chi_square_per_pair: None,
+            edge_density: None,
+            outlier_score: None,
+            outlier: false,
+        });
+    }
+
+    let (positions, images): (Vec<usize>, Vec<_>) = decoded.into_iter().unzip();
+    if !images.is_empty() {
+        match PageAnalyzer::analyze(images) {
+            Ok(analysis) => {
+                for (stats, &position) in analysis.pages.iter().zip(&positions) {
+                    let page = &mut pages[position];
+                    page.lsb_suspicious = stats.frame.is_lsb_suspicious_at(
+                        settings.thresholds.lsb_chi_square,
+                        settings.thresholds.lsb_entropy,
+                    );
+                    page.histogram_anomalies = stats.frame.histogram_anomalies;
+                    page.mean_lsb_entropy = Some(stats.mean_lsb_entropy);
+                    page.chi_square_per_pair = Some(stats.chi_square_per_pair);
+                    page.edge_density = Some(stats.frame.edge_density);
+                    page.outlier_score = stats.outlier_score;
+                    page.outlier = stats.outlier;
+                }
+            }
+            Err(e) => log::error!("Page analysis failed: {}", e),
+        }
+    }
+
+    Some(PagesReport {
+        format: multi.format.to_string(),
+        page_count: pages.len(),
+        outlier_pages: pages
+            .iter()
+            .filter(|p| p.outlier)
+            .map(|p| p.index)
+            .collect(),
+        hidden_pages: pages.iter().filter(|p| p.hidden).map(|p| p.index).collect(),
+        pages,
+    })
+}
+
+// Spectrogram findings of a long recording, one window at a time, each window's image saved
+// as <file>_spectrogram_<n>.png. Also returns the persistent tones of every window.
+fn spectrogram_tiles_report(
+    tiles: &[SpectrogramTile],
+    file_path: &Path,
+    settings: &Settings,
+) -> (SpectrogramReport, Vec<f32>) {
+    let fname = artifact_stem(file_path);
+    let threshold = settings.thresholds.spectrogram_high_frequency_energy;
+    println!(
+        "{} window(s) of {} s analyzed separately",
+        tiles.len(),
+        settings.audio.spectrogram_tile_seconds
+    );
+
+    let mut report = SpectrogramReport {
+        high_frequency_energy: 0.0,
+        hidden_message_detected: false,
+        suspicious_patterns: Vec::new(),
+        output_file: None,
+        tiles: Vec::new(),
+        persistent_tones: Vec::new(),
+    };
+    let mut tones = Vec::new();
+    for (index, tile) in tiles.iter().enumerate() {
+        let label = tile.label();
+        let detected = tile.data.has_hidden_message_at(threshold);
+        if detected {
+            println!(
+                "\n⚠️  {}: high frequency energy {:.4}",
+                label, tile.data.high_frequency_energy
+            );
+            for pattern in &tile.data.suspicious_patterns {
+                println!("  - {}", pattern);
+            }
+        }
+
+        let path = settings.artifact_path(&format!("{}_spectrogram_{:04}.png", fname, index));
+        let output_file = match tile
+            .data
+            .render(settings.audio.spectrogram_colormap)
+            .save(&path)
+        {
+            Ok(()) => Some(path.to_string_lossy().to_string()),
+            Err(e) => {
+                log::warn!("Failed to save {}: {}", path.display(), e);
+                None
+            }
+        };
+
+        report.high_frequency_energy = report
+            .high_frequency_energy
+            .max(tile.data.high_frequency_energy);
+        report.hidden_message_detected |= detected;
+        report
+            .suspicious_patterns
+            .extend(tile.data.suspicious_patterns.iter().cloned());
+        report
+            .persistent_tones
+            .extend(tile.data.persistent_tones.iter().map(tone_report));
+        for tone in tile.data.persistent_tone_frequencies() {
+            if !tones.contains(&tone) {
+                tones.push(tone);
+            }
+        }
+        report.tiles.push(SpectrogramTileReport {
+            start_seconds: tile.start_seconds,
+            end_seconds: tile.end_seconds,
+            high_frequency_energy: tile.data.high_frequency_energy,
+            hidden_message_detected: detected,
+            suspicious_patterns: tile.data.suspicious_patterns.clone(),
+            output_file,
+        });
+    }
+
+    println!(
+        "\nHighest high frequency energy: {:.4}",
+        report.high_frequency_energy
+    );
+    println!(
+        "Hidden message detected: {} ({} of {} windows)",
+        report.hidden_message_detected,
+        report
+            .tiles
+            .iter()
+            .filter(|tile| tile.hidden_message_detected)
+            .count(),
+        tiles.len()
+    );
+    (report, tones)
+}
+
+fn tone_report(tone: &PersistentTone) -> AudioToneReport {
+    AudioToneReport {
+        frequency_hz: tone.frequency_hz,
+        start_seconds: tone.range.start_seconds,
+        end_seconds: tone.range.end_seconds,
+    }
+}
+
+// Print the ultrasonic findings and, with --export-samples, write the shifted band as WAV
+fn ultrasonic_report(
+    ultrasonic: &UltrasonicAnalysis,
+    file_path: &Path,
+    settings: &Settings,
+    export: bool,
+) -> UltrasonicReport {
+    println!(
+        "Band {:.0}-{:.0} Hz holds {:.4} of the energy",
+        ultrasonic.band_low_hz, ultrasonic.band_high_hz, ultrasonic.band_energy_ratio
+    );
+    for tone in &ultrasonic.tones {
+        println!(
+            "  Tone at {:.0} Hz in {:.0}% of tonal frames, {}",
+            tone.frequency_hz,
+            tone.frame_share * 100.0,
+            tone.range
+        );
+    }
+    if let Some(fsk) = &ultrasonic.fsk {
+        println!(
+            "⚠️  FSK signal: mark {:.0} Hz, space {:.0} Hz, {:.1} baud, {} bits",
+            fsk.mark_hz,
+            fsk.space_hz,
+            fsk.baud,
+            fsk.bits.len()
+        );
+        if let Some(text) = fsk.decoded_text() {
+            println!("  Decoded: {:?}", text);
+        }
+    } else if ultrasonic.suspicious {
+        println!("⚠️  Unusual amount of ultrasonic energy");
+    }
+
+    let mut baseband_file = None;
+    if export {
+        let fname = artifact_stem(file_path);
+        let path = settings
+            .artifact_path(&format!("{}_ultrasonic.wav", fname))
+            .to_string_lossy()
+            .to_string();
+        match AudioParser::export_wav(&ultrasonic.baseband, ultrasonic.baseband_rate, &path) {
+            Ok(_) => {
+                println!(
+                    "Band shifted down by {:.0} Hz exported to {}",
+                    ultrasonic.baseband_offset_hz, path
+                );
+                baseband_file = Some(path);
+            }
+            Err(e) => log::error!("Failed to export ultrasonic band: {}", e),
+        }
+    }
+
+    UltrasonicReport {
+        band_low_hz: ultrasonic.band_low_hz,
+        band_high_hz: ultrasonic.band_high_hz,
+        band_energy_ratio: ultrasonic.band_energy_ratio,
+        baseband_rate: ultrasonic.baseband_rate,
+        baseband_offset_hz: ultrasonic.baseband_offset_hz,
+        baseband_file,
+        tones: ultrasonic
+            .tones
+            .iter()
+            .map(|t| UltrasonicToneReport {
+                frequency_hz: t.frequency_hz,
+                frame_share: t.frame_share,
+                start_seconds: t.range.start_seconds,
+                end_seconds: t.range.end_seconds,
+            })
+            .collect(),
+        fsk: ultrasonic.fsk.as_ref().map(fsk_report),
+        is_suspicious: ultrasonic.suspicious,
+    }
+}
+
+// The bits that went into the decoded bytes, as 0s and 1s
+fn bitstream(bits: &[bool]) -> String {
+    bits.iter()
+        .take(MAX_DECODED_BYTES * 8)
+        .map(|&bit| if bit { '1' } else { '0' })
+        .collect()
+}
+
+fn fsk_report(fsk: &FskSignal) -> FskReport {
+    FskReport {
+        mark_hz: fsk.mark_hz,
+        space_hz: fsk.space_hz,
+        baud: fsk.baud,
+        bit_count: fsk.bits.len(),
+        bitstream: bitstream(&fsk.bits),
+        decoded_hex: fsk.decoded.iter().map(|b| format!("{:02x}", b)).collect(),
+        decoded_text: fsk.decoded_text(),
+    }
+}
+
+// Print the residual left after aligning with the reference
+fn differential_report(analysis: &DifferentialAnalysis, reference: &Path) -> DifferentialReport {
+    println!(
+        "Aligned with {} at an offset of {} samples (correlation {:.3}, gain {:.4})",
+        reference.display(),
+        analysis.offset_samples,
+        analysis.alignment_correlation,
+        analysis.gain
+    );
+    if !analysis.aligned() {
+        println!("⚠️  The reference doesn't match this recording; the residual is meaningless");
+    }
+    println!(
+        "Residual: {:.1} dB below the reference, {} of {} samples changed, {:.0}% by one LSB",
+        -analysis.residual_db,
+        analysis.changed_samples,
+        analysis.compared_samples,
+        analysis.lsb_only_share * 100.0
+    );
+    println!(
+        "Residual spectrum: peak at {:.0} Hz, flatness {:.2}",
+        analysis.residual_peak_hz, analysis.residual_flatness
+    );
+    for region in &analysis.regions {
+        println!(
+            "  {:.2}s - {:.2}s: {} samples changed{}",
+            region.start_seconds,
+            region.end_seconds,
+            region.changed_samples,
+            region
+                .estimated_bits
+                .map(|bits| format!(", about {} bits", bits))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(bitrate) = analysis.estimated_bitrate_bps {
+        println!("Estimated payload rate: {:.0} bit/s", bitrate);
+    }
+    if analysis.suspicious {
+        println!("⚠️  Content was injected into the reference recording");
+    }
+    let lsb_payload = analysis.lsb_payload.as_ref().map(lsb_payload_report);
+    match lsb_payload
+        .as_ref()
+        .filter(|payload| payload.is_recognized())
+    {
+        Some(payload) => match &payload.text {
+            Some(text) => println!("  Decoded LSBs: {:?} ({})", text, payload.describe()),
+            None => println!(
+                "  Decoded LSBs: {} ({} bytes), starting {}",
+                payload.describe(),
+                payload.size_bytes,
+                payload.hex_preview
+            ),
+        },
+        None => {
+            if let Some(text) = analysis.lsb_decoded_text() {
+                println!("  Decoded LSBs: {:?}", text);
+            }
+        }
+    }
+
+    DifferentialReport {
+        reference_file: reference.to_string_lossy().to_string(),
+        offset_samples: analysis.offset_samples,
+        alignment_correlation: analysis.alignment_correlation,
+        gain: analysis.gain,
+        compared_samples: analysis.compared_samples,
+        bit_depth: analysis.bit_depth,
+        residual_db: analysis.residual_db,
+        changed_samples: analysis.changed_samples,
+        lsb_only_share: analysis.lsb_only_share,
+        residual_peak_hz: analysis.residual_peak_hz,
+        residual_flatness: analysis.residual_flatness,
+        regions: analysis
+            .regions
+            .iter()
+            .map(|region| ResidualRegionReport {
+                start_seconds: region.start_seconds,
+                end_seconds: region.end_seconds,
+                changed_samples: region.changed_samples,
+                estimated_bits: region.estimated_bits,
+            })
+            .collect(),
+        estimated_bits: analysis.estimated_bits,
+        estimated_bitrate_bps: analysis.estimated_bitrate_bps,
+        lsb_decoded_hex: analysis
+            .lsb_decoded
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+        lsb_decoded_text: analysis.lsb_decoded_text(),
+        lsb_payload,
+        is_suspicious: analysis.suspicious,
+    }
+}
+
+// Characters of a decoded LSB text payload kept in the report
+const MAX_PAYLOAD_TEXT_CHARS: usize = 1024;
+
+fn lsb_payload_report(payload: &PayloadInterpretation) -> LsbPayloadReport {
+    LsbPayloadReport {
+        scheme: payload.scheme.to_string(),
+        bit_order: payload.bit_order.to_string(),
+        length_header: payload.length_header,
+        compression: payload.compression.map(str::to_string),
+        classification: payload.classification.clone(),
+        printable_ratio: payload.printable_ratio,
+        size_bytes: payload.data.len(),
+        text: payload
+            .text()
+            .map(|text| text.chars().take(MAX_PAYLOAD_TEXT_CHARS).collect()),
+        hex_preview: payload
+            .data
+            .iter()
+            .take(MAX_DECODED_BYTES)
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    }
+}
+
+fn scene_report(scene: &Scene) -> SceneReport {
+    SceneReport {
+        start_frame: scene.start_frame,
+        end_frame: scene.end_frame,
+        frames_analyzed: scene.frames_analyzed,
+        frames_skipped: scene.frames_skipped,
+        median_chi_square: scene.median_chi_square,
+        lsb_divergence: scene.lsb_divergence,
+        divergent_frames: scene.divergent_frames.clone(),
+        suspicious: scene.suspicious,
+    }
+}
+
+// Print what each flagged tone carried
+fn demodulation_report(carriers: &[CarrierDemodulation]) -> DemodulationReport {
+    for carrier in carriers {
+        if let Some(fsk) = &carrier.fsk {
+            println!(
+                "⚠️  {:.0} Hz: FSK with {:.0} Hz / {:.0} Hz at {:.1} baud, {} bits",
+                carrier.carrier_hz,
+                fsk.mark_hz,
+                fsk.space_hz,
+                fsk.baud,
+                fsk.bits.len()
+            );
+            if let Some(text) = fsk.decoded_text() {
+                println!("  Decoded: {:?}", text);
+            }
+        }
+        if let Some(psk) = &carrier.psk {
+            println!(
+                "⚠️  {:.0} Hz: BPSK at {:.1} baud, {} bits (constellation {:.2})",
+                carrier.carrier_hz,
+                psk.baud,
+                psk.bits.len(),
+                psk.constellation
+            );
+            if let Some(text) = psk.decoded_text() {
+                println!("  Decoded: {:?}", text);
+            }
+        }
+        if !carrier.recovered() {
+            println!("{:.0} Hz: no keyed data", carrier.carrier_hz);
+        }
+    }
+
+    DemodulationReport {
+        carriers: carriers
+            .iter()
+            .map(|carrier| CarrierReport {
+                carrier_hz: carrier.carrier_hz,
+                fsk: carrier.fsk.as_ref().map(fsk_report),
+                psk: carrier.psk.as_ref().map(|psk| PskReport {
+                    baud: psk.baud,
+                    constellation: psk.constellation,
+                    bit_count: psk.bits.len(),
+                    bitstream: bitstream(&psk.bits),
+                    decoded_hex: psk.decoded.iter().map(|b| format!("{:02x}", b)).collect(),
+                    decoded_text: psk.decoded_text(),
+                }),
+            })
+            .collect(),
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    pretty_env_logger::formatted_builder()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+    let mut args = Args::parse();
+    // `scan` and `extract` only differ from the flags given without a subcommand in where
+    // they come from, so the scan below reads them all from args.scan
+    let mut extract_only = false;
+    match args.command.take() {
+        Some(Command::Scan(scan)) => args.scan = *scan,
+        Some(Command::Extract { file }) => {
+            args.scan = ScanArgs {
+                file: Some(file),
+                extract: true,
+                ..ScanArgs::default()
+            };
+            extract_only = true;
+        }
+        command => args.command = command,
+    }
+    if let Some(input) = args.scan.input.take() {
+        args.scan.file = Some(input);
+    }
+    let mut settings = resolve_settings(&args)?;
+    if extract_only {
+        settings.analyzers = settings.analyzers.triage();
+        settings.plugins.clear();
+    }
+
+    match &args.command {
+        Some(Command::Config { action }) => return run_config_command(action, &args, &settings),
+        Some(Command::Open {
+            package,
+            key,
+            unpack_to,
+            rerun,
+        }) => {
+            return open_evidence(
+                package,
+                key.as_deref(),
+                unpack_to.as_deref(),
+                rerun,
+                &args,
+                &settings,
+            );
+        }
+        Some(Command::Quick {
+            path,
+            width,
+            no_planes,
+        }) => return run_quick(path.as_deref(), *width, *no_planes, &args),
+        Some(Command::Calibrate { clean_dir, path }) => {
+            return calibrate::run(clean_dir, path.as_deref(), &settings);
+        }
+        Some(Command::Case { action }) => return run_case_command(action, &args, &settings),
+        Some(Command::Feedback {
+            action: FeedbackAction::Export { paths, format, to },
+        }) => return export_feedback(paths, *format, to.as_deref(), &settings),
+        Some(Command::Annotate {
+            report,
+            rule,
+            label,
+            comment,
+            analyst,
+        }) => {
+            return annotate_report(
+                report,
+                rule.as_deref(),
+                *label,
+                comment.as_deref(),
+                analyst.as_deref(),
+                &settings,
+            );
+        }
+        Some(Command::Report {
+            action: ReportAction::Show { report, explain },
+        }) => return show_report(report, *explain, args.locale, &settings),
+        Some(Command::Report {
+            action: ReportAction::Convert { report, to, out },
+        }) => return convert_report(report, *to, out.as_deref(), args.locale, &settings),
+        Some(Command::Serve { addr }) => return serve(*addr),
+        Some(Command::Scan(_) | Command::Extract { .. }) | None => {}
+    }
+
+    if args.scan.list_analyzers {
+        plan::print_analyzers(&settings);
+        return Ok(());
+    }
+
+    if let Some(plan_file) = &args.scan.plan {
+        return run_scan_plan(plan_file, &args, &settings);
+    }
+
+    if let Some(image) = &args.scan.disk_image {
+        return run_disk_image(image, &args, &settings);
+    }
+
+    if let Some(capture_file) = &args.scan.pcap {
+        return run_capture(capture_file, &args, &settings);
+    }
+
+    if let Some(dir) = &args.scan.dir {
+        return run_directory(dir, &args, &settings);
+    }
+
+    if let Some(url) = &args.scan.url
+        && settings.profile != Profile::Deep
+    {
+        return scan_url(url);
+    }
+
+    // Kept alive until the scan and any evidence export are done
+    let staged = match &args.scan.inner {
+        Some(inner) => Some(archive_member::stage(
+            inner,
+            settings.limits.max_file_size_mb,
+        )?),
+        None => None,
+    };
+    let downloaded = match &args.scan.url {
+        Some(url) => {
+            let client = remote::client()?;
+            let file = remote::probe(&client, url)?;
+            println!("Downloading {}", url);
+            Some(remote::download(
+                &client,
+                &file,
+                settings.limits.max_file_size_mb,
+            )?)
+        }
+        None => None,
+    };
+    let Some(file) = staged
+        .as_ref()
+        .map(|member| &member.path)
+        .or(downloaded.as_ref().map(|download| &download.path))
+        .or(args.scan.file.as_ref())
+    else {
+        return Err(
+            "No input file given, pass a file to scan, --inner <ARCHIVE!MEMBER>, --url <URL>, --plan <PLAN>, --dir <DIR>, --disk-image <IMAGE> or --pcap <CAPTURE>".into(),
+        );
+    };
+
+    // Read the key up front so a bad key fails before a long scan rather than after it
+    let evidence_key = match &args.scan.evidence_key {
+        Some(key_file) => Some(evidence::load_key(key_file)?),
+        None => None,
+    };
+    // The package takes everything in the output directory, so the scan gets one of its own
+    if args.scan.export_evidence.is_some() {
+        settings.output.dir = evidence::scan_dir(&settings.output.dir, &artifact_stem(file))?;
+    }
+    let report = scan_file(file, &args, &settings)?;
+
+    if let (Some(out), Some(key), Some(report)) = (&args.scan.export_evidence, evidence_key, report)
+    {
+        export_evidence(out, file, &report, &settings, &key)?;
+    }
+    Ok(())
+}
+
+fn export_evidence(
+    out: &Path,
+    file: &Path,
+    report: &SteganalysisReport,
+    settings: &Settings,
+    key: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let artifacts = evidence::collect_artifacts(&settings.output.dir, &settings.output.report);
+    let manifest = evidence::export(out, file, &report.to_json()?, &artifacts, key)?;
+    println!(
+        "📦 Evidence package saved to {} ({} files, manifest signed)",
+        out.display(),
+        manifest.files.len()
+    );
+    Ok(())
+}
+
+fn run_case_command(
+    action: &CaseAction,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        CaseAction::New { case, description } => {
+            let created = Case::create(case, description.clone())?;
+            println!("Case {} created in {}/", created.name, case.display());
+        }
+        CaseAction::Add {
+            case,
+            files,
+            note,
+            evidence_key,
+        } => {
+            let key = match evidence_key {
+                Some(key_file) => Some(evidence::load_key(key_file)?),
+                None => None,
+            };
+            let mut opened = Case::open(case)?;
+            for file in files {
+                let sha256 = evidence::hex(&dedupe::sha256_file(file)?);
+                if let Some(earlier) = opened.find_content(&sha256) {
+                    println!(
+                        "\n{} has the same content as {}, not added again",
+                        file.display(),
+                        earlier.id
+                    );
+                    continue;
+                }
+
+                let id = opened.next_id(file);
+                println!("\n▶ {} as {}", file.display(), id);
+                let mut scan_settings = settings.clone();
+                scan_settings.output.dir = case.join(case::SCANS_DIR).join(&id);
+                scan_settings.output.report = scan_settings.output.dir.join("report.json");
+                std::fs::create_dir_all(&scan_settings.output.dir)?;
+                let Some(report) = scan_file(file, args, &scan_settings)? else {
+                    continue;
+                };
+
+                let evidence = match &key {
+                    Some(key) => {
+                        let package = case.join(case::EVIDENCE_DIR).join(format!("{}.zip", id));
+                        export_evidence(&package, file, &report, &scan_settings, key)?;
+                        Some(format!("{}/{}.zip", case::EVIDENCE_DIR, id))
+                    }
+                    None => None,
+                };
+                opened.scans.push(CaseScan {
+                    id: id.clone(),
+                    file: file.display().to_string(),
+                    sha256,
+                    added: chrono::Utc::now().to_rfc3339(),
+                    report: format!("{}/{}/report.json", case::SCANS_DIR, id),
+                    evidence,
+                    steganography_detected: report.summary.steganography_detected,
+                    confidence_level: report.summary.confidence_level.clone(),
+                    verdict: report.summary.verdict,
+                });
+                if let Some(note) = note {
+                    opened.add_note(note, Some(&id))?;
+                }
+                // Saved after every file, so an interrupted add keeps the scans it finished
+                opened.save(case)?;
+            }
+        }
+        CaseAction::Note { case, text, scan } => {
+            let mut opened = Case::open(case)?;
+            opened.add_note(text, scan.as_deref())?;
+            opened.save(case)?;
+            println!("Note added to case {}", opened.name);
+        }
+        CaseAction::Report { case } => {
+            let report = Case::open(case)?.report(case);
+            let path = case.join(case::CASE_REPORT);
+            std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+
+            println!("Case {}, created {}", report.name, report.created);
+            if let Some(description) = &report.description {
+                println!("{}", description);
+            }
+            println!(
+                "{} scan(s), {} with steganography detected, {} with a payload extracted",
+                report.scan_count, report.detected_count, report.confirmed_count
+            );
+            for entry in &report.scans {
+                let scan = &entry.scan;
+                println!(
+                    "\n  {} {} ({})",
+                    if scan.steganography_detected {
+                        "⚠️ "
+                    } else {
+                        "✅"
+                    },
+                    scan.id,
+                    scan.file
+                );
+                println!(
+                    "     detected: {}, confidence: {}",
+                    scan.steganography_detected, scan.confidence_level
+                );
+                if let Some(e) = &entry.error {
+                    println!("     report unreadable: {}", e);
+                }
+                for indicator in &entry.threat_indicators {
+                    println!("     - {}", indicator);
+                }
+                for payload in &entry.extracted_payloads {
+                    println!(
+                        "     payload: {}{}",
+                        payload.description,
+                        payload
+                            .artifact
+                            .as_ref()
+                            .map(|artifact| format!(" -> {}", artifact))
+                            .unwrap_or_default()
+                    );
+                }
+                for annotation in &entry.annotations {
+                    println!(
+                        "     {}: {}{}",
+                        annotation.rule.as_deref().unwrap_or("scan"),
+                        annotation.label,
+                        annotation
+                            .comment
+                            .as_ref()
+                            .map(|comment| format!(" ({})", comment))
+                            .unwrap_or_default()
+                    );
+                }
+                if let Some(evidence) = &scan.evidence {
+                    println!("     evidence: {}", case.join(evidence).display());
+                }
+                for note in report
+                    .notes
+                    .iter()
+                    .filter(|n| n.scan.as_ref() == Some(&scan.id))
+                {
+                    println!("     note: {}", note.text);
+                }
+            }
+            let case_notes: Vec<_> = report.notes.iter().filter(|n| n.scan.is_none()).collect();
+            if !case_notes.is_empty() {
+                println!("\nNotes:");
+                for note in case_notes {
+                    println!("  - [{}] {}", note.added, note.text);
+                }
+            }
+            println!("\n📄 Case report saved to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn export_feedback(
+    paths: &[PathBuf],
+    format: FeedbackFormat,
+    to: Option<&Path>,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (findings, reports) = feedback::collect(paths, &settings.thresholds)?;
+    match to {
+        Some(path) => feedback::write(&findings, format, &mut std::fs::File::create(path)?)?,
+        None => feedback::write(&findings, format, &mut std::io::stdout().lock())?,
+    }
+
+    let false_positives = findings
+        .iter()
+        .filter(|finding| finding.label == AnnotationLabel::FalsePositive)
+        .count();
+    // Standard output may be the export itself
+    eprintln!(
+        "Exported {} labelled finding(s) from {} report(s): {} true positive(s), {} false positive(s)",
+        findings.len(),
+        reports,
+        findings.len() - false_positives,
+        false_positives
+    );
+    Ok(())
+}
+
+// Add an analyst's annotation to a saved report, or list the annotations it has. Findings are
+// named by the rule IDs the explanations use, worked out again from the stored sections so
+// reports saved without --explain can be annotated too.
+fn annotate_report(
+    report_file: &Path,
+    rule: Option<&str>,
+    label: Option<AnnotationLabel>,
+    comment: Option<&str>,
+    analyst: Option<&str>,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut report: SteganalysisReport =
+        serde_json::from_str(&std::fs::read_to_string(report_file)?)?;
+
+    if label.is_none() && comment.is_none() {
+        if report.annotations.is_empty() {
+            println!("{} has no annotations", report_file.display());
+        }
+        print_annotations(&report.annotations);
+        return Ok(());
+    }
+
+    let mut findings = explain::explain_report(&report, &settings.thresholds, report.locale);
+    findings.extend(report.explanations.iter().cloned());
+    let finding = match rule {
+        Some(rule) => match findings.iter().find(|f| f.rule_id == rule) {
+            Some(finding) => Some(finding),
+            None => {
+                let mut rules: Vec<&str> = findings.iter().map(|f| f.rule_id.as_str()).collect();
+                rules.sort_unstable();
+                rules.dedup();
+                return Err(format!(
+                    "{} has no finding {} (findings: {})",
+                    report_file.display(),
+                    rule,
+                    if rules.is_empty() {
+                        "none".to_string()
+                    } else {
+                        rules.join(", ")
+                    }
+                )
+                .into());
+            }
+        },
+        None => None,
+    };
+
+    let annotation = Annotation {
+        rule: rule.map(str::to_string),
+        label: label.unwrap_or(AnnotationLabel::Comment),
+        comment: comment.map(str::to_string),
+        analyst: analyst.map(str::to_string),
+        added: chrono::Utc::now().to_rfc3339(),
+    };
+    if !annotation.is_complete() {
+        return Err("A comment annotation needs --comment".into());
+    }
+
+    println!(
+        "Annotated {} of {} as {}",
+        rule.unwrap_or("the scan"),
+        report_file.display(),
+        annotation.label
+    );
+    // What the label says about the threshold the finding crossed
+    if let Some(finding) = finding
+        && let (Some(measured), Some(threshold)) = (finding.measured_value, finding.threshold)
+    {
+        println!("  measured {:.4}, threshold {:.4}", measured, threshold);
+    }
+    report.annotations.push(annotation);
+    report.save_to_file(&report_file.to_string_lossy())?;
+    Ok(())
+}
+
+fn print_annotations(annotations: &[Annotation]) {
+    for annotation in annotations {
+        println!(
+            "  - [{}] {}{}{} ({}{})",
+            annotation.rule.as_deref().unwrap_or("scan"),
+            annotation.label,
+            if annotation.comment.is_some() {
+                ": "
+            } else {
+                ""
+            },
+            annotation.comment.as_deref().unwrap_or_default(),
+            annotation.added,
+            annotation
+                .analyst
+                .as_ref()
+                .map(|analyst| format!(", {}", analyst))
+                .unwrap_or_default()
+        );
+    }
+}
+
+// `stegascan report`: a saved report's summary, as the scan printed it
+fn show_report(
+    report_file: &Path,
+    explain: bool,
+    locale: Option<Locale>,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report: SteganalysisReport = serde_json::from_str(&std::fs::read_to_string(report_file)?)?;
+    let locale = locale.unwrap_or(report.locale);
+    println!(
+        "{} ({}, {} bytes), scanned {}",
+        report.file_info.path,
+        report.file_info.detected_type,
+        report.file_info.size_bytes,
+        report.timestamp
+    );
+    if report.modified_during_scan {
+        println!("⚠️  The file changed while it was being scanned");
+    }
+    print_summary(&report, locale);
+
+    if explain {
+        let explanations = explain::report_findings(&report, &settings.thresholds, locale);
+        if !explanations.is_empty() {
+            println!("\n{}", tr(locale, "cli.explanations", &[]));
+            for explanation in &explanations {
+                println!("  - [{}] {}", explanation.rule_id, explanation.description);
+            }
+        }
+    }
+
+    if !report.annotations.is_empty() {
+        println!("\nAnnotations:");
+        print_annotations(&report.annotations);
+    }
+    Ok(())
+}
+
+fn convert_report(
+    report_file: &Path,
+    format: ReportFormat,
+    out: Option<&Path>,
+    locale: Option<Locale>,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report: SteganalysisReport = serde_json::from_str(&std::fs::read_to_string(report_file)?)?;
+    let out = out
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| report_file.with_extension(format.extension()));
+    convert::write(
+        &report,
+        format,
+        &settings.thresholds,
+        locale.unwrap_or(report.locale),
+        &mut std::fs::File::create(&out)?,
+    )?;
+    println!("Converted {} to {}", report_file.display(), out.display());
+    Ok(())
+}
+
+// `stegascan serve`: the API server is its own binary, installed next to this one
+fn serve(addr: Option<SocketAddr>) -> Result<(), Box<dyn std::error::Error>> {
+    let name = format!("stegascan-api{}", std::env::consts::EXE_SUFFIX);
+    let bundled = std::env::current_exe()?.with_file_name(&name);
+    let program = if bundled.is_file() {
+        bundled
+    } else {
+        PathBuf::from(name)
+    };
+
+    let mut command = std::process::Command::new(&program);
+    if let Some(addr) = addr {
+        command.env("STEGASCAN_API_ADDR", addr.to_string());
+    }
+    let status = command
+        .status()
+        .map_err(|e| format!("Could not start {}: {}", program.display(), e))?;
+    if !status.success() {
+        return Err(format!("stegascan-api exited with {}", status).into());
+    }
+    Ok(())
+}
+
+// Check a package against its manifest and signature, unpack it and print its report. The
+// original is only scanned again once the package has checked out.
+fn open_evidence(
+    package_file: &Path,
+    key_file: Option<&Path>,
+    unpack_to: Option<&Path>,
+    rerun: &[String],
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut package = evidence::EvidencePackage::open(package_file)?;
+    println!(
+        "Evidence package {}: {} files, created {} by {}",
+        package_file.display(),
+        package.manifest.files.len(),
+        package.manifest.created,
+        package.manifest.tool
+    );
+
+    match key_file {
+        Some(key_file) => {
+            if !package.verify_signature(&evidence::load_key(key_file)?) {
+                return Err("Manifest signature does not match, the package was altered or the key is wrong".into());
+            }
+            println!("✅ Manifest signature verified");
+        }
+        None => println!("⚠️  No --key given, the manifest signature was not checked"),
+    }
+    let problems = package.check_files();
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("  ❌ {}", problem);
+        }
+        return Err(format!("{} file(s) do not match the manifest", problems.len()).into());
+    }
+    println!("✅ All file hashes match the manifest");
+
+    let dir = match unpack_to {
+        Some(dir) => dir.to_path_buf(),
+        None => settings.artifact_path(
+            &package_file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "evidence".to_string()),
+        ),
+    };
+    package.unpack(&dir)?;
+    println!("Unpacked to {}/", dir.display());
+
+    let report: SteganalysisReport = serde_json::from_slice(&package.read(evidence::REPORT)?)?;
+    print_summary(&report, report.locale);
+    if !report.explanations.is_empty() {
+        println!("\n{}", tr(report.locale, "cli.explanations", &[]));
+        for explanation in &report.explanations {
+            println!("  - [{}] {}", explanation.rule_id, explanation.description);
+        }
+    }
+    println!("\n{}", tr(report.locale, "cli.recommendations", &[]));
+    for recommendation in &report.summary.recommendations {
+        println!("  - {}", recommendation);
+    }
+    let artifacts: Vec<&str> = package
+        .manifest
+        .files
+        .iter()
+        .map(|file| file.path.as_str())
+        .filter(|path| path.starts_with("artifacts/"))
+        .collect();
+    if !artifacts.is_empty() {
+        println!("\nArtifacts:");
+        for artifact in artifacts {
+            println!("  - {}", dir.join(artifact).display());
+        }
+    }
+
+    if rerun.is_empty() {
+        return Ok(());
+    }
+    let mut replay = settings.clone();
+    if !rerun.iter().any(|name| name == "all") {
+        for name in AnalyzerToggles::NAMES {
+            replay.analyzers.set(name, false)?;
+        }
+        for name in rerun {
+            replay.analyzers.set(name, true)?;
+        }
+    }
+    replay.output.dir = dir.join("replay");
+    replay.output.report = replay.output.dir.join("report.json");
+    std::fs::create_dir_all(&replay.output.dir)?;
+    let original = package.unpacked_original(&dir)?;
+    println!("\nRe-running analyzers on {}", package.manifest.original);
+    scan_file(&original, args, &replay)?;
+    Ok(())
+}
+
+// Quick verdict on a file over HTTP from its first and last bytes, see remote.rs
+fn scan_url(url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let client = remote::client()?;
+    let mut file = remote::probe(&client, url)?;
+    println!(
+        "{}: {}, {}, {}",
+        url,
+        file.size
+            .map_or("size unknown".to_string(), |size| format!("{} bytes", size)),
+        file.content_type.as_deref().unwrap_or("no content type"),
+        if file.ranges {
+            "ranges supported"
+        } else {
+            "ranges not advertised"
+        }
+    );
+
+    let pieces = remote::fetch_pieces(&client, &mut file)?;
+    let fetched: usize = pieces.iter().map(|piece| piece.data.len()).sum();
+    println!(
+        "Fetched {} bytes in {} range{}",
+        fetched,
+        pieces.len(),
+        if pieces.len() == 1 { "" } else { "s" }
+    );
+    if file.size.is_some_and(|size| size > fetched as u64) && pieces.len() < 2 {
+        println!("⚠️  The server ignored the Range request, the end of the file was not checked");
+    }
+
+    println!(
+        "{}",
+        quick::verdict_line(url, &remote::findings(&file, &pieces))
+    );
+    println!("Run with --profile deep to download the file and scan it fully");
+    Ok(())
+}
+
+// Run the LSB, WS and channel correlation analyzers on one image and sum them up in a line.
+// A clipboard image is saved to the output directory first, so a finding can be followed
+// up with a full scan of the same pixels.
+fn run_quick(
+    path: Option<&Path>,
+    width: u32,
+    no_planes: bool,
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut quick_args = args.clone();
+    quick_args.profile.get_or_insert(Profile::Fast);
+    let settings = resolve_settings(&quick_args)?;
+
+    let (label, image) = match path {
+        Some(path) => (path.display().to_string(), image::open(path)?),
+        None => {
+            let image = quick::clipboard_image()?;
+            std::fs::create_dir_all(&settings.output.dir)?;
+            let saved = settings.artifact_path(&format!(
+                "clipboard_{}.png",
+                chrono::Local::now().format("%Y%m%d_%H%M%S")
+            ));
+            image.save(&saved)?;
+            (
+                saved.display().to_string(),
+                image::DynamicImage::ImageRgba8(image),
+            )
+        }
+    };
+
+    let lsb_baselines = settings.cover_baselines()?;
+
+    let mut findings = Vec::new();
+    if settings.analyzers.lsb {
+        match LsbAnalyzer::analyze(image.clone()) {
+            Ok(lsb) => {
+                if !no_planes {
+                    let planes: Vec<&image::RgbaImage> = lsb.lsb_planes.iter().collect();
+                    let gaps = 2 * planes.len().saturating_sub(1) as u32;
+                    let columns = width.saturating_sub(gaps) / planes.len().max(1) as u32;
+                    println!("LSB planes ({})", lsb.channel_names.join(", "));
+                    for line in quick::render_side_by_side(&planes, columns) {
+                        println!("{}", line);
+                    }
+                }
+                let content = ContentClassifier::analyze(image.clone())
+                    .ok()
+                    .map(|mut content| {
+                        content.class = content.class_at(settings.thresholds.content_flat_ratio);
+                        content
+                    });
+                // The clipboard image was saved as a PNG
+                let format = path.map_or(CoverFormat::Png, CoverFormat::from_path);
+                let deviation = content
+                    .as_ref()
+                    .and_then(|content| lsb_baselines.deviation(format, content.class, &lsb));
+                findings.push(quick::QuickFinding {
+                    analyzer: "LSB",
+                    suspicious: lsb_verdict(&lsb, content.as_ref(), deviation.as_ref(), &settings),
+                    detail: lsb_detail(&lsb, content.as_ref(), deviation.as_ref()),
+                });
+            }
+            Err(e) => log::warn!("LSB analysis failed: {}", e),
+        }
+    }
+    if settings.analyzers.ws {
+        match WsAnalyzer::analyze(image.clone()) {
+            Ok(ws) => findings.push(quick::QuickFinding {
+                analyzer: "WS",
+                suspicious: ws.is_suspicious_at(settings.thresholds.ws_payload_rate),
+                detail: format!("payload {:.1}%", ws.estimated_payload_rate * 100.0),
+            }),
+            Err(e) => log::warn!("WS analysis failed: {}", e),
+        }
+    }
+    if settings.analyzers.channel_correlation {
+        match ChannelCorrelationAnalyzer::analyze(image) {
+            Ok(correlation) => findings.push(quick::QuickFinding {
+                analyzer: "channel correlation",
+                suspicious: correlation
+                    .is_suspicious_at(settings.thresholds.channel_correlation_collapsed_ratio),
+                detail: format!(
+                    "{:.1}% of blocks collapsed",
+                    correlation.collapsed_ratio * 100.0
+                ),
+            }),
+            Err(e) => log::warn!("Channel correlation analysis failed: {}", e),
+        }
+    }
+
+    println!("{}", quick::verdict_line(&label, &findings));
+    Ok(())
+}
+
+// The measurement the LSB verdict was based on, for the quick verdict line
+fn lsb_detail(
+    lsb: &LsbAnalysis,
+    content: Option<&ContentClassification>,
+    deviation: Option<&LsbDeviation>,
+) -> String {
+    match (content, deviation) {
+        (Some(content), _) if content.class == ContentClass::Synthetic => format!(
+            "synthetic image, {:.2}% LSB noise in flat runs",
+            content.flat_lsb_noise * 100.0
+        ),
+        (_, Some(deviation)) => format!(
+            "{:.1}σ from {}/{} covers",
+            deviation.max_z(),
+            deviation.baseline.format,
+            deviation.baseline.content
+        ),
+        _ => format!(
+            "chi-square {:.2}",
+            lsb.chi_square_scores.iter().cloned().fold(0.0, f64::max)
+        ),
+    }
+}
+
+// How one file of a scan plan ended
+enum PlanOutcome {
+    // None for --dry-run
+    Scanned(Option<SteganalysisReport>),
+    Skipped(String),
+    // Same file as an earlier entry, which holds the actual report
+    Duplicate(DuplicateReference),
+    Failed(String),
+    // Only the triage analyzers ran and found nothing
+    Triaged(SteganalysisReport),
+    // Finished by an earlier run of the batch, according to its checkpoint
+    Resumed(CheckpointEntry),
+}
+
+impl PlanOutcome {
+    // Outcome name, detection, confidence and detail, as flows.json and the checkpoint list them
+    fn status(&self) -> (&'static str, Option<bool>, Option<String>, Option<String>) {
+        match self {
+            PlanOutcome::Scanned(report) => (
+                "scanned",
+                report.as_ref().map(|r| r.summary.steganography_detected),
+                report.as_ref().map(|r| r.summary.confidence_level.clone()),
+                None,
+            ),
+            PlanOutcome::Skipped(reason) => ("skipped", None, None, Some(reason.clone())),
+            PlanOutcome::Duplicate(reference) => (
+                "duplicate",
+                None,
+                None,
+                Some(format!("{} of {}", reference.kind, reference.duplicate_of)),
+            ),
+            PlanOutcome::Failed(e) => ("failed", None, None, Some(e.clone())),
+            PlanOutcome::Triaged(report) => (
+                "triaged",
+                Some(report.summary.steganography_detected),
+                Some(report.summary.confidence_level.clone()),
+                Some("not escalated, the triage analyzers found nothing".to_string()),
+            ),
+            PlanOutcome::Resumed(entry) => (
+                "resumed",
+                entry.steganography_detected,
+                entry.confidence_level.clone(),
+                Some(format!("scanned as {}, see {}", entry.file, entry.report)),
+            ),
+        }
+    }
+}
+
+// Run every scan listed in a plan file
+fn run_scan_plan(
+    plan_file: &Path,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scans = ScanManifest::load(plan_file)?.resolve(plan_file, settings)?;
+    println!("Scan plan {}: {} file(s)", plan_file.display(), scans.len());
+    run_batch(&scans, args, settings)
+}
+
+// Scan every file below a directory that --include and --exclude let through
+fn run_directory(
+    dir: &Path,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files = walker::walk(
+        dir,
+        &args.scan.include,
+        &args.scan.exclude,
+        args.scan.max_depth,
+    )?;
+    println!("Directory {}: {} file(s)", dir.display(), files.len());
+
+    let root = settings.output.dir.join("reports");
+    let scans: Vec<PlannedScan> = files
+        .into_iter()
+        .map(|file| {
+            let relative = file.strip_prefix(dir).unwrap_or(&file);
+            let report = root.join(format!("{}.json", relative.display()));
+            let mut file_settings = settings.clone();
+            file_settings.output.dir = report.parent().unwrap_or(&root).to_path_buf();
+            file_settings.output.report = report;
+            PlannedScan {
+                file,
+                settings: file_settings,
+                via_symlink: false,
+            }
+        })
+        .collect();
+    if !args.scan.dry_run {
+        for scan in &scans {
+            std::fs::create_dir_all(&scan.settings.output.dir)?;
+        }
+    }
+    let outcomes = scan_batch(&scans, args, settings);
+    if args.scan.dry_run {
+        return Ok(());
+    }
+
+    #[derive(Serialize)]
+    struct FileResult<'a> {
+        file: &'a Path,
+        report: &'a Path,
+        // scanned, skipped, duplicate, failed, triaged or resumed
+        outcome: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        steganography_detected: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence_level: Option<String>,
+        #[serde(skip_serializing_if = "<[String]>::is_empty")]
+        threat_indicators: &'a [String],
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    }
+    #[derive(Serialize)]
+    struct BatchReport<'a> {
+        directory: &'a Path,
+        generated_at: String,
+        files: usize,
+        detected: usize,
+        failed: usize,
+        results: Vec<FileResult<'a>>,
+    }
+    let results: Vec<FileResult> = scans
+        .iter()
+        .zip(&outcomes)
+        .map(|(scan, outcome)| {
+            let threat_indicators = match outcome {
+                PlanOutcome::Scanned(Some(report)) | PlanOutcome::Triaged(report) => {
+                    report.summary.threat_indicators.as_slice()
+                }
+                _ => &[],
+            };
+            let (outcome, detected, confidence, detail) = outcome.status();
+            FileResult {
+                file: &scan.file,
+                report: &scan.settings.output.report,
+                outcome,
+                steganography_detected: detected,
+                confidence_level: confidence,
+                threat_indicators,
+                detail,
+            }
+        })
+        .collect();
+    let batch_report = BatchReport {
+        directory: dir,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        files: results.len(),
+        detected: results
+            .iter()
+            .filter(|r| r.steganography_detected == Some(true))
+            .count(),
+        failed: results.iter().filter(|r| r.outcome == "failed").count(),
+        results,
+    };
+    let batch_file = settings.artifact_path("batch_report.json");
+    std::fs::create_dir_all(&settings.output.dir)?;
+    std::fs::write(&batch_file, serde_json::to_string_pretty(&batch_report)?)?;
+    println!("\nBatch report saved to {}", batch_file.display());
+
+    print_batch_results(&scans, &outcomes)
+}
+
+// Extract the files of a disk image and scan each. The report of a file goes to
+// reports/<volume>/<path>.json below the image's directory, next to its artifacts, and
+// contents.json there maps every extracted file back to its volume, and lists where each
+// volume sits in the image.
+fn run_disk_image(
+    image: &Path,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fname = artifact_stem(image);
+    let volumes = disk_image::find_volumes(image)?;
+    if volumes.is_empty() {
+        println!(
+            "No partition table or file system recognized in {}, scanning it as a raw dump",
+            image.display()
+        );
+        scan_file(&image.to_path_buf(), args, settings)?;
+        return Ok(());
+    }
+
+    let root = settings.artifact_path(&format!("{}_image", fname));
+    println!(
+        "Disk image {}: {} volume(s)",
+        image.display(),
+        volumes.len()
+    );
+    let mut extracted = Vec::new();
+    for volume in &volumes {
+        println!(
+            "  - volume {}: {} at offset {}, {} bytes{}",
+            volume.index,
+            volume.file_system,
+            volume.offset,
+            volume.size_bytes,
+            volume
+                .partition
+                .as_ref()
+                .map(|partition| format!(" ({})", partition))
+                .unwrap_or_default()
+        );
+        if args.scan.dry_run {
+            continue;
+        }
+        match disk_image::extract(image, volume, &root.join(volume.dir_name())) {
+            Ok(files) => {
+                println!("    {} file(s) extracted", files.len());
+                extracted.extend(files);
+            }
+            // One damaged volume shouldn't cost the files of the others
+            Err(e) => log::error!("Extracting volume {} failed: {}", volume.index, e),
+        }
+    }
+    if args.scan.dry_run {
+        return Ok(());
+    }
+
+    #[derive(Serialize)]
+    struct Contents<'a> {
+        image: String,
+        volumes: &'a [disk_image::Volume],
+        files: &'a [disk_image::ExtractedFile],
+    }
+    std::fs::create_dir_all(&root)?;
+    let contents = Contents {
+        image: image.display().to_string(),
+        volumes: &volumes,
+        files: &extracted,
+    };
+    std::fs::write(
+        root.join("contents.json"),
+        serde_json::to_string_pretty(&contents)?,
+    )?;
+
+    let scans: Vec<PlannedScan> = extracted
+        .iter()
+        .map(|file| {
+            // The extracted path, unlike the path inside the volume, is known to stay below root
+            let relative = file
+                .extracted
+                .strip_prefix(&root)
+                .unwrap_or(&file.extracted);
+            let report = root
+                .join("reports")
+                .join(format!("{}.json", relative.display()));
+            let mut file_settings = settings.clone();
+            file_settings.output.dir = report.parent().unwrap_or(&root).to_path_buf();
+            file_settings.output.report = report;
+            PlannedScan {
+                file: file.extracted.clone(),
+                settings: file_settings,
+                via_symlink: false,
+            }
+        })
+        .collect();
+    for scan in &scans {
+        std::fs::create_dir_all(&scan.settings.output.dir)?;
+    }
+    run_batch(&scans, args, settings)
+}
+
+// Carve the media files out of a packet capture and scan each. Besides the usual per-file
+// reports, flows.json lists every TCP flow with the files it carried and how their scans
+// came out, so a stego upload can be traced back to the connection it was made on.
+fn run_capture(
+    capture_file: &Path,
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fname = artifact_stem(capture_file);
+    let root = settings.artifact_path(&format!("{}_capture", fname));
+    let carved = capture::carve(capture_file, &root)?;
+    println!(
+        "Capture {}: {} packet(s), {} TCP flow(s), {} media file(s) carved",
+        capture_file.display(),
+        carved.packets,
+        carved.flows.len(),
+        carved.objects.len()
+    );
+    if carved.skipped_packets > 0 {
+        println!(
+            "  {} packet(s) were not TCP or were IP fragments and were left out",
+            carved.skipped_packets
+        );
+    }
+    for flow in &carved.flows {
+        println!(
+            "  - flow {}: {} {} -> {}, {} packet(s){}",
+            flow.index,
+            flow.protocol,
+            flow.client,
+            flow.server,
+            flow.packets,
+            if flow.missing_bytes > 0 {
+                format!(", {} byte(s) not captured", flow.missing_bytes)
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    let scans: Vec<PlannedScan> = carved
+        .objects
+        .iter()
+        .map(|object| {
+            let relative = object
+                .extracted
+                .strip_prefix(&root)
+                .unwrap_or(&object.extracted);
+            let report = root
+                .join("reports")
+                .join(format!("{}.json", relative.display()));
+            let mut file_settings = settings.clone();
+            file_settings.output.dir = report.parent().unwrap_or(&root).to_path_buf();
+            file_settings.output.report = report;
+            PlannedScan {
+                file: object.extracted.clone(),
+                settings: file_settings,
+                via_symlink: false,
+            }
+        })
+        .collect();
+    if !args.scan.dry_run {
+        for scan in &scans {
+            std::fs::create_dir_all(&scan.settings.output.dir)?;
+        }
+    }
+    let outcomes = scan_batch(&scans, args, settings);
+    if args.scan.dry_run {
+        return Ok(());
+    }
+
+    #[derive(Serialize)]
+    struct ObjectResult<'a> {
+        #[serde(flatten)]
+        object: &'a capture::CarvedObject,
+        report: PathBuf,
+        // scanned, skipped, duplicate, failed, triaged or resumed
+        outcome: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        steganography_detected: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence_level: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    }
+    #[derive(Serialize)]
+    struct FlowResult<'a> {
+        #[serde(flatten)]
+        flow: &'a capture::Flow,
+        objects: Vec<ObjectResult<'a>>,
+    }
+    let mut flows: Vec<FlowResult> = carved
+        .flows
+        .iter()
+        .map(|flow| FlowResult {
+            flow,
+            objects: Vec::new(),
+        })
+        .collect();
+    for ((object, scan), outcome) in carved.objects.iter().zip(&scans).zip(&outcomes) {
+        let (outcome, detected, confidence, detail) = outcome.status();
+        flows[object.flow].objects.push(ObjectResult {
+            object,
+            report: scan.settings.output.report.clone(),
+            outcome,
+            steganography_detected: detected,
+            confidence_level: confidence,
+            detail,
+        });
+    }
+    let flows_file = root.join("flows.json");
+    std::fs::create_dir_all(&root)?;
+    std::fs::write(&flows_file, serde_json::to_string_pretty(&flows)?)?;
+    println!("\nPer-flow report saved to {}", flows_file.display());
+
+    print_batch_results(&scans, &outcomes)
+}
+
+// Scan a batch of files and print how each went
+fn run_batch(
+    scans: &[PlannedScan],
+    args: &Args,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcomes = scan_batch(scans, args, settings);
+    if args.scan.dry_run {
+        return Ok(());
+    }
+    print_batch_results(scans, &outcomes)
+}
+
+// Scan a batch of files, carrying on past files that fail, are filtered out or run over
+// --timeout-per-file. Each finished file goes to the checkpoint in the batch's output directory.
+fn scan_batch(scans: &[PlannedScan], args: &Args, settings: &Settings) -> Vec<PlanOutcome> {
+    let checkpoint_path = settings.artifact_path(checkpoint::CHECKPOINT_FILE);
+    let mut checkpoint = if args.scan.dry_run {
+        None
+    } else {
+        match Checkpoint::open(&checkpoint_path, args.scan.resume) {
+            Ok(checkpoint) => Some(checkpoint),
+            // Losing the checkpoint only costs the ability to resume, not the scans
+            Err(e) => {
+                log::error!(
+                    "Can't write the checkpoint {}: {}",
+                    checkpoint_path.display(),
+                    e
+                );
+                None
+            }
+        }
+    };
+    if let Some(checkpoint) = &checkpoint
+        && args.scan.resume
+    {
+        println!(
+            "Resuming from {}: {} file(s) already scanned",
+            checkpoint_path.display(),
+            checkpoint.finished_count()
+        );
+    }
+
+    let filters = SkipFilters::new(&args.scan.skip_ext, args.scan.max_size);
+
+    // Filters go first, so duplicates are only looked for among files that will be scanned
+    let skips: Vec<Option<String>> = scans
+        .iter()
+        .map(|scan| {
+            if scan.via_symlink && args.scan.no_follow_symlinks {
+                Some("reached through a symbolic link".to_string())
+            } else {
+                filters.skip_reason(&scan.file)
+            }
+        })
+        .collect();
+    let mut duplicates = vec![None; scans.len()];
+    if args.scan.dedupe {
+        let candidates: Vec<usize> = (0..scans.len()).filter(|&i| skips[i].is_none()).collect();
+        let files: Vec<&Path> = candidates
+            .iter()
+            .map(|&i| scans[i].file.as_path())
+            .collect();
+        for (found, &index) in find_duplicates(&files).into_iter().zip(&candidates) {
+            duplicates[index] = found.map(|duplicate| Duplicate {
+                canonical: candidates[duplicate.canonical],
+                ..duplicate
+            });
+        }
+    }
+
+    let mut outcomes = Vec::new();
+    for (index, scan) in scans.iter().enumerate() {
+        println!("\n▶ {}", scan.file.display());
+        let sha256 = match &checkpoint {
+            Some(_) if skips[index].is_none() => dedupe::sha256_file(&scan.file)
+                .ok()
+                .map(|hash| evidence::hex(&hash)),
+            _ => None,
+        };
+        // A file only triaged before still needs its full analysis when this run isn't triage
+        let resumed = sha256
+            .as_deref()
+            .and_then(|hash| checkpoint.as_ref()?.finished(hash))
+            .filter(|entry| args.scan.triage || entry.outcome != "triaged");
+        let outcome = if let Some(reason) = &skips[index] {
+            PlanOutcome::Skipped(reason.clone())
+        } else if let Some(entry) = resumed {
+            PlanOutcome::Resumed(entry.clone())
+        } else if let Some(duplicate) = &duplicates[index] {
+            let canonical = &scans[duplicate.canonical];
+            let reference = DuplicateReference::new(
+                &scan.file,
+                &canonical.file,
+                &canonical.settings.output.report,
+                duplicate.kind,
+            );
+            // A reference at the duplicate's report path, so every planned file has a report
+            let written = if args.scan.dry_run {
+                Ok(())
+            } else {
+                reference.write(&scan.settings.output.report)
+            };
+            match written {
+                Ok(()) => PlanOutcome::Duplicate(reference),
+                Err(e) => PlanOutcome::Failed(e.to_string()),
+            }
+        } else if args.scan.triage {
+            let mut triage_settings = scan.settings.clone();
+            triage_settings.analyzers = scan.settings.analyzers.triage();
+            triage_settings.plugins.clear();
+            match scan_planned(&scan.file, args, &triage_settings) {
+                PlanOutcome::Scanned(Some(report)) if report.summary.steganography_detected => {
+                    println!("Flagged by triage, running the full analysis");
+                    scan_planned(&scan.file, args, &scan.settings)
+                }
+                PlanOutcome::Scanned(Some(report)) => PlanOutcome::Triaged(report),
+                outcome => outcome,
+            }
+        } else {
+            scan_planned(&scan.file, args, &scan.settings)
+        };
+        match &outcome {
+            PlanOutcome::Failed(e) => log::error!("Scan of {} failed: {}", scan.file.display(), e),
+            PlanOutcome::Skipped(reason) => println!("Skipped: {}", reason),
+            PlanOutcome::Duplicate(reference) => println!(
+                "Not scanned again: {} of {}",
+                reference.kind, reference.duplicate_of
+            ),
+            PlanOutcome::Resumed(entry) => {
+                println!("Already scanned as {}, see {}", entry.file, entry.report)
+            }
+            PlanOutcome::Triaged(_) => println!("Nothing found by triage, not escalated"),
+            PlanOutcome::Scanned(_) => {}
+        }
+        // Resumed files are in the checkpoint already
+        if let Some(checkpoint) = &mut checkpoint
+            && !matches!(outcome, PlanOutcome::Resumed(_))
+        {
+            let (status, detected, confidence, detail) = outcome.status();
+            let entry = CheckpointEntry {
+                file: scan.file.display().to_string(),
+                sha256,
+                outcome: status.to_string(),
+                report: scan.settings.output.report.display().to_string(),
+                steganography_detected: detected,
+                confidence_level: confidence,
+                detail,
+                finished_at: chrono::Utc::now().to_rfc3339(),
+            };
+            if let Err(e) = checkpoint.record(&entry) {
+                log::warn!("Failed to update the checkpoint: {}", e);
+            }
+        }
+        outcomes.push(outcome);
+    }
+
+    outcomes
+}
+
+// One file of a batch. With a settle period the file is only scanned once it stops changing,
+// and scanned again when it changed during the scan or its scan failed on a changing file.
+fn scan_planned(file: &Path, args: &Args, settings: &Settings) -> PlanOutcome {
+    let period = Duration::try_from_secs_f64(settings.limits.settle_seconds).unwrap_or_default();
+    if period.is_zero() {
+        return scan_planned_once(file, args, settings);
+    }
+
+    let mut rescans = 0;
+    loop {
+        let (state, settled) = match settle::wait_until_settled(file, period) {
+            Ok(settled) => settled,
+            Err(e) => return PlanOutcome::Failed(e.to_string()),
+        };
+        if !settled {
+            log::warn!("{} is still changing, scanning it anyway", file.display());
+        }
+        let outcome = scan_planned_once(file, args, settings);
+        let changed = match &outcome {
+            PlanOutcome::Scanned(Some(report)) => report.modified_during_scan,
+            // A truncated file fails to parse; the failure says nothing about the whole file
+            PlanOutcome::Failed(_) => !settled || state.changed(file),
+            _ => false,
+        };
+        if !changed || rescans == settle::MAX_RESCANS {
+            return outcome;
+        }
+        rescans += 1;
+        log::info!(
+            "{} changed during its scan, scanning it again once it settles",
+            file.display()
+        );
+    }
+}
+
+// One scan of a batch file, with --timeout-per-file if given
+fn scan_planned_once(file: &Path, args: &Args, settings: &Settings) -> PlanOutcome {
+    match args.scan.timeout_per_file {
+        Some(seconds) => scan_with_timeout(file, args, settings, Duration::from_secs(seconds)),
+        None => match scan_file(&file.to_path_buf(), args, settings) {
+            Ok(report) => PlanOutcome::Scanned(report),
+            Err(e) => PlanOutcome::Failed(e.to_string()),
+        },
+    }
 }
 
-fn process_file(path: &PathBuf) -> Result<FileObject, Box<dyn std::error::Error>> {
-    let metadata = std::fs::metadata(&path)?;
-    let infer = Infer::new();
-    let file_type = if let Ok(Some(kind)) = infer.get_from_path(&path) {
-        match kind.mime_type() {
-            mime if mime.starts_with("audio/") => FileType::Audio,
-            mime if mime.starts_with("video/") => FileType::Video,
-            mime if mime.starts_with("text/") || mime.starts_with("application/") => FileType::Text,
-            mime if mime.starts_with("image/") => FileType::Image,
-            _ => {
-                if path.extension().and_then(|ext| ext.to_str()) == Some("wma") {
-                    FileType::Audio
+// The outcome of every file of a batch, failing if any scan did
+fn print_batch_results(
+    scans: &[PlannedScan],
+    outcomes: &[PlanOutcome],
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n╔═══════════════════════════════════════════════════════════╗");
+    println!("║          BATCH SCAN RESULTS                              ║");
+    println!("╚═══════════════════════════════════════════════════════════╝");
+    let mut failed = 0;
+    let mut skipped = 0;
+    let mut triaged = 0;
+    for (scan, outcome) in scans.iter().zip(outcomes) {
+        match outcome {
+            PlanOutcome::Scanned(Some(report)) => println!(
+                "  - {}: {} ({})",
+                scan.file.display(),
+                if report.summary.steganography_detected {
+                    "steganography detected"
                 } else {
-                    FileType::Text
-                }
+                    "clean"
+                },
+                scan.settings.output.report.display()
+            ),
+            PlanOutcome::Scanned(None) => {}
+            PlanOutcome::Skipped(reason) => {
+                skipped += 1;
+                println!("  - {}: skipped: {}", scan.file.display(), reason);
+            }
+            PlanOutcome::Duplicate(reference) => println!(
+                "  - {}: {} of {}, see {}",
+                scan.file.display(),
+                reference.kind,
+                reference.duplicate_of,
+                reference.canonical_report
+            ),
+            PlanOutcome::Failed(e) => {
+                failed += 1;
+                println!("  - {}: failed: {}", scan.file.display(), e);
             }
+            PlanOutcome::Triaged(_) => {
+                triaged += 1;
+                println!(
+                    "  - {}: clean at triage ({})",
+                    scan.file.display(),
+                    scan.settings.output.report.display()
+                );
+            }
+            PlanOutcome::Resumed(entry) => println!(
+                "  - {}: scanned in an earlier run as {}, see {}",
+                scan.file.display(),
+                entry.file,
+                entry.report
+            ),
         }
-    } else {
-        if path.extension().and_then(|ext| ext.to_str()) == Some("wma") {
-            FileType::Audio
-        } else {
-            FileType::Text
+    }
+    if skipped > 0 {
+        println!("{} of {} planned scans skipped", skipped, scans.len());
+    }
+    if triaged > 0 {
+        println!(
+            "{} of {} planned scans cleared by triage without the full analysis",
+            triaged,
+            scans.len()
+        );
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} planned scans failed", failed, scans.len()).into());
+    }
+    Ok(())
+}
+
+// Scan on a worker thread and stop waiting after `timeout`. A thread can't be stopped from
+// outside, so a scan that overruns is left to finish on its own and its result dropped.
+fn scan_with_timeout(
+    file: &Path,
+    args: &Args,
+    settings: &Settings,
+    timeout: Duration,
+) -> PlanOutcome {
+    let (sender, receiver) = mpsc::channel();
+    let (file, args, settings) = (file.to_path_buf(), args.clone(), settings.clone());
+    let spawned = std::thread::Builder::new()
+        .name("plan-scan".to_string())
+        // The scan normally runs on the main thread, which gets a larger stack than spawned ones
+        .stack_size(SCAN_STACK_SIZE)
+        .spawn(move || {
+            let result = scan_file(&file, &args, &settings).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+    if let Err(e) = spawned {
+        return PlanOutcome::Failed(format!("could not start scan: {}", e));
+    }
+
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(report)) => PlanOutcome::Scanned(report),
+        Ok(Err(e)) => PlanOutcome::Failed(e),
+        Err(RecvTimeoutError::Timeout) => {
+            PlanOutcome::Skipped(format!("timed out after {} s", timeout.as_secs()))
         }
+        Err(RecvTimeoutError::Disconnected) => PlanOutcome::Failed("scan panicked".to_string()),
+    }
+}
+
+const SCAN_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+fn print_summary(report: &SteganalysisReport, locale: Locale) {
+    println!("\n╔═══════════════════════════════════════════════════════════╗");
+    println!("║          {:<48}║", tr(locale, "cli.summary_title", &[]));
+    println!("╚═══════════════════════════════════════════════════════════╝");
+    let detected = if report.summary.steganography_detected {
+        "cli.true"
+    } else {
+        "cli.false"
     };
-    Ok(FileObject {
-        file_path: path.to_path_buf(),
-        file_size: metadata.len(),
-        file_type,
-    })
+    println!(
+        "{}",
+        tr(
+            locale,
+            "cli.steganography_detected",
+            &[("value", tr(locale, detected, &[]))]
+        )
+    );
+    let confidence = tr(
+        locale,
+        &format!("confidence.{}", report.summary.confidence_level),
+        &[],
+    );
+    println!(
+        "{}",
+        tr(locale, "cli.confidence_level", &[("value", confidence)])
+    );
+
+    if !report.summary.threat_indicators.is_empty() {
+        println!("\n{}", tr(locale, "cli.threat_indicators", &[]));
+        for indicator in &report.summary.threat_indicators {
+            println!("  - {}", indicator);
+        }
+    }
+
+    if !report.summary.extracted_payloads.is_empty() {
+        println!("\n{}", tr(locale, "cli.extracted_payloads", &[]));
+        for payload in &report.summary.extracted_payloads {
+            match &payload.artifact {
+                Some(artifact) => println!(
+                    "  - {}: {} -> {}",
+                    payload.source, payload.description, artifact
+                ),
+                None => println!("  - {}: {}", payload.source, payload.description),
+            }
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::formatted_builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    let args = Args::parse();
+// Scan one file with the given settings. Returns the finished report, or None for --dry-run.
+fn scan_file(
+    file: &PathBuf,
+    args: &Args,
+    settings: &Settings,
+) -> Result<Option<SteganalysisReport>, Box<dyn std::error::Error>> {
+    let initial_state = settle::FileState::of(file)?;
+    let file_object = process_file(file)?;
+    let max_file_size_mb = settings.limits.max_file_size_mb;
+    let over_limit = max_file_size_mb > 0 && file_object.file_size > max_file_size_mb * 1024 * 1024;
+
+    if args.scan.dry_run {
+        plan::print_plan(
+            &file_object,
+            &plan::plan_scan(&file_object, settings, args.scan.extract),
+        );
+        if over_limit {
+            println!(
+                "⚠️  The scan would be refused: the file is over the configured limit of {} MB",
+                max_file_size_mb
+            );
+        }
+        return Ok(None);
+    }
 
-    let file_object = process_file(&args.file)?;
+    if over_limit {
+        return Err(format!(
+            "{} is {} bytes, over the configured limit of {} MB",
+            file.display(),
+            file_object.file_size,
+            max_file_size_mb
+        )
+        .into());
+    }
+    let scan_plan = plan::plan_scan(&file_object, settings, args.scan.extract);
     let file_objects: Vec<FileObject> = vec![file_object];
 
     // Initialize JSON report
@@ -109,6 +3859,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         file_objects[0].file_size,
         detected_type.to_string(),
     );
+    // The staged copy of an archive member is gone after the scan; name the member instead
+    if let Some(inner) = &args.scan.inner {
+        report.file_info.path = inner.to_string();
+    }
+    if let Some(url) = &args.scan.url {
+        report.file_info.path = url.clone();
+    }
+
+    println!("Scan profile: {:?}", settings.profile);
 
     if args.verbose {
         log::info!(
@@ -119,98 +3878,366 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
-    let _ = std::fs::remove_dir_all("outputs/");
-    std::fs::create_dir("outputs/").unwrap();
+    std::fs::create_dir_all(&settings.output.dir)?;
+    if let Some(report_dir) = settings.output.report.parent() {
+        std::fs::create_dir_all(report_dir)?;
+    }
 
     // Run Magic Bytes Analysis FIRST on all files
     println!("\n╔═══════════════════════════════════════════════════════════╗");
     println!("║          MAGIC BYTES / BINWALK ANALYSIS                  ║");
     println!("╚═══════════════════════════════════════════════════════════╝");
 
-    match MagicBytesAnalyzerWithPath::new(&file_objects[0].file_path).analyze() {
-        Ok(analysis) => {
-            println!("Primary format: {}", analysis.primary_format);
-            if let Some(expected) = &analysis.expected_format {
-                println!("Expected format (by extension): {}", expected);
+    // Also used for nested scans of embedded pictures
+    let custom_signatures = match &settings.magic_bytes.signatures_file {
+        Some(path) => CustomSignature::load(path)?,
+        None => Vec::new(),
+    };
+    let lsb_baselines = settings.cover_baselines()?;
+
+    if !settings.analyzers.magic_bytes {
+        println!("Skipped (disabled)");
+    } else {
+        if !custom_signatures.is_empty() {
+            println!("Custom signatures loaded: {}", custom_signatures.len());
+        }
+
+        match MagicBytesAnalyzerWithPath::new(&file_objects[0].file_path)
+            .with_custom_signatures(&custom_signatures)
+            .with_strictness(settings.magic_bytes.strictness)
+            .analyze()
+        {
+            Ok(analysis) => {
+                println!("Primary format: {}", analysis.primary_format);
+                if let Some(expected) = &analysis.expected_format {
+                    println!("Expected format (by extension): {}", expected);
+                }
+                println!(
+                    "Total signatures found: {}",
+                    analysis.total_signatures_found
+                );
+                println!(
+                    "Multiple formats detected: {}",
+                    analysis.has_multiple_formats
+                );
+
+                println!("\n--- Format Summary ---");
+                println!("Images: {}", analysis.format_summary.image_files);
+                println!("Audio: {}", analysis.format_summary.audio_files);
+                println!("Video: {}", analysis.format_summary.video_files);
+                println!("Text/Documents: {}", analysis.format_summary.text_files);
+                println!("Archives: {}", analysis.format_summary.archive_files);
+                println!("Executables: {}", analysis.format_summary.executable_files);
+                println!("Other: {}", analysis.format_summary.other_files);
+
+                if !analysis.embedded_files.is_empty() {
+                    println!("\n--- Embedded Files Detected ---");
+                    for (idx, file) in analysis.embedded_files.iter().enumerate() {
+                        println!(
+                            "  {}. Offset: 0x{:X} ({})",
+                            idx + 1,
+                            file.offset,
+                            file.offset
+                        );
+                        println!("     Type: {}", file.file_type);
+                        println!("     Description: {}", file.description);
+                        println!("     Confidence: {}", file.confidence);
+                    }
+                }
+
+                if !analysis.suspicious_findings.is_empty() {
+                    println!("\n⚠️  SUSPICIOUS FINDINGS:");
+                    for finding in &analysis.suspicious_findings {
+                        println!("  🚩 {}", finding);
+                    }
+                }
+
+                if analysis.has_suspicious_data {
+                    println!(
+                        "\n⚠️  WARNING: This file contains data that may indicate steganography!"
+                    );
+                }
+
+                // Populate JSON report with magic bytes analysis
+                report.set_magic_bytes_analysis(magic_bytes_report(&analysis));
+            }
+            Err(e) => {
+                log::error!("Magic bytes analysis failed: {}", e);
+            }
+        }
+    }
+
+    // Compare declared structure lengths against the bytes actually present, as a
+    // fallback for containers without a dedicated analyzer
+    if settings.analyzers.slack_space {
+        let analysis = match std::fs::read(&file_objects[0].file_path) {
+            Ok(data) => SlackSpaceAnalyzer::analyze_bytes(&data)
+                .ok()
+                .map(|analysis| (analysis, data)),
+            Err(e) => {
+                log::error!("Error reading file for slack space analysis: {}", e);
+                None
             }
+        };
+
+        // UnknownContainer just means there's no length-prefixed structure to walk
+        if let Some((analysis, data)) = analysis {
+            println!("\n--- Slack Space ({}) ---", analysis.container);
             println!(
-                "Total signatures found: {}",
-                analysis.total_signatures_found
+                "Accounted for: {} of {} bytes",
+                analysis.accounted_bytes, analysis.file_size
             );
+            let fname = artifact_stem(&file_objects[0].file_path);
+            let mut regions = Vec::new();
+            for region in &analysis.regions {
+                print!(
+                    "  Offset 0x{:X}: {} bytes, entropy {:.2} bits/byte ({})",
+                    region.offset, region.length, region.entropy, region.description
+                );
+                match &region.detected_type {
+                    Some(mime) => println!(", starts like {}", mime),
+                    None => println!(),
+                }
+
+                // With --extract, regions that could hold a payload are carved out as-is
+                let mut carved_file = None;
+                let mut sha256 = None;
+                let mut also_carved_from = Vec::new();
+                if args.scan.extract && region.length >= MIN_SLACK_BYTES && !region.all_zero {
+                    let store = settings.payload_store();
+                    let source = file_objects[0].file_path.to_string_lossy().to_string();
+                    let reference = ObjectReference {
+                        source: source.clone(),
+                        offset: region.offset,
+                        description: region.description.clone(),
+                        name: format!("{}_slack_0x{:X}.bin", fname, region.offset),
+                    };
+                    match store.put(&data[region.offset..][..region.length], "bin", reference) {
+                        Ok(object) => {
+                            let path = store.path(&object);
+                            println!("    Carved to {}", path.display());
+                            also_carved_from = object.other_sources(&source);
+                            if !also_carved_from.is_empty() {
+                                println!(
+                                    "    Same payload carved from {}",
+                                    also_carved_from.join(", ")
+                                );
+                            }
+                            carved_file = Some(path.to_string_lossy().to_string());
+                            sha256 = Some(object.sha256);
+                        }
+                        Err(e) => log::error!("Failed to carve slack region: {}", e),
+                    }
+                }
+
+                regions.push(SlackRegionReport {
+                    offset: region.offset,
+                    offset_hex: format!("0x{:X}", region.offset),
+                    length: region.length,
+                    entropy: region.entropy,
+                    description: region.description.clone(),
+                    detected_type: region.detected_type.clone(),
+                    carved_file,
+                    sha256,
+                    also_carved_from,
+                });
+            }
+            if analysis.suspicious {
+                println!("⚠️  Unaccounted data found outside the declared structure");
+            }
+
+            report.set_slack_space(SlackSpaceReport {
+                container: analysis.container.clone(),
+                accounted_bytes: analysis.accounted_bytes,
+                slack_bytes: analysis.file_size - analysis.accounted_bytes,
+                is_suspicious: analysis.suspicious,
+                regions,
+            });
+        }
+    }
+
+    // Compressed streams anywhere in the file, which carry no signature of their own once
+    // inflated data is stored without a header
+    if settings.analyzers.deflate {
+        let analysis = match std::fs::read(&file_objects[0].file_path) {
+            Ok(data) => DeflateHunter::analyze(data).ok(),
+            Err(e) => {
+                log::error!("Error reading file for deflate stream search: {}", e);
+                None
+            }
+        };
+
+        if let Some(analysis) = analysis {
+            println!("\n--- Compressed Streams ---");
             println!(
-                "Multiple formats detected: {}",
-                analysis.has_multiple_formats
+                "Searched {} bytes, skipping {} bytes of the container's own streams",
+                analysis.searched_bytes, analysis.container_bytes
             );
+            if analysis.streams.is_empty() {
+                println!("No embedded compressed streams found");
+            }
+            let fname = artifact_stem(&file_objects[0].file_path);
+            let mut streams = Vec::new();
+            for stream in &analysis.streams {
+                println!(
+                    "  Offset 0x{:X}: {} stream, {} bytes inflate to {} bytes of {}{}",
+                    stream.offset,
+                    stream.format,
+                    stream.compressed_length,
+                    stream.data.len(),
+                    stream.classification,
+                    if stream.complete { "" } else { " (truncated)" }
+                );
+                if let Some(preview) = &stream.preview {
+                    println!("    \"{}\"", preview);
+                }
 
-            println!("\n--- Format Summary ---");
-            println!("Images: {}", analysis.format_summary.image_files);
-            println!("Audio: {}", analysis.format_summary.audio_files);
-            println!("Video: {}", analysis.format_summary.video_files);
-            println!("Text/Documents: {}", analysis.format_summary.text_files);
-            println!("Archives: {}", analysis.format_summary.archive_files);
-            println!("Executables: {}", analysis.format_summary.executable_files);
-            println!("Other: {}", analysis.format_summary.other_files);
-
-            if !analysis.embedded_files.is_empty() {
-                println!("\n--- Embedded Files Detected ---");
-                for (idx, file) in analysis.embedded_files.iter().enumerate() {
-                    println!(
-                        "  {}. Offset: 0x{:X} ({})",
-                        idx + 1,
-                        file.offset,
-                        file.offset
-                    );
-                    println!("     Type: {}", file.file_type);
-                    println!("     Description: {}", file.description);
-                    println!("     Confidence: {}", file.confidence);
+                let mut carved_file = None;
+                let mut sha256 = None;
+                let mut also_carved_from = Vec::new();
+                if args.scan.extract {
+                    let store = settings.payload_store();
+                    let source = file_objects[0].file_path.to_string_lossy().to_string();
+                    let extension = stream.extension();
+                    let reference = ObjectReference {
+                        source: source.clone(),
+                        offset: stream.offset,
+                        description: format!("inflated {} stream", stream.format),
+                        name: format!("{}_deflate_0x{:X}.{}", fname, stream.offset, extension),
+                    };
+                    match store.put(&stream.data, extension, reference) {
+                        Ok(object) => {
+                            let path = store.path(&object);
+                            println!("    Inflated to {}", path.display());
+                            also_carved_from = object.other_sources(&source);
+                            if !also_carved_from.is_empty() {
+                                println!(
+                                    "    Same payload carved from {}",
+                                    also_carved_from.join(", ")
+                                );
+                            }
+                            carved_file = Some(path.to_string_lossy().to_string());
+                            sha256 = Some(object.sha256);
+                        }
+                        Err(e) => log::error!("Failed to write inflated stream: {}", e),
+                    }
                 }
+
+                streams.push(DeflateStreamReport {
+                    offset: stream.offset,
+                    offset_hex: format!("0x{:X}", stream.offset),
+                    format: stream.format.to_string(),
+                    compressed_length: stream.compressed_length,
+                    inflated_length: stream.data.len(),
+                    complete: stream.complete,
+                    classification: stream.classification.clone(),
+                    text_preview: stream.preview.clone(),
+                    carved_file,
+                    sha256,
+                    also_carved_from,
+                });
             }
+            if analysis.suspicious {
+                println!("⚠️  Compressed data found outside the container's own streams");
+            }
+
+            report.set_deflate_streams(DeflateStreamsReport {
+                searched_bytes: analysis.searched_bytes,
+                container_bytes: analysis.container_bytes,
+                is_suspicious: analysis.suspicious,
+                streams,
+            });
+        }
+    }
 
-            if !analysis.suspicious_findings.is_empty() {
-                println!("\n⚠️  SUSPICIOUS FINDINGS:");
-                for finding in &analysis.suspicious_findings {
-                    println!("  🚩 {}", finding);
+    if args.scan.extract {
+        println!("\n--- Embedded File Extraction ---");
+        let fname = artifact_stem(&file_objects[0].file_path);
+        let extract_dir = settings.artifact_path(&format!("{}_extracted", fname));
+        match BinwalkExtractorWithPath::new(&file_objects[0].file_path, &extract_dir).extract() {
+            Ok(results) => {
+                if results.payloads.is_empty() {
+                    println!("No embedded files to extract");
+                }
+                let mut extracted_files = Vec::new();
+                for payload in &results.payloads {
+                    let method = match &payload.method {
+                        ExtractionMethod::Extractor(name) => name.clone(),
+                        ExtractionMethod::Carved => "carved".to_string(),
+                    };
+                    println!(
+                        "  0x{:X}: {} ({} bytes, {}) -> {}",
+                        payload.offset,
+                        payload.description,
+                        payload.size,
+                        method,
+                        payload.path.display()
+                    );
+                    extracted_files.push(ExtractedFileInfo {
+                        offset: payload.offset,
+                        offset_hex: format!("0x{:X}", payload.offset),
+                        size_bytes: payload.size,
+                        description: payload.description.clone(),
+                        method,
+                        path: payload.path.to_string_lossy().to_string(),
+                    });
+                }
+                if results.removed_links > 0 {
+                    println!(
+                        "⚠️  Removed {} extracted symlinks pointing outside {}",
+                        results.removed_links,
+                        results.output_directory.display()
+                    );
                 }
+                report.set_extracted_files(extracted_files);
             }
-
-            if analysis.has_suspicious_data {
-                println!("\n⚠️  WARNING: This file contains data that may indicate steganography!");
+            Err(e) => {
+                log::error!("Embedded file extraction failed: {}", e);
             }
+        }
+    }
 
-            // Populate JSON report with magic bytes analysis
-            let magic_report = MagicBytesReport {
-                primary_format: analysis.primary_format.clone(),
-                expected_format: analysis.expected_format.clone(),
-                total_signatures_found: analysis.total_signatures_found,
-                has_multiple_formats: analysis.has_multiple_formats,
-                has_suspicious_data: analysis.has_suspicious_data,
-                format_summary: FormatSummary {
-                    images: analysis.format_summary.image_files,
-                    audio: analysis.format_summary.audio_files,
-                    video: analysis.format_summary.video_files,
-                    text_documents: analysis.format_summary.text_files,
-                    archives: analysis.format_summary.archive_files,
-                    executables: analysis.format_summary.executable_files,
-                    other: analysis.format_summary.other_files,
-                },
-                embedded_files: analysis
-                    .embedded_files
-                    .iter()
-                    .map(|f| EmbeddedFileInfo {
-                        offset: f.offset,
-                        offset_hex: format!("0x{:X}", f.offset),
-                        description: f.description.clone(),
-                        file_type: f.file_type.clone(),
-                        confidence: f.confidence.clone(),
-                    })
-                    .collect(),
-                suspicious_findings: analysis.suspicious_findings.clone(),
+    // External analyzer plugins registered in the config
+    if !settings.plugins.is_empty() {
+        println!("\n╔═══════════════════════════════════════════════════════════╗");
+        println!("║          PLUGIN ANALYSIS                                 ║");
+        println!("╚═══════════════════════════════════════════════════════════╝\n");
+
+        let mut plugin_reports = Vec::new();
+        for plugin in &settings.plugins {
+            let mut plugin_report = PluginReport {
+                name: plugin.name.clone(),
+                is_suspicious: false,
+                findings: Vec::new(),
+                details: None,
+                error: None,
             };
-            report.set_magic_bytes_analysis(magic_report);
-        }
-        Err(e) => {
-            log::error!("Magic bytes analysis failed: {}", e);
+            match plugin.run(&file_objects[0].file_path) {
+                Ok(output) => {
+                    println!("{}: suspicious = {}", plugin.name, output.suspicious);
+                    for finding in &output.findings {
+                        println!("  - {}", finding.description);
+                    }
+                    plugin_report.is_suspicious = output.suspicious;
+                    plugin_report.details = output.details;
+                    plugin_report.findings = output
+                        .findings
+                        .into_iter()
+                        .map(|f| PluginFindingReport {
+                            description: f.description,
+                            offset: f.offset,
+                            confidence: f.confidence,
+                        })
+                        .collect();
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    plugin_report.error = Some(e.to_string());
+                }
+            }
+            plugin_reports.push(plugin_report);
         }
+        report.set_plugins(plugin_reports);
     }
 
     println!("\n╔═══════════════════════════════════════════════════════════╗");
@@ -220,109 +4247,404 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     for file_object in file_objects.into_iter() {
         match file_object.file_type {
             FileType::Audio => {
-                match AudioParser::parse_path(&file_object.file_path) {
-                    Ok(samples) => {
+                match AudioParser::parse_with_sample_rate(&file_object.file_path) {
+                    Ok((samples, sample_rate)) => {
                         if args.verbose {
-                            log::info!("Audio samples length: {}", samples.len());
+                            log::info!(
+                                "Audio samples length: {}, sample rate: {} Hz",
+                                samples.len(),
+                                sample_rate
+                            );
                         }
 
                         println!("Processed {} audio samples successfully", samples.len());
 
                         let mut audio_analysis = AudioAnalysis {
                             sample_count: samples.len(),
+                            sample_rate,
+                            samples_file: None,
                             id3_analysis: None,
                             spectrogram_analysis: None,
+                            ultrasonic_analysis: None,
+                            demodulation: None,
+                            phase_analysis: None,
+                            differential: None,
+                            trailing_tags: None,
+                            mp4_tags: None,
                         };
 
-                        // ID3 Tag Analysis
-                        println!("\n=== ID3 Tag Analysis ===");
-                        match Id3AnalyzerWithPath::new(&file_object.file_path).analyze() {
-                            Ok(id3_data) => {
-                                if let Some(title) = &id3_data.title {
-                                    println!("Title: {}", title);
+                        if args.scan.export_samples {
+                            let fname = artifact_stem(&file_object.file_path);
+                            let samples_file = settings
+                                .artifact_path(&format!("{}_samples.wav", fname))
+                                .to_string_lossy()
+                                .to_string();
+                            match AudioParser::export_wav(&samples, sample_rate, &samples_file) {
+                                Ok(_) => {
+                                    println!("Analyzed samples exported to {}", samples_file);
+                                    audio_analysis.samples_file = Some(samples_file);
                                 }
-                                if let Some(artist) = &id3_data.artist {
-                                    println!("Artist: {}", artist);
+                                Err(e) => {
+                                    log::error!("Failed to export samples: {}", e);
                                 }
+                            }
+                        }
+
+                        // ID3 Tag Analysis
+                        if settings.analyzers.id3 {
+                            println!("\n=== ID3 Tag Analysis ===");
+                            match Id3AnalyzerWithPath::new(&file_object.file_path).analyze() {
+                                Ok(id3_data) => {
+                                    if let Some(title) = &id3_data.title {
+                                        println!("Title: {}", title);
+                                    }
+                                    if let Some(artist) = &id3_data.artist {
+                                        println!("Artist: {}", artist);
+                                    }
+
+                                    println!("Comments: {}", id3_data.comments.len());
+                                    println!("Pictures: {}", id3_data.pictures.len());
+                                    println!("Private frames: {}", id3_data.private_frames.len());
+                                    println!("Chapters: {}", id3_data.chapters.len());
+                                    for (id, value) in &id3_data.podcast_frames {
+                                        println!("Podcast {}: {}", id, value);
+                                    }
+
+                                    if !id3_data.suspicious_frames.is_empty() {
+                                        println!("\n⚠️  Suspicious findings:");
+                                        for finding in &id3_data.suspicious_frames {
+                                            println!("  - {}", finding);
+                                        }
+                                    }
 
-                                println!("Comments: {}", id3_data.comments.len());
-                                println!("Pictures: {}", id3_data.pictures.len());
-                                println!("Private frames: {}", id3_data.private_frames.len());
+                                    if args.verbose {
+                                        println!("\nAll ID3 frames:");
+                                        for (key, value) in &id3_data.all_frames {
+                                            println!("  {}: {}", key, value);
+                                        }
+                                    }
 
-                                if !id3_data.suspicious_frames.is_empty() {
-                                    println!("\n⚠️  Suspicious findings:");
-                                    for finding in &id3_data.suspicious_frames {
-                                        println!("  - {}", finding);
+                                    // Album art is a common nested carrier, so each APIC
+                                    // picture is saved and put through the image analyzers
+                                    let fname = artifact_stem(&file_object.file_path);
+                                    let mut pictures = Vec::new();
+                                    for (idx, picture) in id3_data.pictures.iter().enumerate() {
+                                        let reference = ObjectReference {
+                                            source: file_object
+                                                .file_path
+                                                .to_string_lossy()
+                                                .to_string(),
+                                            offset: picture.offset.unwrap_or(0),
+                                            description: picture.picture_type.clone(),
+                                            name: format!(
+                                                "{}_apic_{}.{}",
+                                                fname,
+                                                idx,
+                                                picture.extension()
+                                            ),
+                                        };
+                                        let picture_report = scan_embedded_picture(
+                                            picture,
+                                            reference,
+                                            settings,
+                                            &custom_signatures,
+                                        );
+                                        println!(
+                                            "\nPicture {} ({}, {} bytes) -> {}",
+                                            idx,
+                                            picture.picture_type,
+                                            picture.data_size,
+                                            picture_report.file.as_deref().unwrap_or("not saved")
+                                        );
+                                        if !picture_report.also_carved_from.is_empty() {
+                                            println!(
+                                                "  Same picture found in {}",
+                                                picture_report.also_carved_from.join(", ")
+                                            );
+                                        }
+                                        if picture_report.is_suspicious {
+                                            println!(
+                                                "  ⚠️  Embedded picture shows signs of hidden data"
+                                            );
+                                        }
+                                        pictures.push(picture_report);
                                     }
+
+                                    audio_analysis.id3_analysis = Some(Id3Report {
+                                        title: id3_data.title.clone(),
+                                        artist: id3_data.artist.clone(),
+                                        album: id3_data.album.clone(),
+                                        year: id3_data.year,
+                                        comments_count: id3_data.comments.len(),
+                                        pictures_count: id3_data.pictures.len(),
+                                        private_frames_count: id3_data.private_frames.len(),
+                                        suspicious_frames: id3_data.suspicious_frames.clone(),
+                                        pictures,
+                                        chapters: id3_data
+                                            .chapters
+                                            .iter()
+                                            .map(|c| Id3ChapterReport {
+                                                element_id: c.element_id.clone(),
+                                                start_time_ms: c.start_time_ms,
+                                                end_time_ms: c.end_time_ms,
+                                                title: c.title.clone(),
+                                                sub_frames: c.sub_frames.clone(),
+                                                urls: c.urls.clone(),
+                                                picture_count: c.picture_count,
+                                                largest_binary_bytes: c.largest_binary_bytes,
+                                                findings: c.findings.clone(),
+                                            })
+                                            .collect(),
+                                        podcast_frames: id3_data
+                                            .podcast_frames
+                                            .iter()
+                                            .map(|(id, value)| MetadataField {
+                                                key: id.clone(),
+                                                value: value.clone(),
+                                            })
+                                            .collect(),
+                                        lyrics_payloads: id3_data
+                                            .lyrics_payloads
+                                            .iter()
+                                            .map(lyrics_payload_report)
+                                            .collect(),
+                                        v2_tags: id3_data
+                                            .v2_tags
+                                            .iter()
+                                            .map(|t| Id3v2TagReport {
+                                                offset: t.offset,
+                                                version: format!(
+                                                    "2.{}.{}",
+                                                    t.version.0, t.version.1
+                                                ),
+                                                size_bytes: t.size,
+                                            })
+                                            .collect(),
+                                        v1_tag: id3_data.v1_tag.as_ref().map(v1_tag_report),
+                                        tag_gaps: tag_gap_reports(
+                                            &file_object.file_path,
+                                            &id3_data,
+                                            settings,
+                                        ),
+                                        tag_mismatches: id3_data
+                                            .tag_mismatches
+                                            .iter()
+                                            .map(|m| Id3TagMismatchReport {
+                                                field: m.field.clone(),
+                                                v1: m.v1.clone(),
+                                                v2: m.v2.clone(),
+                                            })
+                                            .collect(),
+                                    });
+                                }
+                                Err(e) => {
+                                    log::warn!("ID3 analysis failed: {}", e);
                                 }
+                            }
+                        }
+
+                        // APEv2 and Lyrics3 tags sit between the audio and the ID3v1 tag;
+                        // an M4A keeps its tags in the ilst box instead
+                        if settings.analyzers.id3 {
+                            audio_analysis.trailing_tags =
+                                trailing_tags_report(&file_object.file_path, settings);
+                            audio_analysis.mp4_tags =
+                                mp4_tags_report(&file_object.file_path, settings);
+                        }
 
-                                if args.verbose {
-                                    println!("\nAll ID3 frames:");
-                                    for (key, value) in &id3_data.all_frames {
-                                        println!("  {}: {}", key, value);
+                        // Phase coding, invisible to the magnitude spectrogram
+                        if settings.analyzers.phase {
+                            println!("\n=== Phase Coding Analysis ===");
+                            match PhaseAnalyzer::analyze(samples.clone()) {
+                                Ok(mut phase) => {
+                                    phase.suspicious = phase
+                                        .is_suspicious_at(settings.thresholds.phase_discontinuity);
+                                    println!(
+                                        "Segment length {}: boundary jumps {:.2}x the average, {:.1}% of first-segment phases at +-pi/2",
+                                        phase.segment_length,
+                                        phase.boundary_jump_ratio,
+                                        phase.quantized_share * 100.0
+                                    );
+                                    if phase.quantized_run > 0 {
+                                        println!(
+                                            "  {} consecutive bins at +-pi/2 from bin {}",
+                                            phase.quantized_run, phase.quantized_first_bin
+                                        );
                                     }
+                                    if phase.suspicious {
+                                        println!("⚠️  Audio looks phase coded");
+                                    }
+                                    if let Some(text) = phase.decoded_text() {
+                                        println!("  Decoded: {:?}", text);
+                                    }
+                                    audio_analysis.phase_analysis = Some(PhaseReport {
+                                        segment_length: phase.segment_length,
+                                        boundary_jump_ratio: phase.boundary_jump_ratio,
+                                        quantized_run: phase.quantized_run,
+                                        quantized_first_bin: phase.quantized_first_bin,
+                                        quantized_share: phase.quantized_share,
+                                        decoded_hex: phase
+                                            .decoded
+                                            .iter()
+                                            .map(|b| format!("{:02x}", b))
+                                            .collect(),
+                                        decoded_text: phase.decoded_text(),
+                                        is_suspicious: phase.suspicious,
+                                    });
+                                }
+                                Err(e) => {
+                                    println!("Skipped: {}", e);
                                 }
+                            }
+                        }
 
-                                audio_analysis.id3_analysis = Some(Id3Report {
-                                    title: id3_data.title.clone(),
-                                    artist: id3_data.artist.clone(),
-                                    album: id3_data.album.clone(),
-                                    year: id3_data.year,
-                                    comments_count: id3_data.comments.len(),
-                                    pictures_count: id3_data.pictures.len(),
-                                    private_frames_count: id3_data.private_frames.len(),
-                                    suspicious_frames: id3_data.suspicious_frames.clone(),
-                                });
+                        // Whatever the suspect adds to the recording it was made from
+                        if let Some(reference_path) = &args.scan.compare_with {
+                            println!("\n=== Differential Analysis ===");
+                            let (reference, reference_rate) =
+                                AudioParser::parse_with_sample_rate(reference_path)?;
+                            match DifferentialAnalyzer::analyze((
+                                samples.clone(),
+                                sample_rate,
+                                reference,
+                                reference_rate,
+                            )) {
+                                Ok(differential) => {
+                                    audio_analysis.differential =
+                                        Some(differential_report(&differential, reference_path));
+                                }
+                                Err(e) => {
+                                    println!("Skipped: {}", e);
+                                }
                             }
-                            Err(e) => {
-                                log::warn!("ID3 analysis failed: {}", e);
+                        }
+
+                        // Tones the spectral analyzers flag, handed to the demodulator
+                        let mut carriers = Vec::new();
+
+                        // Content above the audible range, isolated and resampled on its own
+                        if settings.analyzers.ultrasonic {
+                            println!("\n=== Ultrasonic Analysis ===");
+                            match UltrasonicAnalyzer::analyze((
+                                samples.clone(),
+                                sample_rate,
+                                settings.audio.ultrasonic_cutoff_hz,
+                            )) {
+                                Ok(mut ultrasonic) => {
+                                    ultrasonic.suspicious = ultrasonic
+                                        .is_suspicious_at(settings.thresholds.ultrasonic_energy);
+                                    carriers
+                                        .extend(ultrasonic.tones.iter().map(|t| t.frequency_hz));
+                                    audio_analysis.ultrasonic_analysis = Some(ultrasonic_report(
+                                        &ultrasonic,
+                                        &file_object.file_path,
+                                        settings,
+                                        args.scan.export_samples,
+                                    ));
+                                }
+                                Err(e) => {
+                                    println!("Skipped: {}", e);
+                                }
                             }
                         }
 
                         // Spectrogram Analysis
-                        println!("\n=== Spectrogram Analysis ===");
-                        match SpectrogramAnalyzer::analyze(samples) {
-                            Ok(spectrogram_data) => {
-                                println!(
-                                    "High frequency energy: {:.4}",
-                                    spectrogram_data.high_frequency_energy
-                                );
-                                println!(
-                                    "Hidden message detected: {}",
-                                    spectrogram_data.has_hidden_message
-                                );
+                        let tile_seconds = settings.audio.spectrogram_tile_seconds;
+                        let tiled = tile_seconds > 0
+                            && samples.len() > sample_rate as usize * tile_seconds as usize;
+                        if settings.analyzers.spectrogram && tiled {
+                            println!("\n=== Spectrogram Analysis ===");
+                            match analyze_tiles(&samples, sample_rate, tile_seconds) {
+                                Ok(tiles) => {
+                                    let (spectrogram_report, tones) = spectrogram_tiles_report(
+                                        &tiles,
+                                        &file_object.file_path,
+                                        settings,
+                                    );
+                                    carriers.extend(tones);
+                                    audio_analysis.spectrogram_analysis = Some(spectrogram_report);
+                                }
+                                Err(e) => {
+                                    log::error!("Spectrogram analysis failed: {}", e);
+                                }
+                            }
+                        } else if settings.analyzers.spectrogram {
+                            println!("\n=== Spectrogram Analysis ===");
+                            match SpectrogramAnalyzer::analyze((samples.clone(), sample_rate)) {
+                                Ok(mut spectrogram_data) => {
+                                    spectrogram_data.has_hidden_message = spectrogram_data
+                                        .has_hidden_message_at(
+                                            settings.thresholds.spectrogram_high_frequency_energy,
+                                        );
+
+                                    println!(
+                                        "High frequency energy: {:.4}",
+                                        spectrogram_data.high_frequency_energy
+                                    );
+                                    println!(
+                                        "Hidden message detected: {}",
+                                        spectrogram_data.has_hidden_message
+                                    );
 
-                                if !spectrogram_data.suspicious_patterns.is_empty() {
-                                    println!("\n⚠️  Suspicious patterns:");
-                                    for pattern in &spectrogram_data.suspicious_patterns {
-                                        println!("  - {}", pattern);
+                                    if !spectrogram_data.suspicious_patterns.is_empty() {
+                                        println!("\n⚠️  Suspicious patterns:");
+                                        for pattern in &spectrogram_data.suspicious_patterns {
+                                            println!("  - {}", pattern);
+                                        }
                                     }
-                                }
 
-                                let fname =
-                                    file_object.file_path.file_name().unwrap().to_str().unwrap();
-                                let output_file = format!("outputs/{}_spectrogram.png", fname);
-                                spectrogram_data
-                                    .spectrogram_image
-                                    .save(&output_file)
-                                    .unwrap();
-                                println!("Spectrogram saved to {}", output_file);
-
-                                audio_analysis.spectrogram_analysis = Some(SpectrogramReport {
-                                    high_frequency_energy: spectrogram_data.high_frequency_energy,
-                                    hidden_message_detected: spectrogram_data.has_hidden_message,
-                                    suspicious_patterns: spectrogram_data
-                                        .suspicious_patterns
-                                        .clone(),
-                                    output_file,
-                                });
+                                    let fname = artifact_stem(&file_object.file_path);
+                                    let output_file = settings
+                                        .artifact_path(&format!("{}_spectrogram.png", fname))
+                                        .to_string_lossy()
+                                        .to_string();
+                                    spectrogram_data
+                                        .render(settings.audio.spectrogram_colormap)
+                                        .save(&output_file)
+                                        .unwrap();
+                                    println!("Spectrogram saved to {}", output_file);
+                                    carriers.extend(spectrogram_data.persistent_tone_frequencies());
+
+                                    audio_analysis.spectrogram_analysis = Some(SpectrogramReport {
+                                        high_frequency_energy: spectrogram_data
+                                            .high_frequency_energy,
+                                        hidden_message_detected: spectrogram_data
+                                            .has_hidden_message,
+                                        suspicious_patterns: spectrogram_data
+                                            .suspicious_patterns
+                                            .clone(),
+                                        output_file: Some(output_file),
+                                        tiles: Vec::new(),
+                                        persistent_tones: spectrogram_data
+                                            .persistent_tones
+                                            .iter()
+                                            .map(tone_report)
+                                            .collect(),
+                                    });
+                                }
+                                Err(e) => {
+                                    log::error!("Spectrogram analysis failed: {}", e);
+                                }
                             }
-                            Err(e) => {
-                                log::error!("Spectrogram analysis failed: {}", e);
+                        }
+
+                        // Payload recovery from the flagged tones
+                        if settings.analyzers.demodulation && !carriers.is_empty() {
+                            println!("\n=== Tone Demodulation ===");
+                            match Demodulator::analyze((samples, sample_rate, carriers)) {
+                                Ok(demodulated) => {
+                                    audio_analysis.demodulation =
+                                        Some(demodulation_report(&demodulated));
+                                }
+                                Err(e) => {
+                                    println!("Skipped: {}", e);
+                                }
                             }
                         }
 
-                        report.set_format_analysis(FormatSpecificAnalysis::Audio(audio_analysis));
+                        report.set_format_analysis(FormatSpecificAnalysis::Audio(Box::new(
+                            audio_analysis,
+                        )));
                     }
                     Err(e) => {
                         log::error!("Error parsing audio file: {:?}", e);
@@ -333,9 +4655,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
             }
+            FileType::Video if !settings.analyzers.video => {
+                println!("Video frame analysis skipped (disabled)");
+            }
             FileType::Video => {
-                match VideoParser::parse_path(&file_object.file_path) {
-                    Ok(frame_iter) => {
+                let frames = VideoParser::parse_with_options(
+                    &file_object.file_path,
+                    VideoDecodeOptions {
+                        keyframes_only: settings.video.keyframes_only,
+                        hwaccel: settings.video.hwaccel,
+                    },
+                );
+                match frames {
+                    Ok(mut frame_iter) => {
+                        let mut sampler = FrameSampler::new(settings.video_sampling());
+                        let mut scenes = SceneDetector::new(
+                            settings.video.scene_threshold,
+                            settings.video.max_frames_per_scene,
+                        );
                         let mut frame_count = 0;
                         let mut error_count = 0;
                         let mut suspicious_frame_indices = Vec::new();
@@ -344,76 +4681,118 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                         println!("\n=== Video Frame Analysis ===");
                         println!(
-                            "Sampling every {} frames for steganography analysis",
-                            args.video_sample_rate
+                            "Sampling {} for steganography analysis{}",
+                            settings.video_sampling(),
+                            if settings.video.keyframes_only {
+                                " (keyframes only)"
+                            } else {
+                                ""
+                            }
                         );
+                        match (settings.video.hwaccel, frame_iter.hardware_decoder()) {
+                            (HwAccel::None, _) => {}
+                            (_, Some(device)) => println!("Decoding on {}", device),
+                            (requested, None) => println!(
+                                "Hardware decoding ({}) unavailable, decoding in software",
+                                requested
+                            ),
+                        }
 
-                        for (idx, frame_result) in frame_iter.enumerate() {
+                        // Decoding stays on this thread; sampled frames are analyzed on the
+                        // pool and their results handled in frame order as they come back.
+                        // Frames go back to the decoder's buffer pool once analyzed, or
+                        // straight away when not sampled.
+                        let buffers = frame_iter.buffers();
+                        let mut pipeline = FramePipeline::new().with_recycler({
+                            let buffers = buffers.clone();
+                            Arc::new(move |frame| buffers.recycle(frame))
+                        });
+                        let mut idx = 0;
+                        loop {
+                            let frame_result = frame_iter.next();
+                            let done = frame_result.is_none();
                             match frame_result {
-                                Ok(frame) => {
+                                Some(Ok(frame)) => {
                                     frame_count += 1;
 
                                     if args.verbose && idx % 100 == 0 {
                                         log::info!("Processing frame {}...", idx);
                                     }
 
-                                    // Perform detailed analysis on sampled frames
-                                    if idx % args.video_sample_rate == 0 {
-                                        let dynamic_image = image::DynamicImage::ImageRgba8(frame);
-
-                                        match VideoFrameAnalyzer::analyze(dynamic_image) {
-                                            Ok(mut analysis) => {
-                                                analysis.frame_index = idx;
-                                                frames_analyzed += 1;
-
-                                                // Collect entropy for averaging
-                                                let avg_entropy: f64 =
-                                                    analysis.entropy_scores.iter().sum::<f64>()
-                                                        / analysis.entropy_scores.len() as f64;
-                                                total_entropy += avg_entropy;
-
-                                                // Track anomalies
-                                                if analysis.lsb_suspicious
-                                                    || analysis.histogram_anomalies
-                                                {
-                                                    suspicious_frame_indices.push(idx);
-
-                                                    if args.verbose {
-                                                        println!(
-                                                            "\n⚠️  Suspicious frame {} detected:",
-                                                            idx
-                                                        );
-                                                        println!(
-                                                            "   LSB suspicious: {}",
-                                                            analysis.lsb_suspicious
-                                                        );
-                                                        println!(
-                                                            "   Histogram anomalies: {}",
-                                                            analysis.histogram_anomalies
-                                                        );
-                                                        println!(
-                                                            "   Edge density: {:.4}",
-                                                            analysis.edge_density
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                log::warn!("Frame {} analysis failed: {}", idx, e);
-                                            }
-                                        }
+                                    // Every frame goes through the scene detector; sampled
+                                    // frames are analyzed while their scene has budget left
+                                    scenes.observe(idx, &frame);
+                                    if sampler.sample(idx, frame_iter.last_timestamp(), &frame)
+                                        && scenes.admit()
+                                    {
+                                        pipeline.submit(idx, frame);
+                                    } else {
+                                        buffers.recycle(frame);
                                     }
                                 }
-                                Err(e) => {
+                                Some(Err(e)) => {
                                     error_count += 1;
                                     log::error!("Error decoding frame {}: {:?}", idx, e);
                                     if args.verbose {
                                         eprintln!("Detailed frame decode error: {:?}", e);
                                     }
                                 }
+                                None => {}
+                            }
+
+                            for (frame_idx, result) in pipeline.ready(done) {
+                                match result {
+                                    Ok(mut analysis) => {
+                                        analysis.lsb_suspicious = analysis.is_lsb_suspicious_at(
+                                            settings.thresholds.lsb_chi_square,
+                                            settings.thresholds.lsb_entropy,
+                                        );
+                                        frames_analyzed += 1;
+                                        scenes.record(&analysis);
+
+                                        // Collect entropy for averaging
+                                        let avg_entropy: f64 =
+                                            analysis.entropy_scores.iter().sum::<f64>()
+                                                / analysis.entropy_scores.len() as f64;
+                                        total_entropy += avg_entropy;
+
+                                        // Track anomalies
+                                        if analysis.lsb_suspicious || analysis.histogram_anomalies {
+                                            suspicious_frame_indices.push(frame_idx);
+
+                                            if args.verbose {
+                                                println!(
+                                                    "\n⚠️  Suspicious frame {} detected:",
+                                                    frame_idx
+                                                );
+                                                println!(
+                                                    "   LSB suspicious: {}",
+                                                    analysis.lsb_suspicious
+                                                );
+                                                println!(
+                                                    "   Histogram anomalies: {}",
+                                                    analysis.histogram_anomalies
+                                                );
+                                                println!(
+                                                    "   Edge density: {:.4}",
+                                                    analysis.edge_density
+                                                );
+                                            }
+                                        }
+                                    }
+                                    Err(e) => {
+                                        log::warn!("Frame {} analysis failed: {}", frame_idx, e);
+                                    }
+                                }
                             }
+
+                            if done {
+                                break;
+                            }
+                            idx += 1;
                         }
 
+                        let scenes = scenes.finish();
                         let avg_entropy = if frames_analyzed > 0 {
                             total_entropy / frames_analyzed as f64
                         } else {
@@ -435,6 +4814,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("Suspicious frames: {}", suspicious_frame_indices.len());
                         println!("Average entropy: {:.4}", avg_entropy);
                         println!("Errors encountered: {}", error_count);
+                        println!("Scenes: {}", scenes.len());
+                        let skipped: usize = scenes.iter().map(|s| s.frames_skipped).sum();
+                        if skipped > 0 {
+                            println!(
+                                "Sampled frames skipped (over {} per scene): {}",
+                                settings.video.max_frames_per_scene, skipped
+                            );
+                        }
+
+                        for scene in scenes.iter().filter(|s| s.suspicious) {
+                            println!(
+                                "\n⚠️  Scene at frames {}-{}: LSB statistics of frames {:?} differ {:.1}x from the rest of the scene",
+                                scene.start_frame,
+                                scene.end_frame,
+                                scene.divergent_frames,
+                                scene.lsb_divergence
+                            );
+                        }
 
                         if !suspicious_frame_indices.is_empty() {
                             println!(
@@ -444,9 +4841,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             println!("Consider extracting these frames for detailed analysis");
                         }
 
+                        let mp4_tags = mp4_tags_report(&file_object.file_path, settings);
                         report.set_format_analysis(FormatSpecificAnalysis::Video(VideoAnalysis {
                             frames_processed: frame_count,
                             errors_encountered: error_count,
+                            suspicious_frames: suspicious_frame_indices,
+                            sampling: settings.video_sampling().to_string(),
+                            scenes: scenes.iter().map(scene_report).collect(),
+                            mp4_tags,
                         }));
                     }
                     Err(e) => {
@@ -458,6 +4860,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
             }
+            FileType::Text if !settings.analyzers.text => {
+                println!("Text analysis skipped (disabled)");
+            }
             FileType::Text => match TextParser::parse_path(&file_object.file_path) {
                 Ok(text_content) => {
                     println!("\n=== Text File Analysis ===");
@@ -486,13 +4891,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
 
-                    report.set_format_analysis(FormatSpecificAnalysis::Text(TextAnalysis {
-                        file_type: text_content.file_type.clone(),
-                        line_count: text_content.line_count,
-                        word_count: text_content.word_count,
-                        character_count: text_content.char_count,
-                        size_bytes: text_content.byte_size,
-                    }));
+                    // Office documents, ebooks and 3D models are containers of their own
+                    let ole = ole_report(&file_object.file_path, settings, &custom_signatures);
+                    let ebook = ebook_report(&file_object.file_path, settings);
+                    let model = model_report(&file_object.file_path, settings);
+                    let structured =
+                        structured_report(&file_object.file_path, &text_content.content, settings);
+                    let source_code =
+                        source_code_report(&file_object.file_path, &text_content.content, settings);
+
+                    report.set_format_analysis(FormatSpecificAnalysis::Text(Box::new(
+                        TextAnalysis {
+                            file_type: text_content.file_type.clone(),
+                            line_count: text_content.line_count,
+                            word_count: text_content.word_count,
+                            character_count: text_content.char_count,
+                            size_bytes: text_content.byte_size,
+                            ole,
+                            ebook,
+                            model,
+                            structured,
+                            source_code,
+                        },
+                    )));
                 }
                 Err(e) => {
                     log::error!("Error parsing text file: {:?}", e);
@@ -500,211 +4921,900 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             },
             FileType::Image => {
+                // Sub-images are enumerated first: HEIF can't be decoded by ImageParser, but
+                // its item structure can still be checked
+                let page_analysis = if settings.analyzers.pages {
+                    page_report(&file_object.file_path, settings)
+                } else {
+                    None
+                };
+                if let Some(pages) = &page_analysis {
+                    println!("\n--- Multi-Image Page Analysis ---");
+                    println!("{} file with {} sub-images", pages.format, pages.page_count);
+                    for page in &pages.pages {
+                        match (&page.error, page.outlier_score) {
+                            (Some(e), _) => println!("  {} - not analyzed: {}", page.label, e),
+                            (None, score) => println!(
+                                "  {} - {}x{}, LSB entropy: {:.4}, chi-square/pair: {:.4}, outlier score: {}",
+                                page.label,
+                                page.width.unwrap_or_default(),
+                                page.height.unwrap_or_default(),
+                                page.mean_lsb_entropy.unwrap_or_default(),
+                                page.chi_square_per_pair.unwrap_or_default(),
+                                score.map_or("n/a".to_string(), |s| format!("{:.2}", s))
+                            ),
+                        }
+                    }
+                    if !pages.outlier_pages.is_empty() {
+                        println!(
+                            "\n⚠️  Pages with statistics far from the rest: {:?}",
+                            pages.outlier_pages
+                        );
+                    }
+                    if !pages.hidden_pages.is_empty() {
+                        println!(
+                            "\n⚠️  Image items stored but never displayed: {:?}",
+                            pages.hidden_pages
+                        );
+                    }
+                }
+
                 let image = match ImageParser::parse_path(&file_object.file_path) {
                     Ok(image) => image,
                     Err(err) => {
                         log::error!("Error while reading image: {err}");
+                        if page_analysis.is_some() {
+                            report.set_format_analysis(FormatSpecificAnalysis::Image(Box::new(
+                                ImageAnalysis {
+                                    exif_metadata: None,
+                                    missing_metadata: None,
+                                    lsb_analysis: None,
+                                    ws_analysis: None,
+                                    channel_correlation: None,
+                                    adaptive_lsb: None,
+                                    bpcs: None,
+                                    calibration: None,
+                                    webp_analysis: None,
+                                    gif_analysis: None,
+                                    png_text: None,
+                                    page_analysis,
+                                    filter_analysis: None,
+                                    dimensions: ImageDimensions::default(),
+                                },
+                            )));
+                        }
                         continue;
                     }
                 };
 
                 println!("\n=== Image Analysis ===");
+                println!(
+                    "Bit depth: {} bits per channel",
+                    ImageParser::channel_bit_depth(&image)
+                );
+
+                // CMYK/YCCK JPEGs are analyzed on their stored channels, not the RGB conversion
+                let native_jpeg = match ImageParser::parse_native_jpeg(&file_object.file_path) {
+                    Ok(native) => native,
+                    Err(e) => {
+                        log::warn!("Native JPEG decode failed, analyzing RGB instead: {}", e);
+                        None
+                    }
+                };
+                let color_model = native_jpeg
+                    .as_ref()
+                    .map(|native| native.color_model)
+                    .unwrap_or_else(|| ImageParser::color_model(&image));
+                println!("Color model: {}", color_model);
 
                 let mut image_analysis = ImageAnalysis {
                     exif_metadata: None,
+                    missing_metadata: None,
                     lsb_analysis: None,
-                    filter_analysis: FilterAnalysisReport {
-                        filters_generated: 0,
-                        output_files: Vec::new(),
+                    ws_analysis: None,
+                    channel_correlation: None,
+                    adaptive_lsb: None,
+                    bpcs: None,
+                    calibration: None,
+                    webp_analysis: None,
+                    gif_analysis: None,
+                    png_text: None,
+                    page_analysis,
+                    filter_analysis: None,
+                    dimensions: ImageDimensions {
+                        width: image.width(),
+                        height: image.height(),
                     },
                 };
 
+                // Photos and screenshots differ in both their LSB baseline and the metadata
+                // they're expected to carry
+                let content = (settings.analyzers.exif || settings.analyzers.lsb)
+                    .then(|| ContentClassifier::analyze(image.clone()).ok())
+                    .flatten()
+                    .map(|mut content| {
+                        content.class = content.class_at(settings.thresholds.content_flat_ratio);
+                        content
+                    });
+
                 // EXIF Metadata Analysis
-                println!("\n--- EXIF Metadata ---");
-                match ExifAnalyzerWithPath::new(&file_object.file_path).analyze() {
-                    Ok(exif_data) => {
-                        println!("EXIF fields found: {}", exif_data.metadata.len());
-                        println!("Has thumbnail: {}", exif_data.has_thumbnail);
+                if settings.analyzers.exif {
+                    println!("\n--- EXIF Metadata ---");
+                    let exif_data = match ExifAnalyzerWithPath::new(&file_object.file_path)
+                        .analyze()
+                    {
+                        Ok(exif_data) => {
+                            println!("EXIF fields found: {}", exif_data.metadata.len());
+                            println!("Has thumbnail: {}", exif_data.has_thumbnail);
 
-                        if let Some(size) = exif_data.thumbnail_size {
-                            println!("Thumbnail size: {} bytes", size);
-                        }
+                            if let Some(size) = exif_data.thumbnail_size {
+                                println!("Thumbnail size: {} bytes", size);
+                            }
 
-                        if !exif_data.comment_fields.is_empty() {
-                            println!("\nComment fields:");
-                            for comment in &exif_data.comment_fields {
-                                println!("  {}", comment);
+                            if let Some(ref maker_note) = exif_data.maker_note {
+                                println!(
+                                    "MakerNote: {} layout, {} entries, {} of {} bytes accounted for",
+                                    maker_note.layout,
+                                    maker_note.entries.len(),
+                                    maker_note.accounted_bytes,
+                                    maker_note.size
+                                );
                             }
-                        }
 
-                        if !exif_data.suspicious_fields.is_empty() {
-                            println!("\n⚠️  Suspicious EXIF findings:");
-                            for finding in &exif_data.suspicious_fields {
-                                println!("  - {}", finding);
+                            if !exif_data.comment_fields.is_empty() {
+                                println!("\nComment fields:");
+                                for comment in &exif_data.comment_fields {
+                                    println!("  {}", comment);
+                                }
                             }
-                        }
 
-                        if args.verbose && !exif_data.metadata.is_empty() {
-                            println!("\nAll EXIF data:");
-                            for (key, value) in &exif_data.metadata {
-                                println!("  {}: {}", key, value);
+                            if !exif_data.suspicious_fields.is_empty() {
+                                println!("\n⚠️  Suspicious EXIF findings:");
+                                for finding in &exif_data.suspicious_fields {
+                                    println!("  - {}", finding);
+                                }
+                            }
+
+                            if args.verbose && !exif_data.metadata.is_empty() {
+                                println!("\nAll EXIF data:");
+                                for (key, value) in &exif_data.metadata {
+                                    println!("  {}: {}", key, value);
+                                }
+                            }
+
+                            image_analysis.exif_metadata = Some(exif_report(&exif_data));
+                            Some(exif_data)
+                        }
+                        Err(e) => {
+                            if args.verbose {
+                                log::info!(
+                                    "EXIF analysis skipped: {} (format may not support EXIF)",
+                                    e
+                                );
+                            } else {
+                                println!(
+                                    "No EXIF data found (format may not support EXIF metadata)"
+                                );
                             }
+                            None
                         }
+                    };
 
-                        image_analysis.exif_metadata = Some(ExifReport {
-                            fields_found: exif_data.metadata.len(),
-                            has_thumbnail: exif_data.has_thumbnail,
-                            thumbnail_size_bytes: exif_data.thumbnail_size,
-                            comment_fields: exif_data.comment_fields.clone(),
-                            suspicious_fields: exif_data.suspicious_fields.clone(),
-                            metadata: exif_data
-                                .metadata
-                                .iter()
-                                .map(|(k, v)| MetadataField {
-                                    key: k.clone(),
-                                    value: v.clone(),
-                                })
-                                .collect(),
+                    // A camera photo without camera tags has likely had its metadata stripped
+                    let mime = match shared::infer().get_from_path(&file_object.file_path) {
+                        Ok(Some(kind)) => kind.mime_type(),
+                        _ => "",
+                    };
+                    if let Some(content) = &content
+                        && let Some(missing) =
+                            missing_metadata(mime, content.class, exif_data.as_ref())
+                    {
+                        println!(
+                            "\nLooks like a {} but has no {} metadata (possibly stripped)",
+                            missing.expected_source,
+                            missing.missing_fields.join(", ")
+                        );
+                        image_analysis.missing_metadata = Some(MissingMetadataReport {
+                            expected_source: missing.expected_source.to_string(),
+                            missing_fields: missing.missing_fields,
                         });
                     }
-                    Err(e) => {
-                        if args.verbose {
-                            log::info!(
-                                "EXIF analysis skipped: {} (format may not support EXIF)",
-                                e
+                }
+
+                // LSB Analysis
+                if settings.analyzers.lsb {
+                    println!("\n--- LSB Steganography Analysis ---");
+                    let lsb_result = match &native_jpeg {
+                        Some(native) => LsbAnalyzer::analyze_interleaved(
+                            &native.data,
+                            native.color_model.channel_names(),
+                        ),
+                        None => LsbAnalyzer::analyze(image.clone()),
+                    };
+                    match lsb_result {
+                        Ok(mut lsb_analysis) => {
+                            // Screenshots have flat regions whose constant LSBs look anything
+                            // but random, so they are judged on a baseline of their own
+                            // Judged against clean covers of the same format and class
+                            // when there's a baseline for them
+                            let deviation = content.as_ref().and_then(|content| {
+                                lsb_baselines.deviation(
+                                    CoverFormat::from_path(&file_object.file_path),
+                                    content.class,
+                                    &lsb_analysis,
+                                )
+                            });
+                            lsb_analysis.suspicious = lsb_verdict(
+                                &lsb_analysis,
+                                content.as_ref(),
+                                deviation.as_ref(),
+                                settings,
                             );
-                        } else {
-                            println!("No EXIF data found (format may not support EXIF metadata)");
+
+                            if let Some(content) = &content {
+                                println!(
+                                    "Content: {} ({:.1}% flat neighbours, {} colors, {:.2}% LSB noise in flat runs)",
+                                    content.class,
+                                    content.flat_ratio * 100.0,
+                                    content.unique_colors,
+                                    content.flat_lsb_noise * 100.0
+                                );
+                            }
+                            if let Some(deviation) = &deviation {
+                                println!(
+                                    "Baseline: {}/{}, up to {:.1} standard deviations from clean covers (flagged beyond {})",
+                                    deviation.baseline.format,
+                                    deviation.baseline.content,
+                                    deviation.max_z(),
+                                    settings.thresholds.calibration_sigma
+                                );
+                            }
+                            println!("Suspicious: {}", lsb_analysis.suspicious);
+
+                            let mut lsb_channels = Vec::new();
+                            for (i, score) in lsb_analysis.chi_square_scores.iter().enumerate() {
+                                let channel = &lsb_analysis.channel_names[i];
+                                println!(
+                                    "  {} channel - Chi-square: {:.2}, Entropy: {:.4}",
+                                    channel, score, lsb_analysis.entropy_scores[i]
+                                );
+
+                                lsb_channels.push(LsbChannelAnalysis {
+                                    channel_name: channel.to_string(),
+                                    chi_square_score: *score,
+                                    entropy_score: lsb_analysis.entropy_scores[i],
+                                });
+                            }
+
+                            if lsb_analysis.suspicious {
+                                println!("\n⚠️  LSB analysis indicates possible hidden data!");
+                            }
+
+                            let fname = artifact_stem(&file_object.file_path);
+                            let mut lsb_output_files = Vec::new();
+                            for (i, lsb_plane) in lsb_analysis.lsb_planes.iter().enumerate() {
+                                let channel = lsb_analysis.channel_names[i].to_lowercase();
+                                let output_file = settings
+                                    .artifact_path(&format!("{}_lsb_{}.png", fname, channel))
+                                    .to_string_lossy()
+                                    .to_string();
+                                lsb_plane.save(&output_file).unwrap();
+                                lsb_output_files.push(output_file);
+                            }
+                            println!(
+                                "LSB plane images saved to {}/",
+                                settings.output.dir.display()
+                            );
+
+                            image_analysis.lsb_analysis = Some(LsbReport {
+                                is_suspicious: lsb_analysis.suspicious,
+                                bit_depth: lsb_analysis.bit_depth,
+                                color_model: color_model.to_string(),
+                                channels: lsb_channels,
+                                content: content.as_ref().map(content_class_report),
+                                baseline: deviation.as_ref().map(|deviation| {
+                                    lsb_baseline_report(
+                                        deviation,
+                                        settings.thresholds.calibration_sigma,
+                                    )
+                                }),
+                                output_files: lsb_output_files,
+                            });
+                        }
+                        Err(e) => {
+                            log::error!("LSB analysis failed: {}", e);
                         }
                     }
                 }
 
-                // LSB Analysis
-                println!("\n--- LSB Steganography Analysis ---");
-                match LsbAnalyzer::analyze(image.clone()) {
-                    Ok(lsb_analysis) => {
-                        println!("Suspicious: {}", lsb_analysis.suspicious);
-
-                        let mut lsb_channels = Vec::new();
-                        for (i, score) in lsb_analysis.chi_square_scores.iter().enumerate() {
-                            let channel = match i {
-                                0 => "Red",
-                                1 => "Green",
-                                2 => "Blue",
-                                _ => "Unknown",
-                            };
+                // Order-independent LSB estimators, for payloads scattered along a random path
+                if settings.analyzers.ws {
+                    println!("\n--- Sample Pair / Weighted-Stego Analysis ---");
+                    match WsAnalyzer::analyze(image.clone()) {
+                        Ok(mut ws_analysis) => {
+                            ws_analysis.suspicious =
+                                ws_analysis.is_suspicious_at(settings.thresholds.ws_payload_rate);
+
+                            let mut ws_channels = Vec::new();
+                            for (i, estimate) in ws_analysis.channels.iter().enumerate() {
+                                let channel = match i {
+                                    0 => "Red",
+                                    1 => "Green",
+                                    2 => "Blue",
+                                    _ => "Unknown",
+                                };
+                                println!(
+                                    "  {} channel - SPA rate: {:.4}, WS rate: {:.4}",
+                                    channel,
+                                    estimate.sample_pair_rate,
+                                    estimate.weighted_stego_rate
+                                );
+
+                                ws_channels.push(WsChannelReport {
+                                    channel_name: channel.to_string(),
+                                    sample_pair_rate: estimate.sample_pair_rate,
+                                    weighted_stego_rate: estimate.weighted_stego_rate,
+                                });
+                            }
                             println!(
-                                "  {} channel - Chi-square: {:.2}, Entropy: {:.4}",
-                                channel, score, lsb_analysis.entropy_scores[i]
+                                "Estimated change rate: {:.2}% of LSBs",
+                                ws_analysis.estimated_change_rate * 100.0
                             );
 
-                            lsb_channels.push(LsbChannelAnalysis {
-                                channel_name: channel.to_string(),
-                                chi_square_score: *score,
-                                entropy_score: lsb_analysis.entropy_scores[i],
+                            if ws_analysis.suspicious {
+                                println!(
+                                    "\n⚠️  Pair analysis indicates LSB embedding, regardless of embedding order!"
+                                );
+                            }
+
+                            image_analysis.ws_analysis = Some(WsReport {
+                                is_suspicious: ws_analysis.suspicious,
+                                estimated_payload_rate: ws_analysis.estimated_payload_rate,
+                                estimated_change_rate: ws_analysis.estimated_change_rate,
+                                channels: ws_channels,
                             });
                         }
+                        Err(e) => {
+                            log::error!("Pair analysis failed: {}", e);
+                        }
+                    }
+                }
+
+                // Smooth regions keep R/G/B LSBs in step; embedding breaks that per block
+                if settings.analyzers.channel_correlation {
+                    println!("\n--- Channel Correlation Analysis ---");
+                    match ChannelCorrelationAnalyzer::analyze(image.clone()) {
+                        Ok(mut correlation) => {
+                            correlation.suspicious = correlation.is_suspicious_at(
+                                settings.thresholds.channel_correlation_collapsed_ratio,
+                            );
+
+                            println!(
+                                "Smooth blocks evaluated: {} ({}x{} px)",
+                                correlation.blocks_evaluated,
+                                correlation.block_size,
+                                correlation.block_size
+                            );
+                            println!(
+                                "Mean LSB correlation: {:.4} (bit 1 reference: {:.4})",
+                                correlation.mean_lsb_correlation,
+                                correlation.mean_reference_correlation
+                            );
+                            println!(
+                                "Collapsed blocks: {} ({:.2}%)",
+                                correlation.collapsed_block_count,
+                                correlation.collapsed_ratio * 100.0
+                            );
+
+                            let mut overlay = None;
+                            if correlation.suspicious {
+                                println!(
+                                    "\n⚠️  Inter-channel LSB correlation collapsed in smooth regions!"
+                                );
+                                overlay = save_overlay(
+                                    &image,
+                                    &correlation.collapsed_regions,
+                                    &settings.artifact_path(&format!(
+                                        "{}_channel_correlation_overlay.png",
+                                        artifact_stem(&file_object.file_path)
+                                    )),
+                                );
+                            }
 
-                        if lsb_analysis.suspicious {
-                            println!("\n⚠️  LSB analysis indicates possible hidden data!");
+                            image_analysis.channel_correlation = Some(ChannelCorrelationReport {
+                                is_suspicious: correlation.suspicious,
+                                block_size: correlation.block_size,
+                                blocks_evaluated: correlation.blocks_evaluated,
+                                collapsed_block_count: correlation.collapsed_block_count,
+                                collapsed_ratio: correlation.collapsed_ratio,
+                                mean_lsb_correlation: correlation.mean_lsb_correlation,
+                                mean_reference_correlation: correlation.mean_reference_correlation,
+                                collapsed_blocks: correlation
+                                    .collapsed_blocks
+                                    .iter()
+                                    .map(|block| CollapsedBlockReport {
+                                        x: block.x,
+                                        y: block.y,
+                                        lsb_correlation: block.lsb_correlation,
+                                        reference_correlation: block.reference_correlation,
+                                    })
+                                    .collect(),
+                                overlay,
+                            });
+                        }
+                        Err(e) => {
+                            log::error!("Channel correlation analysis failed: {}", e);
                         }
+                    }
+                }
+
+                // Adaptive embedders hide in texture, so compare textured and smooth regions
+                if settings.analyzers.adaptive_lsb {
+                    println!("\n--- Content-Adaptive LSB Analysis ---");
+                    match AdaptiveLsbAnalyzer::analyze(image.clone()) {
+                        Ok(mut adaptive) => {
+                            adaptive.suspicious = adaptive
+                                .is_suspicious_at(settings.thresholds.adaptive_lsb_contrast);
+
+                            for (name, region) in [
+                                ("Smooth", &adaptive.smooth),
+                                ("Textured", &adaptive.textured),
+                            ] {
+                                println!(
+                                    "  {} regions - blocks: {}, PoV score: {:.4}, LSB ones: {:.4}",
+                                    name, region.blocks, region.pov_score, region.lsb_ones_ratio
+                                );
+                            }
+                            println!("Smooth/textured contrast: {:.4}", adaptive.pov_contrast);
+
+                            let mut overlay = None;
+                            if adaptive.suspicious {
+                                println!(
+                                    "\n⚠️  LSBs look random only in textured regions (adaptive embedding)!"
+                                );
+                                overlay = save_overlay(
+                                    &image,
+                                    &adaptive.textured_regions,
+                                    &settings.artifact_path(&format!(
+                                        "{}_adaptive_lsb_overlay.png",
+                                        artifact_stem(&file_object.file_path)
+                                    )),
+                                );
+                            }
 
-                        let fname = file_object.file_path.file_name().unwrap().to_str().unwrap();
-                        let mut lsb_output_files = Vec::new();
-                        for (i, lsb_plane) in lsb_analysis.lsb_planes.iter().enumerate() {
-                            let channel = match i {
-                                0 => "red",
-                                1 => "green",
-                                2 => "blue",
-                                _ => "unknown",
+                            let region_report = |region: &RegionStats| RegionLsbReport {
+                                blocks: region.blocks,
+                                pov_score: region.pov_score,
+                                lsb_ones_ratio: region.lsb_ones_ratio,
                             };
-                            let output_file = format!("outputs/{}_lsb_{}.png", fname, channel);
-                            lsb_plane.save(&output_file).unwrap();
-                            lsb_output_files.push(output_file);
+                            image_analysis.adaptive_lsb = Some(AdaptiveLsbReport {
+                                is_suspicious: adaptive.suspicious,
+                                block_size: adaptive.block_size,
+                                smooth: region_report(&adaptive.smooth),
+                                textured: region_report(&adaptive.textured),
+                                pov_contrast: adaptive.pov_contrast,
+                                overlay,
+                            });
+                        }
+                        Err(e) => {
+                            log::error!("Adaptive LSB analysis failed: {}", e);
+                        }
+                    }
+                }
+
+                // BPCS overwrites noisy blocks in every bit plane, not just the LSBs
+                if settings.analyzers.bpcs {
+                    println!("\n--- BPCS (Bit-Plane Complexity) Analysis ---");
+                    match BpcsAnalyzer::analyze(image.clone()) {
+                        Ok(mut bpcs) => {
+                            bpcs.suspicious =
+                                bpcs.is_suspicious_at(settings.thresholds.bpcs_noise_planes);
+
+                            if args.verbose {
+                                for plane in &bpcs.planes {
+                                    println!(
+                                        "  {} plane {} - complex blocks: {}, simple: {:.2}, mean complexity: {:.3}, near threshold: {:.3}",
+                                        plane.channel,
+                                        plane.plane,
+                                        plane.complex_blocks,
+                                        plane.simple_ratio,
+                                        plane.mean_complexity,
+                                        plane.transition_ratio
+                                    );
+                                }
+                            }
+                            println!(
+                                "Planes with random-looking complex blocks: {}",
+                                bpcs.noise_planes
+                            );
+
+                            let mut overlay = None;
+                            if bpcs.suspicious {
+                                println!(
+                                    "\n⚠️  Complex blocks in structured bit planes look like random data (BPCS embedding)!"
+                                );
+                                overlay = save_overlay(
+                                    &image,
+                                    &bpcs.flagged_regions,
+                                    &settings.artifact_path(&format!(
+                                        "{}_bpcs_overlay.png",
+                                        artifact_stem(&file_object.file_path)
+                                    )),
+                                );
+                            }
+
+                            image_analysis.bpcs = Some(bpcs_report(&bpcs, overlay));
+                        }
+                        Err(e) => {
+                            log::error!("BPCS analysis failed: {}", e);
+                        }
+                    }
+                }
+
+                // F5-style embedding in JPEG coefficients, measured against a calibrated
+                // reference rather than absolute thresholds
+                if settings.analyzers.calibration {
+                    // Only JPEGs get past the signature check
+                    let calibration = match std::fs::read(&file_object.file_path) {
+                        Ok(data) => match CalibrationAnalyzer::analyze(data) {
+                            Ok(calibration) => Some(calibration),
+                            Err(CalibrationError::NotJpeg) => None,
+                            Err(e) => {
+                                log::error!("Calibration analysis failed: {}", e);
+                                None
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("Error reading file for calibration analysis: {}", e);
+                            None
+                        }
+                    };
+
+                    if let Some(mut calibration) = calibration {
+                        println!("\n--- JPEG Calibration Analysis ---");
+                        calibration.suspicious = calibration
+                            .is_suspicious_at(settings.thresholds.calibration_change_rate);
+
+                        if args.verbose {
+                            for mode in &calibration.modes {
+                                println!(
+                                    "  mode ({},{}) - |0|,|1|,|2| observed: {:?}, reference: {:?}, divergence: {:.3}",
+                                    mode.mode.0,
+                                    mode.mode.1,
+                                    mode.observed,
+                                    mode.reference,
+                                    mode.divergence
+                                );
+                            }
+                        }
+                        println!(
+                            "Estimated change rate: {:.1}% (divergence {:.3} over {} blocks)",
+                            calibration.estimated_change_rate * 100.0,
+                            calibration.divergence,
+                            calibration.blocks
+                        );
+                        if calibration.suspicious {
+                            println!(
+                                "\n⚠️  DCT histograms differ from the calibrated reference (F5-style embedding)!"
+                            );
+                        }
+
+                        image_analysis.calibration = Some(calibration_report(calibration));
+                    }
+                }
+
+                // WebP container checks, plus every frame of an animation (ImageParser only
+                // decodes the first)
+                if settings.analyzers.webp {
+                    // The analyzer's only error is "not a WebP", which just means there's nothing to do
+                    let structure = match std::fs::read(&file_object.file_path) {
+                        Ok(data) => WebpStructureAnalyzer::analyze(data).ok(),
+                        Err(e) => {
+                            log::error!("Error reading file for WebP analysis: {}", e);
+                            None
+                        }
+                    };
+
+                    if let Some(structure) = structure {
+                        println!("\n--- WebP Structure & Frame Analysis ---");
+                        println!(
+                            "RIFF size: {} bytes, {} chunks, animated: {}",
+                            structure.riff_size,
+                            structure.chunks.len(),
+                            structure.animated
+                        );
+                        if args.verbose {
+                            for chunk in &structure.chunks {
+                                println!(
+                                    "  {} at offset {} ({} bytes)",
+                                    chunk.fourcc, chunk.offset, chunk.size
+                                );
+                            }
+                        }
+                        if !structure.anomalies.is_empty() {
+                            println!("\n⚠️  WebP structure anomalies:");
+                            for anomaly in &structure.anomalies {
+                                println!("  - {}", anomaly);
+                            }
+                        }
+
+                        let mut frames = Vec::new();
+                        let mut suspicious_frames = Vec::new();
+                        let mut frame_count = 0;
+                        if structure.animated {
+                            match WebpParser::parse_path(&file_object.file_path) {
+                                Ok(webp) => {
+                                    frame_count = webp.frames.len();
+                                    for (idx, frame) in webp.frames.into_iter().enumerate() {
+                                        match VideoFrameAnalyzer::analyze(frame.image) {
+                                            Ok(mut analysis) => {
+                                                analysis.lsb_suspicious = analysis
+                                                    .is_lsb_suspicious_at(
+                                                        settings.thresholds.lsb_chi_square,
+                                                        settings.thresholds.lsb_entropy,
+                                                    );
+                                                if analysis.lsb_suspicious
+                                                    || analysis.histogram_anomalies
+                                                {
+                                                    suspicious_frames.push(idx);
+                                                }
+                                                frames.push(WebpFrameReport {
+                                                    index: idx,
+                                                    delay_ms: frame.delay_ms,
+                                                    lsb_suspicious: analysis.lsb_suspicious,
+                                                    histogram_anomalies: analysis
+                                                        .histogram_anomalies,
+                                                    chi_square_scores: analysis.chi_square_scores,
+                                                    entropy_scores: analysis.entropy_scores,
+                                                });
+                                            }
+                                            Err(e) => {
+                                                log::warn!(
+                                                    "WebP frame {} analysis failed: {}",
+                                                    idx,
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    }
+                                    println!(
+                                        "Frames analyzed: {} ({} suspicious)",
+                                        frames.len(),
+                                        suspicious_frames.len()
+                                    );
+                                    if !suspicious_frames.is_empty() {
+                                        println!(
+                                            "\n⚠️  Suspicious WebP frames: {:?}",
+                                            suspicious_frames
+                                        );
+                                    }
+                                }
+                                Err(e) => {
+                                    log::error!("Error decoding WebP frames: {}", e);
+                                }
+                            }
                         }
-                        println!("LSB plane images saved to outputs/");
 
-                        image_analysis.lsb_analysis = Some(LsbReport {
-                            is_suspicious: lsb_analysis.suspicious,
-                            channels: lsb_channels,
-                            output_files: lsb_output_files,
+                        image_analysis.webp_analysis = Some(WebpReport {
+                            animated: structure.animated,
+                            riff_size: structure.riff_size,
+                            chunks: structure
+                                .chunks
+                                .iter()
+                                .map(|chunk| WebpChunkReport {
+                                    fourcc: chunk.fourcc.clone(),
+                                    offset: chunk.offset,
+                                    size: chunk.size,
+                                })
+                                .collect(),
+                            trailing_bytes: structure.trailing_bytes,
+                            structure_anomalies: structure.anomalies,
+                            frame_count,
+                            frames,
+                            suspicious_frames,
                         });
                     }
-                    Err(e) => {
-                        log::error!("LSB analysis failed: {}", e);
+                }
+
+                // GIF extension blocks are never displayed, so decoders skip whatever is in them
+                if settings.analyzers.gif {
+                    // The analyzer's only error is "not a GIF", which just means there's nothing to do
+                    let gif = match std::fs::read(&file_object.file_path) {
+                        Ok(data) => GifExtensionAnalyzer::analyze(data).ok(),
+                        Err(e) => {
+                            log::error!("Error reading file for GIF analysis: {}", e);
+                            None
+                        }
+                    };
+
+                    if let Some(gif) = gif {
+                        println!("\n--- GIF Extension Analysis ---");
+                        println!(
+                            "GIF{}: {} frames, {} comments, {} application extensions, {} plain text blocks",
+                            gif.version,
+                            gif.frame_count,
+                            gif.comments.len(),
+                            gif.applications.len(),
+                            gif.plain_text_blocks
+                        );
+                        for comment in &gif.comments {
+                            println!(
+                                "  Comment at offset {} ({} bytes, {}): \"{}\"",
+                                comment.offset,
+                                comment.size,
+                                comment.classification,
+                                comment.preview
+                            );
+                        }
+                        for app in &gif.applications {
+                            println!(
+                                "  Application {} at offset {} ({} bytes)",
+                                app.identifier.escape_debug(),
+                                app.offset,
+                                app.size
+                            );
+                        }
+                        if !gif.anomalies.is_empty() {
+                            println!("\n⚠️  GIF extension anomalies:");
+                            for anomaly in &gif.anomalies {
+                                println!("  - {}", anomaly);
+                            }
+                        }
+
+                        image_analysis.gif_analysis = Some(gif_report(gif));
                     }
                 }
 
-                // Image Filter Analysis
-                println!("\n--- Image Filter Analysis ---");
-                if args.verbose {
-                    log::info!("Generating filtered images...");
-                }
-
-                match ImageFilterAnalyzer::analyze(image) {
-                    Ok(output) => {
-                        let mut filter_files = Vec::new();
-                        for (i, img) in output.iter().enumerate() {
-                            if args.verbose && i % 2 == 0 {
-                                log::info!("Saving filter {} of {}...", i + 1, output.len());
-                            }
-                            let filter_file = format!(
-                                "outputs/{}_filter_{}.avif",
-                                file_object.file_path.file_name().unwrap().to_str().unwrap(),
-                                i
+                // PNG text chunks are free-form key/value pairs no viewer shows
+                if settings.analyzers.png_text {
+                    // Only PNGs get past the signature check
+                    let png_text = match std::fs::read(&file_object.file_path) {
+                        Ok(data) => PngTextAnalyzer::analyze(data).ok(),
+                        Err(e) => {
+                            log::error!("Error reading file for PNG text analysis: {}", e);
+                            None
+                        }
+                    };
+
+                    if let Some(png_text) = png_text {
+                        println!("\n--- PNG Text Chunk Analysis ---");
+                        println!("Text chunks: {}", png_text.chunks.len());
+                        for chunk in &png_text.chunks {
+                            println!(
+                                "  {} '{}' at offset {} ({} bytes, {}): \"{}\"",
+                                chunk.chunk_type,
+                                chunk.keyword.escape_debug(),
+                                chunk.offset,
+                                chunk.size,
+                                chunk.classification,
+                                chunk.preview
                             );
-                            img.save(&filter_file).unwrap();
-                            filter_files.push(filter_file);
                         }
-                        println!("Generated {} filtered images", output.len());
+                        if !png_text.anomalies.is_empty() {
+                            println!("\n⚠️  PNG text chunk anomalies:");
+                            for anomaly in &png_text.anomalies {
+                                println!("  - {}", anomaly);
+                            }
+                        }
 
-                        image_analysis.filter_analysis = FilterAnalysisReport {
-                            filters_generated: output.len(),
-                            output_files: filter_files,
-                        };
+                        image_analysis.png_text = Some(png_text_report(png_text));
                     }
-                    Err(e) => {
-                        log::error!("Image filter analysis failed: {:?}", e);
+                }
+
+                // Image Filter Analysis
+                if settings.analyzers.filters {
+                    println!("\n--- Image Filter Analysis ---");
+                    if args.verbose {
+                        log::info!("Generating filtered images...");
+                    }
+
+                    match ImageFilterAnalyzer::analyze(image) {
+                        Ok(output) => {
+                            let mut filter_files = Vec::new();
+                            for (i, img) in output.iter().enumerate() {
+                                if args.verbose && i % 2 == 0 {
+                                    log::info!("Saving filter {} of {}...", i + 1, output.len());
+                                }
+                                let filter_file = settings
+                                    .artifact_path(&format!(
+                                        "{}_filter_{}.avif",
+                                        artifact_stem(&file_object.file_path),
+                                        i
+                                    ))
+                                    .to_string_lossy()
+                                    .to_string();
+                                img.save(&filter_file).unwrap();
+                                filter_files.push(filter_file);
+                            }
+                            println!("Generated {} filtered images", output.len());
+
+                            image_analysis.filter_analysis = Some(FilterAnalysisReport {
+                                filters_generated: output.len(),
+                                output_files: filter_files,
+                            });
+                        }
+                        Err(e) => {
+                            log::error!("Image filter analysis failed: {:?}", e);
+                        }
                     }
                 }
 
-                report.set_format_analysis(FormatSpecificAnalysis::Image(image_analysis));
+                report.set_format_analysis(FormatSpecificAnalysis::Image(Box::new(image_analysis)));
             }
         }
     }
 
     // Finalize and save report
-    report.finalize_summary();
+    let locale = settings.output.locale;
+    if initial_state.changed(file) {
+        log::warn!("{} changed while it was being scanned", file.display());
+        report.modified_during_scan = true;
+    }
+    report.run = Some(plan::run_metadata(&scan_plan, &report, settings));
+    report.finalize_summary(locale);
 
-    println!("\n╔═══════════════════════════════════════════════════════════╗");
-    println!("║          ANALYSIS SUMMARY                                ║");
-    println!("╚═══════════════════════════════════════════════════════════╝");
-    println!(
-        "Steganography detected: {}",
-        report.summary.steganography_detected
-    );
-    println!("Confidence level: {}", report.summary.confidence_level);
+    print_summary(&report, locale);
 
-    if !report.summary.threat_indicators.is_empty() {
-        println!("\nThreat indicators:");
-        for indicator in &report.summary.threat_indicators {
-            println!("  - {}", indicator);
+    if args.scan.explain {
+        let explanations = explain::explain_report(&report, &settings.thresholds, locale);
+        if !explanations.is_empty() {
+            println!("\n{}", tr(locale, "cli.explanations", &[]));
+            for explanation in &explanations {
+                println!("  - [{}] {}", explanation.rule_id, explanation.description);
+            }
         }
+        report.set_explanations(explanations);
     }
 
-    println!("\nRecommendations:");
+    println!("\n{}", tr(locale, "cli.recommendations", &[]));
     for recommendation in &report.summary.recommendations {
         println!("  - {}", recommendation);
     }
 
-    match report.save_to_file(&args.output) {
+    match report.save_to_file(&settings.output.report.to_string_lossy()) {
         Ok(_) => {
-            println!("\n✅ JSON report saved to: {}", args.output);
+            println!(
+                "\n✅ {}",
+                tr(
+                    locale,
+                    "cli.report_saved",
+                    &[("path", settings.output.report.display().to_string())]
+                )
+            );
         }
         Err(e) => {
             log::error!("Failed to save JSON report: {}", e);
         }
     }
 
-    Ok(())
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_file_shortcut_parses_like_scan() {
+        Args::command().debug_assert();
+
+        let shortcut = Args::try_parse_from(["stegascan", "photo.png", "--extract"]).unwrap();
+        assert!(shortcut.command.is_none());
+        assert_eq!(shortcut.scan.input, Some(PathBuf::from("photo.png")));
+        assert!(shortcut.scan.extract);
+
+        let scan =
+            Args::try_parse_from(["stegascan", "scan", "photo.png", "--extract", "-v"]).unwrap();
+        let Some(Command::Scan(scan_args)) = &scan.command else {
+            panic!("expected the scan subcommand");
+        };
+        assert_eq!(scan_args.input, Some(PathBuf::from("photo.png")));
+        assert!(scan_args.extract && scan.verbose);
+
+        assert!(Args::try_parse_from(["stegascan", "photo.png", "--file", "other.png"]).is_err());
+        assert!(
+            Args::try_parse_from(["stegascan", "report", "show", "outputs/report.json"]).is_ok()
+        );
+    }
 }
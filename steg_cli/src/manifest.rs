@@ -0,0 +1,381 @@
+use crate::config::{ConfigError, Settings};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Scan plan for --plan: a reviewable YAML or JSON file listing what to scan, which analyzers
+// to change per entry and where the reports go, in place of shell loops around --file.
+//
+//   output_dir: reports/nightly
+//   scans:
+//     - files: ["evidence/*.png", "evidence/cover.jpg"]
+//       disable: [filters]
+//     - files: ["audio/**/*.wav"]
+//       enable: [demodulation]
+//       output_dir: reports/audio
+//       report: "{stem}-audio.json"
+//
+// Relative paths in the plan are relative to the plan file, so a plan runs the same from
+// any working directory. Everything the plan doesn't set comes from the config file,
+// environment and flags as usual.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanManifest {
+    // Default artifact and report directory for entries without their own
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    pub scans: Vec<ScanEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanEntry {
+    // Paths or glob patterns; every pattern has to match at least one file
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub disable: Vec<String>,
+    #[serde(default)]
+    pub enable: Vec<String>,
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    // Report path for each matched file, relative to the output directory. {name} is the
+    // file name and {stem} the file name without its extension. Defaults to "{name}.json".
+    #[serde(default)]
+    pub report: Option<String>,
+}
+
+// One file to scan with the settings its entry resolved to
+#[derive(Debug)]
+pub struct PlannedScan {
+    pub file: PathBuf,
+    pub settings: Settings,
+    // The file or a directory between it and the plan file is a symbolic link
+    pub via_symlink: bool,
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    Pattern(String, String),
+    NoMatch(String),
+    Analyzer(ConfigError),
+    // Two scans would write the same report
+    DuplicateReport(PathBuf),
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            ManifestError::Parse(path, e) => {
+                write!(f, "Invalid scan plan {}: {}", path.display(), e)
+            }
+            ManifestError::Pattern(pattern, e) => {
+                write!(f, "Invalid file pattern {:?}: {}", pattern, e)
+            }
+            ManifestError::NoMatch(pattern) => write!(f, "No files match {:?}", pattern),
+            ManifestError::Analyzer(e) => write!(f, "{}", e),
+            ManifestError::DuplicateReport(path) => write!(
+                f,
+                "More than one scan writes {}, set a distinct report or output_dir",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl ScanManifest {
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ManifestError::Io(path.to_path_buf(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&text)
+                .map_err(|e| ManifestError::Parse(path.to_path_buf(), e.to_string()))
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| ManifestError::Parse(path.to_path_buf(), e.to_string()))
+        }
+    }
+
+    // Expand every entry into per-file scans, layering the entry's overrides over `settings`
+    pub fn resolve(
+        &self,
+        plan_file: &Path,
+        settings: &Settings,
+    ) -> Result<Vec<PlannedScan>, ManifestError> {
+        let base = plan_file.parent().unwrap_or(Path::new(""));
+        let mut scans = Vec::new();
+        let mut reports = HashSet::new();
+
+        for entry in &self.scans {
+            let mut entry_settings = settings.clone();
+            for name in &entry.disable {
+                entry_settings
+                    .analyzers
+                    .set(name, false)
+                    .map_err(ManifestError::Analyzer)?;
+            }
+            for name in &entry.enable {
+                entry_settings
+                    .analyzers
+                    .set(name, true)
+                    .map_err(ManifestError::Analyzer)?;
+            }
+            if let Some(dir) = entry.output_dir.as_ref().or(self.output_dir.as_ref()) {
+                entry_settings.output.dir = base.join(dir);
+            }
+            let template = entry.report.as_deref().unwrap_or("{name}.json");
+
+            for file in expand(base, &entry.files)? {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+                let report = entry_settings
+                    .output
+                    .dir
+                    .join(template.replace("{name}", &name).replace("{stem}", &stem));
+                if !reports.insert(report.clone()) {
+                    return Err(ManifestError::DuplicateReport(report));
+                }
+
+                let mut file_settings = entry_settings.clone();
+                file_settings.output.report = report;
+                scans.push(PlannedScan {
+                    via_symlink: via_symlink(base, &file),
+                    file,
+                    settings: file_settings,
+                });
+            }
+        }
+
+        Ok(scans)
+    }
+}
+
+// Only links below the plan's own directory count; wherever the plan itself lives is the
+// user's business
+fn via_symlink(base: &Path, file: &Path) -> bool {
+    file.ancestors()
+        .take_while(|path| *path != base && !path.as_os_str().is_empty())
+        .any(|path| path.is_symlink())
+}
+
+// Files a batch leaves out instead of scanning, from --skip-ext and --max-size. They are
+// listed in the batch results with the reason rather than counted as failures.
+#[derive(Debug, Clone, Default)]
+pub struct SkipFilters {
+    // Lowercase, without the leading dot
+    pub extensions: Vec<String>,
+    // 0 for no limit
+    pub max_size_mb: u64,
+}
+
+impl SkipFilters {
+    pub fn new(extensions: &[String], max_size_mb: Option<u64>) -> Self {
+        Self {
+            extensions: extensions
+                .iter()
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+            max_size_mb: max_size_mb.unwrap_or(0),
+        }
+    }
+
+    // Why `file` should be skipped, or None to scan it
+    pub fn skip_reason(&self, file: &Path) -> Option<String> {
+        let extension = file
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        if let Some(ext) = extension.filter(|ext| self.extensions.contains(ext)) {
+            return Some(format!("extension .{} is skipped", ext));
+        }
+
+        if self.max_size_mb > 0 {
+            let size = std::fs::metadata(file).map(|meta| meta.len()).unwrap_or(0);
+            if size > self.max_size_mb * 1024 * 1024 {
+                return Some(format!(
+                    "{} bytes, over the {} MB limit",
+                    size, self.max_size_mb
+                ));
+            }
+        }
+        None
+    }
+}
+
+// Files matched by the patterns in order, skipping directories
+fn expand(base: &Path, patterns: &[String]) -> Result<Vec<PathBuf>, ManifestError> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let full = base.join(pattern);
+        let matches = glob::glob(&full.to_string_lossy())
+            .map_err(|e| ManifestError::Pattern(pattern.clone(), e.to_string()))?;
+        let before = files.len();
+        files.extend(matches.flatten().filter(|path| path.is_file()));
+        if files.len() == before {
+            return Err(ManifestError::NoMatch(pattern.clone()));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stegascan_manifest_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("evidence")).unwrap();
+        for file in ["a.png", "b.png", "c.wav"] {
+            std::fs::write(dir.join("evidence").join(file), b"data").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_resolves_globs_and_overrides() {
+        let dir = plan_dir("resolve");
+        let plan_file = dir.join("plan.yaml");
+        std::fs::write(
+            &plan_file,
+            r#"
+output_dir: reports
+scans:
+  - files: ["evidence/*.png"]
+    disable: [filters, lsb]
+  - files: ["evidence/c.wav"]
+    enable: [demodulation]
+    output_dir: audio
+    report: "{stem}-audio.json"
+"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        settings.analyzers.demodulation = false;
+        let scans = ScanManifest::load(&plan_file)
+            .unwrap()
+            .resolve(&plan_file, &settings)
+            .unwrap();
+
+        assert_eq!(scans.len(), 3);
+        assert!(scans[0].file.ends_with("evidence/a.png"));
+        assert!(scans[1].file.ends_with("evidence/b.png"));
+        assert!(!scans[0].settings.analyzers.filters && !scans[0].settings.analyzers.lsb);
+        assert!(scans[0].settings.analyzers.exif);
+        assert_eq!(scans[0].settings.output.dir, dir.join("reports"));
+        assert_eq!(
+            scans[1].settings.output.report,
+            dir.join("reports").join("b.png.json")
+        );
+        assert!(scans[2].settings.analyzers.demodulation);
+        assert_eq!(
+            scans[2].settings.output.report,
+            dir.join("audio").join("c-audio.json")
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_unmatched_patterns_and_clashing_reports() {
+        let dir = plan_dir("errors");
+        let plan_file = dir.join("plan.json");
+        let settings = Settings::default();
+
+        let resolve = |plan: &str| {
+            std::fs::write(&plan_file, plan).unwrap();
+            ScanManifest::load(&plan_file)?.resolve(&plan_file, &settings)
+        };
+
+        assert!(matches!(
+            resolve(r#"{"scans": [{"files": ["missing/*.png"]}]}"#),
+            Err(ManifestError::NoMatch(_))
+        ));
+        assert!(matches!(
+            resolve(r#"{"scans": [{"files": ["evidence/*"], "report": "same.json"}]}"#),
+            Err(ManifestError::DuplicateReport(_))
+        ));
+        assert!(matches!(
+            resolve(r#"{"scans": [{"files": ["evidence/a.png"], "disable": ["nope"]}]}"#),
+            Err(ManifestError::Analyzer(ConfigError::UnknownAnalyzer(_)))
+        ));
+        assert!(matches!(
+            resolve(r#"{"scans": [], "unknown": 1}"#),
+            Err(ManifestError::Parse(..))
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_flags_files_reached_through_symlinks() {
+        let dir = plan_dir("symlinks");
+        std::os::unix::fs::symlink(dir.join("evidence"), dir.join("linked")).unwrap();
+        std::os::unix::fs::symlink(dir.join("evidence/a.png"), dir.join("evidence/alias.png"))
+            .unwrap();
+        let plan_file = dir.join("plan.yaml");
+        std::fs::write(
+            &plan_file,
+            r#"
+scans:
+  - files: ["evidence/a.png", "evidence/alias.png"]
+  - files: ["linked/b.png"]
+    output_dir: linked_reports
+"#,
+        )
+        .unwrap();
+
+        let scans = ScanManifest::load(&plan_file)
+            .unwrap()
+            .resolve(&plan_file, &Settings::default())
+            .unwrap();
+        let flags: Vec<bool> = scans.iter().map(|scan| scan.via_symlink).collect();
+        assert_eq!(flags, vec![false, true, true]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_skip_filters() {
+        let dir = plan_dir("skip");
+        let evidence = dir.join("evidence");
+        std::fs::write(evidence.join("big.iso"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+        std::fs::write(evidence.join("big.png"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+
+        let filters = SkipFilters::new(&[".ISO".to_string(), "wav".to_string()], Some(1));
+        assert_eq!(filters.extensions, vec!["iso", "wav"]);
+        assert_eq!(
+            filters.skip_reason(&evidence.join("c.wav")).as_deref(),
+            Some("extension .wav is skipped")
+        );
+        assert!(
+            filters
+                .skip_reason(&evidence.join("big.iso"))
+                .is_some_and(|reason| reason.contains(".iso"))
+        );
+        assert!(
+            filters
+                .skip_reason(&evidence.join("big.png"))
+                .is_some_and(|reason| reason.contains("over the 1 MB limit"))
+        );
+        assert_eq!(filters.skip_reason(&evidence.join("a.png")), None);
+        assert_eq!(
+            SkipFilters::default().skip_reason(&evidence.join("big.iso")),
+            None
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,648 @@
+use crate::config::{AnalyzerToggles, Settings};
+use crate::json_report::{
+    AnalyzerRunReport, RunMetadata, SignatureDatabaseReport, SteganalysisReport,
+};
+use crate::{FileObject, FileType};
+use analyzers::deflate_hunter::MAX_HUNT_BYTES;
+use analyzers::magic_bytes_analyzer::signature_database;
+use analyzers::versions::analyzer_version;
+use parsers::multi_image_parser::{MultiImageFormat, MultiImageParser};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+// Analyzer inventory for --list-analyzers and the scan plan printed by --dry-run. The
+// plan mirrors the order and gating of the real scan in main.rs without decoding anything
+// beyond image headers.
+
+pub struct AnalyzerInfo {
+    pub name: &'static str,
+    pub applies_to: &'static str,
+    pub description: &'static str,
+}
+
+pub const ANALYZERS: [AnalyzerInfo; AnalyzerToggles::NAMES.len()] = [
+    AnalyzerInfo {
+        name: "magic_bytes",
+        applies_to: "all",
+        description: "Signature scan for embedded and appended files",
+    },
+    AnalyzerInfo {
+        name: "slack_space",
+        applies_to: "all",
+        description: "Bytes not accounted for by PNG/RIFF/ISO-BMFF/JPEG/MP3/FLAC structure",
+    },
+    AnalyzerInfo {
+        name: "deflate",
+        applies_to: "all",
+        description: "zlib, gzip and raw deflate streams at any offset, outside the container's own",
+    },
+    AnalyzerInfo {
+        name: "exif",
+        applies_to: "image",
+        description: "EXIF metadata, thumbnails and comment fields",
+    },
+    AnalyzerInfo {
+        name: "lsb",
+        applies_to: "image",
+        description: "Chi-square and entropy of least significant bits",
+    },
+    AnalyzerInfo {
+        name: "ws",
+        applies_to: "image",
+        description: "Sample pair / weighted-stego payload estimate",
+    },
+    AnalyzerInfo {
+        name: "channel_correlation",
+        applies_to: "image",
+        description: "Inter-channel LSB correlation in smooth blocks",
+    },
+    AnalyzerInfo {
+        name: "adaptive_lsb",
+        applies_to: "image",
+        description: "Smooth vs. textured region LSB statistics",
+    },
+    AnalyzerInfo {
+        name: "bpcs",
+        applies_to: "image",
+        description: "Bit-plane complexity of 8x8 blocks across all planes (BPCS embedding)",
+    },
+    AnalyzerInfo {
+        name: "calibration",
+        applies_to: "image (JPEG)",
+        description: "DCT histograms against a cropped, requantized reference (F5-style embedding)",
+    },
+    AnalyzerInfo {
+        name: "webp",
+        applies_to: "image (WebP)",
+        description: "RIFF chunk structure and per-frame analysis of animations",
+    },
+    AnalyzerInfo {
+        name: "gif",
+        applies_to: "image (GIF)",
+        description: "Comment, application and unknown extension blocks, trailing data",
+    },
+    AnalyzerInfo {
+        name: "png_text",
+        applies_to: "image (PNG)",
+        description: "tEXt/zTXt/iTXt keywords, duplicates and encoded or binary values",
+    },
+    AnalyzerInfo {
+        name: "pages",
+        applies_to: "image (TIFF/ICO/HEIF)",
+        description: "Every page or sub-image, flagging statistical outliers",
+    },
+    AnalyzerInfo {
+        name: "filters",
+        applies_to: "image",
+        description: "Bit-plane and color filter images for visual inspection",
+    },
+    AnalyzerInfo {
+        name: "id3",
+        applies_to: "audio",
+        description: "ID3 frames, cover art, chapters and lyrics",
+    },
+    AnalyzerInfo {
+        name: "spectrogram",
+        applies_to: "audio",
+        description: "High-frequency energy and spectrogram images",
+    },
+    AnalyzerInfo {
+        name: "ultrasonic",
+        applies_to: "audio",
+        description: "Band above the audible range, resampled and checked for FSK",
+    },
+    AnalyzerInfo {
+        name: "demodulation",
+        applies_to: "audio",
+        description: "FSK and PSK decoding of the tones the spectral analyzers flag",
+    },
+    AnalyzerInfo {
+        name: "phase",
+        applies_to: "audio",
+        description: "Phase coding: quantized first-segment phases and segment boundary jumps",
+    },
+    AnalyzerInfo {
+        name: "video",
+        applies_to: "video",
+        description: "LSB and histogram checks on sampled frames",
+    },
+    AnalyzerInfo {
+        name: "text",
+        applies_to: "text",
+        description: "Zero-width characters, whitespace and encoded runs",
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cost {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Cost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cost::Low => write!(f, "low"),
+            Cost::Medium => write!(f, "medium"),
+            Cost::High => write!(f, "high"),
+        }
+    }
+}
+
+pub struct PlannedStep {
+    pub analyzer: String,
+    // Why the step won't run, None when it will
+    pub skipped: Option<String>,
+    pub cost: Cost,
+    pub parameters: Vec<(String, String)>,
+}
+
+pub struct ScanPlan {
+    pub parser: &'static str,
+    // Header facts the estimates are based on, e.g. image dimensions
+    pub input_summary: String,
+    pub steps: Vec<PlannedStep>,
+}
+
+const MB: u64 = 1024 * 1024;
+
+fn by_size(bytes: u64, medium_at: u64, high_at: u64) -> Cost {
+    if bytes >= high_at {
+        Cost::High
+    } else if bytes >= medium_at {
+        Cost::Medium
+    } else {
+        Cost::Low
+    }
+}
+
+fn has_webp_header(path: &Path) -> bool {
+    let mut header = [0u8; 12];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header[0..4] == b"RIFF" && &header[8..12] == b"WEBP")
+}
+
+fn has_gif_header(path: &Path) -> bool {
+    let mut header = [0u8; 6];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == b"GIF87a" || &header == b"GIF89a")
+}
+
+fn has_jpeg_header(path: &Path) -> bool {
+    let mut header = [0u8; 3];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| header == [0xFF, 0xD8, 0xFF])
+}
+
+fn has_png_header(path: &Path) -> bool {
+    let mut header = [0u8; 8];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == b"\x89PNG\r\n\x1a\n")
+}
+
+// The HEIF ftyp box lists its brands within the first few hundred bytes
+fn multi_image_format(path: &Path) -> Option<MultiImageFormat> {
+    let mut header = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(1024).read_to_end(&mut header))
+        .ok()?;
+    MultiImageParser::detect(&header)
+}
+
+pub fn plan_scan(file: &FileObject, settings: &Settings, extract: bool) -> ScanPlan {
+    let size = file.file_size;
+    let thresholds = &settings.thresholds;
+    let mut steps = Vec::new();
+    let mut step = |name: &str, cost: Cost, parameters: Vec<(&str, String)>| {
+        steps.push(PlannedStep {
+            analyzer: name.to_string(),
+            skipped: (!settings.analyzers.is_enabled(name)).then(|| "disabled".to_string()),
+            cost,
+            parameters: parameters
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        });
+    };
+
+    // Whole-file passes run for every type
+    let mut magic_parameters = vec![("strictness", settings.magic_bytes.strictness.to_string())];
+    if let Some(signatures) = &settings.magic_bytes.signatures_file {
+        magic_parameters.push(("signatures", signatures.display().to_string()));
+    }
+    step(
+        "magic_bytes",
+        by_size(size, 10 * MB, 100 * MB),
+        magic_parameters,
+    );
+    let mut slack_parameters = vec![];
+    if extract {
+        slack_parameters.push((
+            "carve",
+            settings
+                .artifact_path("<file>_slack_0x<offset>.bin")
+                .display()
+                .to_string(),
+        ));
+    }
+    step("slack_space", Cost::Low, slack_parameters);
+    let mut deflate_parameters = vec![("searched", format!("{} MiB", MAX_HUNT_BYTES >> 20))];
+    if extract {
+        deflate_parameters.push((
+            "carve",
+            settings
+                .artifact_path("<file>_deflate_0x<offset>.<ext>")
+                .display()
+                .to_string(),
+        ));
+    }
+    step(
+        "deflate",
+        by_size(size, 4 * MB, MAX_HUNT_BYTES as u64),
+        deflate_parameters,
+    );
+
+    let (parser, input_summary) = match file.file_type {
+        FileType::Image => {
+            let (summary, pixels) = match image::image_dimensions(&file.file_path) {
+                Ok((w, h)) => (format!("{}x{}", w, h), w as u64 * h as u64),
+                Err(_) => ("dimensions unknown".to_string(), 0),
+            };
+            let per_pixel = by_size(pixels, 4_000_000, 25_000_000);
+            let is_webp = has_webp_header(&file.file_path);
+            let is_gif = has_gif_header(&file.file_path);
+            let is_png = has_png_header(&file.file_path);
+            let is_jpeg = has_jpeg_header(&file.file_path);
+            let multi_image = multi_image_format(&file.file_path);
+
+            step("exif", Cost::Low, vec![]);
+            let mut lsb_parameters = vec![
+                ("chi_square", thresholds.lsb_chi_square.to_string()),
+                ("entropy", thresholds.lsb_entropy.to_string()),
+                (
+                    "synthetic_noise",
+                    thresholds.synthetic_lsb_noise.to_string(),
+                ),
+                (
+                    "calibration_sigma",
+                    thresholds.calibration_sigma.to_string(),
+                ),
+            ];
+            if let Some(calibration) = settings.calibration_path() {
+                lsb_parameters.push(("calibration", calibration.display().to_string()));
+            }
+            step("lsb", per_pixel, lsb_parameters);
+            step(
+                "ws",
+                per_pixel,
+                vec![("payload_rate", thresholds.ws_payload_rate.to_string())],
+            );
+            step(
+                "channel_correlation",
+                per_pixel,
+                vec![(
+                    "collapsed_ratio",
+                    thresholds.channel_correlation_collapsed_ratio.to_string(),
+                )],
+            );
+            step(
+                "adaptive_lsb",
+                per_pixel,
+                vec![("contrast", thresholds.adaptive_lsb_contrast.to_string())],
+            );
+            step(
+                "bpcs",
+                per_pixel,
+                vec![("noise_planes", thresholds.bpcs_noise_planes.to_string())],
+            );
+            step(
+                "calibration",
+                per_pixel,
+                vec![(
+                    "change_rate",
+                    thresholds.calibration_change_rate.to_string(),
+                )],
+            );
+            step("webp", Cost::Medium, vec![]);
+            step("gif", Cost::Low, vec![]);
+            step("png_text", Cost::Low, vec![]);
+            // Each page costs about what the main image does
+            step("pages", per_pixel.max(Cost::Medium), vec![]);
+            // Writes a dozen full-size images
+            step("filters", per_pixel.max(Cost::Medium), vec![]);
+
+            if !is_webp && let Some(webp) = steps.iter_mut().find(|s| s.analyzer == "webp") {
+                webp.skipped
+                    .get_or_insert_with(|| "not a WebP file".to_string());
+            }
+            if !is_gif && let Some(gif) = steps.iter_mut().find(|s| s.analyzer == "gif") {
+                gif.skipped
+                    .get_or_insert_with(|| "not a GIF file".to_string());
+            }
+            if !is_jpeg
+                && let Some(calibration) = steps.iter_mut().find(|s| s.analyzer == "calibration")
+            {
+                calibration
+                    .skipped
+                    .get_or_insert_with(|| "not a JPEG file".to_string());
+            }
+            if !is_png && let Some(png_text) = steps.iter_mut().find(|s| s.analyzer == "png_text") {
+                png_text
+                    .skipped
+                    .get_or_insert_with(|| "not a PNG file".to_string());
+            }
+            if multi_image.is_none()
+                && let Some(pages) = steps.iter_mut().find(|s| s.analyzer == "pages")
+            {
+                pages
+                    .skipped
+                    .get_or_insert_with(|| "not a TIFF, ICO or HEIF file".to_string());
+            }
+            let summary = match multi_image {
+                Some(format) => format!("{}, {}", summary, format),
+                None => summary,
+            };
+            ("ImageParser", summary)
+        }
+        FileType::Audio => {
+            step("id3", Cost::Low, vec![]);
+            step(
+                "spectrogram",
+                by_size(size, 5 * MB, 50 * MB),
+                vec![
+                    (
+                        "high_frequency_energy",
+                        thresholds.spectrogram_high_frequency_energy.to_string(),
+                    ),
+                    (
+                        "tile_seconds",
+                        settings.audio.spectrogram_tile_seconds.to_string(),
+                    ),
+                ],
+            );
+            step(
+                "ultrasonic",
+                by_size(size, 5 * MB, 50 * MB),
+                vec![
+                    ("cutoff_hz", settings.audio.ultrasonic_cutoff_hz.to_string()),
+                    ("energy", thresholds.ultrasonic_energy.to_string()),
+                ],
+            );
+            step(
+                "phase",
+                Cost::Low,
+                vec![("discontinuity", thresholds.phase_discontinuity.to_string())],
+            );
+            // Only runs on tones the two analyzers above report
+            step("demodulation", by_size(size, 5 * MB, 50 * MB), vec![]);
+            if !settings.analyzers.spectrogram
+                && !settings.analyzers.ultrasonic
+                && let Some(demodulation) = steps.iter_mut().find(|s| s.analyzer == "demodulation")
+            {
+                demodulation
+                    .skipped
+                    .get_or_insert_with(|| "spectrogram and ultrasonic disabled".to_string());
+            }
+            ("AudioParser", format!("{} bytes encoded", size))
+        }
+        FileType::Video => {
+            let cost = if settings.video.keyframes_only {
+                by_size(size, 100 * MB, 1024 * MB)
+            } else {
+                by_size(size / settings.video_sample_rate() as u64, MB, 10 * MB).max(Cost::Medium)
+            };
+            step(
+                "video",
+                cost,
+                vec![
+                    ("sampling", settings.video_sampling().to_string()),
+                    ("keyframes_only", settings.video.keyframes_only.to_string()),
+                    (
+                        "max_frames_per_scene",
+                        settings.video.max_frames_per_scene.to_string(),
+                    ),
+                    ("hwaccel", settings.video.hwaccel.to_string()),
+                ],
+            );
+            ("VideoParser", format!("{} bytes encoded", size))
+        }
+        FileType::Text => {
+            step("text", by_size(size, 10 * MB, 100 * MB), vec![]);
+            ("TextParser", format!("{} bytes", size))
+        }
+    };
+
+    if extract {
+        steps.push(PlannedStep {
+            analyzer: "extract".to_string(),
+            skipped: None,
+            cost: by_size(size, 10 * MB, 100 * MB),
+            parameters: vec![(
+                "output".to_string(),
+                settings
+                    .artifact_path("<file>_extracted")
+                    .display()
+                    .to_string(),
+            )],
+        });
+    }
+    for plugin in &settings.plugins {
+        steps.push(PlannedStep {
+            analyzer: format!("plugin:{}", plugin.name),
+            skipped: None,
+            // Unknown; bounded by the timeout
+            cost: Cost::Medium,
+            parameters: vec![("timeout_secs".to_string(), plugin.timeout_secs.to_string())],
+        });
+    }
+
+    ScanPlan {
+        parser,
+        input_summary,
+        steps,
+    }
+}
+
+// What the report records about how it was made: the steps of the plan that reported back,
+// with the analyzer versions, and the signatures the magic bytes scan matched against
+pub fn run_metadata(
+    plan: &ScanPlan,
+    report: &SteganalysisReport,
+    settings: &Settings,
+) -> RunMetadata {
+    let reported = report.reported_analyzers();
+    let custom_file = settings.magic_bytes.signatures_file.as_ref();
+    RunMetadata {
+        scanner: concat!("stegascan ", env!("CARGO_PKG_VERSION")).to_string(),
+        profile: clap::ValueEnum::to_possible_value(&settings.profile)
+            .map(|value| value.get_name().to_string()),
+        analyzers: plan
+            .steps
+            .iter()
+            .filter(|step| step.skipped.is_none() && reported.contains(&step.analyzer))
+            .map(|step| AnalyzerRunReport {
+                name: step.analyzer.clone(),
+                version: analyzer_version(&step.analyzer),
+                parameters: step.parameters.iter().cloned().collect(),
+            })
+            .collect(),
+        signature_database: SignatureDatabaseReport {
+            version: signature_database().fingerprint.clone(),
+            signatures: signature_database().signatures,
+            custom_file: custom_file.map(|path| path.display().to_string()),
+            custom_sha256: custom_file
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|bytes| crate::evidence::hex(&Sha256::digest(bytes))),
+        },
+    }
+}
+
+pub fn print_analyzers(settings: &Settings) {
+    println!(
+        "{:<20} {:<9} {:<13} DESCRIPTION",
+        "ANALYZER", "STATUS", "APPLIES TO"
+    );
+    for info in &ANALYZERS {
+        let status = if settings.analyzers.is_enabled(info.name) {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        println!(
+            "{:<20} {:<9} {:<13} {}",
+            info.name, status, info.applies_to, info.description
+        );
+    }
+    for plugin in &settings.plugins {
+        println!(
+            "{:<20} {:<9} {:<13} {:?} plugin {}",
+            format!("plugin:{}", plugin.name),
+            "enabled",
+            "all",
+            plugin.kind,
+            plugin.command.display()
+        );
+    }
+}
+
+pub fn print_plan(file: &FileObject, plan: &ScanPlan) {
+    println!(
+        "Dry run for {} ({:?}, {} bytes)",
+        file.file_path.display(),
+        file.file_type,
+        file.file_size
+    );
+    println!("Parser: {} ({})\n", plan.parser, plan.input_summary);
+    println!("{:<26} {:<22} {:<7} PARAMETERS", "STEP", "STATUS", "COST");
+    for step in &plan.steps {
+        let parameters: Vec<String> = step
+            .parameters
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        println!(
+            "{:<26} {:<22} {:<7} {}",
+            step.analyzer,
+            step.skipped
+                .as_ref()
+                .map(|reason| format!("skip ({})", reason))
+                .unwrap_or_else(|| "run".to_string()),
+            step.cost.to_string(),
+            parameters.join(" ")
+        );
+    }
+
+    let running: Vec<&PlannedStep> = plan.steps.iter().filter(|s| s.skipped.is_none()).collect();
+    let heaviest = running.iter().map(|s| s.cost).max().unwrap_or(Cost::Low);
+    println!(
+        "\n{} of {} steps would run, heaviest cost: {}",
+        running.len(),
+        plan.steps.len(),
+        heaviest
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::{AudioAnalysis, FormatSpecificAnalysis, PhaseReport};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_plan_follows_settings() {
+        let mut settings = Settings::default();
+        settings.analyzers.set("spectrogram", false).unwrap();
+        let file = FileObject {
+            file_path: PathBuf::from("song.mp3"),
+            file_size: 200 * MB,
+            file_type: FileType::Audio,
+        };
+
+        let plan = plan_scan(&file, &settings, true);
+        let names: Vec<&str> = plan.steps.iter().map(|s| s.analyzer.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "magic_bytes",
+                "slack_space",
+                "deflate",
+                "id3",
+                "spectrogram",
+                "ultrasonic",
+                "phase",
+                "demodulation",
+                "extract"
+            ]
+        );
+        assert_eq!(plan.steps[0].cost, Cost::High);
+        assert_eq!(plan.steps[4].skipped.as_deref(), Some("disabled"));
+        assert!(plan.steps[3].skipped.is_none());
+    }
+
+    #[test]
+    fn test_run_metadata_lists_only_analyzers_that_reported() {
+        let settings = Settings::default();
+        let file = FileObject {
+            file_path: PathBuf::from("song.mp3"),
+            file_size: MB,
+            file_type: FileType::Audio,
+        };
+        let plan = plan_scan(&file, &settings, false);
+
+        let mut report = SteganalysisReport::new(&file.file_path, file.file_size, "Audio".into());
+        report.set_format_analysis(FormatSpecificAnalysis::Audio(Box::new(AudioAnalysis {
+            sample_count: 0,
+            sample_rate: 44100,
+            samples_file: None,
+            id3_analysis: None,
+            spectrogram_analysis: None,
+            ultrasonic_analysis: None,
+            demodulation: None,
+            phase_analysis: Some(PhaseReport {
+                segment_length: 1024,
+                boundary_jump_ratio: 1.0,
+                quantized_run: 0,
+                quantized_first_bin: 0,
+                quantized_share: 0.0,
+                decoded_hex: String::new(),
+                decoded_text: None,
+                is_suspicious: false,
+            }),
+            differential: None,
+            trailing_tags: None,
+            mp4_tags: None,
+        })));
+
+        // The other audio steps were planned but left no section, as when decoding fails
+        let run = run_metadata(&plan, &report, &settings);
+        let names: Vec<&str> = run.analyzers.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["phase"]);
+        assert!(run.analyzers[0].version.is_some());
+    }
+}
@@ -0,0 +1,161 @@
+use image::RgbaImage;
+use image::imageops::{self, FilterType};
+
+// `stegascan quick`: triage of a single image, from a path or straight off the clipboard
+// after a screenshot. Only the cheap pixel analyzers run, the LSB planes are drawn in the
+// terminal so structure in them can be eyeballed, and the result is one line.
+
+#[derive(Debug)]
+pub enum QuickError {
+    Clipboard(String),
+}
+
+impl std::fmt::Display for QuickError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuickError::Clipboard(e) => {
+                write!(f, "Could not read an image from the clipboard: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuickError {}
+
+pub fn clipboard_image() -> Result<RgbaImage, QuickError> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| QuickError::Clipboard(e.to_string()))?;
+    let data = clipboard
+        .get_image()
+        .map_err(|e| QuickError::Clipboard(e.to_string()))?;
+    RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+    .ok_or_else(|| QuickError::Clipboard("image data does not match its size".to_string()))
+}
+
+// One analyzer's result, for the verdict
+pub struct QuickFinding {
+    pub analyzer: &'static str,
+    pub suspicious: bool,
+    // The measurement behind the result, e.g. "payload 12.5%"
+    pub detail: String,
+}
+
+pub fn verdict_line(label: &str, findings: &[QuickFinding]) -> String {
+    let flagged: Vec<String> = findings
+        .iter()
+        .filter(|finding| finding.suspicious)
+        .map(|finding| format!("{} ({})", finding.analyzer, finding.detail))
+        .collect();
+    if !flagged.is_empty() {
+        return format!("⚠️  SUSPICIOUS {}: {}", label, flagged.join(", "));
+    }
+    if findings.is_empty() {
+        return format!("❔ UNKNOWN {}: no analyzer could run", label);
+    }
+    let ran: Vec<&str> = findings.iter().map(|finding| finding.analyzer).collect();
+    format!("✅ CLEAN {}: nothing found by {}", label, ran.join(", "))
+}
+
+// Images side by side in the terminal, `columns` characters wide each. Every character is
+// an upper half block coloured with two pixel rows, so the aspect ratio survives.
+pub fn render_side_by_side(images: &[&RgbaImage], columns: u32) -> Vec<String> {
+    let rendered: Vec<Vec<String>> = images
+        .iter()
+        .map(|image| render_blocks(image, columns))
+        .collect();
+    let rows = rendered.iter().map(Vec::len).max().unwrap_or(0);
+    (0..rows)
+        .map(|row| {
+            rendered
+                .iter()
+                .map(|lines| {
+                    lines
+                        .get(row)
+                        .cloned()
+                        .unwrap_or_else(|| " ".repeat(columns as usize))
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        })
+        .collect()
+}
+
+fn render_blocks(image: &RgbaImage, columns: u32) -> Vec<String> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || columns == 0 {
+        return Vec::new();
+    }
+    let columns = columns.min(width);
+    let rows = ((height as u64 * columns as u64 / width as u64) as u32).max(2) & !1;
+    // Nearest keeps the pixel noise of a plane instead of averaging it to grey
+    let scaled = imageops::resize(image, columns, rows, FilterType::Nearest);
+
+    let mut lines = Vec::new();
+    for y in (0..rows).step_by(2) {
+        let mut line = String::new();
+        for x in 0..columns {
+            let top = scaled.get_pixel(x, y);
+            let bottom = scaled.get_pixel(x, y + 1);
+            line.push_str(&format!(
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            ));
+        }
+        line.push_str("\x1b[0m");
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_render_keeps_aspect_ratio() {
+        let image = RgbaImage::from_fn(40, 20, |x, _| {
+            if x < 20 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        });
+        let lines = render_side_by_side(&[&image, &image], 20);
+        // 20 columns of a 2:1 image are 10 pixel rows, two per line
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].matches('▀').count(), 40);
+        assert!(lines[0].starts_with("\x1b[38;2;255;255;255m"));
+    }
+
+    #[test]
+    fn test_verdict_line() {
+        let clean = QuickFinding {
+            analyzer: "LSB",
+            suspicious: false,
+            detail: "chi-square 0.10".to_string(),
+        };
+        let flagged = QuickFinding {
+            analyzer: "WS",
+            suspicious: true,
+            detail: "payload 12.5%".to_string(),
+        };
+        assert_eq!(
+            verdict_line("a.png", &[clean]),
+            "✅ CLEAN a.png: nothing found by LSB"
+        );
+        let clean = QuickFinding {
+            analyzer: "LSB",
+            suspicious: false,
+            detail: String::new(),
+        };
+        assert_eq!(
+            verdict_line("a.png", &[clean, flagged]),
+            "⚠️  SUSPICIOUS a.png: WS (payload 12.5%)"
+        );
+    }
+}
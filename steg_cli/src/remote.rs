@@ -0,0 +1,546 @@
+use analyzers::Analyzer;
+use analyzers::payload_carver::embedded_file_end;
+use analyzers::trailing_tag_analyzer::TrailingTagAnalyzer;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{
+    ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, HeaderName, RANGE,
+};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::quick::QuickFinding;
+
+// --url: a file on a web server is scanned without downloading all of it. A HEAD request gives
+// its size and type, and the quick verdict only needs its first and last PIECE_BYTES, fetched
+// with Range requests: a mislabeled format shows in the head, appended data and trailing tags
+// in the tail. Only --profile deep downloads the whole file, to a scratch directory removed
+// after the scan.
+
+// How much of each end of the file is fetched
+pub const PIECE_BYTES: u64 = 1024 * 1024;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum RemoteError {
+    Http(reqwest::Error),
+    Io(std::io::Error),
+    Status(u16),
+    TooLarge(u64, u64),
+}
+
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteError::Http(e) => write!(f, "HTTP error: {}", e),
+            RemoteError::Io(e) => write!(f, "Download I/O error: {}", e),
+            RemoteError::Status(status) => write!(f, "The server answered with status {}", status),
+            RemoteError::TooLarge(size, limit) => write!(
+                f,
+                "The file is {} bytes, over the configured limit of {} MB",
+                size, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
+impl From<reqwest::Error> for RemoteError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<std::io::Error> for RemoteError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+    pub url: String,
+    // Content-Length, when the server sent one
+    pub size: Option<u64>,
+    // Content-Type without its parameters
+    pub content_type: Option<String>,
+    // Accept-Ranges: bytes was advertised. Servers that don't advertise it are still asked for
+    // ranges; a 200 answer tells they don't support them.
+    pub ranges: bool,
+}
+
+// A stretch of the remote file
+#[derive(Debug, Clone)]
+pub struct Piece {
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+impl Piece {
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+pub fn client() -> Result<Client, RemoteError> {
+    // Downloads of large videos take longer than any whole-request timeout would allow
+    Ok(Client::builder()
+        .user_agent(concat!("stegascan/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(None)
+        .build()?)
+}
+
+pub fn probe(client: &Client, url: &str) -> Result<RemoteFile, RemoteError> {
+    let response = client.head(url).send()?;
+    // Some servers only answer GET; the pieces are still fetched, just without a size to aim at
+    if matches!(
+        response.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        return Ok(RemoteFile {
+            url: url.to_string(),
+            size: None,
+            content_type: None,
+            ranges: false,
+        });
+    }
+    if !response.status().is_success() {
+        return Err(RemoteError::Status(response.status().as_u16()));
+    }
+
+    let header = |name: HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    Ok(RemoteFile {
+        url: url.to_string(),
+        size: header(CONTENT_LENGTH).and_then(|value| value.parse().ok()),
+        content_type: header(CONTENT_TYPE)
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty()),
+        ranges: header(ACCEPT_RANGES).is_some_and(|value| value.eq_ignore_ascii_case("bytes")),
+    })
+}
+
+// The byte ranges, with inclusive ends as in a Range header, of the head and tail of a file of
+// `size` bytes: one range covering the whole file when the two would meet
+pub fn piece_ranges(size: u64, piece: u64) -> Vec<(u64, u64)> {
+    if size == 0 {
+        return Vec::new();
+    }
+    if size <= 2 * piece {
+        return vec![(0, size - 1)];
+    }
+    vec![(0, piece - 1), (size - piece, size - 1)]
+}
+
+// Fetch the head and tail of the file. A server that ignores Range requests sends the whole
+// file; the head is read off the start of it and the tail is left out rather than downloading
+// everything to reach it. Without a Content-Length, the size is learned from the head's
+// Content-Range, or from a whole-file answer shorter than a piece, and recorded in `file`.
+pub fn fetch_pieces(client: &Client, file: &mut RemoteFile) -> Result<Vec<Piece>, RemoteError> {
+    let mut ranges = match file.size {
+        Some(size) => piece_ranges(size, PIECE_BYTES),
+        None => vec![(0, PIECE_BYTES - 1)],
+    };
+
+    let mut pieces = Vec::new();
+    let mut index = 0;
+    while let Some(&(start, end)) = ranges.get(index) {
+        index += 1;
+        let response = client
+            .get(&file.url)
+            .header(RANGE, format!("bytes={}-{}", start, end))
+            .send()?;
+        let piece = match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                if file.size.is_none()
+                    && let Some(total) = content_range_total(&response)
+                {
+                    file.size = Some(total);
+                    if total > end + 1 {
+                        ranges.push((total.saturating_sub(PIECE_BYTES).max(end + 1), total - 1));
+                    }
+                }
+                Piece {
+                    offset: start,
+                    data: read_at_most(response, end - start + 1)?,
+                }
+            }
+            // The range covers the whole file anyway
+            StatusCode::RANGE_NOT_SATISFIABLE if file.size.is_none() => break,
+            status if status.is_success() => {
+                if start == 0 {
+                    let data = read_at_most(response, end + 1)?;
+                    // The body ended before the piece did, so it was the whole file
+                    if file.size.is_none() && (data.len() as u64) <= end {
+                        file.size = Some(data.len() as u64);
+                    }
+                    pieces.push(Piece { offset: 0, data });
+                }
+                break;
+            }
+            status => return Err(RemoteError::Status(status.as_u16())),
+        };
+        pieces.push(piece);
+    }
+    Ok(pieces)
+}
+
+// The complete length in a `Content-Range: bytes <first>-<last>/<length>` header, unless the
+// server left it out as `*`
+fn content_range_total(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
+
+// Dropping the response after reading closes the connection, so the rest of a body the server
+// insisted on sending is never downloaded
+fn read_at_most(response: Response, limit: u64) -> Result<Vec<u8>, RemoteError> {
+    let mut data = Vec::new();
+    response.take(limit).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+// The format the first bytes belong to, as a MIME type
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if head.starts_with(b"BM") && head.len() >= 14 {
+        Some("image/bmp")
+    } else if head.starts_with(b"PK\x03\x04") {
+        Some("application/zip")
+    } else if head.starts_with(b"%PDF") {
+        Some("application/pdf")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(&b"WAVE"[..]) {
+        Some("audio/wav")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(&b"AVI "[..]) {
+        Some("video/x-msvideo")
+    } else if head.starts_with(b"fLaC") {
+        Some("audio/flac")
+    } else if head.starts_with(b"OggS") {
+        Some("audio/ogg")
+    } else if head.get(4..8) == Some(&b"ftyp"[..]) {
+        Some("video/mp4")
+    } else if head.starts_with(b"ID3")
+        || (head.len() >= 2 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0)
+    {
+        Some("audio/mpeg")
+    } else {
+        None
+    }
+}
+
+// Spellings servers use for the same formats. MP4 audio and video share a container, and
+// Office documents are ZIP archives.
+fn same_format(declared: &str, sniffed: &str) -> bool {
+    let canonical = |mime: &str| -> String {
+        match mime {
+            "image/jpg" | "image/pjpeg" => "image/jpeg".to_string(),
+            "image/x-png" => "image/png".to_string(),
+            "image/x-ms-bmp" | "image/x-bmp" => "image/bmp".to_string(),
+            "audio/x-wav" | "audio/wave" | "audio/vnd.wave" => "audio/wav".to_string(),
+            "audio/mp3" | "audio/x-mp3" | "audio/mpeg3" => "audio/mpeg".to_string(),
+            "audio/x-flac" => "audio/flac".to_string(),
+            "video/ogg" | "application/ogg" => "audio/ogg".to_string(),
+            "audio/mp4" | "audio/x-m4a" | "video/quicktime" | "video/x-m4v" => {
+                "video/mp4".to_string()
+            }
+            "application/x-zip-compressed" | "application/x-zip" => "application/zip".to_string(),
+            mime if mime.starts_with("application/vnd.openxmlformats")
+                || mime.starts_with("application/vnd.oasis.opendocument")
+                || mime == "application/epub+zip" =>
+            {
+                "application/zip".to_string()
+            }
+            mime => mime.to_string(),
+        }
+    };
+    canonical(declared) == canonical(sniffed)
+}
+
+// Where the PNG, JPEG or ZIP file whose last bytes are `tail` ends in it: past the last IEND
+// chunk, EOI marker or end of central directory record. A tail is only part of the file, so
+// this is a guess the full scan's structure walk replaces: data appended after a second end
+// marker is missed.
+fn tail_end(format: &str, tail: &[u8]) -> Option<usize> {
+    let last = |marker: &[u8]| {
+        tail.windows(marker.len())
+            .rposition(|window| window == marker)
+    };
+    match format {
+        // Zero length, type, CRC
+        "image/png" => last(b"\0\0\0\0IEND")
+            .map(|pos| pos + 12)
+            .filter(|&end| end <= tail.len()),
+        "image/jpeg" => last(&[0xFF, 0xD9]).map(|pos| pos + 2),
+        "application/zip" => {
+            let pos = last(b"PK\x05\x06")?;
+            let comment = tail.get(pos + 20..pos + 22)?;
+            Some(pos + 22 + u16::from_le_bytes([comment[0], comment[1]]) as usize)
+        }
+        _ => None,
+    }
+}
+
+// What the head and tail tell about the file: a Content-Type its first bytes don't match,
+// bytes after the end marker of a PNG, JPEG or ZIP file, and the findings of the trailing tag
+// analyzer on the tail
+pub fn findings(file: &RemoteFile, pieces: &[Piece]) -> Vec<QuickFinding> {
+    let mut findings = Vec::new();
+    let Some(head) = pieces.first().filter(|piece| piece.offset == 0) else {
+        return findings;
+    };
+    let sniffed = sniff(&head.data);
+
+    if let (Some(declared), Some(sniffed)) = (&file.content_type, sniffed)
+        && declared != "application/octet-stream"
+    {
+        let same = same_format(declared, sniffed);
+        findings.push(QuickFinding {
+            analyzer: "content type",
+            suspicious: !same,
+            detail: if same {
+                format!("served and stored as {}", sniffed)
+            } else {
+                format!("served as {} but starts as {}", declared, sniffed)
+            },
+        });
+    }
+
+    // The tail, when the pieces reach the end of the file. Without a size, nothing tells where
+    // the file ends, so neither the end marker nor the trailing tags are checked.
+    let tail = file
+        .size
+        .and_then(|size| pieces.last().filter(|piece| piece.end() == size));
+
+    if let (Some(format @ ("image/png" | "image/jpeg" | "application/zip")), Some(size)) =
+        (sniffed, file.size)
+    {
+        let end = if head.end() == size {
+            embedded_file_end(&head.data, 0).map(|end| end as u64)
+        } else {
+            tail.and_then(|tail| tail_end(format, &tail.data))
+                .map(|end| tail.map_or(0, |tail| tail.offset) + end as u64)
+        };
+        match end {
+            Some(end) if end < size => findings.push(QuickFinding {
+                analyzer: "end marker",
+                suspicious: true,
+                detail: format!("{} bytes after the end of the {}", size - end, format),
+            }),
+            Some(_) => findings.push(QuickFinding {
+                analyzer: "end marker",
+                suspicious: false,
+                detail: "nothing after the end of the file".to_string(),
+            }),
+            None if tail.is_some() => findings.push(QuickFinding {
+                analyzer: "end marker",
+                suspicious: true,
+                detail: format!(
+                    "no {} end marker in the last {} bytes",
+                    format,
+                    tail.map_or(0, |tail| tail.data.len())
+                ),
+            }),
+            // The tail wasn't fetched
+            None => {}
+        }
+    }
+
+    if let Some(tail) = tail
+        && let Ok(analysis) = TrailingTagAnalyzer::analyze(tail.data.clone())
+    {
+        let flagged = analysis
+            .tags
+            .iter()
+            .flat_map(|tag| &tag.items)
+            .filter(|item| !item.findings.is_empty())
+            .count();
+        let kinds: Vec<String> = analysis
+            .tags
+            .iter()
+            .map(|tag| tag.kind.to_string())
+            .collect();
+        findings.push(QuickFinding {
+            analyzer: "trailing tags",
+            suspicious: analysis.suspicious,
+            detail: format!("{}, {} flagged items", kinds.join(" and "), flagged),
+        });
+    }
+    findings
+}
+
+// The whole file, downloaded for --profile deep. The scratch directory goes away on drop.
+pub struct Download {
+    _dir: tempfile::TempDir,
+    pub path: PathBuf,
+}
+
+// Download the file, refusing it up front when the server says it is over the limit and
+// stopping once it turns out to be. A limit of 0 MB means no limit.
+pub fn download(
+    client: &Client,
+    file: &RemoteFile,
+    max_size_mb: u64,
+) -> Result<Download, RemoteError> {
+    let limit = (max_size_mb > 0).then(|| max_size_mb * 1024 * 1024);
+    if let (Some(size), Some(limit)) = (file.size, limit)
+        && size > limit
+    {
+        return Err(RemoteError::TooLarge(size, max_size_mb));
+    }
+
+    let response = client.get(&file.url).send()?;
+    if !response.status().is_success() {
+        return Err(RemoteError::Status(response.status().as_u16()));
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix("stegascan-url-")
+        .tempdir()?;
+    let download = Download {
+        path: dir.path().join(file_name(&file.url)),
+        _dir: dir,
+    };
+
+    // One byte over the limit is enough to tell
+    let mut body = response.take(limit.map_or(u64::MAX, |limit| limit + 1));
+    let copied = std::io::copy(&mut body, &mut File::create(&download.path)?)?;
+    if let Some(limit) = limit
+        && copied > limit
+    {
+        return Err(RemoteError::TooLarge(copied, max_size_mb));
+    }
+    Ok(download)
+}
+
+// The last segment of the URL's path, which the parsers pick a decoder by. Only a plain file
+// name is kept; anything else becomes "download".
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    path.split_once('/')
+        .and_then(|(_, path)| path.rsplit('/').next())
+        .filter(|name| {
+            !name.is_empty()
+                && !name.starts_with('.')
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        })
+        .unwrap_or("download")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(size: u64, content_type: &str) -> RemoteFile {
+        RemoteFile {
+            url: "https://example.com/media/cover.png".to_string(),
+            size: Some(size),
+            content_type: Some(content_type.to_string()),
+            ranges: true,
+        }
+    }
+
+    fn png() -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 13]);
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&[0; 13 + 4]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"IEND");
+        data.extend_from_slice(&[0xAE, 0x42, 0x60, 0x82]);
+        data
+    }
+
+    #[test]
+    fn test_piece_ranges_and_file_name() {
+        assert!(piece_ranges(0, 10).is_empty());
+        assert_eq!(piece_ranges(20, 10), vec![(0, 19)]);
+        assert_eq!(piece_ranges(100, 10), vec![(0, 9), (90, 99)]);
+
+        assert_eq!(
+            file_name("https://example.com/a/cover.png?x=1"),
+            "cover.png"
+        );
+        assert_eq!(file_name("https://example.com/"), "download");
+        assert_eq!(file_name("https://example.com"), "download");
+        assert_eq!(file_name("https://example.com/a/..%2f..%2fetc"), "download");
+    }
+
+    #[test]
+    fn test_findings() {
+        // The whole file fits in the head
+        let mut data = png();
+        let clean = findings(
+            &remote(data.len() as u64, "image/png"),
+            &[Piece {
+                offset: 0,
+                data: data.clone(),
+            }],
+        );
+        assert_eq!(clean.len(), 2);
+        assert!(clean.iter().all(|finding| !finding.suspicious));
+
+        data.extend_from_slice(b"PK\x03\x04hidden");
+        let appended = findings(
+            &remote(data.len() as u64, "image/jpeg"),
+            &[Piece {
+                offset: 0,
+                data: data.clone(),
+            }],
+        );
+        assert!(appended.iter().all(|finding| finding.suspicious));
+        assert!(appended[1].detail.starts_with("10 bytes after"));
+
+        // Only the two ends of a large file, the tail holding the IEND chunk and what follows
+        let size = 10 * PIECE_BYTES;
+        let head = Piece {
+            offset: 0,
+            data: png()[..33].to_vec(),
+        };
+        let tail_data = data[33..].to_vec();
+        let tail = Piece {
+            offset: size - tail_data.len() as u64,
+            data: tail_data,
+        };
+        let split = findings(&remote(size, "image/png"), &[head.clone(), tail]);
+        assert!(split[1].suspicious && split[1].detail.starts_with("10 bytes after"));
+
+        // Without the tail, nothing can be said about the end
+        let head_only = findings(&remote(size, "image/png"), std::slice::from_ref(&head));
+        assert_eq!(head_only.len(), 1);
+
+        // Nor when the size is unknown: the head is not taken for the tail
+        let unsized_file = RemoteFile {
+            size: None,
+            ..remote(size, "image/png")
+        };
+        assert_eq!(findings(&unsized_file, &[head]).len(), 1);
+    }
+}
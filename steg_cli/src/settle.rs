@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+// Files in hot upload directories may still be written when a batch reaches them: a truncated
+// image parses as corrupt, and the missing tail of a half-copied archive reads as a mangled
+// structure. With a settle period, a file is only scanned once its size and modification time
+// have held still for that long, and a scan during which the file changed anyway is run again
+// once it settles. A scan that keeps seeing changes keeps its report, marked as such.
+
+// Settle periods waited for a file before scanning it regardless
+pub const MAX_SETTLE_PERIODS: u32 = 10;
+
+// Scans of a file that changed while being scanned, after the first
+pub const MAX_RESCANS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileState {
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    // Whether the file is no longer as it was; a file that disappeared has changed too
+    pub fn changed(&self, path: &Path) -> bool {
+        Self::of(path).map_or(true, |now| now != *self)
+    }
+}
+
+// Wait until the file looks the same `period` apart, giving up after MAX_SETTLE_PERIODS.
+// Returns the state the scan starts from and whether the file settled.
+pub fn wait_until_settled(path: &Path, period: Duration) -> std::io::Result<(FileState, bool)> {
+    let mut state = FileState::of(path)?;
+    for _ in 0..MAX_SETTLE_PERIODS {
+        std::thread::sleep(period);
+        let now = FileState::of(path)?;
+        if now == state {
+            return Ok((state, true));
+        }
+        state = now;
+    }
+    Ok((state, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_for_writer_to_finish() {
+        let path = std::env::temp_dir().join(format!("stegascan_settle_{}", std::process::id()));
+        std::fs::write(&path, b"GIF89a").unwrap();
+
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for _ in 0..3 {
+                    std::thread::sleep(Duration::from_millis(30));
+                    let mut data = std::fs::read(&path).unwrap();
+                    data.extend_from_slice(&[0; 512]);
+                    std::fs::write(&path, data).unwrap();
+                }
+            })
+        };
+        let (state, settled) = wait_until_settled(&path, Duration::from_millis(100)).unwrap();
+        writer.join().unwrap();
+
+        assert!(settled);
+        assert_eq!(state.len, 6 + 3 * 512);
+        assert!(!state.changed(&path));
+        std::fs::write(&path, b"GIF89a").unwrap();
+        assert!(state.changed(&path));
+        std::fs::remove_file(&path).unwrap();
+        assert!(state.changed(&path));
+    }
+}
@@ -0,0 +1,133 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Directory traversal for --dir. Corpora can hold millions of files, so the tree is walked on
+// all cores and narrowed while walking: --include globs pick the files to scan, --exclude globs
+// drop files and whole directories, both relative to the directory, and --max-depth stops the
+// descent that many levels down. Unlike a source tree walk,
+// hidden files and anything a .gitignore lists are kept, since that is where payloads hide;
+// symbolic links are not followed.
+
+#[derive(Debug)]
+pub enum WalkError {
+    Pattern(String, String),
+    NotADirectory(PathBuf),
+}
+
+impl std::fmt::Display for WalkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkError::Pattern(pattern, e) => {
+                write!(f, "Invalid file pattern {:?}: {}", pattern, e)
+            }
+            WalkError::NotADirectory(path) => write!(f, "{} is not a directory", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for WalkError {}
+
+// Every file below `root` the patterns let through, sorted so batches run in a stable order
+pub fn walk(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+    max_depth: Option<usize>,
+) -> Result<Vec<PathBuf>, WalkError> {
+    if !root.is_dir() {
+        return Err(WalkError::NotADirectory(root.to_path_buf()));
+    }
+
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in include {
+        overrides
+            .add(pattern)
+            .map_err(|e| WalkError::Pattern(pattern.clone(), e.to_string()))?;
+    }
+    for pattern in exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| WalkError::Pattern(pattern.clone(), e.to_string()))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| WalkError::Pattern(include.join(", "), e.to_string()))?;
+
+    let files = Mutex::new(Vec::new());
+    WalkBuilder::new(root)
+        .standard_filters(false)
+        .follow_links(false)
+        // The walk's depth 0 is the directory itself, its files are at depth 1
+        .max_depth(max_depth.map(|depth| depth + 1))
+        .overrides(overrides)
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                match entry {
+                    Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => files
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(entry.into_path()),
+                    Ok(_) => {}
+                    // An unreadable directory shouldn't cost the rest of the tree
+                    Err(e) => log::warn!("Skipping part of the directory walk: {}", e),
+                }
+                WalkState::Continue
+            })
+        });
+
+    let mut files = files.into_inner().unwrap_or_else(|e| e.into_inner());
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let root = std::env::temp_dir().join(format!("stegascan-walk-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for file in [
+            "cover.png",
+            ".hidden.png",
+            "notes.txt",
+            "album/art.png",
+            "node_modules/pkg/logo.png",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"data").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "album/\n").unwrap();
+
+        let files = walk(
+            &root,
+            &["*.png".to_string()],
+            &["node_modules/**".to_string()],
+            None,
+        )
+        .unwrap();
+        let relative: Vec<String> = files
+            .iter()
+            .map(|file| {
+                file.strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(relative, vec![".hidden.png", "album/art.png", "cover.png"]);
+
+        assert_eq!(walk(&root, &[], &[], None).unwrap().len(), 6);
+        assert_eq!(walk(&root, &[], &[], Some(0)).unwrap().len(), 4);
+        assert_eq!(walk(&root, &[], &[], Some(1)).unwrap().len(), 5);
+        assert!(walk(&root, &["[".to_string()], &[], None).is_err());
+        assert!(walk(&root.join("cover.png"), &[], &[], None).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,17 +1,118 @@
 use analyzers::{
-    Analyzer, exif_analyzer::ExifAnalyzerWithPath, id3_analyzer::Id3AnalyzerWithPath,
-    lsb_analyzer::LsbAnalyzer, magic_bytes_analyzer::MagicBytesAnalyzerWithPath,
-    spectrogram_analyzer::SpectrogramAnalyzer, video_frame_analyzer::VideoFrameAnalyzer,
+    Analyzer,
+    adaptive_lsb_analyzer::{AdaptiveLsbAnalyzer, RegionStats},
+    bpcs_analyzer::BpcsAnalyzer,
+    calibration_analyzer::CalibrationAnalyzer,
+    channel_correlation_analyzer::ChannelCorrelationAnalyzer,
+    content_classifier::{ContentClassifier, FLAT_LSB_NOISE_THRESHOLD},
+    cover_baseline::{BASELINE_SIGMA, Calibration, CoverBaselines, CoverFormat},
+    deflate_hunter::{DeflateHunter, MAX_HUNT_BYTES, MAX_STREAM_BYTES},
+    demodulator::{Demodulator, FskSignal, MAX_DECODED_BYTES},
+    ebook_analyzer::{EbookAnalysis, EbookAnalyzer, is_ebook},
+    exif_analyzer::{ExifAnalyzerWithPath, missing_metadata},
+    external_plugin::PluginSpec,
+    frame_pipeline::FramePipeline,
+    frame_sampler::{FrameSampler, SamplingStrategy},
+    gif_extension_analyzer::GifExtensionAnalyzer,
+    id3_analyzer::{Id3AnalyzerWithPath, LyricsPayloadKind, PictureInfo},
+    lsb_analyzer::{CHI_SQUARE_THRESHOLD, ENTROPY_THRESHOLD, LsbAnalyzer},
+    magic_bytes_analyzer::{
+        CustomSignature, MagicBytesAnalysis, MagicBytesAnalyzerWithPath, Strictness,
+        signature_database,
+    },
+    makernote_analyzer::MakerNoteAnalysis,
+    model_analyzer::{ModelAnalysis, ModelAnalyzer, model_format},
+    mp4_tag_analyzer::Mp4TagAnalyzer,
+    office_analyzer::{OfficeAnalysis, OfficeAnalyzer, is_office_document},
+    page_analyzer::PageAnalyzer,
+    payload_carver::{CarvedPayload, PayloadCarver},
+    phase_analyzer::PhaseAnalyzer,
+    png_text_analyzer::{KeywordKind, PngTextAnalyzer},
+    scene_detector::{Scene, SceneDetector},
+    shared,
+    slack_space_analyzer::{MIN_SLACK_BYTES, SlackSpaceAnalyzer},
+    source_code_analyzer::{SourceCodeAnalysis, SourceCodeAnalyzer, language_for},
+    spectrogram_analyzer::{
+        DEFAULT_TILE_SECONDS, HIGH_FREQUENCY_ENERGY_THRESHOLD, PersistentTone, SpectrogramAnalyzer,
+        analyze_tiles,
+    },
+    structured_text_analyzer::{StructuredTextAnalysis, StructuredTextAnalyzer, structured_format},
+    trailing_tag_analyzer::{TagItem, TrailingTagAnalyzer},
+    ultrasonic_analyzer::{ULTRASONIC_CUTOFF_HZ, UltrasonicAnalyzer},
+    versions::analyzer_version,
+    video_frame_analyzer::VideoFrameAnalyzer,
+    webp_structure_analyzer::WebpStructureAnalyzer,
+    ws_analyzer::WsAnalyzer,
 };
-use infer::Infer;
 use parsers::{
-    Parser as _, audio_parser::AudioParser, image_parser::ImageParser, text_parser::TextParser,
-    video_parser::VideoParser,
+    Parser as _,
+    audio_parser::AudioParser,
+    image_parser::ImageParser,
+    multi_image_parser::{MultiImageParser, MultiImageParserError},
+    text_parser::TextParser,
+    video_parser::{HwAccel, VideoDecodeOptions, VideoParser},
+    webp_parser::WebpParser,
 };
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
 
 use crate::error::ApiError;
+use crate::events::{EventSink, ScanEvent};
 use crate::models::*;
+use crate::uploads::UploadDir;
+use crate::usage;
+
+// Enough of the start of a file for `infer` to recognize any type it knows
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+// Analyzers that need the whole file in memory, such as carving and slack space, are skipped for
+// uploads larger than this so a multi-GB video is never loaded whole
+const WHOLE_FILE_LIMIT_BYTES: u64 = 256 * 1024 * 1024;
+
+// How video files are scanned
+#[derive(Debug, Clone, Copy)]
+pub struct VideoOptions {
+    pub sampling: SamplingStrategy,
+    // Luma histogram distance between consecutive frames that counts as a cut
+    pub scene_threshold: f64,
+    // Most sampled frames analyzed per scene, 0 for no limit
+    pub max_frames_per_scene: usize,
+    // Hardware decoder to try before falling back to software
+    pub hwaccel: HwAccel,
+    // Decode keyframes only, skipping the packets in between
+    pub keyframes_only: bool,
+}
+
+// How thorough a scan is. Fast serves callers that only need the verdict: it skips payload
+// carving, multi-page images, the block-level image analyzers, phase coding and carrier
+// demodulation, and analyzes a few keyframes per video scene, like the CLI's fast profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanProfile {
+    #[default]
+    Standard,
+    Fast,
+}
+
+impl ScanProfile {
+    fn video(self, video: VideoOptions) -> VideoOptions {
+        match self {
+            ScanProfile::Standard => video,
+            ScanProfile::Fast => VideoOptions {
+                sampling: SamplingStrategy::EveryNth(1),
+                max_frames_per_scene: 3,
+                keyframes_only: true,
+                ..video
+            },
+        }
+    }
+}
+
+// Indicators a verdict carries, in the order the summary lists them
+const VERDICT_INDICATORS: usize = 3;
 
 enum FileType {
     Audio,
@@ -20,244 +121,2202 @@ enum FileType {
     Image,
 }
 
-pub async fn run_full_analysis(
-    file_path: &Path,
-    video_sample_rate: usize,
-    _verbose: bool,
-) -> Result<AnalysisResponse, ApiError> {
-    // Get file metadata
-    let metadata = tokio::fs::metadata(file_path).await?;
-    let file_size = metadata.len();
+fn detect_file_type(file_data: &[u8]) -> FileType {
+    if let Some(kind) = shared::infer().get(file_data) {
+        match kind.mime_type() {
+            mime if mime.starts_with("audio/") => FileType::Audio,
+            mime if mime.starts_with("video/") => FileType::Video,
+            mime if mime.starts_with("text/") || mime.starts_with("application/") => FileType::Text,
+            mime if mime.starts_with("image/") => FileType::Image,
+            _ => FileType::Text,
+        }
+    } else {
+        FileType::Text
+    }
+}
+
+async fn build_file_info(file_path: &Path) -> Result<(FileInfo, FileType), ApiError> {
+    // Get file metadata
+    let metadata = tokio::fs::metadata(file_path).await?;
+    let file_size = metadata.len();
+
+    // Detect file type from the header alone
+    let mut header = Vec::new();
+    tokio::fs::File::open(file_path)
+        .await?
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut header)
+        .await?;
+    let file_type = detect_file_type(&header);
+
+    let detected_type = match file_type {
+        FileType::Audio => "Audio",
+        FileType::Video => "Video",
+        FileType::Text => "Text",
+        FileType::Image => "Image",
+    };
+
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|s| s.to_string());
+
+    let file_info = FileInfo {
+        path: file_path.to_string_lossy().to_string(),
+        size_bytes: file_size,
+        detected_type: detected_type.to_string(),
+        extension,
+    };
+
+    Ok((file_info, file_type))
+}
+
+// The whole file, for the analyzers that need it in memory; larger files fail them
+fn read_whole(file_path: &Path) -> Result<Vec<u8>, ApiError> {
+    let size = std::fs::metadata(file_path)?.len();
+    if size > WHOLE_FILE_LIMIT_BYTES {
+        return Err(ApiError::AnalysisFailed(format!(
+            "{} bytes is over the {} MiB read into memory",
+            size,
+            WHOLE_FILE_LIMIT_BYTES >> 20
+        )));
+    }
+    Ok(std::fs::read(file_path)?)
+}
+
+// The full analysis, reporting each section to `events` as soon as it is ready
+pub async fn run_full_analysis_with_events(
+    file_path: &Path,
+    video: VideoOptions,
+    events: EventSink<'_>,
+) -> Result<(AnalysisResponse, Vec<CarvedPayload>), ApiError> {
+    run_analysis(file_path, video, ScanProfile::Standard, events).await
+}
+
+// A fast scan reduced to its verdict
+pub async fn run_verdict_analysis(
+    file_path: &Path,
+    video: VideoOptions,
+) -> Result<VerdictResponse, ApiError> {
+    let (response, _) = run_analysis(file_path, video, ScanProfile::Fast, &|_| {}).await?;
+    Ok(verdict(&response.summary))
+}
+
+fn verdict(summary: &AnalysisSummary) -> VerdictResponse {
+    VerdictResponse {
+        verdict: if summary.steganography_detected {
+            Verdict::Suspicious
+        } else {
+            Verdict::Clean
+        },
+        confidence: summary.confidence_level.clone(),
+        top_indicators: summary
+            .threat_indicators
+            .iter()
+            .take(VERDICT_INDICATORS)
+            .cloned()
+            .collect(),
+    }
+}
+
+async fn run_analysis(
+    file_path: &Path,
+    video: VideoOptions,
+    profile: ScanProfile,
+    events: EventSink<'_>,
+) -> Result<(AnalysisResponse, Vec<CarvedPayload>), ApiError> {
+    let fast = profile == ScanProfile::Fast;
+    let env = RunEnvironment::capture();
+    // Every analyzer that finished reports a section, so the sections sent are the ones that ran
+    let sections = Mutex::new(Vec::new());
+    let record = |event: ScanEvent| {
+        sections.lock().unwrap().push(event.event);
+        events(event);
+    };
+    let events: EventSink<'_> = &record;
+    let (file_info, file_type) = build_file_info(file_path).await?;
+    events(ScanEvent::new("file_info", &file_info));
+
+    let mut response = AnalysisResponse {
+        scan_id: None,
+        cached: false,
+        file_info,
+        magic_bytes_analysis: None,
+        carved_payloads: Vec::new(),
+        slack_space: None,
+        deflate_streams: None,
+        format_specific_analysis: FormatSpecificAnalysis::Unknown,
+        plugins: Vec::new(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        summary: AnalysisSummary {
+            steganography_detected: false,
+            confidence_level: "low".to_string(),
+            threat_indicators: Vec::new(),
+            recommendations: Vec::new(),
+            verdict: SummaryVerdict::Clean,
+            extracted_payloads: Vec::new(),
+        },
+        annotations: Vec::new(),
+        run: None,
+    };
+
+    // Magic bytes analysis, carving out any complete embedded files it found
+    let mut carved = Vec::new();
+    if let Ok(magic_analysis) = analyze_magic_bytes(file_path, &env) {
+        let magic_report = build_magic_bytes_report(&magic_analysis);
+        events(ScanEvent::new("magic_bytes", &magic_report));
+        response.magic_bytes_analysis = Some(magic_report);
+
+        // Carving needs the whole file, so very large uploads are only scanned
+        let file_data = (!fast).then(|| read_whole(file_path).ok()).flatten();
+        if let Some(file_data) = file_data {
+            carved = PayloadCarver::analyze((file_data, magic_analysis))
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        }
+    }
+
+    // Slack space outside the declared container structure; formats without
+    // length-prefixed structure are skipped. Regions that don't start where the magic
+    // bytes scan already carved a payload are carved as well.
+    if let Ok((slack_report, slack_payloads)) = slack_space_report(file_path) {
+        events(ScanEvent::new("slack_space", &slack_report));
+        response.slack_space = Some(slack_report);
+        if !fast {
+            for payload in slack_payloads {
+                if !carved
+                    .iter()
+                    .any(|c: &CarvedPayload| c.offset == payload.offset)
+                {
+                    carved.push(payload);
+                }
+            }
+        }
+    }
+
+    // Compressed streams at any offset outside the container's own, inflated and returned
+    // as carved payloads
+    if !fast {
+        if let Ok((deflate_report, deflate_payloads)) = deflate_streams_report(file_path) {
+            events(ScanEvent::new("deflate", &deflate_report));
+            response.deflate_streams = Some(deflate_report);
+            for payload in deflate_payloads {
+                if !carved
+                    .iter()
+                    .any(|c: &CarvedPayload| c.offset == payload.offset)
+                {
+                    carved.push(payload);
+                }
+            }
+        }
+    }
+
+    // Format-specific analysis
+    match file_type {
+        FileType::Image => {
+            // Every page of a TIFF, entry of an ICO or item of a HEIF; the rest of the
+            // image analyzers only see the first
+            let page_analysis = if fast {
+                None
+            } else {
+                pages_report(file_path).ok().flatten()
+            };
+            if let Some(pages) = &page_analysis {
+                events(ScanEvent::new("pages", pages));
+            }
+
+            if let Ok(image) = ImageParser::parse_path(&file_path) {
+                let dimensions = ImageDimensions {
+                    width: image.width(),
+                    height: image.height(),
+                };
+
+                let exif_metadata = exif_report(file_path).ok();
+                if let Some(exif) = &exif_metadata {
+                    events(ScanEvent::new("exif", exif));
+                }
+
+                let missing_metadata = missing_metadata_report(file_path, image.clone());
+
+                let ws_analysis = ws_report(image.clone()).ok();
+                if let Some(ws) = &ws_analysis {
+                    events(ScanEvent::new("ws", ws));
+                }
+
+                let channel_correlation = (!fast)
+                    .then(|| channel_correlation_report(image.clone()).ok())
+                    .flatten();
+                if let Some(correlation) = &channel_correlation {
+                    events(ScanEvent::new("channel_correlation", correlation));
+                }
+
+                let adaptive_lsb = (!fast)
+                    .then(|| adaptive_lsb_report(image.clone()).ok())
+                    .flatten();
+                if let Some(adaptive) = &adaptive_lsb {
+                    events(ScanEvent::new("adaptive_lsb", adaptive));
+                }
+
+                let bpcs = (!fast).then(|| bpcs_report(image.clone()).ok()).flatten();
+                if let Some(bpcs) = &bpcs {
+                    events(ScanEvent::new("bpcs", bpcs));
+                }
+
+                // Only JPEGs produce a section
+                let calibration = (!fast)
+                    .then(|| calibration_report(file_path).ok())
+                    .flatten();
+                if let Some(calibration) = &calibration {
+                    events(ScanEvent::new("calibration", calibration));
+                }
+
+                // Only WebP files produce a section; anything else fails the RIFF check
+                let webp_analysis = webp_report(file_path, events).ok();
+                if let Some(webp) = &webp_analysis {
+                    events(ScanEvent::new("webp", webp));
+                }
+
+                // Likewise only GIFs pass the header check
+                let gif_analysis = gif_report(file_path).ok();
+                if let Some(gif) = &gif_analysis {
+                    events(ScanEvent::new("gif", gif));
+                }
+
+                let png_text = png_text_report(file_path).ok();
+                if let Some(png_text) = &png_text {
+                    events(ScanEvent::new("png_text", png_text));
+                }
+
+                let lsb_analysis = lsb_report(file_path, image, &env).ok();
+                if let Some(lsb) = &lsb_analysis {
+                    events(ScanEvent::new("lsb", lsb));
+                }
+
+                let image_analysis = ImageAnalysis {
+                    exif_metadata,
+                    missing_metadata,
+                    lsb_analysis,
+                    ws_analysis,
+                    channel_correlation,
+                    adaptive_lsb,
+                    bpcs,
+                    calibration,
+                    webp_analysis,
+                    gif_analysis,
+                    png_text,
+                    page_analysis,
+                    filter_analysis: None,
+                    dimensions,
+                };
+
+                response.format_specific_analysis =
+                    FormatSpecificAnalysis::Image(Box::new(image_analysis));
+            } else if page_analysis.is_some() {
+                // HEIF: the item structure was checked but no pixels can be decoded
+                response.format_specific_analysis =
+                    FormatSpecificAnalysis::Image(Box::new(ImageAnalysis {
+                        exif_metadata: None,
+                        missing_metadata: None,
+                        lsb_analysis: None,
+                        ws_analysis: None,
+                        channel_correlation: None,
+                        adaptive_lsb: None,
+                        bpcs: None,
+                        calibration: None,
+                        webp_analysis: None,
+                        gif_analysis: None,
+                        png_text: None,
+                        page_analysis,
+                        filter_analysis: None,
+                        // Unknown without a decoder
+                        dimensions: ImageDimensions {
+                            width: 0,
+                            height: 0,
+                        },
+                    }));
+            }
+        }
+        FileType::Audio => {
+            if let Ok((samples, sample_rate)) = AudioParser::parse_with_sample_rate(&file_path) {
+                let sample_count = samples.len();
+
+                let id3_analysis = id3_report(file_path, &env).ok().map(|(report, pictures)| {
+                    carved.extend(pictures);
+                    report
+                });
+                if let Some(id3) = &id3_analysis {
+                    events(ScanEvent::new("id3", id3));
+                }
+                let trailing_tags = trailing_tags_report(file_path).map(|(report, items)| {
+                    carved.extend(items);
+                    report
+                });
+                if let Some(trailing_tags) = &trailing_tags {
+                    events(ScanEvent::new("trailing_tags", trailing_tags));
+                }
+                let mp4_tags = mp4_tags_report(file_path).map(|(report, items)| {
+                    carved.extend(items);
+                    report
+                });
+                if let Some(mp4_tags) = &mp4_tags {
+                    events(ScanEvent::new("mp4_tags", mp4_tags));
+                }
+
+                // Recordings whose sample rate doesn't reach past the cutoff are skipped
+                let ultrasonic_analysis = ultrasonic_report(samples.clone(), sample_rate).ok();
+                if let Some(ultrasonic) = &ultrasonic_analysis {
+                    events(ScanEvent::new("ultrasonic", ultrasonic));
+                }
+
+                let phase_analysis = (!fast)
+                    .then(|| phase_report(samples.clone()).ok())
+                    .flatten();
+                if let Some(phase) = &phase_analysis {
+                    events(ScanEvent::new("phase", phase));
+                }
+
+                let mut carriers: Vec<f32> = ultrasonic_analysis
+                    .iter()
+                    .flat_map(|u| u.tones.iter().map(|t| t.frequency_hz))
+                    .collect();
+                let spectrogram_analysis = spectrogram_report(samples.clone(), sample_rate, &env)
+                    .ok()
+                    .map(|(report, tones)| {
+                        carriers.extend(tones);
+                        report
+                    });
+                if let Some(spectrogram) = &spectrogram_analysis {
+                    events(ScanEvent::new("spectrogram", spectrogram));
+                }
+
+                // Without flagged tones there is nothing to demodulate
+                let demodulation = (!fast)
+                    .then(|| demodulation_report(samples, sample_rate, carriers).ok())
+                    .flatten();
+                if let Some(demodulation) = &demodulation {
+                    events(ScanEvent::new("demodulation", demodulation));
+                }
+
+                let audio_analysis = AudioAnalysis {
+                    sample_count,
+                    sample_rate,
+                    samples_file: None,
+                    id3_analysis,
+                    spectrogram_analysis,
+                    ultrasonic_analysis,
+                    demodulation,
+                    phase_analysis,
+                    differential: None,
+                    trailing_tags,
+                    mp4_tags,
+                };
+
+                response.format_specific_analysis =
+                    FormatSpecificAnalysis::Audio(Box::new(audio_analysis));
+            }
+        }
+        FileType::Video => {
+            if let Ok(mut video_analysis) = video_analysis(file_path, profile.video(video), events)
+            {
+                if let Some((mp4_tags, items)) = mp4_tags_report(file_path) {
+                    carved.extend(items);
+                    video_analysis.mp4_tags = Some(mp4_tags);
+                }
+                events(ScanEvent::new("video", &video_analysis));
+                response.format_specific_analysis = FormatSpecificAnalysis::Video(video_analysis);
+            }
+        }
+        FileType::Text => {
+            if let Ok((text_analysis, ole_payloads)) = text_analysis(file_path, &env) {
+                carved.extend(ole_payloads);
+                events(ScanEvent::new("text", &text_analysis));
+                response.format_specific_analysis =
+                    FormatSpecificAnalysis::Text(Box::new(text_analysis));
+            }
+        }
+    }
+
+    // External analyzer plugins from STEGASCAN_PLUGINS_FILE
+    for plugin in plugin_reports(file_path)? {
+        events(ScanEvent::new("plugin", &plugin));
+        response.plugins.push(plugin);
+    }
+
+    // Finalize summary
+    finalize_summary(&mut response, &carved);
+    events(ScanEvent::new("summary", &response.summary));
+    response.run = Some(run_metadata(
+        &env,
+        &sections.into_inner().unwrap(),
+        &response.plugins,
+        profile,
+        profile.video(video),
+    ));
+
+    Ok((response, carved))
+}
+
+// The analyzers that ran with their versions and the parameters they were run with, in the
+// order they reported, followed by the plugins
+fn run_metadata(
+    env: &RunEnvironment,
+    sections: &[&str],
+    plugins: &[PluginReport],
+    profile: ScanProfile,
+    video: VideoOptions,
+) -> RunMetadata {
+    let mut analyzers: Vec<AnalyzerRunReport> = Vec::new();
+    for name in sections {
+        let Some(version) = analyzer_version(name) else {
+            continue;
+        };
+        if analyzers.iter().any(|analyzer| analyzer.name == *name) {
+            continue;
+        }
+        let mut parameters: Vec<(&str, String)> = match *name {
+            "magic_bytes" => vec![(
+                "strictness",
+                env.strictness().unwrap_or_default().to_string(),
+            )],
+            "slack_space" => vec![("min_bytes", MIN_SLACK_BYTES.to_string())],
+            "lsb" => vec![
+                ("chi_square", CHI_SQUARE_THRESHOLD.to_string()),
+                ("entropy", ENTROPY_THRESHOLD.to_string()),
+                ("flat_lsb_noise", FLAT_LSB_NOISE_THRESHOLD.to_string()),
+                ("calibration_sigma", env.calibration_sigma.to_string()),
+            ],
+            "spectrogram" => vec![
+                (
+                    "high_frequency_energy",
+                    HIGH_FREQUENCY_ENERGY_THRESHOLD.to_string(),
+                ),
+                ("calibration_sigma", env.calibration_sigma.to_string()),
+                ("tile_seconds", DEFAULT_TILE_SECONDS.to_string()),
+            ],
+            "ultrasonic" => vec![("cutoff_hz", ULTRASONIC_CUTOFF_HZ.to_string())],
+            "demodulation" => vec![("max_decoded_bytes", MAX_DECODED_BYTES.to_string())],
+            "video" => vec![
+                ("sampling", video.sampling.to_string()),
+                ("scene_threshold", video.scene_threshold.to_string()),
+                (
+                    "max_frames_per_scene",
+                    video.max_frames_per_scene.to_string(),
+                ),
+                ("keyframes_only", video.keyframes_only.to_string()),
+                ("hwaccel", video.hwaccel.to_string()),
+            ],
+            _ => vec![],
+        };
+        if let Some(signatures) = env
+            .signatures_file
+            .as_ref()
+            .filter(|_| *name == "magic_bytes")
+        {
+            parameters.push(("signatures", signatures.clone()));
+        }
+        // Calibrated thresholds replace the built-in ones above
+        if let Some(calibration) = env
+            .calibration_file
+            .as_ref()
+            .filter(|_| matches!(*name, "lsb" | "spectrogram"))
+        {
+            parameters.push(("calibration", calibration.clone()));
+        }
+        analyzers.push(AnalyzerRunReport {
+            name: name.to_string(),
+            version: Some(version),
+            parameters: parameters
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        });
+    }
+    analyzers.extend(plugins.iter().map(|plugin| AnalyzerRunReport {
+        name: format!("plugin:{}", plugin.name),
+        version: None,
+        parameters: BTreeMap::new(),
+    }));
+
+    let custom_file = env.signatures_file.clone();
+    RunMetadata {
+        scanner: concat!("stegascan-api ", env!("CARGO_PKG_VERSION")).to_string(),
+        profile: Some(
+            match profile {
+                ScanProfile::Standard => "standard",
+                ScanProfile::Fast => "fast",
+            }
+            .to_string(),
+        ),
+        analyzers,
+        signature_database: SignatureDatabaseReport {
+            version: signature_database().fingerprint.clone(),
+            signatures: signature_database().signatures,
+            custom_sha256: custom_file
+                .as_ref()
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|bytes| shared::hex(&Sha256::digest(bytes))),
+            custom_file,
+        },
+    }
+}
+
+pub async fn run_single_analyzer(
+    file_path: &Path,
+    analyzer: &str,
+    video: VideoOptions,
+) -> Result<SingleAnalyzerResponse, ApiError> {
+    let (file_info, _file_type) = build_file_info(file_path).await?;
+    let env = RunEnvironment::capture();
+
+    let result = match analyzer {
+        "magic_bytes" | "magic" => {
+            let magic_analysis = analyze_magic_bytes(file_path, &env)?;
+            AnalyzerSection::MagicBytes(build_magic_bytes_report(&magic_analysis))
+        }
+        "slack_space" | "slack" => AnalyzerSection::SlackSpace(slack_space_report(file_path)?.0),
+        "deflate" | "zlib" => AnalyzerSection::DeflateStreams(deflate_streams_report(file_path)?.0),
+        "exif" => AnalyzerSection::Exif(exif_report(file_path)?),
+        "lsb" => {
+            let image = ImageParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Lsb(lsb_report(file_path, image, &env)?)
+        }
+        "ws" => {
+            let image = ImageParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Ws(ws_report(image)?)
+        }
+        "channel_correlation" => {
+            let image = ImageParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::ChannelCorrelation(channel_correlation_report(image)?)
+        }
+        "adaptive_lsb" => {
+            let image = ImageParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::AdaptiveLsb(adaptive_lsb_report(image)?)
+        }
+        "bpcs" => {
+            let image = ImageParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Bpcs(bpcs_report(image)?)
+        }
+        "calibration" => AnalyzerSection::Calibration(calibration_report(file_path)?),
+        "webp" => AnalyzerSection::Webp(webp_report(file_path, &|_| {})?),
+        "gif" => AnalyzerSection::Gif(gif_report(file_path)?),
+        "png_text" => AnalyzerSection::PngText(png_text_report(file_path)?),
+        "pages" | "sub_images" => {
+            AnalyzerSection::Pages(pages_report(file_path)?.ok_or_else(|| {
+                ApiError::AnalysisFailed("Not a multi-image TIFF, ICO or HEIF file".to_string())
+            })?)
+        }
+        "id3" => AnalyzerSection::Id3(id3_report(file_path, &env)?.0),
+        "spectrogram" => {
+            let (samples, sample_rate) = AudioParser::parse_with_sample_rate(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Spectrogram(spectrogram_report(samples, sample_rate, &env)?.0)
+        }
+        "ultrasonic" => {
+            let (samples, sample_rate) = AudioParser::parse_with_sample_rate(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Ultrasonic(ultrasonic_report(samples, sample_rate)?)
+        }
+        "phase" => {
+            let samples = AudioParser::parse_path(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            AnalyzerSection::Phase(phase_report(samples)?)
+        }
+        "demodulation" => {
+            let (samples, sample_rate) = AudioParser::parse_with_sample_rate(&file_path)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+            let mut carriers: Vec<f32> = ultrasonic_report(samples.clone(), sample_rate)
+                .map(|u| u.tones.iter().map(|t| t.frequency_hz).collect())
+                .unwrap_or_default();
+            carriers.extend(spectrogram_report(samples.clone(), sample_rate, &env)?.1);
+            AnalyzerSection::Demodulation(demodulation_report(samples, sample_rate, carriers)?)
+        }
+        "video" => AnalyzerSection::Video(video_analysis(file_path, video, &|_| {})?),
+        "text" => AnalyzerSection::Text(Box::new(text_analysis(file_path, &env)?.0)),
+        "plugins" => AnalyzerSection::Plugins(plugin_reports(file_path)?),
+        _ => return Err(ApiError::UnknownAnalyzer(analyzer.to_string())),
+    };
+
+    Ok(SingleAnalyzerResponse {
+        analyzer: analyzer.to_string(),
+        file_info,
+        result,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+// STEGASCAN_SIGNATURES_FILE adds user-defined signatures to the manual scan and
+// STEGASCAN_SIGNATURE_STRICTNESS sets how much header validation its hits need
+fn analyze_magic_bytes(
+    file_path: &Path,
+    env: &RunEnvironment,
+) -> Result<MagicBytesAnalysis, ApiError> {
+    let custom_signatures = match &env.signatures_file {
+        Some(path) => CustomSignature::load(Path::new(path))
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?,
+        None => Vec::new(),
+    };
+    let strictness = env.strictness().map_err(ApiError::AnalysisFailed)?;
+
+    MagicBytesAnalyzerWithPath::new(file_path)
+        .with_custom_signatures(&custom_signatures)
+        .with_strictness(strictness)
+        .analyze()
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))
+}
+
+// A plugin that fails to run is reported with its error rather than failing the scan; a
+// plugins file that can't be read fails it
+fn plugin_reports(file_path: &Path) -> Result<Vec<PluginReport>, ApiError> {
+    let plugins = match std::env::var("STEGASCAN_PLUGINS_FILE") {
+        Ok(path) => PluginSpec::load(Path::new(&path))
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    Ok(plugins
+        .iter()
+        .map(|plugin| match plugin.run(file_path) {
+            Ok(output) => PluginReport {
+                name: plugin.name.clone(),
+                is_suspicious: output.suspicious,
+                findings: output
+                    .findings
+                    .into_iter()
+                    .map(|f| PluginFindingReport {
+                        description: f.description,
+                        offset: f.offset,
+                        confidence: f.confidence,
+                    })
+                    .collect(),
+                details: output.details,
+                error: None,
+            },
+            Err(e) => {
+                tracing::warn!("{}", e);
+                PluginReport {
+                    name: plugin.name.clone(),
+                    is_suspicious: false,
+                    findings: Vec::new(),
+                    details: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        })
+        .collect())
+}
+
+// Regions that could hold a payload are returned as carved payloads
+fn slack_space_report(
+    file_path: &Path,
+) -> Result<(SlackSpaceReport, Vec<CarvedPayload>), ApiError> {
+    let data = read_whole(file_path)?;
+    let analysis = SlackSpaceAnalyzer::analyze_bytes(&data)
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let payloads = analysis
+        .regions
+        .iter()
+        .filter(|r| r.length >= MIN_SLACK_BYTES && !r.all_zero)
+        .map(|r| CarvedPayload {
+            offset: r.offset,
+            description: format!("{} slack: {}", analysis.container, r.description),
+            file_type: r
+                .detected_type
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            data: data[r.offset..][..r.length].to_vec(),
+        })
+        .collect();
+
+    let report = SlackSpaceReport {
+        container: analysis.container.clone(),
+        accounted_bytes: analysis.accounted_bytes,
+        slack_bytes: analysis.file_size - analysis.accounted_bytes,
+        is_suspicious: analysis.suspicious,
+        regions: analysis
+            .regions
+            .iter()
+            .map(|r| SlackRegionReport {
+                offset: r.offset,
+                offset_hex: format!("0x{:X}", r.offset),
+                length: r.length,
+                entropy: r.entropy,
+                description: r.description.clone(),
+                detected_type: r.detected_type.clone(),
+                carved_file: None,
+                sha256: None,
+                also_carved_from: Vec::new(),
+            })
+            .collect(),
+    };
+    Ok((report, payloads))
+}
+
+// Every stream found is returned inflated as a carved payload
+fn deflate_streams_report(
+    file_path: &Path,
+) -> Result<(DeflateStreamsReport, Vec<CarvedPayload>), ApiError> {
+    // Only the first MAX_HUNT_BYTES are searched; the margin past them lets a stream that
+    // starts near the end still inflate
+    let mut data = Vec::new();
+    std::fs::File::open(file_path)?
+        .take((MAX_HUNT_BYTES + MAX_STREAM_BYTES) as u64)
+        .read_to_end(&mut data)?;
+    let analysis =
+        DeflateHunter::analyze(data).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let report = DeflateStreamsReport {
+        searched_bytes: analysis.searched_bytes,
+        container_bytes: analysis.container_bytes,
+        is_suspicious: analysis.suspicious,
+        streams: analysis
+            .streams
+            .iter()
+            .map(|s| DeflateStreamReport {
+                offset: s.offset,
+                offset_hex: format!("0x{:X}", s.offset),
+                format: s.format.to_string(),
+                compressed_length: s.compressed_length,
+                inflated_length: s.data.len(),
+                complete: s.complete,
+                classification: s.classification.clone(),
+                text_preview: s.preview.clone(),
+                carved_file: None,
+                sha256: None,
+                also_carved_from: Vec::new(),
+            })
+            .collect(),
+    };
+    let payloads = analysis
+        .streams
+        .into_iter()
+        .map(|s| CarvedPayload {
+            offset: s.offset,
+            description: format!("inflated {} stream", s.format),
+            file_type: s.classification,
+            data: s.data,
+        })
+        .collect();
+    Ok((report, payloads))
+}
+
+fn build_magic_bytes_report(magic_analysis: &MagicBytesAnalysis) -> MagicBytesReport {
+    MagicBytesReport {
+        primary_format: magic_analysis.primary_format.clone(),
+        expected_format: magic_analysis.expected_format.clone(),
+        total_signatures_found: magic_analysis.total_signatures_found,
+        has_multiple_formats: magic_analysis.has_multiple_formats,
+        has_suspicious_data: magic_analysis.has_suspicious_data,
+        format_summary: FormatSummary {
+            images: magic_analysis.format_summary.image_files,
+            audio: magic_analysis.format_summary.audio_files,
+            video: magic_analysis.format_summary.video_files,
+            text_documents: magic_analysis.format_summary.text_files,
+            archives: magic_analysis.format_summary.archive_files,
+            executables: magic_analysis.format_summary.executable_files,
+            other: magic_analysis.format_summary.other_files,
+        },
+        embedded_files: magic_analysis
+            .embedded_files
+            .iter()
+            .map(|f| EmbeddedFileInfo {
+                offset: f.offset,
+                offset_hex: format!("0x{:X}", f.offset),
+                description: f.description.clone(),
+                file_type: f.file_type.clone(),
+                confidence: f.confidence.clone(),
+            })
+            .collect(),
+        suspicious_findings: magic_analysis.suspicious_findings.clone(),
+    }
+}
+
+fn exif_report(file_path: &Path) -> Result<ExifReport, ApiError> {
+    let exif_data = ExifAnalyzerWithPath::new(file_path)
+        .analyze()
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(ExifReport {
+        fields_found: exif_data.metadata.len(),
+        has_thumbnail: exif_data.has_thumbnail,
+        thumbnail_size_bytes: exif_data.thumbnail_size,
+        comment_fields: exif_data.comment_fields,
+        suspicious_fields: exif_data.suspicious_fields,
+        metadata: exif_data
+            .metadata
+            .iter()
+            .map(|(k, v)| MetadataField {
+                key: k.clone(),
+                value: v.clone(),
+            })
+            .collect(),
+        maker_note: exif_data.maker_note.as_ref().map(maker_note_report),
+    })
+}
+
+// Camera tags missing from what looks like a camera photo, a weak sign of stripped metadata
+fn missing_metadata_report(
+    file_path: &Path,
+    image: image::DynamicImage,
+) -> Option<MissingMetadataReport> {
+    let mime = shared::infer().get_from_path(file_path).ok()??.mime_type();
+    let content = ContentClassifier::analyze(image).ok()?;
+    let exif = ExifAnalyzerWithPath::new(file_path).analyze().ok();
+    let missing = missing_metadata(mime, content.class, exif.as_ref())?;
+    Some(MissingMetadataReport {
+        expected_source: missing.expected_source.to_string(),
+        missing_fields: missing.missing_fields,
+    })
+}
+
+fn maker_note_report(maker_note: &MakerNoteAnalysis) -> MakerNoteReport {
+    MakerNoteReport {
+        layout: maker_note.layout.to_string(),
+        offset: maker_note.offset,
+        size_bytes: maker_note.size,
+        entries: maker_note.entries.len(),
+        accounted_bytes: maker_note.accounted_bytes,
+        preview_bytes: maker_note.preview_bytes,
+        findings: maker_note
+            .findings
+            .iter()
+            .map(|finding| MakerNoteFindingReport {
+                offset: finding.offset,
+                size_bytes: finding.size,
+                description: finding.description.clone(),
+            })
+            .collect(),
+    }
+}
+
+// The environment settings the analyzers read, taken once when a scan starts so the run
+// metadata records the values the analyzers were actually run with
+struct RunEnvironment {
+    signatures_file: Option<String>,
+    signature_strictness: Option<String>,
+    // As written by `stegascan calibrate`, the statistics of a site's known-clean files that
+    // scans are judged against
+    calibration_file: Option<String>,
+    // Standard deviations from clean files that are too far
+    calibration_sigma: f64,
+}
+
+impl RunEnvironment {
+    fn capture() -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        Self {
+            signatures_file: var("STEGASCAN_SIGNATURES_FILE"),
+            signature_strictness: var("STEGASCAN_SIGNATURE_STRICTNESS"),
+            calibration_file: var("STEGASCAN_CALIBRATION_FILE"),
+            calibration_sigma: var("STEGASCAN_CALIBRATION_SIGMA")
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(BASELINE_SIGMA),
+        }
+    }
+
+    fn strictness(&self) -> Result<Strictness, String> {
+        match &self.signature_strictness {
+            Some(value) => value.parse(),
+            None => Ok(Strictness::default()),
+        }
+    }
+
+    fn calibration(&self) -> Result<Option<Calibration>, ApiError> {
+        match &self.calibration_file {
+            Some(path) => Calibration::read(Path::new(path))
+                .map(Some)
+                .map_err(|e| ApiError::AnalysisFailed(e.to_string())),
+            None => Ok(None),
+        }
+    }
+}
+
+// Images with a cover baseline are judged on how far they stray from clean covers of the
+// same format and class
+fn lsb_report(
+    file_path: &Path,
+    image: image::DynamicImage,
+    env: &RunEnvironment,
+) -> Result<LsbReport, ApiError> {
+    let baselines = match env.calibration()? {
+        Some(calibration) => CoverBaselines::calibrated(calibration),
+        None => CoverBaselines::builtin(),
+    };
+    let sigma = env.calibration_sigma;
+
+    // Screenshots are judged on LSB noise inside their flat regions instead
+    let content = ContentClassifier::analyze(image.clone()).ok();
+
+    // CMYK/YCCK JPEGs are analyzed on their stored channels, not the RGB conversion
+    let native_jpeg = ImageParser::parse_native_jpeg(file_path).ok().flatten();
+    let (color_model, lsb_result) = match native_jpeg {
+        Some(native) => (
+            native.color_model,
+            LsbAnalyzer::analyze_interleaved(&native.data, native.color_model.channel_names()),
+        ),
+        None => (
+            ImageParser::color_model(&image),
+            LsbAnalyzer::analyze(image),
+        ),
+    };
+    let lsb_analysis = lsb_result.map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let channels = lsb_analysis
+        .chi_square_scores
+        .iter()
+        .enumerate()
+        .map(|(i, score)| LsbChannelAnalysis {
+            channel_name: lsb_analysis.channel_names[i].clone(),
+            chi_square_score: *score,
+            entropy_score: lsb_analysis.entropy_scores[i],
+        })
+        .collect();
+
+    let deviation = content.as_ref().and_then(|content| {
+        baselines.deviation(
+            CoverFormat::from_path(file_path),
+            content.class,
+            &lsb_analysis,
+        )
+    });
+    let is_suspicious = match (&content, &deviation) {
+        (Some(content), Some(deviation)) => {
+            deviation.is_suspicious_for(content, sigma, FLAT_LSB_NOISE_THRESHOLD)
+        }
+        (Some(content), None) => lsb_analysis.is_suspicious_for(
+            content,
+            CHI_SQUARE_THRESHOLD,
+            ENTROPY_THRESHOLD,
+            FLAT_LSB_NOISE_THRESHOLD,
+        ),
+        (None, _) => lsb_analysis.suspicious,
+    };
+
+    Ok(LsbReport {
+        is_suspicious,
+        bit_depth: lsb_analysis.bit_depth,
+        color_model: color_model.to_string(),
+        channels,
+        content: content.map(|content| ContentClassReport {
+            class: content.class.to_string(),
+            flat_ratio: content.flat_ratio,
+            unique_colors: content.unique_colors,
+            flat_lsb_noise: content.flat_lsb_noise,
+        }),
+        baseline: deviation.map(|deviation| LsbBaselineReport {
+            format: deviation.baseline.format.to_string(),
+            content: deviation.baseline.content.to_string(),
+            sigma,
+            channels: deviation
+                .channels
+                .into_iter()
+                .map(|channel| LsbChannelDeviation {
+                    channel_name: channel.channel,
+                    chi_square_per_pair: channel.chi_square_per_pair,
+                    chi_square_z: channel.chi_square_z,
+                    entropy_z: channel.entropy_z,
+                })
+                .collect(),
+        }),
+        output_files: Vec::new(),
+    })
+}
+
+fn ws_report(image: image::DynamicImage) -> Result<WsReport, ApiError> {
+    let ws_analysis =
+        WsAnalyzer::analyze(image).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let channels = ws_analysis
+        .channels
+        .iter()
+        .zip(["Red", "Green", "Blue"])
+        .map(|(estimate, channel)| WsChannelReport {
+            channel_name: channel.to_string(),
+            sample_pair_rate: estimate.sample_pair_rate,
+            weighted_stego_rate: estimate.weighted_stego_rate,
+        })
+        .collect();
+
+    Ok(WsReport {
+        is_suspicious: ws_analysis.suspicious,
+        estimated_payload_rate: ws_analysis.estimated_payload_rate,
+        estimated_change_rate: ws_analysis.estimated_change_rate,
+        channels,
+    })
+}
+
+fn channel_correlation_report(
+    image: image::DynamicImage,
+) -> Result<ChannelCorrelationReport, ApiError> {
+    let correlation = ChannelCorrelationAnalyzer::analyze(image)
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let collapsed_blocks = correlation
+        .collapsed_blocks
+        .iter()
+        .map(|block| CollapsedBlockReport {
+            x: block.x,
+            y: block.y,
+            lsb_correlation: block.lsb_correlation,
+            reference_correlation: block.reference_correlation,
+        })
+        .collect();
+
+    Ok(ChannelCorrelationReport {
+        is_suspicious: correlation.suspicious,
+        block_size: correlation.block_size,
+        blocks_evaluated: correlation.blocks_evaluated,
+        collapsed_block_count: correlation.collapsed_block_count,
+        collapsed_ratio: correlation.collapsed_ratio,
+        mean_lsb_correlation: correlation.mean_lsb_correlation,
+        mean_reference_correlation: correlation.mean_reference_correlation,
+        collapsed_blocks,
+        overlay: None,
+    })
+}
+
+fn adaptive_lsb_report(image: image::DynamicImage) -> Result<AdaptiveLsbReport, ApiError> {
+    let adaptive =
+        AdaptiveLsbAnalyzer::analyze(image).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let region_report = |region: &RegionStats| RegionLsbReport {
+        blocks: region.blocks,
+        pov_score: region.pov_score,
+        lsb_ones_ratio: region.lsb_ones_ratio,
+    };
+
+    Ok(AdaptiveLsbReport {
+        is_suspicious: adaptive.suspicious,
+        block_size: adaptive.block_size,
+        smooth: region_report(&adaptive.smooth),
+        textured: region_report(&adaptive.textured),
+        pov_contrast: adaptive.pov_contrast,
+        overlay: None,
+    })
+}
+
+// APIC pictures and data between stacked ID3v2 tags are returned as payloads so they are
+// delivered like carved files
+fn id3_report(
+    file_path: &Path,
+    env: &RunEnvironment,
+) -> Result<(Id3Report, Vec<CarvedPayload>), ApiError> {
+    let id3_data = Id3AnalyzerWithPath::new(file_path)
+        .analyze()
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    let mut pictures = Vec::new();
+    let mut payloads = Vec::new();
+    for picture in id3_data.pictures {
+        pictures.push(id3_picture_report(&picture, env)?);
+        payloads.push(CarvedPayload {
+            offset: picture.offset.unwrap_or(0),
+            description: format!("ID3 APIC picture ({})", picture.picture_type),
+            file_type: "image".to_string(),
+            data: picture.data,
+        });
+    }
+
+    let mut tag_gaps = Vec::new();
+    for gap in id3_data.tag_gaps {
+        tag_gaps.push(Id3TagGapReport {
+            offset: gap.offset,
+            size_bytes: gap.data.len(),
+            classification: gap.classification.clone(),
+            file: None,
+            sha256: None,
+        });
+        payloads.push(CarvedPayload {
+            offset: gap.offset,
+            description: "Data between ID3v2 tags".to_string(),
+            file_type: gap.classification,
+            data: gap.data,
+        });
+    }
+
+    let report = Id3Report {
+        title: id3_data.title,
+        artist: id3_data.artist,
+        album: id3_data.album,
+        year: id3_data.year,
+        comments_count: id3_data.comments.len(),
+        pictures_count: pictures.len(),
+        private_frames_count: id3_data.private_frames.len(),
+        suspicious_frames: id3_data.suspicious_frames,
+        pictures,
+        chapters: id3_data
+            .chapters
+            .into_iter()
+            .map(|c| Id3ChapterReport {
+                element_id: c.element_id,
+                start_time_ms: c.start_time_ms,
+                end_time_ms: c.end_time_ms,
+                title: c.title,
+                sub_frames: c.sub_frames,
+                urls: c.urls,
+                picture_count: c.picture_count,
+                largest_binary_bytes: c.largest_binary_bytes,
+                findings: c.findings,
+            })
+            .collect(),
+        podcast_frames: id3_data
+            .podcast_frames
+            .into_iter()
+            .map(|(key, value)| MetadataField { key, value })
+            .collect(),
+        lyrics_payloads: id3_data
+            .lyrics_payloads
+            .into_iter()
+            .map(|payload| Id3LyricsPayloadReport {
+                frame: payload.frame,
+                timestamp: payload.timestamp,
+                kind: match payload.kind {
+                    LyricsPayloadKind::Base64 => "base64".to_string(),
+                    LyricsPayloadKind::Binary => "binary".to_string(),
+                },
+                length: payload.length,
+                decoded_size: payload.decoded.len(),
+                classification: payload.classification,
+            })
+            .collect(),
+        v2_tags: id3_data
+            .v2_tags
+            .into_iter()
+            .map(|tag| Id3v2TagReport {
+                offset: tag.offset,
+                version: format!("2.{}.{}", tag.version.0, tag.version.1),
+                size_bytes: tag.size,
+            })
+            .collect(),
+        v1_tag: id3_data.v1_tag.map(|tag| Id3v1TagReport {
+            offset: tag.offset,
+            title: tag.title,
+            artist: tag.artist,
+            album: tag.album,
+            year: tag.year,
+            comment: tag.comment,
+            track: tag.track,
+            genre: tag.genre,
+            extended_offset: tag.extended_offset,
+            hidden_fields: tag
+                .hidden_bytes
+                .into_iter()
+                .map(|(field, _)| field)
+                .collect(),
+        }),
+        tag_gaps,
+        tag_mismatches: id3_data
+            .tag_mismatches
+            .into_iter()
+            .map(|m| Id3TagMismatchReport {
+                field: m.field,
+                v1: m.v1,
+                v2: m.v2,
+            })
+            .collect(),
+    };
+
+    Ok((report, payloads))
+}
+
+// Flagged APEv2 and Lyrics3 values are returned as payloads. None when there are no such tags.
+fn trailing_tags_report(file_path: &Path) -> Option<(TrailingTagsReport, Vec<CarvedPayload>)> {
+    let data = read_whole(file_path).ok()?;
+    let analysis = TrailingTagAnalyzer::analyze(data).ok()?;
+
+    let mut payloads = Vec::new();
+    let mut tags = Vec::new();
+    for tag in analysis.tags {
+        let kind = tag.kind.to_string();
+        let items = tag
+            .items
+            .into_iter()
+            .map(|item| tag_item_report(item, &kind, &mut payloads))
+            .collect();
+        tags.push(TrailingTagReport {
+            kind: tag.kind.to_string(),
+            offset: tag.offset,
+            size_bytes: tag.size,
+            items,
+            findings: tag.findings,
+        });
+    }
+
+    Some((
+        TrailingTagsReport {
+            is_suspicious: analysis.suspicious,
+            tags,
+        },
+        payloads,
+    ))
+}
+
+fn mp4_tags_report(file_path: &Path) -> Option<(Mp4TagsReport, Vec<CarvedPayload>)> {
+    let data = read_whole(file_path).ok()?;
+    let analysis = Mp4TagAnalyzer::analyze(data).ok()?;
+
+    let mut payloads = Vec::new();
+    let items = analysis
+        .items
+        .into_iter()
+        .map(|item| tag_item_report(item, "ilst", &mut payloads))
+        .collect();
+    Some((
+        Mp4TagsReport {
+            is_suspicious: analysis.suspicious,
+            offset: analysis.offset,
+            size_bytes: analysis.size,
+            items,
+            findings: analysis.findings,
+        },
+        payloads,
+    ))
+}
+
+// A flagged item's value is carved as "<tag> item <key>"
+fn tag_item_report(item: TagItem, tag: &str, payloads: &mut Vec<CarvedPayload>) -> TagItemReport {
+    let report = TagItemReport {
+        key: item.key.clone(),
+        offset: item.offset,
+        size_bytes: item.value.len(),
+        binary: item.binary,
+        classification: item.classification.clone(),
+        findings: item.findings.clone(),
+        file: None,
+        sha256: None,
+    };
+    if !item.findings.is_empty() {
+        payloads.push(CarvedPayload {
+            offset: item.offset,
+            description: format!("{} item {}", tag, item.key),
+            file_type: item.classification,
+            data: item.value,
+        });
+    }
+    report
+}
+
+// Album art is a common nested carrier, so each picture goes through the image analyzers
+fn id3_picture_report(
+    picture: &PictureInfo,
+    env: &RunEnvironment,
+) -> Result<Id3PictureReport, ApiError> {
+    let picture_file =
+        UploadDir::from_env().persist(&picture.data, &format!(".{}", picture.extension()))?;
+    let path = picture_file.path();
+
+    let magic_bytes_analysis = analyze_magic_bytes(path, env)
+        .ok()
+        .map(|analysis| build_magic_bytes_report(&analysis));
+    let exif_metadata = exif_report(path).ok();
+    let lsb_analysis = ImageParser::parse_path(&path)
+        .ok()
+        .and_then(|image| lsb_report(path, image, env).ok());
+
+    let is_suspicious = magic_bytes_analysis
+        .as_ref()
+        .is_some_and(|magic| magic.has_suspicious_data)
+        || exif_metadata
+            .as_ref()
+            .is_some_and(|exif| !exif.suspicious_fields.is_empty())
+        || lsb_analysis.as_ref().is_some_and(|lsb| lsb.is_suspicious);
+
+    Ok(Id3PictureReport {
+        picture_type: picture.picture_type.clone(),
+        mime_type: picture.mime_type.clone(),
+        description: picture.description.clone(),
+        size_bytes: picture.data_size,
+        offset: picture.offset,
+        file: None,
+        sha256: None,
+        also_carved_from: Vec::new(),
+        magic_bytes_analysis,
+        exif_metadata,
+        lsb_analysis,
+        is_suspicious,
+    })
+}
+
+// Also returns the frequencies of any persistent tones, for the demodulator. Recordings longer
+// than a tile are analyzed per window, as the CLI does.
+fn spectrogram_report(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    env: &RunEnvironment,
+) -> Result<(SpectrogramReport, Vec<f32>), ApiError> {
+    // Calibrated on clean recordings, when there are some
+    let threshold = match env.calibration()?.and_then(|calibration| calibration.audio) {
+        Some(audio) => audio.high_frequency_energy_threshold(env.calibration_sigma),
+        None => HIGH_FREQUENCY_ENERGY_THRESHOLD,
+    };
+
+    if samples.len() > sample_rate as usize * DEFAULT_TILE_SECONDS as usize {
+        let tiles = analyze_tiles(&samples, sample_rate, DEFAULT_TILE_SECONDS)
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        let mut report = SpectrogramReport {
+            high_frequency_energy: 0.0,
+            hidden_message_detected: false,
+            suspicious_patterns: Vec::new(),
+            output_file: None,
+            tiles: Vec::new(),
+            persistent_tones: Vec::new(),
+        };
+        let mut tones = Vec::new();
+        for tile in &tiles {
+            report.high_frequency_energy = report
+                .high_frequency_energy
+                .max(tile.data.high_frequency_energy);
+            let detected = tile.data.has_hidden_message_at(threshold);
+            report.hidden_message_detected |= detected;
+            report
+                .suspicious_patterns
+                .extend(tile.data.suspicious_patterns.iter().cloned());
+            report
+                .persistent_tones
+                .extend(tile.data.persistent_tones.iter().map(tone_report));
+            for tone in tile.data.persistent_tone_frequencies() {
+                if !tones.contains(&tone) {
+                    tones.push(tone);
+                }
+            }
+            report.tiles.push(SpectrogramTileReport {
+                start_seconds: tile.start_seconds,
+                end_seconds: tile.end_seconds,
+                high_frequency_energy: tile.data.high_frequency_energy,
+                hidden_message_detected: detected,
+                suspicious_patterns: tile.data.suspicious_patterns.clone(),
+                output_file: None,
+            });
+        }
+        return Ok((report, tones));
+    }
+
+    let spec_data = SpectrogramAnalyzer::analyze((samples, sample_rate))
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+    let tones = spec_data.persistent_tone_frequencies();
+
+    Ok((
+        SpectrogramReport {
+            high_frequency_energy: spec_data.high_frequency_energy,
+            hidden_message_detected: spec_data.has_hidden_message_at(threshold),
+            suspicious_patterns: spec_data.suspicious_patterns,
+            output_file: None,
+            tiles: Vec::new(),
+            persistent_tones: spec_data.persistent_tones.iter().map(tone_report).collect(),
+        },
+        tones,
+    ))
+}
+
+fn tone_report(tone: &PersistentTone) -> AudioToneReport {
+    AudioToneReport {
+        frequency_hz: tone.frequency_hz,
+        start_seconds: tone.range.start_seconds,
+        end_seconds: tone.range.end_seconds,
+    }
+}
+
+fn ultrasonic_report(samples: Vec<f32>, sample_rate: u32) -> Result<UltrasonicReport, ApiError> {
+    let ultrasonic = UltrasonicAnalyzer::analyze((samples, sample_rate, ULTRASONIC_CUTOFF_HZ))
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(UltrasonicReport {
+        band_low_hz: ultrasonic.band_low_hz,
+        band_high_hz: ultrasonic.band_high_hz,
+        band_energy_ratio: ultrasonic.band_energy_ratio,
+        baseband_rate: ultrasonic.baseband_rate,
+        baseband_offset_hz: ultrasonic.baseband_offset_hz,
+        baseband_file: None,
+        tones: ultrasonic
+            .tones
+            .iter()
+            .map(|t| UltrasonicToneReport {
+                frequency_hz: t.frequency_hz,
+                frame_share: t.frame_share,
+                start_seconds: t.range.start_seconds,
+                end_seconds: t.range.end_seconds,
+            })
+            .collect(),
+        fsk: ultrasonic.fsk.as_ref().map(fsk_report),
+        is_suspicious: ultrasonic.suspicious,
+    })
+}
+
+fn phase_report(samples: Vec<f32>) -> Result<PhaseReport, ApiError> {
+    let phase =
+        PhaseAnalyzer::analyze(samples).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(PhaseReport {
+        segment_length: phase.segment_length,
+        boundary_jump_ratio: phase.boundary_jump_ratio,
+        quantized_run: phase.quantized_run,
+        quantized_first_bin: phase.quantized_first_bin,
+        quantized_share: phase.quantized_share,
+        decoded_hex: phase.decoded.iter().map(|b| format!("{:02x}", b)).collect(),
+        decoded_text: phase.decoded_text(),
+        is_suspicious: phase.suspicious,
+    })
+}
+
+fn demodulation_report(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    carriers: Vec<f32>,
+) -> Result<DemodulationReport, ApiError> {
+    let demodulated = Demodulator::analyze((samples, sample_rate, carriers))
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(DemodulationReport {
+        carriers: demodulated
+            .iter()
+            .map(|carrier| CarrierReport {
+                carrier_hz: carrier.carrier_hz,
+                fsk: carrier.fsk.as_ref().map(fsk_report),
+                psk: carrier.psk.as_ref().map(|psk| PskReport {
+                    baud: psk.baud,
+                    constellation: psk.constellation,
+                    bit_count: psk.bits.len(),
+                    bitstream: bitstream(&psk.bits),
+                    decoded_hex: psk.decoded.iter().map(|b| format!("{:02x}", b)).collect(),
+                    decoded_text: psk.decoded_text(),
+                }),
+            })
+            .collect(),
+    })
+}
+
+fn fsk_report(fsk: &FskSignal) -> FskReport {
+    FskReport {
+        mark_hz: fsk.mark_hz,
+        space_hz: fsk.space_hz,
+        baud: fsk.baud,
+        bit_count: fsk.bits.len(),
+        bitstream: bitstream(&fsk.bits),
+        decoded_hex: fsk.decoded.iter().map(|b| format!("{:02x}", b)).collect(),
+        decoded_text: fsk.decoded_text(),
+    }
+}
+
+// The bits that went into the decoded bytes, as 0s and 1s
+fn bitstream(bits: &[bool]) -> String {
+    bits.iter()
+        .take(MAX_DECODED_BYTES * 8)
+        .map(|&bit| if bit { '1' } else { '0' })
+        .collect()
+}
+
+fn video_analysis(
+    file_path: &Path,
+    video: VideoOptions,
+    events: EventSink<'_>,
+) -> Result<VideoAnalysis, ApiError> {
+    let mut frame_iter = VideoParser::parse_with_options(
+        &file_path,
+        VideoDecodeOptions {
+            keyframes_only: video.keyframes_only,
+            hwaccel: video.hwaccel,
+        },
+    )
+    .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+    let mut sampler = FrameSampler::new(video.sampling);
+    let mut scenes = SceneDetector::new(video.scene_threshold, video.max_frames_per_scene);
+
+    let mut frame_count = 0;
+    let mut error_count = 0;
+    let mut suspicious_frames = Vec::new();
+
+    // Frames come back to the decoder once analyzed, or straight away when not sampled
+    let buffers = frame_iter.buffers();
+    let mut pipeline = FramePipeline::new()
+        .with_recycler({
+            let buffers = buffers.clone();
+            Arc::new(move |frame| buffers.recycle(frame))
+        })
+        .with_cpu_clock(usage::thread_cpu_time);
+    let mut idx = 0;
+    loop {
+        let frame_result = frame_iter.next();
+        let done = frame_result.is_none();
+        match frame_result {
+            Some(Ok(frame)) => {
+                frame_count += 1;
+
+                scenes.observe(idx, &frame);
+                if sampler.sample(idx, frame_iter.last_timestamp(), &frame) && scenes.admit() {
+                    pipeline.submit(idx, frame);
+                } else {
+                    buffers.recycle(frame);
+                }
+            }
+            Some(Err(_)) => {
+                error_count += 1;
+            }
+            None => {}
+        }
+
+        for (frame_idx, result) in pipeline.ready(done) {
+            if let Ok(analysis) = result {
+                scenes.record(&analysis);
+                let suspicious = analysis.lsb_suspicious || analysis.histogram_anomalies;
+                if suspicious {
+                    suspicious_frames.push(frame_idx);
+                }
+                events(ScanEvent::new(
+                    "frame",
+                    &serde_json::json!({ "index": frame_idx, "suspicious": suspicious }),
+                ));
+            }
+        }
+
+        if done {
+            break;
+        }
+        idx += 1;
+    }
+    // Frame analyses ran on the rayon pool, out of sight of the scan's CPU meter
+    usage::add_offloaded_cpu(pipeline.cpu_time());
+
+    Ok(VideoAnalysis {
+        frames_processed: frame_count,
+        errors_encountered: error_count,
+        suspicious_frames,
+        sampling: video.sampling.to_string(),
+        scenes: scenes.finish().iter().map(scene_report).collect(),
+        mp4_tags: None,
+    })
+}
+
+fn scene_report(scene: &Scene) -> SceneReport {
+    SceneReport {
+        start_frame: scene.start_frame,
+        end_frame: scene.end_frame,
+        frames_analyzed: scene.frames_analyzed,
+        frames_skipped: scene.frames_skipped,
+        median_chi_square: scene.median_chi_square,
+        lsb_divergence: scene.lsb_divergence,
+        divergent_frames: scene.divergent_frames.clone(),
+        suspicious: scene.suspicious,
+    }
+}
+
+fn bpcs_report(image: image::DynamicImage) -> Result<BpcsReport, ApiError> {
+    let bpcs = BpcsAnalyzer::analyze(image).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(BpcsReport {
+        is_suspicious: bpcs.suspicious,
+        block_size: bpcs.block_size,
+        noise_planes: bpcs.noise_planes,
+        planes: bpcs
+            .planes
+            .into_iter()
+            .map(|plane| BpcsPlaneReport {
+                channel: plane.channel,
+                plane: plane.plane,
+                complex_blocks: plane.complex_blocks,
+                simple_ratio: plane.simple_ratio,
+                mean_complexity: plane.mean_complexity,
+                transition_ratio: plane.transition_ratio,
+                noise_like: plane.noise_like,
+            })
+            .collect(),
+        flagged_blocks: bpcs.flagged_regions.len(),
+        overlay: None,
+    })
+}
+
+fn calibration_report(file_path: &Path) -> Result<CalibrationReport, ApiError> {
+    let data = read_whole(file_path)?;
+    let calibration =
+        CalibrationAnalyzer::analyze(data).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(CalibrationReport {
+        is_suspicious: calibration.suspicious,
+        blocks: calibration.blocks,
+        estimated_change_rate: calibration.estimated_change_rate,
+        divergence: calibration.divergence,
+        modes: calibration
+            .modes
+            .into_iter()
+            .map(|mode| CalibrationModeReport {
+                row: mode.mode.0,
+                column: mode.mode.1,
+                observed: mode.observed,
+                reference: mode.reference,
+                divergence: mode.divergence,
+                change_rate: mode.change_rate,
+            })
+            .collect(),
+    })
+}
+
+fn webp_report(file_path: &Path, events: EventSink<'_>) -> Result<WebpReport, ApiError> {
+    let data = read_whole(file_path)?;
+    let structure = WebpStructureAnalyzer::analyze(data)
+        .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
 
-    // Detect file type
-    let file_data = tokio::fs::read(file_path).await?;
-    let infer = Infer::new();
-    let file_type = if let Some(kind) = infer.get(&file_data) {
-        match kind.mime_type() {
-            mime if mime.starts_with("audio/") => FileType::Audio,
-            mime if mime.starts_with("video/") => FileType::Video,
-            mime if mime.starts_with("text/") || mime.starts_with("application/") => FileType::Text,
-            mime if mime.starts_with("image/") => FileType::Image,
-            _ => FileType::Text,
+    let mut frames = Vec::new();
+    let mut suspicious_frames = Vec::new();
+    let mut frame_count = 0;
+    if structure.animated {
+        let webp = WebpParser::parse_path(&file_path)
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        frame_count = webp.frames.len();
+
+        for (idx, frame) in webp.frames.into_iter().enumerate() {
+            if let Ok(analysis) = VideoFrameAnalyzer::analyze(frame.image) {
+                let suspicious = analysis.lsb_suspicious || analysis.histogram_anomalies;
+                if suspicious {
+                    suspicious_frames.push(idx);
+                }
+                events(ScanEvent::new(
+                    "frame",
+                    &serde_json::json!({ "index": idx, "suspicious": suspicious }),
+                ));
+                frames.push(WebpFrameReport {
+                    index: idx,
+                    delay_ms: frame.delay_ms,
+                    lsb_suspicious: analysis.lsb_suspicious,
+                    histogram_anomalies: analysis.histogram_anomalies,
+                    chi_square_scores: analysis.chi_square_scores,
+                    entropy_scores: analysis.entropy_scores,
+                });
+            }
         }
-    } else {
-        FileType::Text
-    };
+    }
 
-    let detected_type = match file_type {
-        FileType::Audio => "Audio",
-        FileType::Video => "Video",
-        FileType::Text => "Text",
-        FileType::Image => "Image",
-    };
+    Ok(WebpReport {
+        animated: structure.animated,
+        riff_size: structure.riff_size,
+        chunks: structure
+            .chunks
+            .iter()
+            .map(|chunk| WebpChunkReport {
+                fourcc: chunk.fourcc.clone(),
+                offset: chunk.offset,
+                size: chunk.size,
+            })
+            .collect(),
+        trailing_bytes: structure.trailing_bytes,
+        structure_anomalies: structure.anomalies,
+        frame_count,
+        frames,
+        suspicious_frames,
+    })
+}
 
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_string());
+fn gif_report(file_path: &Path) -> Result<GifReport, ApiError> {
+    let data = read_whole(file_path)?;
+    let gif =
+        GifExtensionAnalyzer::analyze(data).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
 
-    let mut response = AnalysisResponse {
-        file_info: FileInfo {
-            path: file_path.to_string_lossy().to_string(),
-            size_bytes: file_size,
-            detected_type: detected_type.to_string(),
-            extension,
-        },
-        magic_bytes_analysis: None,
-        format_specific_analysis: FormatSpecificAnalysis::Unknown,
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        summary: AnalysisSummary {
-            steganography_detected: false,
-            confidence_level: "low".to_string(),
-            threat_indicators: Vec::new(),
-            recommendations: Vec::new(),
-        },
+    Ok(GifReport {
+        version: gif.version,
+        frame_count: gif.frame_count,
+        comments: gif
+            .comments
+            .into_iter()
+            .map(|comment| GifCommentReport {
+                offset: comment.offset,
+                size: comment.size,
+                preview: comment.preview,
+                base64: comment.base64,
+                decoded_size: comment.decoded.len(),
+                classification: comment.classification,
+            })
+            .collect(),
+        applications: gif
+            .applications
+            .into_iter()
+            .map(|app| GifApplicationReport {
+                offset: app.offset,
+                identifier: app.identifier,
+                size: app.size,
+                known: app.known,
+                classification: app.classification,
+            })
+            .collect(),
+        plain_text_blocks: gif.plain_text_blocks,
+        trailing_bytes: gif.trailing_bytes,
+        structure_anomalies: gif.anomalies,
+    })
+}
+
+fn png_text_report(file_path: &Path) -> Result<PngTextReport, ApiError> {
+    let data = read_whole(file_path)?;
+    let png_text =
+        PngTextAnalyzer::analyze(data).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+
+    Ok(PngTextReport {
+        chunks: png_text
+            .chunks
+            .into_iter()
+            .map(|chunk| PngTextChunkReport {
+                offset: chunk.offset,
+                chunk_type: chunk.chunk_type,
+                keyword: chunk.keyword,
+                keyword_kind: match chunk.keyword_kind {
+                    KeywordKind::Standard => "standard",
+                    KeywordKind::WellKnown => "well_known",
+                    KeywordKind::Unknown => "unknown",
+                    KeywordKind::Invalid => "invalid",
+                }
+                .to_string(),
+                language: chunk.language,
+                compressed: chunk.compressed,
+                size: chunk.size,
+                preview: chunk.preview,
+                encoding: chunk.encoding,
+                decoded_size: chunk.decoded.len(),
+                classification: chunk.classification,
+            })
+            .collect(),
+        duplicate_keywords: png_text.duplicate_keywords,
+        anomalies: png_text.anomalies,
+    })
+}
+
+// None for formats that can't hold several images and for files holding a single,
+// displayed image
+fn pages_report(file_path: &Path) -> Result<Option<PagesReport>, ApiError> {
+    let multi = match MultiImageParser::parse_path(&file_path) {
+        Ok(multi) => multi,
+        Err(MultiImageParserError::Unsupported) => return Ok(None),
+        Err(e) => return Err(ApiError::AnalysisFailed(e.to_string())),
     };
+    if multi.pages.len() < 2 && multi.pages.iter().all(|page| !page.hidden) {
+        return Ok(None);
+    }
 
-    // Magic bytes analysis
-    if let Ok(magic_analysis) = MagicBytesAnalyzerWithPath::new(file_path).analyze() {
-        response.magic_bytes_analysis = Some(MagicBytesReport {
-            primary_format: magic_analysis.primary_format,
-            expected_format: magic_analysis.expected_format,
-            total_signatures_found: magic_analysis.total_signatures_found,
-            has_multiple_formats: magic_analysis.has_multiple_formats,
-            has_suspicious_data: magic_analysis.has_suspicious_data,
-            format_summary: FormatSummary {
-                images: magic_analysis.format_summary.image_files,
-                audio: magic_analysis.format_summary.audio_files,
-                video: magic_analysis.format_summary.video_files,
-                text_documents: magic_analysis.format_summary.text_files,
-                archives: magic_analysis.format_summary.archive_files,
-                executables: magic_analysis.format_summary.executable_files,
-                other: magic_analysis.format_summary.other_files,
-            },
-            embedded_files: magic_analysis
-                .embedded_files
-                .iter()
-                .map(|f| EmbeddedFileInfo {
-                    offset: f.offset,
-                    offset_hex: format!("0x{:X}", f.offset),
-                    description: f.description.clone(),
-                    file_type: f.file_type.clone(),
-                    confidence: f.confidence.clone(),
-                })
-                .collect(),
-            suspicious_findings: magic_analysis.suspicious_findings,
+    let mut pages = Vec::new();
+    let mut decoded = Vec::new();
+    for page in multi.pages {
+        let (error, size) = match page.image {
+            Ok(image) => {
+                let size = (image.width(), image.height());
+                decoded.push((pages.len(), image));
+                (None, Some(size))
+            }
+            Err(e) => (Some(e), None),
+        };
+        pages.push(PageReport {
+            index: page.index,
+            label: page.label,
+            hidden: page.hidden,
+            error,
+            width: size.map(|(w, _)| w),
+            height: size.map(|(_, h)| h),
+            lsb_suspicious: false,
+            histogram_anomalies: false,
+            mean_lsb_entropy: None,
+            chi_square_per_pair: None,
+            edge_density: None,
+            outlier_score: None,
+            outlier: false,
         });
     }
 
-    // Format-specific analysis
-    match file_type {
-        FileType::Image => {
-            if let Ok(image) = ImageParser::parse_path(&file_path) {
-                let dimensions = ImageDimensions {
-                    width: image.width(),
-                    height: image.height(),
-                };
-
-                let mut image_analysis = ImageAnalysis {
-                    exif_metadata: None,
-                    lsb_analysis: None,
-                    dimensions,
-                };
+    let (positions, images): (Vec<usize>, Vec<_>) = decoded.into_iter().unzip();
+    if !images.is_empty() {
+        let analysis =
+            PageAnalyzer::analyze(images).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        for (stats, &position) in analysis.pages.iter().zip(&positions) {
+            let page = &mut pages[position];
+            page.lsb_suspicious = stats.frame.lsb_suspicious;
+            page.histogram_anomalies = stats.frame.histogram_anomalies;
+            page.mean_lsb_entropy = Some(stats.mean_lsb_entropy);
+            page.chi_square_per_pair = Some(stats.chi_square_per_pair);
+            page.edge_density = Some(stats.frame.edge_density);
+            page.outlier_score = stats.outlier_score;
+            page.outlier = stats.outlier;
+        }
+    }
 
-                // EXIF
-                if let Ok(exif_data) = ExifAnalyzerWithPath::new(file_path).analyze() {
-                    image_analysis.exif_metadata = Some(ExifReport {
-                        fields_found: exif_data.metadata.len(),
-                        has_thumbnail: exif_data.has_thumbnail,
-                        thumbnail_size_bytes: exif_data.thumbnail_size,
-                        comment_fields: exif_data.comment_fields,
-                        suspicious_fields: exif_data.suspicious_fields,
-                        metadata: exif_data
-                            .metadata
-                            .iter()
-                            .map(|(k, v)| MetadataField {
-                                key: k.clone(),
-                                value: v.clone(),
-                            })
-                            .collect(),
-                    });
-                }
+    Ok(Some(PagesReport {
+        format: multi.format.to_string(),
+        page_count: pages.len(),
+        outlier_pages: pages
+            .iter()
+            .filter(|p| p.outlier)
+            .map(|p| p.index)
+            .collect(),
+        hidden_pages: pages.iter().filter(|p| p.hidden).map(|p| p.index).collect(),
+        pages,
+    }))
+}
 
-                // LSB
-                if let Ok(lsb_analysis) = LsbAnalyzer::analyze(image) {
-                    let channels = lsb_analysis
-                        .chi_square_scores
-                        .iter()
-                        .enumerate()
-                        .map(|(i, score)| {
-                            let channel = match i {
-                                0 => "Red",
-                                1 => "Green",
-                                2 => "Blue",
-                                _ => "Unknown",
-                            };
-                            LsbChannelAnalysis {
-                                channel_name: channel.to_string(),
-                                chi_square_score: *score,
-                                entropy_score: lsb_analysis.entropy_scores[i],
-                            }
-                        })
-                        .collect();
+// Macros and embedded objects of an Office document, the extraneous members of an ebook, the
+// hidden regions of a 3D model and decoded values of a JSON, XML or YAML document or of source
+// code comments are returned as carved payloads
+fn text_analysis(
+    file_path: &Path,
+    env: &RunEnvironment,
+) -> Result<(TextAnalysis, Vec<CarvedPayload>), ApiError> {
+    let data = read_whole(file_path)?;
+    let text_content =
+        TextParser::parse_path(&file_path).map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
 
-                    image_analysis.lsb_analysis = Some(LsbReport {
-                        is_suspicious: lsb_analysis.suspicious,
-                        channels,
-                    });
-                }
+    let mut payloads = Vec::new();
+    let ole = if is_office_document(&data) {
+        match OfficeAnalyzer::analyze(data.clone())
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))
+            .and_then(|analysis| ole_report(analysis, &mut payloads, env))
+        {
+            Ok(report) => Some(report),
+            Err(e) => {
+                tracing::warn!("Office document analysis failed: {}", e);
+                payloads.clear();
+                None
+            }
+        }
+    } else {
+        None
+    };
 
-                response.format_specific_analysis = FormatSpecificAnalysis::Image(image_analysis);
+    let ebook = if is_ebook(&data) {
+        match EbookAnalyzer::analyze(data.clone()) {
+            Ok(analysis) => Some(ebook_report(analysis, &mut payloads)),
+            Err(e) => {
+                tracing::warn!("Ebook analysis failed: {}", e);
+                None
             }
         }
-        FileType::Audio => {
-            if let Ok(samples) = AudioParser::parse_path(&file_path) {
-                let mut audio_analysis = AudioAnalysis {
-                    sample_count: samples.len(),
-                    id3_analysis: None,
-                    spectrogram_analysis: None,
-                };
+    } else {
+        None
+    };
 
-                // ID3
-                if let Ok(id3_data) = Id3AnalyzerWithPath::new(file_path).analyze() {
-                    audio_analysis.id3_analysis = Some(Id3Report {
-                        title: id3_data.title,
-                        artist: id3_data.artist,
-                        album: id3_data.album,
-                        year: id3_data.year,
-                        comments_count: id3_data.comments.len(),
-                        pictures_count: id3_data.pictures.len(),
-                        private_frames_count: id3_data.private_frames.len(),
-                        suspicious_frames: id3_data.suspicious_frames,
-                    });
-                }
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let model = match model_format(&data, extension) {
+        Some(format) => match ModelAnalyzer::analyze((data, format)) {
+            Ok(analysis) => Some(model_report(analysis, &mut payloads)),
+            Err(e) => {
+                tracing::warn!("Model analysis failed: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
 
-                // Spectrogram
-                if let Ok(spec_data) = SpectrogramAnalyzer::analyze(samples) {
-                    audio_analysis.spectrogram_analysis = Some(SpectrogramReport {
-                        high_frequency_energy: spec_data.high_frequency_energy,
-                        hidden_message_detected: spec_data.has_hidden_message,
-                        suspicious_patterns: spec_data.suspicious_patterns,
-                    });
+    let structured = match structured_format(&text_content.content, extension) {
+        Some(format) => {
+            match StructuredTextAnalyzer::analyze((text_content.content.clone(), format)) {
+                Ok(analysis) => Some(structured_report(analysis, &mut payloads)),
+                Err(e) => {
+                    tracing::warn!("{} structure analysis failed: {}", format, e);
+                    None
                 }
-
-                response.format_specific_analysis = FormatSpecificAnalysis::Audio(audio_analysis);
             }
         }
-        FileType::Video => {
-            if let Ok(frame_iter) = VideoParser::parse_path(&file_path) {
-                let mut frame_count = 0;
-                let mut error_count = 0;
-                let mut suspicious_frames = Vec::new();
-
-                for (idx, frame_result) in frame_iter.enumerate() {
-                    match frame_result {
-                        Ok(frame) => {
-                            frame_count += 1;
-
-                            if idx % video_sample_rate == 0 {
-                                let dynamic_image = image::DynamicImage::ImageRgba8(frame);
-                                if let Ok(analysis) = VideoFrameAnalyzer::analyze(dynamic_image) {
-                                    if analysis.lsb_suspicious || analysis.histogram_anomalies {
-                                        suspicious_frames.push(idx);
-                                    }
-                                }
-                            }
-                        }
-                        Err(_) => {
-                            error_count += 1;
-                        }
-                    }
-                }
+        None => None,
+    };
 
-                response.format_specific_analysis = FormatSpecificAnalysis::Video(VideoAnalysis {
-                    frames_processed: frame_count,
-                    errors_encountered: error_count,
-                    suspicious_frames,
-                });
+    let source_code = match language_for(extension) {
+        Some(language) => {
+            match SourceCodeAnalyzer::analyze((text_content.content.clone(), language)) {
+                Ok(analysis) => Some(source_code_report(analysis, &mut payloads)),
+                Err(e) => {
+                    tracing::warn!("{} comment analysis failed: {}", language.name, e);
+                    None
+                }
             }
         }
-        FileType::Text => {
-            if let Ok(text_content) = TextParser::parse_path(&file_path) {
-                response.format_specific_analysis = FormatSpecificAnalysis::Text(TextAnalysis {
-                    file_type: text_content.file_type,
-                    line_count: text_content.line_count,
-                    word_count: text_content.word_count,
-                    character_count: text_content.char_count,
-                    size_bytes: text_content.byte_size,
+        None => None,
+    };
+
+    Ok((
+        TextAnalysis {
+            file_type: text_content.file_type,
+            line_count: text_content.line_count,
+            word_count: text_content.word_count,
+            character_count: text_content.char_count,
+            size_bytes: text_content.byte_size,
+            ole,
+            ebook,
+            model,
+            structured,
+            source_code,
+        },
+        payloads,
+    ))
+}
+
+// Members the package doesn't list and decoded zero-width payloads are returned as carved
+// payloads
+fn ebook_report(analysis: EbookAnalysis, payloads: &mut Vec<CarvedPayload>) -> EbookReport {
+    let chapters = analysis
+        .chapters
+        .iter()
+        .map(|chapter| {
+            let payload = chapter.text.zero_width_payload.as_ref();
+            if let Some(payload) = payload {
+                payloads.push(CarvedPayload {
+                    offset: 0,
+                    description: format!("Zero-width characters in {}", chapter.path),
+                    file_type: payload.classification.clone(),
+                    data: payload.data.clone(),
                 });
             }
+            EbookChapterReport {
+                path: chapter.path.clone(),
+                is_suspicious: chapter.suspicious,
+                zero_width_chars: chapter.text.zero_width_chars,
+                zero_width_text: payload.and_then(|p| p.text()),
+                zero_width_payload: payload
+                    .filter(|p| p.text().is_none())
+                    .map(|p| p.classification.clone()),
+                whitespace_lines: chapter.text.whitespace_lines,
+                homoglyph_words: chapter.text.homoglyph_words.clone(),
+                hidden_elements: chapter.hidden_elements,
+            }
+        })
+        .collect();
+
+    let mut members = Vec::new();
+    for member in analysis.members {
+        members.push(EbookMemberReport {
+            path: member.path.clone(),
+            size_bytes: member.size,
+            compressed: member.compressed,
+            declared: member.declared,
+            media_type: member.media_type,
+            classification: member.classification.clone(),
+            executable: member.executable,
+            file: None,
+            sha256: None,
+        });
+        // Member contents have no single file offset; 0 stands in for it
+        if let (Some(data), Some(classification)) = (member.data, member.classification) {
+            payloads.push(CarvedPayload {
+                offset: 0,
+                description: format!("Ebook member {}", member.path),
+                file_type: classification,
+                data,
+            });
         }
     }
 
-    // Finalize summary
-    finalize_summary(&mut response);
+    EbookReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        members,
+        anomalies: analysis.anomalies,
+        chapters,
+        unanalyzed_chapters: analysis.unanalyzed_chapters,
+    }
+}
+
+fn structured_report(
+    analysis: StructuredTextAnalysis,
+    payloads: &mut Vec<CarvedPayload>,
+) -> StructuredTextReport {
+    let mut findings = Vec::new();
+    for finding in analysis.findings {
+        findings.push(StructuredFindingReport {
+            kind: finding.kind.to_string(),
+            location: finding.location.clone(),
+            length: finding.length,
+            classification: finding.classification.clone(),
+            preview: finding.preview,
+            file: None,
+            sha256: None,
+        });
+        // Decoded values have no file offset; 0 stands in for it
+        if let (Some(data), Some(classification)) = (finding.decoded, finding.classification) {
+            payloads.push(CarvedPayload {
+                offset: 0,
+                description: format!(
+                    "{} {} at {}",
+                    analysis.format, finding.kind, finding.location
+                ),
+                file_type: classification,
+                data,
+            });
+        }
+    }
+
+    StructuredTextReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        comments: analysis.comments,
+        values: analysis.values,
+        findings,
+    }
+}
+
+fn source_code_report(
+    analysis: SourceCodeAnalysis,
+    payloads: &mut Vec<CarvedPayload>,
+) -> SourceCodeReport {
+    let mut comments = Vec::new();
+    for payload in analysis.payloads {
+        comments.push(CommentPayloadReport {
+            line: payload.line,
+            end_line: payload.end_line,
+            length: payload.length,
+            classification: payload.classification.clone(),
+            preview: payload.preview,
+            file: None,
+            sha256: None,
+        });
+        // Decoded comments have no file offset; 0 stands in for it
+        payloads.push(CarvedPayload {
+            offset: 0,
+            description: format!(
+                "{} comment at lines {}-{}",
+                analysis.language, payload.line, payload.end_line
+            ),
+            file_type: payload.classification,
+            data: payload.data,
+        });
+    }
+
+    SourceCodeReport {
+        language: analysis.language.to_string(),
+        is_suspicious: analysis.suspicious,
+        comments: analysis.comments,
+        comment_chars: analysis.comment_chars,
+        code_chars: analysis.code_chars,
+        comment_ratio: analysis.comment_ratio,
+        abnormal_ratio: analysis.abnormal_ratio,
+        payloads: comments,
+    }
+}
+
+fn model_report(analysis: ModelAnalysis, payloads: &mut Vec<CarvedPayload>) -> ModelReport {
+    let mut regions = Vec::new();
+    for region in analysis.regions {
+        regions.push(ModelRegionReport {
+            location: region.location.clone(),
+            offset: region.offset,
+            size_bytes: region.data.len(),
+            classification: region.classification.clone(),
+            text: (region.classification == "text")
+                .then(|| String::from_utf8_lossy(&region.data).to_string()),
+            file: None,
+            sha256: None,
+        });
+        // Regions decoded from JSON or gathered from STL records have no file offset; 0 stands
+        // in for it
+        payloads.push(CarvedPayload {
+            offset: region.offset.unwrap_or(0) as usize,
+            description: format!("{} {}", analysis.format, region.location),
+            file_type: region.classification,
+            data: region.data,
+        });
+    }
+
+    ModelReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious,
+        triangles: analysis.triangles,
+        buffers: analysis.buffers,
+        anomalies: analysis.anomalies,
+        regions,
+        attributed_triangles: analysis.attributed_triangles,
+        external_buffers: analysis.external_buffers,
+    }
+}
+
+// Each embedded object goes through the magic bytes analyzer in turn, and objects that are
+// Office documents are reported with their own macros and objects
+fn ole_report(
+    analysis: OfficeAnalysis,
+    payloads: &mut Vec<CarvedPayload>,
+    env: &RunEnvironment,
+) -> Result<OleReport, ApiError> {
+    let macros = analysis
+        .macros
+        .iter()
+        .map(|m| OleMacroReport {
+            module: m.name.clone(),
+            stream: m.stream.clone(),
+            source_bytes: m.source.as_ref().map(|source| source.len()),
+            auto_exec: m.auto_exec.clone(),
+            file: None,
+            sha256: None,
+        })
+        .collect();
+    // Stream contents have no file offset; 0 stands in for it
+    for module in &analysis.macros {
+        if let Some(source) = &module.source {
+            payloads.push(CarvedPayload {
+                offset: 0,
+                description: format!("VBA module {} source", module.name),
+                file_type: "text".to_string(),
+                data: source.clone().into_bytes(),
+            });
+        }
+    }
+
+    let mut embedded_objects = Vec::new();
+    for office_object in analysis.embedded_objects {
+        let object = office_object.object;
+        let object_file =
+            UploadDir::from_env().persist(&object.data, &format!(".{}", object.extension()))?;
+        let magic_bytes_analysis = analyze_magic_bytes(object_file.path(), env)
+            .ok()
+            .map(|analysis| build_magic_bytes_report(&analysis));
+        let nested = match office_object.nested {
+            Some(nested) => Some(Box::new(ole_report(*nested, payloads, env)?)),
+            None => None,
+        };
+        embedded_objects.push(OleObjectReport {
+            stream: object.stream.clone(),
+            file_name: object.file_name.clone(),
+            size_bytes: object.data.len(),
+            classification: object.classification.clone(),
+            executable: object.is_executable(),
+            file: None,
+            sha256: None,
+            also_carved_from: Vec::new(),
+            magic_bytes_analysis,
+            nested,
+        });
+        payloads.push(CarvedPayload {
+            offset: 0,
+            description: match &object.file_name {
+                Some(name) => format!("OLE object {} packaged as {:?}", object.stream, name),
+                None => format!("OLE object {}", object.stream),
+            },
+            file_type: object.classification,
+            data: object.data,
+        });
+    }
 
-    Ok(response)
+    Ok(OleReport {
+        format: analysis.format.to_string(),
+        is_suspicious: analysis.suspicious
+            || embedded_objects.iter().any(|o| {
+                o.magic_bytes_analysis
+                    .as_ref()
+                    .is_some_and(|magic| magic.has_suspicious_data)
+                    || o.nested.as_ref().is_some_and(|nested| nested.is_suspicious)
+            }),
+        entries: analysis
+            .entries
+            .iter()
+            .map(|e| OleEntryReport {
+                path: e.path.clone(),
+                is_storage: e.is_storage,
+                size_bytes: e.size,
+                unusual: e.unusual.clone(),
+            })
+            .collect(),
+        macros,
+        embedded_objects,
+    })
 }
 
-fn finalize_summary(response: &mut AnalysisResponse) {
+fn finalize_summary(response: &mut AnalysisResponse, carved: &[CarvedPayload]) {
     let mut indicators = Vec::new();
+    // Count half towards the confidence level
+    let mut low_weight_indicators = 0;
     let mut steg_detected = false;
 
     // Check magic bytes
@@ -272,6 +2331,36 @@ fn finalize_summary(response: &mut AnalysisResponse) {
         indicators.extend(magic.suspicious_findings.clone());
     }
 
+    if let Some(ref slack) = response.slack_space {
+        if slack.is_suspicious {
+            steg_detected = true;
+            indicators.push(format!(
+                "{} bytes of {} slack space not accounted for by the container structure",
+                slack.slack_bytes, slack.container
+            ));
+        }
+    }
+
+    if let Some(ref deflate) = response.deflate_streams {
+        if deflate.is_suspicious {
+            steg_detected = true;
+            indicators.push(format!(
+                "{} compressed stream(s) found outside the container's own compressed data",
+                deflate.streams.len()
+            ));
+        }
+    }
+
+    for plugin in response.plugins.iter().filter(|p| p.is_suspicious) {
+        steg_detected = true;
+        if plugin.findings.is_empty() {
+            indicators.push(format!("Plugin {} flagged the file", plugin.name));
+        }
+        for finding in &plugin.findings {
+            indicators.push(format!("Plugin {}: {}", plugin.name, finding.description));
+        }
+    }
+
     // Check format-specific
     match &response.format_specific_analysis {
         FormatSpecificAnalysis::Image(img) => {
@@ -281,6 +2370,105 @@ fn finalize_summary(response: &mut AnalysisResponse) {
                     indicators.push("LSB analysis indicates hidden data".to_string());
                 }
             }
+            if let Some(ref ws) = img.ws_analysis {
+                if ws.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "Pair analysis estimates {:.1}% of LSBs were changed",
+                        ws.estimated_change_rate * 100.0
+                    ));
+                }
+            }
+            if let Some(ref correlation) = img.channel_correlation {
+                if correlation.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "Inter-channel LSB correlation collapsed in {} of {} smooth blocks",
+                        correlation.collapsed_block_count, correlation.blocks_evaluated
+                    ));
+                }
+            }
+            if let Some(ref webp) = img.webp_analysis {
+                if !webp.structure_anomalies.is_empty() {
+                    steg_detected = true;
+                    indicators.extend(webp.structure_anomalies.clone());
+                }
+                if !webp.suspicious_frames.is_empty() {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "{} of {} WebP frames show LSB or histogram anomalies",
+                        webp.suspicious_frames.len(),
+                        webp.frame_count
+                    ));
+                }
+            }
+            if let Some(ref bpcs) = img.bpcs {
+                if bpcs.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "{} bit planes have complex blocks that look like random data (BPCS embedding)",
+                        bpcs.noise_planes
+                    ));
+                }
+            }
+            if let Some(ref calibration) = img.calibration {
+                if calibration.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "About {:.0}% of the JPEG's DCT coefficients differ from a calibrated reference (F5-style embedding)",
+                        calibration.estimated_change_rate * 100.0
+                    ));
+                }
+            }
+            if let Some(ref gif) = img.gif_analysis {
+                if !gif.structure_anomalies.is_empty() {
+                    steg_detected = true;
+                    indicators.extend(gif.structure_anomalies.clone());
+                }
+            }
+            if let Some(ref png_text) = img.png_text {
+                if !png_text.anomalies.is_empty() {
+                    steg_detected = true;
+                    indicators.extend(png_text.anomalies.clone());
+                }
+            }
+            if let Some(ref adaptive) = img.adaptive_lsb {
+                if adaptive.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(
+                        "Textured regions show random LSBs while smooth regions do not".to_string(),
+                    );
+                }
+            }
+            if let Some(ref pages) = img.page_analysis {
+                if !pages.outlier_pages.is_empty() {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "{} of {} {} pages differ statistically from the rest: {:?}",
+                        pages.outlier_pages.len(),
+                        pages.page_count,
+                        pages.format,
+                        pages.outlier_pages
+                    ));
+                }
+                if !pages.hidden_pages.is_empty() {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "{} {} image items are stored but never displayed: {:?}",
+                        pages.hidden_pages.len(),
+                        pages.format,
+                        pages.hidden_pages
+                    ));
+                }
+            }
+            if let Some(ref missing) = img.missing_metadata {
+                low_weight_indicators += 1;
+                indicators.push(format!(
+                    "Looks like a {} but has no {} metadata (possibly stripped)",
+                    missing.expected_source,
+                    missing.missing_fields.join(", ")
+                ));
+            }
         }
         FormatSpecificAnalysis::Audio(audio) => {
             if let Some(ref spec) = audio.spectrogram_analysis {
@@ -289,6 +2477,81 @@ fn finalize_summary(response: &mut AnalysisResponse) {
                     indicators.push("Spectrogram analysis detected patterns".to_string());
                 }
             }
+            if let Some(ref ultrasonic) = audio.ultrasonic_analysis {
+                if let Some(ref fsk) = ultrasonic.fsk {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "Ultrasonic FSK signal at {:.0}/{:.0} Hz carrying {} bits",
+                        fsk.mark_hz, fsk.space_hz, fsk.bit_count
+                    ));
+                } else if ultrasonic.is_suspicious {
+                    indicators.push(format!(
+                        "{:.1}% of the audio energy lies above {:.0} Hz",
+                        ultrasonic.band_energy_ratio * 100.0,
+                        ultrasonic.band_low_hz
+                    ));
+                }
+            }
+            if let Some(ref phase) = audio.phase_analysis {
+                if phase.is_suspicious {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "Phase coding signs in {}-sample segments: {} quantized bins, boundary jumps {:.1}x",
+                        phase.segment_length, phase.quantized_run, phase.boundary_jump_ratio
+                    ));
+                }
+            }
+            if let Some(ref demodulation) = audio.demodulation {
+                for carrier in &demodulation.carriers {
+                    let keyed = [
+                        carrier.fsk.as_ref().map(|fsk| ("FSK", fsk.bit_count)),
+                        carrier.psk.as_ref().map(|psk| ("PSK", psk.bit_count)),
+                    ];
+                    for (scheme, bit_count) in keyed.into_iter().flatten() {
+                        steg_detected = true;
+                        indicators.push(format!(
+                            "{} data demodulated from the {:.0} Hz tone: {} bits",
+                            scheme, carrier.carrier_hz, bit_count
+                        ));
+                    }
+                }
+            }
+            if let Some(ref id3) = audio.id3_analysis {
+                let flagged = id3.pictures.iter().filter(|p| p.is_suspicious).count();
+                if flagged > 0 {
+                    steg_detected = true;
+                    indicators.push(format!(
+                        "{} embedded ID3 picture(s) show signs of hidden data",
+                        flagged
+                    ));
+                }
+                if !id3.tag_gaps.is_empty() {
+                    steg_detected = true;
+                    let bytes: usize = id3.tag_gaps.iter().map(|g| g.size_bytes).sum();
+                    indicators.push(format!(
+                        "{} bytes hidden between the ID3v2 tag(s) and the audio",
+                        bytes
+                    ));
+                }
+            }
+            if let Some(trailing) = audio
+                .trailing_tags
+                .as_ref()
+                .filter(|trailing| trailing.is_suspicious)
+            {
+                steg_detected = true;
+                indicators.push(format!(
+                    "Flagged APEv2/Lyrics3 tag content: {}",
+                    trailing.flagged().join(", ")
+                ));
+            }
+            if let Some(mp4_tags) = audio.mp4_tags.as_ref().filter(|tags| tags.is_suspicious) {
+                steg_detected = true;
+                indicators.push(format!(
+                    "Flagged MP4 metadata (ilst) items: {}",
+                    mp4_tags.flagged().join(", ")
+                ));
+            }
         }
         FormatSpecificAnalysis::Video(video) => {
             if !video.suspicious_frames.is_empty() {
@@ -298,13 +2561,187 @@ fn finalize_summary(response: &mut AnalysisResponse) {
                     video.suspicious_frames.len()
                 ));
             }
+            for scene in video.scenes.iter().filter(|s| s.suspicious) {
+                steg_detected = true;
+                indicators.push(format!(
+                    "Frames {:?} of the scene at frames {}-{} have LSB statistics {:.1}x off the rest of the scene",
+                    scene.divergent_frames, scene.start_frame, scene.end_frame, scene.lsb_divergence
+                ));
+            }
+            if let Some(mp4_tags) = video.mp4_tags.as_ref().filter(|tags| tags.is_suspicious) {
+                steg_detected = true;
+                indicators.push(format!(
+                    "Flagged MP4 metadata (ilst) items: {}",
+                    mp4_tags.flagged().join(", ")
+                ));
+            }
+        }
+        FormatSpecificAnalysis::Text(text) => {
+            if let Some(ole) = text.ole.as_ref().filter(|ole| ole.is_suspicious) {
+                steg_detected = true;
+                let unusual: Vec<&str> = ole
+                    .entries
+                    .iter()
+                    .filter(|e| e.unusual.is_some())
+                    .map(|e| e.path.as_str())
+                    .collect();
+                if !unusual.is_empty() {
+                    indicators.push(format!(
+                        "{} OLE2 storage(s) or stream(s) a document wouldn't have: {}",
+                        unusual.len(),
+                        unusual.join(", ")
+                    ));
+                }
+                let macros = ole.all_macros();
+                if !macros.is_empty() {
+                    indicators.push(format!("VBA macros in {} module(s)", macros.len()));
+                }
+                let auto_exec: Vec<&str> = macros
+                    .iter()
+                    .flat_map(|module| module.auto_exec.iter().map(String::as_str))
+                    .collect();
+                if !auto_exec.is_empty() {
+                    indicators.push(format!(
+                        "Macros that run when the document is opened or closed: {}",
+                        auto_exec.join(", ")
+                    ));
+                }
+                let executables: Vec<&str> = ole
+                    .all_objects()
+                    .into_iter()
+                    .filter(|o| o.executable)
+                    .map(|o| o.file_name.as_deref().unwrap_or(&o.stream))
+                    .collect();
+                if !executables.is_empty() {
+                    indicators.push(format!(
+                        "{} embedded object(s) are executables: {}",
+                        executables.len(),
+                        executables.join(", ")
+                    ));
+                }
+                let flagged = ole
+                    .all_objects()
+                    .into_iter()
+                    .filter(|o| {
+                        o.magic_bytes_analysis
+                            .as_ref()
+                            .is_some_and(|magic| magic.has_suspicious_data)
+                    })
+                    .count();
+                if flagged > 0 {
+                    indicators.push(format!(
+                        "Suspicious data inside {} embedded OLE object(s)",
+                        flagged
+                    ));
+                }
+            }
+            if let Some(ebook) = text.ebook.as_ref().filter(|ebook| ebook.is_suspicious) {
+                steg_detected = true;
+                let extraneous: Vec<&str> = ebook.extraneous().map(|m| m.path.as_str()).collect();
+                if !extraneous.is_empty() {
+                    indicators.push(format!(
+                        "{} ebook member(s) the package doesn't list: {}",
+                        extraneous.len(),
+                        extraneous.join(", ")
+                    ));
+                }
+                for anomaly in &ebook.anomalies {
+                    indicators.push(format!("Ebook packaging: {}", anomaly));
+                }
+                let chapters = ebook.chapters.iter().filter(|c| c.is_suspicious).count();
+                if chapters > 0 {
+                    indicators.push(format!(
+                        "Text steganography traces in {} chapter(s)",
+                        chapters
+                    ));
+                }
+            }
+            if let Some(structured) = text
+                .structured
+                .as_ref()
+                .filter(|structured| structured.is_suspicious)
+            {
+                steg_detected = true;
+                let places: Vec<String> = structured
+                    .findings
+                    .iter()
+                    .map(|f| format!("{} at {}", f.kind, f.location))
+                    .collect();
+                indicators.push(format!(
+                    "{} place(s) in the {} structure carry data: {}",
+                    places.len(),
+                    structured.format,
+                    places.join(", ")
+                ));
+            }
+            if let Some(source_code) = text
+                .source_code
+                .as_ref()
+                .filter(|source_code| source_code.is_suspicious)
+            {
+                steg_detected = true;
+                if !source_code.payloads.is_empty() {
+                    let lines: Vec<String> = source_code
+                        .payloads
+                        .iter()
+                        .map(|p| format!("{}-{}", p.line, p.end_line))
+                        .collect();
+                    indicators.push(format!(
+                        "{} {} comment(s) decode to data, at lines {}",
+                        lines.len(),
+                        source_code.language,
+                        lines.join(", ")
+                    ));
+                }
+                if source_code.abnormal_ratio {
+                    indicators.push(format!(
+                        "{:.1}% of the source file is comment",
+                        source_code.comment_ratio * 100.0
+                    ));
+                }
+            }
+            if let Some(model) = text.model.as_ref().filter(|model| model.is_suspicious) {
+                steg_detected = true;
+                let locations: Vec<&str> =
+                    model.regions.iter().map(|r| r.location.as_str()).collect();
+                indicators.push(format!(
+                    "{} region(s) of the {} model no loader reads: {}",
+                    locations.len(),
+                    model.format,
+                    locations.join(", ")
+                ));
+                for anomaly in &model.anomalies {
+                    indicators.push(format!("{} structure: {}", model.format, anomaly));
+                }
+            }
         }
         _ => {}
     }
 
-    let confidence = if indicators.len() >= 3 {
+    // Payloads the scan actually recovered. Carved images are left out, as thumbnails and
+    // previews are embedded that way routinely.
+    let mut recovered = recovered_payloads(
+        response.slack_space.as_ref(),
+        response.deflate_streams.as_ref(),
+        &response.format_specific_analysis,
+    );
+    for payload in carved.iter().filter(|p| p.file_type != "Image") {
+        if !recovered.iter().any(|p| p.offset == Some(payload.offset)) {
+            recovered.push(RecoveredPayloadReport {
+                source: "carved".to_string(),
+                description: format!("{} ({} bytes)", payload.description, payload.data.len()),
+                offset: Some(payload.offset),
+                artifact: None,
+            });
+        }
+    }
+    steg_detected |= !recovered.is_empty();
+
+    let weight =
+        (indicators.len() - low_weight_indicators) as f64 + low_weight_indicators as f64 * 0.5;
+    let confidence = if weight >= 3.0 {
         "high"
-    } else if indicators.len() >= 1 {
+    } else if weight >= 1.0 {
         "medium"
     } else {
         "low"
@@ -325,5 +2762,55 @@ fn finalize_summary(response: &mut AnalysisResponse) {
         confidence_level: confidence.to_string(),
         threat_indicators: indicators,
         recommendations,
+        verdict: if steg_detected {
+            SummaryVerdict::Suspicious
+        } else {
+            SummaryVerdict::Clean
+        },
+        extracted_payloads: Vec::new(),
     };
+    response.summary.confirm_payloads(recovered);
+}
+
+// Point the summary's carved payloads at their downloads, once deliver() has stored them
+pub fn link_delivered_payloads(response: &mut AnalysisResponse) {
+    for payload in &mut response.summary.extracted_payloads {
+        if payload.source != "carved" {
+            continue;
+        }
+        payload.artifact = response
+            .carved_payloads
+            .iter()
+            .find(|delivered| Some(delivered.offset) == payload.offset)
+            .and_then(|delivered| delivered.download_url.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_keeps_leading_indicators() {
+        let summary = AnalysisSummary {
+            steganography_detected: true,
+            confidence_level: "high".to_string(),
+            threat_indicators: (1..=5).map(|i| format!("indicator {}", i)).collect(),
+            recommendations: Vec::new(),
+            verdict: SummaryVerdict::Suspicious,
+            extracted_payloads: Vec::new(),
+        };
+
+        let verdict = verdict(&summary);
+        assert_eq!(verdict.verdict, Verdict::Suspicious);
+        assert_eq!(verdict.confidence, "high");
+        assert_eq!(
+            verdict.top_indicators,
+            vec!["indicator 1", "indicator 2", "indicator 3"]
+        );
+        assert_eq!(
+            serde_json::to_value(&verdict).unwrap()["verdict"],
+            "suspicious"
+        );
+    }
 }
@@ -0,0 +1,275 @@
+use analyzers::content_store::{ContentStore, ObjectReference, StoredObject};
+use analyzers::payload_carver::CarvedPayload;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::ApiError;
+use crate::history::tenant_dirs;
+use crate::models::CarvedPayloadInfo;
+use crate::tenant::Tenant;
+
+// Payloads at or below this size are returned inline by default
+const DEFAULT_INLINE_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadDelivery {
+    // Inline under the size cap, stored as an artifact above it
+    Auto,
+    Inline,
+    Artifact,
+    None,
+}
+
+impl PayloadDelivery {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "inline" => PayloadDelivery::Inline,
+            "artifact" => PayloadDelivery::Artifact,
+            "none" => PayloadDelivery::None,
+            _ => PayloadDelivery::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactMetadata {
+    pub id: String,
+    pub file_name: String,
+    pub description: String,
+    pub size_bytes: usize,
+    pub created_at: String,
+}
+
+impl From<&StoredObject> for ArtifactMetadata {
+    fn from(object: &StoredObject) -> Self {
+        // Name and description come from the first scan that carved the payload
+        let first = object.references.first();
+        Self {
+            id: object.sha256.clone(),
+            file_name: first.map_or_else(|| object.file_name.clone(), |r| r.name.clone()),
+            description: first.map(|r| r.description.clone()).unwrap_or_default(),
+            size_bytes: object.size_bytes,
+            created_at: chrono::DateTime::from_timestamp(object.created_at as i64, 0)
+                .unwrap_or_default()
+                .to_rfc3339(),
+        }
+    }
+}
+
+pub struct ArtifactStore {
+    root: PathBuf,
+    inline_limit: usize,
+}
+
+impl ArtifactStore {
+    pub fn new(root: PathBuf, inline_limit: usize) -> Self {
+        Self { root, inline_limit }
+    }
+
+    // STEGASCAN_ARTIFACT_DIR and STEGASCAN_INLINE_PAYLOAD_LIMIT override the defaults
+    pub fn from_env() -> Self {
+        let root = std::env::var("STEGASCAN_ARTIFACT_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir().join("stegascan-artifacts"));
+        let inline_limit = std::env::var("STEGASCAN_INLINE_PAYLOAD_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_INLINE_LIMIT);
+
+        Self::new(root, inline_limit)
+    }
+
+    // Artifacts live under `<root>/<tenant>/` so one tenant can't fetch another's. Within a
+    // tenant they are content-addressed, so a payload carved by many scans is stored once.
+    fn tenant_store(&self, tenant: &Tenant) -> ContentStore {
+        ContentStore::new(self.root.join(tenant.as_str()))
+    }
+
+    // Store a payload carved by the given scan
+    pub fn store(
+        &self,
+        tenant: &Tenant,
+        scan_id: &str,
+        payload: &CarvedPayload,
+    ) -> Result<StoredObject, ApiError> {
+        let reference = ObjectReference {
+            source: scan_id.to_string(),
+            offset: payload.offset,
+            description: payload.description.clone(),
+            name: format!("carved_0x{:X}.bin", payload.offset),
+        };
+        Ok(self
+            .tenant_store(tenant)
+            .put(&payload.data, "bin", reference)?)
+    }
+
+    // Artifacts stored before content addressing are `<uuid>.bin` with an ArtifactMetadata
+    // sidecar `<uuid>.json`, each belonging to the one scan that carved it. Stored scans still
+    // link to them by UUID, so they are served, listed and removed alongside the hashed ones.
+    fn legacy_paths(&self, tenant: &Tenant, id: &str) -> Option<(PathBuf, PathBuf)> {
+        let id = uuid::Uuid::parse_str(id).ok()?.to_string();
+        let dir = self.root.join(tenant.as_str());
+        Some((
+            dir.join(format!("{}.bin", id)),
+            dir.join(format!("{}.json", id)),
+        ))
+    }
+
+    fn load_legacy(data: &Path, metadata: &Path) -> Option<(ArtifactMetadata, Vec<u8>)> {
+        let json = std::fs::read_to_string(metadata).ok()?;
+        let metadata = serde_json::from_str(&json).ok()?;
+        Some((metadata, std::fs::read(data).ok()?))
+    }
+
+    fn legacy_artifacts(&self, tenant: &Tenant) -> Result<Vec<ArtifactMetadata>, ApiError> {
+        let dir = self.root.join(tenant.as_str());
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut artifacts = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if uuid::Uuid::parse_str(stem).is_err() {
+                continue;
+            }
+            let Ok(json) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Ok(metadata) = serde_json::from_str::<ArtifactMetadata>(&json) {
+                artifacts.push(metadata);
+            }
+        }
+        Ok(artifacts)
+    }
+
+    fn remove_legacy(data: &Path, metadata: &Path) -> Result<u64, ApiError> {
+        let mut freed = 0;
+        for path in [data, metadata] {
+            let size = std::fs::metadata(path).map_or(0, |m| m.len());
+            match std::fs::remove_file(path) {
+                Ok(()) => freed += size,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(freed)
+    }
+
+    // IDs are SHA-256 hashes of the content, or UUIDs for artifacts stored before that
+    pub fn load(&self, tenant: &Tenant, id: &str) -> Result<(ArtifactMetadata, Vec<u8>), ApiError> {
+        if let Some((data, metadata)) = self.legacy_paths(tenant, id) {
+            return Self::load_legacy(&data, &metadata)
+                .ok_or_else(|| ApiError::ArtifactNotFound(id.to_string()));
+        }
+
+        let (object, data) = self
+            .tenant_store(tenant)
+            .read(id)
+            .ok_or_else(|| ApiError::ArtifactNotFound(id.to_string()))?;
+
+        Ok((ArtifactMetadata::from(&object), data))
+    }
+
+    // Metadata of every readable artifact of the tenant, in no particular order
+    pub fn list(&self, tenant: &Tenant) -> Result<Vec<ArtifactMetadata>, ApiError> {
+        let mut artifacts: Vec<ArtifactMetadata> = self
+            .tenant_store(tenant)
+            .objects()?
+            .iter()
+            .map(ArtifactMetadata::from)
+            .collect();
+        artifacts.extend(self.legacy_artifacts(tenant)?);
+        Ok(artifacts)
+    }
+
+    // Drop a scan's claim on an artifact, removing the artifact once no scan refers to it.
+    // Returns the bytes freed.
+    pub fn release(&self, tenant: &Tenant, id: &str, scan_id: &str) -> Result<u64, ApiError> {
+        if let Some((data, metadata)) = self.legacy_paths(tenant, id) {
+            return Self::remove_legacy(&data, &metadata);
+        }
+        Ok(self.tenant_store(tenant).release(id, scan_id)?)
+    }
+
+    // Remove an artifact whatever refers to it, returning the bytes freed. Missing artifacts
+    // count as removed.
+    pub fn delete(&self, tenant: &Tenant, id: &str) -> Result<u64, ApiError> {
+        if let Some((data, metadata)) = self.legacy_paths(tenant, id) {
+            return Self::remove_legacy(&data, &metadata);
+        }
+        Ok(self.tenant_store(tenant).remove(id)?)
+    }
+
+    // Bytes an artifact's data and index entry take on disk
+    pub fn artifact_bytes(&self, tenant: &Tenant, id: &str) -> u64 {
+        if let Some((data, metadata)) = self.legacy_paths(tenant, id) {
+            return [data, metadata]
+                .iter()
+                .map(|path| std::fs::metadata(path).map_or(0, |m| m.len()))
+                .sum();
+        }
+        self.tenant_store(tenant).stored_bytes(id)
+    }
+
+    // Tenants that have stored artifacts
+    pub fn tenants(&self) -> Result<Vec<Tenant>, ApiError> {
+        tenant_dirs(&self.root)
+    }
+
+    // Turn carved payloads into report entries, inlining or storing each one
+    pub fn deliver(
+        &self,
+        tenant: &Tenant,
+        scan_id: &str,
+        payloads: Vec<CarvedPayload>,
+        delivery: PayloadDelivery,
+    ) -> Result<Vec<CarvedPayloadInfo>, ApiError> {
+        let mut delivered = Vec::new();
+
+        for payload in payloads {
+            let mut info = CarvedPayloadInfo {
+                offset: payload.offset,
+                offset_hex: format!("0x{:X}", payload.offset),
+                size_bytes: payload.data.len(),
+                description: payload.description.clone(),
+                file_type: payload.file_type.clone(),
+                data_base64: None,
+                artifact_id: None,
+                download_url: None,
+                also_carved_from: Vec::new(),
+            };
+
+            let inline = match delivery {
+                PayloadDelivery::None => {
+                    delivered.push(info);
+                    continue;
+                }
+                PayloadDelivery::Inline => true,
+                PayloadDelivery::Artifact => false,
+                PayloadDelivery::Auto => payload.data.len() <= self.inline_limit,
+            };
+
+            if inline && payload.data.len() <= self.inline_limit {
+                info.data_base64 =
+                    Some(base64::engine::general_purpose::STANDARD.encode(&payload.data));
+            } else {
+                let object = self.store(tenant, scan_id, &payload)?;
+                info.download_url = Some(format!("/api/artifacts/{}", object.sha256));
+                info.also_carved_from = object.other_sources(scan_id);
+                info.artifact_id = Some(object.sha256);
+            }
+
+            delivered.push(info);
+        }
+
+        Ok(delivered)
+    }
+}
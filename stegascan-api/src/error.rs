@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Json, Response},
 };
 use serde_json::json;
@@ -10,9 +10,42 @@ pub enum ApiError {
     #[error("Missing file in request")]
     MissingFile,
 
+    #[error("Upload is over the limit of {0} MB")]
+    UploadTooLarge(u64),
+
     #[error("Analysis failed: {0}")]
     AnalysisFailed(String),
 
+    #[error("Unknown analyzer: {0}")]
+    UnknownAnalyzer(String),
+
+    #[error("Artifact not found: {0}")]
+    ArtifactNotFound(String),
+
+    #[error("Scan not found: {0}")]
+    ScanNotFound(String),
+
+    #[error("Job not found: {0}")]
+    JobNotFound(String),
+
+    #[error("Job not finished: {0}")]
+    JobNotFinished(String),
+
+    #[error("Invalid annotation: {0}")]
+    InvalidAnnotation(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Invalid tenant ID: {0}")]
+    InvalidTenant(String),
+
+    #[error("Rate limit exceeded, retry in {retry_after} seconds")]
+    RateLimited { retry_after: u64 },
+
+    #[error("Daily quota exceeded, retry in {retry_after} seconds")]
+    QuotaExceeded { retry_after: u64 },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -22,11 +55,31 @@ pub enum ApiError {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            ApiError::RateLimited { retry_after } | ApiError::QuotaExceeded { retry_after } => {
+                Some(retry_after)
+            }
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             ApiError::MissingFile => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::UploadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
             ApiError::AnalysisFailed(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            ApiError::UnknownAnalyzer(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::ArtifactNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::ScanNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::JobNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::JobNotFinished(_) => (StatusCode::CONFLICT, self.to_string()),
+            ApiError::InvalidAnnotation(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+            ApiError::InvalidTenant(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::RateLimited { .. } | ApiError::QuotaExceeded { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, self.to_string())
+            }
             ApiError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            ApiError::Multipart(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            // 413 when the request body ran past its limit
+            ApiError::Multipart(ref e) => (e.status(), self.to_string()),
         };
 
         let body = Json(json!({
@@ -34,6 +87,12 @@ impl IntoResponse for ApiError {
             "status": status.as_u16()
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
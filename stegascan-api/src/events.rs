@@ -0,0 +1,29 @@
+use axum::response::sse::Event;
+use serde::Serialize;
+
+// One step of a running scan, streamed to clients as a server-sent event
+#[derive(Debug, Clone)]
+pub struct ScanEvent {
+    pub event: &'static str,
+    pub data: serde_json::Value,
+}
+
+impl ScanEvent {
+    pub fn new<T: Serialize>(event: &'static str, data: &T) -> Self {
+        Self {
+            event,
+            data: serde_json::to_value(data).unwrap_or(serde_json::Value::Null),
+        }
+    }
+}
+
+impl From<ScanEvent> for Event {
+    fn from(scan_event: ScanEvent) -> Self {
+        Event::default()
+            .event(scan_event.event)
+            .data(scan_event.data.to_string())
+    }
+}
+
+// Receives events as each analyzer finishes
+pub type EventSink<'a> = &'a (dyn Fn(ScanEvent) + Send + Sync);
@@ -1,23 +1,161 @@
-use axum::{extract::Multipart, response::Json};
+use analyzers::frame_sampler::{SCENE_CHANGE_THRESHOLD, SamplingMode, SamplingStrategy};
+use analyzers::shared;
+use axum::{
+    extract::{Multipart, Path, Query, State, multipart::Field},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Json,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures::stream::{self, Stream};
+use parsers::video_parser::HwAccel;
+use serde::Deserialize;
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::time::Instant;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
 
-use crate::analysis::run_full_analysis;
+use crate::analysis::{
+    VideoOptions, link_delivered_payloads, run_full_analysis_with_events, run_single_analyzer,
+    run_verdict_analysis,
+};
+use crate::artifacts::PayloadDelivery;
 use crate::error::ApiError;
-use crate::models::AnalysisResponse;
+use crate::events::{EventSink, ScanEvent};
+use crate::history::{CacheKey, ScanRecord, ScanSummary, ScanVerdict};
+use crate::jobs::JobStatus;
+use crate::models::{
+    AnalysisResponse, Annotation, AnnotationRequest, SingleAnalyzerResponse, Verdict,
+    VerdictResponse,
+};
+use crate::retention::{self, PruneReport};
+use crate::state::AppState;
+use crate::stats::{self, StatsReport};
+use crate::tenant::Tenant;
+use crate::uploads::UploadDir;
+use crate::usage::{self, ApiKey, UsageReport};
 
 pub async fn root() -> Json<serde_json::Value> {
     Json(json!({
         "service": "Stegascan API",
         "version": "0.1.0",
         "description": "Steganography detection and analysis API",
-        "endpoint": "POST /api/scan"
+        "endpoint": "POST /api/scan",
+        "job_endpoints": ["GET /api/jobs/{id}/status", "GET /api/jobs/{id}/result"],
+        "stream_endpoint": "POST /api/scan/stream",
+        "verdict_endpoint": "POST /api/scan/verdict",
+        "analyzer_endpoint": "POST /api/analyze/{magic_bytes|slack_space|deflate|exif|lsb|ws|channel_correlation|adaptive_lsb|bpcs|calibration|webp|gif|png_text|pages|id3|spectrogram|ultrasonic|demodulation|phase|video|text|plugins}",
+        "artifact_endpoint": "GET /api/artifacts/{id}",
+        "history_endpoints": ["GET /api/scans[?verdict=clean|suspicious|likely_stego]", "GET /api/scans/{id}", "DELETE /api/scan/{id}"],
+        "annotation_endpoint": "POST /api/scans/{id}/annotations",
+        "stats_endpoint": "GET /api/stats",
+        "usage_endpoint": "GET /api/usage"
     }))
 }
 
-pub async fn scan_file(mut multipart: Multipart) -> Result<Json<AnalysisResponse>, ApiError> {
-    let mut file_data: Option<Vec<u8>> = None;
+struct Upload {
+    // Streamed to the upload directory as it arrived; removed when the upload drops
+    file: NamedTempFile,
+    size: u64,
+    // SHA-256 of the file, hex encoded
+    sha256: String,
+    filename: String,
+    video: VideoOptions,
+    payloads: PayloadDelivery,
+    // Analyze again even when the same content was scanned before
+    force: bool,
+}
+
+impl Upload {
+    // The content together with everything that changes what a scan of it reports: the
+    // extension parsers pick the decoder by, the video options and how payloads are delivered.
+    // Debug output of the parsed values is the canonical form, so `1` and `1.0` agree.
+    fn cache_key(&self) -> CacheKey {
+        let extension = self
+            .file
+            .path()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        CacheKey {
+            sha256: self.sha256.clone(),
+            options: format!(
+                "extension={};video={:?};payloads={:?}",
+                extension, self.video, self.payloads
+            ),
+        }
+    }
+
+    // The stored report of an earlier scan of the same content with the same options, unless
+    // a rescan was forced
+    fn cached_result(&self, state: &AppState, tenant: &Tenant) -> Option<AnalysisResponse> {
+        if self.force {
+            return None;
+        }
+
+        let record = state.history.find_cached(tenant, &self.cache_key())?;
+        let mut result = record.response;
+        result.scan_id = Some(record.id);
+        result.cached = true;
+        // Where the earlier upload was staged is long gone; point at this one
+        result.file_info.path = self.file.path().to_string_lossy().to_string();
+        Some(result)
+    }
+}
+
+// Write the file field to the upload directory chunk by chunk as it arrives, hashing it on the
+// way, so multi-GB videos are never held in memory. The original extension is kept since
+// parsers pick the decoder from it; only the extension of the client's name is used.
+async fn receive_file(
+    mut field: Field<'_>,
+    uploads: &UploadDir,
+    filename: Option<&str>,
+) -> Result<(NamedTempFile, u64, String), ApiError> {
+    let suffix = filename
+        .and_then(|name| std::path::Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|ext| format!(".{}", ext))
+        .unwrap_or_default();
+
+    // The handle keeps ownership of the file, removing it if the upload is refused; chunks go
+    // through an async clone of it so writes don't hold up the runtime's workers
+    let file = uploads.create(&suffix)?;
+    let mut writer = tokio::fs::File::from_std(file.as_file().try_clone()?);
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+    while let Some(chunk) = field.chunk().await? {
+        size += chunk.len() as u64;
+        if uploads.max_bytes() > 0 && size > uploads.max_bytes() {
+            return Err(ApiError::UploadTooLarge(
+                uploads.max_bytes() / (1024 * 1024),
+            ));
+        }
+        hasher.update(&chunk);
+        writer.write_all(&chunk).await?;
+    }
+    writer.flush().await?;
+
+    let sha256 = shared::hex(&hasher.finalize());
+    Ok((file, size, sha256))
+}
+
+async fn read_upload(mut multipart: Multipart, uploads: &UploadDir) -> Result<Upload, ApiError> {
+    let mut file: Option<(NamedTempFile, u64, String)> = None;
     let mut filename: Option<String> = None;
     let mut video_sample_rate: usize = 30;
+    let mut video_sampling = SamplingMode::default();
+    let mut video_seed: u64 = 0;
+    let mut video_scene_threshold = SCENE_CHANGE_THRESHOLD;
+    let mut video_interval_seconds = 1.0;
+    let mut video_max_frames_per_scene = 0;
+    let mut video_hwaccel = HwAccel::default();
+    let mut payloads = PayloadDelivery::Auto;
+    let mut force = false;
 
     // Parse multipart form data
     while let Some(field) = multipart.next_field().await? {
@@ -26,31 +164,503 @@ pub async fn scan_file(mut multipart: Multipart) -> Result<Json<AnalysisResponse
         match field_name.as_str() {
             "file" => {
                 filename = field.file_name().map(|s| s.to_string());
-                file_data = Some(field.bytes().await?.to_vec());
+                file = Some(receive_file(field, uploads, filename.as_deref()).await?);
             }
             "video_sample_rate" => {
                 if let Ok(text) = field.text().await {
                     video_sample_rate = text.parse().unwrap_or(30);
                 }
             }
+            "video_sampling" => {
+                if let Ok(text) = field.text().await {
+                    video_sampling = text.parse().unwrap_or_default();
+                }
+            }
+            "video_seed" => {
+                if let Ok(text) = field.text().await {
+                    video_seed = text.trim().parse().unwrap_or(0);
+                }
+            }
+            "video_scene_threshold" => {
+                if let Ok(text) = field.text().await {
+                    video_scene_threshold = text.trim().parse().unwrap_or(SCENE_CHANGE_THRESHOLD);
+                }
+            }
+            "video_interval_seconds" => {
+                if let Ok(text) = field.text().await {
+                    video_interval_seconds = text.trim().parse().unwrap_or(1.0);
+                }
+            }
+            "video_max_frames_per_scene" => {
+                if let Ok(text) = field.text().await {
+                    video_max_frames_per_scene = text.trim().parse().unwrap_or(0);
+                }
+            }
+            "video_hwaccel" => {
+                if let Ok(text) = field.text().await {
+                    video_hwaccel = text.parse().unwrap_or_default();
+                }
+            }
+            "payloads" => {
+                if let Ok(text) = field.text().await {
+                    payloads = PayloadDelivery::parse(&text);
+                }
+            }
+            "force" => {
+                if let Ok(text) = field.text().await {
+                    force = matches!(text.trim().to_lowercase().as_str(), "true" | "1" | "yes");
+                }
+            }
             _ => {}
         }
     }
 
-    let file_data = file_data.ok_or(ApiError::MissingFile)?;
-    let filename = filename.unwrap_or_else(|| "unknown".to_string());
+    // A rate of 0 would divide by zero when sampling frames
+    let video_sample_rate = video_sample_rate.max(1);
+    let video_sampling = match video_sampling {
+        SamplingMode::Stride => SamplingStrategy::EveryNth(video_sample_rate),
+        SamplingMode::Random => SamplingStrategy::Random {
+            one_in: video_sample_rate,
+            seed: video_seed,
+        },
+        SamplingMode::Scene => SamplingStrategy::SceneChange {
+            threshold: video_scene_threshold,
+        },
+        SamplingMode::Time => SamplingStrategy::UniformTime {
+            interval_seconds: video_interval_seconds,
+        },
+    };
+
+    let (file, size, sha256) = file.ok_or(ApiError::MissingFile)?;
+    Ok(Upload {
+        file,
+        size,
+        sha256,
+        filename: filename.unwrap_or_else(|| "unknown".to_string()),
+        video: VideoOptions {
+            sampling: video_sampling,
+            scene_threshold: video_scene_threshold,
+            max_frames_per_scene: video_max_frames_per_scene,
+            hwaccel: video_hwaccel,
+            keyframes_only: false,
+        },
+        payloads,
+        force,
+    })
+}
+
+// Queue the upload for a background scan and answer with its job right away; large videos
+// take longer to analyze than clients and proxies wait for a response. Progress and the report
+// are at /api/jobs/{id}/status and /api/jobs/{id}/result.
+pub async fn scan_file(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    ApiKey(api_key): ApiKey,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<JobStatus>), ApiError> {
+    let upload = read_upload(multipart, &state.uploads).await?;
+
+    tracing::info!(
+        "Queueing scan of file: {} ({} bytes)",
+        upload.filename,
+        upload.size
+    );
+
+    let job = state.jobs.create(&tenant, &upload.filename, Instant::now());
 
-    tracing::info!("Scanning file: {} ({} bytes)", filename, file_data.len());
+    // A cached report completes the job on the spot
+    if let Some(cached) = upload.cached_result(&state, &tenant) {
+        tracing::info!("Returning cached report for: {}", upload.filename);
+        state.jobs.finish(&job.job_id, Ok(cached), Instant::now());
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(state.jobs.status(&tenant, &job.job_id)?),
+        ));
+    }
+
+    let id = job.job_id.clone();
+    tokio::spawn(async move {
+        let filename = upload.filename.clone();
+        // Analyzers block the thread they run on, so the scan goes to the blocking pool
+        let runtime = tokio::runtime::Handle::current();
+        let scan = {
+            let state = state.clone();
+            let id = id.clone();
+            tokio::task::spawn_blocking(move || {
+                state.jobs.start(&id);
+                let sink = |event: ScanEvent| state.jobs.record_event(&id, &event);
+                runtime.block_on(scan_and_record(
+                    &state,
+                    &tenant,
+                    api_key.as_deref(),
+                    &upload,
+                    &sink,
+                ))
+            })
+        };
 
-    // Create temporary file
-    let temp_file = tempfile::NamedTempFile::new()?;
-    std::fs::write(temp_file.path(), &file_data)?;
+        // A panicked scan surfaces as a failed analysis
+        let outcome = match scan.await {
+            Ok(outcome) => outcome,
+            Err(e) => Err(ApiError::AnalysisFailed(e.to_string())),
+        };
+        match &outcome {
+            Ok(_) => tracing::info!("Analysis completed for: {}", filename),
+            Err(e) => tracing::warn!("Analysis failed for {}: {}", filename, e),
+        }
+        state
+            .jobs
+            .finish(&id, outcome.map_err(|e| e.to_string()), Instant::now());
+    });
 
-    // Run analysis synchronously
-    let result =
-        run_full_analysis(&temp_file.path().to_path_buf(), video_sample_rate, false).await?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
 
-    tracing::info!("Analysis completed for: {}", filename);
+// State and progress of a scan queued by /api/scan
+pub async fn get_job_status(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatus>, ApiError> {
+    Ok(Json(state.jobs.status(&tenant, &id)?))
+}
+
+// The full response of a completed job, as stored in scan history
+pub async fn get_job_result(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<String>,
+) -> Result<Json<AnalysisResponse>, ApiError> {
+    Ok(Json(state.jobs.result(&tenant, &id)?))
+}
+
+// Analyze the upload, deliver what was carved from it and store the report in scan history
+async fn scan_and_record(
+    state: &AppState,
+    tenant: &Tenant,
+    api_key: Option<&str>,
+    upload: &Upload,
+    events: EventSink<'_>,
+) -> Result<AnalysisResponse, ApiError> {
+    let (analysis, mut usage) = usage::measure(run_full_analysis_with_events(
+        upload.file.path(),
+        upload.video,
+        events,
+    ))
+    .await;
+    let (mut result, carved) = analysis?;
+    let scan_id = uuid::Uuid::new_v4().to_string();
+    result.carved_payloads = state
+        .artifacts
+        .deliver(tenant, &scan_id, carved, upload.payloads)?;
+    link_delivered_payloads(&mut result);
+    // The record is stored under this ID, which the delivered artifacts already refer to
+    result.scan_id = Some(scan_id);
+    usage.count_artifacts(&result.carved_payloads);
+    state.history.record(
+        tenant,
+        api_key,
+        &upload.filename,
+        Some(&upload.cache_key()),
+        &result,
+        &usage,
+    )?;
+    Ok(result)
+}
+
+// Events a streaming scan gets ahead of its client before it waits for the client to catch up
+const STREAM_BUFFER: usize = 64;
+
+// Streams each analyzer's findings as server-sent events while the scan runs. The final
+// `complete` event carries the full response; failures end with `error`.
+pub async fn scan_file_stream(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    ApiKey(api_key): ApiKey,
+    multipart: Multipart,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let upload = read_upload(multipart, &state.uploads).await?;
+
+    tracing::info!(
+        "Streaming scan of file: {} ({} bytes)",
+        upload.filename,
+        upload.size
+    );
+
+    let (tx, rx) = mpsc::channel::<ScanEvent>(STREAM_BUFFER);
+    let events = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(Event::from(event)), rx))
+    });
+
+    // A cached report is the whole stream
+    if let Some(cached) = upload.cached_result(&state, &tenant) {
+        tracing::info!("Returning cached report for: {}", upload.filename);
+        let _ = tx.send(ScanEvent::new("complete", &cached)).await;
+        return Ok(Sse::new(events).keep_alive(KeepAlive::default()));
+    }
+
+    tokio::spawn(async move {
+        let filename = upload.filename.clone();
+        // Analyzers block the thread they run on, so the scan goes to the blocking pool
+        let runtime = tokio::runtime::Handle::current();
+        let scan = {
+            let tx = tx.clone();
+            tokio::task::spawn_blocking(move || {
+                // A slow client holds the scan up rather than letting events pile up. The sink
+                // runs inside block_on, where blocking_send refuses to, so the send is waited
+                // on directly. The client may have gone away; keep scanning so history is
+                // still recorded.
+                let sink = |event: ScanEvent| {
+                    let _ = futures::executor::block_on(tx.send(event));
+                };
+                runtime.block_on(scan_and_record(
+                    &state,
+                    &tenant,
+                    api_key.as_deref(),
+                    &upload,
+                    &sink,
+                ))
+            })
+        };
+
+        // A panicked scan surfaces as a failed analysis
+        let outcome = match scan.await {
+            Ok(outcome) => outcome,
+            Err(e) => Err(ApiError::AnalysisFailed(e.to_string())),
+        };
+        match outcome {
+            Ok(result) => {
+                tracing::info!("Streaming analysis completed for: {}", filename);
+                let _ = tx.send(ScanEvent::new("complete", &result)).await;
+            }
+            Err(e) => {
+                tracing::warn!("Streaming analysis failed for {}: {}", filename, e);
+                let _ = tx
+                    .send(ScanEvent::new("error", &json!({ "error": e.to_string() })))
+                    .await;
+            }
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+// Fast scan for upload gatekeeping that answers with the verdict only. Nothing is carved or
+// stored in scan history, and a scan that runs past the verdict budget answers
+// `inconclusive` instead of holding up the upload.
+pub async fn scan_verdict(
+    State(state): State<AppState>,
+    // Not stored, but still resolved so unknown API keys are rejected
+    _tenant: Tenant,
+    multipart: Multipart,
+) -> Result<Json<VerdictResponse>, ApiError> {
+    let upload = read_upload(multipart, &state.uploads).await?;
+
+    tracing::info!(
+        "Verdict scan of file: {} ({} bytes)",
+        upload.filename,
+        upload.size
+    );
+
+    let temp_file = upload.file;
+    let video = upload.video;
+    // Analyzers block the thread they run on, so the scan goes to the blocking pool where it
+    // can't hold up the runtime thread that has to notice the budget running out
+    let runtime = tokio::runtime::Handle::current();
+    let scan = tokio::task::spawn_blocking(move || {
+        runtime.block_on(run_verdict_analysis(temp_file.path(), video))
+    });
+
+    match tokio::time::timeout(state.verdict_budget, scan).await {
+        Ok(joined) => {
+            // A panicked scan surfaces as a failed analysis
+            let verdict = joined.map_err(|e| ApiError::AnalysisFailed(e.to_string()))??;
+            Ok(Json(verdict))
+        }
+        Err(_) => {
+            // The scan can't be interrupted mid-analyzer; it finishes in the background and
+            // its result is dropped
+            tracing::warn!(
+                "Verdict scan of {} exceeded {} ms",
+                upload.filename,
+                state.verdict_budget.as_millis()
+            );
+            Ok(Json(VerdictResponse {
+                verdict: Verdict::Inconclusive,
+                confidence: "low".to_string(),
+                top_indicators: Vec::new(),
+            }))
+        }
+    }
+}
+
+pub async fn analyze_with(
+    State(state): State<AppState>,
+    // Not stored, but still resolved so unknown API keys are rejected
+    _tenant: Tenant,
+    Path(analyzer): Path<String>,
+    multipart: Multipart,
+) -> Result<Json<SingleAnalyzerResponse>, ApiError> {
+    let upload = read_upload(multipart, &state.uploads).await?;
+
+    tracing::info!(
+        "Running {} analyzer on: {} ({} bytes)",
+        analyzer,
+        upload.filename,
+        upload.size
+    );
+
+    // Analyzers block the thread they run on, so they go to the blocking pool
+    let runtime = tokio::runtime::Handle::current();
+    let result = tokio::task::spawn_blocking(move || {
+        runtime.block_on(run_single_analyzer(
+            upload.file.path(),
+            &analyzer,
+            upload.video,
+        ))
+    })
+    .await
+    // A panicked analyzer surfaces as a failed analysis
+    .map_err(|e| ApiError::AnalysisFailed(e.to_string()))??;
 
     Ok(Json(result))
 }
+
+pub async fn download_artifact(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (metadata, data) = state.artifacts.load(&tenant, &id)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", metadata.file_name),
+            ),
+        ],
+        data,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanFilter {
+    pub verdict: Option<ScanVerdict>,
+}
+
+pub async fn list_scans(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(filter): Query<ScanFilter>,
+) -> Result<Json<Vec<ScanSummary>>, ApiError> {
+    Ok(Json(state.history.list(&tenant, filter.verdict)?))
+}
+
+pub async fn get_scan(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<String>,
+) -> Result<Json<ScanRecord>, ApiError> {
+    Ok(Json(state.history.load(&tenant, &id)?))
+}
+
+// Label a finding of a stored scan as a true or false positive, or comment on it. Without an
+// analyst in the body the annotation is credited to the API key.
+pub async fn annotate_scan(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    ApiKey(api_key): ApiKey,
+    Path(id): Path<String>,
+    Json(request): Json<AnnotationRequest>,
+) -> Result<Json<Vec<Annotation>>, ApiError> {
+    let annotation = Annotation {
+        rule: request.rule,
+        label: request.label,
+        comment: request.comment,
+        analyst: request.analyst.or(api_key),
+        added: chrono::Utc::now().to_rfc3339(),
+    };
+    Ok(Json(state.history.annotate(&tenant, &id, annotation)?))
+}
+
+// Remove a stored scan and the artifacts carved from it
+pub async fn delete_scan(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<String>,
+) -> Result<Json<PruneReport>, ApiError> {
+    let report = tokio::task::spawn_blocking(move || {
+        retention::delete_scan(&state.history, &state.artifacts, &tenant, &id)
+    })
+    .await
+    .map_err(|e| ApiError::AnalysisFailed(e.to_string()))??;
+    Ok(Json(report))
+}
+
+// Resources used by the tenant's stored scans, in total and per API key
+pub async fn get_usage(
+    State(state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<UsageReport>, ApiError> {
+    let records = state.history.records(&tenant)?;
+    Ok(Json(usage::aggregate(
+        tenant.as_str(),
+        records
+            .iter()
+            .map(|record| (record.api_key.as_deref(), &record.usage)),
+    )))
+}
+
+// Detections over time, most fired rules and file type breakdown of the tenant's stored scans
+pub async fn get_stats(
+    State(state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<StatsReport>, ApiError> {
+    let records = state.history.records(&tenant)?;
+    Ok(Json(stats::aggregate(
+        tenant.as_str(),
+        records.iter().map(|record| &record.response),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::FromRequest;
+    use axum::http::Request;
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_refused_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = UploadDir::new(dir.path().join("uploads")).with_max_bytes(16);
+
+        // Sent in pieces, so part of the file is written before it goes over the limit
+        let parts = [
+            "--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"cover.png\"\r\n\r\n",
+            "0123456789",
+            "0123456789",
+            "\r\n--XyZ--\r\n",
+        ];
+        let body = Body::from_stream(stream::iter(
+            parts.map(|part| Ok::<_, Infallible>(part.to_string())),
+        ));
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=XyZ")
+            .body(body)
+            .unwrap();
+        let mut multipart = Multipart::from_request(request, &()).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+
+        let error = receive_file(field, &uploads, Some("cover.png"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(std::fs::read_dir(uploads.root()).unwrap().count(), 0);
+    }
+}
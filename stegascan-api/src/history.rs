@@ -0,0 +1,472 @@
+use analyzers::shared;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::ApiError;
+use crate::models::{AnalysisResponse, AnalysisSummary, Annotation};
+use crate::stats;
+use crate::tenant::{Tenant, is_valid_tenant_id};
+use crate::usage::ResourceUsage;
+
+// Index of scans by cache key under each tenant directory
+const CACHE_INDEX_DIR: &str = "cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub id: String,
+    pub tenant: String,
+    pub filename: String,
+    // Label of the API key the scan was made with, see usage::key_label
+    #[serde(default)]
+    pub api_key: Option<String>,
+    // Absent in records written before usage was tracked
+    #[serde(default)]
+    pub usage: ResourceUsage,
+    // SHA-256 of the uploaded file, hex encoded; absent in records written before dedup
+    #[serde(default)]
+    pub sha256: Option<String>,
+    // CacheKey::digest of the scan; absent in records written before options were part of it
+    #[serde(default)]
+    pub cache_key: Option<String>,
+    pub response: AnalysisResponse,
+}
+
+// Dashboard grouping of a stored scan's summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanVerdict {
+    Clean,
+    // Steganography detected on one or two indicators
+    Suspicious,
+    // Steganography detected with high confidence, or a payload recovered
+    LikelyStego,
+}
+
+impl ScanVerdict {
+    pub fn of(summary: &AnalysisSummary) -> Self {
+        match (
+            summary.steganography_detected,
+            summary.confidence_level.as_str(),
+        ) {
+            (false, _) => ScanVerdict::Clean,
+            (true, "high" | "confirmed") => ScanVerdict::LikelyStego,
+            (true, _) => ScanVerdict::Suspicious,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub id: String,
+    pub filename: String,
+    pub detected_type: String,
+    pub steganography_detected: bool,
+    pub confidence_level: String,
+    pub verdict: ScanVerdict,
+    pub timestamp: String,
+}
+
+impl From<&ScanRecord> for ScanSummary {
+    fn from(record: &ScanRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            filename: record.filename.clone(),
+            detected_type: record.response.file_info.detected_type.clone(),
+            steganography_detected: record.response.summary.steganography_detected,
+            confidence_level: record.response.summary.confidence_level.clone(),
+            verdict: ScanVerdict::of(&record.response.summary),
+            timestamp: record.response.timestamp.clone(),
+        }
+    }
+}
+
+// What a stored scan can be reused for: the same content scanned with the same options. The
+// options are whatever changes the report, written out by the caller in a canonical form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    // SHA-256 of the uploaded file, hex encoded
+    pub sha256: String,
+    pub options: String,
+}
+
+impl CacheKey {
+    // Hex SHA-256 of both parts, naming the key's index entry
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.sha256.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.options.as_bytes());
+        shared::hex(&hasher.finalize())
+    }
+}
+
+// Completed scans, stored per tenant as `<root>/<tenant>/<id>.json`
+pub struct ScanHistory {
+    root: PathBuf,
+    // Records are read, changed and written back; one lock per tenant keeps concurrent
+    // annotations and deletes from overwriting each other
+    locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl ScanHistory {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // STEGASCAN_HISTORY_DIR overrides the default location
+    pub fn from_env() -> Self {
+        let root = std::env::var("STEGASCAN_HISTORY_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir().join("stegascan-history"));
+
+        Self::new(root)
+    }
+
+    fn tenant_dir(&self, tenant: &Tenant) -> PathBuf {
+        self.root.join(tenant.as_str())
+    }
+
+    fn tenant_lock(&self, tenant: &Tenant) -> Arc<Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        locks
+            .entry(tenant.as_str().to_string())
+            .or_default()
+            .clone()
+    }
+
+    pub fn record(
+        &self,
+        tenant: &Tenant,
+        api_key: Option<&str>,
+        filename: &str,
+        key: Option<&CacheKey>,
+        response: &AnalysisResponse,
+        usage: &ResourceUsage,
+    ) -> Result<String, ApiError> {
+        let dir = self.tenant_dir(tenant);
+        std::fs::create_dir_all(&dir)?;
+
+        // Handlers assign the ID up front when carved artifacts have to refer to the scan
+        let record = ScanRecord {
+            id: response
+                .scan_id
+                .clone()
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            tenant: tenant.as_str().to_string(),
+            filename: filename.to_string(),
+            api_key: api_key.map(str::to_string),
+            usage: usage.clone(),
+            sha256: key.map(|key| key.sha256.clone()),
+            cache_key: key.map(CacheKey::digest),
+            response: response.clone(),
+        };
+        let json = serde_json::to_string_pretty(&record)
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        std::fs::write(dir.join(format!("{}.json", record.id)), json)?;
+
+        // The latest scan of each cache key, so repeated uploads are found without reading
+        // every record
+        if let Some(path) = record
+            .cache_key
+            .as_deref()
+            .and_then(|digest| self.cache_index_path(tenant, digest))
+        {
+            std::fs::create_dir_all(self.tenant_dir(tenant).join(CACHE_INDEX_DIR))?;
+            std::fs::write(path, &record.id)?;
+        }
+
+        Ok(record.id)
+    }
+
+    pub fn load(&self, tenant: &Tenant, id: &str) -> Result<ScanRecord, ApiError> {
+        // IDs are UUIDs; refuse anything else so the ID can't escape the tenant directory
+        let id = uuid::Uuid::parse_str(id)
+            .map_err(|_| ApiError::ScanNotFound(id.to_string()))?
+            .to_string();
+
+        let json = std::fs::read_to_string(self.tenant_dir(tenant).join(format!("{}.json", id)))
+            .map_err(|_| ApiError::ScanNotFound(id.clone()))?;
+        serde_json::from_str(&json).map_err(|_| ApiError::ScanNotFound(id))
+    }
+
+    // The latest stored scan with the given cache key
+    pub fn find_cached(&self, tenant: &Tenant, key: &CacheKey) -> Option<ScanRecord> {
+        let path = self.cache_index_path(tenant, &key.digest())?;
+        let id = std::fs::read_to_string(path).ok()?;
+        // The record may have been deleted since
+        self.load(tenant, id.trim()).ok()
+    }
+
+    // `<root>/<tenant>/cache/<digest>` holds the scan ID; only well-formed digests get a path
+    fn cache_index_path(&self, tenant: &Tenant, digest: &str) -> Option<PathBuf> {
+        let well_formed = digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit());
+        well_formed.then(|| {
+            self.tenant_dir(tenant)
+                .join(CACHE_INDEX_DIR)
+                .join(digest.to_ascii_lowercase())
+        })
+    }
+
+    // Newest first, optionally only scans with the given verdict
+    pub fn list(
+        &self,
+        tenant: &Tenant,
+        verdict: Option<ScanVerdict>,
+    ) -> Result<Vec<ScanSummary>, ApiError> {
+        let mut scans: Vec<ScanSummary> = self
+            .records(tenant)?
+            .iter()
+            .map(ScanSummary::from)
+            .filter(|scan| verdict.is_none_or(|verdict| scan.verdict == verdict))
+            .collect();
+        scans.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+        Ok(scans)
+    }
+
+    // Remove a stored scan, returning the record so the caller can remove its artifacts
+    pub fn delete(&self, tenant: &Tenant, id: &str) -> Result<ScanRecord, ApiError> {
+        let lock = self.tenant_lock(tenant);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let record = self.load(tenant, id)?;
+        if let Err(e) = std::fs::remove_file(self.record_path(tenant, &record.id)) {
+            // Removed concurrently, e.g. by a retention pass
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return Err(ApiError::ScanNotFound(record.id));
+            }
+            return Err(e.into());
+        }
+
+        // Drop the cache index entry unless a later scan with the same key took it over
+        if let Some(path) = record
+            .cache_key
+            .as_deref()
+            .and_then(|digest| self.cache_index_path(tenant, digest))
+        {
+            if std::fs::read_to_string(&path).is_ok_and(|id| id.trim() == record.id) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+
+        Ok(record)
+    }
+
+    // Add an analyst's annotation to a stored scan, returning all of the scan's annotations.
+    // An annotated rule has to be one the scan fired, so the stats can count it.
+    pub fn annotate(
+        &self,
+        tenant: &Tenant,
+        id: &str,
+        annotation: Annotation,
+    ) -> Result<Vec<Annotation>, ApiError> {
+        if !annotation.is_complete() {
+            return Err(ApiError::InvalidAnnotation(
+                "a comment annotation needs a comment".to_string(),
+            ));
+        }
+        let lock = self.tenant_lock(tenant);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut record = self.load(tenant, id)?;
+        if let Some(rule) = &annotation.rule {
+            let fired = stats::fired_rules(&record.response);
+            if !fired.contains(rule) {
+                return Err(ApiError::InvalidAnnotation(format!(
+                    "rule {} did not fire on scan {} (fired: {})",
+                    rule,
+                    record.id,
+                    if fired.is_empty() {
+                        "none".to_string()
+                    } else {
+                        fired.join(", ")
+                    }
+                )));
+            }
+        }
+
+        record.response.annotations.push(annotation);
+        let json = serde_json::to_string_pretty(&record)
+            .map_err(|e| ApiError::AnalysisFailed(e.to_string()))?;
+        // Written beside the record and renamed over it, so readers never see half a record
+        let path = self.record_path(tenant, &record.id);
+        let staged = path.with_extension("json.tmp");
+        std::fs::write(&staged, json)?;
+        // Removed by something other than this store since it was loaded
+        if !path.exists() {
+            let _ = std::fs::remove_file(&staged);
+            return Err(ApiError::ScanNotFound(record.id));
+        }
+        std::fs::rename(&staged, &path)?;
+        Ok(record.response.annotations)
+    }
+
+    // Bytes the stored record takes on disk
+    pub fn record_bytes(&self, tenant: &Tenant, id: &str) -> u64 {
+        std::fs::metadata(self.record_path(tenant, id)).map_or(0, |m| m.len())
+    }
+
+    fn record_path(&self, tenant: &Tenant, id: &str) -> PathBuf {
+        self.tenant_dir(tenant).join(format!("{}.json", id))
+    }
+
+    // Tenants that have stored scans
+    pub fn tenants(&self) -> Result<Vec<Tenant>, ApiError> {
+        tenant_dirs(&self.root)
+    }
+
+    // Every readable record of the tenant, in no particular order
+    pub fn records(&self, tenant: &Tenant) -> Result<Vec<ScanRecord>, ApiError> {
+        let dir = self.tenant_dir(tenant);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Ok(json) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Ok(record) = serde_json::from_str::<ScanRecord>(&json) {
+                records.push(record);
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+// Subdirectories of a store root named like tenants
+pub(crate) fn tenant_dirs(root: &Path) -> Result<Vec<Tenant>, ApiError> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut tenants = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            if is_valid_tenant_id(name) {
+                tenants.push(Tenant(name.to_string()));
+            }
+        }
+    }
+
+    Ok(tenants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        AnalysisResponse, AnalysisSummary, AnnotationLabel, FileInfo, FormatSpecificAnalysis,
+        SummaryVerdict,
+    };
+
+    fn response() -> AnalysisResponse {
+        AnalysisResponse {
+            scan_id: None,
+            cached: false,
+            file_info: FileInfo {
+                path: "upload".to_string(),
+                size_bytes: 1024,
+                detected_type: "image/png".to_string(),
+                extension: None,
+            },
+            magic_bytes_analysis: None,
+            carved_payloads: Vec::new(),
+            slack_space: None,
+            deflate_streams: None,
+            format_specific_analysis: FormatSpecificAnalysis::Unknown,
+            plugins: Vec::new(),
+            timestamp: "2026-03-01T10:00:00+00:00".to_string(),
+            summary: AnalysisSummary {
+                steganography_detected: false,
+                confidence_level: "low".to_string(),
+                threat_indicators: Vec::new(),
+                recommendations: Vec::new(),
+                verdict: SummaryVerdict::Clean,
+                extracted_payloads: Vec::new(),
+            },
+            annotations: Vec::new(),
+            run: None,
+        }
+    }
+
+    fn comment(text: &str) -> Annotation {
+        Annotation {
+            rule: None,
+            label: AnnotationLabel::Comment,
+            comment: Some(text.to_string()),
+            analyst: None,
+            added: "2026-03-02T08:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_concurrent_annotations_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ScanHistory::new(dir.path().to_path_buf());
+        let tenant = Tenant("red".to_string());
+        let id = history
+            .record(
+                &tenant,
+                None,
+                "cover.png",
+                None,
+                &response(),
+                &ResourceUsage::default(),
+            )
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for index in 0..8 {
+                let (history, tenant, id) = (&history, &tenant, &id);
+                scope.spawn(move || {
+                    history
+                        .annotate(tenant, id, comment(&format!("note {}", index)))
+                        .unwrap();
+                });
+            }
+        });
+
+        let record = history.load(&tenant, &id).unwrap();
+        assert_eq!(record.response.annotations.len(), 8);
+        // Only the record itself is left in the tenant directory
+        let files = std::fs::read_dir(dir.path().join("red")).unwrap().count();
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn test_annotating_deleted_scan_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ScanHistory::new(dir.path().to_path_buf());
+        let tenant = Tenant("red".to_string());
+        let id = history
+            .record(
+                &tenant,
+                None,
+                "cover.png",
+                None,
+                &response(),
+                &ResourceUsage::default(),
+            )
+            .unwrap();
+        history.delete(&tenant, &id).unwrap();
+
+        let result = history.annotate(&tenant, &id, comment("too late"));
+        assert!(matches!(result, Err(ApiError::ScanNotFound(_))));
+        assert!(history.records(&tenant).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::ApiError;
+use crate::events::ScanEvent;
+use crate::models::AnalysisResponse;
+use crate::tenant::Tenant;
+
+// Scans accepted by POST /api/scan run in the background, and clients poll their job for
+// progress and the report. Jobs are only kept in memory, so a restart forgets them; the reports
+// of completed ones are still in scan history. A finished job is forgotten once it has been
+// kept for the retention period.
+
+const DEFAULT_RETENTION_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobProgress {
+    // Report sections finished so far, named as the /api/scan/stream events
+    pub sections: Vec<String>,
+    // Video or animated WebP frames analyzed so far
+    pub frames_analyzed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub job_id: String,
+    pub state: JobState,
+    pub filename: String,
+    pub created: String,
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub progress: JobProgress,
+    // The scan history record of a completed job
+    pub scan_id: Option<String>,
+    pub error: Option<String>,
+}
+
+struct Job {
+    tenant: Tenant,
+    status: JobStatus,
+    result: Option<AnalysisResponse>,
+    finished_at: Option<Instant>,
+}
+
+pub struct JobStore {
+    retention: Duration,
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl JobStore {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // STEGASCAN_JOB_RETENTION_SECS overrides how long finished jobs are kept
+    pub fn from_env() -> Self {
+        Self::new(Duration::from_secs(
+            std::env::var("STEGASCAN_JOB_RETENTION_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_SECS),
+        ))
+    }
+
+    pub fn create(&self, tenant: &Tenant, filename: &str, now: Instant) -> JobStatus {
+        let status = JobStatus {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            state: JobState::Queued,
+            filename: filename.to_string(),
+            created: chrono::Utc::now().to_rfc3339(),
+            started: None,
+            finished: None,
+            progress: JobProgress::default(),
+            scan_id: None,
+            error: None,
+        };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs, now);
+        jobs.insert(
+            status.job_id.clone(),
+            Job {
+                tenant: tenant.clone(),
+                status: status.clone(),
+                result: None,
+                finished_at: None,
+            },
+        );
+        status
+    }
+
+    pub fn start(&self, id: &str) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            job.status.state = JobState::Running;
+            job.status.started = Some(chrono::Utc::now().to_rfc3339());
+        }
+    }
+
+    // Count an event of the running scan towards its progress
+    pub fn record_event(&self, id: &str, event: &ScanEvent) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            match event.event {
+                "frame" => job.status.progress.frames_analyzed += 1,
+                section => job.status.progress.sections.push(section.to_string()),
+            }
+        }
+    }
+
+    pub fn finish(&self, id: &str, outcome: Result<AnalysisResponse, String>, now: Instant) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(id) {
+            job.status.finished = Some(chrono::Utc::now().to_rfc3339());
+            job.finished_at = Some(now);
+            match outcome {
+                Ok(result) => {
+                    job.status.state = JobState::Completed;
+                    job.status.scan_id = result.scan_id.clone();
+                    job.result = Some(result);
+                }
+                Err(error) => {
+                    job.status.state = JobState::Failed;
+                    job.status.error = Some(error);
+                }
+            }
+        }
+        self.prune(&mut jobs, now);
+    }
+
+    pub fn status(&self, tenant: &Tenant, id: &str) -> Result<JobStatus, ApiError> {
+        let jobs = self.jobs.lock().unwrap();
+        Ok(Self::find(&jobs, tenant, id)?.status.clone())
+    }
+
+    // The report of a completed job; a failed job answers with its error
+    pub fn result(&self, tenant: &Tenant, id: &str) -> Result<AnalysisResponse, ApiError> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = Self::find(&jobs, tenant, id)?;
+        match (&job.result, &job.status.error) {
+            (Some(result), _) => Ok(result.clone()),
+            (None, Some(error)) => Err(ApiError::AnalysisFailed(error.clone())),
+            (None, None) => Err(ApiError::JobNotFinished(id.to_string())),
+        }
+    }
+
+    // Other tenants' jobs are reported as missing, like their scans
+    fn find<'a>(
+        jobs: &'a HashMap<String, Job>,
+        tenant: &Tenant,
+        id: &str,
+    ) -> Result<&'a Job, ApiError> {
+        jobs.get(id)
+            .filter(|job| job.tenant == *tenant)
+            .ok_or_else(|| ApiError::JobNotFound(id.to_string()))
+    }
+
+    fn prune(&self, jobs: &mut HashMap<String, Job>, now: Instant) {
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished| now.saturating_duration_since(finished) < self.retention)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle_and_retention() {
+        let store = JobStore::new(Duration::from_secs(60));
+        let tenant = Tenant("acme".to_string());
+        let now = Instant::now();
+
+        let job = store.create(&tenant, "video.mp4", now);
+        assert_eq!(job.state, JobState::Queued);
+        assert!(matches!(
+            store.result(&tenant, &job.job_id),
+            Err(ApiError::JobNotFinished(_))
+        ));
+        assert!(matches!(
+            store.status(&Tenant("other".to_string()), &job.job_id),
+            Err(ApiError::JobNotFound(_))
+        ));
+
+        store.start(&job.job_id);
+        store.record_event(&job.job_id, &ScanEvent::new("file_info", &()));
+        store.record_event(&job.job_id, &ScanEvent::new("frame", &()));
+        store.record_event(&job.job_id, &ScanEvent::new("frame", &()));
+        let running = store.status(&tenant, &job.job_id).unwrap();
+        assert_eq!(running.state, JobState::Running);
+        assert_eq!(running.progress.sections, vec!["file_info"]);
+        assert_eq!(running.progress.frames_analyzed, 2);
+
+        store.finish(&job.job_id, Err("decoder crashed".to_string()), now);
+        let failed = store.status(&tenant, &job.job_id).unwrap();
+        assert_eq!(failed.state, JobState::Failed);
+        assert!(matches!(
+            store.result(&tenant, &job.job_id),
+            Err(ApiError::AnalysisFailed(e)) if e == "decoder crashed"
+        ));
+
+        // Running jobs are kept however old they are, finished ones for the retention period
+        let running = store.create(&tenant, "long.mp4", now);
+        store.create(&tenant, "later.png", now + Duration::from_secs(120));
+        assert!(store.status(&tenant, &job.job_id).is_err());
+        assert!(store.status(&tenant, &running.job_id).is_ok());
+    }
+}
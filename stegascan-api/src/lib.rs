@@ -1,7 +1,18 @@
 pub mod analysis;
+pub mod artifacts;
 pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod history;
+pub mod jobs;
 pub mod models;
+pub mod rate_limit;
+pub mod retention;
+pub mod state;
+pub mod stats;
+pub mod tenant;
+pub mod uploads;
+pub mod usage;
 
 // Re-export key types
 pub use error::ApiError;
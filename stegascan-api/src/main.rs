@@ -1,6 +1,8 @@
 use axum::{
     Router,
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
 };
 use std::net::SocketAddr;
 use tower_http::cors::{Any, CorsLayer};
@@ -8,11 +10,27 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod analysis;
+mod artifacts;
 mod error;
+mod events;
 mod handlers;
+mod history;
+mod jobs;
 mod models;
+mod rate_limit;
+mod retention;
+mod state;
+mod stats;
+mod tenant;
+mod uploads;
+mod usage;
 
 use handlers::*;
+use retention::RetentionPolicy;
+use state::AppState;
+
+// Room for the form fields other than the file
+const FORM_FIELDS_BYTES: u64 = 1024 * 1024;
 
 #[tokio::main]
 async fn main() {
@@ -25,34 +43,97 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Build routes
+    let state = AppState::from_env();
+
+    uploads::spawn_sweeps(state.uploads.clone());
+
+    let retention = RetentionPolicy::from_env();
+    if retention.is_enabled() {
+        retention::spawn(state.clone(), retention);
+    }
+
+    // Build routes; everything under /api is rate limited
+    let api = Router::new()
+        .route("/api/scan", post(scan_file))
+        .route("/api/jobs/:id/status", get(get_job_status))
+        .route("/api/jobs/:id/result", get(get_job_result))
+        .route("/api/scan/stream", post(scan_file_stream))
+        .route("/api/scan/verdict", post(scan_verdict))
+        .route("/api/analyze/:analyzer", post(analyze_with))
+        .route("/api/artifacts/:id", get(download_artifact))
+        .route("/api/scan/:id", delete(delete_scan))
+        .route("/api/scans", get(list_scans))
+        .route("/api/scans/:id", get(get_scan))
+        .route("/api/scans/:id/annotations", post(annotate_scan))
+        .route("/api/stats", get(get_stats))
+        .route("/api/usage", get(get_usage))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::enforce,
+        ));
+
+    // Uploads are streamed to disk and held to their own limit, so the body limit only has to
+    // leave room for them and the other form fields
+    let body_limit = match state.uploads.max_bytes() {
+        0 => DefaultBodyLimit::disable(),
+        max => DefaultBodyLimit::max(
+            usize::try_from(max.saturating_add(FORM_FIELDS_BYTES)).unwrap_or(usize::MAX),
+        ),
+    };
+
     let app = Router::new()
         .route("/", get(root))
-        .route("/api/scan", post(scan_file))
+        .merge(api)
+        .layer(body_limit)
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
                 .allow_headers(Any),
         )
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http())
+        .with_state(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    // STEGASCAN_API_ADDR pins the address; otherwise port 3000 is tried, then 3001
+    let pinned: Option<SocketAddr> = std::env::var("STEGASCAN_API_ADDR").ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid STEGASCAN_API_ADDR {:?}: {}", value, e))
+    });
+    let addr = pinned.unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 3000)));
     tracing::info!("🚀 Stegascan API Server");
-    tracing::info!("📖 Endpoint: POST /api/scan - Upload file and get analysis");
+    tracing::info!("📖 Endpoint: POST /api/scan - Upload file and queue its analysis");
+    tracing::info!("📖 Endpoint: GET /api/jobs/:id/{status,result} - Scan job progress and report");
+    tracing::info!("📖 Endpoint: POST /api/scan/stream - Upload file and stream findings (SSE)");
+    tracing::info!("📖 Endpoint: POST /api/scan/verdict - Fast scan, verdict only");
+    tracing::info!("📖 Endpoint: POST /api/analyze/:analyzer - Run a single analyzer");
+    tracing::info!("📖 Endpoint: GET /api/artifacts/:id - Download a carved payload");
+    tracing::info!("📖 Endpoint: GET /api/scans[/:id] - Tenant scan history");
+    tracing::info!("📖 Endpoint: DELETE /api/scan/:id - Delete a scan and its artifacts");
+    tracing::info!(
+        "📖 Endpoint: POST /api/scans/:id/annotations - Label a finding of a stored scan"
+    );
+    tracing::info!("📖 Endpoint: GET /api/stats - Tenant detection statistics");
+    tracing::info!("📖 Endpoint: GET /api/usage - Tenant resource usage per API key");
 
     let listener = match tokio::net::TcpListener::bind(addr).await {
         Ok(listener) => listener,
-        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && pinned.is_none() => {
             tracing::warn!("Port 3000 in use, trying port 3001...");
             let addr = SocketAddr::from(([0, 0, 0, 0], 3001));
             tokio::net::TcpListener::bind(addr).await.unwrap_or_else(|e| {
-                panic!("Failed to bind to port 3001: {}. Please free up ports 3000-3001 or specify PORT env var", e);
+                panic!("Failed to bind to port 3001: {}. Please free up ports 3000-3001 or set STEGASCAN_API_ADDR", e);
             })
         }
-        Err(e) => panic!("Failed to bind to port: {}", e),
+        Err(e) => panic!("Failed to bind to {}: {}", addr, e),
     };
 
     tracing::info!("✅ Server ready on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    // Client addresses are needed for per-IP rate limiting
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
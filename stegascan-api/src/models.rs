@@ -1,152 +1,119 @@
 use serde::{Deserialize, Serialize};
 
+pub use reports::*;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResponse {
+    // ID of the stored scan in the tenant's history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_id: Option<String>,
+    // Set when the report is a stored scan of identical content rather than a fresh analysis
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
     pub file_info: FileInfo,
     pub magic_bytes_analysis: Option<MagicBytesReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub carved_payloads: Vec<CarvedPayloadInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_space: Option<SlackSpaceReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deflate_streams: Option<DeflateStreamsReport>,
     pub format_specific_analysis: FormatSpecificAnalysis,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginReport>,
     pub timestamp: String,
     pub summary: AnalysisSummary,
+    // Added to the stored scan through POST /api/scans/:id/annotations
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    // Analyzer versions and parameters; unset in scans stored before they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunMetadata>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FileInfo {
-    pub path: String,
-    pub size_bytes: u64,
-    pub detected_type: String,
-    pub extension: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MagicBytesReport {
-    pub primary_format: String,
-    pub expected_format: Option<String>,
-    pub total_signatures_found: usize,
-    pub has_multiple_formats: bool,
-    pub has_suspicious_data: bool,
-    pub format_summary: FormatSummary,
-    pub embedded_files: Vec<EmbeddedFileInfo>,
-    pub suspicious_findings: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FormatSummary {
-    pub images: usize,
-    pub audio: usize,
-    pub video: usize,
-    pub text_documents: usize,
-    pub archives: usize,
-    pub executables: usize,
-    pub other: usize,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmbeddedFileInfo {
+pub struct CarvedPayloadInfo {
     pub offset: usize,
     pub offset_hex: String,
+    pub size_bytes: usize,
     pub description: String,
     pub file_type: String,
+    // Set when the payload is returned inline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_base64: Option<String>,
+    // Set when the payload is stored for download instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    // Other stored scans that carved the identical payload
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_carved_from: Vec<String>,
+}
+
+// Body of POST /api/scans/:id/annotations. The rule is one of the scan's fired rules as
+// /api/stats names them; without it the annotation is about the scan as a whole.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationRequest {
+    #[serde(default)]
+    pub rule: Option<String>,
+    pub label: AnnotationLabel,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub analyst: Option<String>,
+}
+
+// Answer of /api/scan/verdict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerdictResponse {
+    pub verdict: Verdict,
+    // The summary's confidence_level: "low", "medium", "high", or "confirmed" when a payload
+    // was recovered
     pub confidence: String,
+    pub top_indicators: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum FormatSpecificAnalysis {
-    Image(ImageAnalysis),
-    Audio(AudioAnalysis),
-    Video(VideoAnalysis),
-    Text(TextAnalysis),
-    Unknown,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ImageAnalysis {
-    pub exif_metadata: Option<ExifReport>,
-    pub lsb_analysis: Option<LsbReport>,
-    pub dimensions: ImageDimensions,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ImageDimensions {
-    pub width: u32,
-    pub height: u32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExifReport {
-    pub fields_found: usize,
-    pub has_thumbnail: bool,
-    pub thumbnail_size_bytes: Option<usize>,
-    pub comment_fields: Vec<String>,
-    pub suspicious_fields: Vec<String>,
-    pub metadata: Vec<MetadataField>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MetadataField {
-    pub key: String,
-    pub value: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LsbReport {
-    pub is_suspicious: bool,
-    pub channels: Vec<LsbChannelAnalysis>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LsbChannelAnalysis {
-    pub channel_name: String,
-    pub chi_square_score: f64,
-    pub entropy_score: f64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AudioAnalysis {
-    pub sample_count: usize,
-    pub id3_analysis: Option<Id3Report>,
-    pub spectrogram_analysis: Option<SpectrogramReport>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Id3Report {
-    pub title: Option<String>,
-    pub artist: Option<String>,
-    pub album: Option<String>,
-    pub year: Option<i32>,
-    pub comments_count: usize,
-    pub pictures_count: usize,
-    pub private_frames_count: usize,
-    pub suspicious_frames: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SpectrogramReport {
-    pub high_frequency_energy: f64,
-    pub hidden_message_detected: bool,
-    pub suspicious_patterns: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VideoAnalysis {
-    pub frames_processed: usize,
-    pub errors_encountered: usize,
-    pub suspicious_frames: Vec<usize>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    // The scan ran out of its time budget
+    Inconclusive,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TextAnalysis {
-    pub file_type: String,
-    pub line_count: usize,
-    pub word_count: usize,
-    pub character_count: usize,
-    pub size_bytes: usize,
+pub struct SingleAnalyzerResponse {
+    pub analyzer: String,
+    pub file_info: FileInfo,
+    pub result: AnalyzerSection,
+    pub timestamp: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AnalysisSummary {
-    pub steganography_detected: bool,
-    pub confidence_level: String,
-    pub threat_indicators: Vec<String>,
-    pub recommendations: Vec<String>,
+#[serde(untagged)]
+pub enum AnalyzerSection {
+    MagicBytes(MagicBytesReport),
+    SlackSpace(SlackSpaceReport),
+    DeflateStreams(DeflateStreamsReport),
+    Exif(ExifReport),
+    Lsb(LsbReport),
+    Ws(WsReport),
+    ChannelCorrelation(ChannelCorrelationReport),
+    AdaptiveLsb(AdaptiveLsbReport),
+    Bpcs(BpcsReport),
+    Calibration(CalibrationReport),
+    Webp(WebpReport),
+    Gif(GifReport),
+    PngText(PngTextReport),
+    Pages(PagesReport),
+    Id3(Id3Report),
+    Spectrogram(SpectrogramReport),
+    Ultrasonic(UltrasonicReport),
+    Demodulation(DemodulationReport),
+    Phase(PhaseReport),
+    Video(VideoAnalysis),
+    Text(Box<TextAnalysis>),
+    Plugins(Vec<PluginReport>),
 }
@@ -0,0 +1,337 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::ApiError;
+use crate::state::AppState;
+use crate::tenant::TenantDirectory;
+
+// Header carrying the client's API key; clients without a known one are limited per IP
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// How often idle clients are swept from the limiter
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    // Sustained requests per minute per client
+    pub requests_per_minute: u32,
+    // Requests a client may make back to back before the per-minute rate applies
+    pub burst: u32,
+    // Requests per client per UTC day, 0 for unlimited
+    pub daily_quota: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 30,
+            burst: 10,
+            daily_quota: 1000,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    // STEGASCAN_RATE_LIMIT_PER_MINUTE, STEGASCAN_RATE_LIMIT_BURST and
+    // STEGASCAN_DAILY_QUOTA override the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            requests_per_minute: read(
+                "STEGASCAN_RATE_LIMIT_PER_MINUTE",
+                defaults.requests_per_minute,
+            ),
+            burst: read("STEGASCAN_RATE_LIMIT_BURST", defaults.burst).max(1),
+            daily_quota: read("STEGASCAN_DAILY_QUOTA", defaults.daily_quota),
+        }
+    }
+}
+
+struct ClientUsage {
+    tokens: f64,
+    last_refill: Instant,
+    day: NaiveDate,
+    requests_today: u32,
+}
+
+struct Clients {
+    usage: HashMap<String, ClientUsage>,
+    last_sweep: Instant,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    clients: Mutex<Clients>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            clients: Mutex::new(Clients {
+                usage: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    // Take one request from the client's bucket, or report how long until it may retry. The
+    // bucket runs on `now`; the daily quota, and when it next resets, on the wall clock
+    pub fn check(&self, client: &str, now: Instant, wall: DateTime<Utc>) -> Result<(), ApiError> {
+        let today = wall.date_naive();
+        let mut clients = self.clients.lock().unwrap();
+        if now.saturating_duration_since(clients.last_sweep) >= SWEEP_INTERVAL {
+            clients.last_sweep = now;
+            clients
+                .usage
+                .retain(|_, usage| !self.is_idle(usage, now, today));
+        }
+        let usage = clients
+            .usage
+            .entry(client.to_string())
+            .or_insert_with(|| ClientUsage {
+                tokens: self.config.burst as f64,
+                last_refill: now,
+                day: today,
+                requests_today: 0,
+            });
+
+        if usage.day != today {
+            usage.day = today;
+            usage.requests_today = 0;
+        }
+
+        if self.config.daily_quota > 0 && usage.requests_today >= self.config.daily_quota {
+            let midnight = today
+                .succ_opt()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|t| t.and_utc());
+            let retry_after = midnight
+                .map(|t| (t - wall).num_seconds().max(1) as u64)
+                .unwrap_or(86400);
+            return Err(ApiError::QuotaExceeded { retry_after });
+        }
+
+        if self.config.requests_per_minute > 0 {
+            let refill_per_sec = self.config.requests_per_minute as f64 / 60.0;
+            let elapsed = now.duration_since(usage.last_refill).as_secs_f64();
+            usage.tokens = (usage.tokens + elapsed * refill_per_sec).min(self.config.burst as f64);
+            usage.last_refill = now;
+
+            if usage.tokens < 1.0 {
+                let wait = Duration::from_secs_f64((1.0 - usage.tokens) / refill_per_sec);
+                return Err(ApiError::RateLimited {
+                    retry_after: wait.as_secs().max(1),
+                });
+            }
+            usage.tokens -= 1.0;
+        }
+
+        usage.requests_today += 1;
+        Ok(())
+    }
+
+    // A client whose bucket has refilled and whose count for today is spent or stale is
+    // no different from one never seen, so it can be forgotten
+    fn is_idle(&self, usage: &ClientUsage, now: Instant, today: NaiveDate) -> bool {
+        let refilled = self.config.requests_per_minute == 0
+            || usage.tokens
+                + now
+                    .saturating_duration_since(usage.last_refill)
+                    .as_secs_f64()
+                    * self.config.requests_per_minute as f64
+                    / 60.0
+                >= self.config.burst as f64;
+        refilled && (self.config.daily_quota == 0 || usage.day != today)
+    }
+
+    #[cfg(test)]
+    fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().usage.len()
+    }
+}
+
+// Only keys the tenant directory knows get their own bucket; anyone can make up a key, so
+// every other request is counted against its IP address
+fn client_key(request: &Request, tenants: &TenantDirectory) -> String {
+    if let Some(key) = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|key| tenants.knows_key(key))
+    {
+        return format!("key:{}", key);
+    }
+
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+        .unwrap_or_else(|| "ip:unknown".to_string())
+}
+
+pub async fn enforce(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let client = client_key(&request, &state.tenants);
+
+    if let Err(e) = state
+        .rate_limiter
+        .check(&client, Instant::now(), Utc::now())
+    {
+        tracing::warn!("Rejecting request from {}: {}", client, e);
+        return Err(e);
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn noon() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_burst_then_limit_then_refill() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 2,
+            daily_quota: 0,
+        });
+        let start = Instant::now();
+        let wall = noon();
+
+        assert!(limiter.check("ip:1.2.3.4", start, wall).is_ok());
+        assert!(limiter.check("ip:1.2.3.4", start, wall).is_ok());
+        assert!(matches!(
+            limiter.check("ip:1.2.3.4", start, wall),
+            Err(ApiError::RateLimited { .. })
+        ));
+
+        // Other clients have their own bucket
+        assert!(limiter.check("ip:5.6.7.8", start, wall).is_ok());
+
+        // One token back after a second at 60/min
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check("ip:1.2.3.4", later, wall).is_ok());
+    }
+
+    #[test]
+    fn test_daily_quota_resets() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 0,
+            burst: 1,
+            daily_quota: 1,
+        });
+        let now = Instant::now();
+        let wall = noon();
+
+        assert!(limiter.check("key:abc", now, wall).is_ok());
+        // Told to come back at the next midnight of the injected clock
+        assert!(matches!(
+            limiter.check("key:abc", now, wall),
+            Err(ApiError::QuotaExceeded { retry_after: 43200 })
+        ));
+        assert!(
+            limiter
+                .check("key:abc", now, wall + chrono::Duration::days(1))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_share_the_ip_bucket() {
+        let tenants = TenantDirectory::new(HashMap::from([(
+            "secret".to_string(),
+            "team-a".to_string(),
+        )]));
+        let request = |key: &str| {
+            let mut request = Request::builder()
+                .header(API_KEY_HEADER, key)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([1, 2, 3, 4], 5000))));
+            request
+        };
+
+        assert_eq!(client_key(&request("secret"), &tenants), "key:secret");
+        assert_eq!(client_key(&request("made-up-1"), &tenants), "ip:1.2.3.4");
+        assert_eq!(client_key(&request("made-up-2"), &tenants), "ip:1.2.3.4");
+        // Without a key mapping every client is tracked by IP
+        assert_eq!(
+            client_key(&request("secret"), &TenantDirectory::new(HashMap::new())),
+            "ip:1.2.3.4"
+        );
+    }
+
+    #[test]
+    fn test_idle_clients_are_swept() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 2,
+            daily_quota: 0,
+        });
+        let start = Instant::now();
+        let wall = noon();
+
+        for i in 0..100 {
+            limiter
+                .check(&format!("ip:10.0.0.{}", i), start, wall)
+                .unwrap();
+        }
+        assert_eq!(limiter.client_count(), 100);
+
+        // Their buckets refilled long before the next sweep
+        limiter
+            .check("ip:1.2.3.4", start + SWEEP_INTERVAL, wall)
+            .unwrap();
+        assert_eq!(limiter.client_count(), 1);
+    }
+
+    #[test]
+    fn test_quota_outlives_the_bucket() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 1,
+            daily_quota: 1,
+        });
+        let start = Instant::now();
+        let wall = noon();
+
+        limiter.check("ip:1.2.3.4", start, wall).unwrap();
+        // A refilled bucket is kept while it still counts towards wall's quota
+        assert!(matches!(
+            limiter.check("ip:1.2.3.4", start + SWEEP_INTERVAL, wall),
+            Err(ApiError::QuotaExceeded { .. })
+        ));
+        let tomorrow = wall + chrono::Duration::days(1);
+        limiter
+            .check("ip:5.6.7.8", start + SWEEP_INTERVAL * 2, tomorrow)
+            .unwrap();
+        assert_eq!(limiter.client_count(), 1);
+    }
+}
@@ -0,0 +1,544 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::artifacts::ArtifactStore;
+use crate::error::ApiError;
+use crate::history::ScanHistory;
+use crate::state::AppState;
+use crate::tenant::Tenant;
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+// A scan stores its artifacts before its record, so younger unreferenced artifacts may belong to
+// a scan still running
+const ORPHAN_GRACE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    // Scans and artifacts older than this are removed
+    pub max_age: Option<Duration>,
+    // Oldest scans and artifacts are removed until the stores fit
+    pub max_disk_bytes: Option<u64>,
+    // Time between pruning passes
+    pub interval: Duration,
+}
+
+impl RetentionPolicy {
+    // STEGASCAN_RETENTION_MAX_AGE_HOURS and STEGASCAN_RETENTION_MAX_DISK_MB set the limits;
+    // without either nothing is pruned
+    pub fn from_env() -> Self {
+        fn var(name: &str) -> Option<u64> {
+            std::env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        Self {
+            max_age: var("STEGASCAN_RETENTION_MAX_AGE_HOURS")
+                .map(|hours| Duration::from_secs(hours * 3600)),
+            max_disk_bytes: var("STEGASCAN_RETENTION_MAX_DISK_MB").map(|mb| mb * 1024 * 1024),
+            interval: Duration::from_secs(
+                var("STEGASCAN_RETENTION_INTERVAL_SECS")
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(DEFAULT_INTERVAL_SECS),
+            ),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.max_disk_bytes.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PruneReport {
+    pub scans_removed: u64,
+    pub artifacts_removed: u64,
+    pub bytes_freed: u64,
+}
+
+// A scan together with the artifacts it references, or an artifact no scan references. Units are
+// removed whole so a kept scan never links to a removed artifact; artifacts shared with other
+// scans stay until the last of them goes.
+struct Unit {
+    tenant: Tenant,
+    created: DateTime<Utc>,
+    scan: Option<String>,
+    artifacts: Vec<String>,
+    bytes: u64,
+}
+
+// Remove a scan and the artifacts only it references
+pub fn delete_scan(
+    history: &ScanHistory,
+    artifacts: &ArtifactStore,
+    tenant: &Tenant,
+    id: &str,
+) -> Result<PruneReport, ApiError> {
+    let bytes = history.record_bytes(tenant, id);
+    let record = history.delete(tenant, id)?;
+
+    let mut report = PruneReport {
+        scans_removed: 1,
+        artifacts_removed: 0,
+        bytes_freed: bytes,
+    };
+    for artifact in record
+        .response
+        .carved_payloads
+        .iter()
+        .filter_map(|payload| payload.artifact_id.as_deref())
+    {
+        let freed = artifacts.release(tenant, artifact, &record.id)?;
+        if freed > 0 {
+            report.artifacts_removed += 1;
+            report.bytes_freed += freed;
+        }
+    }
+
+    Ok(report)
+}
+
+// Remove what the policy no longer keeps: first everything past the maximum age, then the oldest
+// remaining until the stores fit the disk budget
+pub fn prune(
+    history: &ScanHistory,
+    artifacts: &ArtifactStore,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<PruneReport, ApiError> {
+    let mut units = collect_units(history, artifacts)?;
+    // Oldest first
+    units.sort_by_key(|unit| unit.created);
+
+    let age_cutoff = policy
+        .max_age
+        .and_then(|age| chrono::Duration::from_std(age).ok())
+        .map(|age| now - age);
+    let orphan_cutoff = now - chrono::Duration::from_std(ORPHAN_GRACE).unwrap_or_default();
+    let mut remaining: u64 = units.iter().map(|unit| unit.bytes).sum();
+
+    let mut report = PruneReport::default();
+    for unit in units {
+        if unit.scan.is_none() && unit.created > orphan_cutoff {
+            continue;
+        }
+        let expired = age_cutoff.is_some_and(|cutoff| unit.created < cutoff);
+        let over_budget = policy.max_disk_bytes.is_some_and(|max| remaining > max);
+        if !expired && !over_budget {
+            continue;
+        }
+
+        let removed = remove_unit(history, artifacts, &unit)?;
+        remaining = remaining.saturating_sub(unit.bytes);
+        report.scans_removed += removed.scans_removed;
+        report.artifacts_removed += removed.artifacts_removed;
+        report.bytes_freed += removed.bytes_freed;
+    }
+
+    Ok(report)
+}
+
+fn collect_units(history: &ScanHistory, artifacts: &ArtifactStore) -> Result<Vec<Unit>, ApiError> {
+    let mut tenants = history.tenants()?;
+    for tenant in artifacts.tenants()? {
+        if !tenants.contains(&tenant) {
+            tenants.push(tenant);
+        }
+    }
+
+    let mut units = Vec::new();
+    for tenant in tenants {
+        let mut referenced = HashSet::new();
+        // Shared artifacts count towards the disk usage of the first scan seen with them
+        for record in history.records(&tenant)? {
+            // Records with an unreadable timestamp are never pruned
+            let Some(created) = parse_timestamp(&record.response.timestamp) else {
+                continue;
+            };
+            let ids: Vec<String> = record
+                .response
+                .carved_payloads
+                .iter()
+                .filter_map(|payload| payload.artifact_id.clone())
+                .collect();
+            let bytes = history.record_bytes(&tenant, &record.id)
+                + ids
+                    .iter()
+                    .filter(|id| !referenced.contains(*id))
+                    .map(|id| artifacts.artifact_bytes(&tenant, id))
+                    .sum::<u64>();
+            referenced.extend(ids.iter().cloned());
+            units.push(Unit {
+                tenant: tenant.clone(),
+                created,
+                scan: Some(record.id),
+                artifacts: ids,
+                bytes,
+            });
+        }
+
+        for metadata in artifacts.list(&tenant)? {
+            if referenced.contains(&metadata.id) {
+                continue;
+            }
+            let Some(created) = parse_timestamp(&metadata.created_at) else {
+                continue;
+            };
+            units.push(Unit {
+                tenant: tenant.clone(),
+                created,
+                scan: None,
+                bytes: artifacts.artifact_bytes(&tenant, &metadata.id),
+                artifacts: vec![metadata.id],
+            });
+        }
+    }
+
+    Ok(units)
+}
+
+fn remove_unit(
+    history: &ScanHistory,
+    artifacts: &ArtifactStore,
+    unit: &Unit,
+) -> Result<PruneReport, ApiError> {
+    if let Some(ref id) = unit.scan {
+        return match delete_scan(history, artifacts, &unit.tenant, id) {
+            // Deleted through the API since the units were collected
+            Err(ApiError::ScanNotFound(_)) => Ok(PruneReport::default()),
+            result => result,
+        };
+    }
+
+    let mut report = PruneReport::default();
+    for id in &unit.artifacts {
+        let freed = artifacts.delete(&unit.tenant, id)?;
+        if freed > 0 {
+            report.artifacts_removed += 1;
+            report.bytes_freed += freed;
+        }
+    }
+    Ok(report)
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+// Prune on the policy's interval for as long as the server runs
+pub fn spawn(state: AppState, policy: RetentionPolicy) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(policy.interval);
+        loop {
+            interval.tick().await;
+
+            let history = state.history.clone();
+            let artifacts = state.artifacts.clone();
+            let pass_policy = policy.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                prune(&history, &artifacts, &pass_policy, Utc::now())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(report)) if report.scans_removed + report.artifacts_removed > 0 => {
+                    tracing::info!(
+                        "🧹 Retention removed {} scans and {} artifacts ({} bytes)",
+                        report.scans_removed,
+                        report.artifacts_removed,
+                        report.bytes_freed
+                    );
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Retention pass failed: {}", e),
+                Err(e) => tracing::warn!("Retention pass panicked: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::ArtifactMetadata;
+    use crate::history::CacheKey;
+    use crate::models::{
+        AnalysisResponse, AnalysisSummary, CarvedPayloadInfo, FileInfo, FormatSpecificAnalysis,
+        SummaryVerdict,
+    };
+    use crate::usage::ResourceUsage;
+    use analyzers::payload_carver::CarvedPayload;
+
+    fn response(timestamp: &str, artifact_id: Option<String>) -> AnalysisResponse {
+        AnalysisResponse {
+            scan_id: None,
+            cached: false,
+            file_info: FileInfo {
+                path: "upload".to_string(),
+                size_bytes: 1024,
+                detected_type: "image/png".to_string(),
+                extension: None,
+            },
+            magic_bytes_analysis: None,
+            carved_payloads: artifact_id
+                .into_iter()
+                .map(|id| CarvedPayloadInfo {
+                    offset: 0,
+                    offset_hex: "0x0".to_string(),
+                    size_bytes: 64,
+                    description: "ZIP archive".to_string(),
+                    file_type: "zip".to_string(),
+                    data_base64: None,
+                    download_url: Some(format!("/api/artifacts/{}", id)),
+                    artifact_id: Some(id),
+                    also_carved_from: Vec::new(),
+                })
+                .collect(),
+            slack_space: None,
+            deflate_streams: None,
+            format_specific_analysis: FormatSpecificAnalysis::Unknown,
+            plugins: Vec::new(),
+            timestamp: timestamp.to_string(),
+            summary: AnalysisSummary {
+                steganography_detected: false,
+                confidence_level: "low".to_string(),
+                threat_indicators: Vec::new(),
+                recommendations: Vec::new(),
+                verdict: SummaryVerdict::Clean,
+                extracted_payloads: Vec::new(),
+            },
+            annotations: Vec::new(),
+            run: None,
+        }
+    }
+
+    fn stores(dir: &std::path::Path) -> (ScanHistory, ArtifactStore) {
+        (
+            ScanHistory::new(dir.join("history")),
+            ArtifactStore::new(dir.join("artifacts"), 0),
+        )
+    }
+
+    // Store a scan that carved the given payload
+    fn record(
+        history: &ScanHistory,
+        artifacts: &ArtifactStore,
+        tenant: &Tenant,
+        timestamp: &str,
+        payload: &[u8],
+    ) -> (String, String) {
+        let scan_id = uuid::Uuid::new_v4().to_string();
+        let carved = CarvedPayload {
+            offset: 0,
+            description: "ZIP archive".to_string(),
+            file_type: "zip".to_string(),
+            data: payload.to_vec(),
+        };
+        let artifact = artifacts.store(tenant, &scan_id, &carved).unwrap();
+        let mut response = response(timestamp, Some(artifact.sha256.clone()));
+        response.scan_id = Some(scan_id);
+        let id = history
+            .record(
+                tenant,
+                None,
+                "cover.png",
+                None,
+                &response,
+                &ResourceUsage::default(),
+            )
+            .unwrap();
+        (id, artifact.sha256)
+    }
+
+    #[test]
+    fn test_prune_by_age_removes_scan_with_its_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (history, artifacts) = stores(dir.path());
+        let tenant = Tenant("red".to_string());
+        let (old, old_artifact) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-01T10:00:00+00:00",
+            b"old",
+        );
+        let (new, new_artifact) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-09T10:00:00+00:00",
+            b"new",
+        );
+
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(7 * 24 * 3600)),
+            max_disk_bytes: None,
+            interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+        };
+        let now = parse_timestamp("2026-03-10T00:00:00+00:00").unwrap();
+        let report = prune(&history, &artifacts, &policy, now).unwrap();
+
+        assert_eq!(report.scans_removed, 1);
+        assert_eq!(report.artifacts_removed, 1);
+        assert!(history.load(&tenant, &old).is_err());
+        assert!(artifacts.load(&tenant, &old_artifact).is_err());
+        assert!(history.load(&tenant, &new).is_ok());
+        assert!(artifacts.load(&tenant, &new_artifact).is_ok());
+    }
+
+    #[test]
+    fn test_prune_by_disk_budget_removes_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let (history, artifacts) = stores(dir.path());
+        let tenant = Tenant("red".to_string());
+        let (old, _) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-01T10:00:00+00:00",
+            b"old",
+        );
+        let (new, _) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-02T10:00:00+00:00",
+            b"new",
+        );
+        let budget = history.record_bytes(&tenant, &new) + 1024;
+
+        let policy = RetentionPolicy {
+            max_age: None,
+            max_disk_bytes: Some(budget),
+            interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+        };
+        let now = parse_timestamp("2026-03-03T00:00:00+00:00").unwrap();
+        let report = prune(&history, &artifacts, &policy, now).unwrap();
+
+        assert_eq!(report.scans_removed, 1);
+        assert!(history.load(&tenant, &old).is_err());
+        assert!(history.load(&tenant, &new).is_ok());
+    }
+
+    #[test]
+    fn test_shared_artifact_kept_until_last_scan_is_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let (history, artifacts) = stores(dir.path());
+        let tenant = Tenant("red".to_string());
+        let (first, artifact) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-01T10:00:00+00:00",
+            b"zip",
+        );
+        let (second, shared) = record(
+            &history,
+            &artifacts,
+            &tenant,
+            "2026-03-02T10:00:00+00:00",
+            b"zip",
+        );
+        assert_eq!(artifact, shared);
+
+        let report = delete_scan(&history, &artifacts, &tenant, &first).unwrap();
+        assert_eq!(report.artifacts_removed, 0);
+        assert!(artifacts.load(&tenant, &artifact).is_ok());
+
+        let report = delete_scan(&history, &artifacts, &tenant, &second).unwrap();
+        assert_eq!(report.artifacts_removed, 1);
+        assert!(artifacts.load(&tenant, &artifact).is_err());
+    }
+
+    #[test]
+    fn test_artifact_stored_under_uuid_is_served_and_removed_with_its_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let (history, artifacts) = stores(dir.path());
+        let tenant = Tenant("red".to_string());
+
+        // Layout written before artifacts were content-addressed
+        let legacy = uuid::Uuid::new_v4().to_string();
+        let legacy_dir = dir.path().join("artifacts/red");
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        std::fs::write(legacy_dir.join(format!("{}.bin", legacy)), b"old zip").unwrap();
+        let metadata = ArtifactMetadata {
+            id: legacy.clone(),
+            file_name: "carved_0x0.bin".to_string(),
+            description: "ZIP archive".to_string(),
+            size_bytes: 7,
+            created_at: "2026-03-01T10:00:00+00:00".to_string(),
+        };
+        std::fs::write(
+            legacy_dir.join(format!("{}.json", legacy)),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+        let id = history
+            .record(
+                &tenant,
+                None,
+                "cover.png",
+                None,
+                &response("2026-03-01T10:00:00+00:00", Some(legacy.clone())),
+                &ResourceUsage::default(),
+            )
+            .unwrap();
+
+        let (loaded, data) = artifacts.load(&tenant, &legacy).unwrap();
+        assert_eq!(loaded.id, legacy);
+        assert_eq!(data, b"old zip");
+        assert_eq!(artifacts.list(&tenant).unwrap().len(), 1);
+        assert!(artifacts.artifact_bytes(&tenant, &legacy) > 0);
+
+        let report = delete_scan(&history, &artifacts, &tenant, &id).unwrap();
+        assert_eq!(report.artifacts_removed, 1);
+        assert!(artifacts.load(&tenant, &legacy).is_err());
+        assert!(artifacts.list(&tenant).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deleted_scan_is_no_longer_found_in_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let (history, artifacts) = stores(dir.path());
+        let tenant = Tenant("red".to_string());
+        let key = CacheKey {
+            sha256: "ab".repeat(32),
+            options: "stride 30".to_string(),
+        };
+        let id = history
+            .record(
+                &tenant,
+                None,
+                "cover.png",
+                Some(&key),
+                &response("2026-03-01T10:00:00+00:00", None),
+                &ResourceUsage::default(),
+            )
+            .unwrap();
+
+        assert_eq!(history.find_cached(&tenant, &key).unwrap().id, id);
+        assert!(
+            history
+                .find_cached(&Tenant("blue".to_string()), &key)
+                .is_none()
+        );
+        // The same content scanned with other options is not a hit
+        let other = CacheKey {
+            options: "scene 0.3".to_string(),
+            ..key.clone()
+        };
+        assert!(history.find_cached(&tenant, &other).is_none());
+
+        delete_scan(&history, &artifacts, &tenant, &id).unwrap();
+        assert!(history.find_cached(&tenant, &key).is_none());
+        assert!(
+            !dir.path()
+                .join("history/red/cache")
+                .join(key.digest())
+                .exists()
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::artifacts::ArtifactStore;
+use crate::history::ScanHistory;
+use crate::jobs::JobStore;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::tenant::TenantDirectory;
+use crate::uploads::UploadDir;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub artifacts: Arc<ArtifactStore>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub history: Arc<ScanHistory>,
+    // Background scans started by /api/scan
+    pub jobs: Arc<JobStore>,
+    pub tenants: Arc<TenantDirectory>,
+    // Where uploads are written while they are scanned
+    pub uploads: Arc<UploadDir>,
+    // Longest /api/scan/verdict scans before answering inconclusive
+    pub verdict_budget: Duration,
+}
+
+const DEFAULT_VERDICT_BUDGET_MS: u64 = 2000;
+
+impl AppState {
+    pub fn from_env() -> Self {
+        Self {
+            artifacts: Arc::new(ArtifactStore::from_env()),
+            rate_limiter: Arc::new(RateLimiter::new(RateLimitConfig::from_env())),
+            history: Arc::new(ScanHistory::from_env()),
+            jobs: Arc::new(JobStore::from_env()),
+            tenants: Arc::new(TenantDirectory::from_env()),
+            uploads: Arc::new(UploadDir::from_env()),
+            // STEGASCAN_VERDICT_BUDGET_MS overrides the default
+            verdict_budget: Duration::from_millis(
+                std::env::var("STEGASCAN_VERDICT_BUDGET_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_VERDICT_BUDGET_MS),
+            ),
+        }
+    }
+}
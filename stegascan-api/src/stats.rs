@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::history::ScanVerdict;
+use crate::models::{AnalysisResponse, AnnotationLabel, FormatSpecificAnalysis};
+
+// Rules listed in a stats report, most fired first
+pub const TOP_RULES: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerdictCounts {
+    pub clean: u64,
+    pub suspicious: u64,
+    pub likely_stego: u64,
+}
+
+impl VerdictCounts {
+    fn add(&mut self, verdict: ScanVerdict) {
+        match verdict {
+            ScanVerdict::Clean => self.clean += 1,
+            ScanVerdict::Suspicious => self.suspicious += 1,
+            ScanVerdict::LikelyStego => self.likely_stego += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyStats {
+    // UTC day, YYYY-MM-DD
+    pub date: String,
+    pub scans: u64,
+    #[serde(flatten)]
+    pub verdicts: VerdictCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleCount {
+    pub rule: String,
+    // Scans the rule fired on
+    pub scans: u64,
+    // Of those, the ones an analyst confirmed or rejected; a rule that is mostly false
+    // positives needs a stricter threshold
+    #[serde(default)]
+    pub true_positives: u64,
+    #[serde(default)]
+    pub false_positives: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeStats {
+    pub detected_type: String,
+    pub scans: u64,
+    #[serde(flatten)]
+    pub verdicts: VerdictCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub tenant: String,
+    pub scans: u64,
+    #[serde(flatten)]
+    pub verdicts: VerdictCounts,
+    // Oldest day first; days without scans are left out
+    pub over_time: Vec<DailyStats>,
+    pub top_rules: Vec<RuleCount>,
+    // Most scanned type first
+    pub file_types: Vec<TypeStats>,
+}
+
+// Aggregate stored scans for the dashboard endpoint
+pub fn aggregate<'a>(
+    tenant: &str,
+    responses: impl IntoIterator<Item = &'a AnalysisResponse>,
+) -> StatsReport {
+    let mut scans = 0;
+    let mut verdicts = VerdictCounts::default();
+    let mut by_day: BTreeMap<String, (u64, VerdictCounts)> = BTreeMap::new();
+    let mut by_rule: BTreeMap<String, RuleCount> = BTreeMap::new();
+    let mut by_type: BTreeMap<String, (u64, VerdictCounts)> = BTreeMap::new();
+
+    for response in responses {
+        let verdict = ScanVerdict::of(&response.summary);
+        scans += 1;
+        verdicts.add(verdict);
+
+        // RFC 3339 timestamps start with the date
+        let date = response
+            .timestamp
+            .get(..10)
+            .unwrap_or("unknown")
+            .to_string();
+        let day = by_day.entry(date).or_default();
+        day.0 += 1;
+        day.1.add(verdict);
+
+        let file_type = by_type
+            .entry(response.file_info.detected_type.clone())
+            .or_default();
+        file_type.0 += 1;
+        file_type.1.add(verdict);
+
+        for rule in fired_rules(response) {
+            let count = by_rule.entry(rule.clone()).or_insert_with(|| RuleCount {
+                rule: rule.clone(),
+                scans: 0,
+                true_positives: 0,
+                false_positives: 0,
+            });
+            count.scans += 1;
+            // The latest label counts when a finding was reviewed more than once
+            let label = response
+                .annotations
+                .iter()
+                .rev()
+                .filter(|annotation| annotation.rule.as_ref() == Some(&rule))
+                .find_map(|annotation| match annotation.label {
+                    AnnotationLabel::Comment => None,
+                    label => Some(label),
+                });
+            match label {
+                Some(AnnotationLabel::TruePositive) => count.true_positives += 1,
+                Some(AnnotationLabel::FalsePositive) => count.false_positives += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let mut top_rules: Vec<RuleCount> = by_rule.into_values().collect();
+    // Stable sort keeps ties in name order
+    top_rules.sort_by_key(|rule| Reverse(rule.scans));
+    top_rules.truncate(TOP_RULES);
+
+    let mut file_types: Vec<TypeStats> = by_type
+        .into_iter()
+        .map(|(detected_type, (scans, verdicts))| TypeStats {
+            detected_type,
+            scans,
+            verdicts,
+        })
+        .collect();
+    file_types.sort_by_key(|file_type| Reverse(file_type.scans));
+
+    StatsReport {
+        tenant: tenant.to_string(),
+        scans,
+        verdicts,
+        over_time: by_day
+            .into_iter()
+            .map(|(date, (scans, verdicts))| DailyStats {
+                date,
+                scans,
+                verdicts,
+            })
+            .collect(),
+        top_rules,
+        file_types,
+    }
+}
+
+// Checks that raised an indicator in the scan, named after the section they come from. The
+// conditions are those of the summary in analysis::finalize_summary; indicators themselves
+// carry measurements and don't group across scans.
+pub fn fired_rules(response: &AnalysisResponse) -> Vec<String> {
+    let mut rules = Vec::new();
+    let mut fire = |rule: &str, fired: bool| {
+        if fired {
+            rules.push(rule.to_string());
+        }
+    };
+
+    if let Some(ref magic) = response.magic_bytes_analysis {
+        fire("magic_bytes.suspicious_data", magic.has_suspicious_data);
+        fire("magic_bytes.multiple_formats", magic.has_multiple_formats);
+    }
+    if let Some(ref slack) = response.slack_space {
+        fire("slack_space", slack.is_suspicious);
+    }
+    if let Some(ref deflate) = response.deflate_streams {
+        fire("deflate", deflate.is_suspicious);
+    }
+    for plugin in response.plugins.iter().filter(|p| p.is_suspicious) {
+        fire(&format!("plugin.{}", plugin.name), true);
+    }
+
+    match &response.format_specific_analysis {
+        FormatSpecificAnalysis::Image(img) => {
+            if let Some(ref lsb) = img.lsb_analysis {
+                fire("lsb", lsb.is_suspicious);
+            }
+            if let Some(ref ws) = img.ws_analysis {
+                fire("ws", ws.is_suspicious);
+            }
+            if let Some(ref correlation) = img.channel_correlation {
+                fire("channel_correlation", correlation.is_suspicious);
+            }
+            if let Some(ref webp) = img.webp_analysis {
+                fire("webp.structure", !webp.structure_anomalies.is_empty());
+                fire("webp.frames", !webp.suspicious_frames.is_empty());
+            }
+            if let Some(ref bpcs) = img.bpcs {
+                fire("bpcs", bpcs.is_suspicious);
+            }
+            if let Some(ref calibration) = img.calibration {
+                fire("calibration", calibration.is_suspicious);
+            }
+            if let Some(ref gif) = img.gif_analysis {
+                fire("gif", !gif.structure_anomalies.is_empty());
+            }
+            if let Some(ref png_text) = img.png_text {
+                fire("png_text", !png_text.anomalies.is_empty());
+            }
+            if let Some(ref adaptive) = img.adaptive_lsb {
+                fire("adaptive_lsb", adaptive.is_suspicious);
+            }
+            if let Some(ref pages) = img.page_analysis {
+                fire("pages.outliers", !pages.outlier_pages.is_empty());
+                fire("pages.hidden", !pages.hidden_pages.is_empty());
+            }
+        }
+        FormatSpecificAnalysis::Audio(audio) => {
+            if let Some(ref spec) = audio.spectrogram_analysis {
+                fire("spectrogram", spec.hidden_message_detected);
+            }
+            if let Some(ref ultrasonic) = audio.ultrasonic_analysis {
+                fire(
+                    "ultrasonic",
+                    ultrasonic.fsk.is_some() || ultrasonic.is_suspicious,
+                );
+            }
+            if let Some(ref phase) = audio.phase_analysis {
+                fire("phase", phase.is_suspicious);
+            }
+            if let Some(ref demodulation) = audio.demodulation {
+                fire(
+                    "demodulation",
+                    demodulation
+                        .carriers
+                        .iter()
+                        .any(|carrier| carrier.fsk.is_some() || carrier.psk.is_some()),
+                );
+            }
+            if let Some(ref id3) = audio.id3_analysis {
+                fire(
+                    "id3.pictures",
+                    id3.pictures.iter().any(|picture| picture.is_suspicious),
+                );
+                fire("id3.tag_gap", !id3.tag_gaps.is_empty());
+            }
+            if let Some(ref trailing) = audio.trailing_tags {
+                fire("trailing_tag.item", trailing.is_suspicious);
+            }
+            if let Some(ref mp4_tags) = audio.mp4_tags {
+                fire("mp4_tag.item", mp4_tags.is_suspicious);
+            }
+        }
+        FormatSpecificAnalysis::Video(video) => {
+            fire("video.frames", !video.suspicious_frames.is_empty());
+            fire(
+                "video.scenes",
+                video.scenes.iter().any(|scene| scene.suspicious),
+            );
+            if let Some(ref mp4_tags) = video.mp4_tags {
+                fire("mp4_tag.item", mp4_tags.is_suspicious);
+            }
+        }
+        FormatSpecificAnalysis::Text(text) => {
+            if let Some(ref ole) = text.ole {
+                fire(
+                    "ole.unusual",
+                    ole.entries.iter().any(|e| e.unusual.is_some()),
+                );
+                let macros = ole.all_macros();
+                fire("ole.macros", !macros.is_empty());
+                fire(
+                    "ole.auto_exec",
+                    macros.iter().any(|module| !module.auto_exec.is_empty()),
+                );
+                fire(
+                    "ole.executables",
+                    ole.all_objects().iter().any(|o| o.executable),
+                );
+            }
+            if let Some(ref ebook) = text.ebook {
+                fire("ebook.extraneous", ebook.extraneous().next().is_some());
+                fire("ebook.packaging", !ebook.anomalies.is_empty());
+                fire(
+                    "ebook.chapters",
+                    ebook.chapters.iter().any(|c| c.is_suspicious),
+                );
+            }
+            if let Some(ref model) = text.model {
+                fire("model.hidden_region", !model.regions.is_empty());
+                fire("model.structure", !model.anomalies.is_empty());
+            }
+            if let Some(ref structured) = text.structured {
+                let fired = |kind: &str| structured.findings.iter().any(|f| f.kind == kind);
+                fire("structured.comment", fired("comment"));
+                fire("structured.long_value", fired("long value"));
+                fire("structured.blob", fired("blob"));
+                fire("structured.invisible_key", fired("invisible key"));
+            }
+            if let Some(ref source_code) = text.source_code {
+                fire("source.comment_payload", !source_code.payloads.is_empty());
+                fire("source.comment_ratio", source_code.abnormal_ratio);
+            }
+        }
+        _ => {}
+    }
+
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AnalysisSummary, Annotation, FileInfo, SlackSpaceReport, SummaryVerdict};
+
+    fn response(timestamp: &str, detected_type: &str, indicators: usize) -> AnalysisResponse {
+        AnalysisResponse {
+            scan_id: None,
+            cached: false,
+            file_info: FileInfo {
+                path: "upload".to_string(),
+                size_bytes: 1024,
+                detected_type: detected_type.to_string(),
+                extension: None,
+            },
+            magic_bytes_analysis: None,
+            carved_payloads: Vec::new(),
+            slack_space: None,
+            deflate_streams: None,
+            format_specific_analysis: FormatSpecificAnalysis::Unknown,
+            plugins: Vec::new(),
+            timestamp: timestamp.to_string(),
+            summary: AnalysisSummary {
+                steganography_detected: indicators > 0,
+                confidence_level: match indicators {
+                    0 => "low",
+                    1 | 2 => "medium",
+                    _ => "high",
+                }
+                .to_string(),
+                threat_indicators: vec!["indicator".to_string(); indicators],
+                recommendations: Vec::new(),
+                verdict: if indicators > 0 {
+                    SummaryVerdict::Suspicious
+                } else {
+                    SummaryVerdict::Clean
+                },
+                extracted_payloads: Vec::new(),
+            },
+            annotations: Vec::new(),
+            run: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_by_day_type_and_verdict() {
+        let mut slack = response("2026-03-02T09:30:00+00:00", "image/png", 3);
+        slack.slack_space = Some(SlackSpaceReport {
+            container: "PNG".to_string(),
+            accounted_bytes: 1536,
+            slack_bytes: 512,
+            is_suspicious: true,
+            regions: Vec::new(),
+        });
+        slack.annotations.push(Annotation {
+            rule: Some("slack_space".to_string()),
+            label: AnnotationLabel::FalsePositive,
+            comment: Some("Padding written by the camera".to_string()),
+            analyst: None,
+            added: "2026-03-03T08:00:00+00:00".to_string(),
+        });
+        let responses = [
+            response("2026-03-01T10:00:00+00:00", "image/png", 0),
+            response("2026-03-01T23:59:00+00:00", "audio/mpeg", 1),
+            slack,
+        ];
+
+        let stats = aggregate("red", &responses);
+        assert_eq!(stats.scans, 3);
+        assert_eq!(
+            stats.verdicts,
+            VerdictCounts {
+                clean: 1,
+                suspicious: 1,
+                likely_stego: 1,
+            }
+        );
+        assert_eq!(stats.over_time.len(), 2);
+        assert_eq!(stats.over_time[0].date, "2026-03-01");
+        assert_eq!(stats.over_time[0].scans, 2);
+        assert_eq!(stats.over_time[1].verdicts.likely_stego, 1);
+        assert_eq!(stats.file_types[0].detected_type, "image/png");
+        assert_eq!(stats.file_types[0].scans, 2);
+        assert_eq!(stats.top_rules.len(), 1);
+        assert_eq!(stats.top_rules[0].rule, "slack_space");
+        assert_eq!(stats.top_rules[0].false_positives, 1);
+        assert_eq!(stats.top_rules[0].true_positives, 0);
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap()["likely_stego"],
+            serde_json::json!(1)
+        );
+    }
+}
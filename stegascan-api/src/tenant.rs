@@ -0,0 +1,136 @@
+use axum::{async_trait, extract::FromRequestParts, http::HeaderMap, http::request::Parts};
+use std::collections::HashMap;
+
+use crate::error::ApiError;
+use crate::rate_limit::API_KEY_HEADER;
+use crate::state::AppState;
+
+// Header naming the tenant when no API key mapping is configured
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+pub const DEFAULT_TENANT: &str = "default";
+
+// Namespace that scan history and artifacts are stored under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant(pub String);
+
+impl Tenant {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// Tenant IDs become directory names, so keep them to a safe character set
+pub(crate) fn is_valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub struct TenantDirectory {
+    // API key -> tenant ID; empty when tenants are picked by header
+    keys: HashMap<String, String>,
+}
+
+impl TenantDirectory {
+    pub fn new(keys: HashMap<String, String>) -> Self {
+        Self { keys }
+    }
+
+    // STEGASCAN_TENANT_KEYS holds comma-separated `api_key=tenant_id` pairs. When set,
+    // every request must present a known key and the tenant comes from the mapping.
+    pub fn from_env() -> Self {
+        let keys = std::env::var("STEGASCAN_TENANT_KEYS")
+            .map(|value| {
+                value
+                    .split(',')
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(key, tenant)| (key.trim().to_string(), tenant.trim().to_string()))
+                    .filter(|(key, tenant)| !key.is_empty() && is_valid_tenant_id(tenant))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self::new(keys)
+    }
+
+    // Whether the key maps to a tenant; always false when tenants are picked by header
+    pub fn knows_key(&self, key: &str) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    pub fn resolve(&self, headers: &HeaderMap) -> Result<Tenant, ApiError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        if !self.keys.is_empty() {
+            let key = header(API_KEY_HEADER)
+                .ok_or_else(|| ApiError::Unauthorized("Missing API key".to_string()))?;
+            return self
+                .keys
+                .get(key)
+                .map(|tenant| Tenant(tenant.clone()))
+                .ok_or_else(|| ApiError::Unauthorized("Unknown API key".to_string()));
+        }
+
+        match header(TENANT_HEADER) {
+            Some(id) if is_valid_tenant_id(id) => Ok(Tenant(id.to_string())),
+            Some(id) => Err(ApiError::InvalidTenant(id.to_string())),
+            None => Ok(Tenant(DEFAULT_TENANT.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Tenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        state.tenants.resolve(&parts.headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_resolve_from_header_and_keys() {
+        let open = TenantDirectory::new(HashMap::new());
+        let mut headers = HeaderMap::new();
+        assert_eq!(open.resolve(&headers).unwrap().as_str(), DEFAULT_TENANT);
+
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("team-a"));
+        assert_eq!(open.resolve(&headers).unwrap().as_str(), "team-a");
+
+        headers.insert(TENANT_HEADER, HeaderValue::from_static("../etc"));
+        assert!(matches!(
+            open.resolve(&headers),
+            Err(ApiError::InvalidTenant(_))
+        ));
+
+        let keyed = TenantDirectory::new(HashMap::from([(
+            "secret".to_string(),
+            "team-b".to_string(),
+        )]));
+        assert!(matches!(
+            keyed.resolve(&headers),
+            Err(ApiError::Unauthorized(_))
+        ));
+
+        // The mapped tenant wins over whatever the header claims
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        assert_eq!(keyed.resolve(&headers).unwrap().as_str(), "team-b");
+    }
+}
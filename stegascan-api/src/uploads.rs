@@ -0,0 +1,270 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tempfile::NamedTempFile;
+
+use crate::error::ApiError;
+
+// Uploads, and the files analyzers pull out of them, only exist on disk while they are scanned.
+// Each is written through the handle that created it, under a random name and readable by this
+// user only, into a directory no one else can list. The file goes away when its handle drops,
+// which also happens when a scan fails or panics, and a scan abandoned at its budget takes its
+// file with it when it finishes in the background. Files a killed process left behind are
+// swept at startup and every SWEEP_INTERVAL after.
+
+const DEFAULT_DIR_NAME: &str = "stegascan-uploads";
+
+// Linux tmpfs mount for STEGASCAN_UPLOAD_TMPFS
+const TMPFS_ROOT: &str = "/dev/shm";
+
+const FILE_PREFIX: &str = "upload-";
+
+// Largest upload accepted unless STEGASCAN_MAX_UPLOAD_MB says otherwise
+const DEFAULT_MAX_UPLOAD_MB: u64 = 4096;
+
+// Older leftovers can't belong to a scan still running, even in another instance sharing the
+// directory
+const STALE_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+// A handle locks its file just after creating it; younger files are left alone so a sweep
+// can't catch one in between
+const MIN_SWEEP_AGE: Duration = Duration::from_secs(60);
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct UploadDir {
+    root: PathBuf,
+    // Largest upload in bytes, 0 for no limit
+    max_bytes: u64,
+}
+
+impl UploadDir {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            max_bytes: DEFAULT_MAX_UPLOAD_MB * 1024 * 1024,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    // STEGASCAN_UPLOAD_DIR picks the directory. Without it, STEGASCAN_UPLOAD_TMPFS=true keeps
+    // uploads in memory under /dev/shm, so they never reach a disk. STEGASCAN_MAX_UPLOAD_MB
+    // overrides the upload limit, 0 lifting it.
+    pub fn from_env() -> Self {
+        let max_mb = std::env::var("STEGASCAN_MAX_UPLOAD_MB")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_UPLOAD_MB);
+        Self::at_env_root().with_max_bytes(max_mb.saturating_mul(1024 * 1024))
+    }
+
+    fn at_env_root() -> Self {
+        if let Ok(dir) = std::env::var("STEGASCAN_UPLOAD_DIR") {
+            return Self::new(PathBuf::from(dir));
+        }
+        let tmpfs = std::env::var("STEGASCAN_UPLOAD_TMPFS")
+            .is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"));
+        if tmpfs {
+            if Path::new(TMPFS_ROOT).is_dir() {
+                return Self::new(Path::new(TMPFS_ROOT).join(DEFAULT_DIR_NAME));
+            }
+            tracing::warn!(
+                "STEGASCAN_UPLOAD_TMPFS is set but {} doesn't exist, writing uploads to the temp dir",
+                TMPFS_ROOT
+            );
+        }
+        Self::new(std::env::temp_dir().join(DEFAULT_DIR_NAME))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    // A new empty file ending in `suffix`, since parsers pick the decoder from the extension.
+    // The file is removed when the returned handle drops.
+    pub fn create(&self, suffix: &str) -> Result<NamedTempFile, ApiError> {
+        self.prepare()?;
+        // Created exclusively, with mode 0600 on Unix
+        let file = tempfile::Builder::new()
+            .prefix(FILE_PREFIX)
+            .suffix(suffix)
+            .tempfile_in(&self.root)?;
+        // Held for as long as the handle lives, in this process or another sharing the
+        // directory; a file no one holds a lock on is a leftover. Windows locks are mandatory
+        // and would keep the analyzers from reading the file, so there only age counts.
+        #[cfg(unix)]
+        file.as_file().lock()?;
+        Ok(file)
+    }
+
+    // Write `data` to a new file, see `create`
+    pub fn persist(&self, data: &[u8], suffix: &str) -> Result<NamedTempFile, ApiError> {
+        let mut file = self.create(suffix)?;
+        file.write_all(data)?;
+        file.flush()?;
+        Ok(file)
+    }
+
+    // Create the directory if needed, readable by this user only. A symbolic link in its place
+    // could send uploads somewhere shared, so it is refused.
+    fn prepare(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+        let metadata = std::fs::symlink_metadata(&self.root)?;
+        if !metadata.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", self.root.display()),
+            ));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o777 != 0o700 {
+                std::fs::set_permissions(&self.root, std::fs::Permissions::from_mode(0o700))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Remove uploads no live handle owns, left behind by a process that was killed mid-scan
+    pub fn sweep(&self) -> std::io::Result<usize> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_name().to_string_lossy().starts_with(FILE_PREFIX) {
+                continue;
+            }
+            // Gone already when its handle dropped since the listing
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            let orphaned = age > STALE_AFTER || (age > MIN_SWEEP_AGE && !is_held(&entry.path()));
+            if metadata.is_file() && orphaned {
+                match std::fs::remove_file(entry.path()) {
+                    Ok(()) => removed += 1,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// Whether a live handle holds the file's lock; the lock is released when its process exits
+#[cfg(unix)]
+fn is_held(path: &Path) -> bool {
+    match std::fs::File::open(path) {
+        Ok(file) => file.try_lock().is_err(),
+        // Can't tell, so it stays until it is stale
+        Err(_) => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_held(_path: &Path) -> bool {
+    true
+}
+
+// Sweep the upload directory now and then for as long as the server runs, so the leftovers of
+// a crash go soon after a restart rather than at the next restart a day later
+pub fn spawn_sweeps(uploads: Arc<UploadDir>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let dir = uploads.clone();
+            match tokio::task::spawn_blocking(move || dir.sweep()).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(removed)) => tracing::info!("Removed {} leftover upload(s)", removed),
+                Ok(Err(e)) => tracing::warn!(
+                    "Can't sweep the upload directory {}: {}",
+                    uploads.root().display(),
+                    e
+                ),
+                Err(e) => tracing::warn!("Upload sweep panicked: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persist_and_sweep() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = UploadDir::new(dir.path().join("uploads"));
+
+        let file = uploads.persist(b"payload", ".png").unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.starts_with(uploads.root()));
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with(FILE_PREFIX) && name.ends_with(".png"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"payload");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&path), 0o600);
+            assert_eq!(mode(uploads.root()), 0o700);
+        }
+        drop(file);
+        assert!(!path.exists());
+
+        // Only files of ours are swept: stale ones, and on Unix ones no handle holds any more
+        let age = |path: &Path, age: Duration| {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        };
+        let stale = uploads.persist(b"old", ".bin").unwrap().keep().unwrap().1;
+        let recent = uploads.persist(b"new", ".bin").unwrap().keep().unwrap().1;
+        let orphan = uploads.persist(b"lost", ".bin").unwrap().keep().unwrap().1;
+        let live = uploads.persist(b"scanning", ".bin").unwrap();
+        let foreign = uploads.root().join("notes.txt");
+        std::fs::write(&foreign, b"keep").unwrap();
+        for path in [&stale, &foreign] {
+            age(path, STALE_AFTER * 2);
+        }
+        for path in [&orphan, live.path()] {
+            age(path, MIN_SWEEP_AGE * 2);
+        }
+        let swept = if cfg!(unix) { 2 } else { 1 };
+        assert_eq!(uploads.sweep().unwrap(), swept);
+        assert!(!stale.exists());
+        assert_eq!(orphan.exists(), !cfg!(unix));
+        assert!(recent.exists() && foreign.exists() && live.path().exists());
+
+        assert_eq!(
+            UploadDir::new(dir.path().join("missing")).sweep().unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::models::CarvedPayloadInfo;
+use crate::rate_limit::API_KEY_HEADER;
+use crate::state::AppState;
+
+// Resources one scan consumed, stored with its history record
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    // CPU time spent polling the scan plus the video frame analyses it ran on the rayon pool;
+    // external plugin processes are not included
+    pub cpu_time_ms: u64,
+    pub wall_time_ms: u64,
+    // Highest resident set size of the server process while the scan ran, None where it
+    // can't be read. Concurrent scans share the process, so this is an upper bound.
+    pub peak_rss_bytes: Option<u64>,
+    // Carved payloads written to the artifact store
+    pub artifact_bytes: u64,
+}
+
+impl ResourceUsage {
+    pub fn count_artifacts(&mut self, payloads: &[CarvedPayloadInfo]) {
+        self.artifact_bytes = payloads
+            .iter()
+            .filter(|p| p.artifact_id.is_some())
+            .map(|p| p.size_bytes as u64)
+            .sum();
+    }
+}
+
+#[cfg(unix)]
+pub fn thread_cpu_time() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid timespec for the call to fill in
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(not(unix))]
+pub fn thread_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    // Second field of statm is the resident page count
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size.max(0) as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
+
+thread_local! {
+    // CPU time work handed off from the scan being polled on this thread took elsewhere
+    static OFFLOADED_CPU: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+// Counts CPU time the scan being polled spent on other threads, such as frame analyses on the
+// rayon pool, towards its usage. Outside a measured scan this does nothing.
+pub fn add_offloaded_cpu(time: Duration) {
+    OFFLOADED_CPU.with(|offloaded| offloaded.set(offloaded.get() + time));
+}
+
+// Scans can move between runtime worker threads at every await, so CPU time is taken
+// from the current thread around each individual poll and summed
+struct Metered<F> {
+    inner: Pin<Box<F>>,
+    cpu: Duration,
+}
+
+impl<F: Future> Future for Metered<F> {
+    type Output = (F::Output, Duration);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let outer = OFFLOADED_CPU.with(|offloaded| offloaded.replace(Duration::ZERO));
+        let start = thread_cpu_time();
+        let poll = self.inner.as_mut().poll(cx);
+        self.cpu += thread_cpu_time().saturating_sub(start);
+        self.cpu += OFFLOADED_CPU.with(|offloaded| offloaded.replace(outer));
+        poll.map(|output| (output, self.cpu))
+    }
+}
+
+// Analyzers allocate and free whole decoded files inside a single poll, so RSS is sampled
+// from a separate thread rather than between polls
+const RSS_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+struct RssSampler {
+    stop: Arc<AtomicBool>,
+    peak: Arc<AtomicU64>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl RssSampler {
+    fn start() -> Option<Self> {
+        let initial = resident_bytes()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let peak = Arc::new(AtomicU64::new(initial));
+        let thread = {
+            let (stop, peak) = (stop.clone(), peak.clone());
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(rss) = resident_bytes() {
+                        peak.fetch_max(rss, Ordering::Relaxed);
+                    }
+                    std::thread::sleep(RSS_SAMPLE_INTERVAL);
+                }
+            })
+        };
+        Some(Self { stop, peak, thread })
+    }
+
+    fn finish(self) -> u64 {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+        let last = resident_bytes().unwrap_or(0);
+        self.peak.load(Ordering::Relaxed).max(last)
+    }
+}
+
+// Run a scan, measuring its CPU time, wall time and peak RSS. Artifact bytes are filled in
+// by the caller once payloads have been delivered.
+pub async fn measure<F: Future>(scan: F) -> (F::Output, ResourceUsage) {
+    let started = Instant::now();
+    let sampler = RssSampler::start();
+    let (output, cpu) = Metered {
+        inner: Box::pin(scan),
+        cpu: Duration::ZERO,
+    }
+    .await;
+
+    let usage = ResourceUsage {
+        cpu_time_ms: cpu.as_millis() as u64,
+        wall_time_ms: started.elapsed().as_millis() as u64,
+        peak_rss_bytes: sampler.map(RssSampler::finish),
+        artifact_bytes: 0,
+    };
+    (output, usage)
+}
+
+// Keys are never written to disk; records carry only the last four characters
+pub fn key_label(key: &str) -> String {
+    let tail: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    if key.chars().count() > 4 {
+        format!("...{}", tail)
+    } else {
+        "...".to_string()
+    }
+}
+
+// Label of the API key a request was made with, None for anonymous requests
+pub struct ApiKey(pub Option<String>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiKey {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(ApiKey(
+            parts
+                .headers
+                .get(API_KEY_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(key_label),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub scans: u64,
+    pub cpu_time_ms: u64,
+    pub wall_time_ms: u64,
+    // Largest single-scan peak
+    pub peak_rss_bytes: Option<u64>,
+    pub artifact_bytes: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &ResourceUsage) {
+        self.scans += 1;
+        self.cpu_time_ms += usage.cpu_time_ms;
+        self.wall_time_ms += usage.wall_time_ms;
+        self.peak_rss_bytes = self.peak_rss_bytes.max(usage.peak_rss_bytes);
+        self.artifact_bytes += usage.artifact_bytes;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyUsage {
+    // Key label as stored in scan records, "anonymous" for scans made without a key
+    pub api_key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub tenant: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+    pub api_keys: Vec<KeyUsage>,
+}
+
+// Sum per-scan usage, given as (API key label, usage) pairs
+pub fn aggregate<'a>(
+    tenant: &str,
+    scans: impl IntoIterator<Item = (Option<&'a str>, &'a ResourceUsage)>,
+) -> UsageReport {
+    let mut totals = UsageTotals::default();
+    let mut by_key: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for (key, usage) in scans {
+        totals.add(usage);
+        by_key
+            .entry(key.unwrap_or("anonymous").to_string())
+            .or_default()
+            .add(usage);
+    }
+
+    UsageReport {
+        tenant: tenant.to_string(),
+        totals,
+        api_keys: by_key
+            .into_iter()
+            .map(|(api_key, totals)| KeyUsage { api_key, totals })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_label() {
+        assert_eq!(key_label("key-for-red-team"), "...team");
+        assert_eq!(key_label("abcd"), "...");
+    }
+
+    #[test]
+    fn test_aggregate_per_key() {
+        let scan = |cpu_time_ms, peak_rss_bytes, artifact_bytes| ResourceUsage {
+            cpu_time_ms,
+            wall_time_ms: cpu_time_ms * 2,
+            peak_rss_bytes,
+            artifact_bytes,
+        };
+        let (a, b, c) = (
+            scan(100, Some(10), 0),
+            scan(50, Some(30), 4096),
+            scan(5, None, 0),
+        );
+        let report = aggregate(
+            "red",
+            [(Some("...team"), &a), (Some("...team"), &b), (None, &c)],
+        );
+
+        assert_eq!(report.totals.scans, 3);
+        assert_eq!(report.totals.cpu_time_ms, 155);
+        assert_eq!(report.totals.peak_rss_bytes, Some(30));
+        assert_eq!(report.api_keys.len(), 2);
+        assert_eq!(report.api_keys[0].api_key, "...team");
+        assert_eq!(report.api_keys[0].totals.scans, 2);
+        assert_eq!(report.api_keys[0].totals.artifact_bytes, 4096);
+        assert_eq!(report.api_keys[1].api_key, "anonymous");
+        assert_eq!(report.api_keys[1].totals.peak_rss_bytes, None);
+    }
+
+    #[tokio::test]
+    async fn test_measure_counts_cpu_time() {
+        let (sum, usage) = measure(async {
+            let start = Instant::now();
+            let mut sum = 0u64;
+            while start.elapsed() < Duration::from_millis(50) {
+                sum = sum.wrapping_add(std::hint::black_box(1));
+            }
+            sum
+        })
+        .await;
+
+        assert!(sum > 0);
+        assert!(usage.wall_time_ms >= 50);
+        #[cfg(unix)]
+        assert!(usage.cpu_time_ms >= 25);
+        #[cfg(target_os = "linux")]
+        assert!(usage.peak_rss_bytes.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_measure_counts_offloaded_cpu_time() {
+        let ((), usage) = measure(async {
+            add_offloaded_cpu(Duration::from_secs(2));
+            tokio::task::yield_now().await;
+            add_offloaded_cpu(Duration::from_secs(1));
+        })
+        .await;
+        assert!(usage.cpu_time_ms >= 3000);
+
+        // Nothing is left behind for whatever is polled next on the thread
+        add_offloaded_cpu(Duration::from_secs(5));
+        let ((), usage) = measure(async {}).await;
+        assert!(usage.cpu_time_ms < 5000);
+    }
+}